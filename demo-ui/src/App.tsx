@@ -82,11 +82,11 @@ function App() {
       setStatus(data.status);
 
       const finalStep =
-        data.status === 'BlockedByFirewall' ? 1 :
-          data.status === 'BlockedByEuCompliance' ? 2 :
-            data.status === 'BlockedBySemantic' ? 3 :
-              data.status === 'BlockedByInputModeration' ? 5 :
-                data.status === 'BlockedByOutputModeration' ? 7 :
+        data.status === 'blocked_by_firewall' ? 1 :
+          data.status === 'blocked_by_eu_compliance' ? 2 :
+            data.status === 'blocked_by_semantic' ? 3 :
+              data.status === 'blocked_by_input_moderation' ? 5 :
+                data.status === 'blocked_by_output_moderation' ? 7 :
                   8;
       setActiveStep(finalStep);
 
@@ -9,9 +9,9 @@ interface BiasCardProps {
 export const BiasCard: React.FC<BiasCardProps> = ({ result, loading }) => {
     const getLevelColor = (level: string) => {
         switch (level) {
-            case 'Low': return 'success';
-            case 'Medium': return 'warning';
-            case 'High': return 'danger';
+            case 'low': return 'success';
+            case 'medium': return 'warning';
+            case 'high': return 'danger';
             default: return 'neutral';
         }
     };
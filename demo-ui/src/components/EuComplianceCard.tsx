@@ -8,28 +8,28 @@ interface EuComplianceCardProps {
 
 const statusIcon = (status: ObligationStatus): string => {
   switch (status) {
-    case 'Met': return '✓';
-    case 'Partial': return '◐';
-    case 'Gap': return '✗';
-    case 'NotApplicable': return '—';
+    case 'met': return '✓';
+    case 'partial': return '◐';
+    case 'gap': return '✗';
+    case 'not_applicable': return '—';
   }
 };
 
 const statusClass = (status: ObligationStatus): string => {
   switch (status) {
-    case 'Met': return 'status-met';
-    case 'Partial': return 'status-partial';
-    case 'Gap': return 'status-gap';
-    case 'NotApplicable': return 'status-na';
+    case 'met': return 'status-met';
+    case 'partial': return 'status-partial';
+    case 'gap': return 'status-gap';
+    case 'not_applicable': return 'status-na';
   }
 };
 
 const riskTierClass = (tier: string): string => {
   switch (tier) {
-    case 'Unacceptable': return 'tier-unacceptable';
-    case 'High': return 'tier-high';
-    case 'Limited': return 'tier-limited';
-    case 'Minimal': return 'tier-minimal';
+    case 'unacceptable': return 'tier-unacceptable';
+    case 'high': return 'tier-high';
+    case 'limited': return 'tier-limited';
+    case 'minimal': return 'tier-minimal';
     default: return '';
   }
 };
@@ -9,9 +9,10 @@ interface FirewallCardProps {
 export const FirewallCard: React.FC<FirewallCardProps> = ({ result, loading }) => {
     const getActionColor = (action: string | undefined) => {
         switch (action) {
-            case 'Block': return 'danger';
-            case 'Sanitize': return 'warning';
-            case 'Allow': return 'success';
+            case 'block': return 'danger';
+            case 'sanitize': return 'warning';
+            case 'flag': return 'warning';
+            case 'allow': return 'success';
             default: return 'neutral';
         }
     };
@@ -1,6 +1,6 @@
 import React from 'react';
 
-export type PipelineStatus = 'Idle' | 'Pending' | 'Completed' | 'Sanitized' | 'BlockedByFirewall' | 'BlockedBySemantic' | 'BlockedByInputModeration' | 'BlockedByOutputModeration' | 'BlockedByEuCompliance';
+export type PipelineStatus = 'Idle' | 'Pending' | 'completed' | 'sanitized' | 'blocked_by_firewall' | 'blocked_by_semantic' | 'blocked_by_input_moderation' | 'blocked_by_output_moderation' | 'blocked_by_eu_compliance';
 
 interface PipelineProps {
     status: PipelineStatus;
@@ -23,14 +23,14 @@ export const Pipeline: React.FC<PipelineProps> = ({ status, activeStep, timeMs }
     const getStepStatus = (index: number) => {
         if (status === 'Idle') return 'idle';
         if (index < activeStep) {
-            if (status.includes('Blocked') && index === activeStep - 1) {
+            if (status.startsWith('blocked') && index === activeStep - 1) {
                 return 'blocked';
             }
             return 'completed';
         }
         if (index === activeStep) {
-            if (status.includes('Blocked')) return 'blocked';
-            if (status === 'Completed' || status === 'Sanitized') return 'completed';
+            if (status.startsWith('blocked')) return 'blocked';
+            if (status === 'completed' || status === 'sanitized') return 'completed';
             return 'active';
         }
         return 'idle';
@@ -40,21 +40,21 @@ export const Pipeline: React.FC<PipelineProps> = ({ status, activeStep, timeMs }
         switch (status) {
             case 'Idle': return 'Ready';
             case 'Pending': return 'Processing...';
-            case 'Completed': return 'Allowed';
-            case 'Sanitized': return 'Caution & Allowed';
-            case 'BlockedByFirewall': return 'Blocked by Firewall';
-            case 'BlockedByEuCompliance': return 'Blocked by EU AI Act (Article 5)';
-            case 'BlockedBySemantic': return 'Blocked by Semantic Detection';
-            case 'BlockedByInputModeration': return 'Blocked by Input Moderation';
-            case 'BlockedByOutputModeration': return 'Blocked by Output Moderation';
+            case 'completed': return 'Allowed';
+            case 'sanitized': return 'Caution & Allowed';
+            case 'blocked_by_firewall': return 'Blocked by Firewall';
+            case 'blocked_by_eu_compliance': return 'Blocked by EU AI Act (Article 5)';
+            case 'blocked_by_semantic': return 'Blocked by Semantic Detection';
+            case 'blocked_by_input_moderation': return 'Blocked by Input Moderation';
+            case 'blocked_by_output_moderation': return 'Blocked by Output Moderation';
             default: return status;
         }
     };
 
     const getStatusColor = () => {
         if (status === 'Idle' || status === 'Pending') return 'neutral';
-        if (status.includes('Blocked')) return 'danger';
-        if (status === 'Sanitized') return 'warning';
+        if (status.startsWith('blocked')) return 'danger';
+        if (status === 'sanitized') return 'warning';
         return 'success';
     };
 
@@ -8,7 +8,7 @@ interface ResponseCardProps {
 }
 
 export const ResponseCard: React.FC<ResponseCardProps> = ({ response, status, loading }) => {
-    const isBlocked = status.includes('Blocked');
+    const isBlocked = status.startsWith('blocked');
 
     return (
         <div className="card response-card full-width">
@@ -32,7 +32,7 @@ export const ResponseCard: React.FC<ResponseCardProps> = ({ response, status, lo
                         <p>The generation was halted because the prompt or response violated security/compliance policies.</p>
                     </div>
                 )}
-                {!loading && response && (status === 'Completed' || status === 'Sanitized') && (
+                {!loading && response && (status === 'completed' || status === 'sanitized') && (
                     <div className="response-text">
                         {response.split('\\n').map((line, i) => (
                             <React.Fragment key={i}>
@@ -8,8 +8,8 @@ interface SemanticCardProps {
 export function SemanticCard({ result, loading }: SemanticCardProps) {
   const getLevelColor = (level: string | undefined) => {
     switch (level) {
-      case 'High': return 'danger';
-      case 'Medium': return 'warning';
+      case 'high': return 'danger';
+      case 'medium': return 'warning';
       default: return 'success';
     }
   };
@@ -18,8 +18,8 @@ export function SemanticCard({ result, loading }: SemanticCardProps) {
 
   const getRiskIcon = (level: string | undefined) => {
     switch (level) {
-      case 'High': return '🔍';
-      case 'Medium': return '⚠️';
+      case 'high': return '🔍';
+      case 'medium': return '⚠️';
       default: return '✓';
     }
   };
@@ -61,8 +61,8 @@ export function SemanticCard({ result, loading }: SemanticCardProps) {
             <div className="result-indicator">
               <span className="icon">{getRiskIcon(result.risk_level)}</span>
               <span className="text">
-                {result.risk_level === 'High' ? 'Attack pattern detected' :
-                  result.risk_level === 'Medium' ? 'Elevated risk detected' :
+                {result.risk_level === 'high' ? 'Attack pattern detected' :
+                  result.risk_level === 'medium' ? 'Elevated risk detected' :
                     'No attack pattern detected'}
               </span>
             </div>
@@ -7,13 +7,13 @@ interface StatusCardProps {
 }
 
 export const StatusCard: React.FC<StatusCardProps> = ({ status, loading }) => {
-    const isBlocked = status.includes('Blocked');
+    const isBlocked = status.startsWith('blocked');
 
     const getStatusDisplay = () => {
         if (loading) return { text: 'Analyzing...', icon: '⏳', color: 'neutral' };
         if (status === 'Idle') return { text: 'Ready', icon: '⚡', color: 'neutral' };
-        if (status === 'Completed') return { text: 'Allowed', icon: '✓', color: 'success' };
-        if (status === 'Sanitized') return { text: 'Caution', icon: '⚠️', color: 'warning' };
+        if (status === 'completed') return { text: 'Allowed', icon: '✓', color: 'success' };
+        if (status === 'sanitized') return { text: 'Caution', icon: '⚠️', color: 'warning' };
         return { text: 'Blocked', icon: '✗', color: 'danger' };
     };
 
@@ -35,26 +35,26 @@ export const StatusCard: React.FC<StatusCardProps> = ({ status, loading }) => {
                         <span className="label text-danger">Blocked by:</span>
                         <div className="block-list">
                             <div className="block-item">
-                                <span className="checkbox">{status === 'BlockedByFirewall' ? '☑' : '□'}</span>
+                                <span className="checkbox">{status === 'blocked_by_firewall' ? '☑' : '□'}</span>
                                 <span>Firewall</span>
                             </div>
                             <div className="block-item">
-                                <span className="checkbox">{status === 'BlockedBySemantic' ? '☑' : '□'}</span>
+                                <span className="checkbox">{status === 'blocked_by_semantic' ? '☑' : '□'}</span>
                                 <span>Semantic Detection</span>
                             </div>
                             <div className="block-item">
-                                <span className="checkbox">{status === 'BlockedByInputModeration' ? '☑' : '□'}</span>
+                                <span className="checkbox">{status === 'blocked_by_input_moderation' ? '☑' : '□'}</span>
                                 <span>Input Moderation</span>
                             </div>
                             <div className="block-item">
-                                <span className="checkbox">{status === 'BlockedByOutputModeration' ? '☑' : '□'}</span>
+                                <span className="checkbox">{status === 'blocked_by_output_moderation' ? '☑' : '□'}</span>
                                 <span>Output Moderation</span>
                             </div>
                         </div>
                     </div>
                 )}
 
-                {status === 'Sanitized' && (
+                {status === 'sanitized' && (
                     <div className="sanitized-details mt-4">
                         <span className="label text-warning">Elevated Risk:</span>
                         <p className="detail-text">
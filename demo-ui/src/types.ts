@@ -4,17 +4,25 @@ export interface ModerationResult {
     categories: string[];
 }
 
+export interface MatchedBlockRule {
+    id: string;
+    pattern: string;
+    match_spans: [number, number][];
+}
+
 export interface FirewallResult {
-    action: 'Allow' | 'Block' | 'Sanitize';
-    severity: 'Low' | 'Medium' | 'High' | 'Critical';
+    action: 'allow' | 'flag' | 'block' | 'sanitize';
+    severity: 'low' | 'medium' | 'high' | 'critical';
     matched_rules: string[];
     sanitized_prompt: string;
     reasons: string[];
+    downgrade_reason?: string;
+    block_matches?: MatchedBlockRule[];
 }
 
 export interface SemanticResult {
     risk_score: number;
-    risk_level: 'Low' | 'Medium' | 'High';
+    risk_level: 'low' | 'medium' | 'high';
     nearest_template_id: string | null;
     similarity: number;
     category: string | null;
@@ -22,7 +30,7 @@ export interface SemanticResult {
 
 export interface BiasResult {
     score: number;
-    level: 'Low' | 'Medium' | 'High';
+    level: 'low' | 'medium' | 'high';
     categories: string[];
 }
 
@@ -57,6 +65,7 @@ export interface AuditTrailRequest {
     start_time?: string;
     end_time?: string;
     correlation_id?: string;
+    cursor?: string;
 }
 
 export interface AuditTrailResponse {
@@ -64,9 +73,10 @@ export interface AuditTrailResponse {
     total_count: number;
     limit: number;
     offset: number;
+    next_cursor?: string;
 }
 
-export type ObligationStatus = 'Met' | 'Partial' | 'Gap' | 'NotApplicable';
+export type ObligationStatus = 'met' | 'partial' | 'gap' | 'not_applicable';
 
 export interface ObligationResult {
     id: string;
@@ -78,7 +88,7 @@ export interface ObligationResult {
 }
 
 export interface EuComplianceResult {
-    risk_tier: 'Minimal' | 'Limited' | 'High' | 'Unacceptable';
+    risk_tier: 'minimal' | 'limited' | 'high' | 'unacceptable';
     compliant: boolean;
     obligations: ObligationResult[];
     findings: { code: string; detail: string }[];
@@ -87,7 +97,7 @@ export interface EuComplianceResult {
 
 export interface ComplianceResponse {
     correlation_id: string;
-    status: 'Completed' | 'BlockedByFirewall' | 'BlockedBySemantic' | 'BlockedByInputModeration' | 'BlockedByOutputModeration' | 'BlockedByEuCompliance' | 'Sanitized';
+    status: 'completed' | 'blocked_by_firewall' | 'blocked_by_semantic' | 'blocked_by_input_moderation' | 'blocked_by_output_moderation' | 'blocked_by_eu_compliance' | 'sanitized';
     firewall: FirewallResult;
     semantic: SemanticResult | null;
     bias: BiasResult;
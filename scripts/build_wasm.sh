@@ -0,0 +1,17 @@
+#!/usr/bin/env bash
+# Build the lexical firewall for the browser (`prompt_sentinel::wasm`)
+#
+# Compiles the library as a cdylib for wasm32-unknown-unknown with only the `wasm`
+# feature, then generates the JS bindings into `pkg/`. Needs the target
+# (`rustup target add wasm32-unknown-unknown`) and a `wasm-bindgen` CLI matching the
+# crate's wasm-bindgen version (`cargo install wasm-bindgen-cli`).
+#
+# Extra arguments go to `wasm-bindgen`, e.g. `scripts/build_wasm.sh --target bundler`;
+# the default is `--target web`.
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+cargo rustc --lib --release --target wasm32-unknown-unknown \
+    --no-default-features --features wasm --crate-type cdylib
+wasm-bindgen --out-dir pkg --target web "$@" \
+    target/wasm32-unknown-unknown/release/prompt_sentinel.wasm
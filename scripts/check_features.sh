@@ -0,0 +1,26 @@
+#!/usr/bin/env bash
+# Check every combination of the crate's cargo features, tests included
+#
+# Extra arguments go to cargo, e.g. `scripts/check_features.sh --offline`.
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+features=(server sled-storage sqlite-storage metrics-prometheus telemetry-otlp http-client)
+
+for ((mask = 0; mask < 1 << ${#features[@]}; mask++)); do
+    selected=()
+    for i in "${!features[@]}"; do
+        if ((mask >> i & 1)); then
+            selected+=("${features[i]}")
+        fi
+    done
+    list=$(IFS=,; echo "${selected[*]}")
+    echo "==> --no-default-features --features '${list}'"
+    cargo check --all-targets --no-default-features --features "${list}" "$@"
+done
+
+# The wasm bindings build for the browser only; check them when the target is installed
+if rustup target list --installed 2>/dev/null | grep -qx wasm32-unknown-unknown; then
+    echo "==> --target wasm32-unknown-unknown --no-default-features --features wasm"
+    cargo check --lib --target wasm32-unknown-unknown --no-default-features --features wasm "$@"
+fi
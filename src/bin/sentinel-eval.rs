@@ -0,0 +1,170 @@
+//! Evaluate the firewall and semantic detector against a labelled JSONL dataset
+//!
+//! ```text
+//! sentinel-eval [--dataset PATH] [--config PATH] [--semantic] [--mistral mock|live]
+//!               [--output PATH] [--markdown PATH]
+//!               [--min-recall RATIO] [--max-false-positive-rate RATIO]
+//! ```
+//!
+//! Writes the JSON report to `--output` (stdout by default). Exits with status 1 when
+//! a gate is crossed and 2 when the run itself fails.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::evaluate::dtos::EvalGates;
+use prompt_sentinel::modules::evaluate::service::{Evaluator, load_dataset, render_markdown};
+use prompt_sentinel::modules::mistral_ai::client::{
+    HttpMistralClient, MistralClient, MockMistralClient,
+};
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::modules::semantic_detection::SemanticDetectionService;
+
+const DEFAULT_DATASET: &str = "tests/eval/injection_eval.jsonl";
+
+struct Args {
+    dataset: PathBuf,
+    config: Option<PathBuf>,
+    semantic: bool,
+    live: bool,
+    output: Option<PathBuf>,
+    markdown: Option<PathBuf>,
+    gates: EvalGates,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        dataset: PathBuf::from(DEFAULT_DATASET),
+        config: None,
+        semantic: false,
+        live: false,
+        output: None,
+        markdown: None,
+        gates: EvalGates::default(),
+    };
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        let mut value = || raw.next().ok_or_else(|| format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--dataset" => args.dataset = value()?.into(),
+            "--config" => args.config = Some(value()?.into()),
+            "--semantic" => args.semantic = true,
+            "--mistral" => {
+                args.live = match value()?.as_str() {
+                    "live" => true,
+                    "mock" => false,
+                    other => return Err(format!("--mistral must be mock or live, not {other}")),
+                }
+            }
+            "--output" => args.output = Some(value()?.into()),
+            "--markdown" => args.markdown = Some(value()?.into()),
+            "--min-recall" => args.gates.min_recall = Some(parse_ratio(&flag, &value()?)?),
+            "--max-false-positive-rate" => {
+                args.gates.max_false_positive_rate = Some(parse_ratio(&flag, &value()?)?)
+            }
+            other => return Err(format!("unknown argument {other}")),
+        }
+    }
+    Ok(args)
+}
+
+fn parse_ratio(flag: &str, value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|ratio| (0.0..=1.0).contains(ratio))
+        .ok_or_else(|| format!("{flag} must be a ratio between 0 and 1, not {value}"))
+}
+
+async fn run(args: &Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let settings = match &args.config {
+        Some(path) => AppSettings::load_from(Some(path), &|key| std::env::var(key).ok())?.0,
+        None => AppSettings::load()?.0,
+    };
+    settings.install_file_paths();
+
+    let mut evaluator = Evaluator::new(PromptFirewallService::new(settings.max_input_length))
+        .with_profile_name(
+            args.config
+                .as_deref()
+                .map_or_else(|| "environment".to_owned(), |p| p.display().to_string()),
+        );
+    if args.semantic {
+        let client: Arc<dyn MistralClient> = if args.live {
+            let api_key = settings
+                .mistral_api_key
+                .clone()
+                .ok_or("--mistral live needs MISTRAL_API_KEY")?;
+            Arc::new(
+                HttpMistralClient::new(&settings.mistral_base_url, api_key)?
+                    .with_paths(settings.mistral_paths.clone()),
+            )
+        } else {
+            Arc::new(MockMistralClient::default().with_deterministic_embeddings(0, 256))
+        };
+        let mistral = MistralService::new(
+            client,
+            &settings.generation_model,
+            settings.moderation_model.clone(),
+            &settings.embedding_model,
+        )
+        .with_concurrency_limits(settings.mistral_concurrency);
+        let semantic = SemanticDetectionService::new(
+            mistral,
+            settings.semantic_medium_threshold,
+            settings.semantic_high_threshold,
+            settings.semantic_decision_margin,
+        )
+        .with_chunking(settings.semantic_chunking);
+        semantic.initialize().await?;
+        evaluator = evaluator.with_semantic(semantic);
+    }
+
+    let cases = load_dataset(&args.dataset)?;
+    let report = evaluator
+        .run(args.dataset.display().to_string(), &cases)
+        .await?;
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match &args.output {
+        Some(path) => write(path, &json)?,
+        None => println!("{json}"),
+    }
+    if let Some(path) = &args.markdown {
+        write(path, &render_markdown(&report))?;
+    }
+
+    let failures = args.gates.check(&report);
+    for failure in &failures {
+        eprintln!("gate failed: {failure}");
+    }
+    Ok(failures.is_empty())
+}
+
+fn write(path: &Path, contents: &str) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("sentinel-eval: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("sentinel-eval: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
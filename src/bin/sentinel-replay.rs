@@ -0,0 +1,180 @@
+//! Replay recorded compliance checks against a staging instance and compare decisions
+//!
+//! ```text
+//! sentinel-replay --target URL (--source URL --start TIME --end TIME | --bundle PATH)
+//!                 [--speedup FACTOR] [--timeout-secs SECS] [--output PATH]
+//!                 [--max-divergence-rate RATIO]
+//! ```
+//!
+//! Times are RFC 3339. Bearer tokens are read from `REPLAY_SOURCE_API_KEY` and
+//! `REPLAY_TARGET_API_KEY`. The target must run with `ACCEPT_REPLAY_TRAFFIC=true`.
+//! Writes the JSON report to `--output` (stdout by default). Exits with status 1 when
+//! more requests diverged than `--max-divergence-rate` allows and 2 when the run itself
+//! fails.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use prompt_sentinel::client::PromptSentinelClient;
+use prompt_sentinel::modules::load_replay::service::{LoadReplayer, fetch_window, load_bundle};
+
+enum Source {
+    Window {
+        url: String,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    Bundle(PathBuf),
+}
+
+struct Args {
+    source: Source,
+    target: String,
+    speedup: f64,
+    timeout: Duration,
+    output: Option<PathBuf>,
+    max_divergence_rate: Option<f64>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut source_url = None;
+    let mut start = None;
+    let mut end = None;
+    let mut bundle = None;
+    let mut target = None;
+    let mut speedup = 1.0;
+    let mut timeout = Duration::from_secs(60);
+    let mut output = None;
+    let mut max_divergence_rate = None;
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        let mut value = || raw.next().ok_or_else(|| format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--source" => source_url = Some(value()?),
+            "--start" => start = Some(parse_time(&flag, &value()?)?),
+            "--end" => end = Some(parse_time(&flag, &value()?)?),
+            "--bundle" => bundle = Some(PathBuf::from(value()?)),
+            "--target" => target = Some(value()?),
+            "--speedup" => {
+                let raw = value()?;
+                speedup = raw
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|factor| factor.is_finite() && *factor > 0.0)
+                    .ok_or_else(|| format!("--speedup must be a positive number, not {raw}"))?
+            }
+            "--timeout-secs" => {
+                let raw = value()?;
+                timeout = Duration::from_secs(
+                    raw.parse()
+                        .map_err(|_| format!("--timeout-secs must be whole seconds, not {raw}"))?,
+                )
+            }
+            "--output" => output = Some(value()?.into()),
+            "--max-divergence-rate" => {
+                let raw = value()?;
+                max_divergence_rate = Some(
+                    raw.parse::<f64>()
+                        .ok()
+                        .filter(|ratio| (0.0..=1.0).contains(ratio))
+                        .ok_or_else(|| {
+                            format!("{flag} must be a ratio between 0 and 1, not {raw}")
+                        })?,
+                )
+            }
+            other => return Err(format!("unknown argument {other}")),
+        }
+    }
+
+    let source = match (source_url, bundle) {
+        (Some(url), None) => Source::Window {
+            url,
+            start: start.ok_or("--source needs --start")?,
+            end: end.ok_or("--source needs --end")?,
+        },
+        (None, Some(path)) => Source::Bundle(path),
+        _ => return Err("pass either --source with a window or --bundle".to_owned()),
+    };
+    Ok(Args {
+        source,
+        target: target.ok_or("--target is required")?,
+        speedup,
+        timeout,
+        output,
+        max_divergence_rate,
+    })
+}
+
+fn parse_time(flag: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("{flag} must be an RFC 3339 time: {e}"))
+}
+
+async fn run(args: &Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let (label, records) = match &args.source {
+        Source::Window { url, start, end } => {
+            let source =
+                PromptSentinelClient::new(url, std::env::var("REPLAY_SOURCE_API_KEY").ok());
+            let records = fetch_window(&source, *start, *end).await?;
+            (format!("{url} from {start} to {end}"), records)
+        }
+        Source::Bundle(path) => (path.display().to_string(), load_bundle(path)?),
+    };
+
+    let report = LoadReplayer::new(&args.target, std::env::var("REPLAY_TARGET_API_KEY").ok())
+        .with_speedup(args.speedup)
+        .with_timeout(args.timeout)
+        .run(label, &records)
+        .await?;
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match &args.output {
+        Some(path) => std::fs::write(path, json)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))?,
+        None => println!("{json}"),
+    }
+
+    let answered = report.agreed + report.diverged;
+    let divergence_rate = if answered == 0 {
+        0.0
+    } else {
+        report.diverged as f64 / answered as f64
+    };
+    match args.max_divergence_rate {
+        Some(max) if divergence_rate > max => {
+            eprintln!("gate failed: divergence rate {divergence_rate:.3} exceeds {max}");
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .init();
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("sentinel-replay: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("sentinel-replay: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
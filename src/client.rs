@@ -0,0 +1,267 @@
+//! Typed HTTP client for a running Prompt Sentinel server
+//!
+//! Requests and responses use the server's own DTOs, so the two sides cannot drift apart.
+//! Enabled with the `http-client` feature.
+
+use std::future::Future;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, Response, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::modules::audit::storage::{AuditTrailRequest, AuditTrailResponse};
+use crate::modules::load_replay::dtos::{
+    REPLAY_HEADER, REPLAY_TARGET_HEADER, REPLAY_TARGET_STAGING,
+};
+use crate::modules::semantic_detection::dtos::{SemanticScanRequest, SemanticScanResult};
+use crate::modules::telemetry::context::CORRELATION_ID_HEADER;
+use crate::workflow::{ComplianceRequest, ComplianceResponse};
+
+/// Longest `Retry-After` hint honoured between attempts
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct PromptSentinelClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    correlation_id: Option<String>,
+    replay: bool,
+    max_retries: u32,
+    initial_backoff: Duration,
+    timeout: Duration,
+}
+
+impl PromptSentinelClient {
+    /// Client for the server at `base_url`, sending `api_key` as a bearer token when set
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into(),
+            api_key: api_key.filter(|key| !key.is_empty()),
+            correlation_id: None,
+            replay: false,
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(250),
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Send this `X-Correlation-Id` with every request
+    ///
+    /// A correlation id in a `ComplianceRequest` body still takes precedence.
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Mark every request as replayed traffic, which only a replay target accepts
+    pub fn as_replay(mut self) -> Self {
+        self.replay = true;
+        self
+    }
+
+    /// Retry retryable failures up to `max_retries` times, doubling the wait after each
+    pub fn with_retries(mut self, max_retries: u32, initial_backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Give up on a call, retries and back-off included, after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// `POST /api/compliance/check`
+    pub async fn check(
+        &self,
+        request: ComplianceRequest,
+    ) -> Result<ComplianceResponse, ClientError> {
+        self.send_json(Method::POST, "/api/compliance/check", Some(&request))
+            .await
+    }
+
+    /// `POST /api/audit/trail`
+    pub async fn audit_trail(
+        &self,
+        request: AuditTrailRequest,
+    ) -> Result<AuditTrailResponse, ClientError> {
+        self.send_json(Method::POST, "/api/audit/trail", Some(&request))
+            .await
+    }
+
+    /// `POST /api/semantic/scan`
+    pub async fn semantic_scan(
+        &self,
+        request: SemanticScanRequest,
+    ) -> Result<SemanticScanResult, ClientError> {
+        self.send_json(Method::POST, "/api/semantic/scan", Some(&request))
+            .await
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<(), ClientError> {
+        self.within_timeout(self.send(Method::GET, "/health", None::<&()>))
+            .await
+            .map(drop)
+    }
+
+    /// Whether the server is marked as a staging instance that accepts replayed traffic
+    pub async fn is_replay_target(&self) -> Result<bool, ClientError> {
+        let response = self
+            .within_timeout(self.send(Method::GET, "/health", None::<&()>))
+            .await?;
+        Ok(response
+            .headers()
+            .get(REPLAY_TARGET_HEADER)
+            .is_some_and(|value| value == REPLAY_TARGET_STAGING))
+    }
+
+    async fn send_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T, ClientError> {
+        self.within_timeout(async {
+            let response = self.send(method, path, body).await?;
+            Ok(response.json().await?)
+        })
+        .await
+    }
+
+    async fn within_timeout<T>(
+        &self,
+        call: impl Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        tokio::time::timeout(self.timeout, call)
+            .await
+            .map_err(|_| ClientError::Timeout(self.timeout))?
+    }
+
+    /// Send the request, retrying retryable failures with exponential back-off
+    async fn send<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<Response, ClientError> {
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            debug!("Attempt {} for {} {}", attempt + 1, method, url);
+            let mut request = self.http.request(method.clone(), &url);
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            if let Some(correlation_id) = &self.correlation_id {
+                request = request.header(CORRELATION_ID_HEADER, correlation_id);
+            }
+            if self.replay {
+                request = request.header(REPLAY_HEADER, "true");
+            }
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            let (error, retry_after) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let retry_after = retry_after(response.headers());
+                    (ClientError::from_response(response).await, retry_after)
+                }
+                Err(error) => (ClientError::Transport(error), None),
+            };
+            if attempt >= self.max_retries || !error.is_retryable() {
+                return Err(error);
+            }
+
+            let wait = retry_after.unwrap_or(backoff).min(MAX_RETRY_AFTER);
+            warn!(
+                "{} {} failed ({}), retrying in {:?}",
+                method, url, error, wait
+            );
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+            backoff = backoff.saturating_mul(2);
+        }
+    }
+}
+
+/// `Retry-After` in whole seconds; HTTP dates are not used by the server
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("request to prompt sentinel failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("prompt sentinel API error: HTTP {code} - {message}")]
+    Api {
+        code: u16,
+        message: String,
+        /// Whether the same request may succeed later (overload, maintenance)
+        retryable: bool,
+    },
+    #[error("no response from prompt sentinel within {0:?}")]
+    Timeout(Duration),
+}
+
+impl ClientError {
+    /// Whether retrying the same request may succeed
+    ///
+    /// Connection failures count: the server never received the request.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(error) => error.is_connect(),
+            Self::Api { retryable, .. } => *retryable,
+            Self::Timeout(_) => false,
+        }
+    }
+
+    /// Map an unsuccessful response, reading `error` or `message` from a JSON body and
+    /// falling back to the body text
+    async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|json| {
+                ["error", "message"]
+                    .iter()
+                    .find_map(|key| json.get(key)?.as_str().map(str::to_owned))
+            })
+            .unwrap_or_else(|| body.trim().to_owned());
+        Self::Api {
+            code: status.as_u16(),
+            message: if message.is_empty() {
+                status.canonical_reason().unwrap_or_default().to_owned()
+            } else {
+                message
+            },
+            retryable: matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+        }
+    }
+}
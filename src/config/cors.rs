@@ -0,0 +1,315 @@
+#[cfg(feature = "server")]
+use http::HeaderValue;
+use http::{HeaderName, Method};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// Cross-origin access for one group of routes
+///
+/// With no allowed origins the group is same-origin only: no CORS headers are sent, so
+/// browsers refuse cross-origin calls.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsPolicy {
+    /// Exact origins (`https://app.example.com`) or subdomain wildcards
+    /// (`https://*.example.com`, which does not match the apex domain)
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Development escape hatch: any origin, method and header
+    #[serde(default)]
+    pub allow_any: bool,
+}
+
+/// Policies for the public compliance endpoints and the stricter operator/admin endpoints
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsSettings {
+    pub public: CorsPolicy,
+    pub admin: CorsPolicy,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            public: CorsPolicy {
+                allowed_methods: vec!["GET".to_owned(), "POST".to_owned()],
+                allowed_headers: vec!["content-type".to_owned()],
+                ..CorsPolicy::default()
+            },
+            admin: CorsPolicy {
+                allowed_methods: vec!["GET".to_owned(), "POST".to_owned()],
+                allowed_headers: vec!["authorization".to_owned(), "content-type".to_owned()],
+                ..CorsPolicy::default()
+            },
+        }
+    }
+}
+
+impl CorsSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.public.validate().map_err(|e| format!("CORS: {e}"))?;
+        if self.admin.allow_any {
+            return Err("CORS: the admin policy cannot allow any origin".to_owned());
+        }
+        self.admin
+            .validate()
+            .map_err(|e| format!("admin CORS: {e}"))
+    }
+}
+
+impl CorsPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        self.allowlists().map(|_| ())
+    }
+
+    /// Build the tower-http layer enforcing this policy
+    #[cfg(feature = "server")]
+    pub fn layer(&self) -> Result<CorsLayer, String> {
+        let Some(Allowlists {
+            origins,
+            methods,
+            headers,
+        }) = self.allowlists()?
+        else {
+            return Ok(CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any));
+        };
+
+        Ok(CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(
+                move |origin: &HeaderValue, _parts| {
+                    origin
+                        .to_str()
+                        .map(|origin| origins.iter().any(|pattern| pattern.matches(origin)))
+                        .unwrap_or(false)
+                },
+            ))
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(self.allow_credentials))
+    }
+
+    /// Parsed allowlists; `None` when any origin is allowed
+    fn allowlists(&self) -> Result<Option<Allowlists>, String> {
+        if self.allow_any {
+            if self.allow_credentials {
+                return Err("credentials cannot be allowed together with any origin".to_owned());
+            }
+            return Ok(None);
+        }
+
+        let origins = self
+            .allowed_origins
+            .iter()
+            .map(|origin| OriginPattern::parse(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.trim().to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("invalid method '{method}'"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let headers = self
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_bytes(header.trim().as_bytes())
+                    .map_err(|_| format!("invalid header name '{header}'"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Allowlists {
+            origins,
+            methods,
+            headers,
+        }))
+    }
+}
+
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+struct Allowlists {
+    origins: Vec<OriginPattern>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+}
+
+/// One entry of an origin allowlist
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum OriginPattern {
+    Exact(String),
+    /// `scheme://*.suffix[:port]`, matching one or more labels in front of `suffix`
+    Subdomain {
+        scheme: String,
+        suffix: String,
+        port: Option<String>,
+    },
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("invalid origin '{pattern}': {reason}");
+        let normalized = pattern.trim().to_ascii_lowercase();
+        let origin = Origin::parse(&normalized)
+            .ok_or_else(|| invalid("expected scheme://host[:port] without a path"))?;
+
+        match origin.host.strip_prefix("*.") {
+            Some(suffix) => {
+                if !is_hostname(suffix) || !suffix.contains('.') {
+                    return Err(invalid("wildcard must cover a domain like *.example.com"));
+                }
+                Ok(Self::Subdomain {
+                    scheme: origin.scheme.to_owned(),
+                    suffix: suffix.to_owned(),
+                    port: origin.port.map(str::to_owned),
+                })
+            }
+            None if is_hostname(origin.host) => Ok(Self::Exact(normalized)),
+            None => Err(invalid("'*' is only allowed as the leading label")),
+        }
+    }
+
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        let Some(parsed) = Origin::parse(&origin) else {
+            return false;
+        };
+        if !is_hostname(parsed.host) {
+            return false;
+        }
+        match self {
+            Self::Exact(expected) => *expected == origin,
+            Self::Subdomain {
+                scheme,
+                suffix,
+                port,
+            } => {
+                // Require the label boundary so `evilexample.com` never matches `*.example.com`
+                let Some(subdomain) = parsed
+                    .host
+                    .strip_suffix(suffix.as_str())
+                    .and_then(|rest| rest.strip_suffix('.'))
+                else {
+                    return false;
+                };
+                parsed.scheme == scheme && parsed.port == port.as_deref() && !subdomain.is_empty()
+            }
+        }
+    }
+}
+
+/// The parts of a serialized origin, borrowed from lowercase input
+struct Origin<'a> {
+    scheme: &'a str,
+    host: &'a str,
+    port: Option<&'a str>,
+}
+
+impl<'a> Origin<'a> {
+    fn parse(value: &'a str) -> Option<Self> {
+        let (scheme, authority) = value.split_once("://")?;
+        if !matches!(scheme, "http" | "https")
+            || authority.is_empty()
+            || authority.contains(['/', '?', '#', '@', '[', ']'])
+        {
+            return None;
+        }
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => {
+                if port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                (host, Some(port))
+            }
+            None => (authority, None),
+        };
+        Some(Self { scheme, host, port })
+    }
+}
+
+/// Dot-separated non-empty labels of letters, digits and hyphens
+fn is_hostname(host: &str) -> bool {
+    host.split('.').all(|label| {
+        !label.is_empty()
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(value: &str) -> OriginPattern {
+        OriginPattern::parse(value).expect("valid pattern")
+    }
+
+    #[test]
+    fn exact_origins_match_whole_value_only() {
+        let exact = pattern("https://app.example.com");
+        assert!(exact.matches("https://app.example.com"));
+        assert!(exact.matches("HTTPS://APP.EXAMPLE.COM"));
+        assert!(!exact.matches("http://app.example.com"));
+        assert!(!exact.matches("https://app.example.com:8443"));
+        assert!(!exact.matches("https://app.example.com.evil.test"));
+        assert!(!exact.matches("https://evil.test/https://app.example.com"));
+    }
+
+    #[test]
+    fn wildcard_requires_a_subdomain_label_boundary() {
+        let wildcard = pattern("https://*.example.com");
+        assert!(wildcard.matches("https://app.example.com"));
+        assert!(wildcard.matches("https://eu.app.example.com"));
+        assert!(!wildcard.matches("https://example.com"));
+        assert!(!wildcard.matches("https://evilexample.com"));
+        assert!(!wildcard.matches("https://example.com.evil.test"));
+        assert!(!wildcard.matches("http://app.example.com"));
+        assert!(!wildcard.matches("https://app.example.com:8443"));
+        assert!(!wildcard.matches("https://user@app.example.com"));
+        assert!(!wildcard.matches("https://*.example.com"));
+        assert!(!wildcard.matches("null"));
+    }
+
+    #[test]
+    fn wildcard_port_must_match_exactly() {
+        let wildcard = pattern("http://*.localhost.test:5175");
+        assert!(wildcard.matches("http://ui.localhost.test:5175"));
+        assert!(!wildcard.matches("http://ui.localhost.test"));
+        assert!(!wildcard.matches("http://ui.localhost.test:51750"));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        for invalid in [
+            "*",
+            "https://*",
+            "example.com",
+            "https://example.com/",
+            "https://*.com",
+            "https://app.*.example.com",
+            "https://*example.com",
+            "ftp://example.com",
+            "https://example.com:port",
+        ] {
+            assert!(OriginPattern::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn admin_policy_cannot_allow_any_origin() {
+        let mut settings = CorsSettings::default();
+        assert!(settings.validate().is_ok());
+        settings.admin.allow_any = true;
+        assert!(settings.validate().is_err());
+
+        let mut settings = CorsSettings::default();
+        settings.public.allow_any = true;
+        settings.public.allow_credentials = true;
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+/// Value of a key in `sentinel.toml`
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<FileValue>),
+}
+
+impl FileValue {
+    /// TOML name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Boolean(_) => "boolean",
+            Self::Array(_) => "array",
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::String(value) => Value::from(value.as_str()),
+            Self::Integer(value) => Value::from(*value),
+            Self::Float(value) => Value::from(*value),
+            Self::Boolean(value) => Value::from(*value),
+            Self::Array(items) => Value::Array(items.iter().map(Self::to_json).collect()),
+        }
+    }
+}
+
+/// Syntax error in a configuration file, with its 1-based line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parse the TOML subset the configuration file uses
+///
+/// Supports `[table]` headers (dotted for nesting), `key = value` pairs with strings,
+/// integers, floats, booleans and arrays of those, and `#` comments. Keys are returned
+/// flattened with dots, e.g. `server.port`.
+pub fn parse(content: &str) -> Result<BTreeMap<String, FileValue>, SyntaxError> {
+    let mut values = BTreeMap::new();
+    let mut table = String::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((index, raw)) = lines.next() {
+        let line_number = index + 1;
+        let error = |message: String| SyntaxError {
+            line: line_number,
+            message,
+        };
+        let line = strip_comment(raw).trim().to_owned();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| error("table header is missing its closing `]`".to_owned()))?
+                .trim();
+            if name.is_empty() || !name.split('.').all(is_bare_key) {
+                return Err(error(format!("invalid table name `{name}`")));
+            }
+            table = name.to_owned();
+            continue;
+        }
+
+        let (key, rest) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected `key = value`".to_owned()))?;
+        let key = key.trim();
+        if !key.split('.').all(is_bare_key) {
+            return Err(error(format!("invalid key `{key}`")));
+        }
+        // Arrays may continue over several lines until their brackets balance
+        let mut text = rest.trim().to_owned();
+        while text.starts_with('[') && !brackets_closed(&text) {
+            let Some((_, next)) = lines.next() else {
+                return Err(error(format!("array for `{key}` is never closed")));
+            };
+            text.push(' ');
+            text.push_str(strip_comment(next).trim());
+        }
+        let mut parser = ValueParser {
+            text: &text,
+            pos: 0,
+        };
+        let value = parser
+            .value()
+            .and_then(|value| parser.end().map(|()| value))
+            .map_err(|message| error(format!("`{key}`: {message}")))?;
+
+        let full_key = if table.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{table}.{key}")
+        };
+        if values.insert(full_key.clone(), value).is_some() {
+            return Err(error(format!("`{full_key}` is set more than once")));
+        }
+    }
+    Ok(values)
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Drop a trailing `#` comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..index],
+            None => {}
+        }
+    }
+    line
+}
+
+fn brackets_closed(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+struct ValueParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> ValueParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.text.len() - trimmed.len();
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unexpected `{}` after the value", self.rest()))
+        }
+    }
+
+    fn value(&mut self) -> Result<FileValue, String> {
+        self.skip_whitespace();
+        match self.rest().chars().next() {
+            None => Err("missing value".to_owned()),
+            Some('"') => self.basic_string().map(FileValue::String),
+            Some('\'') => self.literal_string().map(FileValue::String),
+            Some('[') => self.array(),
+            Some(_) => self.scalar(),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += index + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some(other) => return Err(format!("unsupported escape `\\{other}`")),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err("string is never closed".to_owned())
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        let body = &self.rest()[1..];
+        let end = body
+            .find('\'')
+            .ok_or_else(|| "string is never closed".to_owned())?;
+        let value = body[..end].to_owned();
+        self.pos += end + 2;
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<FileValue, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with(']') {
+                self.pos += 1;
+                return Ok(FileValue::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.rest().starts_with(',') {
+                self.pos += 1;
+            } else if !self.rest().starts_with(']') {
+                return Err("expected `,` or `]` in array".to_owned());
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<FileValue, String> {
+        let token = self
+            .rest()
+            .split([',', ']'])
+            .next()
+            .unwrap_or_default()
+            .trim_end();
+        self.pos += token.len();
+        match token {
+            "true" => return Ok(FileValue::Boolean(true)),
+            "false" => return Ok(FileValue::Boolean(false)),
+            _ => {}
+        }
+        let digits = token.replace('_', "");
+        if let Ok(value) = digits.parse::<i64>() {
+            return Ok(FileValue::Integer(value));
+        }
+        if digits.contains(['.', 'e', 'E'])
+            && let Ok(value) = digits.parse::<f64>()
+            && value.is_finite()
+        {
+            return Ok(FileValue::Float(value));
+        }
+        Err(format!(
+            "`{token}` is not a string, number, boolean or array (strings need quotes)"
+        ))
+    }
+}
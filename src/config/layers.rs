@@ -0,0 +1,989 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use super::file::{self, FileValue};
+use super::settings::SettingsError;
+
+/// Shown instead of secret values
+const MASK: &str = "********";
+
+/// Every setting as `(file key, environment variable, secret)`
+///
+/// The file key is the dotted path in `sentinel.toml`; the variable overrides it.
+pub const SETTING_KEYS: &[(&str, &str, bool)] = &[
+    ("server.port", "SERVER_PORT", false),
+    ("server.admin_token", "ADMIN_API_TOKEN", true),
+    ("server.support_token", "SUPPORT_API_TOKEN", true),
+    ("server.max_input_length", "MAX_INPUT_LENGTH", false),
+    (
+        "server.length_overflow_policy",
+        "LENGTH_OVERFLOW_POLICY",
+        false,
+    ),
+    ("server.config_history_limit", "CONFIG_HISTORY_LIMIT", false),
+    (
+        "server.exemption_sweep_interval_secs",
+        "EXEMPTION_SWEEP_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "server.admission_max_concurrent",
+        "ADMISSION_MAX_CONCURRENT",
+        false,
+    ),
+    ("server.admission_max_queue", "ADMISSION_MAX_QUEUE", false),
+    (
+        "server.admission_max_wait_ms",
+        "ADMISSION_MAX_WAIT_MS",
+        false,
+    ),
+    ("mistral.api_key", "MISTRAL_API_KEY", true),
+    ("mistral.base_url", "MISTRAL_BASE_URL", false),
+    ("mistral.chat_path", "MISTRAL_CHAT_PATH", false),
+    ("mistral.moderation_path", "MISTRAL_MODERATION_PATH", false),
+    ("mistral.embeddings_path", "MISTRAL_EMBEDDINGS_PATH", false),
+    ("mistral.models_path", "MISTRAL_MODELS_PATH", false),
+    (
+        "mistral.generation_model",
+        "MISTRAL_GENERATION_MODEL",
+        false,
+    ),
+    (
+        "mistral.generation_preamble",
+        "MISTRAL_GENERATION_PREAMBLE",
+        false,
+    ),
+    (
+        "mistral.deterministic_generation",
+        "DETERMINISTIC_GENERATION",
+        false,
+    ),
+    ("mistral.deterministic_seed", "DETERMINISTIC_SEED", false),
+    (
+        "mistral.moderation_model",
+        "MISTRAL_MODERATION_MODEL",
+        false,
+    ),
+    ("mistral.embedding_model", "MISTRAL_EMBEDDING_MODEL", false),
+    (
+        "mistral.max_concurrent_chat",
+        "MISTRAL_MAX_CONCURRENT_CHAT",
+        false,
+    ),
+    (
+        "mistral.max_concurrent_moderation",
+        "MISTRAL_MAX_CONCURRENT_MODERATION",
+        false,
+    ),
+    (
+        "mistral.max_concurrent_embeddings",
+        "MISTRAL_MAX_CONCURRENT_EMBEDDINGS",
+        false,
+    ),
+    (
+        "mistral.concurrency_max_wait_ms",
+        "MISTRAL_CONCURRENCY_MAX_WAIT_MS",
+        false,
+    ),
+    ("thresholds.bias", "BIAS_THRESHOLD", false),
+    ("bias_rewrite.min_level", "BIAS_REWRITE_MIN_LEVEL", false),
+    ("bias_rewrite.timeout_ms", "BIAS_REWRITE_TIMEOUT_MS", false),
+    ("bias_rewrite.max_tokens", "BIAS_REWRITE_MAX_TOKENS", false),
+    (
+        "thresholds.semantic_medium",
+        "SEMANTIC_MEDIUM_THRESHOLD",
+        false,
+    ),
+    ("thresholds.semantic_high", "SEMANTIC_HIGH_THRESHOLD", false),
+    (
+        "thresholds.semantic_decision_margin",
+        "SEMANTIC_DECISION_MARGIN",
+        false,
+    ),
+    ("paths.firewall_rules", "PROMPT_FIREWALL_RULES_PATH", false),
+    ("paths.bias_rules_dir", "BIAS_RULES_DIR", false),
+    (
+        "paths.semantic_attack_bank",
+        "SEMANTIC_ATTACK_BANK_PATH",
+        false,
+    ),
+    (
+        "paths.eu_risk_keywords",
+        "PROMPT_SENTINEL_EU_KEYWORDS_PATH",
+        false,
+    ),
+    ("paths.sled_db", "SLED_DB_PATH", false),
+    ("paths.audit_sqlite", "AUDIT_SQLITE_PATH", false),
+    ("telemetry.metrics_addr", "METRICS_ADDR", false),
+    ("firewall.strict_rules", "FIREWALL_RULES_STRICT", false),
+    (
+        "firewall.rules_history_limit",
+        "FIREWALL_RULES_HISTORY_LIMIT",
+        false,
+    ),
+    (
+        "firewall.miss_buffer_size",
+        "FIREWALL_MISS_BUFFER_SIZE",
+        false,
+    ),
+    (
+        "moderation.moderate_removed_content",
+        "MODERATE_REMOVED_CONTENT",
+        false,
+    ),
+    (
+        "moderation.moderate_translated_output",
+        "MODERATE_TRANSLATED_OUTPUT",
+        false,
+    ),
+    (
+        "moderation.unmoderated_requests",
+        "UNMODERATED_REQUESTS",
+        false,
+    ),
+    (
+        "moderation.severity_mode",
+        "MODERATION_SEVERITY_MODE",
+        false,
+    ),
+    (
+        "moderation.severity_weights_path",
+        "MODERATION_SEVERITY_WEIGHTS_PATH",
+        false,
+    ),
+    (
+        "moderation.output_chunk_length",
+        "OUTPUT_MODERATION_CHUNK_LENGTH",
+        false,
+    ),
+    (
+        "moderation.output_chunk_overlap",
+        "OUTPUT_MODERATION_CHUNK_OVERLAP",
+        false,
+    ),
+    (
+        "moderation.output_chunk_concurrency",
+        "OUTPUT_MODERATION_CHUNK_CONCURRENCY",
+        false,
+    ),
+    ("output_analysis.bias", "OUTPUT_ANALYSIS_BIAS", false),
+    (
+        "output_analysis.firewall_echo",
+        "OUTPUT_ANALYSIS_FIREWALL_ECHO",
+        false,
+    ),
+    (
+        "output_analysis.semantic",
+        "OUTPUT_ANALYSIS_SEMANTIC",
+        false,
+    ),
+    ("llm_judge.enabled", "LLM_JUDGE_ENABLED", false),
+    (
+        "llm_judge.semantic_window",
+        "LLM_JUDGE_SEMANTIC_WINDOW",
+        false,
+    ),
+    (
+        "llm_judge.firewall_near_miss",
+        "LLM_JUDGE_FIREWALL_NEAR_MISS",
+        false,
+    ),
+    ("llm_judge.max_tokens", "LLM_JUDGE_MAX_TOKENS", false),
+    ("llm_judge.timeout_ms", "LLM_JUDGE_TIMEOUT_MS", false),
+    ("llm_judge.template_path", "LLM_JUDGE_TEMPLATE_PATH", false),
+    (
+        "explain.support_redaction",
+        "EXPLAIN_SUPPORT_REDACTION",
+        false,
+    ),
+    ("explain.admin_redaction", "EXPLAIN_ADMIN_REDACTION", false),
+    ("explain.feedback_url", "EXPLAIN_FEEDBACK_URL", false),
+    (
+        "appeals.exemption_ttl_secs",
+        "APPEAL_EXEMPTION_TTL_SECS",
+        false,
+    ),
+    (
+        "appeals.exemption_max_uses",
+        "APPEAL_EXEMPTION_MAX_USES",
+        false,
+    ),
+    ("startup.mistral", "STARTUP_POLICY_MISTRAL", false),
+    ("startup.semantic", "STARTUP_POLICY_SEMANTIC", false),
+    (
+        "startup.retry_initial_backoff_ms",
+        "STARTUP_RETRY_INITIAL_BACKOFF_MS",
+        false,
+    ),
+    (
+        "startup.retry_max_backoff_ms",
+        "STARTUP_RETRY_MAX_BACKOFF_MS",
+        false,
+    ),
+    ("preprocessing.transforms", "PROMPT_PREPROCESSORS", false),
+    ("url_policy.path", "URL_POLICY_PATH", false),
+    ("url_unfurl.enabled", "URL_UNFURL_ENABLED", false),
+    ("url_unfurl.max_urls", "URL_UNFURL_MAX_URLS", false),
+    ("url_unfurl.max_bytes", "URL_UNFURL_MAX_BYTES", false),
+    ("url_unfurl.timeout_ms", "URL_UNFURL_TIMEOUT_MS", false),
+    (
+        "url_unfurl.max_redirects",
+        "URL_UNFURL_MAX_REDIRECTS",
+        false,
+    ),
+    (
+        "url_unfurl.allowed_networks",
+        "URL_UNFURL_ALLOWED_NETWORKS",
+        false,
+    ),
+    ("audit.backend", "AUDIT_BACKEND", false),
+    ("audit.encryption_key", "AUDIT_ENCRYPTION_KEY", true),
+    (
+        "audit.compression_threshold_bytes",
+        "AUDIT_COMPRESSION_THRESHOLD_BYTES",
+        false,
+    ),
+    ("audit.compression_level", "AUDIT_COMPRESSION_LEVEL", false),
+    ("audit.prompt_storage", "AUDIT_PROMPT_STORAGE", false),
+    (
+        "audit.suppression_window_secs",
+        "AUDIT_SUPPRESSION_WINDOW_SECS",
+        false,
+    ),
+    (
+        "audit.suppression_prompt_hashes",
+        "AUDIT_SUPPRESSION_PROMPT_HASHES",
+        false,
+    ),
+    (
+        "audit.suppression_correlation_prefixes",
+        "AUDIT_SUPPRESSION_CORRELATION_PREFIXES",
+        false,
+    ),
+    (
+        "audit.compliance_report_retention_days",
+        "COMPLIANCE_REPORT_RETENTION_DAYS",
+        false,
+    ),
+    ("audit.write_behind", "AUDIT_WRITE_BEHIND", false),
+    ("audit.flush_interval_ms", "AUDIT_FLUSH_INTERVAL_MS", false),
+    ("audit.batch_size", "AUDIT_BATCH_SIZE", false),
+    ("audit.queue_capacity", "AUDIT_QUEUE_CAPACITY", false),
+    (
+        "audit.integrity_interval_secs",
+        "AUDIT_INTEGRITY_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "audit.integrity_tail_records",
+        "AUDIT_INTEGRITY_TAIL_RECORDS",
+        false,
+    ),
+    (
+        "audit.integrity_full_interval_secs",
+        "AUDIT_INTEGRITY_FULL_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "audit.integrity_fail_readiness",
+        "AUDIT_INTEGRITY_FAIL_READINESS",
+        false,
+    ),
+    (
+        "audit.disk_check_interval_secs",
+        "AUDIT_DISK_CHECK_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "audit.disk_high_water_bytes",
+        "AUDIT_DISK_HIGH_WATER_BYTES",
+        false,
+    ),
+    (
+        "audit.disk_high_water_policy",
+        "AUDIT_DISK_HIGH_WATER_POLICY",
+        false,
+    ),
+    (
+        "audit.disk_max_space_amplification",
+        "AUDIT_DISK_MAX_SPACE_AMPLIFICATION",
+        false,
+    ),
+    ("alerting.webhook_url", "ALERT_WEBHOOK_URL", true),
+    (
+        "model_watch.interval_secs",
+        "MODEL_WATCH_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "model_watch.deprecation_patterns",
+        "MODEL_WATCH_DEPRECATION_PATTERNS",
+        false,
+    ),
+    ("model_watch.notify", "MODEL_WATCH_NOTIFY", false),
+    ("repeat_offender.mode", "REPEAT_OFFENDER_MODE", false),
+    ("repeat_offender.window", "REPEAT_OFFENDER_WINDOW", false),
+    (
+        "repeat_offender.threshold",
+        "REPEAT_OFFENDER_THRESHOLD",
+        false,
+    ),
+    (
+        "repeat_offender.risk_bonus",
+        "REPEAT_OFFENDER_RISK_BONUS",
+        false,
+    ),
+    (
+        "sanitize_probing.enabled",
+        "SANITIZE_PROBING_ENABLED",
+        false,
+    ),
+    (
+        "sanitize_probing.threshold",
+        "SANITIZE_PROBING_THRESHOLD",
+        false,
+    ),
+    (
+        "sanitize_probing.window_secs",
+        "SANITIZE_PROBING_WINDOW_SECS",
+        false,
+    ),
+    (
+        "sanitize_probing.max_edit_ratio",
+        "SANITIZE_PROBING_MAX_EDIT_RATIO",
+        false,
+    ),
+    (
+        "sanitize_probing.ring_size",
+        "SANITIZE_PROBING_RING_SIZE",
+        false,
+    ),
+    ("demo.enabled", "DEMO_MODE", false),
+    ("demo.quota_requests", "DEMO_QUOTA_REQUESTS", false),
+    ("demo.quota_window_secs", "DEMO_QUOTA_WINDOW_SECS", false),
+    ("demo.mock_moderation", "DEMO_MOCK_MODERATION", false),
+    ("demo.mock_embeddings", "DEMO_MOCK_EMBEDDINGS", false),
+    ("cors.allowed_origins", "CORS_ALLOWED_ORIGINS", false),
+    ("cors.allowed_methods", "CORS_ALLOWED_METHODS", false),
+    ("cors.allowed_headers", "CORS_ALLOWED_HEADERS", false),
+    ("cors.allow_credentials", "CORS_ALLOW_CREDENTIALS", false),
+    ("cors.allow_any", "CORS_ALLOW_ANY", false),
+    (
+        "cors.admin.allowed_origins",
+        "CORS_ADMIN_ALLOWED_ORIGINS",
+        false,
+    ),
+    (
+        "cors.admin.allowed_methods",
+        "CORS_ADMIN_ALLOWED_METHODS",
+        false,
+    ),
+    (
+        "cors.admin.allowed_headers",
+        "CORS_ADMIN_ALLOWED_HEADERS",
+        false,
+    ),
+    (
+        "cors.admin.allow_credentials",
+        "CORS_ADMIN_ALLOW_CREDENTIALS",
+        false,
+    ),
+    (
+        "document_scan.max_documents",
+        "DOCUMENT_SCAN_MAX_DOCUMENTS",
+        false,
+    ),
+    (
+        "document_scan.max_total_bytes",
+        "DOCUMENT_SCAN_MAX_TOTAL_BYTES",
+        false,
+    ),
+    ("semantic.sampling.rate", "SEMANTIC_SAMPLING_RATE", false),
+    (
+        "semantic.sampling.max_prompt_length",
+        "SEMANTIC_SAMPLING_MAX_PROMPT_LENGTH",
+        false,
+    ),
+    ("semantic.sampling.secret", "SEMANTIC_SAMPLING_SECRET", true),
+    (
+        "semantic.bank.near_duplicate_threshold",
+        "SEMANTIC_BANK_NEAR_DUPLICATE_THRESHOLD",
+        false,
+    ),
+    (
+        "semantic.bank.max_pairwise_templates",
+        "SEMANTIC_BANK_MAX_PAIRWISE_TEMPLATES",
+        false,
+    ),
+    ("semantic.bank.strict", "SEMANTIC_BANK_STRICT", false),
+    (
+        "semantic.chunking.min_prompt_length",
+        "SEMANTIC_CHUNKING_MIN_PROMPT_LENGTH",
+        false,
+    ),
+    (
+        "semantic.chunking.window_length",
+        "SEMANTIC_CHUNKING_WINDOW_LENGTH",
+        false,
+    ),
+    (
+        "semantic.chunking.overlap",
+        "SEMANTIC_CHUNKING_OVERLAP",
+        false,
+    ),
+    (
+        "semantic.chunking.concurrency",
+        "SEMANTIC_CHUNKING_CONCURRENCY",
+        false,
+    ),
+    (
+        "semantic.embedding_cache_size",
+        "SEMANTIC_EMBEDDING_CACHE_SIZE",
+        false,
+    ),
+    (
+        "caches.memory_budget_bytes",
+        "CACHE_MEMORY_BUDGET_BYTES",
+        false,
+    ),
+    (
+        "caches.check_interval_secs",
+        "CACHE_BUDGET_CHECK_INTERVAL_SECS",
+        false,
+    ),
+    (
+        "stage_failure_policy.language",
+        "STAGE_FAILURE_POLICY_LANGUAGE",
+        false,
+    ),
+    (
+        "stage_failure_policy.bias",
+        "STAGE_FAILURE_POLICY_BIAS",
+        false,
+    ),
+    (
+        "stage_failure_policy.semantic",
+        "STAGE_FAILURE_POLICY_SEMANTIC",
+        false,
+    ),
+    (
+        "stage_failure_policy.moderation",
+        "STAGE_FAILURE_POLICY_MODERATION",
+        false,
+    ),
+    (
+        "stage_failure_policy.translation",
+        "STAGE_FAILURE_POLICY_TRANSLATION",
+        false,
+    ),
+    (
+        "slo.latency_threshold_ms",
+        "SLO_LATENCY_THRESHOLD_MS",
+        false,
+    ),
+    ("slo.latency_target", "SLO_LATENCY_TARGET", false),
+    ("slo.availability_target", "SLO_AVAILABILITY_TARGET", false),
+    (
+        "diagnostics.slow_request_threshold_ms",
+        "SLOW_REQUEST_THRESHOLD_MS",
+        false,
+    ),
+    (
+        "diagnostics.slow_request_route_thresholds",
+        "SLOW_REQUEST_ROUTE_THRESHOLDS",
+        false,
+    ),
+    (
+        "diagnostics.slow_request_buffer_size",
+        "SLOW_REQUEST_BUFFER_SIZE",
+        false,
+    ),
+    (
+        "telemetry.correlation_id_max_length",
+        "CORRELATION_ID_MAX_LENGTH",
+        false,
+    ),
+    (
+        "telemetry.correlation_id_prefixes",
+        "CORRELATION_ID_PREFIXES",
+        false,
+    ),
+    ("telemetry.instance_id", "INSTANCE_ID", false),
+    (
+        "telemetry.log_sampling_window_secs",
+        "LOG_SAMPLING_WINDOW_SECS",
+        false,
+    ),
+    ("risk.weight_firewall", "RISK_WEIGHT_FIREWALL", false),
+    ("risk.weight_semantic", "RISK_WEIGHT_SEMANTIC", false),
+    ("risk.weight_bias", "RISK_WEIGHT_BIAS", false),
+    ("risk.weight_moderation", "RISK_WEIGHT_MODERATION", false),
+    (
+        "risk.weight_degraded_stage",
+        "RISK_WEIGHT_DEGRADED_STAGE",
+        false,
+    ),
+    ("risk.blocked_floor", "RISK_BLOCKED_FLOOR", false),
+    ("policy.decision_policy_path", "DECISION_POLICY_PATH", false),
+    ("refusal.enabled", "REFUSAL_MESSAGES", false),
+    ("refusal.templates_path", "REFUSAL_TEMPLATES_PATH", false),
+    ("chaos.enabled", "CHAOS_MODE", false),
+    ("replay.accept_traffic", "ACCEPT_REPLAY_TRAFFIC", false),
+    (
+        "http_cache.compliance_config_max_age_secs",
+        "CACHE_MAX_AGE_COMPLIANCE_CONFIG_SECS",
+        false,
+    ),
+    (
+        "http_cache.summary_max_age_secs",
+        "CACHE_MAX_AGE_SUMMARY_SECS",
+        false,
+    ),
+    ("usage.api_keys_path", "API_KEYS_PATH", false),
+    (
+        "usage.cost_per_1k_tokens",
+        "USAGE_COST_PER_1K_TOKENS",
+        false,
+    ),
+    (
+        "usage.flush_interval_secs",
+        "USAGE_FLUSH_INTERVAL_SECS",
+        false,
+    ),
+    ("usage.require_key", "USAGE_REQUIRE_KEY", false),
+];
+
+/// Where an effective setting came from
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+    /// Set in code through `FrameworkConfig`
+    Override,
+}
+
+impl ConfigSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::File => "file",
+            Self::Env => "env",
+            Self::Override => "override",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EffectiveSetting {
+    /// Dotted key in the configuration file
+    pub key: String,
+    /// Environment variable that overrides the file
+    pub env: String,
+    /// Secrets are masked; unset optional values are `null`
+    pub value: Value,
+    pub source: ConfigSource,
+}
+
+/// Every setting in effect and where it came from, as reported at startup and by
+/// `GET /api/config/effective`
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EffectiveConfig {
+    /// Configuration file that was read, if any
+    pub config_file: Option<String>,
+    pub settings: Vec<EffectiveSetting>,
+}
+
+impl EffectiveConfig {
+    pub fn get(&self, key: &str) -> Option<&EffectiveSetting> {
+        self.settings.iter().find(|setting| setting.key == key)
+    }
+
+    /// Record a value set in code, which wins over every other layer
+    pub fn set_override(&mut self, key: &str, value: impl Serialize) {
+        let Some(setting) = self.settings.iter_mut().find(|setting| setting.key == key) else {
+            return;
+        };
+        setting.value = display_value(&value, is_secret(key));
+        setting.source = ConfigSource::Override;
+    }
+
+    pub fn log(&self) {
+        match &self.config_file {
+            Some(path) => info!("Configuration file: {}", path),
+            None => info!("No configuration file; using environment variables and defaults"),
+        }
+        for setting in &self.settings {
+            info!(
+                "config {} = {} ({})",
+                setting.key,
+                setting.value,
+                setting.source.as_str()
+            );
+        }
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    SETTING_KEYS
+        .iter()
+        .any(|(file_key, _, secret)| *file_key == key && *secret)
+}
+
+fn display_value(value: &impl Serialize, secret: bool) -> Value {
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    let unset = match &value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        _ => false,
+    };
+    if secret && !unset {
+        Value::from(MASK)
+    } else {
+        value
+    }
+}
+
+/// A setting's raw value from the highest layer that sets it
+enum Raw<'a> {
+    Env(String),
+    File(&'a FileValue),
+}
+
+/// Reads settings from the environment, then the configuration file, then defaults,
+/// recording where each value came from
+pub(crate) struct Layers<'a> {
+    file: BTreeMap<String, FileValue>,
+    file_path: Option<String>,
+    env: &'a dyn Fn(&str) -> Option<String>,
+    effective: Vec<EffectiveSetting>,
+}
+
+impl<'a> Layers<'a> {
+    pub(crate) fn new(
+        file_path: Option<&Path>,
+        env: &'a dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, SettingsError> {
+        let Some(path) = file_path else {
+            return Ok(Self {
+                file: BTreeMap::new(),
+                file_path: None,
+                env,
+                effective: Vec::new(),
+            });
+        };
+        let display = path.display().to_string();
+        let content = std::fs::read_to_string(path).map_err(|e| SettingsError::ConfigFile {
+            path: display.clone(),
+            reason: e.to_string(),
+        })?;
+        let file = file::parse(&content).map_err(|e| SettingsError::ConfigSyntax {
+            path: display.clone(),
+            line: e.line,
+            message: e.message,
+        })?;
+        if let Some(unknown) = file
+            .keys()
+            .find(|key| !SETTING_KEYS.iter().any(|(known, _, _)| known == key))
+        {
+            return Err(SettingsError::UnknownConfigKey {
+                path: display,
+                key: unknown.clone(),
+            });
+        }
+        Ok(Self {
+            file,
+            file_path: Some(display),
+            env,
+            effective: Vec::new(),
+        })
+    }
+
+    pub(crate) fn finish(self) -> EffectiveConfig {
+        EffectiveConfig {
+            config_file: self.file_path,
+            settings: self.effective,
+        }
+    }
+
+    fn file_key(env_key: &str) -> &'static str {
+        SETTING_KEYS
+            .iter()
+            .find(|(_, env, _)| *env == env_key)
+            .map(|(file_key, _, _)| *file_key)
+            .unwrap_or_else(|| panic!("{env_key} is missing from SETTING_KEYS"))
+    }
+
+    /// Resolve one setting; `from_env` parses a variable, `from_file` converts a file value
+    fn resolve<T: Serialize>(
+        &mut self,
+        env_key: &str,
+        default: T,
+        from_env: impl FnOnce(String) -> Result<T, SettingsError>,
+        from_file: impl FnOnce(&FileValue, &str) -> Result<T, SettingsError>,
+    ) -> Result<T, SettingsError> {
+        let key = Self::file_key(env_key);
+        let raw = match (self.env)(env_key) {
+            Some(value) => Some(Raw::Env(value)),
+            None => self.file.get(key).map(Raw::File),
+        };
+        let (value, source) = match raw {
+            Some(Raw::Env(value)) => (from_env(value)?, ConfigSource::Env),
+            Some(Raw::File(value)) => {
+                let path = self.file_path.clone().unwrap_or_default();
+                (from_file(value, &path)?, ConfigSource::File)
+            }
+            None => (default, ConfigSource::Default),
+        };
+        self.effective.push(EffectiveSetting {
+            key: key.to_owned(),
+            env: env_key.to_owned(),
+            value: display_value(&value, is_secret(key)),
+            source,
+        });
+        Ok(value)
+    }
+
+    pub(crate) fn string(&mut self, env_key: &str, default: &str) -> Result<String, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(env_key, default.to_owned(), Ok, |value, path| match value {
+            FileValue::String(text) => Ok(text.clone()),
+            other => Err(type_error(path, key, "a string", other)),
+        })
+    }
+
+    /// A string that is unset when empty
+    pub(crate) fn optional_string(
+        &mut self,
+        env_key: &str,
+    ) -> Result<Option<String>, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            None,
+            |value| Ok(Some(value).filter(|v| !v.trim().is_empty())),
+            |value, path| match value {
+                FileValue::String(text) => Ok(Some(text.clone()).filter(|v| !v.trim().is_empty())),
+                other => Err(type_error(path, key, "a string", other)),
+            },
+        )
+    }
+
+    pub(crate) fn f32(&mut self, env_key: &str, default: f32) -> Result<f32, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| {
+                value
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|source| SettingsError::ParseFloat {
+                        key: env_key.to_owned(),
+                        source,
+                    })
+            },
+            |value, path| match value {
+                FileValue::Float(number) => Ok(*number as f32),
+                FileValue::Integer(number) => Ok(*number as f32),
+                other => Err(type_error(path, key, "a number", other)),
+            },
+        )
+        .inspect(|value| {
+            // Report `0.6` rather than the widened `0.6000000238418579`
+            if let Some(last) = self.effective.last_mut()
+                && let Ok(shortest) = value.to_string().parse::<f64>()
+            {
+                last.value = Value::from(shortest);
+            }
+        })
+    }
+
+    pub(crate) fn f64(&mut self, env_key: &str, default: f64) -> Result<f64, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|source| SettingsError::ParseFloat {
+                        key: env_key.to_owned(),
+                        source,
+                    })
+            },
+            |value, path| match value {
+                FileValue::Float(number) => Ok(*number),
+                FileValue::Integer(number) => Ok(*number as f64),
+                other => Err(type_error(path, key, "a number", other)),
+            },
+        )
+    }
+
+    pub(crate) fn usize(&mut self, env_key: &str, default: usize) -> Result<usize, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|source| SettingsError::ParseInt {
+                        key: env_key.to_owned(),
+                        source,
+                    })
+            },
+            |value, path| match value {
+                FileValue::Integer(number) => usize::try_from(*number)
+                    .map_err(|_| type_error(path, key, "a non-negative integer", value)),
+                other => Err(type_error(path, key, "a non-negative integer", other)),
+            },
+        )
+    }
+
+    pub(crate) fn u16(&mut self, env_key: &str, default: u16) -> Result<u16, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| {
+                value
+                    .trim()
+                    .parse::<u16>()
+                    .map_err(|source| SettingsError::ParseInt {
+                        key: env_key.to_owned(),
+                        source,
+                    })
+            },
+            |value, path| match value {
+                FileValue::Integer(number) => u16::try_from(*number)
+                    .map_err(|_| type_error(path, key, "an integer from 0 to 65535", value)),
+                other => Err(type_error(path, key, "an integer from 0 to 65535", other)),
+            },
+        )
+    }
+
+    pub(crate) fn bool(&mut self, env_key: &str, default: bool) -> Result<bool, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(SettingsError::ParseBool {
+                    key: env_key.to_owned(),
+                    value,
+                }),
+            },
+            |value, path| match value {
+                FileValue::Boolean(flag) => Ok(*flag),
+                other => Err(type_error(path, key, "a boolean", other)),
+            },
+        )
+    }
+
+    /// Comma-separated in the environment, an array of strings in the file; blanks ignored
+    pub(crate) fn list(
+        &mut self,
+        env_key: &str,
+        default: &[String],
+    ) -> Result<Vec<String>, SettingsError> {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default.to_vec(),
+            |value| {
+                Ok(value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_owned)
+                    .collect())
+            },
+            |value, path| {
+                let FileValue::Array(items) = value else {
+                    return Err(type_error(path, key, "an array of strings", value));
+                };
+                items
+                    .iter()
+                    .map(|item| match item {
+                        FileValue::String(text) => Ok(text.trim().to_owned()),
+                        other => Err(type_error(path, key, "an array of strings", other)),
+                    })
+                    .filter(|item| !matches!(item, Ok(text) if text.is_empty()))
+                    .collect()
+            },
+        )
+    }
+
+    /// A string naming one of a type's variants
+    pub(crate) fn parsed<T>(&mut self, env_key: &str, default: T) -> Result<T, SettingsError>
+    where
+        T: std::str::FromStr<Err = String> + Serialize,
+    {
+        let key = Self::file_key(env_key);
+        self.resolve(
+            env_key,
+            default,
+            |value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|e| SettingsError::Invalid(format!("{env_key}: {e}")))
+            },
+            |value, path| match value {
+                FileValue::String(text) => text
+                    .parse()
+                    .map_err(|e| SettingsError::Invalid(format!("{path}: {key}: {e}"))),
+                other => Err(type_error(path, key, "a string", other)),
+            },
+        )
+    }
+}
+
+fn type_error(path: &str, key: &str, expected: &str, found: &FileValue) -> SettingsError {
+    let found = match found {
+        _ if is_secret(key) => found.type_name().to_owned(),
+        FileValue::String(text) => format!("string {text:?}"),
+        FileValue::Integer(number) => format!("integer {number}"),
+        FileValue::Float(number) => format!("float {number}"),
+        FileValue::Boolean(flag) => format!("boolean {flag}"),
+        FileValue::Array(_) => "array".to_owned(),
+    };
+    SettingsError::ConfigType {
+        path: path.to_owned(),
+        key: key.to_owned(),
+        expected: expected.to_owned(),
+        found,
+    }
+}
+
+/// Rule and bank file locations resolved at startup, keyed by environment variable
+static RESOLVED_PATHS: LazyLock<RwLock<BTreeMap<String, String>>> =
+    LazyLock::new(|| RwLock::new(BTreeMap::new()));
+
+/// Make resolved file locations visible to the modules that load those files lazily
+pub(crate) fn install_paths(paths: &[(&str, &str)]) {
+    let mut resolved = RESOLVED_PATHS.write().unwrap();
+    for (env_key, path) in paths {
+        resolved.insert((*env_key).to_owned(), (*path).to_owned());
+    }
+}
+
+/// Location of a rule or bank file: the value resolved at startup, else the variable,
+/// else `default`
+pub(crate) fn configured_path(env_key: &str, default: &str) -> String {
+    if let Some(path) = RESOLVED_PATHS.read().unwrap().get(env_key) {
+        return path.clone();
+    }
+    std::env::var(env_key).unwrap_or_else(|_| default.to_owned())
+}
@@ -1 +1,4 @@
+pub mod cors;
+pub mod file;
+pub mod layers;
 pub mod settings;
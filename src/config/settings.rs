@@ -1,99 +1,1175 @@
 use std::env;
 use std::num::ParseFloatError;
 use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
+use crate::config::cors::{CorsPolicy, CorsSettings};
+use crate::config::layers::{EffectiveConfig, Layers, install_paths};
+use crate::firewall_core::DEFAULT_MAX_INPUT_LENGTH;
+use crate::modules::admission::dtos::AdmissionLimits;
+use crate::modules::alerting::service::validate_webhook_url;
+use crate::modules::appeals::dtos::AppealPolicy;
+use crate::modules::audit::batched::WriteBehindConfig;
+use crate::modules::audit::compression::AuditCompression;
+use crate::modules::audit::disk::AuditDiskConfig;
+use crate::modules::audit::encryption::AuditEncryptionKey;
+use crate::modules::audit::integrity::AuditIntegrityConfig;
+use crate::modules::audit::storage::{AuditBackend, PromptStorageMode};
+use crate::modules::audit::suppression::AuditSuppressionPolicy;
+use crate::modules::bias_detection::dtos::BiasRewriteConfig;
+use crate::modules::bias_detection::service::DEFAULT_BIAS_RULES_DIR;
+use crate::modules::bias_detection::service::validate_threshold;
+use crate::modules::caches::dtos::CacheBudgetConfig;
+use crate::modules::demo::dtos::DemoConfig;
+use crate::modules::dependencies::dtos::StartupConfig;
+use crate::modules::diagnostics::dtos::SlowRequestPolicy;
+use crate::modules::eu_law_compliance::service::DEFAULT_EU_KEYWORDS_PATH;
+use crate::modules::http_cache::dtos::CachePolicy;
+use crate::modules::mistral_ai::client::MistralApiPaths;
+use crate::modules::mistral_ai::concurrency::MistralConcurrencyLimits;
+use crate::modules::mistral_ai::severity::{ModerationSeverity, SeverityMode, SeverityWeights};
+use crate::modules::model_watch::dtos::ModelWatchConfig;
+use crate::modules::preprocessing::dtos::PromptTransform;
+use crate::modules::prompt_firewall::dtos::LengthOverflowPolicy;
+use crate::modules::prompt_firewall::misses::DEFAULT_FIREWALL_MISS_CAPACITY;
+use crate::modules::prompt_firewall::rules::DEFAULT_FIREWALL_RULES_PATH;
+use crate::modules::prompt_firewall::service::{
+    DEFAULT_RULES_ARCHIVE_LIMIT, validate_max_input_length,
+};
+use crate::modules::prompt_firewall::unfurl::UrlUnfurlConfig;
+use crate::modules::prompt_firewall::urls::UrlPolicy;
+use crate::modules::repeat_offender::dtos::RepeatOffenderConfig;
+use crate::modules::sanitize_probing::dtos::SanitizeProbingConfig;
+use crate::modules::semantic_detection::dtos::{
+    BankHygienePolicy, SemanticChunkingPolicy, SemanticSamplingPolicy, SemanticThresholds,
+};
+use crate::modules::semantic_detection::embeddings::DEFAULT_EMBEDDING_CACHE_SIZE;
+use crate::modules::semantic_detection::service::DEFAULT_ATTACK_BANK_PATH;
+use crate::modules::slo::dtos::SloObjectives;
+use crate::modules::telemetry::correlation::{
+    CorrelationIdPolicy, DEFAULT_MAX_CORRELATION_ID_LENGTH, validate_instance_id,
+};
+use crate::modules::telemetry::sampling::DEFAULT_LOG_SAMPLING_WINDOW_SECS;
+use crate::modules::usage::dtos::{ApiKeyRegistryConfig, UsageConfig};
+use crate::workflow::{
+    DecisionPolicy, DocumentScanLimits, ExplanationPolicy, JudgePromptTemplate, LlmJudgeConfig,
+    OutputAnalysisConfig, OutputModerationChunking, RefusalPolicy, RefusalTemplates, RiskWeights,
+    StageFailurePolicy, UnmoderatedPolicy,
+};
+
 pub const DEFAULT_MISTRAL_BASE_URL: &str = "https://api.mistral.ai";
 pub const DEFAULT_MISTRAL_GENERATION_MODEL: &str = "mistral-small-latest";
 pub const DEFAULT_MISTRAL_MODERATION_MODEL: &str = "mistral-moderation-latest";
+/// `MISTRAL_MODERATION_MODEL` value that runs without moderation
+pub const MODERATION_DISABLED: &str = "disabled";
 pub const DEFAULT_MISTRAL_EMBEDDING_MODEL: &str = "mistral-embed";
+pub const DEFAULT_AUDIT_SQLITE_PATH: &str = "prompt_sentinel_audit.sqlite3";
+pub const DEFAULT_SLED_DB_PATH: &str = "prompt_sentinel_data";
+pub const DEFAULT_METRICS_ADDR: &str = "0.0.0.0:9090";
+/// Configuration file read when `PROMPT_SENTINEL_CONFIG` is unset, if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "sentinel.toml";
+pub const CONFIG_PATH_ENV: &str = "PROMPT_SENTINEL_CONFIG";
 
 #[derive(Clone, Debug)]
 pub struct AppSettings {
     pub server_port: u16,
     pub mistral_api_key: Option<String>,
     pub mistral_base_url: String,
+    /// Endpoint paths joined to `mistral_base_url`, for gateways that route them
+    /// elsewhere (default: Mistral's own routes)
+    pub mistral_paths: MistralApiPaths,
     pub generation_model: String,
+    /// System message sent ahead of every prompt at generation, e.g. defensive
+    /// instructions hardening the model against injection (default: none)
+    pub generation_preamble: Option<String>,
+    /// Generate with temperature 0 and `deterministic_seed` for requests that do not set
+    /// `deterministic` themselves (default: off)
+    pub deterministic_generation: bool,
+    /// Seed sent with every deterministic generation (default: 0)
+    pub deterministic_seed: u64,
     pub moderation_model: Option<String>,
     pub embedding_model: String,
     pub bias_threshold: f32,
+    /// Level, time budget and length of debiased rewrite suggestions
+    /// (default: Medium and above, 1.5s, 256 tokens)
+    pub bias_rewrite: BiasRewriteConfig,
     pub max_input_length: usize,
+    /// What happens to prompts over `max_input_length`: block them (the default) or
+    /// truncate them and evaluate what is kept
+    pub length_overflow_policy: LengthOverflowPolicy,
     /// Threshold for semantic Low/Medium boundary (default: 0.70)
     pub semantic_medium_threshold: f32,
     /// Threshold for semantic Medium/High boundary (default: 0.80)
     pub semantic_high_threshold: f32,
     /// Extra buffer added to semantic thresholds to reduce borderline false positives
     pub semantic_decision_margin: f32,
+    /// Also moderate the fragments removed by firewall sanitization
+    pub moderate_removed_content: bool,
+    /// Also moderate the translated output when it differs from the English output
+    /// (default: on; needs a moderation model)
+    pub moderate_translated_output: bool,
+    /// Whether requests answered without moderation, because no moderation model is
+    /// configured, complete or take the sanitized path (default: sanitize)
+    pub unmoderated_requests: UnmoderatedPolicy,
+    /// How flagged moderation categories turn into a severity (default: `max` over the
+    /// built-in category weights)
+    pub moderation_severity: ModerationSeverity,
+    /// Number of configuration snapshots kept in history (default: 20)
+    pub config_history_limit: usize,
+    /// Storage backend for audit records (default: sled)
+    pub audit_backend: AuditBackend,
+    /// Database file used when `audit_backend` is SQLite
+    pub audit_sqlite_path: String,
+    /// Encrypts sled audit records at rest when set (default: off)
+    pub audit_encryption_key: Option<AuditEncryptionKey>,
+    /// zstd compression of sled audit records longer than the threshold (default:
+    /// above 4096 bytes, level 3)
+    pub audit_compression: AuditCompression,
+    /// Queue audit appends and write them in batches when set, trading a short
+    /// durability window for throughput (default: off)
+    pub audit_write_behind: Option<WriteBehindConfig>,
+    /// Background verification of the audit chain (default: the newest 1000 records
+    /// every 5 minutes, the whole chain daily, readiness unaffected)
+    pub audit_integrity: AuditIntegrityConfig,
+    /// Disk usage checks of the sled database (default: every minute, no high-water
+    /// mark)
+    pub audit_disk: AuditDiskConfig,
+    /// Webhook that receives critical alerts as JSON, such as audit chain breaks
+    /// (default: none)
+    pub alert_webhook_url: Option<String>,
+    /// Periodic comparison of the Mistral model list with the one seen before
+    /// (default: hourly, `deprecated` and `legacy` ids, no webhook)
+    pub model_watch: ModelWatchConfig,
+    /// Escalation of prompts resembling recently blocked ones (default: off)
+    pub repeat_offender: RepeatOffenderConfig,
+    /// Blocking of sessions that keep resending content sanitization removes
+    /// (default: off)
+    pub sanitize_probing: SanitizeProbingConfig,
+    /// Public playground mode; cannot be combined with admin or support tokens
+    /// (default: off)
+    pub demo: DemoConfig,
+    /// Bearer token required by admin endpoints; they are disabled when unset
+    pub admin_token: Option<String>,
+    /// Bearer token for support staff, accepted by the decision explanation endpoint
+    /// only; unset by default
+    pub support_token: Option<String>,
+    /// Cross-origin policies; both default to same-origin only
+    pub cors: CorsSettings,
+    /// Bounds on `POST /api/compliance/scan-documents` batches
+    pub document_scan_limits: DocumentScanLimits,
+    /// Caps on concurrent Mistral calls per operation
+    pub mistral_concurrency: MistralConcurrencyLimits,
+    /// Bounds on compliance workflows executing at once, with a wait queue in front
+    /// of them (default: off)
+    pub admission: Option<AdmissionLimits>,
+    /// Transforms run on prompts before the firewall, in order (default: none)
+    pub prompt_preprocessors: Vec<PromptTransform>,
+    /// Refuse to start when the firewall rules file is invalid or its assertions fail,
+    /// instead of falling back to the built-in rules
+    pub strict_firewall_rules: bool,
+    /// What happens to URLs in prompts per scheme and domain, from the JSON file at
+    /// `URL_POLICY_PATH` (default: every URL allowed and recorded)
+    pub url_policy: UrlPolicy,
+    /// Fetching and scanning the pages behind allowlisted URLs; makes network calls,
+    /// so it must be turned on explicitly (default: off; 3 URLs, 16 KiB, 2s each)
+    pub url_unfurl: UrlUnfurlConfig,
+    /// Fraction of short, clean English prompts that still get the semantic scan
+    /// (default: all of them)
+    pub semantic_sampling: SemanticSamplingPolicy,
+    /// Secret the sampling choice is keyed with; set the same one on every instance
+    /// so retries are sampled alike wherever they land (default: random per process)
+    pub semantic_sampling_secret: Option<String>,
+    /// Duplicate checks on the attack bank; strict mode refuses exact duplicates
+    pub semantic_bank_hygiene: BankHygienePolicy,
+    /// How long prompts are split into windows for the semantic scan
+    /// (default: prompts over 1024 characters, in 512-character windows)
+    pub semantic_chunking: SemanticChunkingPolicy,
+    /// Embeddings of recently scanned prompts kept so repeats skip the embedding call;
+    /// 0 turns the cache off (default: 1024)
+    pub semantic_embedding_cache_size: usize,
+    /// Estimated memory all in-memory caches may hold together, enforced on a schedule
+    /// (default: 64 MiB, checked every 30s)
+    pub cache_budget: CacheBudgetConfig,
+    /// How generated answers too long for one moderation call are split
+    /// (default: answers over 16000 characters, in 16000-character chunks)
+    pub output_moderation_chunking: OutputModerationChunking,
+    /// Checks besides moderation run on generated answers (default: none)
+    pub output_analysis: OutputAnalysisConfig,
+    /// Second opinion of the generation model on prompts in the gray zone, with its
+    /// budget and question (default: off)
+    pub llm_judge: LlmJudgeConfig,
+    /// How much decision explanations reveal per audience, and the feedback path they
+    /// point users at (default: generalized for support, full for admins, no feedback)
+    pub explanation: ExplanationPolicy,
+    /// Lifetime and uses of the firewall exemption an overturned appeal grants
+    /// (default: one use within seven days)
+    pub appeals: AppealPolicy,
+    /// Whether Mistral and the semantic bank are validated before serving, in the
+    /// background, or not at all (default: fail fast on both)
+    pub startup: StartupConfig,
+    /// Seconds between sweeps that archive expired and used-up firewall exemptions
+    pub exemption_sweep_interval_secs: u64,
+    /// Whether audit records keep the prompt text, which replaying a decision needs
+    /// (default: full)
+    pub audit_prompt_storage: PromptStorageMode,
+    /// Which repeated synthetic decisions are collapsed into per-window aggregate
+    /// records, and the window length (default: off)
+    pub audit_suppression: AuditSuppressionPolicy,
+    /// Days a generated EU compliance report is kept; 0 keeps reports as long as the
+    /// audit trail, which is never pruned (default: 0)
+    pub compliance_report_retention_days: u64,
+    /// Number of firewall rule set versions kept for historical replay (default: 20)
+    pub firewall_rules_history_limit: usize,
+    /// Firewall misses kept for `GET /api/stats/firewall-misses` (default: 100)
+    pub firewall_miss_buffer_size: usize,
+    /// Whether each Mistral-backed stage blocks the request or is skipped when it fails
+    /// (default: moderation closed, everything else open)
+    pub stage_failure_policy: StageFailurePolicy,
+    /// Latency and availability objectives tracked by `GET /api/slo/status`
+    /// (default: 99% within 2s, 99.9% available)
+    pub slo: SloObjectives,
+    /// Latency thresholds past which requests leave a diagnostic in
+    /// `GET /api/debug/slow-requests` (default: 5s, 100 entries kept)
+    pub slow_requests: SlowRequestPolicy,
+    /// Directory of the sled database (default: `prompt_sentinel_data`)
+    pub sled_db_path: String,
+    /// Address the Prometheus metrics server listens on (default: `0.0.0.0:9090`)
+    pub metrics_addr: String,
+    pub firewall_rules_path: String,
+    /// Directory searched for `bias_rules.<lang>.json` term packs
+    pub bias_rules_dir: String,
+    pub semantic_attack_bank_path: String,
+    pub eu_risk_keywords_path: String,
+    /// Caller-supplied correlation ids kept as is; others are replaced by generated ids
+    /// (default: up to 128 plain characters, any prefix)
+    pub correlation_ids: CorrelationIdPolicy,
+    /// Identifies this replica in the correlation ids it generates (default: a random
+    /// id kept in the sled database, or a new one per start without it)
+    pub instance_id: Option<String>,
+    /// Seconds in which repeats of one WARN line from one client are collapsed into a
+    /// summary; 0 logs every line (default: 60)
+    pub log_sampling_window_secs: u64,
+    /// Weights of the composite risk score and the lowest score of a blocked request
+    /// (default: firewall 40, semantic 30, bias 10, moderation 20, 10 per degraded stage,
+    /// floor 80)
+    pub risk_weights: RiskWeights,
+    /// Ordered rules that turn stage evidence into a decision, from the JSON file at
+    /// `DECISION_POLICY_PATH` (default: the built-in precedence)
+    pub decision_policy: DecisionPolicy,
+    /// Refusal messages for blocked requests: on by default with `REFUSAL_MESSAGES`
+    /// (default: off), worded by the JSON file at `REFUSAL_TEMPLATES_PATH` (default: the
+    /// built-in English, Spanish, French and German templates)
+    pub refusal: RefusalPolicy,
+    /// Allow faults to be injected into Mistral calls and audit writes through
+    /// `POST /api/chaos/config`; for staging only (default: off)
+    pub chaos_mode: bool,
+    /// Mark the instance as a staging target that `sentinel-replay` may send recorded
+    /// traffic to; never set it in production (default: off)
+    pub accept_replay_traffic: bool,
+    /// `Cache-Control: max-age` of the read-only config and summary endpoints
+    /// (default: 5s each)
+    pub http_cache: CachePolicy,
+    /// API keys callers may present, with their daily quotas, from the JSON file at
+    /// `API_KEYS_PATH` (default: none, so every request is anonymous)
+    pub api_keys: ApiKeyRegistryConfig,
+    /// Pricing of per-key usage and how often it is persisted
+    /// (default: no price, written every 10s)
+    pub usage: UsageConfig,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            server_port: 3000,
+            mistral_api_key: None,
+            mistral_base_url: DEFAULT_MISTRAL_BASE_URL.to_owned(),
+            mistral_paths: MistralApiPaths::default(),
+            generation_model: DEFAULT_MISTRAL_GENERATION_MODEL.to_owned(),
+            generation_preamble: None,
+            deterministic_generation: false,
+            deterministic_seed: 0,
+            moderation_model: Some(DEFAULT_MISTRAL_MODERATION_MODEL.to_owned()),
+            embedding_model: DEFAULT_MISTRAL_EMBEDDING_MODEL.to_owned(),
+            bias_threshold: 0.35,
+            bias_rewrite: BiasRewriteConfig::default(),
+            max_input_length: DEFAULT_MAX_INPUT_LENGTH,
+            length_overflow_policy: LengthOverflowPolicy::default(),
+            semantic_medium_threshold: 0.70,
+            semantic_high_threshold: 0.80,
+            semantic_decision_margin: 0.02,
+            moderate_removed_content: false,
+            moderate_translated_output: true,
+            unmoderated_requests: UnmoderatedPolicy::default(),
+            moderation_severity: ModerationSeverity::default(),
+            config_history_limit: 20,
+            audit_backend: AuditBackend::default(),
+            audit_sqlite_path: DEFAULT_AUDIT_SQLITE_PATH.to_owned(),
+            audit_encryption_key: None,
+            audit_compression: AuditCompression::default(),
+            audit_write_behind: None,
+            audit_integrity: AuditIntegrityConfig::default(),
+            audit_disk: AuditDiskConfig::default(),
+            alert_webhook_url: None,
+            model_watch: ModelWatchConfig::default(),
+            repeat_offender: RepeatOffenderConfig::default(),
+            sanitize_probing: SanitizeProbingConfig::default(),
+            demo: DemoConfig::default(),
+            admin_token: None,
+            support_token: None,
+            cors: CorsSettings::default(),
+            document_scan_limits: DocumentScanLimits::default(),
+            mistral_concurrency: MistralConcurrencyLimits::default(),
+            admission: None,
+            prompt_preprocessors: Vec::new(),
+            strict_firewall_rules: false,
+            url_policy: UrlPolicy::default(),
+            url_unfurl: UrlUnfurlConfig::default(),
+            semantic_sampling: SemanticSamplingPolicy::default(),
+            semantic_sampling_secret: None,
+            semantic_bank_hygiene: BankHygienePolicy::default(),
+            semantic_chunking: SemanticChunkingPolicy::default(),
+            semantic_embedding_cache_size: DEFAULT_EMBEDDING_CACHE_SIZE,
+            cache_budget: CacheBudgetConfig::default(),
+            output_moderation_chunking: OutputModerationChunking::default(),
+            output_analysis: OutputAnalysisConfig::default(),
+            llm_judge: LlmJudgeConfig::default(),
+            explanation: ExplanationPolicy::default(),
+            appeals: AppealPolicy::default(),
+            startup: StartupConfig::default(),
+            exemption_sweep_interval_secs: 60,
+            audit_prompt_storage: PromptStorageMode::default(),
+            audit_suppression: AuditSuppressionPolicy::default(),
+            compliance_report_retention_days: 0,
+            firewall_rules_history_limit: DEFAULT_RULES_ARCHIVE_LIMIT,
+            firewall_miss_buffer_size: DEFAULT_FIREWALL_MISS_CAPACITY,
+            stage_failure_policy: StageFailurePolicy::default(),
+            slo: SloObjectives::default(),
+            slow_requests: SlowRequestPolicy::default(),
+            sled_db_path: DEFAULT_SLED_DB_PATH.to_owned(),
+            metrics_addr: DEFAULT_METRICS_ADDR.to_owned(),
+            firewall_rules_path: DEFAULT_FIREWALL_RULES_PATH.to_owned(),
+            bias_rules_dir: DEFAULT_BIAS_RULES_DIR.to_owned(),
+            semantic_attack_bank_path: DEFAULT_ATTACK_BANK_PATH.to_owned(),
+            eu_risk_keywords_path: DEFAULT_EU_KEYWORDS_PATH.to_owned(),
+            correlation_ids: CorrelationIdPolicy::default(),
+            instance_id: None,
+            log_sampling_window_secs: DEFAULT_LOG_SAMPLING_WINDOW_SECS,
+            risk_weights: RiskWeights::default(),
+            decision_policy: DecisionPolicy::default(),
+            refusal: RefusalPolicy::default(),
+            chaos_mode: false,
+            accept_replay_traffic: false,
+            http_cache: CachePolicy::default(),
+            api_keys: ApiKeyRegistryConfig::default(),
+            usage: UsageConfig::default(),
+        }
+    }
 }
 
 impl AppSettings {
+    /// Settings from environment variables and defaults only
     pub fn from_env() -> Result<Self, SettingsError> {
-        let server_port = parse_env_u16("SERVER_PORT", 3000)?;
-        let bias_threshold = parse_env_f32("BIAS_THRESHOLD", 0.35)?;
-        let max_input_length = parse_env_usize("MAX_INPUT_LENGTH", 4096)?;
-        let semantic_medium_threshold = parse_env_f32("SEMANTIC_MEDIUM_THRESHOLD", 0.70)?;
-        let semantic_high_threshold = parse_env_f32("SEMANTIC_HIGH_THRESHOLD", 0.80)?;
-        let semantic_decision_margin = parse_env_f32("SEMANTIC_DECISION_MARGIN", 0.02)?;
+        Self::load_from(None, &|key| env::var(key).ok()).map(|(settings, _)| settings)
+    }
+
+    /// Settings from the configuration file named by `PROMPT_SENTINEL_CONFIG`, or
+    /// `sentinel.toml` when it exists, with environment variables taking precedence
+    pub fn load() -> Result<(Self, EffectiveConfig), SettingsError> {
+        let path = match env::var(CONFIG_PATH_ENV) {
+            Ok(path) if !path.trim().is_empty() => Some(PathBuf::from(path)),
+            _ => Some(PathBuf::from(DEFAULT_CONFIG_PATH)).filter(|path| path.exists()),
+        };
+        Self::load_from(path.as_deref(), &|key| env::var(key).ok())
+    }
+
+    /// Layer `env` over `file` over defaults, reporting where each value came from
+    ///
+    /// A missing or malformed file, an unknown key and a value of the wrong type are
+    /// errors rather than falling back to the default.
+    pub fn load_from(
+        file: Option<&Path>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<(Self, EffectiveConfig), SettingsError> {
+        let mut layers = Layers::new(file, env)?;
+        let settings = Self::from_layers(&mut layers)?;
+        Ok((settings, layers.finish()))
+    }
+
+    /// Point the modules that read rule and bank files lazily at the configured paths
+    pub fn install_file_paths(&self) {
+        install_paths(&[
+            ("PROMPT_FIREWALL_RULES_PATH", &self.firewall_rules_path),
+            ("BIAS_RULES_DIR", &self.bias_rules_dir),
+            ("SEMANTIC_ATTACK_BANK_PATH", &self.semantic_attack_bank_path),
+            (
+                "PROMPT_SENTINEL_EU_KEYWORDS_PATH",
+                &self.eu_risk_keywords_path,
+            ),
+        ]);
+    }
+
+    fn from_layers(layers: &mut Layers) -> Result<Self, SettingsError> {
+        let server_port = layers.u16("SERVER_PORT", 3000)?;
+        let admin_token = layers.optional_string("ADMIN_API_TOKEN")?;
+        let support_token = layers.optional_string("SUPPORT_API_TOKEN")?;
+        let mistral_api_key = layers.optional_string("MISTRAL_API_KEY")?;
+        let mistral_base_url = layers.string("MISTRAL_BASE_URL", DEFAULT_MISTRAL_BASE_URL)?;
+        let path_defaults = MistralApiPaths::default();
+        let mistral_paths = MistralApiPaths {
+            chat: layers.string("MISTRAL_CHAT_PATH", &path_defaults.chat)?,
+            moderation: layers.string("MISTRAL_MODERATION_PATH", &path_defaults.moderation)?,
+            embeddings: layers.string("MISTRAL_EMBEDDINGS_PATH", &path_defaults.embeddings)?,
+            models: layers.string("MISTRAL_MODELS_PATH", &path_defaults.models)?,
+        };
+        let generation_model =
+            layers.string("MISTRAL_GENERATION_MODEL", DEFAULT_MISTRAL_GENERATION_MODEL)?;
+        let generation_preamble = layers.optional_string("MISTRAL_GENERATION_PREAMBLE")?;
+        let deterministic_generation = layers.bool("DETERMINISTIC_GENERATION", false)?;
+        let deterministic_seed = layers.usize("DETERMINISTIC_SEED", 0)? as u64;
+        let moderation_model =
+            layers.string("MISTRAL_MODERATION_MODEL", DEFAULT_MISTRAL_MODERATION_MODEL)?;
+        // Empty or `disabled` turns moderation off
+        let moderation_model = Some(moderation_model.trim().to_owned())
+            .filter(|model| !model.is_empty() && !model.eq_ignore_ascii_case(MODERATION_DISABLED));
+        let embedding_model =
+            layers.string("MISTRAL_EMBEDDING_MODEL", DEFAULT_MISTRAL_EMBEDDING_MODEL)?;
+        let firewall_rules_path =
+            layers.string("PROMPT_FIREWALL_RULES_PATH", DEFAULT_FIREWALL_RULES_PATH)?;
+        let bias_rules_dir = layers.string("BIAS_RULES_DIR", DEFAULT_BIAS_RULES_DIR)?;
+        let semantic_attack_bank_path =
+            layers.string("SEMANTIC_ATTACK_BANK_PATH", DEFAULT_ATTACK_BANK_PATH)?;
+        let eu_risk_keywords_path =
+            layers.string("PROMPT_SENTINEL_EU_KEYWORDS_PATH", DEFAULT_EU_KEYWORDS_PATH)?;
+        let sled_db_path = layers.string("SLED_DB_PATH", DEFAULT_SLED_DB_PATH)?;
+        let audit_sqlite_path = layers.string("AUDIT_SQLITE_PATH", DEFAULT_AUDIT_SQLITE_PATH)?;
+        let metrics_addr = layers.string("METRICS_ADDR", DEFAULT_METRICS_ADDR)?;
+        let bias_threshold = layers.f32("BIAS_THRESHOLD", 0.35)?;
+        let rewrite_defaults = BiasRewriteConfig::default();
+        let bias_rewrite = BiasRewriteConfig {
+            min_level: layers.parsed("BIAS_REWRITE_MIN_LEVEL", rewrite_defaults.min_level)?,
+            timeout_ms: layers.usize(
+                "BIAS_REWRITE_TIMEOUT_MS",
+                rewrite_defaults.timeout_ms as usize,
+            )? as u64,
+            max_tokens: layers.usize(
+                "BIAS_REWRITE_MAX_TOKENS",
+                rewrite_defaults.max_tokens as usize,
+            )? as u32,
+        };
+        let max_input_length = layers.usize("MAX_INPUT_LENGTH", DEFAULT_MAX_INPUT_LENGTH)?;
+        let length_overflow_policy =
+            layers.parsed("LENGTH_OVERFLOW_POLICY", LengthOverflowPolicy::default())?;
+        let semantic_medium_threshold = layers.f32("SEMANTIC_MEDIUM_THRESHOLD", 0.70)?;
+        let semantic_high_threshold = layers.f32("SEMANTIC_HIGH_THRESHOLD", 0.80)?;
+        let semantic_decision_margin = layers.f32("SEMANTIC_DECISION_MARGIN", 0.02)?;
+        let moderate_removed_content = layers.bool("MODERATE_REMOVED_CONTENT", false)?;
+        let moderate_translated_output = layers.bool("MODERATE_TRANSLATED_OUTPUT", true)?;
+        let unmoderated_requests =
+            layers.parsed("UNMODERATED_REQUESTS", UnmoderatedPolicy::default())?;
+        let moderation_severity = ModerationSeverity {
+            mode: layers.parsed("MODERATION_SEVERITY_MODE", SeverityMode::default())?,
+            weights: match layers.optional_string("MODERATION_SEVERITY_WEIGHTS_PATH")? {
+                Some(path) => {
+                    SeverityWeights::load(Path::new(&path)).map_err(SettingsError::Invalid)?
+                }
+                None => SeverityWeights::default(),
+            },
+        };
+        let strict_firewall_rules = layers.bool("FIREWALL_RULES_STRICT", false)?;
+        let config_history_limit = layers.usize("CONFIG_HISTORY_LIMIT", 20)?;
+        let audit_backend = layers.parsed("AUDIT_BACKEND", AuditBackend::default())?;
+        if !audit_backend.is_available() {
+            return Err(SettingsError::Invalid(format!(
+                "AUDIT_BACKEND {:?} needs a build with the {} feature",
+                audit_backend,
+                audit_backend.required_feature().unwrap_or_default()
+            )));
+        }
+        let audit_encryption_key = match layers.optional_string("AUDIT_ENCRYPTION_KEY")? {
+            Some(value) => Some(
+                AuditEncryptionKey::from_config(&value)
+                    .map_err(|e| SettingsError::Invalid(format!("AUDIT_ENCRYPTION_KEY: {e}")))?,
+            ),
+            None => None,
+        };
+        if audit_encryption_key.is_some() && audit_backend != AuditBackend::Sled {
+            return Err(SettingsError::Invalid(
+                "AUDIT_ENCRYPTION_KEY is only supported with AUDIT_BACKEND=sled".to_owned(),
+            ));
+        }
+        let compression_defaults = AuditCompression::default();
+        let audit_compression = AuditCompression {
+            threshold_bytes: layers.usize(
+                "AUDIT_COMPRESSION_THRESHOLD_BYTES",
+                compression_defaults.threshold_bytes,
+            )?,
+            level: layers.usize(
+                "AUDIT_COMPRESSION_LEVEL",
+                compression_defaults.level as usize,
+            )? as i32,
+        };
+        let write_behind_defaults = WriteBehindConfig::default();
+        let write_behind = WriteBehindConfig {
+            flush_interval_ms: layers.usize(
+                "AUDIT_FLUSH_INTERVAL_MS",
+                write_behind_defaults.flush_interval_ms as usize,
+            )? as u64,
+            max_batch_size: layers
+                .usize("AUDIT_BATCH_SIZE", write_behind_defaults.max_batch_size)?,
+            queue_capacity: layers
+                .usize("AUDIT_QUEUE_CAPACITY", write_behind_defaults.queue_capacity)?,
+        };
+        let audit_write_behind = if layers.bool("AUDIT_WRITE_BEHIND", false)? {
+            write_behind
+                .validate()
+                .map_err(|e| SettingsError::Invalid(format!("AUDIT_WRITE_BEHIND: {e}")))?;
+            Some(write_behind)
+        } else {
+            None
+        };
+        let integrity_defaults = AuditIntegrityConfig::default();
+        let audit_integrity = AuditIntegrityConfig {
+            tail_interval_secs: layers.usize(
+                "AUDIT_INTEGRITY_INTERVAL_SECS",
+                integrity_defaults.tail_interval_secs as usize,
+            )? as u64,
+            tail_records: layers.usize(
+                "AUDIT_INTEGRITY_TAIL_RECORDS",
+                integrity_defaults.tail_records,
+            )?,
+            full_interval_secs: layers.usize(
+                "AUDIT_INTEGRITY_FULL_INTERVAL_SECS",
+                integrity_defaults.full_interval_secs as usize,
+            )? as u64,
+            fail_readiness: layers.bool(
+                "AUDIT_INTEGRITY_FAIL_READINESS",
+                integrity_defaults.fail_readiness,
+            )?,
+        };
+        let disk_defaults = AuditDiskConfig::default();
+        let audit_disk = AuditDiskConfig {
+            check_interval_secs: layers.usize(
+                "AUDIT_DISK_CHECK_INTERVAL_SECS",
+                disk_defaults.check_interval_secs as usize,
+            )? as u64,
+            high_water_bytes: layers.usize(
+                "AUDIT_DISK_HIGH_WATER_BYTES",
+                disk_defaults.high_water_bytes as usize,
+            )? as u64,
+            high_water_policy: layers.parsed(
+                "AUDIT_DISK_HIGH_WATER_POLICY",
+                disk_defaults.high_water_policy,
+            )?,
+            max_space_amplification: layers.f64(
+                "AUDIT_DISK_MAX_SPACE_AMPLIFICATION",
+                disk_defaults.max_space_amplification,
+            )?,
+        };
+        let alert_webhook_url = layers.optional_string("ALERT_WEBHOOK_URL")?;
+        let watch_defaults = ModelWatchConfig::default();
+        let model_watch = ModelWatchConfig {
+            interval_secs: layers.usize(
+                "MODEL_WATCH_INTERVAL_SECS",
+                watch_defaults.interval_secs as usize,
+            )? as u64,
+            deprecation_patterns: layers.list(
+                "MODEL_WATCH_DEPRECATION_PATTERNS",
+                &watch_defaults.deprecation_patterns,
+            )?,
+            notify: layers.bool("MODEL_WATCH_NOTIFY", watch_defaults.notify)?,
+        };
+
+        let repeat_defaults = RepeatOffenderConfig::default();
+        let repeat_offender = RepeatOffenderConfig {
+            mode: layers.parsed("REPEAT_OFFENDER_MODE", repeat_defaults.mode)?,
+            window_size: layers.usize("REPEAT_OFFENDER_WINDOW", repeat_defaults.window_size)?,
+            similarity_threshold: layers.f32(
+                "REPEAT_OFFENDER_THRESHOLD",
+                repeat_defaults.similarity_threshold,
+            )?,
+            risk_bonus: layers.f32("REPEAT_OFFENDER_RISK_BONUS", repeat_defaults.risk_bonus)?,
+        };
+
+        let probing_defaults = SanitizeProbingConfig::default();
+        let sanitize_probing = SanitizeProbingConfig {
+            enabled: layers.bool("SANITIZE_PROBING_ENABLED", probing_defaults.enabled)?,
+            threshold: layers.usize("SANITIZE_PROBING_THRESHOLD", probing_defaults.threshold)?,
+            window_secs: layers.usize(
+                "SANITIZE_PROBING_WINDOW_SECS",
+                probing_defaults.window_secs as usize,
+            )? as u64,
+            max_edit_ratio: layers.f32(
+                "SANITIZE_PROBING_MAX_EDIT_RATIO",
+                probing_defaults.max_edit_ratio,
+            )?,
+            ring_size: layers.usize("SANITIZE_PROBING_RING_SIZE", probing_defaults.ring_size)?,
+        };
+
+        let demo_defaults = DemoConfig::default();
+        let demo = DemoConfig {
+            enabled: layers.bool("DEMO_MODE", demo_defaults.enabled)?,
+            quota_requests: u32::try_from(
+                layers.usize("DEMO_QUOTA_REQUESTS", demo_defaults.quota_requests as usize)?,
+            )
+            .unwrap_or(u32::MAX),
+            quota_window_secs: layers.usize(
+                "DEMO_QUOTA_WINDOW_SECS",
+                demo_defaults.quota_window_secs as usize,
+            )? as u64,
+            mock_moderation: layers.bool("DEMO_MOCK_MODERATION", demo_defaults.mock_moderation)?,
+            mock_embeddings: layers.bool("DEMO_MOCK_EMBEDDINGS", demo_defaults.mock_embeddings)?,
+        };
+
+        let cors_defaults = CorsSettings::default();
+        let cors = CorsSettings {
+            public: CorsPolicy {
+                allowed_origins: layers.list("CORS_ALLOWED_ORIGINS", &[])?,
+                allowed_methods: layers.list(
+                    "CORS_ALLOWED_METHODS",
+                    &cors_defaults.public.allowed_methods,
+                )?,
+                allowed_headers: layers.list(
+                    "CORS_ALLOWED_HEADERS",
+                    &cors_defaults.public.allowed_headers,
+                )?,
+                allow_credentials: layers.bool("CORS_ALLOW_CREDENTIALS", false)?,
+                allow_any: layers.bool("CORS_ALLOW_ANY", false)?,
+            },
+            admin: CorsPolicy {
+                allowed_origins: layers.list("CORS_ADMIN_ALLOWED_ORIGINS", &[])?,
+                allowed_methods: layers.list(
+                    "CORS_ADMIN_ALLOWED_METHODS",
+                    &cors_defaults.admin.allowed_methods,
+                )?,
+                allowed_headers: layers.list(
+                    "CORS_ADMIN_ALLOWED_HEADERS",
+                    &cors_defaults.admin.allowed_headers,
+                )?,
+                allow_credentials: layers.bool("CORS_ADMIN_ALLOW_CREDENTIALS", false)?,
+                allow_any: false,
+            },
+        };
+
+        let document_defaults = DocumentScanLimits::default();
+        let document_scan_limits = DocumentScanLimits {
+            max_documents: layers.usize(
+                "DOCUMENT_SCAN_MAX_DOCUMENTS",
+                document_defaults.max_documents,
+            )?,
+            max_total_bytes: layers.usize(
+                "DOCUMENT_SCAN_MAX_TOTAL_BYTES",
+                document_defaults.max_total_bytes,
+            )?,
+        };
+
+        let concurrency_defaults = MistralConcurrencyLimits::default();
+        let mistral_concurrency = MistralConcurrencyLimits {
+            max_concurrent_chat: layers.usize(
+                "MISTRAL_MAX_CONCURRENT_CHAT",
+                concurrency_defaults.max_concurrent_chat,
+            )?,
+            max_concurrent_moderation: layers.usize(
+                "MISTRAL_MAX_CONCURRENT_MODERATION",
+                concurrency_defaults.max_concurrent_moderation,
+            )?,
+            max_concurrent_embeddings: layers.usize(
+                "MISTRAL_MAX_CONCURRENT_EMBEDDINGS",
+                concurrency_defaults.max_concurrent_embeddings,
+            )?,
+            max_wait_ms: layers.usize(
+                "MISTRAL_CONCURRENCY_MAX_WAIT_MS",
+                concurrency_defaults.max_wait_ms as usize,
+            )? as u64,
+        };
+        let admission_defaults = AdmissionLimits::default();
+        let admission_limits = AdmissionLimits {
+            max_concurrent: layers.usize("ADMISSION_MAX_CONCURRENT", 0)?,
+            max_queue: layers.usize("ADMISSION_MAX_QUEUE", admission_defaults.max_queue)?,
+            max_wait_ms: layers.usize(
+                "ADMISSION_MAX_WAIT_MS",
+                admission_defaults.max_wait_ms as usize,
+            )? as u64,
+        };
+        // A limit of 0 leaves requests unbounded, as they were before admission control
+        let admission = (admission_limits.max_concurrent > 0).then_some(admission_limits);
+
+        let prompt_preprocessors = layers
+            .list("PROMPT_PREPROCESSORS", &[])?
+            .iter()
+            .map(|name| name.parse())
+            .collect::<Result<Vec<PromptTransform>, _>>()
+            .map_err(SettingsError::Invalid)?;
+
+        let url_policy = match layers.optional_string("URL_POLICY_PATH")? {
+            Some(path) => UrlPolicy::load(Path::new(&path)).map_err(SettingsError::Invalid)?,
+            None => UrlPolicy::default(),
+        };
+        let unfurl_defaults = UrlUnfurlConfig::default();
+        let url_unfurl = UrlUnfurlConfig {
+            enabled: layers.bool("URL_UNFURL_ENABLED", unfurl_defaults.enabled)?,
+            max_urls: layers.usize("URL_UNFURL_MAX_URLS", unfurl_defaults.max_urls)?,
+            max_bytes: layers.usize("URL_UNFURL_MAX_BYTES", unfurl_defaults.max_bytes)?,
+            timeout_ms: layers
+                .usize("URL_UNFURL_TIMEOUT_MS", unfurl_defaults.timeout_ms as usize)?
+                as u64,
+            max_redirects: layers
+                .usize("URL_UNFURL_MAX_REDIRECTS", unfurl_defaults.max_redirects)?,
+            allowed_networks: layers
+                .list("URL_UNFURL_ALLOWED_NETWORKS", &[])?
+                .iter()
+                .map(|network| network.parse())
+                .collect::<Result<_, _>>()
+                .map_err(SettingsError::Invalid)?,
+        };
+        if url_unfurl.enabled && (url_unfurl.max_bytes == 0 || url_unfurl.timeout_ms == 0) {
+            return Err(SettingsError::Invalid(
+                "URL_UNFURL_MAX_BYTES and URL_UNFURL_TIMEOUT_MS must be greater than zero"
+                    .to_owned(),
+            ));
+        }
+
+        let sampling_defaults = SemanticSamplingPolicy::default();
+        let semantic_sampling = SemanticSamplingPolicy {
+            rate: layers.f32("SEMANTIC_SAMPLING_RATE", sampling_defaults.rate)?,
+            max_prompt_chars: layers.usize(
+                "SEMANTIC_SAMPLING_MAX_PROMPT_LENGTH",
+                sampling_defaults.max_prompt_chars,
+            )?,
+        };
+        let semantic_sampling_secret = layers.optional_string("SEMANTIC_SAMPLING_SECRET")?;
+
+        let hygiene_defaults = BankHygienePolicy::default();
+        let semantic_bank_hygiene = BankHygienePolicy {
+            near_duplicate_threshold: layers.f32(
+                "SEMANTIC_BANK_NEAR_DUPLICATE_THRESHOLD",
+                hygiene_defaults.near_duplicate_threshold,
+            )?,
+            max_pairwise_templates: layers.usize(
+                "SEMANTIC_BANK_MAX_PAIRWISE_TEMPLATES",
+                hygiene_defaults.max_pairwise_templates,
+            )?,
+            strict: layers.bool("SEMANTIC_BANK_STRICT", hygiene_defaults.strict)?,
+        };
+
+        let chunking_defaults = SemanticChunkingPolicy::default();
+        let semantic_chunking = SemanticChunkingPolicy {
+            min_prompt_chars: layers.usize(
+                "SEMANTIC_CHUNKING_MIN_PROMPT_LENGTH",
+                chunking_defaults.min_prompt_chars,
+            )?,
+            window_chars: layers.usize(
+                "SEMANTIC_CHUNKING_WINDOW_LENGTH",
+                chunking_defaults.window_chars,
+            )?,
+            overlap_chars: layers
+                .usize("SEMANTIC_CHUNKING_OVERLAP", chunking_defaults.overlap_chars)?,
+            max_concurrency: layers.usize(
+                "SEMANTIC_CHUNKING_CONCURRENCY",
+                chunking_defaults.max_concurrency,
+            )?,
+        };
+        let semantic_embedding_cache_size = layers.usize(
+            "SEMANTIC_EMBEDDING_CACHE_SIZE",
+            DEFAULT_EMBEDDING_CACHE_SIZE,
+        )?;
+        let budget_defaults = CacheBudgetConfig::default();
+        let cache_budget = CacheBudgetConfig {
+            max_bytes: layers.usize(
+                "CACHE_MEMORY_BUDGET_BYTES",
+                budget_defaults.max_bytes as usize,
+            )? as u64,
+            check_interval_secs: layers.usize(
+                "CACHE_BUDGET_CHECK_INTERVAL_SECS",
+                budget_defaults.check_interval_secs as usize,
+            )? as u64,
+        };
+
+        let output_chunking_defaults = OutputModerationChunking::default();
+        let output_moderation_chunking = OutputModerationChunking {
+            max_chars: layers.usize(
+                "OUTPUT_MODERATION_CHUNK_LENGTH",
+                output_chunking_defaults.max_chars,
+            )?,
+            overlap_chars: layers.usize(
+                "OUTPUT_MODERATION_CHUNK_OVERLAP",
+                output_chunking_defaults.overlap_chars,
+            )?,
+            max_concurrency: layers.usize(
+                "OUTPUT_MODERATION_CHUNK_CONCURRENCY",
+                output_chunking_defaults.max_concurrency,
+            )?,
+        };
+        let output_analysis = OutputAnalysisConfig {
+            bias: layers.bool("OUTPUT_ANALYSIS_BIAS", false)?,
+            firewall_echo: layers.bool("OUTPUT_ANALYSIS_FIREWALL_ECHO", false)?,
+            semantic: layers.bool("OUTPUT_ANALYSIS_SEMANTIC", false)?,
+        };
+        let judge_defaults = LlmJudgeConfig::default();
+        let llm_judge = LlmJudgeConfig {
+            enabled: layers.bool("LLM_JUDGE_ENABLED", judge_defaults.enabled)?,
+            semantic_window: layers
+                .f32("LLM_JUDGE_SEMANTIC_WINDOW", judge_defaults.semantic_window)?,
+            firewall_near_miss: layers.bool(
+                "LLM_JUDGE_FIREWALL_NEAR_MISS",
+                judge_defaults.firewall_near_miss,
+            )?,
+            max_tokens: layers.usize("LLM_JUDGE_MAX_TOKENS", judge_defaults.max_tokens as usize)?
+                as u32,
+            timeout_ms: layers.usize("LLM_JUDGE_TIMEOUT_MS", judge_defaults.timeout_ms as usize)?
+                as u64,
+            template: match layers.optional_string("LLM_JUDGE_TEMPLATE_PATH")? {
+                Some(path) => {
+                    JudgePromptTemplate::load(Path::new(&path)).map_err(SettingsError::Invalid)?
+                }
+                None => judge_defaults.template,
+            },
+        };
+
+        let explanation_defaults = ExplanationPolicy::default();
+        let explanation = ExplanationPolicy {
+            support_redaction: layers.parsed(
+                "EXPLAIN_SUPPORT_REDACTION",
+                explanation_defaults.support_redaction,
+            )?,
+            admin_redaction: layers.parsed(
+                "EXPLAIN_ADMIN_REDACTION",
+                explanation_defaults.admin_redaction,
+            )?,
+            feedback_url: layers.optional_string("EXPLAIN_FEEDBACK_URL")?,
+        };
+
+        let appeal_defaults = AppealPolicy::default();
+        let appeals = AppealPolicy {
+            exemption_ttl_secs: layers.usize(
+                "APPEAL_EXEMPTION_TTL_SECS",
+                appeal_defaults.exemption_ttl_secs as usize,
+            )? as u64,
+            exemption_max_uses: layers.usize(
+                "APPEAL_EXEMPTION_MAX_USES",
+                appeal_defaults.exemption_max_uses as usize,
+            )? as u32,
+        };
+        let startup_defaults = StartupConfig::default();
+        let startup = StartupConfig {
+            mistral: layers.parsed("STARTUP_POLICY_MISTRAL", startup_defaults.mistral)?,
+            semantic: layers.parsed("STARTUP_POLICY_SEMANTIC", startup_defaults.semantic)?,
+            retry_initial_backoff_ms: layers.usize(
+                "STARTUP_RETRY_INITIAL_BACKOFF_MS",
+                startup_defaults.retry_initial_backoff_ms as usize,
+            )? as u64,
+            retry_max_backoff_ms: layers.usize(
+                "STARTUP_RETRY_MAX_BACKOFF_MS",
+                startup_defaults.retry_max_backoff_ms as usize,
+            )? as u64,
+        };
+
+        let exemption_sweep_interval_secs =
+            layers.usize("EXEMPTION_SWEEP_INTERVAL_SECS", 60)? as u64;
+        let audit_prompt_storage =
+            layers.parsed("AUDIT_PROMPT_STORAGE", PromptStorageMode::default())?;
+        // Strangers' prompts are never stored verbatim
+        let audit_prompt_storage = if demo.enabled {
+            PromptStorageMode::Redacted
+        } else {
+            audit_prompt_storage
+        };
+        let audit_suppression = AuditSuppressionPolicy {
+            window_secs: layers.usize("AUDIT_SUPPRESSION_WINDOW_SECS", 0)? as u64,
+            prompt_hashes: layers.list("AUDIT_SUPPRESSION_PROMPT_HASHES", &[])?,
+            correlation_id_prefixes: layers.list("AUDIT_SUPPRESSION_CORRELATION_PREFIXES", &[])?,
+        };
+        let compliance_report_retention_days =
+            layers.usize("COMPLIANCE_REPORT_RETENTION_DAYS", 0)? as u64;
+        let firewall_rules_history_limit =
+            layers.usize("FIREWALL_RULES_HISTORY_LIMIT", DEFAULT_RULES_ARCHIVE_LIMIT)?;
+        let firewall_miss_buffer_size =
+            layers.usize("FIREWALL_MISS_BUFFER_SIZE", DEFAULT_FIREWALL_MISS_CAPACITY)?;
+
+        let failure_defaults = StageFailurePolicy::default();
+        let stage_failure_policy = StageFailurePolicy {
+            language: layers.parsed("STAGE_FAILURE_POLICY_LANGUAGE", failure_defaults.language)?,
+            bias: layers.parsed("STAGE_FAILURE_POLICY_BIAS", failure_defaults.bias)?,
+            semantic: layers.parsed("STAGE_FAILURE_POLICY_SEMANTIC", failure_defaults.semantic)?,
+            moderation: layers.parsed(
+                "STAGE_FAILURE_POLICY_MODERATION",
+                failure_defaults.moderation,
+            )?,
+            translation: layers.parsed(
+                "STAGE_FAILURE_POLICY_TRANSLATION",
+                failure_defaults.translation,
+            )?,
+        };
+
+        let slo_defaults = SloObjectives::default();
+        let slo = SloObjectives {
+            latency_threshold_ms: layers.usize(
+                "SLO_LATENCY_THRESHOLD_MS",
+                slo_defaults.latency_threshold_ms as usize,
+            )? as u64,
+            latency_target: layers.f64("SLO_LATENCY_TARGET", slo_defaults.latency_target)?,
+            availability_target: layers
+                .f64("SLO_AVAILABILITY_TARGET", slo_defaults.availability_target)?,
+        };
+
+        let slow_request_defaults = SlowRequestPolicy::default();
+        let slow_requests = SlowRequestPolicy {
+            threshold_ms: layers.usize(
+                "SLOW_REQUEST_THRESHOLD_MS",
+                slow_request_defaults.threshold_ms as usize,
+            )? as u64,
+            route_thresholds: SlowRequestPolicy::parse_route_thresholds(
+                &layers.list("SLOW_REQUEST_ROUTE_THRESHOLDS", &[])?,
+            )
+            .map_err(|e| SettingsError::Invalid(format!("SLOW_REQUEST_ROUTE_THRESHOLDS: {e}")))?,
+            capacity: layers.usize("SLOW_REQUEST_BUFFER_SIZE", slow_request_defaults.capacity)?,
+        };
+
+        let correlation_ids = CorrelationIdPolicy {
+            max_length: layers.usize(
+                "CORRELATION_ID_MAX_LENGTH",
+                DEFAULT_MAX_CORRELATION_ID_LENGTH,
+            )?,
+            allowed_prefixes: layers.list("CORRELATION_ID_PREFIXES", &[])?,
+        };
+        let instance_id = layers.optional_string("INSTANCE_ID")?;
+        let log_sampling_window_secs = layers.usize(
+            "LOG_SAMPLING_WINDOW_SECS",
+            DEFAULT_LOG_SAMPLING_WINDOW_SECS as usize,
+        )? as u64;
+        let risk_defaults = RiskWeights::default();
+        let risk_weights = RiskWeights {
+            firewall: layers.f32("RISK_WEIGHT_FIREWALL", risk_defaults.firewall)?,
+            semantic: layers.f32("RISK_WEIGHT_SEMANTIC", risk_defaults.semantic)?,
+            bias: layers.f32("RISK_WEIGHT_BIAS", risk_defaults.bias)?,
+            moderation: layers.f32("RISK_WEIGHT_MODERATION", risk_defaults.moderation)?,
+            degraded_stage: layers
+                .f32("RISK_WEIGHT_DEGRADED_STAGE", risk_defaults.degraded_stage)?,
+            blocked_floor: u8::try_from(
+                layers.u16("RISK_BLOCKED_FLOOR", u16::from(risk_defaults.blocked_floor))?,
+            )
+            .unwrap_or(u8::MAX),
+        };
+        let decision_policy = match layers.optional_string("DECISION_POLICY_PATH")? {
+            Some(path) => DecisionPolicy::load(Path::new(&path)).map_err(SettingsError::Invalid)?,
+            None => DecisionPolicy::default(),
+        };
+        let refusal = RefusalPolicy {
+            enabled: layers.bool("REFUSAL_MESSAGES", false)?,
+            templates: match layers.optional_string("REFUSAL_TEMPLATES_PATH")? {
+                Some(path) => {
+                    RefusalTemplates::load(Path::new(&path)).map_err(SettingsError::Invalid)?
+                }
+                None => RefusalTemplates::default(),
+            },
+        };
+        let chaos_mode = layers.bool("CHAOS_MODE", false)?;
+        let accept_replay_traffic = layers.bool("ACCEPT_REPLAY_TRAFFIC", false)?;
+        let cache_defaults = CachePolicy::default();
+        let http_cache = CachePolicy {
+            compliance_config_max_age_secs: layers.usize(
+                "CACHE_MAX_AGE_COMPLIANCE_CONFIG_SECS",
+                cache_defaults.compliance_config_max_age_secs as usize,
+            )? as u64,
+            summary_max_age_secs: layers.usize(
+                "CACHE_MAX_AGE_SUMMARY_SECS",
+                cache_defaults.summary_max_age_secs as usize,
+            )? as u64,
+        };
+        let api_keys = match layers.optional_string("API_KEYS_PATH")? {
+            Some(path) => {
+                ApiKeyRegistryConfig::load(Path::new(&path)).map_err(SettingsError::Invalid)?
+            }
+            None => ApiKeyRegistryConfig::default(),
+        };
+        let usage_defaults = UsageConfig::default();
+        let usage = UsageConfig {
+            cost_per_1k_tokens: layers.f64(
+                "USAGE_COST_PER_1K_TOKENS",
+                usage_defaults.cost_per_1k_tokens,
+            )?,
+            flush_interval_secs: layers.usize(
+                "USAGE_FLUSH_INTERVAL_SECS",
+                usage_defaults.flush_interval_secs as usize,
+            )? as u64,
+            require_key: layers.bool("USAGE_REQUIRE_KEY", usage_defaults.require_key)?,
+        };
+        if usage.cost_per_1k_tokens < 0.0 {
+            return Err(SettingsError::Invalid(
+                "USAGE_COST_PER_1K_TOKENS must not be negative".to_owned(),
+            ));
+        }
+
+        validate_max_input_length(max_input_length).map_err(SettingsError::Invalid)?;
+        repeat_offender.validate().map_err(SettingsError::Invalid)?;
+        sanitize_probing
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        demo.validate().map_err(SettingsError::Invalid)?;
+        if demo.enabled && (admin_token.is_some() || support_token.is_some() || chaos_mode) {
+            return Err(SettingsError::Invalid(
+                "DEMO_MODE cannot be combined with ADMIN_API_TOKEN, SUPPORT_API_TOKEN or \
+                 CHAOS_MODE"
+                    .to_owned(),
+            ));
+        }
+        cors.validate().map_err(SettingsError::Invalid)?;
+        startup.validate().map_err(SettingsError::Invalid)?;
+        document_scan_limits
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        mistral_concurrency
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        validate_threshold(bias_threshold).map_err(SettingsError::Invalid)?;
+        bias_rewrite.validate().map_err(SettingsError::Invalid)?;
+        SemanticThresholds {
+            medium_threshold: semantic_medium_threshold,
+            high_threshold: semantic_high_threshold,
+            decision_margin: semantic_decision_margin,
+        }
+        .validate()
+        .map_err(SettingsError::Invalid)?;
+        semantic_sampling
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        semantic_bank_hygiene
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        semantic_chunking
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        output_moderation_chunking
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        llm_judge.validate().map_err(SettingsError::Invalid)?;
+        explanation.validate().map_err(SettingsError::Invalid)?;
+        appeals.validate().map_err(SettingsError::Invalid)?;
+        audit_suppression
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        audit_integrity.validate().map_err(SettingsError::Invalid)?;
+        audit_disk.validate().map_err(SettingsError::Invalid)?;
+        audit_compression
+            .validate()
+            .map_err(SettingsError::Invalid)?;
+        if let Some(admission) = &admission {
+            admission.validate().map_err(SettingsError::Invalid)?;
+        }
+        if let Some(url) = &alert_webhook_url {
+            validate_webhook_url(url).map_err(SettingsError::Invalid)?;
+        }
+        slo.validate().map_err(SettingsError::Invalid)?;
+        slow_requests.validate().map_err(SettingsError::Invalid)?;
+        correlation_ids.validate().map_err(SettingsError::Invalid)?;
+        if let Some(instance_id) = &instance_id {
+            validate_instance_id(instance_id).map_err(SettingsError::Invalid)?;
+        }
+        risk_weights.validate().map_err(SettingsError::Invalid)?;
+        if exemption_sweep_interval_secs == 0 {
+            return Err(SettingsError::Invalid(
+                "exemption sweep interval must be greater than zero".to_owned(),
+            ));
+        }
+        if firewall_rules_history_limit == 0 {
+            return Err(SettingsError::Invalid(
+                "firewall rules history limit must be greater than zero".to_owned(),
+            ));
+        }
+        if firewall_miss_buffer_size == 0 {
+            return Err(SettingsError::Invalid(
+                "firewall miss buffer must hold at least one entry".to_owned(),
+            ));
+        }
 
         Ok(Self {
             server_port,
-            mistral_api_key: env::var("MISTRAL_API_KEY").ok().filter(|v| !v.is_empty()),
-            mistral_base_url: env::var("MISTRAL_BASE_URL")
-                .unwrap_or_else(|_| DEFAULT_MISTRAL_BASE_URL.to_owned()),
-            generation_model: env::var("MISTRAL_GENERATION_MODEL")
-                .unwrap_or_else(|_| DEFAULT_MISTRAL_GENERATION_MODEL.to_owned()),
-            moderation_model: Some(
-                env::var("MISTRAL_MODERATION_MODEL")
-                    .unwrap_or_else(|_| DEFAULT_MISTRAL_MODERATION_MODEL.to_owned()),
-            ),
-            embedding_model: env::var("MISTRAL_EMBEDDING_MODEL")
-                .unwrap_or_else(|_| DEFAULT_MISTRAL_EMBEDDING_MODEL.to_owned()),
+            mistral_api_key,
+            mistral_base_url,
+            mistral_paths,
+            generation_model,
+            generation_preamble,
+            deterministic_generation,
+            deterministic_seed,
+            moderation_model,
+            embedding_model,
             bias_threshold,
+            bias_rewrite,
             max_input_length,
+            length_overflow_policy,
             semantic_medium_threshold,
             semantic_high_threshold,
             semantic_decision_margin,
+            moderate_removed_content,
+            moderate_translated_output,
+            unmoderated_requests,
+            moderation_severity,
+            config_history_limit,
+            audit_backend,
+            audit_sqlite_path,
+            audit_encryption_key,
+            audit_compression,
+            audit_write_behind,
+            audit_integrity,
+            audit_disk,
+            alert_webhook_url,
+            model_watch,
+            repeat_offender,
+            sanitize_probing,
+            demo,
+            admin_token,
+            support_token,
+            cors,
+            document_scan_limits,
+            mistral_concurrency,
+            admission,
+            prompt_preprocessors,
+            url_policy,
+            url_unfurl,
+            strict_firewall_rules,
+            semantic_sampling,
+            semantic_sampling_secret,
+            semantic_bank_hygiene,
+            semantic_chunking,
+            semantic_embedding_cache_size,
+            cache_budget,
+            output_moderation_chunking,
+            output_analysis,
+            llm_judge,
+            explanation,
+            appeals,
+            startup,
+            exemption_sweep_interval_secs,
+            audit_prompt_storage,
+            audit_suppression,
+            compliance_report_retention_days,
+            firewall_rules_history_limit,
+            firewall_miss_buffer_size,
+            stage_failure_policy,
+            slo,
+            slow_requests,
+            sled_db_path,
+            metrics_addr,
+            firewall_rules_path,
+            bias_rules_dir,
+            semantic_attack_bank_path,
+            eu_risk_keywords_path,
+            correlation_ids,
+            instance_id,
+            log_sampling_window_secs,
+            risk_weights,
+            decision_policy,
+            refusal,
+            chaos_mode,
+            accept_replay_traffic,
+            http_cache,
+            api_keys,
+            usage,
         })
     }
 }
 
-fn parse_env_f32(key: &str, default: f32) -> Result<f32, SettingsError> {
-    match env::var(key) {
-        Ok(value) => value
-            .parse::<f32>()
-            .map_err(|source| SettingsError::ParseFloat {
-                key: key.to_owned(),
-                source,
-            }),
-        Err(_) => Ok(default),
-    }
-}
-
-fn parse_env_usize(key: &str, default: usize) -> Result<usize, SettingsError> {
-    match env::var(key) {
-        Ok(value) => value
-            .parse::<usize>()
-            .map_err(|source| SettingsError::ParseInt {
-                key: key.to_owned(),
-                source,
-            }),
-        Err(_) => Ok(default),
-    }
-}
-
-fn parse_env_u16(key: &str, default: u16) -> Result<u16, SettingsError> {
-    match env::var(key) {
-        Ok(value) => value
-            .parse::<u16>()
-            .map_err(|source| SettingsError::ParseInt {
-                key: key.to_owned(),
-                source,
-            }),
-        Err(_) => Ok(default),
-    }
-}
-
 #[derive(Debug, Error)]
 pub enum SettingsError {
     #[error("failed to parse floating-point setting {key}: {source}")]
@@ -103,4 +1179,25 @@ pub enum SettingsError {
     },
     #[error("failed to parse integer setting {key}: {source}")]
     ParseInt { key: String, source: ParseIntError },
+    #[error("failed to parse boolean setting {key}: {value}")]
+    ParseBool { key: String, value: String },
+    #[error("invalid setting: {0}")]
+    Invalid(String),
+    #[error("failed to read config file {path}: {reason}")]
+    ConfigFile { path: String, reason: String },
+    #[error("config file {path}, line {line}: {message}")]
+    ConfigSyntax {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    #[error("config file {path}: unknown setting {key}")]
+    UnknownConfigKey { path: String, key: String },
+    #[error("config file {path}: {key} must be {expected}, found {found}")]
+    ConfigType {
+        path: String,
+        key: String,
+        expected: String,
+        found: String,
+    },
 }
@@ -0,0 +1,206 @@
+use std::ops::Range;
+
+const ESC: char = '\u{1B}';
+const BEL: char = '\u{07}';
+/// Single-character C1 forms of `ESC [` and `ESC ]`
+const C1_CSI: char = '\u{9B}';
+const C1_OSC: char = '\u{9D}';
+/// What lossy UTF-8 or UTF-16 decoding leaves behind for broken input such as unpaired
+/// surrogates
+const REPLACEMENT: char = '\u{FFFD}';
+
+/// Control characters and escape sequences found in a prompt
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ControlScan {
+    /// Sorted, disjoint byte ranges to remove
+    pub(crate) ranges: Vec<Range<usize>>,
+    /// ANSI escape sequences among the ranges
+    pub(crate) escape_sequences: usize,
+    /// Characters covered by the ranges, escape sequences included
+    pub(crate) characters: usize,
+}
+
+impl ControlScan {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Share of the prompt's non-whitespace characters that are control characters, in
+    /// percent
+    pub(crate) fn percent_of(&self, prompt: &str) -> usize {
+        let visible = prompt.chars().filter(|ch| !ch.is_whitespace()).count();
+        if visible == 0 {
+            return 0;
+        }
+        self.characters * 100 / visible
+    }
+
+    /// `text` with the ranges removed
+    pub(crate) fn strip(&self, text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for range in &self.ranges {
+            stripped.push_str(&text[cursor..range.start]);
+            cursor = range.end;
+        }
+        stripped.push_str(&text[cursor..]);
+        stripped
+    }
+
+    /// Reason text naming what was found, without echoing any of it
+    pub(crate) fn describe(&self) -> String {
+        let plain = self.ranges.len() - self.escape_sequences;
+        match (plain, self.escape_sequences) {
+            (0, sequences) => format!("{sequences} ANSI escape sequence(s)"),
+            (plain, 0) => format!("{plain} control character(s)"),
+            (plain, sequences) => {
+                format!("{plain} control character(s) and {sequences} ANSI escape sequence(s)")
+            }
+        }
+    }
+}
+
+/// Whether a terminal could act on `ch` instead of printing it
+///
+/// Tab, line feed and carriage return are ordinary text. The Unicode replacement
+/// character is included since it only appears where decoding failed.
+pub(crate) fn is_control_character(ch: char) -> bool {
+    (ch.is_control() && !matches!(ch, '\t' | '\n' | '\r')) || ch == REPLACEMENT
+}
+
+/// Find ANSI escape sequences and stray control characters in `prompt`
+///
+/// A sequence runs from its introducer to its final byte, so the parameters of
+/// `ESC[31m` go with it rather than being left behind as text.
+pub(crate) fn scan_control_characters(prompt: &str) -> ControlScan {
+    let mut scan = ControlScan::default();
+    let mut chars = prompt.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        if !is_control_character(ch) {
+            continue;
+        }
+        let mut end = start + ch.len_utf8();
+        let mut is_sequence = false;
+        let introducer = match ch {
+            ESC => chars.peek().map(|&(_, next)| next),
+            C1_CSI => Some('['),
+            C1_OSC => Some(']'),
+            _ => None,
+        };
+        match introducer {
+            Some('[') => {
+                if ch == ESC {
+                    chars.next();
+                    end += 1;
+                }
+                // Parameter and intermediate bytes, then one final byte
+                while let Some(&(offset, next)) = chars.peek() {
+                    if !(' '..='~').contains(&next) {
+                        break;
+                    }
+                    chars.next();
+                    end = offset + 1;
+                    if ('@'..='~').contains(&next) {
+                        break;
+                    }
+                }
+                is_sequence = true;
+            }
+            // Operating system commands and string controls end with BEL or `ESC \`
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                if ch == ESC {
+                    chars.next();
+                    end += 1;
+                }
+                while let Some((offset, next)) = chars.next() {
+                    end = offset + next.len_utf8();
+                    if next == BEL {
+                        break;
+                    }
+                    if next == ESC {
+                        if let Some(&(offset, '\\')) = chars.peek() {
+                            chars.next();
+                            end = offset + 1;
+                        }
+                        break;
+                    }
+                }
+                is_sequence = true;
+            }
+            // Two-character escapes such as `ESC c` (reset) or `ESC 7`
+            Some(next) if ch == ESC && (' '..='~').contains(&next) => {
+                chars.next();
+                end += 1;
+                is_sequence = true;
+            }
+            _ => {}
+        }
+        scan.characters += prompt[start..end].chars().count();
+        scan.escape_sequences += usize::from(is_sequence);
+        scan.ranges.push(start..end);
+    }
+
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(prompt: &str) -> Vec<&str> {
+        scan_control_characters(prompt)
+            .ranges
+            .into_iter()
+            .map(|range| &prompt[range])
+            .collect()
+    }
+
+    #[test]
+    fn whole_escape_sequences_are_found() {
+        let prompt =
+            "\u{1b}[1;31mred\u{1b}[0m \u{1b}]0;title\u{07}done \u{1b}]8;;x\u{1b}\\ \u{1b}c";
+        assert_eq!(
+            removed(prompt),
+            [
+                "\u{1b}[1;31m",
+                "\u{1b}[0m",
+                "\u{1b}]0;title\u{07}",
+                "\u{1b}]8;;x\u{1b}\\",
+                "\u{1b}c"
+            ]
+        );
+        let scan = scan_control_characters(prompt);
+        assert_eq!(scan.escape_sequences, 5);
+        assert_eq!(scan.describe(), "5 ANSI escape sequence(s)");
+    }
+
+    #[test]
+    fn stray_controls_are_found_but_whitespace_is_kept() {
+        let prompt = "ring\u{07}\u{08}\tline\r\nnext\u{9b}2J\u{fffd}";
+        assert_eq!(
+            removed(prompt),
+            ["\u{07}", "\u{08}", "\u{9b}2J", "\u{fffd}"]
+        );
+        let scan = scan_control_characters(prompt);
+        assert_eq!(
+            scan.describe(),
+            "3 control character(s) and 1 ANSI escape sequence(s)"
+        );
+        assert!(scan_control_characters("plain text\twith tabs\n").is_empty());
+    }
+
+    #[test]
+    fn unterminated_sequences_stop_at_text() {
+        assert_eq!(removed("a\u{1b}[12"), ["\u{1b}[12"]);
+        assert_eq!(removed("a\u{1b}[1\u{e9}b"), ["\u{1b}[1"]);
+        assert_eq!(removed("a\u{1b}"), ["\u{1b}"]);
+    }
+
+    #[test]
+    fn percent_counts_visible_characters() {
+        let prompt = "\u{07}\u{07}\u{07} ab";
+        assert_eq!(scan_control_characters(prompt).percent_of(prompt), 60);
+        assert_eq!(ControlScan::default().percent_of("  "), 0);
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use super::taxonomy::CategoryRef;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallAction {
+    #[default]
+    #[serde(alias = "Allow")]
+    Allow,
+    /// Passed through unchanged but annotated, e.g. a block downgraded for quoting
+    #[serde(alias = "Flag")]
+    Flag,
+    #[serde(alias = "Sanitize")]
+    Sanitize,
+    #[serde(alias = "Block")]
+    Block,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallSeverity {
+    #[default]
+    #[serde(alias = "Low")]
+    Low,
+    #[serde(alias = "Medium")]
+    Medium,
+    #[serde(alias = "High")]
+    High,
+    #[serde(alias = "Critical")]
+    Critical,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PromptFirewallResult {
+    pub action: FirewallAction,
+    pub severity: FirewallSeverity,
+    pub sanitized_prompt: String,
+    pub reasons: Vec<String>,
+    pub matched_rules: Vec<String>,
+    /// Fragments stripped from the prompt by sanitize patterns, in application order
+    #[serde(default)]
+    pub sanitization_edits: Vec<SanitizationEdit>,
+    /// Why a block was downgraded to `Flag` or `Sanitize`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downgrade_reason: Option<String>,
+    /// Block rules behind a block or a quoted-mention downgrade, with where they matched
+    ///
+    /// Spans point into the text the firewall evaluated. A match that only appeared after
+    /// sanitization is still located in that text, spanning the content that was removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block_matches: Vec<MatchedBlockRule>,
+    /// Languages of a code-switched prompt, which was evaluated both as written and
+    /// translated; empty when the prompt is in one language
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mixed_languages: Vec<String>,
+    /// The firewall evaluated an English translation of the prompt, so
+    /// `sanitized_prompt` is translated text
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub translated: bool,
+    /// The prompt was over the input limit and only this much of it was evaluated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<InputTruncation>,
+    /// Block rules in `monitor` mode that matched the prompt as written; they never
+    /// change the action
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_matches: Vec<MatchedBlockRule>,
+    /// URLs found in the prompt, in order of first appearance, with what the URL policy
+    /// did with each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<ExtractedUrl>,
+}
+
+/// A block rule found in scanned text
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MatchedBlockRule {
+    pub id: String,
+    pub pattern: String,
+    /// Byte ranges `[start, end)` of each match in the scanned text, sorted
+    ///
+    /// Ranges cover the original characters, including homoglyphs, zero-width characters
+    /// and leetspeak that canonicalization rewrote. Fuzzy matches cover the whole window
+    /// of words that came close to the pattern, so ranges may overlap.
+    #[serde(default)]
+    pub match_spans: Vec<(usize, usize)>,
+    /// Threat category of the rule, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<CategoryRef>,
+}
+
+/// A URL found in a prompt
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExtractedUrl {
+    /// Normalized URL: lowercase scheme and host, internationalized hosts in punycode,
+    /// no default port, fragment or credentials
+    pub url: String,
+    pub scheme: String,
+    pub host: String,
+    pub action: UrlAction,
+    /// The URL as written carried a user name or password, which were dropped
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub had_credentials: bool,
+    /// Outcome of fetching the linked page, for allowlisted URLs when unfurling is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unfurl: Option<UrlUnfurl>,
+}
+
+/// What the URL policy does with a URL in a prompt
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlAction {
+    /// Keep the URL and only record it
+    #[default]
+    Allow,
+    /// Remove the URL from the prompt, which is then sanitized
+    Strip,
+    /// Block the prompt
+    Block,
+}
+
+/// A linked page fetched and scanned for indirect prompt injection
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UrlUnfurl {
+    pub status: UnfurlStatus,
+    /// Bytes of the page scanned, at most the configured size cap
+    #[serde(default)]
+    pub bytes_scanned: usize,
+    /// The page was longer than the size cap and only its start was scanned
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Why the fetch was refused or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Block rules matched by the page content: evidence of injection payloads that a
+    /// downstream step fetching the URL would read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<MatchedBlockRule>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnfurlStatus {
+    Fetched,
+    /// Not fetched because the URL, an address it resolved to or a redirect target was
+    /// not allowed
+    Refused,
+    /// The fetch timed out, failed to connect or returned an error status
+    Failed,
+}
+
+/// A single fragment removed from the prompt by a sanitize pattern
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SanitizationEdit {
+    pub rule_id: String,
+    /// Removed text exactly as it appeared in the prompt
+    pub removed: String,
+    /// Placeholder put where the text was, for patterns in `replace` mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// Threat category of the rule, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<CategoryRef>,
+}
+
+/// What the firewall does with a prompt longer than the input limit
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthOverflowPolicy {
+    /// Block the prompt
+    #[default]
+    Block,
+    /// Keep the first characters up to the limit
+    Truncate,
+    /// Keep the start and the end, joined by an elision marker, so text appended to the
+    /// end of a long document is still evaluated
+    TruncateHeadTail,
+}
+
+impl LengthOverflowPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Truncate => "truncate",
+            Self::TruncateHeadTail => "truncate_head_tail",
+        }
+    }
+}
+
+impl std::str::FromStr for LengthOverflowPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "block" => Ok(Self::Block),
+            "truncate" => Ok(Self::Truncate),
+            "truncate_head_tail" => Ok(Self::TruncateHeadTail),
+            other => Err(format!(
+                "unknown length overflow policy '{other}' (expected block, truncate or \
+                 truncate_head_tail)"
+            )),
+        }
+    }
+}
+
+/// How an over-long prompt was cut down before the firewall evaluated it
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InputTruncation {
+    pub policy: LengthOverflowPolicy,
+    /// Length of the prompt as sent, in characters
+    pub original_chars: usize,
+    /// Characters of the prompt kept, not counting the elision marker
+    pub retained_chars: usize,
+}
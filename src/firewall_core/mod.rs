@@ -0,0 +1,48 @@
+//! The lexical prompt firewall on its own
+//!
+//! Canonicalization (homoglyphs, leetspeak, zero-width characters), fuzzy matching,
+//! control-character handling, quoted-mention downgrades and sanitization, evaluated
+//! against an explicit [`FirewallRulesConfig`]. Nothing here reads files, calls Mistral
+//! or records metrics, so the module builds for `wasm32-unknown-unknown` with the
+//! `wasm` feature (see the `wasm` module) and gives the same verdicts as the server,
+//! which runs this very code through `modules::prompt_firewall`.
+//!
+//! [`FirewallRulesConfig`]: rules::FirewallRulesConfig
+
+mod control;
+pub mod dtos;
+mod quotes;
+pub mod rules;
+pub mod taxonomy;
+
+use dtos::PromptFirewallResult;
+use rules::{CompiledFirewallRules, FirewallRulesConfig};
+
+/// Input length limit of the server unless `MAX_INPUT_LENGTH` changes it
+pub const DEFAULT_MAX_INPUT_LENGTH: usize = 4096;
+
+/// Evaluate `prompt` against the rule set in `rules_json`, with the default input limit
+///
+/// `rules_json` has the shape of `config/firewall_rules.json` or of the `firewall_rules`
+/// in `GET /api/config`. Rule sets the server would refuse, including ones whose
+/// assertions fail, are refused here too.
+pub fn inspect(prompt: &str, rules_json: &str) -> Result<PromptFirewallResult, String> {
+    inspect_with_limit(prompt, rules_json, DEFAULT_MAX_INPUT_LENGTH)
+}
+
+/// [`inspect`] for a server whose `MAX_INPUT_LENGTH` is `max_input_length`
+pub fn inspect_with_limit(
+    prompt: &str,
+    rules_json: &str,
+    max_input_length: usize,
+) -> Result<PromptFirewallResult, String> {
+    let rules = compile_rules_json(rules_json)?;
+    Ok(rules::evaluate_with_rules(prompt, max_input_length, &rules))
+}
+
+/// Parse, validate and compile a rule set, refusing it unless every assertion holds
+pub fn compile_rules_json(rules_json: &str) -> Result<CompiledFirewallRules, String> {
+    let config: FirewallRulesConfig =
+        serde_json::from_str(rules_json).map_err(|e| format!("invalid rules: {e}"))?;
+    CompiledFirewallRules::compile(config).map_err(|e| e.to_string())
+}
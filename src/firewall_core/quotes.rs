@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+const CODE_FENCE: &str = "```";
+
+/// A quoted or code-fenced stretch of a prompt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct QuotedSegment {
+    /// The whole segment including its delimiters
+    pub(crate) outer: Range<usize>,
+    /// Text between the delimiters
+    pub(crate) inner: Range<usize>,
+}
+
+/// Top-level quoted segments of `prompt`, in order
+///
+/// Code fences, inline backticks and paired quotation marks are recognised; quotes
+/// nested inside a segment belong to it. A straight single quote only opens after a
+/// non-alphanumeric character and only closes before one, so apostrophes are ignored.
+/// An opening mark without a partner quotes nothing.
+pub(crate) fn quoted_segments(prompt: &str) -> Vec<QuotedSegment> {
+    let mut segments = Vec::new();
+    let mut cursor = 0usize;
+
+    while cursor < prompt.len() {
+        let rest = &prompt[cursor..];
+        let Some(opener) = rest.chars().next() else {
+            break;
+        };
+
+        let (delimiter_len, closing) = if rest.starts_with(CODE_FENCE) {
+            (CODE_FENCE.len(), Some(Closing::Fence))
+        } else {
+            (opener.len_utf8(), closing_for(prompt, cursor, opener))
+        };
+        let inner_start = cursor + delimiter_len;
+
+        match closing.and_then(|closing| closing.find(prompt, inner_start)) {
+            Some((inner_end, outer_end)) => {
+                if inner_end > inner_start {
+                    segments.push(QuotedSegment {
+                        outer: cursor..outer_end,
+                        inner: inner_start..inner_end,
+                    });
+                }
+                cursor = outer_end;
+            }
+            None => cursor = inner_start,
+        }
+    }
+
+    segments
+}
+
+#[derive(Clone, Copy)]
+enum Closing {
+    Fence,
+    Char(char),
+    /// Straight single quote followed by a non-alphanumeric character or the end
+    Apostrophe,
+}
+
+impl Closing {
+    /// Start and end of the closing delimiter at or after `from`
+    fn find(self, prompt: &str, from: usize) -> Option<(usize, usize)> {
+        match self {
+            Self::Fence => prompt[from..]
+                .find(CODE_FENCE)
+                .map(|offset| (from + offset, from + offset + CODE_FENCE.len())),
+            Self::Char(mark) => prompt[from..]
+                .find(mark)
+                .map(|offset| (from + offset, from + offset + mark.len_utf8())),
+            Self::Apostrophe => prompt[from..]
+                .match_indices('\'')
+                .map(|(offset, _)| from + offset)
+                .find(|&index| {
+                    !prompt[index + 1..]
+                        .chars()
+                        .next()
+                        .is_some_and(char::is_alphanumeric)
+                })
+                .map(|index| (index, index + 1)),
+        }
+    }
+}
+
+fn closing_for(prompt: &str, index: usize, opener: char) -> Option<Closing> {
+    match opener {
+        '"' => Some(Closing::Char('"')),
+        '`' => Some(Closing::Char('`')),
+        '“' => Some(Closing::Char('”')),
+        '‘' => Some(Closing::Char('’')),
+        '«' => Some(Closing::Char('»')),
+        '\'' => {
+            let after_word = prompt[..index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+            (!after_word).then_some(Closing::Apostrophe)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inner_texts(prompt: &str) -> Vec<&str> {
+        quoted_segments(prompt)
+            .into_iter()
+            .map(|segment| &prompt[segment.inner])
+            .collect()
+    }
+
+    #[test]
+    fn finds_quotes_fences_and_inline_code() {
+        let prompt = "Say \"one\", then “two”, `three` and\n```\nfour\n```";
+        assert_eq!(inner_texts(prompt), vec!["one", "two", "three", "\nfour\n"]);
+    }
+
+    #[test]
+    fn apostrophes_do_not_open_or_close_quotes() {
+        let prompt = "It's the 'don't stop' trick, isn't it";
+        assert_eq!(inner_texts(prompt), vec!["don't stop"]);
+    }
+
+    #[test]
+    fn unmatched_and_empty_quotes_are_skipped() {
+        assert!(inner_texts("an \"unterminated quote").is_empty());
+        assert_eq!(inner_texts("a \"\" pair then \"b\""), vec!["b"]);
+    }
+
+    #[test]
+    fn nested_quotes_belong_to_outer_segment() {
+        let prompt = "He wrote \"the 'classic' one\" here";
+        assert_eq!(inner_texts(prompt), vec!["the 'classic' one"]);
+    }
+}
@@ -0,0 +1,289 @@
+//! Threat categories shared by every detection layer
+//!
+//! Firewall rules, semantic templates, moderation categories and bias findings each
+//! name what they caught in their own words. [`ThreatCategory`] is the vocabulary they
+//! are resolved to, so decisions and statistics can be grouped the same way whichever
+//! layer matched. Names outside it resolve through an alias table: the built-in
+//! [`DEFAULT_CATEGORY_ALIASES`], which covers the names used before the taxonomy
+//! existed and Mistral's moderation categories, and any aliases a rules or bank file
+//! adds. Categories the taxonomy has no entry for can still be used with a namespace,
+//! as in `acme:wire_fraud`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// What an attack or a flagged prompt was trying to do
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatCategory {
+    /// Telling the model to drop the instructions it was given
+    InstructionOverride,
+    /// Getting the model to reveal its system prompt or hidden instructions
+    SystemPromptExfiltration,
+    /// Persona and role-play framings that claim the model has no rules
+    RolePlayJailbreak,
+    /// Asking the model to ignore its safety or content policy
+    PolicyBypass,
+    /// Hiding instructions in encodings, control characters or another language
+    EncodingObfuscation,
+    /// Impersonating privileged users or asking for credentials and access
+    SocialEngineering,
+    /// Markup or code meant to be executed downstream
+    CodeInjection,
+    /// Sexual, hateful, violent or criminal content
+    HarmfulContent,
+    SelfHarm,
+    /// Medical, financial or legal advice
+    RegulatedAdvice,
+    PersonalData,
+    BiasedLanguage,
+}
+
+impl ThreatCategory {
+    pub const ALL: [Self; 12] = [
+        Self::InstructionOverride,
+        Self::SystemPromptExfiltration,
+        Self::RolePlayJailbreak,
+        Self::PolicyBypass,
+        Self::EncodingObfuscation,
+        Self::SocialEngineering,
+        Self::CodeInjection,
+        Self::HarmfulContent,
+        Self::SelfHarm,
+        Self::RegulatedAdvice,
+        Self::PersonalData,
+        Self::BiasedLanguage,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InstructionOverride => "instruction_override",
+            Self::SystemPromptExfiltration => "system_prompt_exfiltration",
+            Self::RolePlayJailbreak => "role_play_jailbreak",
+            Self::PolicyBypass => "policy_bypass",
+            Self::EncodingObfuscation => "encoding_obfuscation",
+            Self::SocialEngineering => "social_engineering",
+            Self::CodeInjection => "code_injection",
+            Self::HarmfulContent => "harmful_content",
+            Self::SelfHarm => "self_harm",
+            Self::RegulatedAdvice => "regulated_advice",
+            Self::PersonalData => "personal_data",
+            Self::BiasedLanguage => "biased_language",
+        }
+    }
+}
+
+impl FromStr for ThreatCategory {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_str() == value)
+            .ok_or_else(|| format!("unknown threat category {value}"))
+    }
+}
+
+impl fmt::Display for ThreatCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Names resolved to a taxonomy entry without any configuration
+pub const DEFAULT_CATEGORY_ALIASES: &[(&str, ThreatCategory)] = &[
+    ("prompt_injection", ThreatCategory::InstructionOverride),
+    (
+        "system_prompt_extraction",
+        ThreatCategory::SystemPromptExfiltration,
+    ),
+    ("prompt_leak", ThreatCategory::SystemPromptExfiltration),
+    ("roleplay_jailbreak", ThreatCategory::RolePlayJailbreak),
+    ("jailbreak", ThreatCategory::RolePlayJailbreak),
+    ("obfuscation", ThreatCategory::EncodingObfuscation),
+    ("translation_smuggling", ThreatCategory::EncodingObfuscation),
+    // Mistral moderation categories
+    ("sexual", ThreatCategory::HarmfulContent),
+    ("hate_and_discrimination", ThreatCategory::HarmfulContent),
+    ("violence_and_threats", ThreatCategory::HarmfulContent),
+    (
+        "dangerous_and_criminal_content",
+        ThreatCategory::HarmfulContent,
+    ),
+    ("selfharm", ThreatCategory::SelfHarm),
+    ("health", ThreatCategory::RegulatedAdvice),
+    ("financial", ThreatCategory::RegulatedAdvice),
+    ("law", ThreatCategory::RegulatedAdvice),
+    ("pii", ThreatCategory::PersonalData),
+];
+
+/// A resolved category: a taxonomy entry, or a custom `namespace:name`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CategoryRef {
+    Known(ThreatCategory),
+    Custom(String),
+}
+
+impl CategoryRef {
+    /// The taxonomy entry, when this is not a custom category
+    pub fn known(&self) -> Option<ThreatCategory> {
+        match self {
+            Self::Known(category) => Some(*category),
+            Self::Custom(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Known(category) => category.as_str(),
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// `namespace:name` with both parts non-empty and without whitespace
+    fn custom(value: &str) -> Option<Self> {
+        let (namespace, name) = value.split_once(':')?;
+        let valid = |part: &str| !part.is_empty() && !part.contains(char::is_whitespace);
+        (valid(namespace) && valid(name)).then(|| Self::Custom(value.to_owned()))
+    }
+}
+
+impl fmt::Display for CategoryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for CategoryRef {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.parse() {
+            Ok(category) => Ok(Self::Known(category)),
+            Err(_) => Self::custom(&value).ok_or_else(|| {
+                format!("{value} is neither a threat category nor a namespace:name category")
+            }),
+        }
+    }
+}
+
+impl From<CategoryRef> for String {
+    fn from(category: CategoryRef) -> Self {
+        category.as_str().to_owned()
+    }
+}
+
+/// Resolve `name` to the taxonomy
+///
+/// Taxonomy names resolve to themselves, then `aliases` (a file's own alias table)
+/// are consulted before the built-in ones, and names with a namespace become custom
+/// categories. Anything else is an error naming the value.
+pub fn resolve_category(
+    name: &str,
+    aliases: &BTreeMap<String, ThreatCategory>,
+) -> Result<CategoryRef, String> {
+    let name = name.trim();
+    if let Ok(category) = name.parse() {
+        return Ok(CategoryRef::Known(category));
+    }
+    let alias = aliases.get(name).copied().or_else(|| {
+        DEFAULT_CATEGORY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, category)| *category)
+    });
+    match alias {
+        Some(category) => Ok(CategoryRef::Known(category)),
+        None => CategoryRef::custom(name).ok_or_else(|| {
+            format!(
+                "category {name:?} is not in the threat taxonomy; use a taxonomy name, add it \
+                 to category_aliases, or give it a namespace such as custom:{name}"
+            )
+        }),
+    }
+}
+
+/// A moderation category in the taxonomy; unknown ones become `moderation:<name>`
+pub fn moderation_category(name: &str) -> CategoryRef {
+    resolve_category(name, &BTreeMap::new())
+        .unwrap_or_else(|_| CategoryRef::Custom(format!("moderation:{name}")))
+}
+
+/// The layer of the pipeline whose finding a category came from
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatLayer {
+    Firewall,
+    Semantic,
+    InputModeration,
+    OutputModeration,
+    Bias,
+}
+
+impl ThreatLayer {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Firewall => "firewall",
+            Self::Semantic => "semantic",
+            Self::InputModeration => "input_moderation",
+            Self::OutputModeration => "output_moderation",
+            Self::Bias => "bias",
+        }
+    }
+}
+
+/// The category of a request, the layer it came from and the finding behind it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResolvedThreat {
+    pub category: CategoryRef,
+    pub layer: ThreatLayer,
+    /// Rule id, template id, moderation category or bias category that resolved to it
+    pub source: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_resolve_through_the_taxonomy_aliases_and_namespaces() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("dan".to_owned(), ThreatCategory::RolePlayJailbreak);
+        let resolve = |name: &str| resolve_category(name, &aliases);
+
+        assert_eq!(
+            resolve("policy_bypass"),
+            Ok(CategoryRef::Known(ThreatCategory::PolicyBypass))
+        );
+        assert_eq!(
+            resolve("system_prompt_extraction"),
+            Ok(CategoryRef::Known(ThreatCategory::SystemPromptExfiltration))
+        );
+        assert_eq!(
+            resolve("dan"),
+            Ok(CategoryRef::Known(ThreatCategory::RolePlayJailbreak))
+        );
+        assert_eq!(
+            resolve("acme:wire_fraud"),
+            Ok(CategoryRef::Custom("acme:wire_fraud".to_owned()))
+        );
+        assert!(
+            resolve("wire_fraud")
+                .unwrap_err()
+                .contains("custom:wire_fraud")
+        );
+        assert!(resolve(":wire_fraud").is_err());
+        assert_eq!(
+            moderation_category("brand_new"),
+            CategoryRef::Custom("moderation:brand_new".to_owned())
+        );
+
+        let json = serde_json::to_string(&CategoryRef::Known(ThreatCategory::SelfHarm)).unwrap();
+        assert_eq!(json, "\"self_harm\"");
+        assert!(serde_json::from_str::<CategoryRef>("\"jailbreak\"").is_err());
+    }
+}
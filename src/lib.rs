@@ -1,10 +1,56 @@
+//! Prompt Sentinel: a compliance pipeline for LLM prompts
+//!
+//! [`ComplianceEngine`] runs the firewall, bias, semantic and moderation stages and
+//! writes the audit trail; `PromptSentinelServer` exposes it over HTTP.
+//!
+//! # Cargo features
+//!
+//! | Feature | Default | Enables |
+//! |---------|---------|---------|
+//! | `server` | yes | The `server` module, the `test_support` HTTP test harness, the request middleware, CORS layers and the `prompt_sentinel_server` binary (axum, tower, tower-http) |
+//! | `sled-storage` | yes | sled-backed audit storage, configuration history, rule archive and candidate queue |
+//! | `sqlite-storage` | yes | `AUDIT_BACKEND=sqlite` (rusqlite with bundled SQLite) |
+//! | `metrics-prometheus` | yes | `telemetry::prometheus` recorder and `/metrics` listener (metrics-exporter-prometheus) |
+//! | `telemetry-otlp` | yes | `init_tracing_with`, for installing an OpenTelemetry exporter layer |
+//! | `http-client` | no | The `client` module, a typed client for the HTTP API, and the `sentinel-replay` binary |
+//! | `wasm` | no | The `wasm` module, `wasm-bindgen` bindings to the lexical firewall |
+//!
+//! With `default-features = false` the crate is the library core: the workflow, every
+//! detection module, in-memory storage and the mock Mistral client. Metrics are still
+//! recorded through the `metrics` facade for whatever recorder the embedding application
+//! installs.
+//!
+//! ```toml
+//! prompt_sentinel = { version = "0.1", default-features = false }
+//! ```
+//!
+//! `scripts/check_features.sh` checks every feature combination.
+//!
+//! For `wasm32` targets only [`firewall_core`] and the `wasm` bindings are built, since
+//! everything else needs a tokio runtime and network access; see `scripts/build_wasm.sh`.
+
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+pub mod client;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod config;
+pub mod firewall_core;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod modules;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
 pub mod server;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod test_support;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod workflow;
 
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
 pub use server::{FrameworkConfig, PromptSentinelServer};
+#[cfg(not(target_arch = "wasm32"))]
 pub use workflow::{
-    ComplianceEngine, ComplianceRequest, ComplianceResponse, DecisionEvidence, WorkflowError,
-    WorkflowStatus,
+    API_VERSION, ComplianceEngine, ComplianceRequest, ComplianceResponse, DecisionEvidence,
+    DocumentScanRequest, DocumentScanResponse, PipelineStage, ReplayMode, ReplayReport,
+    ResponseProfile, ScannedDocument, StageOutcome, StageOutcomes, StageStatus, TraceStep,
+    WorkflowError, WorkflowStatus,
 };
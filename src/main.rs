@@ -1,5 +1,4 @@
 use prompt_sentinel::FrameworkConfig;
-use prompt_sentinel::modules::telemetry::metrics::TelemetryMetrics;
 use prompt_sentinel::modules::telemetry::tracing::init_tracing;
 use tracing::info;
 
@@ -13,15 +12,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Starting Prompt Sentinel Framework");
 
-    // Start metrics server on port 9090
-    info!("Starting metrics server on 0.0.0.0:9090");
-    TelemetryMetrics::start_metrics_server("0.0.0.0:9090")?;
-
-    // Use default configuration (reads from env vars)
-    let config = FrameworkConfig::default();
+    // Resolve settings from sentinel.toml and env vars; invalid configuration is fatal
+    let (settings, effective) = FrameworkConfig::default().load_settings()?;
 
     // Initialize the framework
-    let server = config.initialize().await?;
+    let server = FrameworkConfig::initialize_with((settings, effective)).await?;
 
     // Start the server
     server.start().await?;
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// Bounds on compliance workflows the server runs at once
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AdmissionLimits {
+    /// Workflows executing at once
+    pub max_concurrent: usize,
+    /// Requests waiting for a slot; further requests are shed immediately
+    pub max_queue: usize,
+    /// Longest a request waits for a slot before it is shed
+    pub max_wait_ms: u64,
+}
+
+impl Default for AdmissionLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 64,
+            max_queue: 256,
+            max_wait_ms: 5_000,
+        }
+    }
+}
+
+impl AdmissionLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent == 0 {
+            return Err("admission concurrency limit must be greater than zero".to_owned());
+        }
+        if self.max_wait_ms == 0 {
+            return Err("admission max wait must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AdmissionStatus {
+    pub limits: AdmissionLimits,
+    /// Workflows currently executing
+    pub in_flight: usize,
+    pub queue_depth: usize,
+    pub admitted_total: u64,
+    /// Requests that had to wait for a slot, whether or not they got one
+    pub queued_total: u64,
+    pub shed_total: u64,
+    /// Wait of the most recently admitted request that queued
+    pub last_wait_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShedReason {
+    QueueFull,
+    WaitExceeded,
+}
+
+impl ShedReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::QueueFull => "queue_full",
+            Self::WaitExceeded => "wait_exceeded",
+        }
+    }
+}
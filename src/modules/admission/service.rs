@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::dtos::{AdmissionLimits, AdmissionStatus, ShedReason};
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Bounds the compliance workflows the server executes at once
+///
+/// Requests over the limit wait in a bounded queue for a free slot and are shed when
+/// the queue is full or their wait runs out, so a traffic spike turns into fast `503`
+/// answers instead of every request slowing down together. It sits in front of the
+/// HTTP handlers; the engine itself never waits on it.
+#[derive(Clone)]
+pub struct AdmissionController {
+    limits: AdmissionLimits,
+    semaphore: Arc<Semaphore>,
+    state: Arc<Mutex<ControllerState>>,
+}
+
+#[derive(Default)]
+struct ControllerState {
+    in_flight: usize,
+    queue_depth: usize,
+    admitted_total: u64,
+    queued_total: u64,
+    shed_total: u64,
+    last_wait_ms: Option<u64>,
+}
+
+/// Slot held while one workflow executes
+pub struct AdmissionPermit {
+    state: Arc<Mutex<ControllerState>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        get_metrics().set_admission_in_flight(state.in_flight);
+    }
+}
+
+/// Place in the queue, given up when the wait ends or the client disconnects
+struct QueuedRequest {
+    state: Arc<Mutex<ControllerState>>,
+}
+
+impl Drop for QueuedRequest {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.queue_depth -= 1;
+        get_metrics().set_admission_queue_depth(state.queue_depth);
+    }
+}
+
+impl AdmissionController {
+    pub fn new(limits: AdmissionLimits) -> Self {
+        Self {
+            limits,
+            semaphore: Arc::new(Semaphore::new(limits.max_concurrent)),
+            state: Arc::default(),
+        }
+    }
+
+    pub fn status(&self) -> AdmissionStatus {
+        let state = self.state.lock().unwrap();
+        AdmissionStatus {
+            limits: self.limits,
+            in_flight: state.in_flight,
+            queue_depth: state.queue_depth,
+            admitted_total: state.admitted_total,
+            queued_total: state.queued_total,
+            shed_total: state.shed_total,
+            last_wait_ms: state.last_wait_ms,
+        }
+    }
+
+    /// Wait for a slot to run one workflow in
+    ///
+    /// Returns at once while the server is under its limit. Otherwise the caller queues
+    /// for the next free slot, in arrival order, and is shed when the queue is already
+    /// full or no slot frees up within the configured wait.
+    pub async fn admit(&self) -> Result<AdmissionPermit, AdmissionRejection> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(self.admitted(permit, None));
+        }
+
+        let queued = {
+            let mut state = self.state.lock().unwrap();
+            if state.queue_depth >= self.limits.max_queue {
+                return Err(self.shed(&mut state, ShedReason::QueueFull));
+            }
+            state.queue_depth += 1;
+            state.queued_total += 1;
+            get_metrics().set_admission_queue_depth(state.queue_depth);
+            QueuedRequest {
+                state: self.state.clone(),
+            }
+        };
+
+        let queued_at = Instant::now();
+        let acquired = tokio::time::timeout(
+            Duration::from_millis(self.limits.max_wait_ms),
+            self.semaphore.clone().acquire_owned(),
+        )
+        .await;
+        let waited = queued_at.elapsed();
+        drop(queued);
+        get_metrics().record_admission_wait(waited.as_secs_f64());
+
+        match acquired {
+            // The semaphore is never closed
+            Ok(permit) => {
+                Ok(self.admitted(permit.expect("admission semaphore closed"), Some(waited)))
+            }
+            Err(_) => Err(self.shed(&mut self.state.lock().unwrap(), ShedReason::WaitExceeded)),
+        }
+    }
+
+    fn admitted(&self, permit: OwnedSemaphorePermit, waited: Option<Duration>) -> AdmissionPermit {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight += 1;
+        state.admitted_total += 1;
+        if let Some(waited) = waited {
+            state.last_wait_ms = Some(waited.as_millis() as u64);
+        }
+        get_metrics().set_admission_in_flight(state.in_flight);
+        AdmissionPermit {
+            state: self.state.clone(),
+            _permit: permit,
+        }
+    }
+
+    fn shed(&self, state: &mut ControllerState, reason: ShedReason) -> AdmissionRejection {
+        state.shed_total += 1;
+        get_metrics().increment_admission_shed(reason.as_str());
+        AdmissionRejection {
+            reason,
+            // Retry-After is whole seconds; a full wait is the soonest a slot is likely
+            retry_after_secs: self.limits.max_wait_ms.div_ceil(1000).max(1),
+        }
+    }
+}
+
+/// A request shed because the server is at its concurrency limit
+#[derive(Debug, Error)]
+#[error("server is at capacity, retry in {retry_after_secs}s")]
+pub struct AdmissionRejection {
+    pub reason: ShedReason,
+    /// Seconds the client should wait before retrying, for the `Retry-After` header
+    pub retry_after_secs: u64,
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Body posted to `ALERT_WEBHOOK_URL`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Alert {
+    /// What happened, such as `audit_chain_broken`; stable for routing rules
+    pub alert: String,
+    pub level: AlertLevel,
+    /// One line for chat channels and pager titles
+    pub summary: String,
+    /// Alert-specific fields
+    pub details: Value,
+    pub raised_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertLevel {
+    Warning,
+    /// Needs someone now
+    Critical,
+}
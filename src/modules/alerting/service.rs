@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use thiserror::Error;
+
+use super::dtos::Alert;
+
+/// Longest a webhook receiver may take to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts alerts as JSON to a webhook, such as a Slack or PagerDuty integration
+#[derive(Clone, Debug)]
+pub struct WebhookNotifier {
+    url: String,
+    http: Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            http: Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Post `alert`; any status outside 2xx is an error
+    pub async fn notify(&self, alert: &Alert) -> Result<(), NotifyError> {
+        let response = self
+            .http
+            .post(&self.url)
+            .json(alert)
+            .send()
+            .await
+            .map_err(|e| NotifyError::Request(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NotifyError::Status(status.as_u16()));
+        }
+        Ok(())
+    }
+}
+
+/// Reject anything but an absolute `http` or `https` URL
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    let valid = ["http://", "https://"].iter().any(|scheme| {
+        url.strip_prefix(scheme)
+            .is_some_and(|rest| !rest.is_empty())
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err("alert webhook URL must be an http:// or https:// URL".to_owned())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("webhook request failed: {0}")]
+    Request(String),
+    #[error("webhook answered with status {0}")]
+    Status(u16),
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/appeals`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppealRequest {
+    /// Correlation id of the blocked decision being appealed
+    pub correlation_id: String,
+    /// Why the user believes the decision was wrong
+    pub justification: String,
+}
+
+impl AppealRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.correlation_id.trim().is_empty() {
+            return Err("correlation_id must not be empty".to_owned());
+        }
+        if self.justification.trim().is_empty() {
+            return Err("justification must not be empty".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Where an appeal is in its lifecycle; only open appeals can be resolved
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppealState {
+    Open,
+    Upheld,
+    Overturned,
+}
+
+impl AppealState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Upheld => "upheld",
+            Self::Overturned => "overturned",
+        }
+    }
+}
+
+/// A reviewer's finding on an appeal
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppealOutcome {
+    /// The block stands
+    Upheld,
+    /// The block was wrong
+    Overturned,
+}
+
+impl From<AppealOutcome> for AppealState {
+    fn from(outcome: AppealOutcome) -> Self {
+        match outcome {
+            AppealOutcome::Upheld => Self::Upheld,
+            AppealOutcome::Overturned => Self::Overturned,
+        }
+    }
+}
+
+/// Body of `POST /api/appeals/{id}/resolve`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResolveAppealRequest {
+    pub outcome: AppealOutcome,
+    pub note: String,
+    pub resolved_by: String,
+}
+
+impl ResolveAppealRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.note.trim().is_empty() {
+            return Err("note must not be empty".to_owned());
+        }
+        if self.resolved_by.trim().is_empty() {
+            return Err("resolved_by must not be empty".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppealResolution {
+    pub outcome: AppealOutcome,
+    pub note: String,
+    pub resolved_by: String,
+    pub resolved_at: DateTime<Utc>,
+    /// Firewall exemptions granted because the appeal was overturned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exemption_ids: Vec<String>,
+}
+
+/// An appeal against a blocked decision
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Appeal {
+    pub id: String,
+    /// Correlation id of the appealed decision
+    pub correlation_id: String,
+    /// `final_status` of the appealed decision, e.g. `blocked_by_firewall`
+    pub decision_status: String,
+    pub justification: String,
+    pub state: AppealState,
+    pub filed_at: DateTime<Utc>,
+    /// Absent while the appeal is open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<AppealResolution>,
+}
+
+/// Response of `GET /api/appeals`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppealsResponse {
+    /// Oldest first
+    pub appeals: Vec<Appeal>,
+}
+
+/// The firewall exemption an overturned appeal grants
+///
+/// It covers the rules that blocked the decision, only for prompts containing the
+/// appealed prompt, and lapses after `exemption_max_uses` uses or
+/// `exemption_ttl_secs` seconds.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppealPolicy {
+    pub exemption_ttl_secs: u64,
+    pub exemption_max_uses: u32,
+}
+
+impl Default for AppealPolicy {
+    fn default() -> Self {
+        Self {
+            exemption_ttl_secs: 7 * 24 * 60 * 60,
+            exemption_max_uses: 1,
+        }
+    }
+}
+
+impl AppealPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.exemption_ttl_secs == 0 {
+            return Err("appeal exemption lifetime must be positive".to_owned());
+        }
+        if self.exemption_max_uses == 0 {
+            return Err("appeal exemption uses must be positive".to_owned());
+        }
+        Ok(())
+    }
+}
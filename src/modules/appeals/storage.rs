@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::Appeal;
+
+#[cfg(feature = "sled-storage")]
+const APPEALS_TREE: &str = "appeals";
+
+/// Appeals against blocked decisions, open and resolved
+///
+/// Resolved appeals are kept; the audit trail records each transition, this store only
+/// the latest state.
+pub trait AppealStore: Send + Sync {
+    /// Insert an appeal, replacing any with the same id
+    fn upsert(&self, appeal: Appeal) -> Result<(), AppealStoreError>;
+    fn get(&self, id: &str) -> Result<Option<Appeal>, AppealStoreError>;
+    /// Every appeal, ordered by id
+    fn list(&self) -> Result<Vec<Appeal>, AppealStoreError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryAppealStore {
+    inner: Arc<Mutex<BTreeMap<String, Appeal>>>,
+}
+
+impl InMemoryAppealStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AppealStore for InMemoryAppealStore {
+    fn upsert(&self, appeal: Appeal) -> Result<(), AppealStoreError> {
+        self.inner
+            .lock()
+            .map_err(|_| AppealStoreError::LockPoisoned)?
+            .insert(appeal.id.clone(), appeal);
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Appeal>, AppealStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| AppealStoreError::LockPoisoned)?
+            .get(id)
+            .cloned())
+    }
+
+    fn list(&self) -> Result<Vec<Appeal>, AppealStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| AppealStoreError::LockPoisoned)?
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+/// Appeals kept in their own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledAppealStore {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledAppealStore {
+    pub fn new(db: &Db) -> Result<Self, AppealStoreError> {
+        let tree = db
+            .open_tree(APPEALS_TREE)
+            .map_err(|e| AppealStoreError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl AppealStore for SledAppealStore {
+    fn upsert(&self, appeal: Appeal) -> Result<(), AppealStoreError> {
+        let serialized = serde_json::to_vec(&appeal)
+            .map_err(|e| AppealStoreError::SerializationError(e.to_string()))?;
+        self.tree
+            .insert(appeal.id.as_bytes(), serialized)
+            .map_err(|e| AppealStoreError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| AppealStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Appeal>, AppealStoreError> {
+        self.tree
+            .get(id.as_bytes())
+            .map_err(|e| AppealStoreError::DatabaseError(e.to_string()))?
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| AppealStoreError::SerializationError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn list(&self) -> Result<Vec<Appeal>, AppealStoreError> {
+        self.tree
+            .iter()
+            .values()
+            .map(|entry| {
+                let data = entry.map_err(|e| AppealStoreError::DatabaseError(e.to_string()))?;
+                serde_json::from_slice(&data)
+                    .map_err(|e| AppealStoreError::SerializationError(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AppealStoreError {
+    #[error("appeal store lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::storage::{AuditStorage, AuditStorageError, AuditTrailResponse, StoredAuditRecord};
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Durability window and bounds of [`BatchedAuditStorage`]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WriteBehindConfig {
+    /// Longest a record waits in the queue before its batch is written (default: 50)
+    pub flush_interval_ms: u64,
+    /// Records written per batch; a full batch is written without waiting (default: 256)
+    pub max_batch_size: usize,
+    /// Records queued before appends wait for the writer (default: 4096)
+    pub queue_capacity: usize,
+}
+
+impl Default for WriteBehindConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval_ms: 50,
+            max_batch_size: 256,
+            queue_capacity: 4096,
+        }
+    }
+}
+
+impl WriteBehindConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.flush_interval_ms == 0 {
+            return Err("flush interval must be at least 1 ms".to_owned());
+        }
+        if self.max_batch_size == 0 {
+            return Err("batch size must be at least 1".to_owned());
+        }
+        if self.queue_capacity < self.max_batch_size {
+            return Err(format!(
+                "queue capacity {} is smaller than the batch size {}",
+                self.queue_capacity, self.max_batch_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Audit storage that queues appends and writes them to another backend in batches
+///
+/// A background thread writes the queue every `flush_interval_ms`, or as soon as
+/// `max_batch_size` records are waiting, using [`AuditStorage::append_batch`]. Appends
+/// never drop records: once `queue_capacity` records are unwritten they block until
+/// the writer catches up. The chain head reported by [`latest_chain_hash`] includes
+/// queued records, so new records chain onto them as if they were already stored.
+///
+/// Records still queued when the process dies are lost: at most `queue_capacity`
+/// records, and normally no more than `flush_interval_ms` worth of traffic. The
+/// `audit_unflushed_records` gauge shows the current exposure. [`flush`] writes the
+/// queue out, reads flush before they query the wrapped storage, and dropping the
+/// storage flushes it before the writer thread stops.
+///
+/// [`latest_chain_hash`]: AuditStorage::latest_chain_hash
+/// [`flush`]: AuditStorage::flush
+pub struct BatchedAuditStorage {
+    shared: Arc<Shared>,
+    writer: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    inner: Arc<dyn AuditStorage>,
+    config: WriteBehindConfig,
+    queue: Mutex<Queue>,
+    /// Signalled whenever the queue changes or a flush or shutdown is requested
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<StoredAuditRecord>,
+    /// Records handed to the wrapped storage and not yet confirmed
+    writing: usize,
+    /// Chain hash of the last record being written
+    writing_head: Option<String>,
+    flush_requested: bool,
+    shutdown: bool,
+    /// Records ever queued, and ever written to the wrapped storage
+    accepted: u64,
+    written: u64,
+    /// Completed write attempts, successful or not
+    attempts: u64,
+    /// Error of the last write attempt, cleared by the next successful one
+    last_error: Option<String>,
+}
+
+impl Queue {
+    fn unflushed(&self) -> usize {
+        self.pending.len() + self.writing
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> Result<MutexGuard<'_, Queue>, AuditStorageError> {
+        self.queue
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)
+    }
+}
+
+impl BatchedAuditStorage {
+    /// Queue appends to `inner`; `config` should pass [`WriteBehindConfig::validate`]
+    pub fn new(inner: Arc<dyn AuditStorage>, config: WriteBehindConfig) -> Self {
+        let shared = Arc::new(Shared {
+            inner,
+            config,
+            queue: Mutex::default(),
+            changed: Condvar::new(),
+        });
+        let writer = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("audit-write-behind".to_owned())
+                .spawn(move || write_queued(&shared))
+                .expect("spawn audit writer thread")
+        };
+        Self {
+            shared,
+            writer: Some(writer),
+        }
+    }
+
+    /// Records accepted but not yet written to the wrapped storage
+    pub fn unflushed(&self) -> usize {
+        self.shared
+            .lock()
+            .map(|queue| queue.unflushed())
+            .unwrap_or(0)
+    }
+}
+
+impl AuditStorage for BatchedAuditStorage {
+    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
+        let capacity = self.shared.config.queue_capacity.max(1);
+        let mut queue = self
+            .shared
+            .changed
+            .wait_while(self.shared.lock()?, |queue| queue.unflushed() >= capacity)
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        queue.pending.push_back(record);
+        queue.accepted += 1;
+        get_metrics().set_audit_unflushed_records(queue.unflushed());
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
+        let queue = self.shared.lock()?;
+        match queue.pending.back() {
+            Some(record) => Ok(Some(record.proof.chain_hash.clone())),
+            None if queue.writing_head.is_some() => Ok(queue.writing_head.clone()),
+            None => self.shared.inner.latest_chain_hash(),
+        }
+    }
+
+    /// Wait until every record queued before the call is written, or a write fails
+    fn flush(&self) -> Result<(), AuditStorageError> {
+        let mut queue = self.shared.lock()?;
+        let started = queue.attempts;
+        let target = queue.accepted;
+        queue.flush_requested = true;
+        self.shared.changed.notify_all();
+        while queue.written < target {
+            if queue.attempts > started
+                && let Some(error) = &queue.last_error
+            {
+                return Err(AuditStorageError::DatabaseError(error.clone()));
+            }
+            queue = self
+                .shared
+                .changed
+                .wait(queue)
+                .map_err(|_| AuditStorageError::LockPoisoned)?;
+        }
+        Ok(())
+    }
+
+    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        self.flush()?;
+        self.shared.inner.all()
+    }
+
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        self.flush()?;
+        self.shared.inner.tail(limit)
+    }
+
+    fn get_with_filters(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        self.flush()?;
+        self.shared
+            .inner
+            .get_with_filters(limit, offset, start_time, end_time, correlation_id)
+    }
+
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        self.flush()?;
+        self.shared
+            .inner
+            .get_page(cursor, limit, start_time, end_time, correlation_id)
+    }
+}
+
+impl Drop for BatchedAuditStorage {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.shutdown = true;
+        }
+        self.shared.changed.notify_all();
+        if let Some(writer) = self.writer.take() {
+            writer.join().ok();
+        }
+    }
+}
+
+/// Writer thread: drain the queue in batches until shutdown
+fn write_queued(shared: &Shared) {
+    let interval = Duration::from_millis(shared.config.flush_interval_ms);
+    let batch_size = shared.config.max_batch_size.max(1);
+    let Ok(mut queue) = shared.queue.lock() else {
+        return;
+    };
+    loop {
+        let Ok(next) = shared
+            .changed
+            .wait_while(queue, |queue| queue.pending.is_empty() && !queue.shutdown)
+        else {
+            return;
+        };
+        queue = next;
+        if queue.pending.is_empty() {
+            return;
+        }
+
+        // Gather records until the batch is full or the oldest has waited long enough
+        let deadline = Instant::now() + interval;
+        while queue.pending.len() < batch_size && !queue.flush_requested && !queue.shutdown {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            let Ok((next, _)) = shared.changed.wait_timeout(queue, remaining) else {
+                return;
+            };
+            queue = next;
+        }
+
+        let count = queue.pending.len().min(batch_size);
+        let batch: Vec<StoredAuditRecord> = queue.pending.drain(..count).collect();
+        queue.writing = batch.len();
+        queue.writing_head = batch.last().map(|record| record.proof.chain_hash.clone());
+        if queue.pending.is_empty() {
+            queue.flush_requested = false;
+        }
+        drop(queue);
+
+        let result = shared.inner.append_batch(&batch);
+
+        let Ok(next) = shared.queue.lock() else {
+            return;
+        };
+        queue = next;
+        queue.writing = 0;
+        queue.writing_head = None;
+        queue.attempts += 1;
+        let failed = match result {
+            Ok(()) => {
+                queue.written += batch.len() as u64;
+                queue.last_error = None;
+                false
+            }
+            Err(e) => {
+                error!("Writing {} queued audit records failed: {}", batch.len(), e);
+                let total = batch.len();
+                let unwritten = unwritten(shared.inner.as_ref(), batch);
+                queue.written += (total - unwritten.len()) as u64;
+                for record in unwritten.into_iter().rev() {
+                    queue.pending.push_front(record);
+                }
+                queue.last_error = Some(e.to_string());
+                // The flush waiting on this batch reports the error instead
+                queue.flush_requested = false;
+                true
+            }
+        };
+        get_metrics().set_audit_unflushed_records(queue.unflushed());
+        shared.changed.notify_all();
+
+        if failed {
+            if queue.shutdown {
+                error!(
+                    "Discarding {} audit records that could not be written before shutdown",
+                    queue.pending.len()
+                );
+                queue.pending.clear();
+                get_metrics().set_audit_unflushed_records(0);
+                shared.changed.notify_all();
+                return;
+            }
+            // Back off before retrying so a failing backend is not hammered, unless a
+            // flush asks for the records now
+            let Ok((next, _)) = shared.changed.wait_timeout_while(queue, interval, |queue| {
+                !queue.shutdown && !queue.flush_requested
+            }) else {
+                return;
+            };
+            queue = next;
+        }
+    }
+}
+
+/// The records of a failed batch that did not reach `inner`, going by its chain head
+fn unwritten(
+    inner: &dyn AuditStorage,
+    mut batch: Vec<StoredAuditRecord>,
+) -> Vec<StoredAuditRecord> {
+    let Ok(Some(head)) = inner.latest_chain_hash() else {
+        return batch;
+    };
+    match batch
+        .iter()
+        .position(|record| record.proof.chain_hash == head)
+    {
+        Some(last_written) => batch.split_off(last_written + 1),
+        None => batch,
+    }
+}
@@ -0,0 +1,101 @@
+// Only the sled store compresses records; settings are still parsed and checked without it
+#![cfg_attr(not(feature = "sled-storage"), allow(dead_code))]
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Marks a compressed record; plaintext records are JSON and start with `{`
+const ENVELOPE_MAGIC: &[u8] = b"PSZST1";
+
+/// zstd compression of large audit records at rest, selected with
+/// `AUDIT_COMPRESSION_THRESHOLD_BYTES`
+///
+/// A record whose JSON encoding is longer than `threshold_bytes` is stored as
+/// `PSZST1 | zstd frame`; shorter ones stay plain JSON. Compression comes before
+/// encryption, so an encrypted record hides whether it was compressed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AuditCompression {
+    /// Records longer than this are compressed; 0 turns compression off (default: 4096)
+    pub threshold_bytes: usize,
+    /// zstd level, 1 (fastest) to 22 (smallest) (default: 3)
+    pub level: i32,
+}
+
+impl Default for AuditCompression {
+    fn default() -> Self {
+        Self {
+            threshold_bytes: 4096,
+            level: 3,
+        }
+    }
+}
+
+impl AuditCompression {
+    /// Compression turned off
+    pub fn disabled() -> Self {
+        Self {
+            threshold_bytes: 0,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=22).contains(&self.level) {
+            return Err(format!(
+                "audit compression level must be between 1 and 22, got {}",
+                self.level
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.threshold_bytes > 0
+    }
+
+    /// Whether a record `len` bytes long is compressed
+    pub fn applies_to(&self, len: usize) -> bool {
+        self.enabled() && len > self.threshold_bytes
+    }
+
+    /// `serialized` as it is stored: compressed above the threshold, unchanged below it
+    pub(crate) fn compress<'a>(
+        &self,
+        serialized: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, CompressionError> {
+        if !self.applies_to(serialized.len()) {
+            return Ok(Cow::Borrowed(serialized));
+        }
+        let frame = zstd::bulk::compress(serialized, self.level)
+            .map_err(|e| CompressionError::Compress(e.to_string()))?;
+        let mut envelope = Vec::with_capacity(ENVELOPE_MAGIC.len() + frame.len());
+        envelope.extend_from_slice(ENVELOPE_MAGIC);
+        envelope.extend_from_slice(&frame);
+        Ok(Cow::Owned(envelope))
+    }
+}
+
+/// Whether a stored value is a compression envelope rather than plain JSON
+pub(crate) fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(ENVELOPE_MAGIC)
+}
+
+/// JSON held by `data`, decompressing it when it is an envelope
+pub(crate) fn decompress(data: &[u8]) -> Result<Cow<'_, [u8]>, CompressionError> {
+    match data.strip_prefix(ENVELOPE_MAGIC) {
+        Some(frame) => zstd::stream::decode_all(frame)
+            .map(Cow::Owned)
+            .map_err(|e| CompressionError::Decompress(e.to_string())),
+        None => Ok(Cow::Borrowed(data)),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    #[error("failed to compress audit record: {0}")]
+    Compress(String),
+    #[error("compressed audit record is corrupt: {0}")]
+    Decompress(String),
+}
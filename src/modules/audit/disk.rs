@@ -0,0 +1,345 @@
+//! Disk usage of the sled database and what happens when it runs out of room
+//!
+//! sled never hands space back on its own schedule, and a full disk turns every audit
+//! append, and so every request, into an error. [`DiskMonitor`] measures the database
+//! directory on a schedule, reports it in `GET /ready`, the admin summary and the
+//! `sled_directory_bytes` and `sled_size_on_disk_bytes` gauges, and asks sled to
+//! reclaim space when it is amplified past a limit. Above the configured high-water
+//! mark it logs at error level and, under the `hash_only` policy, switches audit
+//! writes to [`AuditWriteMode::HashOnly`] until usage falls back below the mark.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error, info, warn};
+
+use super::logger::{AuditLogger, AuditWriteMode};
+use super::storage::AuditStorageError;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// What crossing the high-water mark does besides logging, selected with
+/// `AUDIT_DISK_HIGH_WATER_POLICY`
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HighWaterPolicy {
+    /// Only log; audit writes keep failing once the disk is full
+    #[default]
+    Log,
+    /// Write audit records hash-only until usage is back under the mark
+    HashOnly,
+}
+
+impl std::str::FromStr for HighWaterPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "log" => Ok(Self::Log),
+            "hash_only" => Ok(Self::HashOnly),
+            other => Err(format!(
+                "unknown high-water policy '{other}' (expected log or hash_only)"
+            )),
+        }
+    }
+}
+
+/// How often the database is measured and where its high-water mark lies
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AuditDiskConfig {
+    /// Seconds between measurements; 0 disables them (default: 60)
+    pub check_interval_secs: u64,
+    /// Size of the database directory, in bytes, at which usage is too high; 0 sets no
+    /// mark (default: 0)
+    pub high_water_bytes: u64,
+    /// What happens at the high-water mark besides logging (default: log)
+    pub high_water_policy: HighWaterPolicy,
+    /// Bytes on disk per byte of live data above which sled is asked to reclaim space
+    /// (default: 2.0)
+    pub max_space_amplification: f64,
+}
+
+impl Default for AuditDiskConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 60,
+            high_water_bytes: 0,
+            high_water_policy: HighWaterPolicy::Log,
+            max_space_amplification: 2.0,
+        }
+    }
+}
+
+impl AuditDiskConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_space_amplification.is_nan() || self.max_space_amplification < 1.0 {
+            return Err("maximum space amplification must be at least 1.0".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// A database that can report and give back the space it takes up
+pub trait DiskFootprint: Send + Sync {
+    /// Bytes the database occupies, as it accounts for them
+    fn size_on_disk(&self) -> Result<u64, AuditStorageError>;
+    /// Bytes on disk per byte of live data
+    fn space_amplification(&self) -> Result<f64, AuditStorageError>;
+    /// Let the database release space it no longer uses
+    fn reclaim(&self) -> Result<(), AuditStorageError>;
+}
+
+#[cfg(feature = "sled-storage")]
+impl DiskFootprint for sled::Db {
+    fn size_on_disk(&self) -> Result<u64, AuditStorageError> {
+        sled::Db::size_on_disk(self).map_err(|e| AuditStorageError::DatabaseError(e.to_string()))
+    }
+
+    fn space_amplification(&self) -> Result<f64, AuditStorageError> {
+        sled::Db::space_amplification(self)
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))
+    }
+
+    /// sled 0.34 has no explicit compaction: its segment cleaner rewrites sparse
+    /// segments as writes reach disk, so a flush lets it free them
+    fn reclaim(&self) -> Result<(), AuditStorageError> {
+        self.flush()
+            .map(|_| ())
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))
+    }
+}
+
+/// One measurement of the database
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DiskUsage {
+    /// Total size of the files in the database directory
+    pub directory_bytes: u64,
+    /// Size the database reports for itself
+    pub size_on_disk_bytes: u64,
+    pub space_amplification: f64,
+    /// The database was asked to reclaim space before this measurement
+    pub reclaimed: bool,
+    pub measured_at: DateTime<Utc>,
+}
+
+/// Disk usage and whether audit writes are degraded by it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DiskStatus {
+    /// Latest measurement; absent until the first check
+    pub usage: Option<DiskUsage>,
+    /// 0 when no mark is set
+    pub high_water_bytes: u64,
+    pub high_water_policy: HighWaterPolicy,
+    pub above_high_water: bool,
+    /// `hash_only` while records are stored without their content
+    pub audit_mode: AuditWriteMode,
+}
+
+struct MonitorState {
+    config: AuditDiskConfig,
+    /// Switched to hash-only writes under [`HighWaterPolicy::HashOnly`]
+    logger: Option<AuditLogger>,
+    usage: Option<DiskUsage>,
+    above_high_water: bool,
+}
+
+/// Measures the database directory and degrades audit writes above the high-water mark
+#[derive(Clone)]
+pub struct DiskMonitor {
+    path: PathBuf,
+    footprint: Arc<dyn DiskFootprint>,
+    state: Arc<Mutex<MonitorState>>,
+}
+
+impl DiskMonitor {
+    /// Monitor the database stored in `path`
+    pub fn new(
+        path: impl Into<PathBuf>,
+        footprint: Arc<dyn DiskFootprint>,
+        config: AuditDiskConfig,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            footprint,
+            state: Arc::new(Mutex::new(MonitorState {
+                config,
+                logger: None,
+                usage: None,
+                above_high_water: false,
+            })),
+        }
+    }
+
+    /// Logger whose writes the high-water policy applies to, shared with every clone
+    pub fn with_audit_logger(self, logger: AuditLogger) -> Self {
+        self.state.lock().unwrap().logger = Some(logger);
+        self
+    }
+
+    pub fn config(&self) -> AuditDiskConfig {
+        self.state.lock().unwrap().config
+    }
+
+    /// Move the high-water mark; takes effect at the next check
+    pub fn set_high_water_bytes(&self, bytes: u64) {
+        self.state.lock().unwrap().config.high_water_bytes = bytes;
+    }
+
+    pub fn status(&self) -> DiskStatus {
+        let state = self.state.lock().unwrap();
+        DiskStatus {
+            usage: state.usage.clone(),
+            high_water_bytes: state.config.high_water_bytes,
+            high_water_policy: state.config.high_water_policy,
+            above_high_water: state.above_high_water,
+            audit_mode: state
+                .logger
+                .as_ref()
+                .map_or(AuditWriteMode::Full, AuditLogger::write_mode),
+        }
+    }
+
+    /// Measure the database now and apply the high-water policy
+    ///
+    /// A failed measurement is logged and leaves the previous status in place.
+    pub async fn check(&self) -> DiskStatus {
+        let monitor = self.clone();
+        let max_space_amplification = self.config().max_space_amplification;
+        let measured =
+            tokio::task::spawn_blocking(move || monitor.measure(max_space_amplification))
+                .await
+                .unwrap_or_else(|e| {
+                    Err(AuditStorageError::DatabaseError(format!(
+                        "measurement task failed: {e}"
+                    )))
+                });
+        match measured {
+            Ok(usage) => self.record(usage),
+            Err(e) => error!(
+                "Failed to measure the database at {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+        self.status()
+    }
+
+    /// Run [`check`](Self::check) every `interval`, starting now
+    pub fn spawn_checks(&self, interval: Duration) -> JoinHandle<()> {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                monitor.check().await;
+            }
+        })
+    }
+
+    fn measure(&self, max_space_amplification: f64) -> Result<DiskUsage, AuditStorageError> {
+        let mut space_amplification = self.footprint.space_amplification()?;
+        let reclaimed = space_amplification > max_space_amplification;
+        if reclaimed {
+            // Small databases are always amplified by their preallocated segments
+            debug!(
+                "Reclaiming space in {}: amplification {:.2} is over {:.2}",
+                self.path.display(),
+                space_amplification,
+                max_space_amplification
+            );
+            self.footprint.reclaim()?;
+            space_amplification = self.footprint.space_amplification()?;
+        }
+        let directory_bytes = directory_size(&self.path).map_err(|e| {
+            AuditStorageError::DatabaseError(format!(
+                "could not measure {}: {e}",
+                self.path.display()
+            ))
+        })?;
+        Ok(DiskUsage {
+            directory_bytes,
+            size_on_disk_bytes: self.footprint.size_on_disk()?,
+            space_amplification,
+            reclaimed,
+            measured_at: Utc::now(),
+        })
+    }
+
+    fn record(&self, usage: DiskUsage) {
+        let metrics = get_metrics();
+        metrics.set_sled_disk_usage(usage.directory_bytes, usage.size_on_disk_bytes);
+        let mut state = self.state.lock().unwrap();
+        let mark = state.config.high_water_bytes;
+        let above = mark > 0 && usage.directory_bytes >= mark;
+        debug!(
+            "Database at {} uses {} bytes",
+            self.path.display(),
+            usage.directory_bytes
+        );
+
+        if above && !state.above_high_water {
+            error!(
+                "CRITICAL: database at {} uses {} bytes, over its high-water mark of {} bytes",
+                self.path.display(),
+                usage.directory_bytes,
+                mark
+            );
+        } else if above {
+            warn!(
+                "Database at {} is still over its high-water mark: {} of {} bytes",
+                self.path.display(),
+                usage.directory_bytes,
+                mark
+            );
+        } else if state.above_high_water {
+            info!(
+                "Database at {} is back under its high-water mark: {} of {} bytes",
+                self.path.display(),
+                usage.directory_bytes,
+                mark
+            );
+        }
+
+        if let Some(logger) = &state.logger {
+            let mode = if above && state.config.high_water_policy == HighWaterPolicy::HashOnly {
+                AuditWriteMode::HashOnly
+            } else {
+                AuditWriteMode::Full
+            };
+            if mode != logger.write_mode() {
+                match mode {
+                    AuditWriteMode::HashOnly => error!(
+                        "Audit records are now written hash-only; their content is dropped \
+                         until disk usage falls below the high-water mark"
+                    ),
+                    AuditWriteMode::Full => info!("Audit records are written in full again"),
+                }
+                logger.set_write_mode(mode);
+            }
+            metrics.set_audit_hash_only(mode == AuditWriteMode::HashOnly);
+        }
+
+        state.above_high_water = above;
+        state.usage = Some(usage);
+    }
+}
+
+/// Total length of the files under `path`
+fn directory_size(path: &Path) -> std::io::Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
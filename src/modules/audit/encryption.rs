@@ -0,0 +1,176 @@
+// Only the sled store seals records; keys are still parsed and checked without it
+#![cfg_attr(not(feature = "sled-storage"), allow(dead_code))]
+
+use std::fmt;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Marks an encrypted record; plaintext records are JSON and start with `{`
+const ENVELOPE_MAGIC: &[u8] = b"PSENC1";
+const KEY_ID_LEN: usize = 8;
+const HEADER_LEN: usize = ENVELOPE_MAGIC.len() + KEY_ID_LEN + NONCE_LEN;
+pub const AUDIT_KEY_LEN: usize = 32;
+
+/// AES-256 key for audit records at rest, selected with `AUDIT_ENCRYPTION_KEY`
+///
+/// Each record is sealed as `PSENC1 | key id | nonce | ciphertext and tag`. The key id
+/// is derived from the key, so a record names the key it needs without revealing it.
+#[derive(Clone)]
+pub struct AuditEncryptionKey {
+    bytes: [u8; AUDIT_KEY_LEN],
+    id: [u8; KEY_ID_LEN],
+}
+
+impl AuditEncryptionKey {
+    pub fn from_bytes(bytes: [u8; AUDIT_KEY_LEN]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"prompt-sentinel-audit-key");
+        hasher.update(bytes);
+        let digest = hasher.finalize();
+        let mut id = [0u8; KEY_ID_LEN];
+        id.copy_from_slice(&digest[..KEY_ID_LEN]);
+        Self { bytes, id }
+    }
+
+    /// Parse a base64-encoded key, or read one from a file
+    ///
+    /// A key file holds either the base64 text or the 32 raw bytes.
+    pub fn from_config(value: &str) -> Result<Self, EncryptionError> {
+        let value = value.trim();
+        if let Ok(key) = Self::from_base64(value) {
+            return Ok(key);
+        }
+        let path = Path::new(value);
+        if !path.is_file() {
+            return Err(EncryptionError::InvalidKey(format!(
+                "expected {AUDIT_KEY_LEN} base64-encoded bytes or a key file path"
+            )));
+        }
+        let content = std::fs::read(path).map_err(|e| {
+            EncryptionError::InvalidKey(format!("cannot read {}: {e}", path.display()))
+        })?;
+        if let Ok(bytes) = <[u8; AUDIT_KEY_LEN]>::try_from(content.as_slice()) {
+            return Ok(Self::from_bytes(bytes));
+        }
+        Self::from_base64(String::from_utf8_lossy(&content).trim())
+    }
+
+    fn from_base64(value: &str) -> Result<Self, EncryptionError> {
+        let decoded = STANDARD
+            .decode(value)
+            .map_err(|e| EncryptionError::InvalidKey(e.to_string()))?;
+        let bytes = <[u8; AUDIT_KEY_LEN]>::try_from(decoded.as_slice()).map_err(|_| {
+            EncryptionError::InvalidKey(format!(
+                "key must be {AUDIT_KEY_LEN} bytes, got {}",
+                decoded.len()
+            ))
+        })?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Fresh random key
+    pub fn generate() -> Result<Self, EncryptionError> {
+        let mut bytes = [0u8; AUDIT_KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| EncryptionError::Encrypt)?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Key encoded for `AUDIT_ENCRYPTION_KEY`
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.bytes)
+    }
+
+    /// Non-secret identifier stored with every record the key encrypts
+    pub fn key_id(&self) -> String {
+        hex::encode(self.id)
+    }
+
+    fn sealing_key(&self) -> LessSafeKey {
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &self.bytes).expect("AES-256 keys are 32 bytes"),
+        )
+    }
+
+    /// Seal `plaintext` under a random nonce
+    pub(crate) fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| EncryptionError::Encrypt)?;
+        let mut sealed = plaintext.to_vec();
+        self.sealing_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&self.id),
+                &mut sealed,
+            )
+            .map_err(|_| EncryptionError::Encrypt)?;
+
+        let mut envelope = Vec::with_capacity(HEADER_LEN + sealed.len());
+        envelope.extend_from_slice(ENVELOPE_MAGIC);
+        envelope.extend_from_slice(&self.id);
+        envelope.extend_from_slice(&nonce);
+        envelope.extend_from_slice(&sealed);
+        Ok(envelope)
+    }
+
+    /// Open an envelope this key sealed
+    pub(crate) fn decrypt(&self, envelope: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if envelope_key_id(envelope) != Some(self.key_id()) {
+            return Err(EncryptionError::Decrypt);
+        }
+        let nonce = <[u8; NONCE_LEN]>::try_from(&envelope[HEADER_LEN - NONCE_LEN..HEADER_LEN])
+            .map_err(|_| EncryptionError::Decrypt)?;
+        let mut sealed = envelope[HEADER_LEN..].to_vec();
+        let plaintext = self
+            .sealing_key()
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&self.id),
+                &mut sealed,
+            )
+            .map_err(|_| EncryptionError::Decrypt)?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+impl fmt::Debug for AuditEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditEncryptionKey")
+            .field("key_id", &self.key_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether a stored value is an encryption envelope rather than plaintext JSON
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENVELOPE_MAGIC)
+}
+
+/// Id of the key that sealed an envelope
+pub(crate) fn envelope_key_id(data: &[u8]) -> Option<String> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN {
+        return None;
+    }
+    Some(hex::encode(
+        &data[ENVELOPE_MAGIC.len()..ENVELOPE_MAGIC.len() + KEY_ID_LEN],
+    ))
+}
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("invalid audit encryption key: {0}")]
+    InvalidKey(String),
+    #[error("failed to encrypt audit record")]
+    Encrypt,
+    #[error("audit record failed authentication; it is corrupt or was sealed with another key")]
+    Decrypt,
+}
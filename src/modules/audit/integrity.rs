@@ -0,0 +1,491 @@
+//! Background verification of the audit hash chain
+//!
+//! Tail checks re-hash the newest records and confirm the chain still runs through the
+//! head an earlier check verified; they read the end of the trail through
+//! [`AuditStorage::tail`], so they stay cheap however long the trail grows. Full
+//! verifications walk the chain from its first record. A failure is logged as
+//! CRITICAL, sets the `audit_chain_broken` gauge, shows up in `GET /ready` and is
+//! posted once to the alert webhook.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, error, info};
+
+use super::proof::{chain_hash, hash_record};
+use super::storage::{AuditStorage, AuditStorageError, StoredAuditRecord};
+use crate::modules::alerting::dtos::{Alert, AlertLevel};
+use crate::modules::alerting::service::WebhookNotifier;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// `alert` of the webhook payload sent when verification fails
+pub const CHAIN_BROKEN_ALERT: &str = "audit_chain_broken";
+
+/// How often the chain is verified and what a failure does to readiness
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AuditIntegrityConfig {
+    /// Seconds between tail checks; 0 disables them (default: 300)
+    pub tail_interval_secs: u64,
+    /// Newest records covered by each tail check (default: 1000)
+    pub tail_records: usize,
+    /// Seconds between full verifications; 0 leaves them to `POST /api/audit/verify`
+    /// (default: 86400)
+    pub full_interval_secs: u64,
+    /// Answer `GET /ready` with 503 while the chain is broken (default: off)
+    pub fail_readiness: bool,
+}
+
+impl Default for AuditIntegrityConfig {
+    fn default() -> Self {
+        Self {
+            tail_interval_secs: 300,
+            tail_records: 1_000,
+            full_interval_secs: 86_400,
+            fail_readiness: false,
+        }
+    }
+}
+
+impl AuditIntegrityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tail_records == 0 {
+            return Err("audit integrity tail checks must cover at least one record".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationScope {
+    /// The newest records and their link to the previously verified head
+    Tail,
+    /// Every record from the start of the chain
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityFailureKind {
+    /// A payload no longer matches its record hash
+    RecordHash,
+    /// A chain hash does not follow from the record before it
+    ChainLink,
+    /// The head verified by an earlier check is gone or was rewritten
+    HeadContinuity,
+    /// The records could not be read or decrypted
+    Storage,
+}
+
+/// Where and why verification failed
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IntegrityFailure {
+    pub kind: IntegrityFailureKind,
+    pub scope: VerificationScope,
+    /// Record verification stopped at, when there is one
+    pub correlation_id: Option<String>,
+    pub record_timestamp: Option<DateTime<Utc>>,
+    pub detail: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl IntegrityFailure {
+    fn new(
+        kind: IntegrityFailureKind,
+        scope: VerificationScope,
+        record: Option<(&str, DateTime<Utc>)>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            scope,
+            correlation_id: record.map(|(correlation_id, _)| correlation_id.to_owned()),
+            record_timestamp: record.map(|(_, timestamp)| timestamp),
+            detail: detail.into(),
+            detected_at: Utc::now(),
+        }
+    }
+
+    fn at(
+        kind: IntegrityFailureKind,
+        scope: VerificationScope,
+        record: &StoredAuditRecord,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            kind,
+            scope,
+            Some((&record.correlation_id, record.timestamp)),
+            detail,
+        )
+    }
+
+    fn storage(scope: VerificationScope, error: AuditStorageError) -> Self {
+        Self::new(
+            IntegrityFailureKind::Storage,
+            scope,
+            None,
+            format!("audit records could not be read: {error}"),
+        )
+    }
+
+    /// Whether `other` reports the same break, found by a later check
+    fn same_break(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.correlation_id == other.correlation_id
+            && self.record_timestamp == other.record_timestamp
+    }
+
+    fn alert(&self) -> Alert {
+        Alert {
+            alert: CHAIN_BROKEN_ALERT.to_owned(),
+            level: AlertLevel::Critical,
+            summary: format!("Audit chain verification failed: {}", self.detail),
+            details: serde_json::to_value(self).unwrap_or_default(),
+            raised_at: self.detected_at,
+        }
+    }
+}
+
+/// Outcome of one verification
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub scope: VerificationScope,
+    /// Records whose hashes were recomputed; 0 when verification failed
+    pub records_verified: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<IntegrityFailure>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// What the checker currently knows about the chain
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IntegrityStatus {
+    /// The break found, until a full verification passes
+    pub failure: Option<IntegrityFailure>,
+    pub last_tail_check: Option<DateTime<Utc>>,
+    pub last_full_check: Option<DateTime<Utc>>,
+}
+
+impl IntegrityStatus {
+    pub fn is_broken(&self) -> bool {
+        self.failure.is_some()
+    }
+}
+
+/// Newest record a passing check verified
+#[derive(Clone, Debug)]
+struct VerifiedHead {
+    correlation_id: String,
+    timestamp: DateTime<Utc>,
+    chain_hash: String,
+}
+
+impl VerifiedHead {
+    fn of(record: &StoredAuditRecord) -> Self {
+        Self {
+            correlation_id: record.correlation_id.clone(),
+            timestamp: record.timestamp,
+            chain_hash: record.proof.chain_hash.clone(),
+        }
+    }
+
+    fn is(&self, record: &StoredAuditRecord) -> bool {
+        record.correlation_id == self.correlation_id && record.timestamp == self.timestamp
+    }
+}
+
+#[derive(Default)]
+struct CheckerState {
+    status: IntegrityStatus,
+    head: Option<VerifiedHead>,
+}
+
+/// Verifies the audit chain on demand and on a schedule, and raises the alarm on breaks
+#[derive(Clone)]
+pub struct AuditIntegrityChecker {
+    storage: Arc<dyn AuditStorage>,
+    config: AuditIntegrityConfig,
+    notifier: Option<WebhookNotifier>,
+    state: Arc<Mutex<CheckerState>>,
+}
+
+impl AuditIntegrityChecker {
+    pub fn new(storage: Arc<dyn AuditStorage>, config: AuditIntegrityConfig) -> Self {
+        Self {
+            storage,
+            config,
+            notifier: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Post failures to `notifier`; `None` only logs them
+    pub fn with_notifier(mut self, notifier: Option<WebhookNotifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn config(&self) -> AuditIntegrityConfig {
+        self.config
+    }
+
+    pub fn status(&self) -> IntegrityStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    /// Whether the chain is broken and `fail_readiness` makes that fail `GET /ready`
+    pub fn blocks_readiness(&self) -> bool {
+        self.config.fail_readiness && self.status().is_broken()
+    }
+
+    /// Verify the newest `tail_records` records and that the chain still runs through
+    /// the head the previous check verified
+    ///
+    /// When more records were appended since then than one check covers, the old head
+    /// is out of reach and only a full verification covers the gap.
+    pub async fn check_tail(&self) -> VerificationReport {
+        self.run(VerificationScope::Tail).await
+    }
+
+    /// Verify every record from the start of the chain
+    ///
+    /// A pass clears a break reported earlier, for example after the trail was restored
+    /// from a backup.
+    pub async fn check_full(&self) -> VerificationReport {
+        self.run(VerificationScope::Full).await
+    }
+
+    /// Run [`check_tail`](Self::check_tail) every `interval`, starting now
+    pub fn spawn_tail_checks(&self, interval: Duration) -> JoinHandle<()> {
+        self.spawn_checks(VerificationScope::Tail, Instant::now(), interval)
+    }
+
+    /// Run [`check_full`](Self::check_full) every `interval`, starting one interval
+    /// from now
+    pub fn spawn_full_checks(&self, interval: Duration) -> JoinHandle<()> {
+        self.spawn_checks(VerificationScope::Full, Instant::now() + interval, interval)
+    }
+
+    fn spawn_checks(
+        &self,
+        scope: VerificationScope,
+        start: Instant,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let checker = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval_at(start, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                checker.run(scope).await;
+            }
+        })
+    }
+
+    async fn run(&self, scope: VerificationScope) -> VerificationReport {
+        let checker = self.clone();
+        let verified = tokio::task::spawn_blocking(move || checker.verify(scope))
+            .await
+            .unwrap_or_else(|e| {
+                Err(IntegrityFailure::new(
+                    IntegrityFailureKind::Storage,
+                    scope,
+                    None,
+                    format!("verification task failed: {e}"),
+                ))
+            });
+        self.record(scope, verified).await
+    }
+
+    /// Records verified and the new head
+    fn verify(
+        &self,
+        scope: VerificationScope,
+    ) -> Result<(usize, Option<VerifiedHead>), IntegrityFailure> {
+        let (previous, records) = match scope {
+            VerificationScope::Tail => {
+                // One record more than checked, so the oldest checked record has the
+                // chain hash it extends
+                let mut records = self
+                    .storage
+                    .tail(self.config.tail_records.saturating_add(1))
+                    .map_err(|e| IntegrityFailure::storage(scope, e))?;
+                let previous =
+                    (records.len() > self.config.tail_records).then(|| records.remove(0));
+                (previous, records)
+            }
+            VerificationScope::Full => (
+                None,
+                self.storage
+                    .all()
+                    .map_err(|e| IntegrityFailure::storage(scope, e))?,
+            ),
+        };
+
+        let mut link = match &previous {
+            Some(previous) => {
+                verify_record_hash(scope, previous)?;
+                Some(previous.proof.chain_hash.as_str())
+            }
+            None => None,
+        };
+        for record in &records {
+            verify_record_hash(scope, record)?;
+            if chain_hash(link, &record.proof.record_hash) != record.proof.chain_hash {
+                return Err(IntegrityFailure::at(
+                    IntegrityFailureKind::ChainLink,
+                    scope,
+                    record,
+                    format!(
+                        "chain hash of {} does not follow from the record before it",
+                        record.correlation_id
+                    ),
+                ));
+            }
+            link = Some(&record.proof.chain_hash);
+        }
+
+        let head = self.state.lock().unwrap().head.clone();
+        if let Some(head) = head {
+            let window: Vec<_> = previous.iter().chain(&records).collect();
+            verify_continuity(scope, &head, &window)?;
+        }
+        Ok((records.len(), records.last().map(VerifiedHead::of)))
+    }
+
+    async fn record(
+        &self,
+        scope: VerificationScope,
+        verified: Result<(usize, Option<VerifiedHead>), IntegrityFailure>,
+    ) -> VerificationReport {
+        let checked_at = Utc::now();
+        let alert = {
+            let mut state = self.state.lock().unwrap();
+            match scope {
+                VerificationScope::Tail => state.status.last_tail_check = Some(checked_at),
+                VerificationScope::Full => state.status.last_full_check = Some(checked_at),
+            }
+            let alert = match &verified {
+                Ok((records, head)) => {
+                    debug!("Verified {} audit records ({:?})", records, scope);
+                    if let Some(head) = head {
+                        state.head = Some(head.clone());
+                    }
+                    // A tail check passes again once a broken record falls out of its
+                    // window, so only a full verification vouches for the chain again
+                    let cleared = state.status.failure.as_ref().is_some_and(|failure| {
+                        scope == VerificationScope::Full
+                            || failure.kind == IntegrityFailureKind::Storage
+                    });
+                    if cleared {
+                        info!("Audit chain verified again ({} records)", records);
+                        state.status.failure = None;
+                    }
+                    None
+                }
+                Err(failure) => {
+                    error!(
+                        kind = ?failure.kind,
+                        correlation_id = failure.correlation_id.as_deref().unwrap_or("-"),
+                        "CRITICAL: audit chain verification failed ({:?}): {}",
+                        scope,
+                        failure.detail
+                    );
+                    let repeated = state
+                        .status
+                        .failure
+                        .as_ref()
+                        .is_some_and(|current| current.same_break(failure));
+                    if repeated {
+                        None
+                    } else {
+                        state.status.failure = Some(failure.clone());
+                        Some(failure.alert())
+                    }
+                }
+            };
+            get_metrics().set_audit_chain_broken(state.status.is_broken());
+            alert
+        };
+
+        if let (Some(alert), Some(notifier)) = (alert, &self.notifier)
+            && let Err(e) = notifier.notify(&alert).await
+        {
+            error!("Failed to post the audit chain alert to the webhook: {}", e);
+        }
+
+        let (records_verified, failure) = match verified {
+            Ok((records, _)) => (records, None),
+            Err(failure) => (0, Some(failure)),
+        };
+        VerificationReport {
+            scope,
+            records_verified,
+            failure,
+            checked_at,
+        }
+    }
+}
+
+fn verify_record_hash(
+    scope: VerificationScope,
+    record: &StoredAuditRecord,
+) -> Result<(), IntegrityFailure> {
+    if hash_record(&record.payload) == record.proof.record_hash {
+        return Ok(());
+    }
+    Err(IntegrityFailure::at(
+        IntegrityFailureKind::RecordHash,
+        scope,
+        record,
+        format!(
+            "payload of {} does not match its record hash",
+            record.correlation_id
+        ),
+    ))
+}
+
+/// Fail unless `head` is still in `window`, consecutive records oldest first, unchanged
+fn verify_continuity(
+    scope: VerificationScope,
+    head: &VerifiedHead,
+    window: &[&StoredAuditRecord],
+) -> Result<(), IntegrityFailure> {
+    let missing = || {
+        IntegrityFailure::new(
+            IntegrityFailureKind::HeadContinuity,
+            scope,
+            Some((&head.correlation_id, head.timestamp)),
+            format!(
+                "previously verified record {} is no longer in the trail",
+                head.correlation_id
+            ),
+        )
+    };
+    let Some(oldest) = window.first() else {
+        return Err(missing());
+    };
+    if oldest.timestamp > head.timestamp {
+        // Older than the window: left to full verification
+        return Ok(());
+    }
+    match window.iter().find(|record| head.is(record)) {
+        Some(record) if record.proof.chain_hash == head.chain_hash => Ok(()),
+        Some(record) => Err(IntegrityFailure::at(
+            IntegrityFailureKind::HeadContinuity,
+            scope,
+            record,
+            format!(
+                "chain hash of {} changed since it was verified",
+                record.correlation_id
+            ),
+        )),
+        None => Err(missing()),
+    }
+}
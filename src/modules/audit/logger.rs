@@ -1,15 +1,42 @@
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::warn;
 
-use super::proof::{AuditProof, chain_hash, hash_record};
-use super::storage::{AuditStorage, AuditStorageError, StoredAuditRecord};
+use crate::firewall_core::dtos::{ExtractedUrl, InputTruncation, MatchedBlockRule};
+use crate::firewall_core::taxonomy::ResolvedThreat;
+use crate::modules::appeals::dtos::Appeal;
+use crate::modules::exemptions::dtos::{AppliedExemption, Exemption};
+use crate::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatMessage, ModerationCategory, ModerationResponse,
+};
+use crate::modules::preprocessing::dtos::AppliedTransform;
+use crate::modules::sanitize_probing::dtos::ProbingEscalation;
+use crate::modules::telemetry::metrics::get_metrics;
+use crate::workflow::{
+    JudgeEvidence, OutputAnalysisSummary, OutputModerationChunks, ReasonCode, ReasonParams,
+    RiskInputs, StageFailure, StageStatuses, TemplateEvidence, ToggleableStage, TraceStep,
+};
+
+use super::proof::{AuditProof, chain_hash, content_hash, hash_record};
+use super::storage::{AuditStorage, AuditStorageError, PromptStorageMode, StoredAuditRecord};
+use super::suppression::{AuditSuppressionPolicy, RepeatSuppressor, SuppressionRule};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AuditEvent {
     pub correlation_id: String,
+    /// Earlier decision this one follows from: the decision whose overturned appeal
+    /// granted an exemption applied here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_correlation_id: Option<String>,
+    /// Session the caller sent the request under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
     pub original_prompt: String,
     pub sanitized_prompt: String,
     pub firewall_action: String,
@@ -27,13 +54,20 @@ pub struct AuditEvent {
     pub final_status: String,
     /// Human-readable explanation of the decision
     pub final_reason: String,
+    /// Stable machine-readable cause of the decision
+    #[serde(default)]
+    pub final_reason_code: ReasonCode,
+    /// Structured values rendered into `final_reason`
+    #[serde(default)]
+    pub reason_params: ReasonParams,
     pub model_used: Option<String>,
     /// Short preview of the output (first 160 chars)
     pub output_preview: Option<String>,
     /// Full model response text (for complete audit trail)
     pub full_output_text: Option<String>,
-    /// Categories flagged by output moderation
-    pub output_moderation_categories: Vec<String>,
+    /// Categories flagged by moderation, with their severities; records written before
+    /// severities were kept list bare names
+    pub output_moderation_categories: Vec<ModerationCategory>,
     /// EU AI Act risk tier classification
     pub eu_risk_tier: Option<String>,
     /// EU AI Act compliance findings
@@ -46,20 +80,582 @@ pub struct AuditEvent {
     pub detected_language: Option<String>,
     /// Whether the response was translated back to original language
     pub was_translated: bool,
+    /// Step-by-step explanation of how the decision was reached
+    #[serde(default)]
+    pub decision_trace: Vec<TraceStep>,
+    /// Earlier blocked request this prompt closely resembled
+    #[serde(default)]
+    pub similar_blocked_correlation_id: Option<String>,
+    /// Earlier sanitized requests of the session that made this one a probing attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitize_probing: Option<ProbingEscalation>,
+    /// Written by `POST /api/selftest` rather than a real caller
+    #[serde(default)]
+    pub self_test: bool,
+    /// Versions of the rule sets the decision was made with
+    #[serde(default)]
+    pub config_fingerprint: ConfigFingerprint,
+    /// Transforms run on `original_prompt` before the firewall
+    #[serde(default)]
+    pub preprocessing: Vec<AppliedTransform>,
+    /// Why the semantic scan did not run ("sampled_out", "disabled_by_admin")
+    #[serde(default)]
+    pub semantic_skipped_reason: Option<String>,
+    /// Why moderation did not run ("not_configured")
+    #[serde(default)]
+    pub moderation_skipped_reason: Option<String>,
+    /// Stages an administrator had paused when the request was processed
+    #[serde(default)]
+    pub disabled_stages: Vec<ToggleableStage>,
+    /// Exemptions that suppressed firewall rules for this request
+    #[serde(default)]
+    pub applied_exemptions: Vec<AppliedExemption>,
+    /// Whether `original_prompt` and `sanitized_prompt` hold the text or only its hash
+    #[serde(default)]
+    pub prompt_storage: PromptStorageMode,
+    /// Stages that failed while the request was processed
+    #[serde(default)]
+    pub degraded_stages: Vec<StageFailure>,
+    /// Moderation of the English output
+    #[serde(default)]
+    pub output_moderation: Option<ModerationResponse>,
+    /// How the English output was split for moderation, when it was too long for one call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_moderation_chunks: Option<OutputModerationChunks>,
+    /// Moderation of the translated output, when a second pass ran
+    #[serde(default)]
+    pub translated_output_moderation: Option<ModerationResponse>,
+    /// Verdict of each check run on the generated answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_analysis: Option<OutputAnalysisSummary>,
+    /// Index into `decision_trace` of the step that decided the outcome
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decisive_step: Option<usize>,
+    /// Decision policy rule that decided the outcome, when a custom policy applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_rule: Option<String>,
+    /// Rewording hints from bias detection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bias_mitigation_hints: Vec<String>,
+    /// Composite risk score (0-100) of the decision
+    #[serde(default)]
+    pub risk_score: Option<u8>,
+    /// Signals the risk score was computed from
+    #[serde(default)]
+    pub risk_inputs: Option<RiskInputs>,
+    /// The firewall allowed the prompt and the semantic scan or input moderation blocked it
+    #[serde(default)]
+    pub firewall_miss: bool,
+    /// The firewall blocked a prompt the semantic scan scored low
+    #[serde(default)]
+    pub firewall_overblock: bool,
+    /// The prompt switched between languages
+    #[serde(default)]
+    pub language_mix_detected: bool,
+    /// Languages found in a mixed-language prompt
+    #[serde(default)]
+    pub mixed_languages: Vec<String>,
+    /// Set when the record validates an exchange the caller generated themselves
+    #[serde(default)]
+    pub exchange_validation: Option<ExchangeValidation>,
+    /// What was sent to the chat API, when the request reached generation
+    #[serde(default)]
+    pub generation_input_digest: Option<GenerationInputDigest>,
+    /// What the generation call was sent with besides its messages, enough to reissue it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_parameters: Option<GenerationParameters>,
+    /// The registered template the request filled in, with per-slot verdicts;
+    /// `original_prompt` holds the prompt its values made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateEvidence>,
+    /// How `original_prompt` was cut down to the input limit before the firewall ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_truncation: Option<InputTruncation>,
+    /// What happened to each stage, in the vocabulary of the response's `stages`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage_statuses: Option<StageStatuses>,
+    /// Threat category of the decision and the layer it came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_category: Option<ResolvedThreat>,
+    /// Question and answer of the judge model, when the prompt was in the gray zone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_judge: Option<JudgeEvidence>,
+    /// Monitor-mode block rules the prompt matched, which did not affect the decision
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_firewall_matches: Vec<MatchedBlockRule>,
+    /// URLs in the prompt with the URL policy's action and any unfurl findings; under
+    /// redacted prompt storage each `url` is a fingerprint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub firewall_urls: Vec<ExtractedUrl>,
+}
+
+/// The exact input of a generation call and how it was derived from the caller's prompt
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GenerationInputDigest {
+    /// `sha256:` hash of the JSON message list as sent
+    pub sha256: String,
+    /// Steps between the caller's prompt and the generation input, in order
+    pub transformations: Vec<String>,
+    /// Content of the user message; kept only under full prompt storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_content: Option<String>,
+}
+
+impl GenerationInputDigest {
+    /// `sha256:` hash of `messages` serialized as they go over the wire
+    pub fn messages_hash(messages: &[ChatMessage]) -> String {
+        format!("sha256:{}", content_hash(&messages))
+    }
+}
+
+/// Model and sampling parameters of a generation call
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct GenerationParameters {
+    pub model: String,
+    pub safe_prompt: bool,
+    /// Set when the request asked for deterministic generation
+    #[serde(default)]
+    pub deterministic: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+}
+
+impl GenerationParameters {
+    /// Parameters of `request`, leaving out its messages
+    pub fn of(request: &ChatCompletionRequest) -> Self {
+        Self {
+            model: request.model.clone(),
+            safe_prompt: request.safe_prompt,
+            deterministic: request.random_seed.is_some(),
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            random_seed: request.random_seed,
+        }
+    }
+
+    /// A request sending `messages` with these parameters
+    pub fn request(&self, messages: Vec<ChatMessage>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: self.model.clone(),
+            messages,
+            safe_prompt: self.safe_prompt,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            random_seed: self.random_seed,
+        }
+    }
+}
+
+/// The texts of a validated exchange, by content hash
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExchangeValidation {
+    pub prompt_hash: String,
+    pub generated_text_hash: String,
+}
+
+/// Content hashes of the rule sets and policies in effect, taken when each is loaded
+///
+/// Each hash is the SHA-256 of the component's canonical JSON, so two records with the
+/// same value were decided against identical rules.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConfigFingerprint {
+    pub firewall_rules: String,
+    pub bias_rules: String,
+    /// Absent until the semantic detector has loaded its template bank
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attack_bank: Option<String>,
+    pub moderation_policy: String,
+}
+
+/// Audit payload recorded when runtime configuration is replaced
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigChangeEvent {
+    pub correlation_id: String,
+    /// Always "configuration_change"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// Operation that changed the configuration (e.g. "restore", "maintenance_enabled")
+    pub action: String,
+    /// Content hash of the configuration before the change
+    pub previous_hash: String,
+    /// Content hash of the configuration after the change
+    pub new_hash: String,
+    /// Snapshot document version that was applied, for snapshot restores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_version: Option<u32>,
+    /// Free-form context for the change (e.g. the maintenance message)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Audit payload recorded when a firewall exemption is granted or stops applying
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExemptionEvent {
+    pub correlation_id: String,
+    /// Always "exemption"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// "granted", or why the exemption was archived ("expired", "exhausted", "revoked")
+    pub action: String,
+    /// Decision the exemption was granted for, when it was granted for one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_correlation_id: Option<String>,
+    pub exemption: Exemption,
+}
+
+/// Audit payload recorded when an appeal against a blocked decision changes state
+///
+/// Recorded under the appealed decision's correlation id, so the decision, the appeal,
+/// any exemption it led to and the resolution read back as one trail.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AppealEvent {
+    pub correlation_id: String,
+    /// Always "appeal"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// "filed", or the outcome of the resolution ("upheld", "overturned")
+    pub action: String,
+    /// Request that made the change
+    pub request_correlation_id: String,
+    /// The appeal after the change
+    pub appeal: Appeal,
+}
+
+/// Audit payload recorded when an audited decision is re-evaluated
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayEvent {
+    pub correlation_id: String,
+    /// Always "replay"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// Request whose decision was replayed
+    pub replayed_correlation_id: String,
+    /// The replayed decision again, under the key every record outside a decision's own
+    /// correlation id uses to point at it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_correlation_id: Option<String>,
+    /// "current", "historical" or "regenerate"
+    pub mode: String,
+    /// Fingerprint of the firewall rules the replay ran against
+    pub firewall_rules: String,
+    pub original_status: String,
+    pub replayed_status: String,
+    /// Decision evidence fields whose value changed
+    pub changed_fields: Vec<String>,
+    /// Whether the reissued generation reproduced the recorded output, in regenerate mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_matched: Option<bool>,
+}
+
+/// Audit payload recorded when the caller went away after the input checks passed
+///
+/// No decision record follows, so this is what billing reconciliation sees of a
+/// generation that may have been paid for without being delivered.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AbandonmentEvent {
+    pub correlation_id: String,
+    /// Always "abandoned_by_client"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// "generation" or "output_checks"
+    pub stage: String,
+    /// Whether the generation call had been sent to Mistral
+    pub generation_started: bool,
+    /// Time from the start of the run to the cancellation
+    pub elapsed_ms: u64,
+}
+
+/// Audit payload standing for repeats of a synthetic event that were not stored
+///
+/// Recorded under the correlation id of the occurrence stored in full, once per
+/// suppression window that counted any.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SuppressedRepeatsEvent {
+    pub correlation_id: String,
+    /// Always "suppressed_repeats"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// Rule the repeats matched
+    pub rule: SuppressionRule,
+    /// `v1:` fingerprint of the repeated prompt
+    pub prompt_hash: String,
+    pub final_status: String,
+    /// Repeats counted in the window
+    pub count: u64,
+    /// When the first and last counted repeat were seen
+    pub first_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+    pub window_secs: u64,
+}
+
+/// Audit payload summarizing a batch of scanned documents
+///
+/// Documents are referenced by id and content hash; their text is never recorded.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DocumentScanEvent {
+    pub correlation_id: String,
+    /// Always "document_scan"; distinguishes these records from prompt events
+    pub event_type: String,
+    /// Aggregate verdict for the batch
+    pub verdict: String,
+    /// Content hash of the caller-supplied context, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_hash: Option<String>,
+    pub documents: Vec<DocumentAuditEntry>,
+}
+
+/// One document of a [`DocumentScanEvent`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DocumentAuditEntry {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub content_hash: String,
+    pub verdict: String,
+    pub matched_rules: Vec<String>,
+    pub semantic_risk_score: Option<f32>,
+    pub semantic_template_id: Option<String>,
+}
+
+/// How much of each audit payload is stored
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditWriteMode {
+    #[default]
+    Full,
+    /// Payloads are replaced by a [`HashOnlyRecord`], so the chain continues while
+    /// bulk content is dropped
+    HashOnly,
+}
+
+/// What is stored in place of a payload while audit writes are hash-only
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HashOnlyRecord {
+    pub correlation_id: String,
+    /// Always `hash_only`; marks a record whose content was dropped
+    pub audit_mode: AuditWriteMode,
+    /// `event_type` of the dropped payload; prompt events have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+    /// `final_status` of a dropped prompt event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_status: Option<String>,
+    /// `sha256:` hash of the dropped payload
+    pub payload_hash: String,
+    /// Length of the dropped payload in bytes
+    pub payload_bytes: usize,
+}
+
+impl HashOnlyRecord {
+    fn of(correlation_id: &str, payload: &str) -> Self {
+        let fields: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(payload).unwrap_or_default();
+        let field = |name: &str| fields.get(name).and_then(|v| v.as_str()).map(str::to_owned);
+        Self {
+            correlation_id: correlation_id.to_owned(),
+            audit_mode: AuditWriteMode::HashOnly,
+            event_type: field("event_type"),
+            final_status: field("final_status"),
+            payload_hash: format!("sha256:{}", hash_record(payload)),
+            payload_bytes: payload.len(),
+        }
+    }
+}
+
+/// Escape the control characters `serde_json` leaves raw, DEL and the C1 range, so a
+/// payload printed to a terminal or copied into an export cannot carry escape sequences
+///
+/// Payloads are compact JSON, where control characters can only occur inside strings,
+/// so the escaped payload still decodes to the same event.
+fn escape_control_characters(payload: String) -> String {
+    if !payload.chars().any(char::is_control) {
+        return payload;
+    }
+    let mut escaped = String::with_capacity(payload.len() + 8);
+    for ch in payload.chars() {
+        if ch.is_control() {
+            escaped.push_str(&format!("\\u{:04x}", u32::from(ch)));
+        } else {
+            escaped.push(ch);
+        }
+    }
+    escaped
 }
 
 #[derive(Clone)]
 pub struct AuditLogger {
     storage: Arc<dyn AuditStorage>,
+    /// Held from reading the chain head until the record extending it is stored
+    append_lock: Arc<Mutex<()>>,
+    /// Set while records are written hash-only; shared by every clone
+    hash_only: Arc<AtomicBool>,
+    /// Open windows of repeated synthetic events, when suppression is on
+    suppressor: Option<Arc<RepeatSuppressor>>,
 }
 
 impl AuditLogger {
     pub fn new(storage: Arc<dyn AuditStorage>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            append_lock: Arc::new(Mutex::new(())),
+            hash_only: Arc::default(),
+            suppressor: None,
+        }
+    }
+
+    /// Collapse repeated synthetic events as `policy` says; a disabled policy stores all
+    pub fn with_suppression(mut self, policy: AuditSuppressionPolicy) -> Self {
+        self.suppressor = policy
+            .is_enabled()
+            .then(|| Arc::new(RepeatSuppressor::new(policy)));
+        self
+    }
+
+    pub fn suppression(&self) -> Option<&AuditSuppressionPolicy> {
+        self.suppressor.as_deref().map(RepeatSuppressor::policy)
+    }
+
+    pub fn write_mode(&self) -> AuditWriteMode {
+        if self.hash_only.load(Ordering::Relaxed) {
+            AuditWriteMode::HashOnly
+        } else {
+            AuditWriteMode::Full
+        }
+    }
+
+    /// Switch every clone of this logger to `mode` from the next record on
+    pub fn set_write_mode(&self, mode: AuditWriteMode) {
+        self.hash_only
+            .store(mode == AuditWriteMode::HashOnly, Ordering::Relaxed);
+    }
+
+    /// Record a decision, or count it when it repeats a synthetic event
+    ///
+    /// A counted repeat is not written and gets a proof with the `suppressed` algorithm
+    /// and no hashes.
+    pub async fn log_event(&self, event: AuditEvent) -> Result<AuditProof, AuditError> {
+        if let Some(suppressor) = &self.suppressor {
+            let observation = suppressor.observe(&event, Utc::now());
+            if !observation.closed.is_empty() {
+                let logger = self.clone();
+                tokio::task::spawn_blocking(move || logger.log_suppressed(observation.closed))
+                    .await
+                    .map_err(|e| AuditError::Task(e.to_string()))??;
+            }
+            if let Some(rule) = observation.suppressed {
+                get_metrics().increment_audit_suppressed_repeats(rule.as_str());
+                return Ok(AuditProof {
+                    algorithm: "suppressed".to_owned(),
+                    record_hash: String::new(),
+                    chain_hash: String::new(),
+                });
+            }
+        }
+        let payload = serde_json::to_string(&event)?;
+        self.append_off_runtime(event.correlation_id, payload).await
+    }
+
+    /// Write the aggregates of suppression windows that have ended, on the calling thread
+    pub fn close_suppression_windows(&self) -> Result<(), AuditError> {
+        match &self.suppressor {
+            Some(suppressor) => self.log_suppressed(suppressor.close_expired(Utc::now())),
+            None => Ok(()),
+        }
     }
 
-    pub fn log_event(&self, event: AuditEvent) -> Result<AuditProof, AuditError> {
+    /// Close ended suppression windows once per window length until the runtime shuts
+    /// down, so the repeats of traffic that stopped are recorded; `None` while off
+    pub fn spawn_suppression_sweeper(&self) -> Option<JoinHandle<()>> {
+        let window_secs = self.suppression()?.window_secs;
+        let logger = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(window_secs));
+            loop {
+                ticker.tick().await;
+                let sweeping = logger.clone();
+                let closed =
+                    tokio::task::spawn_blocking(move || sweeping.close_suppression_windows())
+                        .await
+                        .map_err(|e| AuditError::Task(e.to_string()))
+                        .and_then(|closed| closed);
+                if let Err(e) = closed {
+                    warn!("Recording suppressed audit repeats failed: {e}");
+                }
+            }
+        }))
+    }
+
+    fn log_suppressed(&self, aggregates: Vec<SuppressedRepeatsEvent>) -> Result<(), AuditError> {
+        for aggregate in aggregates {
+            let payload = serde_json::to_string(&aggregate)?;
+            self.append(aggregate.correlation_id, payload)?;
+        }
+        Ok(())
+    }
+
+    /// Record a configuration change on the calling thread
+    ///
+    /// Stays synchronous because callers audit the change while holding the locks that
+    /// apply it; these are rare admin operations.
+    pub fn log_config_change(&self, event: ConfigChangeEvent) -> Result<AuditProof, AuditError> {
+        let payload = serde_json::to_string(&event)?;
+        self.append(event.correlation_id, payload)
+    }
+
+    /// Record an exemption change on the calling thread, like configuration changes
+    pub fn log_exemption(&self, event: ExemptionEvent) -> Result<AuditProof, AuditError> {
+        let payload = serde_json::to_string(&event)?;
+        self.append(event.correlation_id, payload)
+    }
+
+    /// Record an appeal transition on the calling thread, like exemption changes
+    pub fn log_appeal(&self, event: AppealEvent) -> Result<AuditProof, AuditError> {
+        let payload = serde_json::to_string(&event)?;
+        self.append(event.correlation_id, payload)
+    }
+
+    /// Record an abandoned request on the calling thread
+    ///
+    /// The pipeline notices abandonment while its future is dropped, where nothing can
+    /// be awaited; it hands this to the blocking pool itself.
+    pub fn log_abandonment(&self, event: AbandonmentEvent) -> Result<AuditProof, AuditError> {
         let payload = serde_json::to_string(&event)?;
+        self.append(event.correlation_id, payload)
+    }
+
+    pub async fn log_document_scan(
+        &self,
+        event: DocumentScanEvent,
+    ) -> Result<AuditProof, AuditError> {
+        let payload = serde_json::to_string(&event)?;
+        self.append_off_runtime(event.correlation_id, payload).await
+    }
+
+    pub async fn log_replay(&self, event: ReplayEvent) -> Result<AuditProof, AuditError> {
+        let payload = serde_json::to_string(&event)?;
+        self.append_off_runtime(event.correlation_id, payload).await
+    }
+
+    /// Append on the blocking pool so storage I/O such as sled flushes never stalls a
+    /// runtime worker. The write completes even if the caller stops waiting for it.
+    async fn append_off_runtime(
+        &self,
+        correlation_id: String,
+        payload: String,
+    ) -> Result<AuditProof, AuditError> {
+        let logger = self.clone();
+        tokio::task::spawn_blocking(move || logger.append(correlation_id, payload))
+            .await
+            .map_err(|e| AuditError::Task(e.to_string()))?
+    }
+
+    fn append(&self, correlation_id: String, payload: String) -> Result<AuditProof, AuditError> {
+        let mut payload = escape_control_characters(payload);
+        if self.write_mode() == AuditWriteMode::HashOnly {
+            payload = serde_json::to_string(&HashOnlyRecord::of(&correlation_id, &payload))?;
+        }
+        let _guard = self
+            .append_lock
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
         let record_hash = hash_record(&payload);
         let previous_chain = self.storage.latest_chain_hash()?;
         let chain_hash = chain_hash(previous_chain.as_deref(), &record_hash);
@@ -71,7 +667,7 @@ impl AuditLogger {
         };
 
         let record = StoredAuditRecord {
-            correlation_id: event.correlation_id,
+            correlation_id,
             timestamp: Utc::now(),
             payload,
             proof: proof.clone(),
@@ -85,6 +681,15 @@ impl AuditLogger {
         self.storage.all().map_err(Into::into)
     }
 
+    /// Write out records the storage has queued, and the repeats counted in open
+    /// suppression windows; blocks until they are stored
+    pub fn flush(&self) -> Result<(), AuditError> {
+        if let Some(suppressor) = &self.suppressor {
+            self.log_suppressed(suppressor.close_all(Utc::now()))?;
+        }
+        self.storage.flush().map_err(Into::into)
+    }
+
     pub fn storage(&self) -> &Arc<dyn AuditStorage> {
         &self.storage
     }
@@ -96,4 +701,6 @@ pub enum AuditError {
     Serialization(#[from] serde_json::Error),
     #[error("audit storage failure: {0}")]
     Storage(#[from] AuditStorageError),
+    #[error("audit write task failed: {0}")]
+    Task(String),
 }
@@ -1,3 +1,13 @@
+pub mod batched;
+pub mod compression;
+pub mod disk;
+pub mod encryption;
+pub mod integrity;
 pub mod logger;
 pub mod proof;
+#[cfg(feature = "sled-storage")]
+pub mod sled;
+#[cfg(feature = "sqlite-storage")]
+pub mod sqlite;
 pub mod storage;
+pub mod suppression;
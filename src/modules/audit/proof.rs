@@ -14,6 +14,11 @@ pub fn hash_record(payload: &str) -> String {
     hex::encode(hasher.finalize())
 }
 
+/// SHA-256 of the canonical JSON encoding of `value`
+pub fn content_hash<T: Serialize>(value: &T) -> String {
+    hash_record(&serde_json::to_string(value).unwrap_or_default())
+}
+
 pub fn chain_hash(previous_chain_hash: Option<&str>, record_hash: &str) -> String {
     let mut hasher = Sha256::new();
     if let Some(previous) = previous_chain_hash {
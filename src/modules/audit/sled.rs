@@ -0,0 +1,632 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::{Db, IVec, Tree};
+
+use super::compression::{self, AuditCompression};
+use super::encryption::{self, AuditEncryptionKey};
+use super::storage::{
+    AuditStorage, AuditStorageError, AuditTrailResponse, StoredAuditRecord, decode_cursor,
+    encode_cursor,
+};
+
+/// Records encrypted per batch while rotating keys
+const ROTATION_BATCH_SIZE: usize = 500;
+/// Records compressed per batch by [`SledAuditStorage::recompress`]
+const RECOMPRESSION_BATCH_SIZE: usize = 500;
+
+/// `{correlation_id}\0{record_key}` for every record, so lookups by correlation id skip
+/// the rest of the trail
+const CORRELATION_INDEX_TREE: &str = "audit_correlation_index";
+/// Present once the index covers records written before it existed
+const INDEX_COMPLETE_KEY: &[u8] = b"\xffcomplete";
+
+#[derive(Clone)]
+pub struct SledAuditStorage {
+    db: Db,
+    index: Tree,
+    keys: Arc<RwLock<AuditKeys>>,
+    compression: AuditCompression,
+}
+
+/// Key new records are sealed with, plus keys still needed to read older ones
+#[derive(Default)]
+struct AuditKeys {
+    current: Option<AuditEncryptionKey>,
+    retired: Vec<AuditEncryptionKey>,
+}
+
+impl AuditKeys {
+    fn find(&self, key_id: &str) -> Option<&AuditEncryptionKey> {
+        self.current
+            .iter()
+            .chain(self.retired.iter())
+            .find(|key| key.key_id() == key_id)
+    }
+}
+
+/// Progress of [`SledAuditStorage::rotate_key`]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct KeyRotationReport {
+    /// Records moved from the old key to the new one
+    pub reencrypted: usize,
+    /// Plaintext records written before encryption was enabled, now encrypted
+    pub encrypted_plaintext: usize,
+    /// Records already under the new key
+    pub already_current: usize,
+    /// Records still to rotate; non-zero only when the run was capped
+    pub remaining: usize,
+}
+
+/// Progress of [`SledAuditStorage::recompress`]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RecompressionReport {
+    /// Records compressed by this run
+    pub compressed: usize,
+    /// Records that were already compressed
+    pub already_compressed: usize,
+    /// Records at or below the threshold, left as they are
+    pub below_threshold: usize,
+    /// Records still to compress; non-zero only when the run was capped
+    pub remaining: usize,
+}
+
+impl SledAuditStorage {
+    pub fn new(db_path: &str) -> Result<Self, AuditStorageError> {
+        let db =
+            sled::open(db_path).map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+        Self::from_db(db)
+    }
+
+    /// Store audit records in an already opened database
+    ///
+    /// Records written before the correlation id index existed are indexed here.
+    pub fn from_db(db: Db) -> Result<Self, AuditStorageError> {
+        let index = db
+            .open_tree(CORRELATION_INDEX_TREE)
+            .map_err(database_error)?;
+        let storage = Self {
+            db,
+            index,
+            keys: Arc::default(),
+            compression: AuditCompression::disabled(),
+        };
+        storage.build_index()?;
+        Ok(storage)
+    }
+
+    fn build_index(&self) -> Result<(), AuditStorageError> {
+        if self
+            .index
+            .contains_key(INDEX_COMPLETE_KEY)
+            .map_err(database_error)?
+        {
+            return Ok(());
+        }
+        let mut batch = sled::Batch::default();
+        for key in self.db.iter().keys() {
+            let key = key.map_err(database_error)?;
+            if let Some((_, correlation_id)) = parse_key(&key) {
+                batch.insert(index_key(correlation_id, &key), &[]);
+            }
+        }
+        batch.insert(INDEX_COMPLETE_KEY, &[]);
+        self.index.apply_batch(batch).map_err(database_error)?;
+        self.index.flush().map_err(database_error)?;
+        Ok(())
+    }
+
+    /// Encrypt records with AES-256-GCM before they are written
+    ///
+    /// Plaintext records already in the database stay readable. Record hashes are
+    /// computed over the plaintext payload, so chain verification is unchanged.
+    pub fn with_encryption(self, key: AuditEncryptionKey) -> Self {
+        self.keys.write().unwrap().current = Some(key);
+        self
+    }
+
+    /// Compress records whose JSON encoding is longer than the threshold before they are
+    /// written
+    ///
+    /// Uncompressed records already in the database stay readable. Record hashes are
+    /// computed over the uncompressed payload, so chain verification is unchanged.
+    pub fn with_compression(mut self, compression: AuditCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Fail unless the newest encrypted record can be decrypted with the configured key
+    ///
+    /// Call at startup so a wrong or missing key is reported up front rather than on the
+    /// first audit trail read.
+    pub fn verify_key(&self) -> Result<(), AuditStorageError> {
+        for entry in self.db.iter().values().rev() {
+            let data = entry.map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+            if encryption::is_encrypted(&data) {
+                self.decode(&data)?;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-encrypt every record under `new`, which also becomes the key for new records
+    ///
+    /// Works in batches and skips records already under `new`, so an interrupted rotation
+    /// is resumed by calling it again with the same keys. Plaintext records are encrypted
+    /// along the way.
+    pub fn rotate_key(
+        &self,
+        old: &AuditEncryptionKey,
+        new: &AuditEncryptionKey,
+    ) -> Result<KeyRotationReport, AuditStorageError> {
+        self.rotate_key_limited(old, new, usize::MAX)
+    }
+
+    /// [`rotate_key`](Self::rotate_key) that stops after re-encrypting `max_records`
+    pub fn rotate_key_limited(
+        &self,
+        old: &AuditEncryptionKey,
+        new: &AuditEncryptionKey,
+        max_records: usize,
+    ) -> Result<KeyRotationReport, AuditStorageError> {
+        {
+            let mut keys = self.keys.write().unwrap();
+            let previous = keys.current.replace(new.clone());
+            for key in previous.into_iter().chain([old.clone()]) {
+                if key.key_id() != new.key_id() && keys.find(&key.key_id()).is_none() {
+                    keys.retired.push(key);
+                }
+            }
+        }
+
+        let mut report = KeyRotationReport::default();
+        let mut batch = sled::Batch::default();
+        let mut batched = 0;
+        for entry in self.db.iter() {
+            let (key, data) = entry.map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+            let plaintext = match encryption::envelope_key_id(&data) {
+                Some(key_id) if key_id == new.key_id() => {
+                    report.already_current += 1;
+                    continue;
+                }
+                Some(key_id) if key_id != old.key_id() => {
+                    return Err(AuditStorageError::WrongEncryptionKey {
+                        record_key: key_id,
+                        configured: old.key_id(),
+                    });
+                }
+                _ if report.reencrypted + report.encrypted_plaintext >= max_records => {
+                    report.remaining += 1;
+                    continue;
+                }
+                Some(_) => {
+                    report.reencrypted += 1;
+                    old.decrypt(&data)?
+                }
+                None => {
+                    report.encrypted_plaintext += 1;
+                    data.to_vec()
+                }
+            };
+            batch.insert(key, new.encrypt(&plaintext)?);
+            batched += 1;
+            if batched == ROTATION_BATCH_SIZE {
+                self.apply_and_flush(std::mem::take(&mut batch))?;
+                batched = 0;
+            }
+        }
+        self.apply_and_flush(batch)?;
+
+        if report.remaining == 0 {
+            self.keys
+                .write()
+                .unwrap()
+                .retired
+                .retain(|key| key.key_id() != old.key_id());
+        }
+        Ok(report)
+    }
+
+    /// Compress every record written before compression was enabled that is above the
+    /// threshold
+    ///
+    /// Works in batches and skips records already compressed, so an interrupted run is
+    /// resumed by calling it again. Rewritten records are encrypted when a key is
+    /// configured.
+    pub fn recompress(&self) -> Result<RecompressionReport, AuditStorageError> {
+        self.recompress_limited(usize::MAX)
+    }
+
+    /// [`recompress`](Self::recompress) that stops after compressing `max_records`
+    pub fn recompress_limited(
+        &self,
+        max_records: usize,
+    ) -> Result<RecompressionReport, AuditStorageError> {
+        let mut report = RecompressionReport::default();
+        let mut batch = sled::Batch::default();
+        let mut batched = 0;
+        for entry in self.db.iter() {
+            let (key, data) = entry.map_err(database_error)?;
+            let stored = self.unseal(&data)?;
+            if compression::is_compressed(&stored) {
+                report.already_compressed += 1;
+                continue;
+            }
+            if !self.compression.applies_to(stored.len()) {
+                report.below_threshold += 1;
+                continue;
+            }
+            if report.compressed >= max_records {
+                report.remaining += 1;
+                continue;
+            }
+            report.compressed += 1;
+            batch.insert(key, self.encode(&stored)?);
+            batched += 1;
+            if batched == RECOMPRESSION_BATCH_SIZE {
+                self.apply_and_flush(std::mem::take(&mut batch))?;
+                batched = 0;
+            }
+        }
+        self.apply_and_flush(batch)?;
+        Ok(report)
+    }
+
+    /// Key and stored bytes of `record`
+    fn entry(&self, record: &StoredAuditRecord) -> Result<(String, Vec<u8>), AuditStorageError> {
+        let serialized = serde_json::to_string(record)
+            .map_err(|e| AuditStorageError::SerializationError(e.to_string()))?;
+
+        // Use timestamp-prefixed key for chronological ordering
+        // Format: {timestamp_nanos}_{correlation_id}
+        let key = format!(
+            "{:020}_{}",
+            timestamp_nanos(&record.timestamp),
+            record.correlation_id
+        );
+        Ok((key, self.encode(serialized.as_bytes())?))
+    }
+
+    /// Keys of the records matching `filter` from `lower` on, in trail order
+    ///
+    /// With a correlation id only its index entries are read, not the whole trail.
+    fn matching_keys<'a>(
+        &'a self,
+        filter: &'a KeyFilter<'a>,
+        lower: Bound<Vec<u8>>,
+    ) -> impl Iterator<Item = Result<IVec, AuditStorageError>> + 'a {
+        let keys: Box<dyn Iterator<Item = Result<IVec, AuditStorageError>> + 'a> =
+            match filter.correlation_id {
+                Some(correlation_id) => {
+                    let prefix = index_key(correlation_id, b"");
+                    let start = prefix.len();
+                    // An entry written just before its record failed to store points nowhere
+                    Box::new(
+                        self.index
+                            .scan_prefix(prefix)
+                            .keys()
+                            .map(move |entry| {
+                                entry
+                                    .map(|entry| IVec::from(&entry[start..]))
+                                    .map_err(database_error)
+                            })
+                            .filter(move |key| match key {
+                                Ok(key) => {
+                                    within(&lower, key) && self.db.contains_key(key).unwrap_or(true)
+                                }
+                                Err(_) => true,
+                            }),
+                    )
+                }
+                None => Box::new(
+                    self.db
+                        .range((lower, Bound::Unbounded))
+                        .keys()
+                        .map(|key| key.map_err(database_error)),
+                ),
+            };
+        keys.map_while(|key| match key {
+            Err(e) => Some(Some(Err(e))),
+            Ok(key) => match filter.check(&key) {
+                KeyMatch::Past => None,
+                KeyMatch::Skip => Some(None),
+                KeyMatch::Match => Some(Some(Ok(key))),
+            },
+        })
+        .flatten()
+    }
+
+    fn record(&self, key: &[u8]) -> Result<StoredAuditRecord, AuditStorageError> {
+        let data =
+            self.db.get(key).map_err(database_error)?.ok_or_else(|| {
+                AuditStorageError::DatabaseError("indexed record missing".to_owned())
+            })?;
+        self.decode(&data)
+    }
+
+    fn apply_and_flush(&self, batch: sled::Batch) -> Result<(), AuditStorageError> {
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Compressed when above the threshold, then encrypted when a key is configured
+    fn encode(&self, serialized: &[u8]) -> Result<Vec<u8>, AuditStorageError> {
+        let compressed = self.compression.compress(serialized)?;
+        match self.keys.read().unwrap().current.as_ref() {
+            Some(key) => Ok(key.encrypt(&compressed)?),
+            None => Ok(compressed.into_owned()),
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<StoredAuditRecord, AuditStorageError> {
+        let stored = self.unseal(data)?;
+        let serialized = compression::decompress(&stored)?;
+        serde_json::from_slice(&serialized)
+            .map_err(|e| AuditStorageError::SerializationError(e.to_string()))
+    }
+
+    /// `data` decrypted, or as it is when it is not encrypted
+    fn unseal<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>, AuditStorageError> {
+        match encryption::envelope_key_id(data) {
+            Some(key_id) => {
+                let keys = self.keys.read().unwrap();
+                let key = keys.find(&key_id).ok_or_else(|| match &keys.current {
+                    Some(current) => AuditStorageError::WrongEncryptionKey {
+                        record_key: key_id.clone(),
+                        configured: current.key_id(),
+                    },
+                    None => AuditStorageError::EncryptionKeyMissing,
+                })?;
+                Ok(Cow::Owned(key.decrypt(data)?))
+            }
+            None => Ok(Cow::Borrowed(data)),
+        }
+    }
+}
+
+impl AuditStorage for SledAuditStorage {
+    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
+        let (key, value) = self.entry(&record)?;
+        // Indexed first: an entry without its record is skipped, a record without its
+        // entry could not be found by correlation id
+        self.index
+            .insert(index_key(&record.correlation_id, key.as_bytes()), &[])
+            .map_err(database_error)?;
+        self.db
+            .insert(key, value)
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+
+        self.db
+            .flush()
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// All records in one atomic sled batch and a single flush
+    fn append_batch(&self, records: &[StoredAuditRecord]) -> Result<(), AuditStorageError> {
+        let mut batch = sled::Batch::default();
+        let mut index = sled::Batch::default();
+        for record in records {
+            let (key, value) = self.entry(record)?;
+            index.insert(index_key(&record.correlation_id, key.as_bytes()), &[]);
+            batch.insert(key.into_bytes(), value);
+        }
+        self.index.apply_batch(index).map_err(database_error)?;
+        self.apply_and_flush(batch)
+    }
+
+    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
+        // Iterate in reverse to get the chronologically latest record
+        let last_record = self
+            .db
+            .iter()
+            .next_back()
+            .transpose()
+            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+
+        match last_record {
+            Some((_, data)) => Ok(Some(self.decode(&data)?.proof.chain_hash)),
+            None => Ok(None),
+        }
+    }
+
+    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let mut records = Vec::new();
+
+        for result in self.db.iter() {
+            let (_, data) = result.map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
+            records.push(self.decode(&data)?);
+        }
+
+        Ok(records)
+    }
+
+    /// Read backwards from the newest key, so only `limit` records are decoded
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let mut records = self
+            .db
+            .iter()
+            .values()
+            .rev()
+            .take(limit)
+            .map(|data| self.decode(&data.map_err(database_error)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        records.reverse();
+        Ok(records)
+    }
+
+    fn get_with_filters(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        let filter = KeyFilter::new(start_time, end_time, correlation_id.as_deref());
+
+        // Keys carry the timestamp and correlation id, so only the page is decoded
+        let mut total_count = 0;
+        let mut records = Vec::new();
+        let mut last_key = None;
+        for key in self.matching_keys(&filter, filter.lower_bound()) {
+            let key = key?;
+            if total_count >= offset && records.len() < limit {
+                records.push(self.record(&key)?);
+                last_key = Some(key);
+            }
+            total_count += 1;
+        }
+
+        let next_cursor = last_key
+            .filter(|_| offset + records.len() < total_count)
+            .map(|key| encode_cursor(&key));
+        Ok(AuditTrailResponse {
+            records,
+            total_count,
+            limit,
+            offset,
+            next_cursor,
+            suppressed_repeats: 0,
+        })
+    }
+
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        let limit = limit.unwrap_or(100);
+        let filter = KeyFilter::new(start_time, end_time, correlation_id.as_deref());
+        let lower = match cursor {
+            Some(cursor) => {
+                let key = decode_cursor(cursor)?;
+                if parse_key(&key).is_none() {
+                    return Err(AuditStorageError::InvalidCursor);
+                }
+                Bound::Excluded(key)
+            }
+            None => filter.lower_bound(),
+        };
+
+        let mut total_count = 0;
+        for key in self.matching_keys(&filter, filter.lower_bound()) {
+            key?;
+            total_count += 1;
+        }
+
+        let mut records = Vec::new();
+        let mut last_key = None;
+        let mut more = false;
+        for key in self.matching_keys(&filter, lower) {
+            let key = key?;
+            if records.len() == limit {
+                more = true;
+                break;
+            }
+            records.push(self.record(&key)?);
+            last_key = Some(key);
+        }
+
+        Ok(AuditTrailResponse {
+            records,
+            total_count,
+            limit,
+            offset: 0,
+            next_cursor: last_key.filter(|_| more).map(|key| encode_cursor(&key)),
+            suppressed_repeats: 0,
+        })
+    }
+}
+
+fn database_error(e: sled::Error) -> AuditStorageError {
+    AuditStorageError::DatabaseError(e.to_string())
+}
+
+/// Whether `key` is at or past `lower`
+fn within(lower: &Bound<Vec<u8>>, key: &[u8]) -> bool {
+    match lower {
+        Bound::Included(bound) => key >= bound.as_slice(),
+        Bound::Excluded(bound) => key > bound.as_slice(),
+        Bound::Unbounded => true,
+    }
+}
+
+fn index_key(correlation_id: &str, record_key: &[u8]) -> Vec<u8> {
+    [correlation_id.as_bytes(), b"\0", record_key].concat()
+}
+
+fn timestamp_nanos(timestamp: &DateTime<Utc>) -> i64 {
+    timestamp.timestamp_nanos_opt().unwrap_or(0)
+}
+
+/// Timestamp and correlation id of a `{timestamp_nanos}_{correlation_id}` record key
+fn parse_key(key: &[u8]) -> Option<(i64, &str)> {
+    let (nanos, correlation_id) = std::str::from_utf8(key).ok()?.split_once('_')?;
+    Some((nanos.parse().ok()?, correlation_id))
+}
+
+/// Audit trail filters checked against record keys, before records are decoded
+struct KeyFilter<'a> {
+    start: Option<i64>,
+    end: Option<i64>,
+    correlation_id: Option<&'a str>,
+}
+
+enum KeyMatch {
+    Match,
+    Skip,
+    /// Past the end of the time range; no later key can match
+    Past,
+}
+
+impl<'a> KeyFilter<'a> {
+    fn new(
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<&'a str>,
+    ) -> Self {
+        Self {
+            start: start_time.as_ref().map(timestamp_nanos),
+            end: end_time.as_ref().map(timestamp_nanos),
+            correlation_id,
+        }
+    }
+
+    fn lower_bound(&self) -> Bound<Vec<u8>> {
+        match self.start {
+            Some(start) if start >= 0 => Bound::Included(format!("{start:020}").into_bytes()),
+            _ => Bound::Unbounded,
+        }
+    }
+
+    fn check(&self, key: &[u8]) -> KeyMatch {
+        let Some((nanos, correlation_id)) = parse_key(key) else {
+            return KeyMatch::Skip;
+        };
+        if self.end.is_some_and(|end| nanos > end) {
+            KeyMatch::Past
+        } else if self.start.is_some_and(|start| nanos < start)
+            || self.correlation_id.is_some_and(|id| id != correlation_id)
+        {
+            KeyMatch::Skip
+        } else {
+            KeyMatch::Match
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::{Type, Value};
+use rusqlite::{Connection, OptionalExtension, Row, TransactionBehavior, params};
+
+use super::proof::{AuditProof, chain_hash};
+use super::storage::{
+    AuditStorage, AuditStorageError, AuditTrailResponse, StoredAuditRecord, decode_numeric_cursor,
+    encode_cursor,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audit_records (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    correlation_id TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    timestamp_nanos INTEGER NOT NULL,
+    final_status TEXT,
+    payload TEXT NOT NULL,
+    algorithm TEXT NOT NULL,
+    record_hash TEXT NOT NULL,
+    chain_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_records_correlation_id ON audit_records (correlation_id);
+CREATE INDEX IF NOT EXISTS audit_records_timestamp ON audit_records (timestamp_nanos);
+CREATE INDEX IF NOT EXISTS audit_records_final_status ON audit_records (final_status);
+";
+
+const SELECT_COLUMNS: &str = "SELECT correlation_id, timestamp, payload, algorithm, record_hash, chain_hash, seq FROM audit_records";
+
+/// Audit storage in a SQLite database, for deployments that query or replicate it with
+/// standard SQLite tooling
+///
+/// Records are kept in insertion order (`seq`), which is also chain order. A single
+/// connection behind a mutex serialises writers; the database runs in WAL mode so
+/// external readers are not blocked.
+pub struct SqliteAuditStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteAuditStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditStorageError> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        Self::with_connection(conn)
+    }
+
+    /// Private database that disappears with the storage, mainly for tests
+    pub fn in_memory() -> Result<Self, AuditStorageError> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, AuditStorageError> {
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl AuditStorage for SqliteAuditStorage {
+    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        // IMMEDIATE takes the write lock up front, so the chain head read below cannot be
+        // changed by another process before the insert commits
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(db_error)?;
+
+        let previous = latest_chain_hash(&tx)?;
+        let expected = chain_hash(previous.as_deref(), &record.proof.record_hash);
+        if expected != record.proof.chain_hash {
+            return Err(AuditStorageError::ChainConflict);
+        }
+
+        tx.execute(
+            "INSERT INTO audit_records (correlation_id, timestamp, timestamp_nanos, final_status, \
+             payload, algorithm, record_hash, chain_hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.correlation_id,
+                record.timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true),
+                timestamp_nanos(&record.timestamp),
+                final_status(&record.payload),
+                record.payload,
+                record.proof.algorithm,
+                record.proof.record_hash,
+                record.proof.chain_hash,
+            ],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        latest_chain_hash(&conn)
+    }
+
+    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        query_records(&conn, &format!("{SELECT_COLUMNS} ORDER BY seq"), Vec::new())
+    }
+
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        let mut records = query_records(
+            &conn,
+            &format!("{SELECT_COLUMNS} ORDER BY seq DESC LIMIT ?"),
+            vec![Value::Integer(i64::try_from(limit).unwrap_or(i64::MAX))],
+        )?;
+        records.reverse();
+        Ok(records)
+    }
+
+    fn get_with_filters(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        let (where_clause, mut values) = filter_clause(start_time, end_time, correlation_id, None);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        let total_count = count_records(&conn, &where_clause, &values)?;
+
+        values.push(Value::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+        values.push(Value::Integer(i64::try_from(offset).unwrap_or(i64::MAX)));
+        let rows = query_rows(
+            &conn,
+            &format!("{SELECT_COLUMNS}{where_clause} ORDER BY seq LIMIT ? OFFSET ?"),
+            values,
+        )?;
+
+        let next_cursor = rows
+            .last()
+            .filter(|_| offset + rows.len() < total_count)
+            .map(|(seq, _)| encode_cursor(seq.to_string().as_bytes()));
+        Ok(AuditTrailResponse {
+            records: rows.into_iter().map(|(_, record)| record).collect(),
+            total_count,
+            limit,
+            offset,
+            next_cursor,
+            suppressed_repeats: 0,
+        })
+    }
+
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        let limit = limit.unwrap_or(100);
+        let after = cursor
+            .map(|cursor| {
+                decode_numeric_cursor(cursor).and_then(|seq| {
+                    i64::try_from(seq).map_err(|_| AuditStorageError::InvalidCursor)
+                })
+            })
+            .transpose()?;
+        let (where_clause, values) =
+            filter_clause(start_time, end_time, correlation_id.clone(), None);
+        let (page_clause, mut page_values) =
+            filter_clause(start_time, end_time, correlation_id, after);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        let total_count = count_records(&conn, &where_clause, &values)?;
+
+        // One extra row tells whether another page follows
+        page_values.push(Value::Integer(
+            i64::try_from(limit).unwrap_or(i64::MAX).saturating_add(1),
+        ));
+        let mut rows = query_rows(
+            &conn,
+            &format!("{SELECT_COLUMNS}{page_clause} ORDER BY seq LIMIT ?"),
+            page_values,
+        )?;
+        let more = rows.len() > limit;
+        rows.truncate(limit);
+
+        let next_cursor = rows
+            .last()
+            .filter(|_| more)
+            .map(|(seq, _)| encode_cursor(seq.to_string().as_bytes()));
+        Ok(AuditTrailResponse {
+            records: rows.into_iter().map(|(_, record)| record).collect(),
+            total_count,
+            limit,
+            offset: 0,
+            next_cursor,
+            suppressed_repeats: 0,
+        })
+    }
+}
+
+/// `WHERE` clause and its values for the audit trail filters, optionally only records
+/// after sequence number `after_seq`
+fn filter_clause(
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    correlation_id: Option<String>,
+    after_seq: Option<i64>,
+) -> (String, Vec<Value>) {
+    let mut clauses = Vec::new();
+    let mut values = Vec::new();
+    if let Some(start) = start_time {
+        clauses.push("timestamp_nanos >= ?");
+        values.push(Value::Integer(timestamp_nanos(&start)));
+    }
+    if let Some(end) = end_time {
+        clauses.push("timestamp_nanos <= ?");
+        values.push(Value::Integer(timestamp_nanos(&end)));
+    }
+    if let Some(cid) = correlation_id {
+        clauses.push("correlation_id = ?");
+        values.push(Value::Text(cid));
+    }
+    if let Some(seq) = after_seq {
+        clauses.push("seq > ?");
+        values.push(Value::Integer(seq));
+    }
+    let where_clause = if clauses.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", clauses.join(" AND "))
+    };
+    (where_clause, values)
+}
+
+fn count_records(
+    conn: &Connection,
+    where_clause: &str,
+    values: &[Value],
+) -> Result<usize, AuditStorageError> {
+    let count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM audit_records{where_clause}"),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    Ok(count as usize)
+}
+
+fn latest_chain_hash(conn: &Connection) -> Result<Option<String>, AuditStorageError> {
+    conn.query_row(
+        "SELECT chain_hash FROM audit_records ORDER BY seq DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+fn query_records(
+    conn: &Connection,
+    sql: &str,
+    values: Vec<Value>,
+) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+    let rows = query_rows(conn, sql, values)?;
+    Ok(rows.into_iter().map(|(_, record)| record).collect())
+}
+
+/// Records with their sequence numbers
+fn query_rows(
+    conn: &Connection,
+    sql: &str,
+    values: Vec<Value>,
+) -> Result<Vec<(i64, StoredAuditRecord)>, AuditStorageError> {
+    let mut statement = conn.prepare(sql).map_err(db_error)?;
+    let rows = statement
+        .query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((row.get(6)?, read_record(row)?))
+        })
+        .map_err(db_error)?;
+    rows.map(|row| row.map_err(db_error)).collect()
+}
+
+fn read_record(row: &Row<'_>) -> rusqlite::Result<StoredAuditRecord> {
+    let timestamp: String = row.get(1)?;
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    Ok(StoredAuditRecord {
+        correlation_id: row.get(0)?,
+        timestamp,
+        payload: row.get(2)?,
+        proof: AuditProof {
+            algorithm: row.get(3)?,
+            record_hash: row.get(4)?,
+            chain_hash: row.get(5)?,
+        },
+    })
+}
+
+fn timestamp_nanos(timestamp: &DateTime<Utc>) -> i64 {
+    timestamp.timestamp_nanos_opt().unwrap_or(0)
+}
+
+/// Workflow outcome of prompt events, so investigations can filter on it in SQL
+fn final_status(payload: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()?
+        .get("final_status")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+fn db_error(error: rusqlite::Error) -> AuditStorageError {
+    AuditStorageError::DatabaseError(error.to_string())
+}
@@ -1,19 +1,49 @@
 use std::sync::{Arc, Mutex};
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sled::Db;
 use thiserror::Error;
 
+use super::compression::CompressionError;
+use super::encryption::EncryptionError;
 use super::proof::AuditProof;
+#[cfg(feature = "sled-storage")]
+pub use super::sled::{KeyRotationReport, RecompressionReport, SledAuditStorage};
+
+/// Deepest `offset` the audit trail endpoint accepts; page further with `cursor`
+pub const MAX_AUDIT_TRAIL_OFFSET: usize = 10_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditTrailRequest {
     pub limit: Option<usize>,
+    /// Records to skip. Appends between requests shift offset pages, so prefer `cursor`
     pub offset: Option<usize>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub correlation_id: Option<String>,
+    /// `next_cursor` of the previous page; the page starts after the record it names
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+impl AuditTrailRequest {
+    /// Reject offsets past [`MAX_AUDIT_TRAIL_OFFSET`] and requests mixing both paging modes
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cursor.is_some() && self.offset.is_some_and(|offset| offset > 0) {
+            return Err("pass either cursor or offset, not both".to_owned());
+        }
+        if self
+            .offset
+            .is_some_and(|offset| offset > MAX_AUDIT_TRAIL_OFFSET)
+        {
+            return Err(format!(
+                "offset must not exceed {MAX_AUDIT_TRAIL_OFFSET}; page with cursor instead"
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +52,17 @@ pub struct AuditTrailResponse {
     pub total_count: usize,
     pub limit: usize,
     pub offset: usize,
+    /// Pass as `cursor` to fetch the next page; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Repeats of synthetic events standing behind the `suppressed_repeats` records on
+    /// this page; they are not counted in `total_count`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub suppressed_repeats: u64,
+}
+
+fn is_zero(count: &u64) -> bool {
+    *count == 0
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -35,7 +76,35 @@ pub struct StoredAuditRecord {
 pub trait AuditStorage: Send + Sync {
     fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError>;
     fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError>;
+
+    /// Store `records` in order; backends override it to write them in one go
+    ///
+    /// Records before a failed one may already be stored when this returns an error.
+    fn append_batch(&self, records: &[StoredAuditRecord]) -> Result<(), AuditStorageError> {
+        records
+            .iter()
+            .try_for_each(|record| self.append(record.clone()))
+    }
+
+    /// Write out records accepted but not yet stored durably
+    ///
+    /// A no-op for backends that store every record before `append` returns.
+    fn flush(&self) -> Result<(), AuditStorageError> {
+        Ok(())
+    }
+
     fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError>;
+
+    /// The newest `limit` records, oldest first
+    ///
+    /// The default implementation reads [`all`](Self::all); backends override it to
+    /// read only the end of the trail.
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let mut records = self.all()?;
+        records.drain(..records.len().saturating_sub(limit));
+        Ok(records)
+    }
+
     fn get_with_filters(
         &self,
         limit: Option<usize>,
@@ -44,6 +113,132 @@ pub trait AuditStorage: Send + Sync {
         end_time: Option<DateTime<Utc>>,
         correlation_id: Option<String>,
     ) -> Result<AuditTrailResponse, AuditStorageError>;
+
+    /// Up to `limit` matching records after `cursor`, or from the start without one
+    ///
+    /// A cursor names the last record of the previous page, so records appended while a
+    /// client pages through the trail neither shift pages nor show up twice. The default
+    /// implementation positions by index into [`all`](Self::all); backends override it to
+    /// seek to the cursor instead of reading every record.
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        page_by_index(
+            &self.all()?,
+            cursor,
+            limit,
+            start_time,
+            end_time,
+            correlation_id.as_deref(),
+        )
+    }
+}
+
+/// Opaque cursor naming a backend position
+pub(crate) fn encode_cursor(position: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(position)
+}
+
+pub(crate) fn decode_cursor(cursor: &str) -> Result<Vec<u8>, AuditStorageError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| AuditStorageError::InvalidCursor)
+}
+
+/// Cursor position of a numbered record (index or sequence number)
+pub(crate) fn decode_numeric_cursor(cursor: &str) -> Result<u64, AuditStorageError> {
+    String::from_utf8(decode_cursor(cursor)?)
+        .ok()
+        .and_then(|position| position.parse().ok())
+        .ok_or(AuditStorageError::InvalidCursor)
+}
+
+pub(crate) fn record_matches(
+    record: &StoredAuditRecord,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    correlation_id: Option<&str>,
+) -> bool {
+    start_time.is_none_or(|start| record.timestamp >= start)
+        && end_time.is_none_or(|end| record.timestamp <= end)
+        && correlation_id.is_none_or(|cid| record.correlation_id == cid)
+}
+
+/// Cursor page over records kept in append order, positioned by index
+fn page_by_index(
+    records: &[StoredAuditRecord],
+    cursor: Option<&str>,
+    limit: Option<usize>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    correlation_id: Option<&str>,
+) -> Result<AuditTrailResponse, AuditStorageError> {
+    let limit = limit.unwrap_or(100);
+    let after = cursor.map(decode_numeric_cursor).transpose()?;
+    let matching = records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| record_matches(record, start_time, end_time, correlation_id));
+    let total_count = matching.clone().count();
+    let mut page =
+        matching.skip_while(|(index, _)| after.is_some_and(|after| *index as u64 <= after));
+    let selected: Vec<(usize, &StoredAuditRecord)> = page.by_ref().take(limit).collect();
+    let next_cursor = match (selected.last(), page.next()) {
+        (Some((index, _)), Some(_)) => Some(encode_cursor(index.to_string().as_bytes())),
+        _ => None,
+    };
+    Ok(AuditTrailResponse {
+        records: selected
+            .into_iter()
+            .map(|(_, record)| record.clone())
+            .collect(),
+        total_count,
+        limit,
+        offset: 0,
+        next_cursor,
+        suppressed_repeats: 0,
+    })
+}
+
+/// Offset page over records kept in append order; the cursor names the last record
+fn offset_page_by_index(
+    records: &[StoredAuditRecord],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    correlation_id: Option<&str>,
+) -> AuditTrailResponse {
+    let limit = limit.unwrap_or(100);
+    let offset = offset.unwrap_or(0);
+    let matching: Vec<(usize, &StoredAuditRecord)> = records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| record_matches(record, start_time, end_time, correlation_id))
+        .collect();
+    let total_count = matching.len();
+    let selected: Vec<(usize, &StoredAuditRecord)> =
+        matching.into_iter().skip(offset).take(limit).collect();
+    let next_cursor = selected
+        .last()
+        .filter(|_| offset + selected.len() < total_count)
+        .map(|(index, _)| encode_cursor(index.to_string().as_bytes()));
+    AuditTrailResponse {
+        records: selected
+            .into_iter()
+            .map(|(_, record)| record.clone())
+            .collect(),
+        total_count,
+        limit,
+        offset,
+        next_cursor,
+        suppressed_repeats: 0,
+    }
 }
 
 #[derive(Clone, Default)]
@@ -83,6 +278,14 @@ impl AuditStorage for InMemoryAuditStorage {
         Ok(guard.clone())
     }
 
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        Ok(guard[guard.len().saturating_sub(limit)..].to_vec())
+    }
+
     fn get_with_filters(
         &self,
         limit: Option<usize>,
@@ -91,46 +294,40 @@ impl AuditStorage for InMemoryAuditStorage {
         end_time: Option<DateTime<Utc>>,
         correlation_id: Option<String>,
     ) -> Result<AuditTrailResponse, AuditStorageError> {
-        let all_records = self.all()?;
-
-        // Apply time filters
-        let filtered_records: Vec<StoredAuditRecord> = all_records
-            .into_iter()
-            .filter(|record| {
-                let in_time_range = start_time
-                    .as_ref()
-                    .map(|start| record.timestamp >= *start)
-                    .unwrap_or(true)
-                    && end_time
-                        .as_ref()
-                        .map(|end| record.timestamp <= *end)
-                        .unwrap_or(true);
-
-                let matches_correlation = correlation_id
-                    .as_ref()
-                    .map(|cid| record.correlation_id == *cid)
-                    .unwrap_or(true);
-
-                in_time_range && matches_correlation
-            })
-            .collect();
-
-        // Apply pagination
-        let limit = limit.unwrap_or(100);
-        let offset = offset.unwrap_or(0);
-        let total_count = filtered_records.len();
-        let paginated_records: Vec<StoredAuditRecord> = filtered_records
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
-
-        Ok(AuditTrailResponse {
-            records: paginated_records,
-            total_count,
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        Ok(offset_page_by_index(
+            &guard,
             limit,
             offset,
-        })
+            start_time,
+            end_time,
+            correlation_id.as_deref(),
+        ))
+    }
+
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|_| AuditStorageError::LockPoisoned)?;
+        page_by_index(
+            &guard,
+            cursor,
+            limit,
+            start_time,
+            end_time,
+            correlation_id.as_deref(),
+        )
     }
 }
 
@@ -142,123 +339,101 @@ pub enum AuditStorageError {
     DatabaseError(String),
     #[error("serialization error: {0}")]
     SerializationError(String),
+    #[error("record does not extend the current chain head")]
+    ChainConflict,
+    #[error("audit trail cursor is malformed or from another backend")]
+    InvalidCursor,
+    #[error("audit records are encrypted but AUDIT_ENCRYPTION_KEY is not set")]
+    EncryptionKeyMissing,
+    #[error(
+        "audit record is encrypted with key {record_key}, but the configured key is {configured}; check AUDIT_ENCRYPTION_KEY or finish the key rotation"
+    )]
+    WrongEncryptionKey {
+        record_key: String,
+        configured: String,
+    },
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
 }
 
-#[derive(Clone)]
-pub struct SledAuditStorage {
-    db: Db,
+/// Where audit records are kept, selected with `AUDIT_BACKEND`
+///
+/// Defaults to sled, or to memory when the crate is built without `sled-storage`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditBackend {
+    Sled,
+    Sqlite,
+    Memory,
 }
 
-impl SledAuditStorage {
-    pub fn new(db_path: &str) -> Result<Self, AuditStorageError> {
-        let db =
-            sled::open(db_path).map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
-        Ok(Self { db })
+impl Default for AuditBackend {
+    fn default() -> Self {
+        if cfg!(feature = "sled-storage") {
+            Self::Sled
+        } else {
+            Self::Memory
+        }
     }
 }
 
-impl AuditStorage for SledAuditStorage {
-    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
-        let serialized = serde_json::to_string(&record)
-            .map_err(|e| AuditStorageError::SerializationError(e.to_string()))?;
-
-        // Use timestamp-prefixed key for chronological ordering
-        // Format: {timestamp_nanos}_{correlation_id}
-        let key = format!(
-            "{:020}_{}",
-            record.timestamp.timestamp_nanos_opt().unwrap_or(0),
-            record.correlation_id
-        );
-        self.db
-            .insert(key, serialized.as_bytes())
-            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
-
-        self.db
-            .flush()
-            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
-
-        Ok(())
+impl AuditBackend {
+    /// Cargo feature the backend is compiled in with, if it needs one
+    pub fn required_feature(self) -> Option<&'static str> {
+        match self {
+            Self::Sled => Some("sled-storage"),
+            Self::Sqlite => Some("sqlite-storage"),
+            Self::Memory => None,
+        }
     }
 
-    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
-        // Iterate in reverse to get the chronologically latest record
-        let last_record = self
-            .db
-            .iter()
-            .next_back()
-            .transpose()
-            .map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
-
-        match last_record {
-            Some((_, data)) => {
-                let record: StoredAuditRecord = serde_json::from_slice(&data)
-                    .map_err(|e| AuditStorageError::SerializationError(e.to_string()))?;
-                Ok(Some(record.proof.chain_hash))
-            }
-            None => Ok(None),
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Sled => cfg!(feature = "sled-storage"),
+            Self::Sqlite => cfg!(feature = "sqlite-storage"),
+            Self::Memory => true,
         }
     }
+}
 
-    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
-        let mut records = Vec::new();
-
-        for result in self.db.iter() {
-            let (_, data) = result.map_err(|e| AuditStorageError::DatabaseError(e.to_string()))?;
-            let record: StoredAuditRecord = serde_json::from_slice(&data)
-                .map_err(|e| AuditStorageError::SerializationError(e.to_string()))?;
-            records.push(record);
+impl std::str::FromStr for AuditBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sled" => Ok(Self::Sled),
+            "sqlite" => Ok(Self::Sqlite),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!(
+                "unknown audit backend '{other}' (expected sled, sqlite or memory)"
+            )),
         }
-
-        Ok(records)
     }
+}
 
-    fn get_with_filters(
-        &self,
-        limit: Option<usize>,
-        offset: Option<usize>,
-        start_time: Option<DateTime<Utc>>,
-        end_time: Option<DateTime<Utc>>,
-        correlation_id: Option<String>,
-    ) -> Result<AuditTrailResponse, AuditStorageError> {
-        let all_records = self.all()?;
+/// How much prompt text audit records keep, selected with `AUDIT_PROMPT_STORAGE`
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptStorageMode {
+    /// Original and sanitized prompts are stored verbatim
+    #[default]
+    Full,
+    /// Prompts are replaced by their `v1:` fingerprint
+    Redacted,
+}
 
-        // Apply time filters
-        let filtered_records: Vec<StoredAuditRecord> = all_records
-            .into_iter()
-            .filter(|record| {
-                let in_time_range = start_time
-                    .as_ref()
-                    .map(|start| record.timestamp >= *start)
-                    .unwrap_or(true)
-                    && end_time
-                        .as_ref()
-                        .map(|end| record.timestamp <= *end)
-                        .unwrap_or(true);
-
-                let matches_correlation = correlation_id
-                    .as_ref()
-                    .map(|cid| record.correlation_id == *cid)
-                    .unwrap_or(true);
-
-                in_time_range && matches_correlation
-            })
-            .collect();
-
-        // Apply pagination
-        let limit = limit.unwrap_or(100);
-        let offset = offset.unwrap_or(0);
-        let total_count = filtered_records.len();
-        let paginated_records: Vec<StoredAuditRecord> = filtered_records
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
+impl std::str::FromStr for PromptStorageMode {
+    type Err = String;
 
-        Ok(AuditTrailResponse {
-            records: paginated_records,
-            total_count,
-            limit,
-            offset,
-        })
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "redacted" => Ok(Self::Redacted),
+            other => Err(format!(
+                "unknown prompt storage mode '{other}' (expected full or redacted)"
+            )),
+        }
     }
 }
@@ -0,0 +1,314 @@
+//! Collapsing of repeated synthetic audit events
+//!
+//! Health checks and synthetic monitors send the same prompt every few seconds. Events
+//! matching a suppression rule are grouped by prompt hash, status and reason code: the
+//! first of a group is stored in full, and repeats within the following window are only
+//! counted. Each window that counted any is closed by one [`SuppressedRepeatsEvent`], an
+//! ordinary record in the hash chain. A group quiet for a whole window starts over with
+//! a full record.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::logger::{AuditEvent, SuppressedRepeatsEvent};
+use super::proof::hash_record;
+use super::storage::{PromptStorageMode, StoredAuditRecord};
+use crate::modules::request_fingerprint::service::{is_fingerprint, prompt_fingerprint};
+
+/// Header marking a compliance check as synthetic; honoured with the admin token only
+pub const SYNTHETIC_HEADER: &str = "x-sentinel-synthetic";
+
+tokio::task_local! {
+    /// Whether an administrator marked the request running on this task as synthetic
+    static SYNTHETIC: bool;
+}
+
+/// Run `future` with its audit events marked as synthetic traffic when `synthetic`
+pub async fn scope<F: Future>(synthetic: bool, future: F) -> F::Output {
+    if synthetic {
+        SYNTHETIC.scope(true, future).await
+    } else {
+        future.await
+    }
+}
+
+/// Whether the request running on this task was marked as synthetic
+pub fn is_synthetic() -> bool {
+    SYNTHETIC.try_with(|synthetic| *synthetic).unwrap_or(false)
+}
+
+/// Which events are collapsed, and over how long
+///
+/// Suppression is off while `window_secs` is zero or no rule could match. Requests an
+/// administrator marks as synthetic always match while it is on.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AuditSuppressionPolicy {
+    pub window_secs: u64,
+    /// Fingerprints (`v1:`) of prompts that are always synthetic. A plain SHA-256 of the
+    /// prompt, with or without its `sha256:` prefix, still matches prompts stored in full
+    pub prompt_hashes: Vec<String>,
+    /// Correlation id prefixes of synthetic requests, such as `synthetic-`
+    pub correlation_id_prefixes: Vec<String>,
+}
+
+impl AuditSuppressionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for hash in &self.prompt_hashes {
+            let hash = hash.to_ascii_lowercase();
+            let hex = hash.strip_prefix("sha256:").unwrap_or(&hash);
+            let is_digest = hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_fingerprint(&hash) && !is_digest {
+                return Err(format!(
+                    "audit suppression prompt hash {hash} is neither a v1: fingerprint nor a SHA-256 hex digest"
+                ));
+            }
+        }
+        if self.correlation_id_prefixes.iter().any(String::is_empty) {
+            return Err("audit suppression correlation id prefixes must not be empty".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.window_secs > 0
+    }
+
+    /// The rule `event` is collapsed under, if any
+    fn matching_rule(&self, event: &AuditEvent, prompt_hash: &str) -> Option<SuppressionRule> {
+        if event.self_test || event.exchange_validation.is_some() {
+            return None;
+        }
+        if is_synthetic() {
+            Some(SuppressionRule::AdminFlag)
+        } else if self
+            .correlation_id_prefixes
+            .iter()
+            .any(|prefix| event.correlation_id.starts_with(prefix))
+        {
+            Some(SuppressionRule::CorrelationPrefix)
+        } else if self
+            .prompt_hashes
+            .iter()
+            .any(|hash| matches_prompt(hash, event, prompt_hash))
+        {
+            Some(SuppressionRule::PromptHash)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why an event was eligible for suppression
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionRule {
+    /// Marked synthetic by an administrator
+    AdminFlag,
+    CorrelationPrefix,
+    PromptHash,
+}
+
+impl SuppressionRule {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AdminFlag => "admin_flag",
+            Self::CorrelationPrefix => "correlation_prefix",
+            Self::PromptHash => "prompt_hash",
+        }
+    }
+}
+
+/// What to do with an observed event
+#[derive(Debug, Default)]
+pub struct Observation {
+    /// Rule the event was counted under instead of being stored
+    pub suppressed: Option<SuppressionRule>,
+    /// Aggregates of windows that closed before the event, to be stored first
+    pub closed: Vec<SuppressedRepeatsEvent>,
+}
+
+/// Repeats of one group counted since `started`
+#[derive(Debug)]
+struct RepeatWindow {
+    /// Correlation id of the fully stored occurrence the repeats are filed under
+    correlation_id: String,
+    rule: SuppressionRule,
+    prompt_hash: String,
+    final_status: String,
+    started: DateTime<Utc>,
+    count: u64,
+    first_at: Option<DateTime<Utc>>,
+    last_at: Option<DateTime<Utc>>,
+}
+
+impl RepeatWindow {
+    fn last_activity(&self) -> DateTime<Utc> {
+        self.last_at.unwrap_or(self.started)
+    }
+
+    /// The aggregate of the repeats counted so far, and a fresh window from `now`
+    fn close(&mut self, now: DateTime<Utc>, window_secs: u64) -> Option<SuppressedRepeatsEvent> {
+        let aggregate = (self.count > 0).then(|| SuppressedRepeatsEvent {
+            correlation_id: self.correlation_id.clone(),
+            event_type: "suppressed_repeats".to_owned(),
+            rule: self.rule,
+            prompt_hash: self.prompt_hash.clone(),
+            final_status: self.final_status.clone(),
+            count: self.count,
+            first_at: self.first_at.unwrap_or(self.started),
+            last_at: self.last_activity(),
+            window_secs,
+        });
+        self.started = now;
+        self.count = 0;
+        self.first_at = None;
+        aggregate
+    }
+
+    fn count(&mut self, now: DateTime<Utc>) {
+        self.count += 1;
+        self.first_at.get_or_insert(now);
+        self.last_at = Some(now);
+    }
+}
+
+/// Tracks the open windows of an [`AuditSuppressionPolicy`]; shared by logger clones
+#[derive(Debug)]
+pub struct RepeatSuppressor {
+    policy: AuditSuppressionPolicy,
+    windows: Mutex<HashMap<String, RepeatWindow>>,
+}
+
+impl RepeatSuppressor {
+    pub fn new(policy: AuditSuppressionPolicy) -> Self {
+        Self {
+            policy,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &AuditSuppressionPolicy {
+        &self.policy
+    }
+
+    fn window(&self) -> Duration {
+        Duration::seconds(self.policy.window_secs as i64)
+    }
+
+    /// Decide whether `event`, seen at `now`, is stored or counted
+    pub fn observe(&self, event: &AuditEvent, now: DateTime<Utc>) -> Observation {
+        let prompt_hash = prompt_hash(event);
+        let Some(rule) = self.policy.matching_rule(event, &prompt_hash) else {
+            return Observation::default();
+        };
+        let key = format!(
+            "{prompt_hash}|{}|{}",
+            event.final_status,
+            serde_json::to_string(&event.final_reason_code).unwrap_or_default()
+        );
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(window) = windows.get_mut(&key) else {
+            windows.insert(
+                key,
+                RepeatWindow {
+                    correlation_id: event.correlation_id.clone(),
+                    rule,
+                    prompt_hash,
+                    final_status: event.final_status.clone(),
+                    started: now,
+                    count: 0,
+                    first_at: None,
+                    last_at: None,
+                },
+            );
+            return Observation::default();
+        };
+        if now < window.started + self.window() {
+            window.count(now);
+            return Observation {
+                suppressed: Some(window.rule),
+                closed: Vec::new(),
+            };
+        }
+
+        let quiet = window.last_activity() + self.window() <= now;
+        let closed = window
+            .close(now, self.policy.window_secs)
+            .into_iter()
+            .collect();
+        if quiet {
+            windows.remove(&key);
+            drop(windows);
+            // Stored in full, and the representative of a new group
+            let mut observation = self.observe(event, now);
+            observation.closed = closed;
+            return observation;
+        }
+        window.count(now);
+        Observation {
+            suppressed: Some(window.rule),
+            closed,
+        }
+    }
+
+    /// Close the windows that ended by `now`, forgetting groups quiet for a whole window
+    pub fn close_expired(&self, now: DateTime<Utc>) -> Vec<SuppressedRepeatsEvent> {
+        let window = self.window();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let mut closed = Vec::new();
+        windows.retain(|_, open| {
+            if now < open.started + window {
+                return true;
+            }
+            let quiet = open.last_activity() + window <= now;
+            closed.extend(open.close(now, self.policy.window_secs));
+            !quiet
+        });
+        closed
+    }
+
+    /// Close every window that counted repeats, ended or not
+    pub fn close_all(&self, now: DateTime<Utc>) -> Vec<SuppressedRepeatsEvent> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        windows
+            .values_mut()
+            .filter(|open| open.count > 0)
+            .filter_map(|open| open.close(now, self.policy.window_secs))
+            .collect()
+    }
+}
+
+/// Repeats the `suppressed_repeats` records among `records` stand for
+pub fn repeats_in(records: &[StoredAuditRecord]) -> u64 {
+    records
+        .iter()
+        .filter_map(|record| serde_json::from_str::<SuppressedRepeatsEvent>(&record.payload).ok())
+        .filter(|aggregate| aggregate.event_type == "suppressed_repeats")
+        .map(|aggregate| aggregate.count)
+        .sum()
+}
+
+/// Fingerprint of the prompt of `event`, whether it was stored in full or not
+fn prompt_hash(event: &AuditEvent) -> String {
+    match event.prompt_storage {
+        PromptStorageMode::Redacted => event.original_prompt.clone(),
+        PromptStorageMode::Full => prompt_fingerprint(&event.original_prompt),
+    }
+}
+
+/// Whether the configured `hash` names the prompt of `event`
+///
+/// A plain SHA-256, from before fingerprints, is of the prompt exactly as sent, so it can
+/// only be checked against a prompt that was stored.
+fn matches_prompt(hash: &str, event: &AuditEvent, fingerprint: &str) -> bool {
+    let hash = hash.to_ascii_lowercase();
+    if is_fingerprint(&hash) {
+        return hash == fingerprint;
+    }
+    event.prompt_storage == PromptStorageMode::Full
+        && hash.strip_prefix("sha256:").unwrap_or(&hash) == hash_record(&event.original_prompt)
+}
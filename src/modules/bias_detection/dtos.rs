@@ -6,6 +6,16 @@ use super::model::{BiasCategory, BiasLevel};
 pub struct BiasScanRequest {
     pub text: String,
     pub threshold: Option<f32>,
+    /// Language of `text` if already known (ISO code or English name, e.g. "de" or "German")
+    #[serde(default)]
+    pub language_hint: Option<String>,
+    /// English rendering of `text` made by the caller, used instead of translating when
+    /// no term pack covers `language_hint`
+    ///
+    /// The workflow passes the translation it already made for the firewall; a
+    /// deserialized request has none, so the service translates when it needs to.
+    #[serde(skip)]
+    pub pre_translated_text: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -15,4 +25,58 @@ pub struct BiasScanResult {
     pub categories: Vec<BiasCategory>,
     pub matched_terms: Vec<String>,
     pub mitigation_hints: Vec<String>,
+    /// Language pack the matched terms came from; `None` for the built-in English rules
+    #[serde(default)]
+    pub term_pack: Option<String>,
+    /// Debiased rephrasing of the prompt, when one was requested and passed moderation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_rewrite: Option<String>,
+}
+
+impl BiasScanResult {
+    /// Result for text that was not scanned: no bias found
+    pub fn not_scanned() -> Self {
+        Self {
+            score: 0.0,
+            level: BiasLevel::Low,
+            categories: Vec::new(),
+            matched_terms: Vec::new(),
+            mitigation_hints: Vec::new(),
+            term_pack: None,
+            suggested_rewrite: None,
+        }
+    }
+}
+
+/// When and how rewrite suggestions are generated for biased prompts
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BiasRewriteConfig {
+    /// Lowest bias level that gets a suggestion
+    pub min_level: BiasLevel,
+    /// Suggestions not generated and moderated within this budget are dropped
+    pub timeout_ms: u64,
+    /// Cap on the length of a suggestion
+    pub max_tokens: u32,
+}
+
+impl Default for BiasRewriteConfig {
+    fn default() -> Self {
+        Self {
+            min_level: BiasLevel::Medium,
+            timeout_ms: 1_500,
+            max_tokens: 256,
+        }
+    }
+}
+
+impl BiasRewriteConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timeout_ms == 0 {
+            return Err("bias rewrite timeout must be positive".to_owned());
+        }
+        if self.max_tokens == 0 {
+            return Err("bias rewrite max tokens must be positive".to_owned());
+        }
+        Ok(())
+    }
 }
@@ -10,6 +10,8 @@ pub async fn handle_bias_scan(
         .scan(BiasScanRequest {
             text: text.into(),
             threshold,
+            language_hint: None,
+            pre_translated_text: None,
         })
         .await
 }
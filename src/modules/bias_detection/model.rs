@@ -1,12 +1,34 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+/// Ordered from least to most biased
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
 pub enum BiasLevel {
+    #[serde(alias = "Low")]
     Low,
+    #[serde(alias = "Medium")]
     Medium,
+    #[serde(alias = "High")]
     High,
 }
 
+impl FromStr for BiasLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            other => Err(format!(
+                "unknown bias level '{other}' (expected low, medium or high)"
+            )),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum BiasCategory {
     Gender,
@@ -19,3 +41,18 @@ pub enum BiasCategory {
     Nationality,
     HarmfulLanguage,
 }
+
+/// Language-specific bias terms loaded from `config/bias_rules.<lang>.json`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BiasTermPack {
+    pub language: String,
+    pub rules: Vec<BiasTermPackRule>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BiasTermPackRule {
+    pub category: BiasCategory,
+    pub weight: f32,
+    pub hint: String,
+    pub terms: Vec<String>,
+}
@@ -1,15 +1,62 @@
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
 
-use super::dtos::{BiasScanRequest, BiasScanResult};
-use super::model::{BiasCategory, BiasLevel};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use super::dtos::{BiasRewriteConfig, BiasScanRequest, BiasScanResult};
+use super::model::{BiasCategory, BiasLevel, BiasTermPack};
+use crate::config::layers::configured_path;
+use crate::modules::audit::proof::content_hash;
+use crate::modules::mistral_ai::client::MistralClientError;
+use crate::modules::mistral_ai::service::{MistralService, MistralServiceError};
+
+pub(crate) const DEFAULT_BIAS_RULES_DIR: &str = "config";
+const BIAS_RULES_DIR_ENV: &str = "BIAS_RULES_DIR";
+
+/// Language packs keyed by ISO 639-1 code, loaded once from `BIAS_RULES_DIR`
+static TERM_PACKS: LazyLock<HashMap<String, BiasTermPack>> = LazyLock::new(|| {
+    let dir = configured_path(BIAS_RULES_DIR_ENV, DEFAULT_BIAS_RULES_DIR);
+    load_term_packs(Path::new(&dir))
+});
+
+/// Content hash of the built-in rules together with the loaded language packs
+static RULES_FINGERPRINT: LazyLock<String> = LazyLock::new(|| {
+    let built_in: Vec<_> = RULES
+        .iter()
+        .map(|rule| {
+            serde_json::json!({
+                "category": rule.category,
+                "terms": rule.terms,
+                "weight": rule.weight,
+                "hint": rule.hint,
+            })
+        })
+        .collect();
+    let packs: BTreeMap<_, _> = TERM_PACKS.iter().collect();
+    content_hash(&serde_json::json!({ "built_in": built_in, "term_packs": packs }))
+});
 
 #[derive(Clone)]
 pub struct BiasDetectionService {
-    default_threshold: f32,
+    default_threshold: Arc<RwLock<f32>>,
     mistral_service: Option<Arc<dyn crate::modules::mistral_ai::client::MistralClient>>,
+    rewriter: Option<BiasRewriter>,
 }
 
+/// Generation model and limits used for rewrite suggestions
+#[derive(Clone)]
+struct BiasRewriter {
+    mistral: MistralService,
+    config: BiasRewriteConfig,
+}
+
+/// Low temperature keeps the suggestion close to the original request
+const REWRITE_TEMPERATURE: f32 = 0.2;
+
 #[derive(Clone, Debug)]
 struct BiasRule {
     category: BiasCategory,
@@ -1071,8 +1118,9 @@ const RULES: &[BiasRule] = &[
 impl BiasDetectionService {
     pub fn new(default_threshold: f32) -> Self {
         Self {
-            default_threshold,
+            default_threshold: Arc::new(RwLock::new(default_threshold)),
             mistral_service: None,
+            rewriter: None,
         }
     }
 
@@ -1081,67 +1129,178 @@ impl BiasDetectionService {
         mistral_service: Arc<dyn crate::modules::mistral_ai::client::MistralClient>,
     ) -> Self {
         Self {
-            default_threshold,
+            default_threshold: Arc::new(RwLock::new(default_threshold)),
             mistral_service: Some(mistral_service),
+            rewriter: None,
         }
     }
 
-    async fn translate_if_needed(&self, text: &str) -> String {
+    /// Offer debiased rephrasings of prompts at or above `config.min_level`, written by
+    /// the generation model of `mistral`
+    pub fn with_rewrite_suggestions(
+        mut self,
+        mistral: MistralService,
+        config: BiasRewriteConfig,
+    ) -> Self {
+        self.rewriter = Some(BiasRewriter { mistral, config });
+        self
+    }
+
+    /// A debiased rephrasing of `prompt`, given its scan result
+    ///
+    /// `None` without rewrite suggestions configured, below the configured level, when
+    /// generation fails or exceeds the time budget, or when moderation flags the
+    /// suggestion. Failures are logged and never fail the caller.
+    pub async fn suggest_rewrite(&self, prompt: &str, scan: &BiasScanResult) -> Option<String> {
+        let rewriter = self.rewriter.as_ref()?;
+        if scan.level < rewriter.config.min_level {
+            return None;
+        }
+        let budget = Duration::from_millis(rewriter.config.timeout_ms);
+        match tokio::time::timeout(budget, rewriter.rewrite(prompt, scan)).await {
+            Ok(Ok(suggestion)) => suggestion,
+            Ok(Err(e)) => {
+                warn!("Bias rewrite suggestion failed: {}", e);
+                None
+            }
+            Err(_) => {
+                debug!(
+                    "Bias rewrite suggestion dropped after {}ms",
+                    rewriter.config.timeout_ms
+                );
+                None
+            }
+        }
+    }
+
+    /// Threshold applied when a scan request does not override it
+    pub fn default_threshold(&self) -> f32 {
+        *self.default_threshold.read().unwrap()
+    }
+
+    pub(crate) fn default_threshold_lock(&self) -> &RwLock<f32> {
+        &self.default_threshold
+    }
+
+    /// Content hash of the bias rules and language packs in use
+    pub fn rules_fingerprint(&self) -> String {
+        RULES_FINGERPRINT.clone()
+    }
+
+    /// English rendering of `text` for the built-in rules
+    ///
+    /// Detection and translation errors are returned, so the caller decides whether to
+    /// fall back to scanning the text as written.
+    async fn translate_if_needed(
+        &self,
+        text: &str,
+        language_hint: Option<&str>,
+    ) -> Result<String, MistralClientError> {
         let Some(mistral_service) = &self.mistral_service else {
-            return text.to_owned();
+            return Ok(text.to_owned());
         };
 
-        // First detect language - only translate if NOT English
-        let Ok(lang_detection) = mistral_service
-            .detect_language(crate::modules::mistral_ai::dtos::LanguageDetectionRequest {
-                text: text.to_owned(),
-            })
-            .await
-        else {
-            return text.to_owned();
+        // Detect language unless the caller already knows it - only translate if NOT English
+        let language = match language_hint {
+            Some(language) => language.to_owned(),
+            None => {
+                mistral_service
+                    .detect_language(crate::modules::mistral_ai::dtos::LanguageDetectionRequest {
+                        text: text.to_owned(),
+                        sampling: crate::modules::mistral_ai::sampling::current(),
+                    })
+                    .await?
+                    .language
+            }
         };
 
         // Skip translation if already English (to avoid paraphrasing)
-        if lang_detection.language.to_lowercase() == "english" {
-            return text.to_owned();
+        if language_code(&language) == "en" {
+            return Ok(text.to_owned());
         }
 
         // Translate non-English text to English
-        let Ok(translation) = mistral_service
+        let translation = mistral_service
             .translate_text(crate::modules::mistral_ai::dtos::TranslationRequest {
                 text: text.to_owned(),
                 target_language: "English".to_owned(),
+                sampling: crate::modules::mistral_ai::sampling::current(),
             })
-            .await
-        else {
-            return text.to_owned();
-        };
-
-        translation.translated_text
+            .await?;
+        Ok(translation.translated_text)
     }
 
+    /// Scan, falling back to the untranslated text when translation fails
     pub async fn scan(&self, request: BiasScanRequest) -> BiasScanResult {
-        let text_to_analyze = self.translate_if_needed(&request.text).await;
-        let threshold = normalize_threshold(request.threshold, self.default_threshold);
-        let normalized = text_to_analyze.to_ascii_lowercase();
+        self.try_scan(request)
+            .await
+            .unwrap_or_else(|failure| failure.fallback)
+    }
 
-        let mut score = 0.0f32;
-        let mut categories = HashSet::new();
-        let mut matched_terms = Vec::new();
-        let mut mitigation_hints = HashSet::new();
+    /// Scan, reporting a failed translation instead of silently scanning the original text
+    pub async fn try_scan(
+        &self,
+        request: BiasScanRequest,
+    ) -> Result<BiasScanResult, BiasScanFailure> {
+        let threshold = normalize_threshold(request.threshold, self.default_threshold());
+        let mut matches = TermMatches::default();
+        let mut translation_error = None;
 
-        for rule in RULES {
-            for term in rule.terms {
-                if contains_term_with_boundaries(&normalized, term) {
-                    score += rule.weight;
-                    categories.insert(rule.category.clone());
-                    matched_terms.push((*term).to_owned());
-                    mitigation_hints.insert(rule.hint.to_owned());
+        // A native term pack avoids the lossy (and costly) translation round trip
+        let term_pack = request
+            .language_hint
+            .as_deref()
+            .and_then(|hint| TERM_PACKS.get(&language_code(hint)));
+        if let Some(pack) = term_pack {
+            debug!("Scanning bias with the '{}' term pack", pack.language);
+            let normalized = request.text.to_lowercase();
+            for rule in &pack.rules {
+                matches.collect(
+                    &normalized,
+                    &rule.category,
+                    rule.terms.iter().map(String::as_str),
+                    rule.weight,
+                    &rule.hint,
+                );
+            }
+        } else {
+            let translated = match request.pre_translated_text.clone() {
+                Some(text) => Ok(text),
+                None => {
+                    self.translate_if_needed(&request.text, request.language_hint.as_deref())
+                        .await
+                }
+            };
+            let text_to_analyze = match translated {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!(
+                        "Bias scan translation failed, scanning the original text: {}",
+                        e
+                    );
+                    translation_error = Some(e);
+                    request.text.clone()
                 }
+            };
+            let normalized = text_to_analyze.to_ascii_lowercase();
+            for rule in RULES {
+                matches.collect(
+                    &normalized,
+                    &rule.category,
+                    rule.terms.iter().copied(),
+                    rule.weight,
+                    rule.hint,
+                );
             }
         }
 
-        score = score.min(1.0);
+        let TermMatches {
+            score,
+            categories,
+            matched_terms,
+            mitigation_hints,
+        } = matches;
+        let score = score.min(1.0);
         let high_cutoff = high_risk_cutoff(threshold);
         let level = if score >= high_cutoff {
             BiasLevel::High
@@ -1157,16 +1316,166 @@ impl BiasDetectionService {
         let mut mitigation_hints = mitigation_hints.into_iter().collect::<Vec<_>>();
         mitigation_hints.sort();
 
-        BiasScanResult {
+        let result = BiasScanResult {
             score,
             level,
             categories,
             matched_terms,
             mitigation_hints,
+            term_pack: term_pack.map(|pack| pack.language.clone()),
+            suggested_rewrite: None,
+        };
+        match translation_error {
+            Some(source) => Err(BiasScanFailure {
+                source,
+                fallback: result,
+            }),
+            None => Ok(result),
+        }
+    }
+}
+
+impl BiasRewriter {
+    /// The moderated suggestion, or `None` when moderation flags it or it is empty
+    async fn rewrite(
+        &self,
+        prompt: &str,
+        scan: &BiasScanResult,
+    ) -> Result<Option<String>, MistralServiceError> {
+        let categories = scan
+            .categories
+            .iter()
+            .map(|category| format!("{category:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let instruction = format!(
+            "Rewrite the following prompt so that it asks for the same thing without \
+             stereotypes, generalizations or demeaning language about any group \
+             (flagged categories: {categories}). Keep the original language and intent. \
+             Reply with ONLY the rewritten prompt.\n\nPrompt: {prompt}"
+        );
+        let generation = self
+            .mistral
+            .generate_constrained(instruction, REWRITE_TEMPERATURE, self.config.max_tokens)
+            .await?;
+        let suggestion = generation.output_text.trim().to_owned();
+        if suggestion.is_empty() {
+            return Ok(None);
+        }
+        let moderation = self.mistral.moderate_text(suggestion.clone()).await?;
+        if moderation.flagged {
+            warn!(
+                "Bias rewrite suggestion flagged by moderation: {}",
+                moderation.categories.join(", ")
+            );
+            return Ok(None);
+        }
+        Ok(Some(suggestion))
+    }
+}
+
+/// Translation for the bias scan failed
+#[derive(Debug, Error)]
+#[error("bias scan translation failed: {source}")]
+pub struct BiasScanFailure {
+    pub source: MistralClientError,
+    /// Result of scanning the untranslated text with the built-in rules
+    pub fallback: BiasScanResult,
+}
+
+#[derive(Default)]
+struct TermMatches {
+    score: f32,
+    categories: HashSet<BiasCategory>,
+    matched_terms: Vec<String>,
+    mitigation_hints: HashSet<String>,
+}
+
+impl TermMatches {
+    fn collect<'a>(
+        &mut self,
+        normalized: &str,
+        category: &BiasCategory,
+        terms: impl IntoIterator<Item = &'a str>,
+        weight: f32,
+        hint: &str,
+    ) {
+        for term in terms {
+            if contains_term_with_boundaries(normalized, term) {
+                self.score += weight;
+                self.categories.insert(category.clone());
+                self.matched_terms.push(term.to_owned());
+                self.mitigation_hints.insert(hint.to_owned());
+            }
         }
     }
 }
 
+/// Maps a detected language name or locale to its ISO 639-1 code
+fn language_code(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    match primary {
+        "english" => "en",
+        "german" | "deutsch" => "de",
+        "french" | "français" | "francais" => "fr",
+        "spanish" | "español" | "espanol" => "es",
+        "italian" | "italiano" => "it",
+        "portuguese" | "português" | "portugues" => "pt",
+        "dutch" | "nederlands" => "nl",
+        other => other,
+    }
+    .to_owned()
+}
+
+fn load_term_packs(dir: &Path) -> HashMap<String, BiasTermPack> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+
+    let mut packs = HashMap::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        let Some(code) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("bias_rules."))
+            .and_then(|name| name.strip_suffix(".json"))
+        else {
+            continue;
+        };
+
+        let pack = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                serde_json::from_str::<BiasTermPack>(&content).map_err(|e| e.to_string())
+            });
+        match pack {
+            Ok(mut pack) if pack.language == code => {
+                for rule in &mut pack.rules {
+                    rule.terms = rule.terms.iter().map(|term| term.to_lowercase()).collect();
+                }
+                packs.insert(code.to_owned(), pack);
+            }
+            Ok(pack) => warn!(
+                "Ignoring bias term pack {:?}: declares language '{}'",
+                path, pack.language
+            ),
+            Err(reason) => warn!("Ignoring bias term pack {:?}: {}", path, reason),
+        }
+    }
+    packs
+}
+
+/// Reject default thresholds outside the scoring range
+pub fn validate_threshold(threshold: f32) -> Result<(), String> {
+    if !threshold.is_finite() || !(0.0..=1.0).contains(&threshold) {
+        return Err(format!(
+            "bias threshold {threshold} must be within 0.0..=1.0"
+        ));
+    }
+    Ok(())
+}
+
 /// Applies an optional caller override and clamps the effective threshold
 /// into a safe range so scoring stays predictable across inputs.
 fn normalize_threshold(override_threshold: Option<f32>, default_threshold: f32) -> f32 {
@@ -1207,21 +1516,18 @@ fn has_word_boundaries(text: &str, start: usize, end: usize) -> bool {
     let left_is_boundary = text[..start]
         .chars()
         .next_back()
-        .map_or(true, |ch| !ch.is_alphanumeric());
+        .is_none_or(|ch| !ch.is_alphanumeric());
     let right_is_boundary = text[end..]
         .chars()
         .next()
-        .map_or(true, |ch| !ch.is_alphanumeric());
+        .is_none_or(|ch| !ch.is_alphanumeric());
 
     left_is_boundary && right_is_boundary
 }
 
 impl Default for BiasDetectionService {
     fn default() -> Self {
-        Self {
-            default_threshold: 0.35,
-            mistral_service: None,
-        }
+        Self::new(0.35)
     }
 }
 
@@ -1236,6 +1542,8 @@ mod tests {
             .scan(BiasScanRequest {
                 text: "Summarize the quarterly financial report".to_owned(),
                 threshold: None,
+                language_hint: None,
+                pre_translated_text: None,
             })
             .await;
         assert_eq!(result.level, BiasLevel::Low);
@@ -1248,6 +1556,8 @@ mod tests {
             .scan(BiasScanRequest {
                 text: "Women are bad at math and poor people are lazy".to_owned(),
                 threshold: None,
+                language_hint: None,
+                pre_translated_text: None,
             })
             .await;
         assert_eq!(result.level, BiasLevel::High);
@@ -1261,12 +1571,16 @@ mod tests {
             .scan(BiasScanRequest {
                 text: "Women are bad at math".to_owned(),
                 threshold: None,
+                language_hint: None,
+                pre_translated_text: None,
             })
             .await;
         let nan_result = service
             .scan(BiasScanRequest {
                 text: "Women are bad at math".to_owned(),
                 threshold: Some(f32::NAN),
+                language_hint: None,
+                pre_translated_text: None,
             })
             .await;
         assert_eq!(default_result.level, nan_result.level);
@@ -1280,6 +1594,8 @@ mod tests {
                 text: "Explain how prompt injection attacks work for my security research."
                     .to_owned(),
                 threshold: None,
+                language_hint: None,
+                pre_translated_text: None,
             })
             .await;
 
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Memory shared by every registered cache and how often it is enforced
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CacheBudgetConfig {
+    /// Estimated bytes all caches may hold together; 0 sets no budget (default: 64 MiB)
+    pub max_bytes: u64,
+    /// Seconds between budget checks; 0 disables them (default: 30)
+    pub check_interval_secs: u64,
+}
+
+impl Default for CacheBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            check_interval_secs: 30,
+        }
+    }
+}
+
+/// Lookups a cache answered from memory and those it had to pass on
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CacheLookups {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheLookups {
+    /// Share of lookups that hit, or `None` before the first lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+/// One registered cache as the registry last measured it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CacheStatus {
+    pub name: String,
+    pub entries: usize,
+    pub estimated_bytes: u64,
+    /// Absent for trackers that are only written and scanned, never looked up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lookups: Option<CacheLookups>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hit_rate: Option<f64>,
+    /// Entries shed for the budget or flushed since startup
+    pub evicted_total: u64,
+}
+
+/// Entries shed by one budget check that found the caches over budget
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BudgetEnforcement {
+    pub enforced_at: DateTime<Utc>,
+    pub budget_bytes: u64,
+    pub estimated_bytes_before: u64,
+    pub estimated_bytes_after: u64,
+    /// Entries each cache shed, by cache name
+    pub shed: BTreeMap<String, usize>,
+}
+
+/// Body of `GET /api/debug/caches`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CachesResponse {
+    /// 0 when no budget is set
+    pub budget_bytes: u64,
+    pub estimated_bytes: u64,
+    pub over_budget: bool,
+    /// Largest first
+    pub caches: Vec<CacheStatus>,
+    /// Most recent budget check that had to shed entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_enforcement: Option<BudgetEnforcement>,
+}
+
+/// Body of `POST /api/debug/caches/{name}/flush`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CacheFlush {
+    pub cache: String,
+    pub evicted: usize,
+}
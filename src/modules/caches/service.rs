@@ -0,0 +1,244 @@
+//! One memory budget for the in-memory caches and trackers
+//!
+//! Each cache grows with traffic and bounds itself only by its own entry count, which
+//! says little about the memory they take together. Caches register with a
+//! [`CacheRegistry`], which measures them on a schedule, exports their size and hit
+//! rate as gauges and, when they are over the budget together, asks each to shed a
+//! share of the excess proportional to its size. Shedding and flushing are always safe:
+//! a registered cache treats a miss as the normal path it was saving.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use super::dtos::{
+    BudgetEnforcement, CacheBudgetConfig, CacheFlush, CacheLookups, CacheStatus, CachesResponse,
+};
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// An in-memory structure whose entries can be dropped at any time
+pub trait ManagedCache: Send + Sync {
+    /// Entries held now
+    fn entries(&self) -> usize;
+    /// Approximate bytes the entries hold, keys and heap allocations included
+    fn estimated_bytes(&self) -> usize;
+    /// Drop up to `count` entries, least valuable first, and return how many went
+    fn shed(&self, count: usize) -> usize;
+    /// Hits and misses since startup; `None` for trackers that are never looked up
+    fn lookups(&self) -> Option<CacheLookups> {
+        None
+    }
+}
+
+/// Hit and miss counts a cache keeps for [`ManagedCache::lookups`], shared by clones
+#[derive(Clone, Default)]
+pub struct HitCounter {
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl HitCounter {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn lookups(&self) -> CacheLookups {
+        CacheLookups {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct RegisteredCache {
+    cache: Arc<dyn ManagedCache>,
+    evicted_total: u64,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    caches: BTreeMap<String, RegisteredCache>,
+    last_enforcement: Option<BudgetEnforcement>,
+}
+
+/// Caches sharing one memory budget, by name
+///
+/// Shared by clones.
+#[derive(Clone)]
+pub struct CacheRegistry {
+    config: CacheBudgetConfig,
+    state: Arc<Mutex<RegistryState>>,
+}
+
+impl Default for CacheRegistry {
+    fn default() -> Self {
+        Self::new(CacheBudgetConfig::default())
+    }
+}
+
+impl CacheRegistry {
+    pub fn new(config: CacheBudgetConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(RegistryState::default())),
+        }
+    }
+
+    pub fn config(&self) -> CacheBudgetConfig {
+        self.config
+    }
+
+    /// Put `cache` under the budget as `name`, replacing a cache registered before under
+    /// the same name
+    pub fn register(&self, name: &str, cache: Arc<dyn ManagedCache>) {
+        self.state.lock().unwrap().caches.insert(
+            name.to_owned(),
+            RegisteredCache {
+                cache,
+                evicted_total: 0,
+            },
+        );
+    }
+
+    /// Names of the registered caches, in order
+    pub fn names(&self) -> Vec<String> {
+        self.state.lock().unwrap().caches.keys().cloned().collect()
+    }
+
+    /// Measure every cache and update its gauges
+    pub fn status(&self) -> CachesResponse {
+        let state = self.state.lock().unwrap();
+        let mut caches: Vec<_> = state
+            .caches
+            .iter()
+            .map(|(name, registered)| measure(name, registered))
+            .collect();
+        caches.sort_by_key(|cache| std::cmp::Reverse(cache.estimated_bytes));
+        let estimated_bytes = caches.iter().map(|cache| cache.estimated_bytes).sum();
+        CachesResponse {
+            budget_bytes: self.config.max_bytes,
+            estimated_bytes,
+            over_budget: self.is_over_budget(estimated_bytes),
+            caches,
+            last_enforcement: state.last_enforcement.clone(),
+        }
+    }
+
+    /// Shed entries until the caches fit the budget again
+    ///
+    /// Each cache sheds a share of the excess proportional to the bytes it holds,
+    /// converted to entries at its average entry size, so the largest caches give up the
+    /// most. Returns `None` while the caches are within budget.
+    pub fn enforce(&self) -> Option<BudgetEnforcement> {
+        let mut state = self.state.lock().unwrap();
+        let measured: Vec<_> = state
+            .caches
+            .iter()
+            .map(|(name, registered)| measure(name, registered))
+            .collect();
+        let before: u64 = measured.iter().map(|cache| cache.estimated_bytes).sum();
+        if !self.is_over_budget(before) {
+            return None;
+        }
+
+        let excess = before - self.config.max_bytes;
+        let mut shed = BTreeMap::new();
+        for cache in &measured {
+            if cache.entries == 0 || cache.estimated_bytes == 0 {
+                continue;
+            }
+            let share = (excess as u128 * cache.estimated_bytes as u128).div_ceil(before as u128);
+            let entry_bytes = (cache.estimated_bytes / cache.entries as u64).max(1);
+            let count = (share.div_ceil(entry_bytes as u128) as usize).min(cache.entries);
+            let registered = state.caches.get_mut(&cache.name).unwrap();
+            let evicted = registered.cache.shed(count);
+            if evicted > 0 {
+                registered.evicted_total += evicted as u64;
+                get_metrics().increment_cache_evictions(&cache.name, "budget", evicted);
+                shed.insert(cache.name.clone(), evicted);
+            }
+        }
+
+        let after = state
+            .caches
+            .iter()
+            .map(|(name, registered)| measure(name, registered).estimated_bytes)
+            .sum();
+        warn!(
+            "In-memory caches held an estimated {} bytes over a budget of {}; shed {:?}, now {} bytes",
+            before, self.config.max_bytes, shed, after
+        );
+        let enforcement = BudgetEnforcement {
+            enforced_at: Utc::now(),
+            budget_bytes: self.config.max_bytes,
+            estimated_bytes_before: before,
+            estimated_bytes_after: after,
+            shed,
+        };
+        state.last_enforcement = Some(enforcement.clone());
+        Some(enforcement)
+    }
+
+    /// Drop every entry of the cache registered as `name`, or `None` if there is none
+    pub fn flush(&self, name: &str) -> Option<CacheFlush> {
+        let mut state = self.state.lock().unwrap();
+        let registered = state.caches.get_mut(name)?;
+        let evicted = registered.cache.shed(registered.cache.entries());
+        registered.evicted_total += evicted as u64;
+        get_metrics().increment_cache_evictions(name, "flush", evicted);
+        info!("Flushed {} entries from the {} cache", evicted, name);
+        measure(name, registered);
+        Some(CacheFlush {
+            cache: name.to_owned(),
+            evicted,
+        })
+    }
+
+    /// Run [`enforce`](Self::enforce) every `interval`, starting now
+    pub fn spawn_enforcer(&self, interval: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                registry.enforce();
+            }
+        })
+    }
+
+    fn is_over_budget(&self, estimated_bytes: u64) -> bool {
+        self.config.max_bytes > 0 && estimated_bytes > self.config.max_bytes
+    }
+}
+
+/// Measure a cache and publish what was measured to its gauges
+fn measure(name: &str, registered: &RegisteredCache) -> CacheStatus {
+    let entries = registered.cache.entries();
+    let estimated_bytes = registered.cache.estimated_bytes() as u64;
+    let lookups = registered.cache.lookups();
+    let hit_rate = lookups.and_then(|lookups| lookups.hit_rate());
+    let metrics = get_metrics();
+    metrics.set_cache_usage(name, entries, estimated_bytes);
+    if let Some(rate) = hit_rate {
+        metrics.set_cache_hit_rate(name, rate);
+    }
+    CacheStatus {
+        name: name.to_owned(),
+        entries,
+        estimated_bytes,
+        lookups,
+        hit_rate,
+        evicted_total: registered.evicted_total,
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::dtos::{FaultKind, FaultSpec};
+use super::service::ChaosController;
+use crate::modules::mistral_ai::client::{MistralClient, MistralClientError, MistralEndpoint};
+use crate::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationBatchRequest,
+    ModerationRequest, ModerationResponse, TranslationRequest, TranslationResponse,
+};
+
+/// Mistral client that fails or delays calls as the [`ChaosController`] decides
+///
+/// Calls that are not faulted go straight to the wrapped client.
+pub struct ChaosMistralClient {
+    inner: Arc<dyn MistralClient>,
+    chaos: ChaosController,
+}
+
+impl ChaosMistralClient {
+    pub fn new(inner: Arc<dyn MistralClient>, chaos: ChaosController) -> Self {
+        Self { inner, chaos }
+    }
+
+    async fn inject(&self, endpoint: MistralEndpoint) -> Result<(), MistralClientError> {
+        match self.chaos.draw(endpoint.into()) {
+            None => Ok(()),
+            Some(FaultSpec {
+                kind: FaultKind::Latency,
+                latency_ms,
+                ..
+            }) => {
+                tokio::time::sleep(Duration::from_millis(latency_ms.unwrap_or_default())).await;
+                Ok(())
+            }
+            Some(FaultSpec {
+                kind: FaultKind::Error,
+                ..
+            }) => Err(MistralClientError::ApiError {
+                status: 503,
+                message: "chaos: injected fault".to_owned(),
+            }),
+            Some(FaultSpec {
+                kind: FaultKind::Malformed,
+                ..
+            }) => Err(MistralClientError::InvalidResponse(
+                "chaos: injected malformed response".to_owned(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl MistralClient for ChaosMistralClient {
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralClientError> {
+        self.inject(MistralEndpoint::Chat).await?;
+        self.inner.chat_completion(request).await
+    }
+
+    async fn moderate(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, MistralClientError> {
+        self.inject(MistralEndpoint::Moderation).await?;
+        self.inner.moderate(request).await
+    }
+
+    async fn moderate_batch(
+        &self,
+        request: ModerationBatchRequest,
+    ) -> Result<Vec<ModerationResponse>, MistralClientError> {
+        self.inject(MistralEndpoint::Moderation).await?;
+        self.inner.moderate_batch(request).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, MistralClientError> {
+        self.inject(MistralEndpoint::Embeddings).await?;
+        self.inner.embeddings(request).await
+    }
+
+    async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.inject(MistralEndpoint::Models).await?;
+        self.inner.list_models().await
+    }
+
+    async fn detect_language(
+        &self,
+        request: LanguageDetectionRequest,
+    ) -> Result<LanguageDetectionResponse, MistralClientError> {
+        self.inject(MistralEndpoint::LanguageDetection).await?;
+        self.inner.detect_language(request).await
+    }
+
+    async fn translate_text(
+        &self,
+        request: TranslationRequest,
+    ) -> Result<TranslationResponse, MistralClientError> {
+        self.inject(MistralEndpoint::Translation).await?;
+        self.inner.translate_text(request).await
+    }
+}
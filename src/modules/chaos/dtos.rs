@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::modules::mistral_ai::client::MistralEndpoint;
+
+/// Longest delay a latency fault may add
+pub const MAX_FAULT_LATENCY_MS: u64 = 60_000;
+
+/// Dependency call a fault can be injected into
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosTarget {
+    Chat,
+    Moderation,
+    Embeddings,
+    Models,
+    LanguageDetection,
+    Translation,
+    /// Audit record writes; reads are never faulted
+    AuditStorage,
+}
+
+impl ChaosTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Moderation => "moderation",
+            Self::Embeddings => "embeddings",
+            Self::Models => "models",
+            Self::LanguageDetection => "language_detection",
+            Self::Translation => "translation",
+            Self::AuditStorage => "audit_storage",
+        }
+    }
+}
+
+impl From<MistralEndpoint> for ChaosTarget {
+    fn from(endpoint: MistralEndpoint) -> Self {
+        match endpoint {
+            MistralEndpoint::Chat => Self::Chat,
+            MistralEndpoint::Moderation => Self::Moderation,
+            MistralEndpoint::Embeddings => Self::Embeddings,
+            MistralEndpoint::Models => Self::Models,
+            MistralEndpoint::LanguageDetection => Self::LanguageDetection,
+            MistralEndpoint::Translation => Self::Translation,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    /// The call fails as though the dependency answered 503
+    Error,
+    /// The call succeeds after `latency_ms`
+    Latency,
+    /// The call fails as though the response broke its contract; Mistral calls only
+    Malformed,
+}
+
+impl FaultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Latency => "latency",
+            Self::Malformed => "malformed",
+        }
+    }
+}
+
+/// How often, and how, calls to one target fail
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FaultSpec {
+    /// Share of calls faulted, from 0.0 to 1.0
+    pub probability: f64,
+    pub kind: FaultKind,
+    /// Delay added by a `latency` fault
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+/// Body of `POST /api/chaos/config`; an empty map turns fault injection off
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChaosConfig {
+    #[serde(default)]
+    pub faults: BTreeMap<ChaosTarget, FaultSpec>,
+}
+
+impl ChaosConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (target, fault) in &self.faults {
+            let target = target.as_str();
+            if !(0.0..=1.0).contains(&fault.probability) {
+                return Err(format!("{target}: probability must be between 0.0 and 1.0"));
+            }
+            match (fault.kind, fault.latency_ms) {
+                (FaultKind::Latency, Some(1..=MAX_FAULT_LATENCY_MS)) => {}
+                (FaultKind::Latency, _) => {
+                    return Err(format!(
+                        "{target}: latency faults need latency_ms between 1 and {MAX_FAULT_LATENCY_MS}"
+                    ));
+                }
+                (_, Some(_)) => {
+                    return Err(format!(
+                        "{target}: latency_ms only applies to latency faults"
+                    ));
+                }
+                (_, None) => {}
+            }
+        }
+        if self
+            .faults
+            .get(&ChaosTarget::AuditStorage)
+            .is_some_and(|fault| fault.kind == FaultKind::Malformed)
+        {
+            return Err("audit_storage: malformed faults only apply to Mistral calls".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Fault injection state, returned by `POST /api/chaos/config` and in the system summary
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChaosStatus {
+    /// Whether the server was started with `CHAOS_MODE=true`
+    pub available: bool,
+    /// Whether any fault is configured
+    pub active: bool,
+    pub faults: BTreeMap<ChaosTarget, FaultSpec>,
+    /// Faults injected since startup
+    pub injected_total: BTreeMap<ChaosTarget, u64>,
+    /// When the configuration last changed
+    pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,4 @@
+pub mod client;
+pub mod dtos;
+pub mod service;
+pub mod storage;
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+use tracing::warn;
+
+use super::dtos::{ChaosConfig, ChaosStatus, ChaosTarget, FaultSpec};
+use crate::modules::telemetry::metrics::get_metrics;
+
+#[derive(Debug, Error)]
+pub enum ChaosError {
+    #[error("fault injection is unavailable; start the server with CHAOS_MODE=true to use it")]
+    Unavailable,
+    #[error("invalid chaos configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Default)]
+struct ChaosState {
+    config: ChaosConfig,
+    injected_total: BTreeMap<ChaosTarget, u64>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+/// Fault injection for rehearsing dependency failures in staging
+///
+/// Shared by clones between the admin endpoint and the wrappers around the Mistral
+/// client and audit storage. Without `CHAOS_MODE=true` the controller is unavailable:
+/// it refuses every configuration, so no fault can be injected.
+#[derive(Clone)]
+pub struct ChaosController {
+    available: bool,
+    state: Arc<Mutex<ChaosState>>,
+}
+
+impl Default for ChaosController {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl ChaosController {
+    pub fn new(available: bool) -> Self {
+        Self {
+            available,
+            state: Arc::new(Mutex::new(ChaosState::default())),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// Replace the configured faults
+    pub fn configure(&self, config: ChaosConfig) -> Result<ChaosStatus, ChaosError> {
+        if !self.available {
+            return Err(ChaosError::Unavailable);
+        }
+        config.validate().map_err(ChaosError::Invalid)?;
+        warn!(
+            chaos = true,
+            faults = ?config.faults,
+            "CHAOS configuration changed"
+        );
+        let mut state = self.state.lock().unwrap();
+        state.config = config;
+        state.updated_at = Some(Utc::now());
+        Ok(status(self.available, &state))
+    }
+
+    pub fn status(&self) -> ChaosStatus {
+        status(self.available, &self.state.lock().unwrap())
+    }
+
+    /// Decide whether this call to `target` is faulted
+    ///
+    /// A fault is counted in `chaos_faults_injected_total` and logged at WARN with
+    /// `chaos = true` before it is returned.
+    pub fn draw(&self, target: ChaosTarget) -> Option<FaultSpec> {
+        let mut state = self.state.lock().unwrap();
+        let fault = *state.config.faults.get(&target)?;
+        if fault.probability < 1.0 && unit_draw() >= fault.probability {
+            return None;
+        }
+        *state.injected_total.entry(target).or_default() += 1;
+        drop(state);
+        get_metrics().increment_chaos_faults(target.as_str(), fault.kind.as_str());
+        warn!(
+            chaos = true,
+            chaos_target = target.as_str(),
+            fault = fault.kind.as_str(),
+            latency_ms = fault.latency_ms,
+            "CHAOS fault injected"
+        );
+        Some(fault)
+    }
+}
+
+fn status(available: bool, state: &ChaosState) -> ChaosStatus {
+    ChaosStatus {
+        available,
+        active: !state.config.faults.is_empty(),
+        faults: state.config.faults.clone(),
+        injected_total: state.injected_total.clone(),
+        updated_at: state.updated_at,
+    }
+}
+
+/// Uniform draw from `[0, 1)`
+fn unit_draw() -> f64 {
+    let mut bytes = [0u8; 8];
+    // The system generator only fails on platforms without one; never fault then
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 1.0;
+    }
+    (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::chaos::dtos::FaultKind;
+
+    fn config(target: ChaosTarget, probability: f64, kind: FaultKind) -> ChaosConfig {
+        ChaosConfig {
+            faults: BTreeMap::from([(
+                target,
+                FaultSpec {
+                    probability,
+                    kind,
+                    latency_ms: (kind == FaultKind::Latency).then_some(5),
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn unavailable_controller_refuses_every_configuration() {
+        let chaos = ChaosController::default();
+        let error = chaos
+            .configure(config(ChaosTarget::Chat, 1.0, FaultKind::Error))
+            .unwrap_err();
+        assert!(matches!(error, ChaosError::Unavailable));
+        assert_eq!(chaos.draw(ChaosTarget::Chat), None);
+        assert!(!chaos.status().active);
+    }
+
+    #[test]
+    fn probabilities_decide_which_calls_fail() {
+        let chaos = ChaosController::new(true);
+        chaos
+            .configure(config(ChaosTarget::Embeddings, 1.0, FaultKind::Error))
+            .unwrap();
+        assert!(chaos.draw(ChaosTarget::Embeddings).is_some());
+        assert_eq!(chaos.draw(ChaosTarget::Chat), None);
+
+        chaos
+            .configure(config(ChaosTarget::Embeddings, 0.0, FaultKind::Latency))
+            .unwrap();
+        assert_eq!(chaos.draw(ChaosTarget::Embeddings), None);
+
+        let status = chaos.status();
+        assert!(status.active);
+        assert_eq!(status.injected_total[&ChaosTarget::Embeddings], 1);
+    }
+
+    #[test]
+    fn invalid_faults_are_rejected() {
+        let chaos = ChaosController::new(true);
+        for invalid in [
+            config(ChaosTarget::Chat, 1.5, FaultKind::Error),
+            config(ChaosTarget::AuditStorage, 1.0, FaultKind::Malformed),
+            ChaosConfig {
+                faults: BTreeMap::from([(
+                    ChaosTarget::Chat,
+                    FaultSpec {
+                        probability: 1.0,
+                        kind: FaultKind::Latency,
+                        latency_ms: None,
+                    },
+                )]),
+            },
+        ] {
+            let error = chaos.configure(invalid).unwrap_err();
+            assert!(matches!(error, ChaosError::Invalid(_)), "{error}");
+        }
+    }
+}
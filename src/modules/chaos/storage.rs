@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use super::dtos::{ChaosTarget, FaultKind};
+use super::service::ChaosController;
+use crate::modules::audit::storage::{
+    AuditStorage, AuditStorageError, AuditTrailResponse, StoredAuditRecord,
+};
+
+/// Audit storage whose writes fail or stall as the [`ChaosController`] decides
+///
+/// Reads, flushes and writes that are not faulted go straight to the wrapped storage.
+pub struct ChaosAuditStorage {
+    inner: Arc<dyn AuditStorage>,
+    chaos: ChaosController,
+}
+
+impl ChaosAuditStorage {
+    pub fn new(inner: Arc<dyn AuditStorage>, chaos: ChaosController) -> Self {
+        Self { inner, chaos }
+    }
+
+    fn inject(&self) -> Result<(), AuditStorageError> {
+        match self.chaos.draw(ChaosTarget::AuditStorage) {
+            Some(fault) if fault.kind == FaultKind::Latency => {
+                // Writes are synchronous, so the delay blocks the writer as a slow disk would
+                std::thread::sleep(Duration::from_millis(fault.latency_ms.unwrap_or_default()));
+                Ok(())
+            }
+            Some(_) => Err(AuditStorageError::DatabaseError(
+                "chaos: injected audit write failure".to_owned(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AuditStorage for ChaosAuditStorage {
+    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
+        self.inject()?;
+        self.inner.append(record)
+    }
+
+    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
+        self.inner.latest_chain_hash()
+    }
+
+    fn append_batch(&self, records: &[StoredAuditRecord]) -> Result<(), AuditStorageError> {
+        self.inject()?;
+        self.inner.append_batch(records)
+    }
+
+    fn flush(&self) -> Result<(), AuditStorageError> {
+        self.inner.flush()
+    }
+
+    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        self.inner.all()
+    }
+
+    fn tail(&self, limit: usize) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        self.inner.tail(limit)
+    }
+
+    fn get_with_filters(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        self.inner
+            .get_with_filters(limit, offset, start_time, end_time, correlation_id)
+    }
+
+    fn get_page(
+        &self,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        self.inner
+            .get_page(cursor, limit, start_time, end_time, correlation_id)
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::modules::audit::proof::{self, AuditProof};
+use crate::modules::eu_law_compliance::service::EuRiskKeywordConfig;
+use crate::modules::prompt_firewall::rules::FirewallRulesConfig;
+use crate::modules::semantic_detection::dtos::SemanticThresholds;
+use crate::workflow::WorkflowPolicy;
+
+/// Version of the snapshot document layout
+pub const CONFIG_SNAPSHOT_VERSION: u32 = 1;
+
+/// Numeric limits used by the detection stages
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ThresholdConfig {
+    pub max_input_length: usize,
+    pub bias_threshold: f32,
+    pub semantic: SemanticThresholds,
+}
+
+/// Every piece of configuration that can be changed without a restart
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RuntimeConfig {
+    pub firewall_rules: FirewallRulesConfig,
+    pub eu_risk_keywords: EuRiskKeywordConfig,
+    pub thresholds: ThresholdConfig,
+    pub workflow_policy: WorkflowPolicy,
+}
+
+impl RuntimeConfig {
+    /// SHA-256 of the canonical JSON encoding
+    pub fn content_hash(&self) -> String {
+        proof::content_hash(self)
+    }
+}
+
+/// Downloadable, versioned copy of the effective runtime configuration
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ConfigSnapshot {
+    pub version: u32,
+    pub taken_at: DateTime<Utc>,
+    /// Hash of `config`; restores are rejected when it does not match
+    pub content_hash: String,
+    pub config: RuntimeConfig,
+}
+
+impl ConfigSnapshot {
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            version: CONFIG_SNAPSHOT_VERSION,
+            taken_at: Utc::now(),
+            content_hash: config.content_hash(),
+            config,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigRestoreResponse {
+    pub status: String,
+    pub previous_hash: String,
+    pub current_hash: String,
+    pub audit_proof: AuditProof,
+}
+
+/// Result of switching a monitor-mode block rule to enforcing
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RulePromotionResponse {
+    pub status: String,
+    pub rule_id: String,
+    pub previous_hash: String,
+    pub current_hash: String,
+    pub audit_proof: AuditProof,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigHistoryResponse {
+    /// Stored snapshots, newest first
+    pub snapshots: Vec<ConfigSnapshot>,
+}
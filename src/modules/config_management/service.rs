@@ -0,0 +1,319 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::Utc;
+use thiserror::Error;
+use tracing::info;
+
+use super::dtos::{
+    CONFIG_SNAPSHOT_VERSION, ConfigRestoreResponse, ConfigSnapshot, RulePromotionResponse,
+    RuntimeConfig, ThresholdConfig,
+};
+use super::storage::{ConfigHistoryError, ConfigHistoryStorage};
+use crate::modules::audit::logger::{AuditError, AuditLogger, ConfigChangeEvent};
+use crate::modules::bias_detection::service::{BiasDetectionService, validate_threshold};
+use crate::modules::eu_law_compliance::service::EuLawComplianceService;
+use crate::modules::prompt_firewall::rules::{
+    AssertionFailure, CompiledFirewallRules, FirewallRulesConfig, RuleMode, RulesLoadError,
+    report_expiry,
+};
+use crate::modules::prompt_firewall::service::{PromptFirewallService, validate_max_input_length};
+use crate::modules::semantic_detection::service::SemanticDetectionService;
+use crate::workflow::{ComplianceEngine, WorkflowPolicy};
+
+/// Snapshots, restores and tracks the runtime-tunable configuration of an engine
+#[derive(Clone)]
+pub struct ConfigManagementService {
+    firewall_service: PromptFirewallService,
+    semantic_service: SemanticDetectionService,
+    bias_service: BiasDetectionService,
+    eu_compliance_service: EuLawComplianceService,
+    policy: Arc<RwLock<WorkflowPolicy>>,
+    audit_logger: AuditLogger,
+    history: Arc<dyn ConfigHistoryStorage>,
+    history_limit: usize,
+}
+
+impl ConfigManagementService {
+    /// Manage the configuration shared with `engine`'s services
+    pub fn new(
+        engine: &ComplianceEngine,
+        history: Arc<dyn ConfigHistoryStorage>,
+        history_limit: usize,
+    ) -> Self {
+        Self {
+            firewall_service: engine.firewall_service().clone(),
+            semantic_service: engine.semantic_service().clone(),
+            bias_service: engine.bias_service().clone(),
+            eu_compliance_service: EuLawComplianceService,
+            policy: engine.policy_lock().clone(),
+            audit_logger: engine.audit_logger().clone(),
+            history,
+            history_limit,
+        }
+    }
+
+    /// Effective configuration as currently applied
+    pub fn current_config(&self) -> RuntimeConfig {
+        self.config_with(
+            self.firewall_service.rules_config(),
+            self.firewall_service.max_input_length(),
+        )
+    }
+
+    /// Effective configuration with the firewall section given rather than read, for
+    /// callers already holding the firewall lock
+    fn config_with(
+        &self,
+        firewall_rules: FirewallRulesConfig,
+        max_input_length: usize,
+    ) -> RuntimeConfig {
+        RuntimeConfig {
+            firewall_rules,
+            eu_risk_keywords: self.eu_compliance_service.risk_keyword_config(),
+            thresholds: ThresholdConfig {
+                max_input_length,
+                bias_threshold: self.bias_service.default_threshold(),
+                semantic: self.semantic_service.thresholds(),
+            },
+            workflow_policy: self.policy.read().unwrap().clone(),
+        }
+    }
+
+    /// Capture the effective configuration and keep it in the history
+    pub fn snapshot(&self) -> Result<ConfigSnapshot, ConfigManagementError> {
+        let snapshot = ConfigSnapshot::new(self.current_config());
+        self.history.record(&snapshot, self.history_limit)?;
+        Ok(snapshot)
+    }
+
+    /// Stored snapshots, newest first
+    pub fn history(&self) -> Result<Vec<ConfigSnapshot>, ConfigManagementError> {
+        Ok(self.history.list()?)
+    }
+
+    /// Validate every section of `snapshot` and apply them all at once.
+    ///
+    /// Nothing is changed unless every section validates, the EU keyword file
+    /// is persisted and the change is recorded in the audit trail.
+    pub fn restore(
+        &self,
+        correlation_id: &str,
+        snapshot: ConfigSnapshot,
+    ) -> Result<ConfigRestoreResponse, ConfigManagementError> {
+        if snapshot.version != CONFIG_SNAPSHOT_VERSION {
+            return Err(ConfigManagementError::UnsupportedVersion(snapshot.version));
+        }
+        let new_hash = snapshot.config.content_hash();
+        if new_hash != snapshot.content_hash {
+            return Err(ConfigManagementError::HashMismatch {
+                expected: snapshot.content_hash,
+                actual: new_hash,
+            });
+        }
+
+        let RuntimeConfig {
+            firewall_rules,
+            eu_risk_keywords,
+            thresholds,
+            workflow_policy,
+        } = snapshot.config.clone();
+        let compiled_rules =
+            CompiledFirewallRules::compile(firewall_rules).map_err(|error| match error {
+                RulesLoadError::Invalid(reason) => invalid("firewall_rules", reason),
+                RulesLoadError::AssertionsFailed(failures) => {
+                    ConfigManagementError::RuleAssertionsFailed(failures)
+                }
+            })?;
+        eu_risk_keywords
+            .validate()
+            .map_err(|reason| invalid("eu_risk_keywords", reason))?;
+        validate_max_input_length(thresholds.max_input_length)
+            .and_then(|_| validate_threshold(thresholds.bias_threshold))
+            .and_then(|_| thresholds.semantic.validate())
+            .map_err(|reason| invalid("thresholds", reason))?;
+
+        // Hold every write lock so requests never observe a half-applied configuration
+        let mut firewall = self.firewall_service.runtime().write().unwrap();
+        let mut semantic = self.semantic_service.thresholds_lock().write().unwrap();
+        let mut bias_threshold = self.bias_service.default_threshold_lock().write().unwrap();
+        let mut policy = self.policy.write().unwrap();
+        let mut keywords = self
+            .eu_compliance_service
+            .risk_keyword_lock()
+            .write()
+            .unwrap();
+
+        let previous_hash = RuntimeConfig {
+            firewall_rules: firewall.rules.config().clone(),
+            eu_risk_keywords: keywords.clone(),
+            thresholds: ThresholdConfig {
+                max_input_length: firewall.max_input_length,
+                bias_threshold: *bias_threshold,
+                semantic: *semantic,
+            },
+            workflow_policy: policy.clone(),
+        }
+        .content_hash();
+
+        let keywords_changed = *keywords != eu_risk_keywords;
+        if keywords_changed {
+            self.eu_compliance_service
+                .persist_risk_keywords(&eu_risk_keywords)?;
+        }
+
+        let audit_proof = match self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: "restore".to_owned(),
+            previous_hash: previous_hash.clone(),
+            new_hash: new_hash.clone(),
+            snapshot_version: Some(snapshot.version),
+            detail: None,
+        }) {
+            Ok(proof) => proof,
+            Err(error) => {
+                if keywords_changed {
+                    // Best effort: put the previous keyword file back
+                    let _ = self.eu_compliance_service.persist_risk_keywords(&keywords);
+                }
+                return Err(error.into());
+            }
+        };
+
+        firewall.max_input_length = thresholds.max_input_length;
+        report_expiry(&compiled_rules, Utc::now());
+        firewall.rules = Arc::new(compiled_rules);
+        *semantic = thresholds.semantic;
+        *bias_threshold = thresholds.bias_threshold;
+        *policy = workflow_policy;
+        *keywords = eu_risk_keywords;
+        if keywords_changed {
+            self.eu_compliance_service.bump_risk_keyword_version();
+        }
+        drop((firewall, semantic, bias_threshold, policy, keywords));
+        self.firewall_service.archive_current_rules();
+
+        info!(
+            "Runtime configuration restored: {} -> {}",
+            previous_hash, new_hash
+        );
+        self.history.record(&snapshot, self.history_limit)?;
+
+        Ok(ConfigRestoreResponse {
+            status: "success".to_owned(),
+            previous_hash,
+            current_hash: new_hash,
+            audit_proof,
+        })
+    }
+
+    /// Switch the monitor-mode block rule `rule_id` to enforcing.
+    ///
+    /// The promoted rule set must still satisfy its assertions. The change is recorded
+    /// in the audit trail and the history, and applies from the next request; like a
+    /// restore, it is not written back to the rules file.
+    pub fn promote_rule(
+        &self,
+        correlation_id: &str,
+        rule_id: &str,
+    ) -> Result<RulePromotionResponse, ConfigManagementError> {
+        let mut firewall = self.firewall_service.runtime().write().unwrap();
+        let current = firewall.rules.config().clone();
+        let mut promoted = current.clone();
+        let rule = promoted
+            .block_rules
+            .iter_mut()
+            .find(|rule| rule.id == rule_id)
+            .ok_or_else(|| ConfigManagementError::UnknownRule(rule_id.to_owned()))?;
+        if !rule.is_monitored() {
+            return Err(ConfigManagementError::NotMonitored(rule_id.to_owned()));
+        }
+        rule.mode = RuleMode::Remove;
+        let compiled_rules =
+            CompiledFirewallRules::compile(promoted.clone()).map_err(|error| match error {
+                RulesLoadError::Invalid(reason) => invalid("firewall_rules", reason),
+                RulesLoadError::AssertionsFailed(failures) => {
+                    ConfigManagementError::RuleAssertionsFailed(failures)
+                }
+            })?;
+
+        let previous_hash = self
+            .config_with(current.clone(), firewall.max_input_length)
+            .content_hash();
+        let snapshot = ConfigSnapshot::new(self.config_with(promoted, firewall.max_input_length));
+        let audit_proof = self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: "firewall_rule_promoted".to_owned(),
+            previous_hash: previous_hash.clone(),
+            new_hash: snapshot.content_hash.clone(),
+            snapshot_version: Some(snapshot.version),
+            detail: Some(format!(
+                "block rule {rule_id} switched from monitor to enforcing; firewall rules {} -> {}",
+                firewall.rules.fingerprint(),
+                compiled_rules.fingerprint()
+            )),
+        })?;
+
+        report_expiry(&compiled_rules, Utc::now());
+        firewall.rules = Arc::new(compiled_rules);
+        drop(firewall);
+        self.firewall_service.archive_current_rules();
+
+        info!(
+            "Firewall rule {} promoted from monitor to enforcing",
+            rule_id
+        );
+        self.history.record(&snapshot, self.history_limit)?;
+
+        Ok(RulePromotionResponse {
+            status: "success".to_owned(),
+            rule_id: rule_id.to_owned(),
+            previous_hash,
+            current_hash: snapshot.content_hash,
+            audit_proof,
+        })
+    }
+}
+
+fn invalid(section: &'static str, reason: String) -> ConfigManagementError {
+    ConfigManagementError::InvalidSection { section, reason }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigManagementError {
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+    #[error("snapshot content hash mismatch: expected {expected}, computed {actual}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("invalid {section} section: {reason}")]
+    InvalidSection {
+        section: &'static str,
+        reason: String,
+    },
+    #[error("{} of the firewall rule assertions failed", .0.len())]
+    RuleAssertionsFailed(Vec<AssertionFailure>),
+    #[error("no block rule with id {0}")]
+    UnknownRule(String),
+    #[error("block rule {0} is not in monitor mode")]
+    NotMonitored(String),
+    #[error("failed to persist configuration: {0}")]
+    Persist(#[from] std::io::Error),
+    #[error("failed to audit configuration change: {0}")]
+    Audit(#[from] AuditError),
+    #[error("config history failure: {0}")]
+    History(#[from] ConfigHistoryError),
+}
+
+impl ConfigManagementError {
+    /// Whether the error was caused by the submitted document rather than the server
+    pub fn is_rejected_input(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsupportedVersion(_)
+                | Self::HashMismatch { .. }
+                | Self::InvalidSection { .. }
+                | Self::RuleAssertionsFailed(_)
+        )
+    }
+}
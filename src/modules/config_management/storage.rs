@@ -0,0 +1,118 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::ConfigSnapshot;
+
+#[cfg(feature = "sled-storage")]
+const CONFIG_HISTORY_TREE: &str = "config_snapshots";
+
+pub trait ConfigHistoryStorage: Send + Sync {
+    /// Store a snapshot and drop the oldest entries beyond `limit`
+    fn record(&self, snapshot: &ConfigSnapshot, limit: usize) -> Result<(), ConfigHistoryError>;
+    /// Stored snapshots, newest first
+    fn list(&self) -> Result<Vec<ConfigSnapshot>, ConfigHistoryError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryConfigHistory {
+    inner: Arc<Mutex<Vec<ConfigSnapshot>>>,
+}
+
+impl InMemoryConfigHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConfigHistoryStorage for InMemoryConfigHistory {
+    fn record(&self, snapshot: &ConfigSnapshot, limit: usize) -> Result<(), ConfigHistoryError> {
+        let mut guard = self
+            .inner
+            .lock()
+            .map_err(|_| ConfigHistoryError::LockPoisoned)?;
+        guard.push(snapshot.clone());
+        let excess = guard.len().saturating_sub(limit);
+        guard.drain(..excess);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<ConfigSnapshot>, ConfigHistoryError> {
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|_| ConfigHistoryError::LockPoisoned)?;
+        Ok(guard.iter().rev().cloned().collect())
+    }
+}
+
+/// Snapshot history kept in its own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledConfigHistory {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledConfigHistory {
+    pub fn new(db: &Db) -> Result<Self, ConfigHistoryError> {
+        let tree = db
+            .open_tree(CONFIG_HISTORY_TREE)
+            .map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl ConfigHistoryStorage for SledConfigHistory {
+    fn record(&self, snapshot: &ConfigSnapshot, limit: usize) -> Result<(), ConfigHistoryError> {
+        let serialized = serde_json::to_vec(snapshot)
+            .map_err(|e| ConfigHistoryError::SerializationError(e.to_string()))?;
+
+        // Timestamp-prefixed keys keep the tree in chronological order
+        let key = format!(
+            "{:020}_{}",
+            snapshot.taken_at.timestamp_nanos_opt().unwrap_or(0),
+            snapshot.content_hash
+        );
+        self.tree
+            .insert(key, serialized)
+            .map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+
+        let excess = self.tree.len().saturating_sub(limit);
+        for entry in self.tree.iter().keys().take(excess).collect::<Vec<_>>() {
+            let key = entry.map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+            self.tree
+                .remove(key)
+                .map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+        }
+
+        self.tree
+            .flush()
+            .map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<ConfigSnapshot>, ConfigHistoryError> {
+        let mut snapshots = Vec::new();
+        for result in self.tree.iter().rev() {
+            let (_, data) = result.map_err(|e| ConfigHistoryError::DatabaseError(e.to_string()))?;
+            let snapshot: ConfigSnapshot = serde_json::from_slice(&data)
+                .map_err(|e| ConfigHistoryError::SerializationError(e.to_string()))?;
+            snapshots.push(snapshot);
+        }
+        Ok(snapshots)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigHistoryError {
+    #[error("config history lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
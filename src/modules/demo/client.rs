@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::dtos::DemoConfig;
+use crate::modules::mistral_ai::client::{MistralClient, MistralClientError, MockMistralClient};
+use crate::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationBatchRequest,
+    ModerationRequest, ModerationResponse, TranslationRequest, TranslationResponse,
+};
+
+const MOCK_EMBEDDING_SEED: u64 = 0;
+/// Dimension of `mistral-embed`
+const MOCK_EMBEDDING_DIMENSION: usize = 1024;
+
+/// Mistral client for the public demo
+///
+/// Chat completions are refused, so no prompt can spend the generation budget even if a
+/// code path other than generation asks for one. Moderation and embeddings are answered
+/// locally when the demo configuration says so; everything else goes to the wrapped
+/// client.
+pub struct DemoMistralClient {
+    inner: Arc<dyn MistralClient>,
+    local: MockMistralClient,
+    config: DemoConfig,
+}
+
+impl DemoMistralClient {
+    pub fn new(inner: Arc<dyn MistralClient>, config: DemoConfig) -> Self {
+        Self {
+            inner,
+            local: MockMistralClient::default()
+                .with_deterministic_embeddings(MOCK_EMBEDDING_SEED, MOCK_EMBEDDING_DIMENSION),
+            config,
+        }
+    }
+
+    fn moderation(&self) -> &dyn MistralClient {
+        if self.config.mock_moderation {
+            &self.local
+        } else {
+            self.inner.as_ref()
+        }
+    }
+}
+
+#[async_trait]
+impl MistralClient for DemoMistralClient {
+    async fn chat_completion(
+        &self,
+        _request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralClientError> {
+        Err(MistralClientError::ApiError {
+            status: 403,
+            message: "chat completions are disabled in demo mode".to_owned(),
+        })
+    }
+
+    async fn moderate(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, MistralClientError> {
+        self.moderation().moderate(request).await
+    }
+
+    async fn moderate_batch(
+        &self,
+        request: ModerationBatchRequest,
+    ) -> Result<Vec<ModerationResponse>, MistralClientError> {
+        self.moderation().moderate_batch(request).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, MistralClientError> {
+        if self.config.mock_embeddings {
+            self.local.embeddings(request).await
+        } else {
+            self.inner.embeddings(request).await
+        }
+    }
+
+    async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.inner.list_models().await
+    }
+
+    async fn detect_language(
+        &self,
+        request: LanguageDetectionRequest,
+    ) -> Result<LanguageDetectionResponse, MistralClientError> {
+        self.inner.detect_language(request).await
+    }
+
+    async fn translate_text(
+        &self,
+        request: TranslationRequest,
+    ) -> Result<TranslationResponse, MistralClientError> {
+        self.inner.translate_text(request).await
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// A public playground: real checks, canned answers, tight per-address quotas
+///
+/// Generation never calls Mistral, audit records keep prompt hashes only, and only the
+/// public compliance endpoints are served.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DemoConfig {
+    pub enabled: bool,
+    /// Requests each client address may send per window
+    pub quota_requests: u32,
+    pub quota_window_secs: u64,
+    /// Answer input and output moderation locally instead of calling Mistral
+    pub mock_moderation: bool,
+    /// Embed locally instead of calling Mistral; the semantic stage then only recognizes
+    /// the attack templates verbatim
+    pub mock_embeddings: bool,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quota_requests: 20,
+            quota_window_secs: 3600,
+            mock_moderation: false,
+            mock_embeddings: false,
+        }
+    }
+}
+
+impl DemoConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.quota_requests == 0 {
+            return Err("demo quota must allow at least one request".to_owned());
+        }
+        if self.quota_window_secs == 0 {
+            return Err("demo quota window must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+}
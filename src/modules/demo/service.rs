@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use super::dtos::DemoConfig;
+use crate::modules::prompt_firewall::dtos::FirewallAction;
+
+/// Addresses tracked before windows that have ended are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Model name recorded for canned answers
+pub const DEMO_MODEL: &str = "demo-canned";
+
+/// Answer given in place of generation, chosen by what the firewall did with the prompt
+pub fn canned_response(action: &FirewallAction) -> &'static str {
+    match action {
+        FirewallAction::Sanitize => {
+            "This is a demo answer. Parts of your prompt were removed by the firewall \
+             before it would have reached the model; the evidence shows what was removed \
+             and why."
+        }
+        FirewallAction::Flag => {
+            "This is a demo answer. Your prompt was allowed but flagged for review; the \
+             evidence shows which checks raised it."
+        }
+        _ => {
+            "This is a demo answer. Your prompt passed every check and would now be sent \
+             to the model; the evidence shows what each check found."
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "The demo allows {limit} requests per {window} from each address. \
+     Please try again in {} minute(s).", retry_after_secs.div_ceil(60)
+)]
+pub struct QuotaExceeded {
+    pub limit: u32,
+    pub window: String,
+    pub retry_after_secs: u64,
+}
+
+/// Fixed-window request quota per client address
+#[derive(Clone)]
+pub struct DemoQuota {
+    limit: u32,
+    window: Duration,
+    clients: Arc<Mutex<HashMap<IpAddr, ClientWindow>>>,
+}
+
+struct ClientWindow {
+    started_at: Instant,
+    requests: u32,
+}
+
+impl DemoQuota {
+    pub fn new(config: &DemoConfig) -> Self {
+        Self {
+            limit: config.quota_requests,
+            window: Duration::from_secs(config.quota_window_secs),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Count a request from `client`, refusing it once the window's quota is used up
+    pub fn admit(&self, client: IpAddr) -> Result<(), QuotaExceeded> {
+        self.admit_at(client, Instant::now())
+    }
+
+    fn admit_at(&self, client: IpAddr, now: Instant) -> Result<(), QuotaExceeded> {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_TRACKED_CLIENTS {
+            clients.retain(|_, window| now.duration_since(window.started_at) < self.window);
+        }
+        let window = clients.entry(client).or_insert(ClientWindow {
+            started_at: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started_at) >= self.window {
+            *window = ClientWindow {
+                started_at: now,
+                requests: 0,
+            };
+        }
+        if window.requests >= self.limit {
+            let remaining = self.window - now.duration_since(window.started_at);
+            return Err(QuotaExceeded {
+                limit: self.limit,
+                window: describe(self.window),
+                retry_after_secs: remaining.as_secs().max(1),
+            });
+        }
+        window.requests += 1;
+        Ok(())
+    }
+}
+
+fn describe(window: Duration) -> String {
+    match window.as_secs() {
+        3600 => "hour".to_owned(),
+        60 => "minute".to_owned(),
+        secs if secs % 3600 == 0 => format!("{} hours", secs / 3600),
+        secs if secs % 60 == 0 => format!("{} minutes", secs / 60),
+        secs => format!("{secs} seconds"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(limit: u32) -> DemoQuota {
+        DemoQuota::new(&DemoConfig {
+            enabled: true,
+            quota_requests: limit,
+            ..DemoConfig::default()
+        })
+    }
+
+    #[test]
+    fn each_address_gets_its_own_quota() {
+        let quota = quota(2);
+        let now = Instant::now();
+        let first: IpAddr = "203.0.113.1".parse().unwrap();
+        let second: IpAddr = "203.0.113.2".parse().unwrap();
+
+        assert!(quota.admit_at(first, now).is_ok());
+        assert!(quota.admit_at(first, now).is_ok());
+        let exceeded = quota.admit_at(first, now).unwrap_err();
+        assert_eq!(exceeded.retry_after_secs, 3600);
+        assert_eq!(
+            exceeded.to_string(),
+            "The demo allows 2 requests per hour from each address. Please try again in \
+             60 minute(s)."
+        );
+        assert!(quota.admit_at(second, now).is_ok());
+    }
+
+    #[test]
+    fn the_quota_resets_when_the_window_ends() {
+        let quota = quota(1);
+        let now = Instant::now();
+        let client: IpAddr = "203.0.113.1".parse().unwrap();
+
+        assert!(quota.admit_at(client, now).is_ok());
+        let exceeded = quota
+            .admit_at(client, now + Duration::from_secs(3000))
+            .unwrap_err();
+        assert_eq!(exceeded.retry_after_secs, 600);
+        assert!(
+            quota
+                .admit_at(client, now + Duration::from_secs(3600))
+                .is_ok()
+        );
+    }
+}
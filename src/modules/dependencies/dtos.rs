@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An external dependency validated at startup
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Dependency {
+    /// The configured generation, moderation and embedding models
+    Mistral,
+    /// Embeddings of the semantic attack bank, which come from Mistral
+    Semantic,
+}
+
+impl Dependency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mistral => "mistral",
+            Self::Semantic => "semantic",
+        }
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// When a dependency is validated, and what a failure does
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPolicy {
+    /// Validate before serving and refuse to start on failure
+    #[default]
+    FailFast,
+    /// Serve at once, not ready until a background validation succeeds
+    Lazy,
+    /// Never validate
+    Skip,
+}
+
+impl StartupPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FailFast => "fail_fast",
+            Self::Lazy => "lazy",
+            Self::Skip => "skip",
+        }
+    }
+}
+
+impl std::str::FromStr for StartupPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "fail_fast" => Ok(Self::FailFast),
+            "lazy" => Ok(Self::Lazy),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "unknown startup policy '{other}' (expected fail_fast, lazy or skip)"
+            )),
+        }
+    }
+}
+
+/// Per-dependency startup policies and the backoff of lazy retries
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StartupConfig {
+    pub mistral: StartupPolicy,
+    pub semantic: StartupPolicy,
+    /// Wait before the first retry of a failed lazy validation, doubled per attempt
+    pub retry_initial_backoff_ms: u64,
+    /// Longest wait between lazy retries
+    pub retry_max_backoff_ms: u64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            mistral: StartupPolicy::default(),
+            semantic: StartupPolicy::default(),
+            retry_initial_backoff_ms: 1_000,
+            retry_max_backoff_ms: 60_000,
+        }
+    }
+}
+
+impl StartupConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.retry_initial_backoff_ms == 0 {
+            return Err("startup retry initial backoff must be greater than zero".to_owned());
+        }
+        if self.retry_max_backoff_ms < self.retry_initial_backoff_ms {
+            return Err(format!(
+                "startup retry max backoff ({} ms) is shorter than the initial backoff ({} ms)",
+                self.retry_max_backoff_ms, self.retry_initial_backoff_ms
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where a dependency's validation stands
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyState {
+    /// Lazy validation has not finished its first attempt
+    Pending,
+    Healthy,
+    /// The last validation failed; a lazy dependency keeps retrying
+    Unhealthy,
+    /// The policy is `skip`, so it is never validated
+    Skipped,
+}
+
+/// One dependency in `GET /ready` and the system summary
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DependencyStatus {
+    pub dependency: Dependency,
+    pub policy: StartupPolicy,
+    pub state: DependencyState,
+    /// Validations run so far
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+impl DependencyStatus {
+    /// Whether the server can count on this dependency, or was told not to check it
+    pub fn is_ready(&self) -> bool {
+        matches!(
+            self.state,
+            DependencyState::Healthy | DependencyState::Skipped
+        )
+    }
+}
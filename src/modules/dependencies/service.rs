@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::Utc;
+use thiserror::Error;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use super::dtos::{Dependency, DependencyState, DependencyStatus, StartupConfig, StartupPolicy};
+
+/// One validation of a dependency; `Err` carries the reason it failed
+pub type Probe =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync>;
+
+/// Wrap an async validation as a [`Probe`] that can be run again on every retry
+pub fn probe<F, Fut>(validate: F) -> Probe
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    Arc::new(move || Box::pin(validate()))
+}
+
+#[derive(Debug, Error)]
+#[error("{dependency} failed startup validation: {message}")]
+pub struct StartupError {
+    pub dependency: Dependency,
+    pub message: String,
+}
+
+/// Validates dependencies by their [`StartupPolicy`] and tracks the outcome for readiness
+///
+/// Dependencies are scheduled in order; a lazy one scheduled after a prerequisite waits
+/// for it before its own first attempt.
+#[derive(Clone)]
+pub struct DependencyHealth {
+    statuses: Arc<RwLock<BTreeMap<Dependency, DependencyStatus>>>,
+    /// Bumped on every status change, so waiting retries notice prerequisites recover
+    changes: Arc<watch::Sender<u64>>,
+    config: StartupConfig,
+}
+
+impl Default for DependencyHealth {
+    fn default() -> Self {
+        Self::new(StartupConfig::default())
+    }
+}
+
+impl DependencyHealth {
+    pub fn new(config: StartupConfig) -> Self {
+        Self {
+            statuses: Arc::new(RwLock::new(BTreeMap::new())),
+            changes: Arc::new(watch::Sender::new(0)),
+            config,
+        }
+    }
+
+    /// Validate `dependency` as `policy` says
+    ///
+    /// `fail_fast` awaits `probe` and returns its failure; `lazy` returns at once and
+    /// retries in the background with exponential backoff until `probe` succeeds, after
+    /// `after` is ready if given; `skip` never runs it.
+    pub async fn schedule(
+        &self,
+        dependency: Dependency,
+        policy: StartupPolicy,
+        after: Option<Dependency>,
+        probe: Probe,
+    ) -> Result<(), StartupError> {
+        match policy {
+            StartupPolicy::Skip => {
+                info!("Skipping startup validation of {}", dependency);
+                self.record(dependency, policy, DependencyState::Skipped, None);
+            }
+            StartupPolicy::FailFast => {
+                info!("Validating {} at startup...", dependency);
+                let outcome = probe().await;
+                if let Err(message) = outcome {
+                    error!("{} validation failed: {}", dependency, message);
+                    self.record(
+                        dependency,
+                        policy,
+                        DependencyState::Unhealthy,
+                        Some(message.clone()),
+                    );
+                    return Err(StartupError {
+                        dependency,
+                        message,
+                    });
+                }
+                info!("{} validated successfully", dependency);
+                self.record(dependency, policy, DependencyState::Healthy, None);
+            }
+            StartupPolicy::Lazy => {
+                info!("Validating {} in the background", dependency);
+                self.set(dependency, |_| DependencyStatus {
+                    dependency,
+                    policy,
+                    state: DependencyState::Pending,
+                    attempts: 0,
+                    last_error: None,
+                    checked_at: None,
+                });
+                self.spawn_retries(dependency, after, probe);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every scheduled dependency, in declaration order
+    pub fn statuses(&self) -> Vec<DependencyStatus> {
+        self.statuses.read().unwrap().values().cloned().collect()
+    }
+
+    /// Whether every scheduled dependency is healthy or skipped
+    pub fn is_ready(&self) -> bool {
+        self.statuses
+            .read()
+            .unwrap()
+            .values()
+            .all(DependencyStatus::is_ready)
+    }
+
+    fn is_dependency_ready(&self, dependency: Dependency) -> bool {
+        self.statuses
+            .read()
+            .unwrap()
+            .get(&dependency)
+            .is_none_or(DependencyStatus::is_ready)
+    }
+
+    fn spawn_retries(&self, dependency: Dependency, after: Option<Dependency>, probe: Probe) {
+        let health = self.clone();
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_millis(health.config.retry_initial_backoff_ms);
+            let max_backoff = Duration::from_millis(health.config.retry_max_backoff_ms);
+            loop {
+                if let Some(prerequisite) = after {
+                    let mut changes = health.changes.subscribe();
+                    while !health.is_dependency_ready(prerequisite) {
+                        if changes.changed().await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                match probe().await {
+                    Ok(()) => {
+                        info!("{} validated successfully; now ready", dependency);
+                        health.record(
+                            dependency,
+                            StartupPolicy::Lazy,
+                            DependencyState::Healthy,
+                            None,
+                        );
+                        return;
+                    }
+                    Err(message) => {
+                        warn!(
+                            "{} validation failed, retrying in {:?}: {}",
+                            dependency, backoff, message
+                        );
+                        health.record(
+                            dependency,
+                            StartupPolicy::Lazy,
+                            DependencyState::Unhealthy,
+                            Some(message),
+                        );
+                    }
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+            }
+        });
+    }
+
+    /// Record the outcome of one validation attempt
+    fn record(
+        &self,
+        dependency: Dependency,
+        policy: StartupPolicy,
+        state: DependencyState,
+        last_error: Option<String>,
+    ) {
+        let attempted = state != DependencyState::Skipped;
+        self.set(dependency, |previous| DependencyStatus {
+            dependency,
+            policy,
+            state,
+            attempts: previous.map_or(0, |status| status.attempts) + u32::from(attempted),
+            last_error,
+            checked_at: attempted.then(Utc::now),
+        });
+    }
+
+    fn set(
+        &self,
+        dependency: Dependency,
+        status: impl FnOnce(Option<&DependencyStatus>) -> DependencyStatus,
+    ) {
+        {
+            let mut statuses = self.statuses.write().unwrap();
+            let next = status(statuses.get(&dependency));
+            statuses.insert(dependency, next);
+        }
+        self.changes.send_modify(|version| *version += 1);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// When a request counts as slow, and how many diagnostics are kept
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SlowRequestPolicy {
+    /// Requests slower than this are diagnosed, unless their route has its own threshold
+    pub threshold_ms: u64,
+    /// Thresholds keyed by route template, such as `/api/compliance/check`
+    pub route_thresholds: BTreeMap<String, u64>,
+    /// Diagnostics kept; the oldest is dropped first
+    pub capacity: usize,
+}
+
+impl Default for SlowRequestPolicy {
+    fn default() -> Self {
+        Self {
+            threshold_ms: 5_000,
+            route_thresholds: BTreeMap::new(),
+            capacity: 100,
+        }
+    }
+}
+
+impl SlowRequestPolicy {
+    /// Reject zero thresholds and an empty buffer
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold_ms == 0 {
+            return Err("slow request threshold must be greater than zero".to_owned());
+        }
+        if let Some((route, _)) = self.route_thresholds.iter().find(|(_, ms)| **ms == 0) {
+            return Err(format!(
+                "slow request threshold for {route} must be greater than zero"
+            ));
+        }
+        if self.capacity == 0 {
+            return Err("slow request buffer must hold at least one entry".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Parse `route=milliseconds` entries, as given in `SLOW_REQUEST_ROUTE_THRESHOLDS`
+    pub fn parse_route_thresholds(entries: &[String]) -> Result<BTreeMap<String, u64>, String> {
+        entries
+            .iter()
+            .map(|entry| {
+                let (route, ms) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected route=milliseconds, got {entry:?}"))?;
+                let route = route.trim();
+                if !route.starts_with('/') {
+                    return Err(format!("route {route:?} must start with '/'"));
+                }
+                let ms = ms
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid threshold {:?} for {route}", ms.trim()))?;
+                Ok((route.to_owned(), ms))
+            })
+            .collect()
+    }
+
+    /// Threshold that applies to `route`
+    pub fn threshold_for(&self, route: &str) -> u64 {
+        self.route_thresholds
+            .get(route)
+            .copied()
+            .unwrap_or(self.threshold_ms)
+    }
+}
+
+/// Time spent in one workflow stage
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageTiming {
+    pub stage: String,
+    pub duration_ms: u64,
+}
+
+/// What a slow request spent its time on
+///
+/// Built from timings the request gathered anyway; never holds prompt or output text.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SlowRequestRecord {
+    pub correlation_id: String,
+    pub method: String,
+    /// Route template, such as `/api/compliance/check`
+    pub route: String,
+    pub status: u16,
+    pub recorded_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub threshold_ms: u64,
+    /// Workflow stages in the order they ran; empty for routes without a workflow
+    pub stages: Vec<StageTiming>,
+    /// The stage that took longest, if any ran
+    pub slowest_stage: Option<String>,
+    /// Mistral API retries by endpoint
+    pub mistral_retries: BTreeMap<String, u32>,
+    /// Time spent waiting for a Mistral concurrency slot, by operation
+    pub queue_wait_ms: BTreeMap<String, u64>,
+    /// Declared request body size
+    pub request_bytes: Option<u64>,
+    /// Response body size, when known before streaming
+    pub response_bytes: Option<u64>,
+}
+
+/// Recent slow requests returned by `GET /api/debug/slow-requests`, newest first
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SlowRequestsResponse {
+    pub policy: SlowRequestPolicy,
+    pub total: usize,
+    pub requests: Vec<SlowRequestRecord>,
+}
@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::warn;
+
+use super::dtos::{SlowRequestPolicy, SlowRequestRecord, SlowRequestsResponse, StageTiming};
+use crate::modules::telemetry::metrics::get_metrics;
+
+tokio::task_local! {
+    /// Timings reported by the stages of the request running on this task
+    static TIMINGS: Arc<Mutex<RequestTimings>>;
+}
+
+/// Timings a request gathered while it ran
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestTimings {
+    pub stages: Vec<StageTiming>,
+    pub mistral_retries: BTreeMap<String, u32>,
+    pub queue_wait_ms: BTreeMap<String, u64>,
+}
+
+fn with_timings(update: impl FnOnce(&mut RequestTimings)) {
+    // Outside a request, such as in background tasks, there is nothing to report to
+    let _ = TIMINGS.try_with(|timings| update(&mut timings.lock().unwrap()));
+}
+
+/// Report a finished workflow stage to the request running on this task
+pub fn record_stage(stage: &str, duration_ms: u64) {
+    with_timings(|timings| {
+        timings.stages.push(StageTiming {
+            stage: stage.to_owned(),
+            duration_ms,
+        })
+    });
+}
+
+/// Report a Mistral API retry to the request running on this task
+pub fn record_mistral_retry(endpoint: &str) {
+    with_timings(|timings| {
+        *timings
+            .mistral_retries
+            .entry(endpoint.to_owned())
+            .or_default() += 1
+    });
+}
+
+/// Report time spent waiting for a Mistral concurrency slot
+pub fn record_queue_wait(operation: &str, wait: Duration) {
+    let wait_ms = wait.as_millis().try_into().unwrap_or(u64::MAX);
+    with_timings(|timings| {
+        *timings
+            .queue_wait_ms
+            .entry(operation.to_owned())
+            .or_default() += wait_ms
+    });
+}
+
+/// Run `future` and collect the timings its stages report along the way
+pub async fn collect_timings<F: Future>(future: F) -> (F::Output, RequestTimings) {
+    let timings = Arc::new(Mutex::new(RequestTimings::default()));
+    let output = TIMINGS.scope(timings.clone(), future).await;
+    let collected = std::mem::take(&mut *timings.lock().unwrap());
+    (output, collected)
+}
+
+/// A request that has been answered
+#[derive(Clone, Debug)]
+pub struct CompletedRequest<'a> {
+    pub correlation_id: &'a str,
+    pub method: &'a str,
+    /// Route template
+    pub route: &'a str,
+    pub status: u16,
+    pub duration: Duration,
+    pub request_bytes: Option<u64>,
+    pub response_bytes: Option<u64>,
+}
+
+/// Ring buffer of diagnostics for requests that exceeded their latency threshold
+///
+/// Shared by clones; the request-context middleware feeds it when the router carries
+/// it as an extension.
+#[derive(Clone)]
+pub struct SlowRequestLog {
+    policy: SlowRequestPolicy,
+    entries: Arc<Mutex<VecDeque<SlowRequestRecord>>>,
+}
+
+impl Default for SlowRequestLog {
+    fn default() -> Self {
+        Self::new(SlowRequestPolicy::default())
+    }
+}
+
+impl SlowRequestLog {
+    pub fn new(policy: SlowRequestPolicy) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(policy.capacity))),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &SlowRequestPolicy {
+        &self.policy
+    }
+
+    /// Keep a diagnostic for `request` if it was slow, and return it
+    ///
+    /// A slow request is also logged at WARN and counted in `slow_requests_total`.
+    pub fn observe(
+        &self,
+        request: CompletedRequest<'_>,
+        timings: RequestTimings,
+    ) -> Option<SlowRequestRecord> {
+        let threshold_ms = self.policy.threshold_for(request.route);
+        let duration_ms = request.duration.as_millis().try_into().unwrap_or(u64::MAX);
+        if duration_ms <= threshold_ms {
+            return None;
+        }
+
+        let slowest_stage = timings
+            .stages
+            .iter()
+            .max_by_key(|timing| timing.duration_ms)
+            .map(|timing| timing.stage.clone());
+        let record = SlowRequestRecord {
+            correlation_id: request.correlation_id.to_owned(),
+            method: request.method.to_owned(),
+            route: request.route.to_owned(),
+            status: request.status,
+            recorded_at: Utc::now(),
+            duration_ms,
+            threshold_ms,
+            stages: timings.stages,
+            slowest_stage,
+            mistral_retries: timings.mistral_retries,
+            queue_wait_ms: timings.queue_wait_ms,
+            request_bytes: request.request_bytes,
+            response_bytes: request.response_bytes,
+        };
+
+        get_metrics().increment_slow_requests(&record.route);
+        warn!(
+            correlation_id = %record.correlation_id,
+            route = %record.route,
+            status = record.status,
+            duration_ms = record.duration_ms,
+            threshold_ms = record.threshold_ms,
+            slowest_stage = record.slowest_stage.as_deref().unwrap_or("none"),
+            stages = ?record.stages,
+            mistral_retries = ?record.mistral_retries,
+            queue_wait_ms = ?record.queue_wait_ms,
+            "Slow request"
+        );
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.policy.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(record.clone());
+        Some(record)
+    }
+
+    /// Kept diagnostics, newest first
+    pub fn recent(&self) -> SlowRequestsResponse {
+        let requests: Vec<_> = self.entries.lock().unwrap().iter().rev().cloned().collect();
+        SlowRequestsResponse {
+            policy: self.policy.clone(),
+            total: requests.len(),
+            requests,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(route: &str, duration_ms: u64) -> CompletedRequest<'_> {
+        CompletedRequest {
+            correlation_id: "slow-1",
+            method: "POST",
+            route,
+            status: 200,
+            duration: Duration::from_millis(duration_ms),
+            request_bytes: Some(42),
+            response_bytes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stages_report_to_the_request_they_run_in() {
+        let ((), timings) = collect_timings(async {
+            record_stage("firewall", 3);
+            record_stage("semantic", 40);
+            record_mistral_retry("embeddings");
+            record_mistral_retry("embeddings");
+            record_queue_wait("chat", Duration::from_millis(5));
+        })
+        .await;
+        assert_eq!(timings.stages.len(), 2);
+        assert_eq!(timings.mistral_retries["embeddings"], 2);
+        assert_eq!(timings.queue_wait_ms["chat"], 5);
+
+        // Outside a collecting request nothing is kept, and nothing fails
+        record_stage("firewall", 1);
+    }
+
+    #[test]
+    fn only_requests_over_their_route_threshold_are_kept() {
+        let log = SlowRequestLog::new(SlowRequestPolicy {
+            threshold_ms: 100,
+            route_thresholds: BTreeMap::from([("/health".to_owned(), 10)]),
+            capacity: 2,
+        });
+        assert!(
+            log.observe(
+                request("/api/compliance/check", 100),
+                RequestTimings::default()
+            )
+            .is_none()
+        );
+        assert!(
+            log.observe(request("/health", 11), RequestTimings::default())
+                .is_some()
+        );
+
+        let timings = RequestTimings {
+            stages: vec![
+                StageTiming {
+                    stage: "firewall".to_owned(),
+                    duration_ms: 2,
+                },
+                StageTiming {
+                    stage: "generation".to_owned(),
+                    duration_ms: 180,
+                },
+            ],
+            ..RequestTimings::default()
+        };
+        let record = log
+            .observe(request("/api/compliance/check", 200), timings)
+            .unwrap();
+        assert_eq!(record.slowest_stage.as_deref(), Some("generation"));
+        assert_eq!(record.threshold_ms, 100);
+
+        // The oldest entry makes room, and the newest is listed first
+        log.observe(
+            request("/api/compliance/check", 300),
+            RequestTimings::default(),
+        );
+        let recent = log.recent();
+        assert_eq!(recent.total, 2);
+        assert_eq!(recent.requests[0].duration_ms, 300);
+        assert_eq!(recent.requests[1].duration_ms, 200);
+    }
+
+    #[test]
+    fn route_thresholds_parse_from_settings_entries() {
+        let parsed = SlowRequestPolicy::parse_route_thresholds(&[
+            "/api/compliance/check=8000".to_owned(),
+            " /health = 50 ".to_owned(),
+        ])
+        .unwrap();
+        assert_eq!(parsed["/api/compliance/check"], 8000);
+        assert_eq!(parsed["/health"], 50);
+        for invalid in ["/health", "health=50", "/health=soon"] {
+            assert!(
+                SlowRequestPolicy::parse_route_thresholds(&[invalid.to_owned()]).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
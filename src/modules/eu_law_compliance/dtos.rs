@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use super::model::{AiRiskTier, ComplianceFinding};
 
@@ -24,6 +25,10 @@ pub struct ComplianceReportRequest {
     pub request_timestamp: DateTime<Utc>,
     pub correlation_id: String,
     pub generate_pdf: bool,
+    /// Generate a new report even if one exists for `correlation_id`, replacing it;
+    /// the server only honours it with the admin token
+    #[serde(default)]
+    pub regenerate: bool,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -35,6 +40,62 @@ pub struct ComplianceReportResponse {
     pub generated_at: DateTime<Utc>,
     pub pdf_available: bool,
     pub pdf_url: Option<String>,
+    /// SHA-256 of the report's JSON encoding with this field empty, so a retrieved copy
+    /// can be checked against the one first returned
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+impl ComplianceReportResponse {
+    /// Hash of everything but `content_hash` itself
+    pub fn compute_content_hash(&self) -> String {
+        let unhashed = Self {
+            content_hash: String::new(),
+            ..self.clone()
+        };
+        let json = serde_json::to_string(&unhashed).unwrap_or_default();
+        hex::encode(Sha256::digest(json))
+    }
+}
+
+/// A generated report together with the request it answered
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StoredComplianceReport {
+    pub request: ComplianceReportRequest,
+    pub report: ComplianceReportResponse,
+}
+
+/// One entry of `GET /api/compliance/reports`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ComplianceReportSummary {
+    pub report_id: String,
+    pub correlation_id: String,
+    pub risk_tier: AiRiskTier,
+    pub compliant: bool,
+    pub generated_at: DateTime<Utc>,
+    pub content_hash: String,
+}
+
+impl From<&StoredComplianceReport> for ComplianceReportSummary {
+    fn from(stored: &StoredComplianceReport) -> Self {
+        Self {
+            report_id: stored.report.report_id.clone(),
+            correlation_id: stored.request.correlation_id.clone(),
+            risk_tier: stored.report.risk_tier.clone(),
+            compliant: stored.report.compliant,
+            generated_at: stored.report.generated_at,
+            content_hash: stored.report.content_hash.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ComplianceReportList {
+    /// Newest first
+    pub reports: Vec<ComplianceReportSummary>,
+    pub total_count: usize,
+    pub limit: usize,
+    pub offset: usize,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -1,4 +1,6 @@
 pub mod dtos;
 pub mod handler;
 pub mod model;
+pub mod reports;
 pub mod service;
+pub mod storage;
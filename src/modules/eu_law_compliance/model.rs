@@ -1,10 +1,18 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+use crate::firewall_core::rules::PhraseMatchKind;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum AiRiskTier {
+    #[default]
+    #[serde(alias = "Minimal")]
     Minimal,
+    #[serde(alias = "Limited")]
     Limited,
+    #[serde(alias = "High")]
     High,
+    #[serde(alias = "Unacceptable")]
     Unacceptable,
 }
 
@@ -31,18 +39,35 @@ impl AiRiskTier {
 pub struct ComplianceFinding {
     pub code: String,
     pub detail: String,
+    /// Keyword that put the text in the risk tier behind this finding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_keyword: Option<KeywordMatch>,
+}
+
+/// Risk keyword found in a prompt or intended-use description
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct KeywordMatch {
+    pub keyword: String,
+    /// `normalized` or `fuzzy` when the text disguised the keyword, e.g. as
+    /// "s0cial sc0ring"
+    pub match_kind: PhraseMatchKind,
 }
 
 /// Compliance status for individual obligations
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum ObligationStatus {
     /// Requirement fully satisfied
+    #[serde(alias = "Met")]
     Met,
     /// Requirement partially satisfied, action needed
+    #[serde(alias = "Partial")]
     Partial,
     /// Requirement not satisfied, blocking gap
+    #[serde(alias = "Gap")]
     Gap,
     /// Not applicable to this risk tier
+    #[serde(alias = "NotApplicable")]
     NotApplicable,
 }
 
@@ -64,7 +89,7 @@ pub struct ObligationResult {
 }
 
 /// Structured EU AI Act compliance result
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub struct EuComplianceResult {
     /// Classified risk tier
     pub risk_tier: AiRiskTier,
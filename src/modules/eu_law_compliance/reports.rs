@@ -0,0 +1,167 @@
+//! Generated EU compliance reports, kept so they can be retrieved and listed later
+//!
+//! There is one report per correlation id: asking again with the same request returns
+//! the stored report unless the request sets `regenerate`, and a different request for
+//! the id is refused rather than answered with, or replacing, another caller's report. Reports older than the retention period are
+//! never served; they are removed whenever reports are listed, and by report generation
+//! at most once per [`PRUNE_INTERVAL_SECS`].
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::dtos::{
+    ComplianceReportList, ComplianceReportRequest, ComplianceReportResponse,
+    ComplianceReportSummary, StoredComplianceReport,
+};
+use super::service::{EuLawComplianceService, report_id};
+use super::storage::{ComplianceReportStore, InMemoryReportStore, ReportStoreError};
+
+/// Reports per page of `GET /api/compliance/reports` when no limit is given
+pub const DEFAULT_REPORT_PAGE_SIZE: usize = 50;
+/// Largest page `GET /api/compliance/reports` returns
+pub const MAX_REPORT_PAGE_SIZE: usize = 500;
+/// Least time between two sweeps for expired reports started by report generation
+pub const PRUNE_INTERVAL_SECS: i64 = 60 * 60;
+
+#[derive(Clone)]
+pub struct ComplianceReportService {
+    store: Arc<dyn ComplianceReportStore>,
+    /// `None` keeps reports forever
+    retention: Option<Duration>,
+    /// Time of the last sweep for expired reports; held from lookup to insert, so
+    /// concurrent requests for one correlation id agree
+    writes: Arc<Mutex<Option<DateTime<Utc>>>>,
+}
+
+impl Default for ComplianceReportService {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryReportStore::new()), 0)
+    }
+}
+
+impl ComplianceReportService {
+    /// Keep reports in `store` for `retention_days` days; 0 keeps them forever
+    pub fn new(store: Arc<dyn ComplianceReportStore>, retention_days: u64) -> Self {
+        let retention = (retention_days > 0)
+            .then(|| Duration::try_days(i64::try_from(retention_days).unwrap_or(i64::MAX)))
+            .flatten();
+        Self {
+            store,
+            retention,
+            writes: Arc::default(),
+        }
+    }
+
+    /// The stored report for the request's correlation id, or a new one
+    ///
+    /// With `regenerate` set a new report replaces the stored one. Either way a stored
+    /// report for a different request is left alone and
+    /// [`ReportStoreError::RequestMismatch`] returned.
+    pub fn generate(
+        &self,
+        request: ComplianceReportRequest,
+    ) -> Result<ComplianceReportResponse, ReportStoreError> {
+        let mut last_pruned = self
+            .writes
+            .lock()
+            .map_err(|_| ReportStoreError::LockPoisoned)?;
+        let now = Utc::now();
+        if last_pruned.is_none_or(|at| now - at >= Duration::seconds(PRUNE_INTERVAL_SECS)) {
+            self.prune(now)?;
+            *last_pruned = Some(now);
+        }
+        let id = report_id(&request.correlation_id);
+        if let Some(stored) = self.store.get(&id)?
+            && !self.is_expired(&stored, now)
+        {
+            if !same_request(&stored.request, &request) {
+                return Err(ReportStoreError::RequestMismatch(id));
+            }
+            if !request.regenerate {
+                return Ok(stored.report);
+            }
+        }
+
+        let report = EuLawComplianceService.generate_compliance_report(request.clone());
+        self.store.upsert(StoredComplianceReport {
+            request,
+            report: report.clone(),
+        })?;
+        Ok(report)
+    }
+
+    /// The report with this id and the request it answered, unless it has expired
+    pub fn get(&self, id: &str) -> Result<Option<StoredComplianceReport>, ReportStoreError> {
+        let now = Utc::now();
+        Ok(self
+            .store
+            .get(id)?
+            .filter(|stored| !self.is_expired(stored, now)))
+    }
+
+    /// Reports newest first, `offset` of them skipped
+    pub fn list(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Result<ComplianceReportList, ReportStoreError> {
+        let limit = limit
+            .unwrap_or(DEFAULT_REPORT_PAGE_SIZE)
+            .min(MAX_REPORT_PAGE_SIZE);
+        let offset = offset.unwrap_or(0);
+        let mut reports = {
+            let mut last_pruned = self
+                .writes
+                .lock()
+                .map_err(|_| ReportStoreError::LockPoisoned)?;
+            let now = Utc::now();
+            self.prune(now)?;
+            *last_pruned = Some(now);
+            self.store.list()?
+        };
+        reports.sort_by(|a, b| {
+            b.report
+                .generated_at
+                .cmp(&a.report.generated_at)
+                .then_with(|| a.report.report_id.cmp(&b.report.report_id))
+        });
+        Ok(ComplianceReportList {
+            total_count: reports.len(),
+            reports: reports
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(ComplianceReportSummary::from)
+                .collect(),
+            limit,
+            offset,
+        })
+    }
+
+    /// Remove expired reports
+    fn prune(&self, now: DateTime<Utc>) -> Result<(), ReportStoreError> {
+        if self.retention.is_none() {
+            return Ok(());
+        }
+        for stored in self.store.list()? {
+            if self.is_expired(&stored, now) {
+                self.store.remove(&stored.report.report_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_expired(&self, stored: &StoredComplianceReport, now: DateTime<Utc>) -> bool {
+        self.retention
+            .is_some_and(|retention| stored.report.generated_at + retention <= now)
+    }
+}
+
+/// Whether two requests ask for the same report, whatever their `regenerate` flags
+fn same_request(a: &ComplianceReportRequest, b: &ComplianceReportRequest) -> bool {
+    ComplianceReportRequest {
+        regenerate: b.regenerate,
+        ..a.clone()
+    } == *b
+}
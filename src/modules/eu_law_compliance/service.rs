@@ -1,4 +1,5 @@
 use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 
 use chrono::Utc;
@@ -9,9 +10,14 @@ use super::dtos::{
     ComplianceConfigurationResponse, ComplianceConfigurationSummary, ComplianceReportRequest,
     ComplianceReportResponse, DocumentationRequirements, RiskKeywordCounts,
 };
-use super::model::{AiRiskTier, ComplianceFinding, EuComplianceResult, ObligationResult, ObligationStatus};
+use super::model::{
+    AiRiskTier, ComplianceFinding, EuComplianceResult, KeywordMatch, ObligationResult,
+    ObligationStatus,
+};
+use crate::config::layers::configured_path;
+use crate::modules::prompt_firewall::rules::{PhraseMatchKind, PhraseMatcher, loaded_rules};
 
-const DEFAULT_EU_KEYWORDS_PATH: &str = "config/eu_risk_keywords.json";
+pub(crate) const DEFAULT_EU_KEYWORDS_PATH: &str = "config/eu_risk_keywords.json";
 const EU_KEYWORDS_PATH_ENV: &str = "PROMPT_SENTINEL_EU_KEYWORDS_PATH";
 
 const DEFAULT_UNACCEPTABLE_KEYWORDS: &[&str] = &[
@@ -48,14 +54,37 @@ const DEFAULT_LIMITED_KEYWORDS: &[&str] = &[
     "deepfake",
 ];
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct EuRiskKeywordConfig {
+/// Risk-tier keyword lists as stored in `config/eu_risk_keywords.json`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EuRiskKeywordConfig {
     #[serde(default = "default_unacceptable_keywords")]
-    unacceptable: Vec<String>,
+    pub unacceptable: Vec<String>,
     #[serde(default = "default_high_keywords")]
-    high: Vec<String>,
+    pub high: Vec<String>,
     #[serde(default = "default_limited_keywords")]
-    limited: Vec<String>,
+    pub limited: Vec<String>,
+}
+
+impl EuRiskKeywordConfig {
+    /// Reject keyword lists that could never match a lowercased prompt
+    pub fn validate(&self) -> Result<(), String> {
+        let tiers = [
+            ("unacceptable", &self.unacceptable),
+            ("high", &self.high),
+            ("limited", &self.limited),
+        ];
+        for (tier, keywords) in tiers {
+            for keyword in keywords {
+                if keyword.trim().is_empty() {
+                    return Err(format!("{tier} keywords must not contain blank entries"));
+                }
+                if keyword.to_ascii_lowercase() != *keyword {
+                    return Err(format!("{tier} keyword '{keyword}' must be lowercase"));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for EuRiskKeywordConfig {
@@ -72,6 +101,8 @@ impl Default for EuRiskKeywordConfig {
 #[derive(Clone, Debug)]
 struct ConfigManager {
     config: Arc<RwLock<EuRiskKeywordConfig>>,
+    /// Bumped on every change, so readers can tell whether their copy is current
+    version: Arc<AtomicU64>,
 }
 
 impl ConfigManager {
@@ -79,9 +110,14 @@ impl ConfigManager {
         let config = load_risk_keywords();
         Self {
             config: Arc::new(RwLock::new(config)),
+            version: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    fn lock(&self) -> &RwLock<EuRiskKeywordConfig> {
+        &self.config
+    }
+
     fn get_config(&self) -> EuRiskKeywordConfig {
         let guard = self.config.read().unwrap();
         guard.clone()
@@ -95,6 +131,7 @@ impl ConfigManager {
 
         // Update in-memory config
         *guard = new_config;
+        self.version.fetch_add(1, Ordering::SeqCst);
 
         Ok(())
     }
@@ -109,9 +146,35 @@ lazy_static::lazy_static! {
 pub struct EuLawComplianceService;
 
 impl EuLawComplianceService {
+    /// Keyword lists currently used for risk classification
+    pub fn risk_keyword_config(&self) -> EuRiskKeywordConfig {
+        CONFIG_MANAGER.get_config()
+    }
+
+    pub(crate) fn risk_keyword_lock(&self) -> &'static RwLock<EuRiskKeywordConfig> {
+        CONFIG_MANAGER.lock()
+    }
+
+    /// Changes every time the keyword lists are updated or restored
+    pub fn risk_keyword_version(&self) -> u64 {
+        CONFIG_MANAGER.version.load(Ordering::SeqCst)
+    }
+
+    /// Record a change made directly through [`Self::risk_keyword_lock`]
+    pub(crate) fn bump_risk_keyword_version(&self) {
+        CONFIG_MANAGER.version.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn persist_risk_keywords(
+        &self,
+        config: &EuRiskKeywordConfig,
+    ) -> Result<(), std::io::Error> {
+        save_risk_keywords(config)
+    }
+
     /// Check compliance for a prompt/use-case and return structured result
     pub fn check_prompt(&self, prompt: &str) -> EuComplianceResult {
-        let risk_tier = classify_risk(prompt);
+        let (risk_tier, matched_keyword) = classify_risk(prompt);
         let mut obligations = Vec::new();
         let mut findings = Vec::new();
 
@@ -121,6 +184,7 @@ impl EuLawComplianceService {
                 code: "EU-RISK-001".to_owned(),
                 detail: "Prompt matches a prohibited-risk category under EU AI Act Article 5."
                     .to_owned(),
+                matched_keyword: matched_keyword.clone(),
             });
             ObligationStatus::Gap
         } else {
@@ -160,6 +224,7 @@ impl EuLawComplianceService {
             findings.push(ComplianceFinding {
                 code: "EU-TRN-002".to_owned(),
                 detail: "Transparency notice required for this risk tier.".to_owned(),
+                matched_keyword: matched_keyword.clone(),
             });
         }
         obligations.push(ObligationResult {
@@ -167,7 +232,9 @@ impl EuLawComplianceService {
             name: "Transparency Obligations".to_owned(),
             legal_basis: "Article 50, EU AI Act (Regulation 2024/1689)".to_owned(),
             status: transparency_status,
-            detail: Some("Users must be informed they are interacting with an AI system.".to_owned()),
+            detail: Some(
+                "Users must be informed they are interacting with an AI system.".to_owned(),
+            ),
             applicable_from: Some("2026-08-02".to_owned()),
         });
 
@@ -189,18 +256,24 @@ impl EuLawComplianceService {
                 name: "Human Oversight".to_owned(),
                 legal_basis: "Article 14, EU AI Act (Regulation 2024/1689)".to_owned(),
                 status: ObligationStatus::Partial,
-                detail: Some("High-risk AI must enable human oversight and intervention.".to_owned()),
+                detail: Some(
+                    "High-risk AI must enable human oversight and intervention.".to_owned(),
+                ),
                 applicable_from: Some("2026-08-02".to_owned()),
             });
 
             findings.push(ComplianceFinding {
                 code: "EU-HIGH-001".to_owned(),
-                detail: "High-risk use case detected. Additional compliance controls required.".to_owned(),
+                detail: "High-risk use case detected. Additional compliance controls required."
+                    .to_owned(),
+                matched_keyword: matched_keyword.clone(),
             });
         }
 
         let compliant = !matches!(risk_tier, AiRiskTier::Unacceptable)
-            && !obligations.iter().any(|o| matches!(o.status, ObligationStatus::Gap));
+            && !obligations
+                .iter()
+                .any(|o| matches!(o.status, ObligationStatus::Gap));
 
         EuComplianceResult {
             risk_tier,
@@ -213,7 +286,7 @@ impl EuLawComplianceService {
 
     pub fn check(&self, request: ComplianceCheckRequest) -> ComplianceCheckResponse {
         let intended_use = request.intended_use.trim();
-        let risk_tier = classify_risk(intended_use);
+        let (risk_tier, matched_keyword) = classify_risk(intended_use);
         let mut findings = Vec::new();
 
         if intended_use.len() < 8 {
@@ -221,6 +294,7 @@ impl EuLawComplianceService {
                 code: "EU-SCOPE-001".to_owned(),
                 detail: "Intended-use description is too short for reliable risk classification."
                     .to_owned(),
+                matched_keyword: None,
             });
         }
 
@@ -229,6 +303,7 @@ impl EuLawComplianceService {
                 code: "EU-RISK-001".to_owned(),
                 detail: "Intended use matches a prohibited-risk category under EU AI Act controls."
                     .to_owned(),
+                matched_keyword: matched_keyword.clone(),
             });
         }
 
@@ -237,18 +312,21 @@ impl EuLawComplianceService {
                 findings.push(ComplianceFinding {
                     code: "EU-DOC-001".to_owned(),
                     detail: "Technical documentation is missing.".to_owned(),
+                    matched_keyword: matched_keyword.clone(),
                 });
             }
             if !request.transparency_notice_available {
                 findings.push(ComplianceFinding {
                     code: "EU-TRN-001".to_owned(),
                     detail: "Transparency notice is missing.".to_owned(),
+                    matched_keyword: matched_keyword.clone(),
                 });
             }
             if !request.copyright_controls_available {
                 findings.push(ComplianceFinding {
                     code: "EU-CPY-001".to_owned(),
                     detail: "Copyright safeguard documentation is missing.".to_owned(),
+                    matched_keyword: matched_keyword.clone(),
                 });
             }
         } else if matches!(risk_tier, AiRiskTier::Limited) && !request.transparency_notice_available
@@ -256,6 +334,7 @@ impl EuLawComplianceService {
             findings.push(ComplianceFinding {
                 code: "EU-TRN-002".to_owned(),
                 detail: "Limited-risk systems must include a transparency notice.".to_owned(),
+                matched_keyword: matched_keyword.clone(),
             });
         }
 
@@ -278,22 +357,21 @@ impl EuLawComplianceService {
             copyright_controls_available: true,
         });
 
-        ComplianceReportResponse {
-            report_id: format!("COMP-REPORT-{}", request.correlation_id),
+        let id = report_id(&request.correlation_id);
+        let mut report = ComplianceReportResponse {
+            report_id: id.clone(),
             risk_tier: check_response.risk_tier,
             compliant: check_response.compliant,
             findings: check_response.findings,
             generated_at: Utc::now(),
             pdf_available: request.generate_pdf,
-            pdf_url: if request.generate_pdf {
-                Some(format!(
-                    "/api/compliance/reports/{}/pdf",
-                    request.correlation_id
-                ))
-            } else {
-                None
-            },
-        }
+            pdf_url: request
+                .generate_pdf
+                .then(|| format!("/api/compliance/reports/{id}/pdf")),
+            content_hash: String::new(),
+        };
+        report.content_hash = report.compute_content_hash();
+        report
     }
 
     pub fn get_current_configuration(&self) -> ComplianceConfigurationSummary {
@@ -340,6 +418,14 @@ impl EuLawComplianceService {
             }
         }
 
+        if let Err(reason) = new_config.validate() {
+            return ComplianceConfigurationResponse {
+                status: "error".to_string(),
+                message: format!("Invalid configuration: {}", reason),
+                current_configuration: self.get_current_configuration(),
+            };
+        }
+
         // Save updated configuration to file and memory
         match CONFIG_MANAGER.update_config(new_config) {
             Ok(_) => ComplianceConfigurationResponse {
@@ -356,38 +442,69 @@ impl EuLawComplianceService {
     }
 }
 
-fn classify_risk(intended_use: &str) -> AiRiskTier {
-    let text = intended_use.to_ascii_lowercase();
+/// Id of the report generated for `correlation_id`; there is at most one per id
+pub fn report_id(correlation_id: &str) -> String {
+    format!("COMP-REPORT-{correlation_id}")
+}
+
+/// Risk tier of `text` and the keyword that decided it
+///
+/// Keywords are looked up in the firewall's canonical form of the text, so homoglyphs,
+/// leetspeak and zero-width characters do not hide them. Prohibited-use keywords are
+/// also matched fuzzily, with the firewall's fuzzy settings; the other tiers are not,
+/// since their shorter keywords have harmless neighbours ("order control" is one edit
+/// from "border control").
+fn classify_risk(text: &str) -> (AiRiskTier, Option<KeywordMatch>) {
     let keywords = CONFIG_MANAGER.get_config();
+    let matcher = PhraseMatcher::new(text);
+    let fuzzy = loaded_rules().config().fuzzy_matching.clone();
 
-    if contains_any(&text, &keywords.unacceptable) {
-        AiRiskTier::Unacceptable
-    } else if contains_any(&text, &keywords.high) {
-        AiRiskTier::High
-    } else if contains_any(&text, &keywords.limited) {
-        AiRiskTier::Limited
-    } else {
-        AiRiskTier::Minimal
+    if let Some(found) = find_keyword(&keywords.unacceptable, |keyword| {
+        matcher.find_fuzzy(keyword, &fuzzy)
+    }) {
+        return (AiRiskTier::Unacceptable, Some(found));
+    }
+    for (tier, tier_keywords) in [
+        (AiRiskTier::High, &keywords.high),
+        (AiRiskTier::Limited, &keywords.limited),
+    ] {
+        if let Some(found) = find_keyword(tier_keywords, |keyword| matcher.find(keyword)) {
+            return (tier, Some(found));
+        }
     }
+    (AiRiskTier::Minimal, None)
 }
 
 fn load_risk_keywords() -> EuRiskKeywordConfig {
-    let path =
-        std::env::var(EU_KEYWORDS_PATH_ENV).unwrap_or_else(|_| DEFAULT_EU_KEYWORDS_PATH.to_owned());
+    let path = configured_path(EU_KEYWORDS_PATH_ENV, DEFAULT_EU_KEYWORDS_PATH);
 
     fs::read_to_string(path)
         .ok()
         .and_then(|content| serde_json::from_str::<EuRiskKeywordConfig>(&content).ok())
+        .filter(|config| match config.validate() {
+            Ok(()) => true,
+            Err(reason) => {
+                tracing::warn!("Ignoring invalid EU risk keyword file: {}", reason);
+                false
+            }
+        })
         .unwrap_or_default()
 }
 
-fn contains_any(text: &str, keywords: &[String]) -> bool {
-    keywords.iter().any(|keyword| text.contains(keyword))
+fn find_keyword(
+    keywords: &[String],
+    find: impl Fn(&str) -> Option<PhraseMatchKind>,
+) -> Option<KeywordMatch> {
+    keywords.iter().find_map(|keyword| {
+        find(keyword).map(|match_kind| KeywordMatch {
+            keyword: keyword.clone(),
+            match_kind,
+        })
+    })
 }
 
 fn save_risk_keywords(config: &EuRiskKeywordConfig) -> Result<(), std::io::Error> {
-    let path =
-        std::env::var(EU_KEYWORDS_PATH_ENV).unwrap_or_else(|_| DEFAULT_EU_KEYWORDS_PATH.to_owned());
+    let path = configured_path(EU_KEYWORDS_PATH_ENV, DEFAULT_EU_KEYWORDS_PATH);
 
     // Create directory if it doesn't exist
     if let Some(parent) = std::path::Path::new(&path).parent() {
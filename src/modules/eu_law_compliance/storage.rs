@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::StoredComplianceReport;
+
+#[cfg(feature = "sled-storage")]
+const COMPLIANCE_REPORTS_TREE: &str = "compliance_reports";
+
+/// Generated EU compliance reports, keyed by report id
+pub trait ComplianceReportStore: Send + Sync {
+    /// Insert a report, replacing any with the same id
+    fn upsert(&self, report: StoredComplianceReport) -> Result<(), ReportStoreError>;
+    fn get(&self, report_id: &str) -> Result<Option<StoredComplianceReport>, ReportStoreError>;
+    /// Every report, ordered by id
+    fn list(&self) -> Result<Vec<StoredComplianceReport>, ReportStoreError>;
+    /// Remove a report; removing an unknown id is not an error
+    fn remove(&self, report_id: &str) -> Result<(), ReportStoreError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryReportStore {
+    inner: Arc<Mutex<BTreeMap<String, StoredComplianceReport>>>,
+}
+
+impl InMemoryReportStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ComplianceReportStore for InMemoryReportStore {
+    fn upsert(&self, report: StoredComplianceReport) -> Result<(), ReportStoreError> {
+        self.inner
+            .lock()
+            .map_err(|_| ReportStoreError::LockPoisoned)?
+            .insert(report.report.report_id.clone(), report);
+        Ok(())
+    }
+
+    fn get(&self, report_id: &str) -> Result<Option<StoredComplianceReport>, ReportStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| ReportStoreError::LockPoisoned)?
+            .get(report_id)
+            .cloned())
+    }
+
+    fn list(&self) -> Result<Vec<StoredComplianceReport>, ReportStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| ReportStoreError::LockPoisoned)?
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn remove(&self, report_id: &str) -> Result<(), ReportStoreError> {
+        self.inner
+            .lock()
+            .map_err(|_| ReportStoreError::LockPoisoned)?
+            .remove(report_id);
+        Ok(())
+    }
+}
+
+/// Reports kept in their own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledReportStore {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledReportStore {
+    pub fn new(db: &Db) -> Result<Self, ReportStoreError> {
+        let tree = db
+            .open_tree(COMPLIANCE_REPORTS_TREE)
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl ComplianceReportStore for SledReportStore {
+    fn upsert(&self, report: StoredComplianceReport) -> Result<(), ReportStoreError> {
+        let serialized = serde_json::to_vec(&report)
+            .map_err(|e| ReportStoreError::SerializationError(e.to_string()))?;
+        self.tree
+            .insert(report.report.report_id.as_bytes(), serialized)
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get(&self, report_id: &str) -> Result<Option<StoredComplianceReport>, ReportStoreError> {
+        self.tree
+            .get(report_id.as_bytes())
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| ReportStoreError::SerializationError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn list(&self) -> Result<Vec<StoredComplianceReport>, ReportStoreError> {
+        self.tree
+            .iter()
+            .values()
+            .map(|entry| {
+                let data = entry.map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+                serde_json::from_slice(&data)
+                    .map_err(|e| ReportStoreError::SerializationError(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn remove(&self, report_id: &str) -> Result<(), ReportStoreError> {
+        self.tree
+            .remove(report_id.as_bytes())
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| ReportStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReportStoreError {
+    #[error("compliance report store lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+    #[error("compliance report {0} was generated for a different request")]
+    RequestMismatch(String),
+}
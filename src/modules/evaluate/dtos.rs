@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::modules::prompt_firewall::dtos::FirewallAction;
+use crate::modules::semantic_detection::SemanticRiskLevel;
+
+/// One labelled prompt of an evaluation dataset (a JSONL line)
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EvalCase {
+    pub id: String,
+    pub text: String,
+    pub expected: Expectation,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Whether a case should be blocked
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Expectation {
+    Block,
+    Allow,
+}
+
+impl Expectation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+/// Detection layers an evaluation can run
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvalLayer {
+    /// Blocks on a firewall `Block` action
+    Firewall,
+    /// Blocks on `High` semantic risk
+    Semantic,
+}
+
+/// How one case fared
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CaseResult {
+    pub id: String,
+    pub expected: Expectation,
+    pub tags: Vec<String>,
+    pub firewall_action: FirewallAction,
+    /// Missing when the semantic layer did not run
+    #[serde(default)]
+    pub semantic_score: Option<f32>,
+    #[serde(default)]
+    pub semantic_level: Option<SemanticRiskLevel>,
+    /// Layers that would have blocked the case
+    pub blocked_by: Vec<EvalLayer>,
+    /// Monitor-mode firewall rules the case matched; they never count as a block
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_rules: Vec<String>,
+    pub predicted: Expectation,
+    pub correct: bool,
+}
+
+/// Confusion counts with `block` as the positive class
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Confusion {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+/// Detection quality over a set of cases; ratios are `None` when undefined
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ClassMetrics {
+    pub cases: usize,
+    #[serde(flatten)]
+    pub confusion: Confusion,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+    pub f1: Option<f64>,
+    pub false_positive_rate: Option<f64>,
+}
+
+/// Spread of semantic risk scores over a set of cases
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScoreDistribution {
+    pub count: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub p50: f32,
+    pub p90: f32,
+    /// Case counts in ten buckets of width 0.1, from `[0.0, 0.1)` to `[0.9, 1.0]`
+    pub histogram: Vec<usize>,
+}
+
+/// What one layer adds on top of the others
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LayerContribution {
+    pub layer: EvalLayer,
+    pub attacks_blocked: usize,
+    /// Attacks no other layer blocked: recall lost without this layer
+    pub attacks_blocked_alone: usize,
+    pub false_positives: usize,
+    /// Benign cases only this layer blocked: false positives saved without it
+    pub false_positives_alone: usize,
+}
+
+/// Configuration the evaluation ran with
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EvalProfile {
+    /// Label of the configuration, such as the settings file used
+    pub name: String,
+    pub layers: Vec<EvalLayer>,
+    pub max_input_length: usize,
+    /// `(medium, high)` semantic cutoffs, margin included, when the layer ran
+    #[serde(default)]
+    pub semantic_cutoffs: Option<(f32, f32)>,
+}
+
+/// Machine-readable result of an evaluation run
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EvalReport {
+    pub generated_at: DateTime<Utc>,
+    pub dataset: String,
+    pub profile: EvalProfile,
+    pub overall: ClassMetrics,
+    pub tags: BTreeMap<String, ClassMetrics>,
+    /// Semantic scores by expected outcome (`block`, `allow`); empty without the layer
+    pub score_distributions: BTreeMap<String, ScoreDistribution>,
+    pub layers: Vec<LayerContribution>,
+    pub cases: Vec<CaseResult>,
+}
+
+/// Bounds a run must stay within to pass
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EvalGates {
+    pub min_recall: Option<f64>,
+    pub max_false_positive_rate: Option<f64>,
+}
@@ -0,0 +1,2 @@
+pub mod dtos;
+pub mod service;
@@ -0,0 +1,473 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use chrono::Utc;
+use thiserror::Error;
+
+use super::dtos::{
+    CaseResult, ClassMetrics, Confusion, EvalCase, EvalGates, EvalLayer, EvalProfile, EvalReport,
+    Expectation, LayerContribution, ScoreDistribution,
+};
+use crate::modules::prompt_firewall::dtos::{FirewallAction, PromptFirewallRequest};
+use crate::modules::prompt_firewall::service::PromptFirewallService;
+use crate::modules::semantic_detection::service::SemanticDetectionError;
+use crate::modules::semantic_detection::{
+    SemanticDetectionService, SemanticRiskLevel, SemanticScanRequest,
+};
+
+const HISTOGRAM_BUCKETS: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum EvaluateError {
+    #[error("Failed to read dataset {path}: {message}")]
+    Io { path: String, message: String },
+    #[error("Invalid dataset line {line}: {message}")]
+    InvalidCase { line: usize, message: String },
+    #[error("Semantic scan of case {id} failed: {source}")]
+    Semantic {
+        id: String,
+        source: SemanticDetectionError,
+    },
+}
+
+/// Read a JSONL dataset: one [`EvalCase`] per line, blank lines skipped
+pub fn load_dataset(path: &Path) -> Result<Vec<EvalCase>, EvaluateError> {
+    let text = std::fs::read_to_string(path).map_err(|e| EvaluateError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| EvaluateError::InvalidCase {
+                line: index + 1,
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Runs labelled prompts through the detection layers and scores the outcome
+///
+/// A case counts as blocked when the firewall blocks it or, with the semantic layer
+/// enabled, when its semantic risk is `High`, as in the compliance workflow. Every
+/// layer sees every case so their marginal contributions can be compared.
+#[derive(Clone)]
+pub struct Evaluator {
+    firewall: PromptFirewallService,
+    semantic: Option<SemanticDetectionService>,
+    profile_name: String,
+}
+
+impl Evaluator {
+    pub fn new(firewall: PromptFirewallService) -> Self {
+        Self {
+            firewall,
+            semantic: None,
+            profile_name: "default".to_owned(),
+        }
+    }
+
+    /// Also score cases with `semantic`, which should already be initialized
+    pub fn with_semantic(mut self, semantic: SemanticDetectionService) -> Self {
+        self.semantic = Some(semantic);
+        self
+    }
+
+    /// Label the configuration in the report
+    pub fn with_profile_name(mut self, name: impl Into<String>) -> Self {
+        self.profile_name = name.into();
+        self
+    }
+
+    pub async fn run(
+        &self,
+        dataset: impl Into<String>,
+        cases: &[EvalCase],
+    ) -> Result<EvalReport, EvaluateError> {
+        let mut results = Vec::with_capacity(cases.len());
+        for case in cases {
+            results.push(self.evaluate_case(case).await?);
+        }
+
+        let mut layers = vec![EvalLayer::Firewall];
+        if self.semantic.is_some() {
+            layers.push(EvalLayer::Semantic);
+        }
+        let mut tags: BTreeMap<String, Vec<&CaseResult>> = BTreeMap::new();
+        for result in &results {
+            for tag in &result.tags {
+                tags.entry(tag.clone()).or_default().push(result);
+            }
+        }
+        let mut score_distributions = BTreeMap::new();
+        for expected in [Expectation::Block, Expectation::Allow] {
+            let scores: Vec<f32> = results
+                .iter()
+                .filter(|result| result.expected == expected)
+                .filter_map(|result| result.semantic_score)
+                .collect();
+            if let Some(distribution) = distribution(scores) {
+                score_distributions.insert(expected.as_str().to_owned(), distribution);
+            }
+        }
+
+        Ok(EvalReport {
+            generated_at: Utc::now(),
+            dataset: dataset.into(),
+            profile: EvalProfile {
+                name: self.profile_name.clone(),
+                max_input_length: self.firewall.max_input_length(),
+                semantic_cutoffs: self.semantic.as_ref().map(|s| s.risk_cutoffs()),
+                layers: layers.clone(),
+            },
+            overall: metrics(results.iter()),
+            tags: tags
+                .into_iter()
+                .map(|(tag, results)| (tag, metrics(results.into_iter())))
+                .collect(),
+            score_distributions,
+            layers: layers
+                .into_iter()
+                .map(|layer| contribution(layer, &results))
+                .collect(),
+            cases: results,
+        })
+    }
+
+    async fn evaluate_case(&self, case: &EvalCase) -> Result<CaseResult, EvaluateError> {
+        let firewall = self
+            .firewall
+            .inspect(PromptFirewallRequest {
+                prompt: case.text.clone(),
+                correlation_id: None,
+                detected_language: None,
+                pre_translated_text: None,
+            })
+            .await;
+        let semantic = match &self.semantic {
+            Some(semantic) => Some(
+                semantic
+                    .scan(SemanticScanRequest {
+                        text: case.text.clone(),
+                        detected_language: None,
+                        pre_translated_text: None,
+                    })
+                    .await
+                    .map_err(|source| EvaluateError::Semantic {
+                        id: case.id.clone(),
+                        source,
+                    })?,
+            ),
+            None => None,
+        };
+
+        let mut blocked_by = Vec::new();
+        if firewall.action == FirewallAction::Block {
+            blocked_by.push(EvalLayer::Firewall);
+        }
+        if semantic
+            .as_ref()
+            .is_some_and(|s| s.risk_level == SemanticRiskLevel::High)
+        {
+            blocked_by.push(EvalLayer::Semantic);
+        }
+        let predicted = if blocked_by.is_empty() {
+            Expectation::Allow
+        } else {
+            Expectation::Block
+        };
+        Ok(CaseResult {
+            id: case.id.clone(),
+            expected: case.expected,
+            tags: case.tags.clone(),
+            firewall_action: firewall.action,
+            monitored_rules: firewall
+                .monitored_matches
+                .into_iter()
+                .map(|matched| matched.id)
+                .collect(),
+            semantic_score: semantic.as_ref().map(|s| s.risk_score),
+            semantic_level: semantic.map(|s| s.risk_level),
+            blocked_by,
+            predicted,
+            correct: predicted == case.expected,
+        })
+    }
+}
+
+impl Confusion {
+    pub fn record(&mut self, expected: Expectation, predicted: Expectation) {
+        match (expected, predicted) {
+            (Expectation::Block, Expectation::Block) => self.true_positives += 1,
+            (Expectation::Allow, Expectation::Block) => self.false_positives += 1,
+            (Expectation::Allow, Expectation::Allow) => self.true_negatives += 1,
+            (Expectation::Block, Expectation::Allow) => self.false_negatives += 1,
+        }
+    }
+
+    pub fn precision(&self) -> Option<f64> {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> Option<f64> {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn f1(&self) -> Option<f64> {
+        let (precision, recall) = (self.precision()?, self.recall()?);
+        if precision + recall == 0.0 {
+            return Some(0.0);
+        }
+        Some(2.0 * precision * recall / (precision + recall))
+    }
+
+    pub fn false_positive_rate(&self) -> Option<f64> {
+        ratio(
+            self.false_positives,
+            self.false_positives + self.true_negatives,
+        )
+    }
+}
+
+impl EvalGates {
+    /// Gates the report crosses, one message each; empty when it passes
+    pub fn check(&self, report: &EvalReport) -> Vec<String> {
+        let mut failures = Vec::new();
+        if let Some(min) = self.min_recall {
+            match report.overall.recall {
+                Some(recall) if recall >= min => {}
+                recall => failures.push(format!(
+                    "recall {} is below the gate of {min:.3}",
+                    format_ratio(recall)
+                )),
+            }
+        }
+        if let Some(max) = self.max_false_positive_rate {
+            match report.overall.false_positive_rate {
+                Some(rate) if rate > max => failures.push(format!(
+                    "false positive rate {rate:.3} is above the gate of {max:.3}"
+                )),
+                _ => {}
+            }
+        }
+        failures
+    }
+}
+
+/// Human-readable summary of a report for pull requests and archives
+pub fn render_markdown(report: &EvalReport) -> String {
+    let mut out = String::new();
+    let layers: Vec<&str> = report
+        .profile
+        .layers
+        .iter()
+        .map(|layer| match layer {
+            EvalLayer::Firewall => "firewall",
+            EvalLayer::Semantic => "semantic",
+        })
+        .collect();
+    let _ = writeln!(out, "# Evaluation report\n");
+    let _ = writeln!(
+        out,
+        "Dataset `{}`, profile `{}`, layers: {}, generated {}.\n",
+        report.dataset,
+        report.profile.name,
+        layers.join(", "),
+        report.generated_at.to_rfc3339()
+    );
+    let _ = writeln!(
+        out,
+        "| Scope | Cases | TP | FP | TN | FN | Precision | Recall | F1 | FP rate |"
+    );
+    let _ = writeln!(out, "|---|---|---|---|---|---|---|---|---|---|");
+    let row = |out: &mut String, scope: &str, m: &ClassMetrics| {
+        let _ = writeln!(
+            out,
+            "| {scope} | {} | {} | {} | {} | {} | {} | {} | {} | {} |",
+            m.cases,
+            m.confusion.true_positives,
+            m.confusion.false_positives,
+            m.confusion.true_negatives,
+            m.confusion.false_negatives,
+            format_ratio(m.precision),
+            format_ratio(m.recall),
+            format_ratio(m.f1),
+            format_ratio(m.false_positive_rate),
+        );
+    };
+    row(&mut out, "**overall**", &report.overall);
+    for (tag, metrics) in &report.tags {
+        row(&mut out, tag, metrics);
+    }
+
+    let _ = writeln!(out, "\n## Layers\n");
+    let _ = writeln!(
+        out,
+        "| Layer | Attacks blocked | Only this layer | False positives | Only this layer |"
+    );
+    let _ = writeln!(out, "|---|---|---|---|---|");
+    for (layer, contribution) in layers.iter().zip(&report.layers) {
+        let _ = writeln!(
+            out,
+            "| {layer} | {} | {} | {} | {} |",
+            contribution.attacks_blocked,
+            contribution.attacks_blocked_alone,
+            contribution.false_positives,
+            contribution.false_positives_alone
+        );
+    }
+
+    if !report.score_distributions.is_empty() {
+        let _ = writeln!(out, "\n## Semantic scores\n");
+        let _ = writeln!(out, "| Expected | Cases | Min | P50 | P90 | Max | Mean |");
+        let _ = writeln!(out, "|---|---|---|---|---|---|---|");
+        for (expected, d) in &report.score_distributions {
+            let _ = writeln!(
+                out,
+                "| {expected} | {} | {:.3} | {:.3} | {:.3} | {:.3} | {:.3} |",
+                d.count, d.min, d.p50, d.p90, d.max, d.mean
+            );
+        }
+    }
+
+    let wrong: Vec<&CaseResult> = report.cases.iter().filter(|case| !case.correct).collect();
+    if !wrong.is_empty() {
+        let _ = writeln!(out, "\n## Misclassified cases\n");
+        for case in wrong {
+            let _ = writeln!(
+                out,
+                "- `{}` expected {}, tags {}",
+                case.id,
+                case.expected.as_str(),
+                case.tags.join(", ")
+            );
+        }
+    }
+    out
+}
+
+fn metrics<'a>(results: impl Iterator<Item = &'a CaseResult>) -> ClassMetrics {
+    let mut confusion = Confusion::default();
+    let mut cases = 0;
+    for result in results {
+        cases += 1;
+        confusion.record(result.expected, result.predicted);
+    }
+    ClassMetrics {
+        cases,
+        confusion,
+        precision: confusion.precision(),
+        recall: confusion.recall(),
+        f1: confusion.f1(),
+        false_positive_rate: confusion.false_positive_rate(),
+    }
+}
+
+fn contribution(layer: EvalLayer, results: &[CaseResult]) -> LayerContribution {
+    let mut contribution = LayerContribution {
+        layer,
+        attacks_blocked: 0,
+        attacks_blocked_alone: 0,
+        false_positives: 0,
+        false_positives_alone: 0,
+    };
+    for result in results
+        .iter()
+        .filter(|result| result.blocked_by.contains(&layer))
+    {
+        let alone = result.blocked_by.len() == 1;
+        match result.expected {
+            Expectation::Block => {
+                contribution.attacks_blocked += 1;
+                contribution.attacks_blocked_alone += usize::from(alone);
+            }
+            Expectation::Allow => {
+                contribution.false_positives += 1;
+                contribution.false_positives_alone += usize::from(alone);
+            }
+        }
+    }
+    contribution
+}
+
+fn distribution(mut scores: Vec<f32>) -> Option<ScoreDistribution> {
+    if scores.is_empty() {
+        return None;
+    }
+    scores.sort_by(f32::total_cmp);
+    // Nearest-rank percentile
+    let percentile = |p: f32| {
+        let rank = (p * scores.len() as f32).ceil() as usize;
+        scores[rank.clamp(1, scores.len()) - 1]
+    };
+    let mut histogram = vec![0; HISTOGRAM_BUCKETS];
+    for score in &scores {
+        let bucket = (score.clamp(0.0, 1.0) * HISTOGRAM_BUCKETS as f32) as usize;
+        histogram[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+    }
+    Some(ScoreDistribution {
+        count: scores.len(),
+        min: scores[0],
+        max: scores[scores.len() - 1],
+        mean: scores.iter().sum::<f32>() / scores.len() as f32,
+        p50: percentile(0.5),
+        p90: percentile(0.9),
+        histogram,
+    })
+}
+
+fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
+}
+
+fn format_ratio(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".to_owned(), |value| format!("{value:.3}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusion_ratios_are_undefined_without_cases() {
+        let mut confusion = Confusion::default();
+        assert_eq!(confusion.precision(), None);
+        assert_eq!(confusion.false_positive_rate(), None);
+
+        for (expected, predicted) in [
+            (Expectation::Block, Expectation::Block),
+            (Expectation::Block, Expectation::Block),
+            (Expectation::Block, Expectation::Allow),
+            (Expectation::Allow, Expectation::Block),
+            (Expectation::Allow, Expectation::Allow),
+            (Expectation::Allow, Expectation::Allow),
+            (Expectation::Allow, Expectation::Allow),
+        ] {
+            confusion.record(expected, predicted);
+        }
+        assert_eq!(confusion.precision(), Some(2.0 / 3.0));
+        assert_eq!(confusion.recall(), Some(2.0 / 3.0));
+        assert!((confusion.f1().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(confusion.false_positive_rate(), Some(0.25));
+    }
+
+    #[test]
+    fn distributions_use_nearest_rank_percentiles() {
+        let scores = vec![0.95, 0.05, 0.15, 0.25, 0.35, 0.45, 0.55, 0.65, 0.75, 0.85];
+        let d = distribution(scores).unwrap();
+        assert_eq!(d.count, 10);
+        assert_eq!(d.p50, 0.45);
+        assert_eq!(d.p90, 0.85);
+        assert_eq!(d.histogram, vec![1; HISTOGRAM_BUCKETS]);
+        assert!(distribution(Vec::new()).is_none());
+    }
+}
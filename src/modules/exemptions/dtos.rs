@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/exemptions`
+///
+/// At least one of `expires_at` and `max_uses` is required, so every exemption lapses.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExemptionRequest {
+    /// Firewall rule the exemption suppresses
+    pub rule_id: String,
+    /// Phrase the prompt must contain (case-insensitive); any prompt matching the rule
+    /// qualifies when absent
+    #[serde(default)]
+    pub content_match: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+    pub granted_by: String,
+    pub reason: String,
+}
+
+impl ExemptionRequest {
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.rule_id.trim().is_empty() {
+            return Err("rule_id must not be empty".to_owned());
+        }
+        if self.granted_by.trim().is_empty() {
+            return Err("granted_by must not be empty".to_owned());
+        }
+        if self.reason.trim().is_empty() {
+            return Err("reason must not be empty".to_owned());
+        }
+        if self
+            .content_match
+            .as_deref()
+            .is_some_and(|phrase| phrase.trim().is_empty())
+        {
+            return Err("content_match must not be empty when set".to_owned());
+        }
+        if self.expires_at.is_none() && self.max_uses.is_none() {
+            return Err("an exemption needs expires_at, max_uses or both".to_owned());
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err("expires_at must be in the future".to_owned());
+        }
+        if self.max_uses == Some(0) {
+            return Err("max_uses must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// A granted suppression of one firewall rule
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Exemption {
+    pub id: String,
+    pub rule_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_match: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u32>,
+    /// Uses left before the exemption stops applying; `None` when uses are unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_uses: Option<u32>,
+    pub granted_by: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    /// Decision the exemption was granted for, by overturning an appeal against it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_correlation_id: Option<String>,
+}
+
+impl Exemption {
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_uses == Some(0)
+    }
+
+    /// Whether the exemption covers `rule_id` matching in `prompt`
+    pub fn covers(&self, rule_id: &str, prompt: &str) -> bool {
+        self.rule_id == rule_id
+            && self.content_match.as_deref().is_none_or(|phrase| {
+                prompt
+                    .to_lowercase()
+                    .contains(&phrase.trim().to_lowercase())
+            })
+    }
+}
+
+/// Why an exemption stopped applying
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveReason {
+    Expired,
+    Exhausted,
+    Revoked,
+}
+
+impl ArchiveReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::Exhausted => "exhausted",
+            Self::Revoked => "revoked",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ArchivedExemption {
+    #[serde(flatten)]
+    pub exemption: Exemption,
+    pub archived_at: DateTime<Utc>,
+    pub archive_reason: ArchiveReason,
+}
+
+/// Response of `GET /api/exemptions`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExemptionsResponse {
+    /// Entries still in force
+    pub active: Vec<Exemption>,
+    /// Most recently archived entries, newest first
+    pub archived: Vec<ArchivedExemption>,
+}
+
+/// An exemption used by a request, as recorded in the decision evidence
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppliedExemption {
+    pub exemption_id: String,
+    pub rule_id: String,
+    /// Uses left after this one; `None` when uses are unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_uses: Option<u32>,
+    /// Decision the exemption was granted for, see [`Exemption::related_correlation_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_correlation_id: Option<String>,
+}
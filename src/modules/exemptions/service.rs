@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::dtos::{
+    AppliedExemption, ArchiveReason, ArchivedExemption, Exemption, ExemptionRequest,
+    ExemptionsResponse,
+};
+use crate::modules::audit::logger::{AuditError, AuditLogger, ExemptionEvent};
+use crate::modules::telemetry::correlation::generate_correlation_id;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Archived entries kept in memory for `GET /api/exemptions`; the audit log keeps them all
+const ARCHIVE_LIMIT: usize = 100;
+
+/// Time- and use-bounded suppressions of individual firewall rules
+#[derive(Clone)]
+pub struct ExemptionService {
+    state: Arc<Mutex<ExemptionState>>,
+    audit_logger: AuditLogger,
+}
+
+#[derive(Default)]
+struct ExemptionState {
+    active: Vec<Exemption>,
+    archived: VecDeque<ArchivedExemption>,
+}
+
+impl ExemptionState {
+    fn archive(
+        &mut self,
+        exemption: Exemption,
+        reason: ArchiveReason,
+        now: DateTime<Utc>,
+    ) -> ArchivedExemption {
+        get_metrics().increment_exemptions_archived(reason.as_str());
+        get_metrics().set_active_exemptions(self.active.len());
+        let archived = ArchivedExemption {
+            exemption,
+            archived_at: now,
+            archive_reason: reason,
+        };
+        self.archived.push_front(archived.clone());
+        self.archived.truncate(ARCHIVE_LIMIT);
+        archived
+    }
+}
+
+impl ExemptionService {
+    pub fn new(audit_logger: AuditLogger) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ExemptionState::default())),
+            audit_logger,
+        }
+    }
+
+    pub fn list(&self) -> ExemptionsResponse {
+        let state = self.state.lock().unwrap();
+        ExemptionsResponse {
+            active: state.active.clone(),
+            archived: state.archived.iter().cloned().collect(),
+        }
+    }
+
+    /// Grant an exemption, auditing it before it takes effect
+    pub fn grant(
+        &self,
+        correlation_id: &str,
+        request: ExemptionRequest,
+    ) -> Result<Exemption, ExemptionError> {
+        self.grant_related(correlation_id, None, request)
+    }
+
+    /// Grant an exemption for the decision `decision_correlation_id`, audited under it
+    ///
+    /// Records of the exemption and of the decisions it lets through carry the
+    /// decision's correlation id, so they appear in its lineage.
+    pub fn grant_for_decision(
+        &self,
+        decision_correlation_id: &str,
+        request: ExemptionRequest,
+    ) -> Result<Exemption, ExemptionError> {
+        self.grant_related(
+            decision_correlation_id,
+            Some(decision_correlation_id.to_owned()),
+            request,
+        )
+    }
+
+    fn grant_related(
+        &self,
+        correlation_id: &str,
+        related_correlation_id: Option<String>,
+        request: ExemptionRequest,
+    ) -> Result<Exemption, ExemptionError> {
+        let now = Utc::now();
+        request.validate(now).map_err(ExemptionError::Invalid)?;
+        let exemption = Exemption {
+            id: Uuid::new_v4().to_string(),
+            rule_id: request.rule_id.trim().to_owned(),
+            content_match: request.content_match,
+            expires_at: request.expires_at,
+            max_uses: request.max_uses,
+            remaining_uses: request.max_uses,
+            granted_by: request.granted_by,
+            reason: request.reason,
+            created_at: now,
+            related_correlation_id,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        self.audit(correlation_id, "granted", &exemption)?;
+        info!(
+            "Exemption {} granted for rule {} by {}",
+            exemption.id, exemption.rule_id, exemption.granted_by
+        );
+        state.active.push(exemption.clone());
+        get_metrics().set_active_exemptions(state.active.len());
+        Ok(exemption)
+    }
+
+    /// Withdraw an exemption; requests evaluated afterwards no longer see it
+    pub fn revoke(&self, correlation_id: &str, id: &str) -> Result<Exemption, ExemptionError> {
+        let mut state = self.state.lock().unwrap();
+        let position = state
+            .active
+            .iter()
+            .position(|exemption| exemption.id == id)
+            .ok_or_else(|| ExemptionError::NotFound(id.to_owned()))?;
+        self.audit(
+            correlation_id,
+            ArchiveReason::Revoked.as_str(),
+            &state.active[position],
+        )?;
+        let exemption = state.active.remove(position);
+        info!("Exemption {} revoked", exemption.id);
+        state.archive(exemption.clone(), ArchiveReason::Revoked, Utc::now());
+        Ok(exemption)
+    }
+
+    /// Whether any of `matched_rules` currently has a usable exemption for `prompt`
+    ///
+    /// Unlike [`claim`](Self::claim) no use is consumed, so the answer may be stale by the
+    /// time the request reaches the firewall.
+    pub fn covers_any(&self, matched_rules: &[String], prompt: &str) -> bool {
+        let now = Utc::now();
+        let state = self.state.lock().unwrap();
+        state.active.iter().any(|exemption| {
+            !exemption.is_expired_at(now)
+                && !exemption.is_exhausted()
+                && matched_rules
+                    .iter()
+                    .any(|rule_id| exemption.covers(rule_id, prompt))
+        })
+    }
+
+    /// Use the exemptions covering `matched_rules` in `prompt`, at most one per rule
+    ///
+    /// Checking and decrementing happen under one lock, so concurrent requests can never
+    /// use an exemption more often than `max_uses`. Expired and exhausted entries are
+    /// skipped even before the sweep archives them.
+    pub fn claim(&self, matched_rules: &[String], prompt: &str) -> Vec<AppliedExemption> {
+        let now = Utc::now();
+        let mut state = self.state.lock().unwrap();
+        let mut applied = Vec::new();
+        for rule_id in matched_rules {
+            let Some(exemption) = state.active.iter_mut().find(|exemption| {
+                !exemption.is_expired_at(now)
+                    && !exemption.is_exhausted()
+                    && exemption.covers(rule_id, prompt)
+            }) else {
+                continue;
+            };
+            if let Some(remaining) = &mut exemption.remaining_uses {
+                *remaining -= 1;
+            }
+            get_metrics().increment_exemptions_applied(rule_id);
+            applied.push(AppliedExemption {
+                exemption_id: exemption.id.clone(),
+                rule_id: rule_id.clone(),
+                remaining_uses: exemption.remaining_uses,
+                related_correlation_id: exemption.related_correlation_id.clone(),
+            });
+        }
+        applied
+    }
+
+    /// Move expired and exhausted entries to the archive, auditing each one
+    pub fn sweep(&self) -> Vec<ArchivedExemption> {
+        let now = Utc::now();
+        let mut state = self.state.lock().unwrap();
+        let (lapsed, active): (Vec<_>, Vec<_>) = std::mem::take(&mut state.active)
+            .into_iter()
+            .partition(|exemption| exemption.is_expired_at(now) || exemption.is_exhausted());
+        state.active = active;
+
+        let correlation_id = generate_correlation_id();
+        let mut archived = Vec::with_capacity(lapsed.len());
+        for exemption in lapsed {
+            let reason = if exemption.is_expired_at(now) {
+                ArchiveReason::Expired
+            } else {
+                ArchiveReason::Exhausted
+            };
+            // The entry already stopped applying, so archive it even if auditing fails
+            if let Err(e) = self.audit(&correlation_id, reason.as_str(), &exemption) {
+                error!("Failed to audit archived exemption {}: {}", exemption.id, e);
+            }
+            info!("Exemption {} archived: {}", exemption.id, reason.as_str());
+            archived.push(state.archive(exemption, reason, now));
+        }
+        archived
+    }
+
+    /// Sweep every `interval` until the runtime shuts down
+    pub fn spawn_sweeper(&self, interval: Duration) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                service.sweep();
+            }
+        })
+    }
+
+    fn audit(
+        &self,
+        correlation_id: &str,
+        action: &str,
+        exemption: &Exemption,
+    ) -> Result<(), AuditError> {
+        self.audit_logger
+            .log_exemption(ExemptionEvent {
+                correlation_id: correlation_id.to_owned(),
+                event_type: "exemption".to_owned(),
+                action: action.to_owned(),
+                related_correlation_id: exemption.related_correlation_id.clone(),
+                exemption: exemption.clone(),
+            })
+            .map(drop)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ExemptionError {
+    #[error("invalid exemption: {0}")]
+    Invalid(String),
+    #[error("no active exemption with id {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
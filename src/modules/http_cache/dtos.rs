@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// How long clients may reuse a read-only response before revalidating it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// `Cache-Control: max-age` of `GET /api/compliance/config`
+    pub compliance_config_max_age_secs: u64,
+    /// `Cache-Control: max-age` of `GET /api/admin/summary`
+    pub summary_max_age_secs: u64,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            compliance_config_max_age_secs: 5,
+            summary_max_age_secs: 5,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::LazyLock;
+
+use http::HeaderMap;
+use http::header::IF_NONE_MATCH;
+use serde::Serialize;
+
+use crate::modules::audit::proof;
+
+/// Differs between processes, so tags issued before a restart never match
+/// version counters that started over
+static BOOT_ID: LazyLock<String> = LazyLock::new(|| uuid::Uuid::new_v4().simple().to_string());
+
+/// Strong tag for a resource whose state carries a version counter
+pub fn version_etag(resource: &str, version: u64) -> String {
+    format!("\"{resource}-{}-{version}\"", &BOOT_ID[..8])
+}
+
+/// Strong tag for a resource that is cheap to build but has no version of its own
+pub fn content_etag<T: Serialize>(value: &T) -> String {
+    format!("\"{}\"", &proof::content_hash(value)[..16])
+}
+
+/// `Cache-Control` value for a per-user response clients may reuse for `max_age_secs`
+pub fn cache_control(max_age_secs: u64) -> String {
+    format!("private, max-age={max_age_secs}")
+}
+
+/// Whether `If-None-Match` names `etag`, so the client's copy is still current
+pub fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        // If-None-Match uses weak comparison
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn headers(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn if_none_match_accepts_lists_weak_tags_and_wildcards() {
+        let etag = version_etag("compliance-config", 3);
+        assert!(!is_not_modified(&headers(&[]), &etag));
+        assert!(is_not_modified(&headers(&[&etag]), &etag));
+        assert!(is_not_modified(
+            &headers(&[&format!("\"other\", W/{etag}")]),
+            &etag
+        ));
+        assert!(is_not_modified(&headers(&["\"other\"", &etag]), &etag));
+        assert!(is_not_modified(&headers(&["*"]), &etag));
+        assert!(!is_not_modified(
+            &headers(&[&version_etag("compliance-config", 4)]),
+            &etag
+        ));
+    }
+
+    #[test]
+    fn content_tags_follow_the_content() {
+        assert_eq!(content_etag(&[1, 2]), content_etag(&[1, 2]));
+        assert_ne!(content_etag(&[1, 2]), content_etag(&[2, 1]));
+    }
+}
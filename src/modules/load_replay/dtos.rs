@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::ComplianceRequest;
+
+/// Sent with every replayed request; an instance that is not a replay target refuses it
+pub const REPLAY_HEADER: &str = "x-sentinel-replay";
+/// Sent by `GET /health` on an instance that accepts replayed traffic
+pub const REPLAY_TARGET_HEADER: &str = "x-replay-target";
+/// Value of [`REPLAY_TARGET_HEADER`]
+pub const REPLAY_TARGET_STAGING: &str = "staging";
+
+/// Replayed status of a request the target refused as invalid input (HTTP 422), such as
+/// a prompt over a lower `MAX_INPUT_LENGTH`
+pub const REJECTED_STATUS: &str = "rejected";
+
+/// An audited compliance check, rebuilt so it can be sent again
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ReplayCase {
+    /// Correlation id of the original request
+    pub correlation_id: String,
+    pub recorded_at: DateTime<Utc>,
+    pub request: ComplianceRequest,
+    /// `final_status` of the audit record
+    pub original_status: String,
+    /// Generation latency the original request recorded, when it reached generation
+    #[serde(default)]
+    pub original_latency_ms: Option<u64>,
+}
+
+/// Audit records that could not be replayed, by reason
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SkippedRecords {
+    /// Only the prompt hash was stored (`AUDIT_PROMPT_STORAGE=redacted`)
+    pub prompt_not_stored: usize,
+    /// Written by `POST /api/selftest`
+    pub self_test: usize,
+    /// Validated exchanges, configuration changes and other records that are not
+    /// compliance checks
+    pub not_a_check: usize,
+}
+
+/// Spread of latencies in milliseconds, or of prompt lengths in characters
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Distribution {
+    pub count: usize,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LatencyComparison {
+    /// Generation latency recorded by the requests that reached generation
+    pub original_generation: Distribution,
+    /// Round trip of each replayed request, as the replay saw it
+    pub replayed: Distribution,
+}
+
+/// A replayed request that reached a different status
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Divergence {
+    pub correlation_id: String,
+    /// Correlation id the target gave the replayed request; `None` when it was rejected
+    pub replay_correlation_id: Option<String>,
+    pub original_status: String,
+    pub replayed_status: String,
+}
+
+/// A replayed request the target did not answer
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReplayFailure {
+    pub correlation_id: String,
+    pub error: String,
+}
+
+/// Comparison of the original decisions with the target's
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LoadReplayReport {
+    pub generated_at: DateTime<Utc>,
+    /// Where the audit records came from: a source URL and window, or a bundle file
+    pub source: String,
+    pub target: String,
+    /// How many times faster than recorded the requests were sent
+    pub speedup: f64,
+    /// Time from the first recorded request to the last
+    pub recorded_span_ms: u64,
+    /// Time the replay took to send them all and collect the answers
+    pub replay_duration_ms: u64,
+    pub skipped: SkippedRecords,
+    /// Requests sent
+    pub requests: usize,
+    /// Answered with the original status
+    pub agreed: usize,
+    /// Answered with a different status
+    pub diverged: usize,
+    /// Not answered
+    pub failed: usize,
+    /// `agreed` over the answered requests; `None` when none was answered
+    pub status_agreement: Option<f64>,
+    /// Answered requests by original status, then by replayed status
+    pub status_matrix: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Prompt lengths in characters of the requests sent, so the traffic shape can be
+    /// checked against the window
+    pub prompt_length: Distribution,
+    pub latency: LatencyComparison,
+    pub divergences: Vec<Divergence>,
+    pub failures: Vec<ReplayFailure>,
+}
@@ -0,0 +1,3 @@
+pub mod dtos;
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+pub mod service;
@@ -0,0 +1,356 @@
+//! Replay of recorded compliance checks against a staging instance
+//!
+//! Requests are rebuilt from the decision records of an audit trail window, or of a
+//! saved `POST /api/audit/trail` response, and sent to the target at their recorded
+//! offsets from the first one, divided by the speed-up factor. Only prompts stored in
+//! full can be replayed. The target must say it is a staging instance before anything
+//! is sent, and refuses replayed requests otherwise.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use super::dtos::{
+    Distribution, Divergence, LatencyComparison, LoadReplayReport, REJECTED_STATUS, ReplayCase,
+    ReplayFailure, SkippedRecords,
+};
+use crate::client::{ClientError, PromptSentinelClient};
+use crate::modules::audit::logger::AuditEvent;
+use crate::modules::audit::storage::{AuditTrailRequest, PromptStorageMode, StoredAuditRecord};
+use crate::workflow::ComplianceRequest;
+
+/// Records fetched per audit trail page
+const SOURCE_PAGE_SIZE: usize = 500;
+
+/// Fetch every audit record written between `start` and `end` on the source instance
+pub async fn fetch_window(
+    source: &PromptSentinelClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<StoredAuditRecord>, LoadReplayError> {
+    let mut records = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = source
+            .audit_trail(AuditTrailRequest {
+                limit: Some(SOURCE_PAGE_SIZE),
+                offset: None,
+                start_time: Some(start),
+                end_time: Some(end),
+                correlation_id: None,
+                cursor,
+            })
+            .await
+            .map_err(LoadReplayError::Source)?;
+        records.extend(page.records);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(records),
+        }
+    }
+}
+
+/// Read an exported audit bundle: a saved `POST /api/audit/trail` response, or a JSON
+/// array of its records
+pub fn load_bundle(path: &Path) -> Result<Vec<StoredAuditRecord>, LoadReplayError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bundle {
+        Trail { records: Vec<StoredAuditRecord> },
+        Records(Vec<StoredAuditRecord>),
+    }
+
+    let bundle_error = |e: String| LoadReplayError::Bundle(format!("{}: {e}", path.display()));
+    let json = std::fs::read_to_string(path).map_err(|e| bundle_error(e.to_string()))?;
+    match serde_json::from_str(&json).map_err(|e| bundle_error(e.to_string()))? {
+        Bundle::Trail { records } | Bundle::Records(records) => Ok(records),
+    }
+}
+
+/// Rebuild the compliance checks among `records`, oldest first
+pub fn replay_cases(records: &[StoredAuditRecord]) -> (Vec<ReplayCase>, SkippedRecords) {
+    let mut skipped = SkippedRecords::default();
+    let mut cases = Vec::new();
+    for record in records {
+        let Ok(event) = serde_json::from_str::<AuditEvent>(&record.payload) else {
+            skipped.not_a_check += 1;
+            continue;
+        };
+        if event.self_test {
+            skipped.self_test += 1;
+        } else if event.exchange_validation.is_some() {
+            skipped.not_a_check += 1;
+        } else if event.prompt_storage != PromptStorageMode::Full {
+            skipped.prompt_not_stored += 1;
+        } else {
+            cases.push(ReplayCase {
+                correlation_id: event.correlation_id,
+                recorded_at: record.timestamp,
+                request: ComplianceRequest {
+                    correlation_id: None,
+                    prompt: event.original_prompt,
+                    suggest_rewrite: false,
+                    template: None,
+                    deterministic: None,
+                    session_id: event.session_id,
+                },
+                original_status: event.final_status,
+                original_latency_ms: event.response_latency_ms,
+            });
+        }
+    }
+    cases.sort_by_key(|case| case.recorded_at);
+    (cases, skipped)
+}
+
+/// Sends recorded traffic to a target instance and compares its decisions
+pub struct LoadReplayer {
+    target: PromptSentinelClient,
+    target_url: String,
+    speedup: f64,
+}
+
+impl LoadReplayer {
+    /// Replay against the server at `target_url`, in real time
+    ///
+    /// Failed requests are not retried, so the target sees the recorded load and no more.
+    pub fn new(target_url: impl Into<String>, api_key: Option<String>) -> Self {
+        let target_url = target_url.into();
+        Self {
+            target: PromptSentinelClient::new(target_url.clone(), api_key)
+                .as_replay()
+                .with_retries(0, Duration::ZERO),
+            target_url,
+            speedup: 1.0,
+        }
+    }
+
+    /// Send requests `speedup` times faster than they were recorded
+    pub fn with_speedup(mut self, speedup: f64) -> Self {
+        self.speedup = speedup;
+        self
+    }
+
+    /// Give up on a replayed request after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.target = self.target.with_timeout(timeout);
+        self
+    }
+
+    /// Replay the compliance checks among `records` and compare the outcomes
+    ///
+    /// `source` labels where the records came from in the report.
+    pub async fn run(
+        &self,
+        source: impl Into<String>,
+        records: &[StoredAuditRecord],
+    ) -> Result<LoadReplayReport, LoadReplayError> {
+        if !(self.speedup.is_finite() && self.speedup > 0.0) {
+            return Err(LoadReplayError::InvalidSpeedup(self.speedup));
+        }
+        let (cases, skipped) = replay_cases(records);
+        if cases.is_empty() {
+            return Err(LoadReplayError::NothingToReplay(skipped));
+        }
+        // Checked before the first request, so a production instance never sees one
+        if !self
+            .target
+            .is_replay_target()
+            .await
+            .map_err(LoadReplayError::Target)?
+        {
+            return Err(LoadReplayError::TargetNotStaging(self.target_url.clone()));
+        }
+
+        let first = cases[0].recorded_at;
+        let recorded_span = (cases[cases.len() - 1].recorded_at - first)
+            .to_std()
+            .unwrap_or_default();
+        info!(
+            "Replaying {} requests recorded over {:?} against {} at {}x",
+            cases.len(),
+            recorded_span,
+            self.target_url,
+            self.speedup
+        );
+        let started = Instant::now();
+        let mut sends = JoinSet::new();
+        for (index, case) in cases.iter().enumerate() {
+            let offset = (case.recorded_at - first)
+                .to_std()
+                .unwrap_or_default()
+                .div_f64(self.speedup);
+            let client = self.target.clone();
+            let request = case.request.clone();
+            sends.spawn(async move {
+                tokio::time::sleep_until(started + offset).await;
+                let sent = Instant::now();
+                let answer = match client.check(request).await {
+                    Ok(response) => Ok((response.status.name(), Some(response.correlation_id))),
+                    // Refused before the workflow ran, by a limit the original passed
+                    Err(ClientError::Api { code: 422, .. }) => {
+                        Ok((REJECTED_STATUS.to_owned(), None))
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                (index, sent.elapsed(), answer)
+            });
+        }
+        let mut outcomes: Vec<Option<ReplayOutcome>> = vec![None; cases.len()];
+        while let Some(joined) = sends.join_next().await {
+            let (index, latency, answer) = joined.expect("replayed requests do not panic");
+            outcomes[index] = Some((latency, answer));
+        }
+        let outcomes = outcomes.into_iter().flatten().collect::<Vec<_>>();
+
+        let mut report = compare(&cases, &outcomes);
+        report.source = source.into();
+        report.target = self.target_url.clone();
+        report.speedup = self.speedup;
+        report.skipped = skipped;
+        report.recorded_span_ms = recorded_span.as_millis() as u64;
+        report.replay_duration_ms = started.elapsed().as_millis() as u64;
+        if report.diverged > 0 {
+            warn!(
+                "{} of {} replayed requests diverged from the original decision",
+                report.diverged, report.requests
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// Round trip of one replayed request, and its status and correlation id on the target
+type ReplayOutcome = (Duration, Result<(String, Option<String>), String>);
+
+/// Compare each case's original status with its replayed one
+fn compare(cases: &[ReplayCase], outcomes: &[ReplayOutcome]) -> LoadReplayReport {
+    let mut status_matrix: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    let mut divergences = Vec::new();
+    let mut failures = Vec::new();
+    let mut latencies = Vec::new();
+    let mut agreed = 0;
+    for (case, (latency, answer)) in cases.iter().zip(outcomes) {
+        let (replayed_status, replay_correlation_id) = match answer {
+            Ok(answer) => answer.clone(),
+            Err(error) => {
+                failures.push(ReplayFailure {
+                    correlation_id: case.correlation_id.clone(),
+                    error: error.clone(),
+                });
+                continue;
+            }
+        };
+        latencies.push(latency.as_millis() as u64);
+        *status_matrix
+            .entry(case.original_status.clone())
+            .or_default()
+            .entry(replayed_status.clone())
+            .or_default() += 1;
+        if replayed_status == case.original_status {
+            agreed += 1;
+        } else {
+            divergences.push(Divergence {
+                correlation_id: case.correlation_id.clone(),
+                replay_correlation_id,
+                original_status: case.original_status.clone(),
+                replayed_status,
+            });
+        }
+    }
+
+    let answered = agreed + divergences.len();
+    LoadReplayReport {
+        generated_at: Utc::now(),
+        source: String::new(),
+        target: String::new(),
+        speedup: 1.0,
+        recorded_span_ms: 0,
+        replay_duration_ms: 0,
+        skipped: SkippedRecords::default(),
+        requests: cases.len(),
+        agreed,
+        diverged: divergences.len(),
+        failed: failures.len(),
+        status_agreement: (answered > 0).then(|| agreed as f64 / answered as f64),
+        status_matrix,
+        prompt_length: distribution(
+            cases
+                .iter()
+                .map(|case| case.request.prompt.chars().count() as u64)
+                .collect(),
+        ),
+        latency: LatencyComparison {
+            original_generation: distribution(
+                cases
+                    .iter()
+                    .filter_map(|case| case.original_latency_ms)
+                    .collect(),
+            ),
+            replayed: distribution(latencies),
+        },
+        divergences,
+        failures,
+    }
+}
+
+/// Nearest-rank percentiles of `values`
+fn distribution(mut values: Vec<u64>) -> Distribution {
+    if values.is_empty() {
+        return Distribution::default();
+    }
+    values.sort_unstable();
+    let rank = |quantile: f64| {
+        let index = (quantile * values.len() as f64).ceil() as usize;
+        values[index.clamp(1, values.len()) - 1]
+    };
+    Distribution {
+        count: values.len(),
+        p50: rank(0.50),
+        p90: rank(0.90),
+        p99: rank(0.99),
+        max: values[values.len() - 1],
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoadReplayError {
+    #[error("{0} is not marked as a staging instance that accepts replayed traffic")]
+    TargetNotStaging(String),
+    #[error("speed-up factor must be a positive number, not {0}")]
+    InvalidSpeedup(f64),
+    #[error(
+        "no replayable compliance checks: {} without a stored prompt, {} self-tests, {} other records",
+        .0.prompt_not_stored, .0.self_test, .0.not_a_check
+    )]
+    NothingToReplay(SkippedRecords),
+    #[error("reading the source audit trail failed: {0}")]
+    Source(ClientError),
+    #[error("contacting the target failed: {0}")]
+    Target(ClientError),
+    #[error("unreadable audit bundle {0}")]
+    Bundle(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let spread = distribution((1..=100).rev().collect());
+        assert_eq!(
+            (spread.count, spread.p50, spread.p90, spread.p99, spread.max),
+            (100, 50, 90, 99, 100)
+        );
+        assert_eq!(distribution(vec![7]).p50, 7);
+        assert_eq!(distribution(Vec::new()), Distribution::default());
+    }
+}
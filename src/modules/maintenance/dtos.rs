@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAINTENANCE_MESSAGE: &str =
+    "Service is undergoing maintenance, please retry shortly";
+pub const DEFAULT_MAX_QUEUE: usize = 100;
+pub const DEFAULT_MAX_WAIT_MS: u64 = 30_000;
+
+/// Body of `POST /api/admin/maintenance`; omitted fields keep their current value
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub max_queue: Option<usize>,
+    #[serde(default)]
+    pub max_wait_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MaintenanceSettings {
+    pub enabled: bool,
+    /// Returned to callers whose request is rejected
+    pub message: String,
+    /// Maximum number of requests held at once
+    pub max_queue: usize,
+    /// Longest a single request may wait before it is rejected
+    pub max_wait_ms: u64,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: DEFAULT_MAINTENANCE_MESSAGE.to_owned(),
+            max_queue: DEFAULT_MAX_QUEUE,
+            max_wait_ms: DEFAULT_MAX_WAIT_MS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MaintenanceStatus {
+    pub settings: MaintenanceSettings,
+    pub queue_depth: usize,
+    pub queued_total: u64,
+    pub rejected_total: u64,
+    /// Wait of the most recently released request
+    pub last_wait_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    QueueFull,
+    WaitExceeded,
+}
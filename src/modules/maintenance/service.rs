@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+use super::dtos::{MaintenanceRequest, MaintenanceSettings, MaintenanceStatus, RejectionReason};
+use crate::modules::audit::logger::{AuditError, AuditLogger, ConfigChangeEvent};
+use crate::modules::audit::proof::hash_record;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Holds Mistral-bound requests in a bounded FIFO queue while maintenance is enabled
+#[derive(Clone)]
+pub struct MaintenanceService {
+    state: Arc<Mutex<GateState>>,
+    audit_logger: AuditLogger,
+}
+
+#[derive(Default)]
+struct GateState {
+    settings: MaintenanceSettings,
+    waiters: VecDeque<Waiter>,
+    next_ticket: u64,
+    queued_total: u64,
+    rejected_total: u64,
+    last_wait_ms: Option<u64>,
+}
+
+struct Waiter {
+    ticket: u64,
+    release: oneshot::Sender<()>,
+}
+
+impl GateState {
+    fn status(&self) -> MaintenanceStatus {
+        MaintenanceStatus {
+            settings: self.settings.clone(),
+            queue_depth: self.waiters.len(),
+            queued_total: self.queued_total,
+            rejected_total: self.rejected_total,
+            last_wait_ms: self.last_wait_ms,
+        }
+    }
+
+    fn reject(&mut self, reason: RejectionReason) -> MaintenanceRejection {
+        self.rejected_total += 1;
+        get_metrics().increment_maintenance_rejections(match reason {
+            RejectionReason::QueueFull => "queue_full",
+            RejectionReason::WaitExceeded => "wait_exceeded",
+        });
+        MaintenanceRejection {
+            reason,
+            message: self.settings.message.clone(),
+        }
+    }
+
+    /// Release every queued request, oldest first
+    fn drain(&mut self) {
+        while let Some(waiter) = self.waiters.pop_front() {
+            // The receiver is gone if the request timed out or its client disconnected
+            let _ = waiter.release.send(());
+        }
+        get_metrics().set_maintenance_queue_depth(0);
+    }
+}
+
+impl MaintenanceService {
+    pub fn new(audit_logger: AuditLogger) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(GateState::default())),
+            audit_logger,
+        }
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.state.lock().unwrap().status()
+    }
+
+    /// Wait until the request may proceed to Mistral
+    ///
+    /// Returns immediately when maintenance is off. Otherwise the caller joins the queue
+    /// and is released in arrival order once maintenance is disabled, or rejected when the
+    /// queue is full or its wait budget runs out.
+    pub async fn admit(&self) -> Result<(), MaintenanceRejection> {
+        let (ticket, receiver, max_wait) = {
+            let mut state = self.state.lock().unwrap();
+            if !state.settings.enabled {
+                return Ok(());
+            }
+            if state.waiters.len() >= state.settings.max_queue {
+                return Err(state.reject(RejectionReason::QueueFull));
+            }
+            let (release, receiver) = oneshot::channel();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.queued_total += 1;
+            state.waiters.push_back(Waiter { ticket, release });
+            get_metrics().set_maintenance_queue_depth(state.waiters.len());
+            (
+                ticket,
+                receiver,
+                Duration::from_millis(state.settings.max_wait_ms),
+            )
+        };
+
+        let queued_at = Instant::now();
+        let released = tokio::time::timeout(max_wait, receiver).await.is_ok();
+        let waited = queued_at.elapsed();
+
+        let mut state = self.state.lock().unwrap();
+        if !released {
+            // The queue may have been drained between the timeout firing and taking the lock
+            if let Some(position) = state.waiters.iter().position(|w| w.ticket == ticket) {
+                state.waiters.remove(position);
+                get_metrics().set_maintenance_queue_depth(state.waiters.len());
+                return Err(state.reject(RejectionReason::WaitExceeded));
+            }
+        }
+        state.last_wait_ms = Some(waited.as_millis() as u64);
+        get_metrics().record_maintenance_wait(waited.as_secs_f64());
+        Ok(())
+    }
+
+    /// Apply a maintenance toggle, auditing the transition before it takes effect
+    pub fn update(
+        &self,
+        correlation_id: &str,
+        request: MaintenanceRequest,
+    ) -> Result<MaintenanceStatus, MaintenanceError> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.settings.clone();
+        let next = MaintenanceSettings {
+            enabled: request.enabled,
+            message: request.message.unwrap_or_else(|| previous.message.clone()),
+            max_queue: request.max_queue.unwrap_or(previous.max_queue),
+            max_wait_ms: request.max_wait_ms.unwrap_or(previous.max_wait_ms),
+        };
+        validate_settings(&next).map_err(MaintenanceError::Invalid)?;
+
+        let action = match (previous.enabled, next.enabled) {
+            (false, true) => "maintenance_enabled",
+            (true, false) => "maintenance_disabled",
+            _ => "maintenance_updated",
+        };
+        self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: action.to_owned(),
+            previous_hash: settings_hash(&previous),
+            new_hash: settings_hash(&next),
+            snapshot_version: None,
+            detail: Some(next.message.clone()),
+        })?;
+
+        info!(
+            "Maintenance mode {}: max_queue={}, max_wait_ms={}",
+            if next.enabled { "enabled" } else { "disabled" },
+            next.max_queue,
+            next.max_wait_ms
+        );
+        state.settings = next;
+        if !state.settings.enabled {
+            state.drain();
+        } else if state.waiters.len() > state.settings.max_queue {
+            warn!(
+                "Maintenance queue holds {} requests, above the new limit of {}",
+                state.waiters.len(),
+                state.settings.max_queue
+            );
+        }
+        Ok(state.status())
+    }
+}
+
+fn validate_settings(settings: &MaintenanceSettings) -> Result<(), String> {
+    if settings.message.trim().is_empty() {
+        return Err("message must not be empty".to_owned());
+    }
+    if settings.max_wait_ms == 0 {
+        return Err("max_wait_ms must be greater than zero".to_owned());
+    }
+    Ok(())
+}
+
+fn settings_hash(settings: &MaintenanceSettings) -> String {
+    hash_record(&serde_json::to_string(settings).unwrap_or_default())
+}
+
+/// A request turned away while maintenance is enabled
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct MaintenanceRejection {
+    pub reason: RejectionReason,
+    pub message: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MaintenanceError {
+    #[error("invalid maintenance settings: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
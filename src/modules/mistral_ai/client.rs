@@ -1,18 +1,26 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use async_trait::async_trait;
 use reqwest::Client;
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
 use super::dtos::{
     ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
-    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationRequest,
-    ModerationResponse, TokenUsage, TranslationRequest, TranslationResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationBatchRequest,
+    ModerationRequest, ModerationResponse, TokenUsage, TranslationRequest, TranslationResponse,
 };
+use super::severity::ModerationSeverity;
+use super::wire::{
+    self, ChatCompletionBody, EmbeddingsBody, ModelListBody, ModerationBody, ModerationResultBody,
+    UsageBody, invalid_response,
+};
+use crate::modules::diagnostics::service::record_mistral_retry;
 use crate::modules::mistral_ai::dtos::ChatMessage;
+use crate::modules::telemetry::metrics::{RequestTimer, get_metrics, status_class};
 
 #[async_trait]
 pub trait MistralClient: Send + Sync {
@@ -24,6 +32,25 @@ pub trait MistralClient: Send + Sync {
         &self,
         request: ModerationRequest,
     ) -> Result<ModerationResponse, MistralClientError>;
+    /// Moderate several inputs, returning one response per input in the same order
+    ///
+    /// Clients without a batch endpoint moderate each input in turn.
+    async fn moderate_batch(
+        &self,
+        request: ModerationBatchRequest,
+    ) -> Result<Vec<ModerationResponse>, MistralClientError> {
+        let mut responses = Vec::with_capacity(request.input.len());
+        for input in request.input {
+            responses.push(
+                self.moderate(ModerationRequest {
+                    model: request.model.clone(),
+                    input,
+                })
+                .await?,
+            );
+        }
+        Ok(responses)
+    }
     async fn embeddings(
         &self,
         request: EmbeddingRequest,
@@ -39,51 +66,148 @@ pub trait MistralClient: Send + Sync {
     ) -> Result<TranslationResponse, MistralClientError>;
 }
 
+/// Paths of the Mistral endpoints, joined to the base URL
+///
+/// The defaults are Mistral's own routes; self-hosted gateways that expose them elsewhere
+/// override them one by one. Language detection and translation go through `chat`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MistralApiPaths {
+    pub chat: String,
+    pub moderation: String,
+    pub embeddings: String,
+    pub models: String,
+}
+
+impl Default for MistralApiPaths {
+    fn default() -> Self {
+        Self {
+            chat: "/v1/chat/completions".to_owned(),
+            moderation: "/v1/moderations".to_owned(),
+            embeddings: "/v1/embeddings".to_owned(),
+            models: "/v1/models".to_owned(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HttpMistralClient {
     http: Client,
+    /// Normalized by [`normalize_base_url`]
     base_url: String,
+    paths: MistralApiPaths,
     api_key: String,
     max_retries: u32,
     retry_delay: Duration,
+    /// Failed attempts in a row per endpoint, shared between clones
+    failure_streaks: Arc<Mutex<HashMap<MistralEndpoint, u64>>>,
 }
 
 impl HttpMistralClient {
-    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>) -> Self {
-        Self {
+    /// Client for the API under `base_url`, which must be an `http` or `https` URL
+    ///
+    /// A base URL ending in `/v1`, the version segment Mistral's paths start with, is
+    /// accepted with a warning and the segment is not sent twice.
+    pub fn new(
+        base_url: impl Into<String>,
+        api_key: impl Into<String>,
+    ) -> Result<Self, MistralClientError> {
+        let base_url = normalize_base_url(&base_url.into())?;
+        if base_url.ends_with("/v1") {
+            warn!(
+                "Mistral base URL {} ends with /v1: it is not repeated for endpoint paths \
+                 starting with /v1, but MISTRAL_BASE_URL should be the API root, e.g. \
+                 https://api.mistral.ai",
+                base_url
+            );
+        }
+        Ok(Self {
             http: Client::builder()
                 .timeout(Duration::from_secs(120)) // Increased timeout from 30s to 60s
                 .build()
                 .unwrap(),
-            base_url: base_url.into(),
+            base_url,
+            paths: MistralApiPaths::default(),
             api_key: api_key.into(),
             max_retries: 3,
             retry_delay: Duration::from_millis(500),
-        }
+            failure_streaks: Arc::default(),
+        })
+    }
+
+    /// Send each endpoint's requests to `paths` instead of Mistral's own routes
+    pub fn with_paths(mut self, paths: MistralApiPaths) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Wait `delay` between attempts instead of 500 ms
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
     }
 
     fn url(&self, path: &str) -> String {
-        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+        join_url(&self.base_url, path)
     }
 
+    /// Record the outcome of one attempt and update the endpoint's failure streak
+    fn record_attempt(&self, endpoint: MistralEndpoint, status_class: &str, timer: &RequestTimer) {
+        let metrics = get_metrics();
+        let label = endpoint.as_str();
+        metrics.increment_mistral_api_calls(label, status_class);
+        metrics.record_mistral_api_latency(label, "attempt", timer.elapsed_seconds());
+
+        let mut streaks = self.failure_streaks.lock().unwrap();
+        let streak = streaks.entry(endpoint).or_default();
+        if status_class == "2xx" {
+            *streak = 0;
+        } else {
+            *streak += 1;
+        }
+        metrics.set_mistral_api_failure_streak(label, *streak);
+    }
+
+    /// Body of the first successful response, decoded as `endpoint`'s response
+    ///
+    /// A body that does not decode fails at once: the same body would come back.
     async fn send_request_with_retry<T: serde::de::DeserializeOwned>(
         &self,
+        endpoint: MistralEndpoint,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<(T, String), MistralClientError> {
+        let call_timer = RequestTimer::new();
+        let result = self.send_attempts(endpoint, request_builder).await;
+        get_metrics().record_mistral_api_latency(
+            endpoint.as_str(),
+            "call",
+            call_timer.elapsed_seconds(),
+        );
+        let body = result?;
+        Ok((wire::decode(endpoint, &body)?, body))
+    }
+
+    async fn send_attempts(
+        &self,
+        endpoint: MistralEndpoint,
         request_builder: reqwest::RequestBuilder,
-    ) -> Result<T, MistralClientError> {
+    ) -> Result<String, MistralClientError> {
         let mut last_error = None;
 
         for attempt in 0..=self.max_retries {
             match request_builder.try_clone() {
                 Some(cloned_builder) => {
                     debug!("Attempt {} for Mistral API request", attempt + 1);
+                    let attempt_timer = RequestTimer::new();
 
                     match cloned_builder.send().await {
                         Ok(response) => {
                             let status = response.status();
+                            let response_url = response.url().clone();
+                            self.record_attempt(endpoint, status_class(status), &attempt_timer);
                             if response.status().is_success() {
-                                let json = response.json::<T>().await?;
+                                let body = response.text().await?;
                                 debug!("Mistral API request successful");
-                                return Ok(json);
+                                return Ok(body);
                             } else {
                                 let error_body = response.text().await.unwrap_or_default();
                                 error!("Mistral API error {}: {}", status, error_body);
@@ -103,10 +227,23 @@ impl HttpMistralClient {
                                         message: format!("Rate limited: {}", error_body),
                                     });
                                 } else if status == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
-                                    last_error = Some(MistralClientError::ApiError {
+                                    // The same payload is rejected however often it is sent
+                                    return Err(MistralClientError::ApiError {
                                         status: status.as_u16(),
                                         message: format!("Prompt too large: {}", error_body),
                                     });
+                                } else if status == reqwest::StatusCode::NOT_FOUND {
+                                    // A missing route stays missing; it is almost always
+                                    // the base URL or a path override
+                                    return Err(MistralClientError::ApiError {
+                                        status: status.as_u16(),
+                                        message: format!(
+                                            "Not found at {}: {} (check that MISTRAL_BASE_URL \
+                                             is the API root, e.g. https://api.mistral.ai, \
+                                             and the MISTRAL_*_PATH overrides)",
+                                            response_url, error_body
+                                        ),
+                                    });
                                 } else {
                                     last_error = Some(MistralClientError::ApiError {
                                         status: status.as_u16(),
@@ -117,6 +254,7 @@ impl HttpMistralClient {
                         }
                         Err(e) => {
                             error!("Mistral API request failed: {}", e);
+                            self.record_attempt(endpoint, "error", &attempt_timer);
                             last_error = Some(MistralClientError::Request(e));
                         }
                     }
@@ -130,6 +268,8 @@ impl HttpMistralClient {
 
             if attempt < self.max_retries {
                 warn!("Retrying in {:?}...", self.retry_delay);
+                get_metrics().increment_mistral_api_retries(endpoint.as_str());
+                record_mistral_retry(endpoint.as_str());
                 tokio::time::sleep(self.retry_delay).await;
             }
         }
@@ -153,26 +293,16 @@ impl MistralClient for HttpMistralClient {
 
         let request_builder = self
             .http
-            .post(self.url("/v1/chat/completions"))
+            .post(self.url(&self.paths.chat))
             .bearer_auth(&self.api_key)
             .json(&request);
 
-        let json: Value = self.send_request_with_retry(request_builder).await?;
-        let output_text = extract_content(&json)?;
-        let model = json
-            .get("model")
-            .and_then(Value::as_str)
-            .unwrap_or(request.model.as_str())
-            .to_owned();
-
-        // Extract token usage if available
-        let usage = json.get("usage").and_then(|u| {
-            Some(TokenUsage {
-                prompt_tokens: u.get("prompt_tokens")?.as_u64()? as u32,
-                completion_tokens: u.get("completion_tokens")?.as_u64()? as u32,
-                total_tokens: u.get("total_tokens")?.as_u64()? as u32,
-            })
-        });
+        let (response, body): (ChatCompletionBody, _) = self
+            .send_request_with_retry(MistralEndpoint::Chat, request_builder)
+            .await?;
+        let output_text = response.output_text(&body)?;
+        let model = response.model.unwrap_or(request.model);
+        let usage = response.usage.as_ref().and_then(UsageBody::token_usage);
 
         debug!("Chat completion successful for model: {}", model);
         Ok(ChatCompletionResponse {
@@ -190,48 +320,65 @@ impl MistralClient for HttpMistralClient {
 
         let request_builder = self
             .http
-            .post(self.url("/v1/moderations"))
+            .post(self.url(&self.paths.moderation))
             .bearer_auth(&self.api_key)
             .json(&request);
 
-        let json: Value = self.send_request_with_retry(request_builder).await?;
-        let result = json
-            .get("results")
-            .and_then(Value::as_array)
-            .and_then(|results| results.first())
-            .ok_or_else(|| {
-                MistralClientError::InvalidResponse("missing moderation results".to_owned())
-            })?;
-
-        let flagged = result
-            .get("flagged")
-            .and_then(Value::as_bool)
-            .unwrap_or(false);
-
-        let mut categories = Vec::new();
-        if let Some(map) = result.get("categories").and_then(Value::as_object) {
-            for (category, value) in map {
-                if value.as_bool().unwrap_or(false) {
-                    categories.push(category.clone());
-                }
-            }
-        }
-
-        let severity = if flagged {
-            (categories.len() as f32 / 5.0).min(1.0)
-        } else {
-            0.0
-        };
+        let (moderation, body): (ModerationBody, _) = self
+            .send_request_with_retry(MistralEndpoint::Moderation, request_builder)
+            .await?;
+        let result = moderation.results.into_iter().next().ok_or_else(|| {
+            invalid_response(
+                MistralEndpoint::Moderation,
+                "missing moderation results",
+                &body,
+            )
+        })?;
 
+        let response = parse_moderation_result(result);
         debug!(
             "Moderation completed: flagged={}, severity={}",
-            flagged, severity
+            response.flagged, response.severity
         );
-        Ok(ModerationResponse {
-            flagged,
-            categories,
-            severity,
-        })
+        Ok(response)
+    }
+
+    async fn moderate_batch(
+        &self,
+        request: ModerationBatchRequest,
+    ) -> Result<Vec<ModerationResponse>, MistralClientError> {
+        info!(
+            "Sending batch moderation request with {} inputs",
+            request.input.len()
+        );
+
+        let request_builder = self
+            .http
+            .post(self.url(&self.paths.moderation))
+            .bearer_auth(&self.api_key)
+            .json(&request);
+
+        let (ModerationBody { results }, _): (ModerationBody, String) = self
+            .send_request_with_retry(MistralEndpoint::Moderation, request_builder)
+            .await?;
+        // Results are matched to inputs by position only, so a short or long list cannot
+        // be attributed safely
+        if results.len() != request.input.len() {
+            return Err(MistralClientError::InvalidResponse(format!(
+                "moderation returned {} results for {} inputs",
+                results.len(),
+                request.input.len()
+            )));
+        }
+
+        let responses: Vec<ModerationResponse> =
+            results.into_iter().map(parse_moderation_result).collect();
+        debug!(
+            "Batch moderation completed: {} of {} flagged",
+            responses.iter().filter(|response| response.flagged).count(),
+            responses.len()
+        );
+        Ok(responses)
     }
 
     async fn embeddings(
@@ -242,25 +389,25 @@ impl MistralClient for HttpMistralClient {
 
         let request_builder = self
             .http
-            .post(self.url("/v1/embeddings"))
+            .post(self.url(&self.paths.embeddings))
             .bearer_auth(&self.api_key)
             .json(&request);
 
-        let json: Value = self.send_request_with_retry(request_builder).await?;
-        let vector_values = json
-            .get("data")
-            .and_then(Value::as_array)
-            .and_then(|data| data.first())
-            .and_then(|item| item.get("embedding"))
-            .and_then(Value::as_array)
+        let (embeddings, body): (EmbeddingsBody, _) = self
+            .send_request_with_retry(MistralEndpoint::Embeddings, request_builder)
+            .await?;
+        let vector = embeddings
+            .data
+            .into_iter()
+            .next()
             .ok_or_else(|| {
-                MistralClientError::InvalidResponse("missing embedding vector".to_owned())
-            })?;
-
-        let vector = vector_values
-            .iter()
-            .map(|value| value.as_f64().unwrap_or_default() as f32)
-            .collect::<Vec<_>>();
+                invalid_response(
+                    MistralEndpoint::Embeddings,
+                    "missing embedding vector",
+                    &body,
+                )
+            })?
+            .embedding;
 
         debug!("Embedding successful: vector length = {}", vector.len());
         Ok(EmbeddingResponse {
@@ -274,18 +421,13 @@ impl MistralClient for HttpMistralClient {
 
         let request_builder = self
             .http
-            .get(self.url("/v1/models"))
+            .get(self.url(&self.paths.models))
             .bearer_auth(&self.api_key);
 
-        let json: Value = self.send_request_with_retry(request_builder).await?;
-        let models = json
-            .get("data")
-            .and_then(Value::as_array)
-            .ok_or_else(|| MistralClientError::InvalidResponse("missing model list".to_owned()))?
-            .iter()
-            .filter_map(|model| model.get("id").and_then(Value::as_str))
-            .map(ToOwned::to_owned)
-            .collect::<Vec<_>>();
+        let (ModelListBody { data }, _): (ModelListBody, String) = self
+            .send_request_with_retry(MistralEndpoint::Models, request_builder)
+            .await?;
+        let models = data.into_iter().map(|model| model.id).collect::<Vec<_>>();
 
         debug!("Available models: {:?}", models);
         Ok(ModelListResponse { models })
@@ -309,7 +451,11 @@ impl MistralClient for HttpMistralClient {
                 content: prompt,
             }],
             safe_prompt: false, // Don't add safety prefix - we want raw language detection
-        };
+            temperature: None,
+            max_tokens: None,
+            random_seed: None,
+        }
+        .with_sampling(request.sampling);
 
         let response = self.chat_completion(chat_request).await?;
 
@@ -346,7 +492,11 @@ impl MistralClient for HttpMistralClient {
                 content: prompt,
             }],
             safe_prompt: false, // Don't add safety moderation - we need raw translations for analysis
-        };
+            temperature: None,
+            max_tokens: None,
+            random_seed: None,
+        }
+        .with_sampling(request.sampling);
 
         let response = self.chat_completion(chat_request).await?;
 
@@ -356,12 +506,126 @@ impl MistralClient for HttpMistralClient {
     }
 }
 
+/// Client endpoints, for scripting and inspecting [`MockMistralClient`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MistralEndpoint {
+    Chat,
+    Moderation,
+    Embeddings,
+    Models,
+    LanguageDetection,
+    Translation,
+}
+
+impl MistralEndpoint {
+    /// `endpoint` label of the Mistral API metrics
+    ///
+    /// [`HttpMistralClient`] runs language detection and translation as chat
+    /// completions, so only the first four appear in its metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Moderation => "moderate",
+            Self::Embeddings => "embeddings",
+            Self::Models => "models",
+            Self::LanguageDetection => "language_detection",
+            Self::Translation => "translation",
+        }
+    }
+}
+
+/// Error returned by a scripted mock call
+///
+/// Kept separate from [`MistralClientError`], which cannot be cloned, so one script can
+/// fail several calls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptedFailure {
+    Api { status: u16, message: String },
+    InvalidResponse(String),
+}
+
+impl ScriptedFailure {
+    /// HTTP 503, the default for the `fail_*_times` builders
+    pub fn unavailable() -> Self {
+        Self::Api {
+            status: 503,
+            message: "service unavailable (scripted)".to_owned(),
+        }
+    }
+
+    /// HTTP 429
+    pub fn rate_limited() -> Self {
+        Self::Api {
+            status: 429,
+            message: "rate limit exceeded (scripted)".to_owned(),
+        }
+    }
+
+    fn to_error(&self) -> MistralClientError {
+        match self {
+            Self::Api { status, message } => MistralClientError::ApiError {
+                status: *status,
+                message: message.clone(),
+            },
+            Self::InvalidResponse(message) => MistralClientError::InvalidResponse(message.clone()),
+        }
+    }
+}
+
+/// A request received by [`MockMistralClient`] while recording
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedCall {
+    Chat(ChatCompletionRequest),
+    Moderation(ModerationRequest),
+    /// One call carrying several inputs; counts as a single moderation call
+    ModerationBatch(ModerationBatchRequest),
+    Embeddings(EmbeddingRequest),
+    Models,
+    LanguageDetection(LanguageDetectionRequest),
+    Translation(TranslationRequest),
+}
+
+impl RecordedCall {
+    pub fn endpoint(&self) -> MistralEndpoint {
+        match self {
+            Self::Chat(_) => MistralEndpoint::Chat,
+            Self::Moderation(_) | Self::ModerationBatch(_) => MistralEndpoint::Moderation,
+            Self::Embeddings(_) => MistralEndpoint::Embeddings,
+            Self::Models => MistralEndpoint::Models,
+            Self::LanguageDetection(_) => MistralEndpoint::LanguageDetection,
+            Self::Translation(_) => MistralEndpoint::Translation,
+        }
+    }
+}
+
+/// Scripted behaviour shared by every clone of a mock
+#[derive(Debug, Default)]
+struct MockScript {
+    /// Runs of `(remaining calls, failure)`, consumed front to back
+    failures: HashMap<MistralEndpoint, VecDeque<(usize, ScriptedFailure)>>,
+    delays: HashMap<MistralEndpoint, Duration>,
+    /// `Some` once recording is enabled
+    calls: Option<Vec<RecordedCall>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct MockMistralClient {
     chat_response: ChatCompletionResponse,
+    chat_overrides: Vec<(String, String)>,
     moderation_responses: Arc<Mutex<Vec<ModerationResponse>>>,
+    moderation_overrides: Vec<(String, ModerationResponse)>,
+    /// Answer batch moderation with HTTP 422, like a provider that only takes strings
+    rejects_batch_moderation: bool,
+    /// Longest moderation input, in characters, accepted before answering HTTP 413
+    moderation_input_limit: Option<usize>,
+    /// Returned for every embedding input unless deterministic embeddings are enabled
     embedding_response: EmbeddingResponse,
-    models: Vec<String>,
+    embedding_overrides: Vec<(String, Vec<f32>)>,
+    /// `(seed, dimension)` of text-derived embeddings
+    deterministic_embeddings: Option<(u64, usize)>,
+    /// Shared between clones, like the script
+    models: Arc<Mutex<Vec<String>>>,
+    script: Arc<Mutex<MockScript>>,
 }
 
 impl Default for MockMistralClient {
@@ -376,26 +640,25 @@ impl Default for MockMistralClient {
                     total_tokens: 30,
                 }),
             },
+            chat_overrides: Vec::new(),
             moderation_responses: Arc::new(Mutex::new(vec![
-                ModerationResponse {
-                    flagged: false,
-                    categories: Vec::new(),
-                    severity: 0.0,
-                },
-                ModerationResponse {
-                    flagged: false,
-                    categories: Vec::new(),
-                    severity: 0.0,
-                },
+                ModerationResponse::default(),
+                ModerationResponse::default(),
             ])),
+            moderation_overrides: Vec::new(),
+            rejects_batch_moderation: false,
+            moderation_input_limit: None,
             embedding_response: EmbeddingResponse {
                 model: "mistral-embed".to_owned(),
                 vector: vec![0.1, 0.2, 0.3],
             },
-            models: vec![
+            embedding_overrides: Vec::new(),
+            deterministic_embeddings: None,
+            models: Arc::new(Mutex::new(vec![
                 "mistral-large-latest".to_owned(),
                 "mistral-embed".to_owned(),
-            ],
+            ])),
+            script: Arc::default(),
         }
     }
 }
@@ -419,42 +682,350 @@ impl MockMistralClient {
         self.chat_response = response;
         self
     }
+
+    /// Answers with `output_text` any chat request whose messages contain `needle`
+    /// (case-insensitive); other requests get the chat response
+    pub fn with_chat_override(
+        mut self,
+        needle: impl Into<String>,
+        output_text: impl Into<String>,
+    ) -> Self {
+        self.chat_overrides
+            .push((needle.into().to_lowercase(), output_text.into()));
+        self
+    }
+
+    /// Returns `response` for any moderation input containing `needle`
+    /// (case-insensitive), bypassing the moderation sequence.
+    pub fn with_moderation_override(
+        mut self,
+        needle: impl Into<String>,
+        response: ModerationResponse,
+    ) -> Self {
+        self.moderation_overrides
+            .push((needle.into().to_lowercase(), response));
+        self
+    }
+
+    /// Reject array input to moderation with HTTP 422; single inputs still work
+    pub fn without_batch_moderation(mut self) -> Self {
+        self.rejects_batch_moderation = true;
+        self
+    }
+
+    /// Refuse moderation of any input longer than `chars` characters with HTTP 413,
+    /// a whole batch when one of its inputs is
+    pub fn with_moderation_input_limit(mut self, chars: usize) -> Self {
+        self.moderation_input_limit = Some(chars);
+        self
+    }
+
+    pub fn with_embedding_response(mut self, response: EmbeddingResponse) -> Self {
+        self.embedding_response = response;
+        self
+    }
+
+    /// Returns `vector` for any embedding input containing `needle` (case-insensitive)
+    pub fn with_embedding_override(mut self, needle: impl Into<String>, vector: Vec<f32>) -> Self {
+        self.embedding_overrides
+            .push((needle.into().to_lowercase(), vector));
+        self
+    }
+
+    /// Embed each input as a stable pseudo-random unit vector derived from its text
+    ///
+    /// Without this every input gets the same vector, so an initialized attack bank
+    /// matches any prompt with similarity 1.0. With a `dimension` in the hundreds,
+    /// unrelated texts come out nearly orthogonal; overrides still take precedence, so
+    /// tests can pin a prompt to a template with [`MockMistralClient::deterministic_embedding`].
+    pub fn with_deterministic_embeddings(mut self, seed: u64, dimension: usize) -> Self {
+        self.deterministic_embeddings = Some((seed, dimension));
+        self
+    }
+
+    /// Vector [`MockMistralClient::with_deterministic_embeddings`] returns for `text`
+    ///
+    /// FNV-1a over the seed and the exact input text seeds a SplitMix64 sequence, one
+    /// step per component in -1.0..1.0, and the result is scaled to unit length.
+    pub fn deterministic_embedding(text: &str, seed: u64, dimension: usize) -> Vec<f32> {
+        let mut state = seed
+            .to_le_bytes()
+            .iter()
+            .chain(text.as_bytes())
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        let mut vector: Vec<f32> = (0..dimension)
+            .map(|_| {
+                state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                ((z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0) as f32
+            })
+            .collect();
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+
+    /// Fail the next `times` embedding calls with HTTP 503
+    pub fn fail_embeddings_times(self, times: usize) -> Self {
+        self.fail_next(
+            MistralEndpoint::Embeddings,
+            times,
+            ScriptedFailure::unavailable(),
+        );
+        self
+    }
+
+    /// Fail the next `times` moderation calls with HTTP 503
+    pub fn fail_moderation_times(self, times: usize) -> Self {
+        self.fail_next(
+            MistralEndpoint::Moderation,
+            times,
+            ScriptedFailure::unavailable(),
+        );
+        self
+    }
+
+    /// Fail the next `times` chat completions with HTTP 503
+    pub fn fail_chat_times(self, times: usize) -> Self {
+        self.fail_next(MistralEndpoint::Chat, times, ScriptedFailure::unavailable());
+        self
+    }
+
+    /// Wait `delay` before answering every chat completion
+    pub fn delay_chat(self, delay: Duration) -> Self {
+        self.set_delay(MistralEndpoint::Chat, delay);
+        self
+    }
+
+    /// Keep every request for [`Self::recorded_calls`]
+    pub fn record_calls(self) -> Self {
+        self.script
+            .lock()
+            .unwrap()
+            .calls
+            .get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Fail the next `times` calls to `endpoint` with `failure`, after any failures
+    /// already scripted for it
+    ///
+    /// Clones share their script, so this also works on a mock already handed to a
+    /// service.
+    pub fn fail_next(&self, endpoint: MistralEndpoint, times: usize, failure: ScriptedFailure) {
+        if times > 0 {
+            self.script
+                .lock()
+                .unwrap()
+                .failures
+                .entry(endpoint)
+                .or_default()
+                .push_back((times, failure));
+        }
+    }
+
+    /// Answer model listings with `models` from now on, also on clones already handed
+    /// to a service
+    pub fn set_models(&self, models: Vec<String>) {
+        *self.models.lock().unwrap() = models;
+    }
+
+    /// Wait `delay` before answering every call to `endpoint`, including failed ones
+    pub fn set_delay(&self, endpoint: MistralEndpoint, delay: Duration) {
+        self.script.lock().unwrap().delays.insert(endpoint, delay);
+    }
+
+    /// Requests received since recording was enabled, in arrival order
+    pub fn recorded_calls(&self) -> Vec<RecordedCall> {
+        self.script
+            .lock()
+            .unwrap()
+            .calls
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Number of recorded requests to `endpoint`
+    pub fn call_count(&self, endpoint: MistralEndpoint) -> usize {
+        self.script
+            .lock()
+            .unwrap()
+            .calls
+            .as_ref()
+            .map_or(0, |calls| {
+                calls
+                    .iter()
+                    .filter(|call| call.endpoint() == endpoint)
+                    .count()
+            })
+    }
+
+    /// Next scripted moderation for `input`: a matching override, else the sequence
+    fn next_moderation(&self, input: &str) -> Result<ModerationResponse, MistralClientError> {
+        let input = input.to_lowercase();
+        if let Some((_, response)) = self
+            .moderation_overrides
+            .iter()
+            .find(|(needle, _)| input.contains(needle.as_str()))
+        {
+            return Ok(response.clone());
+        }
+
+        let mut guard = self.moderation_responses.lock().map_err(|_| {
+            MistralClientError::InvalidResponse("moderation queue poisoned".to_owned())
+        })?;
+
+        if guard.len() > 1 {
+            Ok(guard.remove(0))
+        } else {
+            Ok(guard[0].clone())
+        }
+    }
+
+    fn check_moderation_input(&self, input: &str) -> Result<(), MistralClientError> {
+        match self.moderation_input_limit {
+            Some(limit) if input.chars().count() > limit => Err(MistralClientError::ApiError {
+                status: 413,
+                message: format!("Prompt too large: input exceeds {limit} characters (scripted)"),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the call, apply the scripted delay and return the scripted failure, if any
+    async fn enter(
+        &self,
+        endpoint: MistralEndpoint,
+        call: impl FnOnce() -> RecordedCall,
+    ) -> Result<(), MistralClientError> {
+        let (delay, failure) = {
+            let mut script = self.script.lock().unwrap();
+            if let Some(calls) = script.calls.as_mut() {
+                calls.push(call());
+            }
+            let failure = script.failures.get_mut(&endpoint).and_then(|runs| {
+                let (remaining, failure) = runs.front_mut()?;
+                let error = failure.to_error();
+                *remaining -= 1;
+                if *remaining == 0 {
+                    runs.pop_front();
+                }
+                Some(error)
+            });
+            (script.delays.get(&endpoint).copied(), failure)
+        };
+
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        failure.map_or(Ok(()), Err)
+    }
 }
 
 #[async_trait]
 impl MistralClient for MockMistralClient {
     async fn chat_completion(
         &self,
-        _request: ChatCompletionRequest,
+        request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse, MistralClientError> {
-        Ok(self.chat_response.clone())
+        let content = request
+            .messages
+            .iter()
+            .map(|message| message.content.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.enter(MistralEndpoint::Chat, || RecordedCall::Chat(request))
+            .await?;
+        let overridden = self
+            .chat_overrides
+            .iter()
+            .find(|(needle, _)| content.contains(needle.as_str()));
+        Ok(match overridden {
+            Some((_, output_text)) => ChatCompletionResponse {
+                output_text: output_text.clone(),
+                ..self.chat_response.clone()
+            },
+            None => self.chat_response.clone(),
+        })
     }
 
     async fn moderate(
         &self,
-        _request: ModerationRequest,
+        request: ModerationRequest,
     ) -> Result<ModerationResponse, MistralClientError> {
-        let mut guard = self.moderation_responses.lock().map_err(|_| {
-            MistralClientError::InvalidResponse("moderation queue poisoned".to_owned())
-        })?;
+        let input = request.input.clone();
+        self.enter(MistralEndpoint::Moderation, || {
+            RecordedCall::Moderation(request)
+        })
+        .await?;
+        self.check_moderation_input(&input)?;
+        self.next_moderation(&input)
+    }
 
-        if guard.len() > 1 {
-            Ok(guard.remove(0))
-        } else {
-            Ok(guard[0].clone())
+    async fn moderate_batch(
+        &self,
+        request: ModerationBatchRequest,
+    ) -> Result<Vec<ModerationResponse>, MistralClientError> {
+        let inputs = request.input.clone();
+        self.enter(MistralEndpoint::Moderation, || {
+            RecordedCall::ModerationBatch(request)
+        })
+        .await?;
+        if self.rejects_batch_moderation {
+            return Err(MistralClientError::ApiError {
+                status: 422,
+                message: "input must be a string (scripted)".to_owned(),
+            });
         }
+        for input in &inputs {
+            self.check_moderation_input(input)?;
+        }
+        // Each input consumes the sequence as a single call would
+        inputs
+            .iter()
+            .map(|input| self.next_moderation(input))
+            .collect()
     }
 
     async fn embeddings(
         &self,
-        _request: EmbeddingRequest,
+        request: EmbeddingRequest,
     ) -> Result<EmbeddingResponse, MistralClientError> {
-        Ok(self.embedding_response.clone())
+        let text = request.input.clone();
+        let input = text.to_lowercase();
+        self.enter(MistralEndpoint::Embeddings, || {
+            RecordedCall::Embeddings(request)
+        })
+        .await?;
+        let overridden = self
+            .embedding_overrides
+            .iter()
+            .find(|(needle, _)| input.contains(needle.as_str()));
+        Ok(match (overridden, self.deterministic_embeddings) {
+            (Some((_, vector)), _) => EmbeddingResponse {
+                vector: vector.clone(),
+                ..self.embedding_response.clone()
+            },
+            (None, Some((seed, dimension))) => EmbeddingResponse {
+                vector: Self::deterministic_embedding(&text, seed, dimension),
+                ..self.embedding_response.clone()
+            },
+            (None, None) => self.embedding_response.clone(),
+        })
     }
 
     async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.enter(MistralEndpoint::Models, || RecordedCall::Models)
+            .await?;
         Ok(ModelListResponse {
-            models: self.models.clone(),
+            models: self.models.lock().unwrap().clone(),
         })
     }
 
@@ -464,6 +1035,10 @@ impl MistralClient for MockMistralClient {
     ) -> Result<LanguageDetectionResponse, MistralClientError> {
         // Simple mock: detect English or Spanish based on text
         let text_lower = request.text.to_ascii_lowercase();
+        self.enter(MistralEndpoint::LanguageDetection, || {
+            RecordedCall::LanguageDetection(request)
+        })
+        .await?;
         if text_lower.contains("hola") || text_lower.contains("el") || text_lower.contains("la") {
             Ok(LanguageDetectionResponse {
                 language: "Spanish".to_owned(),
@@ -481,6 +1056,10 @@ impl MistralClient for MockMistralClient {
         &self,
         request: TranslationRequest,
     ) -> Result<TranslationResponse, MistralClientError> {
+        self.enter(MistralEndpoint::Translation, || {
+            RecordedCall::Translation(request.clone())
+        })
+        .await?;
         // Mock client cannot actually translate - return original text unchanged.
         // For real multilingual support, use a real Mistral API key.
         // The real HttpMistralClient uses the Mistral API which supports any language.
@@ -490,35 +1069,58 @@ impl MistralClient for MockMistralClient {
     }
 }
 
-fn extract_content(response: &Value) -> Result<String, MistralClientError> {
-    let message_content = response
-        .get("choices")
-        .and_then(Value::as_array)
-        .and_then(|choices| choices.first())
-        .and_then(|choice| choice.get("message"))
-        .and_then(|message| message.get("content"))
-        .ok_or_else(|| {
-            MistralClientError::InvalidResponse("missing response content".to_owned())
-        })?;
+fn parse_moderation_result(result: ModerationResultBody) -> ModerationResponse {
+    let categories: Vec<String> = result
+        .categories
+        .into_iter()
+        .filter(|(_, flagged)| flagged.unwrap_or(false))
+        .map(|(category, _)| category)
+        .collect();
+    let category_scores = result
+        .category_scores
+        .into_iter()
+        .filter_map(|(category, score)| Some((category, score?)))
+        .collect();
 
-    if let Some(content) = message_content.as_str() {
-        return Ok(content.to_owned());
-    }
+    let mut response = ModerationResponse {
+        flagged: result.flagged.unwrap_or(!categories.is_empty()),
+        categories,
+        category_scores,
+        ..ModerationResponse::default()
+    };
+    ModerationSeverity::default().apply(&mut response);
+    response
+}
 
-    if let Some(items) = message_content.as_array() {
-        let combined = items
-            .iter()
-            .filter_map(|item| item.get("text").and_then(Value::as_str))
-            .collect::<Vec<_>>()
-            .join("\n");
-        if !combined.is_empty() {
-            return Ok(combined);
-        }
+/// Check that `raw` is an `http` or `https` URL without query or fragment, and return
+/// it without trailing slashes
+pub fn normalize_base_url(raw: &str) -> Result<String, MistralClientError> {
+    let invalid = |reason: String| MistralClientError::InvalidBaseUrl {
+        url: raw.to_owned(),
+        reason,
+    };
+    let url = reqwest::Url::parse(raw.trim()).map_err(|e| invalid(e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid(format!(
+            "scheme {} is not http or https",
+            url.scheme()
+        )));
     }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid(
+            "a query or fragment cannot prefix paths".to_owned(),
+        ));
+    }
+    Ok(url.as_str().trim_end_matches('/').to_owned())
+}
 
-    Err(MistralClientError::InvalidResponse(
-        "unsupported response content shape".to_owned(),
-    ))
+/// Join `path` to `base_url`, dropping a `/v1` the base URL already ends with
+fn join_url(base_url: &str, path: &str) -> String {
+    let mut path = path.trim_start_matches('/');
+    if base_url.ends_with("/v1") {
+        path = path.strip_prefix("v1/").unwrap_or(path);
+    }
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
 }
 
 #[derive(Debug, Error)]
@@ -529,4 +1131,119 @@ pub enum MistralClientError {
     ApiError { status: u16, message: String },
     #[error("mistral response contract invalid: {0}")]
     InvalidResponse(String),
+    #[error("invalid Mistral base URL {url}: {reason}")]
+    InvalidBaseUrl { url: String, reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn moderation_results_keep_raw_category_scores() {
+        let response = parse_moderation_result(
+            serde_json::from_value(json!({
+                "flagged": true,
+                "categories": { "law": true, "selfharm": false },
+                "category_scores": { "law": 0.81, "selfharm": 0.02 },
+            }))
+            .unwrap(),
+        );
+        assert_eq!(response.categories, ["law"]);
+        assert_eq!(response.category_scores.len(), 2);
+        assert_eq!(response.category_scores["law"], 0.81);
+        assert_eq!(response.category_severities["law"], 0.3);
+        assert_eq!(response.severity, 0.3);
+        assert_eq!(
+            response.flagged_categories()[0].score,
+            Some(0.81),
+            "{response:?}"
+        );
+    }
+
+    #[test]
+    fn base_urls_are_normalized() {
+        let cases = [
+            ("https://api.mistral.ai", "https://api.mistral.ai"),
+            ("https://api.mistral.ai/", "https://api.mistral.ai"),
+            (" https://api.mistral.ai ", "https://api.mistral.ai"),
+            ("https://api.mistral.ai/v1", "https://api.mistral.ai/v1"),
+            ("https://api.mistral.ai/v1/", "https://api.mistral.ai/v1"),
+            ("http://localhost:8080", "http://localhost:8080"),
+            (
+                "http://gateway.local/mistral/",
+                "http://gateway.local/mistral",
+            ),
+            ("HTTPS://API.MISTRAL.AI", "https://api.mistral.ai"),
+        ];
+        for (raw, normalized) in cases {
+            assert_eq!(normalize_base_url(raw).unwrap(), normalized, "{raw}");
+        }
+    }
+
+    #[test]
+    fn base_urls_that_cannot_prefix_a_path_are_rejected() {
+        for raw in [
+            "",
+            "api.mistral.ai",
+            "ftp://api.mistral.ai",
+            "file:///tmp/mistral",
+            "https://api.mistral.ai?key=1",
+            "https://api.mistral.ai/#v1",
+        ] {
+            let error = normalize_base_url(raw).expect_err(raw);
+            assert!(
+                matches!(error, MistralClientError::InvalidBaseUrl { .. }),
+                "{raw}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn joined_urls_never_repeat_the_version_segment() {
+        let cases = [
+            (
+                "https://api.mistral.ai",
+                "/v1/models",
+                "https://api.mistral.ai/v1/models",
+            ),
+            (
+                "https://api.mistral.ai/v1",
+                "/v1/models",
+                "https://api.mistral.ai/v1/models",
+            ),
+            (
+                "https://api.mistral.ai/v1",
+                "v1/models",
+                "https://api.mistral.ai/v1/models",
+            ),
+            (
+                "http://gw/mistral",
+                "/v1/embeddings",
+                "http://gw/mistral/v1/embeddings",
+            ),
+            (
+                "http://gw/mistral/v1",
+                "/v1/embeddings",
+                "http://gw/mistral/v1/embeddings",
+            ),
+            ("http://gw/v1", "/moderate", "http://gw/v1/moderate"),
+            ("http://gw/api", "moderate", "http://gw/api/moderate"),
+        ];
+        for (base, path, joined) in cases {
+            assert_eq!(join_url(base, path), joined, "{base} + {path}");
+        }
+    }
+
+    #[test]
+    fn construction_fails_on_an_invalid_base_url() {
+        assert!(HttpMistralClient::new("ftp://api.mistral.ai", "key").is_err());
+        let client = HttpMistralClient::new("https://api.mistral.ai/v1/", "key").unwrap();
+        assert_eq!(
+            client.url(&client.paths.chat),
+            "https://api.mistral.ai/v1/chat/completions"
+        );
+    }
 }
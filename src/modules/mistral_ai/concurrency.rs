@@ -0,0 +1,209 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::service::MistralServiceError;
+use crate::modules::diagnostics::service::record_queue_wait;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Caps on concurrent Mistral calls, shared by every request using one API key
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MistralConcurrencyLimits {
+    pub max_concurrent_chat: usize,
+    pub max_concurrent_moderation: usize,
+    pub max_concurrent_embeddings: usize,
+    /// Longest a call may wait for a free slot before failing
+    pub max_wait_ms: u64,
+}
+
+impl Default for MistralConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_chat: 16,
+            max_concurrent_moderation: 32,
+            max_concurrent_embeddings: 32,
+            max_wait_ms: 2_000,
+        }
+    }
+}
+
+impl MistralConcurrencyLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, limit) in [
+            ("chat", self.max_concurrent_chat),
+            ("moderation", self.max_concurrent_moderation),
+            ("embeddings", self.max_concurrent_embeddings),
+        ] {
+            if limit == 0 {
+                return Err(format!(
+                    "mistral {name} concurrency limit must be greater than zero"
+                ));
+            }
+        }
+        if self.max_wait_ms == 0 {
+            return Err("mistral concurrency max wait must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Mistral operations with their own concurrency limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitedOperation {
+    Chat,
+    Moderation,
+    Embeddings,
+}
+
+impl LimitedOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Moderation => "moderation",
+            Self::Embeddings => "embeddings",
+        }
+    }
+}
+
+/// One semaphore per limited operation
+#[derive(Clone)]
+pub(crate) struct ConcurrencyGovernor {
+    chat: Arc<OperationLimiter>,
+    moderation: Arc<OperationLimiter>,
+    embeddings: Arc<OperationLimiter>,
+    max_wait: Duration,
+}
+
+struct OperationLimiter {
+    operation: LimitedOperation,
+    semaphore: Arc<Semaphore>,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+/// Slot held for the duration of one client call
+pub(crate) struct CallPermit {
+    limiter: Arc<OperationLimiter>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for CallPermit {
+    fn drop(&mut self) {
+        let in_flight = self.limiter.in_flight.fetch_sub(1, Ordering::SeqCst) - 1;
+        get_metrics().set_mistral_in_flight(self.limiter.operation.as_str(), in_flight);
+    }
+}
+
+impl ConcurrencyGovernor {
+    pub(crate) fn new(limits: MistralConcurrencyLimits) -> Self {
+        let limiter = |operation, limit| {
+            Arc::new(OperationLimiter {
+                operation,
+                semaphore: Arc::new(Semaphore::new(limit)),
+                in_flight: AtomicUsize::new(0),
+                queued: AtomicUsize::new(0),
+            })
+        };
+        Self {
+            chat: limiter(LimitedOperation::Chat, limits.max_concurrent_chat),
+            moderation: limiter(
+                LimitedOperation::Moderation,
+                limits.max_concurrent_moderation,
+            ),
+            embeddings: limiter(
+                LimitedOperation::Embeddings,
+                limits.max_concurrent_embeddings,
+            ),
+            max_wait: Duration::from_millis(limits.max_wait_ms),
+        }
+    }
+
+    /// Wait up to the configured budget for a slot
+    pub(crate) async fn acquire(
+        &self,
+        operation: LimitedOperation,
+    ) -> Result<CallPermit, MistralServiceError> {
+        let limiter = match operation {
+            LimitedOperation::Chat => &self.chat,
+            LimitedOperation::Moderation => &self.moderation,
+            LimitedOperation::Embeddings => &self.embeddings,
+        };
+        let metrics = get_metrics();
+
+        let queued = limiter.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        metrics.set_mistral_queued(operation.as_str(), queued);
+        let waiting_since = Instant::now();
+        let acquired =
+            tokio::time::timeout(self.max_wait, limiter.semaphore.clone().acquire_owned()).await;
+        record_queue_wait(operation.as_str(), waiting_since.elapsed());
+        let queued = limiter.queued.fetch_sub(1, Ordering::SeqCst) - 1;
+        metrics.set_mistral_queued(operation.as_str(), queued);
+
+        match acquired {
+            // The semaphore is never closed
+            Ok(permit) => {
+                let permit = permit.expect("mistral concurrency semaphore closed");
+                let in_flight = limiter.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                metrics.set_mistral_in_flight(operation.as_str(), in_flight);
+                Ok(CallPermit {
+                    limiter: limiter.clone(),
+                    _permit: permit,
+                })
+            }
+            Err(_) => {
+                metrics.increment_mistral_concurrency_timeouts(operation.as_str());
+                Err(MistralServiceError::ConcurrencyLimitTimeout {
+                    operation,
+                    max_wait: self.max_wait,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_concurrent_chat: usize, max_wait_ms: u64) -> MistralConcurrencyLimits {
+        MistralConcurrencyLimits {
+            max_concurrent_chat,
+            max_wait_ms,
+            ..MistralConcurrencyLimits::default()
+        }
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        assert!(MistralConcurrencyLimits::default().validate().is_ok());
+        assert!(limits(0, 100).validate().is_err());
+        assert!(limits(1, 0).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn waits_past_the_budget_time_out() {
+        let governor = ConcurrencyGovernor::new(limits(1, 20));
+        let held = governor.acquire(LimitedOperation::Chat).await.unwrap();
+
+        let error = governor
+            .acquire(LimitedOperation::Chat)
+            .await
+            .err()
+            .expect("no free chat slot");
+        assert!(matches!(
+            error,
+            MistralServiceError::ConcurrencyLimitTimeout {
+                operation: LimitedOperation::Chat,
+                ..
+            }
+        ));
+        // Other operations have their own slots
+        assert!(governor.acquire(LimitedOperation::Moderation).await.is_ok());
+
+        drop(held);
+        assert!(governor.acquire(LimitedOperation::Chat).await.is_ok());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -6,16 +8,49 @@ pub struct ChatMessage {
     pub content: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub safe_prompt: bool,
+    /// Sampling temperature; the model default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Cap on generated tokens; the model default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Seed for the provider's sampler; random per call when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+}
+
+impl ChatCompletionRequest {
+    /// Pin temperature and seed when `sampling` is set; otherwise leave the request as is
+    pub fn with_sampling(mut self, sampling: Option<DeterministicSampling>) -> Self {
+        if let Some(sampling) = sampling {
+            self.temperature = Some(DeterministicSampling::TEMPERATURE);
+            self.random_seed = Some(sampling.random_seed);
+        }
+        self
+    }
+}
+
+/// Sampling pinned for reproducible output: temperature 0 and a fixed seed
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeterministicSampling {
+    pub random_seed: u64,
+}
+
+impl DeterministicSampling {
+    pub const TEMPERATURE: f32 = 0.0;
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct LanguageDetectionRequest {
     pub text: String,
+    /// Sampling of the chat call behind the detection, when the request is deterministic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<DeterministicSampling>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -28,6 +63,9 @@ pub struct LanguageDetectionResponse {
 pub struct TranslationRequest {
     pub text: String,
     pub target_language: String,
+    /// Sampling of the chat call behind the translation, when the request is deterministic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<DeterministicSampling>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -56,11 +94,99 @@ pub struct ModerationRequest {
     pub input: String,
 }
 
+/// Several inputs moderated in one call; results come back in input order
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ModerationBatchRequest {
+    pub model: Option<String>,
+    pub input: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub struct ModerationResponse {
     pub flagged: bool,
     pub categories: Vec<String>,
+    /// 0.0–1.0, combined from the flagged categories' weights by
+    /// [`ModerationSeverity`](super::severity::ModerationSeverity)
     pub severity: f32,
+    /// Raw per-category scores, when the provider reports them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub category_scores: BTreeMap<String, f32>,
+    /// Severity of each flagged category
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub category_severities: BTreeMap<String, f32>,
+}
+
+impl ModerationResponse {
+    /// Flagged categories in reported order, with their severities and raw scores
+    pub fn flagged_categories(&self) -> Vec<ModerationCategory> {
+        self.categories
+            .iter()
+            .map(|category| ModerationCategory {
+                category: category.clone(),
+                severity: self
+                    .category_severities
+                    .get(category)
+                    .copied()
+                    .unwrap_or_default(),
+                score: self.category_scores.get(category).copied(),
+            })
+            .collect()
+    }
+}
+
+/// A category moderation flagged and how severe it is
+///
+/// Reads back from a bare category name too, as written before severities were
+/// recorded; those get a severity of 0.0.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(from = "ModerationCategoryRepr")]
+pub struct ModerationCategory {
+    pub category: String,
+    /// Weight of the category in the severity table
+    pub severity: f32,
+    /// Provider's raw score for the category
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+impl PartialEq<&str> for ModerationCategory {
+    fn eq(&self, other: &&str) -> bool {
+        self.category == *other
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModerationCategoryRepr {
+    Name(String),
+    Detailed {
+        category: String,
+        #[serde(default)]
+        severity: f32,
+        #[serde(default)]
+        score: Option<f32>,
+    },
+}
+
+impl From<ModerationCategoryRepr> for ModerationCategory {
+    fn from(repr: ModerationCategoryRepr) -> Self {
+        match repr {
+            ModerationCategoryRepr::Name(category) => Self {
+                category,
+                severity: 0.0,
+                score: None,
+            },
+            ModerationCategoryRepr::Detailed {
+                category,
+                severity,
+                score,
+            } => Self {
+                category,
+                severity,
+                score,
+            },
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -83,7 +209,10 @@ pub struct ModelListResponse {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ModelValidationResponse {
     pub generation_model: ModelValidationStatus,
+    /// `None` when moderation is disabled, with `moderation` set to "not_configured"
     pub moderation_model: Option<ModelValidationStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation: Option<String>,
     pub embedding_model: ModelValidationStatus,
     pub overall_status: String,
 }
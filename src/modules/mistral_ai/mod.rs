@@ -1,4 +1,8 @@
 pub mod client;
+pub mod concurrency;
 pub mod dtos;
 pub mod handler;
+pub mod sampling;
 pub mod service;
+pub mod severity;
+pub mod wire;
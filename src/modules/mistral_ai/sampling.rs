@@ -0,0 +1,21 @@
+use std::future::Future;
+
+use super::dtos::DeterministicSampling;
+
+tokio::task_local! {
+    /// Sampling every chat call of the request running on this task is sent with
+    static SAMPLING: DeterministicSampling;
+}
+
+/// Run `future` with its chat calls pinned to `sampling`, or unchanged when `None`
+pub async fn scope<F: Future>(sampling: Option<DeterministicSampling>, future: F) -> F::Output {
+    match sampling {
+        Some(sampling) => SAMPLING.scope(sampling, future).await,
+        None => future.await,
+    }
+}
+
+/// Sampling of the request running on this task; `None` outside a deterministic request
+pub fn current() -> Option<DeterministicSampling> {
+    SAMPLING.try_with(|sampling| *sampling).ok()
+}
@@ -1,15 +1,25 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 
 use super::client::{MistralClient, MistralClientError};
+use super::concurrency::{ConcurrencyGovernor, LimitedOperation, MistralConcurrencyLimits};
 use super::dtos::{
     ChatCompletionRequest, ChatCompletionResponse, ChatMessage, EmbeddingRequest,
     EmbeddingResponse, LanguageDetectionRequest, LanguageDetectionResponse,
-    ModelValidationResponse, ModelValidationStatus, ModerationRequest, ModerationResponse,
-    TranslationRequest, TranslationResponse,
+    ModelValidationResponse, ModelValidationStatus, ModerationBatchRequest, ModerationRequest,
+    ModerationResponse, TranslationRequest, TranslationResponse,
 };
+use super::sampling;
+use super::severity::ModerationSeverity;
+
+/// Reported for moderation when no moderation model is configured
+pub const MODERATION_NOT_CONFIGURED: &str = "not_configured";
 
 #[derive(Clone)]
 pub struct MistralService {
@@ -17,6 +27,10 @@ pub struct MistralService {
     generation_model: String,
     moderation_model: Option<String>,
     embedding_model: String,
+    governor: ConcurrencyGovernor,
+    /// Set once the provider has refused array input to moderation
+    batch_moderation_rejected: Arc<AtomicBool>,
+    moderation_severity: Arc<ModerationSeverity>,
 }
 
 impl MistralService {
@@ -31,9 +45,26 @@ impl MistralService {
             generation_model: generation_model.into(),
             moderation_model,
             embedding_model: embedding_model.into(),
+            governor: ConcurrencyGovernor::new(MistralConcurrencyLimits::default()),
+            batch_moderation_rejected: Arc::default(),
+            moderation_severity: Arc::default(),
         }
     }
 
+    /// Replace the default caps on concurrent chat, moderation and embedding calls
+    ///
+    /// Clones made before this call keep sharing the previous limits.
+    pub fn with_concurrency_limits(mut self, limits: MistralConcurrencyLimits) -> Self {
+        self.governor = ConcurrencyGovernor::new(limits);
+        self
+    }
+
+    /// Score moderation results with `severity` instead of the default weight table
+    pub fn with_moderation_severity(mut self, severity: ModerationSeverity) -> Self {
+        self.moderation_severity = Arc::new(severity);
+        self
+    }
+
     pub async fn validate_generation_model(&self) -> Result<(), MistralServiceError> {
         info!("Validating generation model: {}", self.generation_model);
         let models = self.client.list_models().await?;
@@ -102,7 +133,121 @@ impl MistralService {
             model: self.moderation_model.clone(),
             input: input.into(),
         };
-        self.client.moderate(request).await.map_err(Into::into)
+        let mut response = {
+            let _permit = self.governor.acquire(LimitedOperation::Moderation).await?;
+            self.client.moderate(request).await?
+        };
+        self.moderation_severity.apply(&mut response);
+        Ok(response)
+    }
+
+    /// Moderate several inputs, in one call when the provider accepts arrays
+    ///
+    /// Responses are in input order. If the provider rejects array input (HTTP 400 or
+    /// 422), this and every later batch fall back to one call per input.
+    pub async fn moderate_batch(
+        &self,
+        inputs: Vec<String>,
+    ) -> Result<Vec<ModerationResponse>, MistralServiceError> {
+        self.moderate_batch_concurrently(inputs, 1).await
+    }
+
+    /// [`moderate_batch`](Self::moderate_batch), sending up to `max_concurrency` single
+    /// calls at once when the inputs cannot go in one call
+    ///
+    /// A batch the provider finds too large (HTTP 413) is also split into single calls,
+    /// but only for this request.
+    pub async fn moderate_batch_concurrently(
+        &self,
+        inputs: Vec<String>,
+        max_concurrency: usize,
+    ) -> Result<Vec<ModerationResponse>, MistralServiceError> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.batch_moderation_rejected.load(Ordering::Relaxed) {
+            debug!(
+                "Moderating {} inputs in one call with model: {:?}",
+                inputs.len(),
+                self.moderation_model
+            );
+            let request = ModerationBatchRequest {
+                model: self.moderation_model.clone(),
+                input: inputs.clone(),
+            };
+            let result = {
+                let _permit = self.governor.acquire(LimitedOperation::Moderation).await?;
+                self.client.moderate_batch(request).await
+            };
+            match result {
+                Ok(mut responses) if responses.len() == inputs.len() => {
+                    for response in &mut responses {
+                        self.moderation_severity.apply(response);
+                    }
+                    return Ok(responses);
+                }
+                Ok(responses) => {
+                    return Err(MistralClientError::InvalidResponse(format!(
+                        "moderation returned {} results for {} inputs",
+                        responses.len(),
+                        inputs.len()
+                    ))
+                    .into());
+                }
+                Err(MistralClientError::ApiError {
+                    status: status @ (400 | 422),
+                    message,
+                }) => {
+                    warn!(
+                        "Moderation provider rejected batch input (HTTP {}: {}); \
+                         falling back to one call per input",
+                        status, message
+                    );
+                    self.batch_moderation_rejected
+                        .store(true, Ordering::Relaxed);
+                }
+                Err(MistralClientError::ApiError {
+                    status: 413,
+                    message,
+                }) => {
+                    warn!(
+                        "Moderation provider rejected {} inputs as too large ({}); \
+                         moderating them one at a time",
+                        inputs.len(),
+                        message
+                    );
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        if max_concurrency <= 1 {
+            let mut responses = Vec::with_capacity(inputs.len());
+            for input in inputs {
+                responses.push(self.moderate_text(input).await?);
+            }
+            return Ok(responses);
+        }
+
+        let count = inputs.len();
+        let permits = Arc::new(Semaphore::new(max_concurrency));
+        let mut tasks = JoinSet::new();
+        for (index, input) in inputs.into_iter().enumerate() {
+            let service = self.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, service.moderate_text(input).await)
+            });
+        }
+        let mut responses = vec![ModerationResponse::default(); count];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, response) =
+                joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            // Returning early drops the set, which aborts the remaining calls
+            responses[index] = response?;
+        }
+        Ok(responses)
     }
 
     pub async fn generate_text(
@@ -110,15 +255,86 @@ impl MistralService {
         prompt: impl Into<String>,
         safe_prompt: bool,
     ) -> Result<ChatCompletionResponse, MistralServiceError> {
-        debug!("Generating text with model: {}", self.generation_model);
-        let request = ChatCompletionRequest {
+        self.generate_chat(
+            vec![ChatMessage {
+                role: "user".to_owned(),
+                content: prompt.into(),
+            }],
+            safe_prompt,
+        )
+        .await
+    }
+
+    /// Generate a reply to `messages`, sent exactly as given
+    pub async fn generate_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        safe_prompt: bool,
+    ) -> Result<ChatCompletionResponse, MistralServiceError> {
+        self.complete(self.generation_request(messages, safe_prompt))
+            .await
+    }
+
+    /// The request [`generate_chat`](Self::generate_chat) sends for `messages`, with the
+    /// sampling of the deterministic request running on this task
+    pub fn generation_request(
+        &self,
+        messages: Vec<ChatMessage>,
+        safe_prompt: bool,
+    ) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: self.generation_model.clone(),
+            messages,
+            safe_prompt,
+            temperature: None,
+            max_tokens: None,
+            random_seed: None,
+        }
+        .with_sampling(sampling::current())
+    }
+
+    /// Send a generation request as built, such as one reissued from the audit trail
+    pub async fn complete(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralServiceError> {
+        debug!("Generating text with model: {}", request.model);
+        self.chat(request).await
+    }
+
+    /// Generate a short, predictable answer: safe prompt on, the given temperature and a
+    /// token cap
+    pub async fn generate_constrained(
+        &self,
+        prompt: impl Into<String>,
+        temperature: f32,
+        max_tokens: u32,
+    ) -> Result<ChatCompletionResponse, MistralServiceError> {
+        debug!(
+            "Generating constrained text with model: {}",
+            self.generation_model
+        );
+        self.chat(ChatCompletionRequest {
             model: self.generation_model.clone(),
             messages: vec![ChatMessage {
                 role: "user".to_owned(),
                 content: prompt.into(),
             }],
-            safe_prompt,
-        };
+            safe_prompt: true,
+            temperature: Some(temperature),
+            max_tokens: Some(max_tokens),
+            random_seed: None,
+        })
+        .await
+    }
+
+    /// Send `request`, pinned to the sampling of a deterministic request on this task
+    async fn chat(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralServiceError> {
+        let request = request.with_sampling(sampling::current());
+        let _permit = self.governor.acquire(LimitedOperation::Chat).await?;
         self.client
             .chat_completion(request)
             .await
@@ -134,6 +350,7 @@ impl MistralService {
             model: self.embedding_model.clone(),
             input: text.into(),
         };
+        let _permit = self.governor.acquire(LimitedOperation::Embeddings).await?;
         self.client.embeddings(request).await.map_err(Into::into)
     }
 
@@ -142,7 +359,10 @@ impl MistralService {
         text: impl Into<String>,
     ) -> Result<LanguageDetectionResponse, MistralServiceError> {
         debug!("Detecting language of text");
-        let request = LanguageDetectionRequest { text: text.into() };
+        let request = LanguageDetectionRequest {
+            text: text.into(),
+            sampling: sampling::current(),
+        };
         self.client
             .detect_language(request)
             .await
@@ -159,6 +379,7 @@ impl MistralService {
         let request = TranslationRequest {
             text: text.into(),
             target_language: target_language.into(),
+            sampling: sampling::current(),
         };
         self.client
             .translate_text(request)
@@ -166,6 +387,19 @@ impl MistralService {
             .map_err(Into::into)
     }
 
+    /// Ids of every model the API currently lists
+    pub async fn list_models(&self) -> Result<Vec<String>, MistralServiceError> {
+        Ok(self.client.list_models().await?.models)
+    }
+
+    /// The generation, moderation and embedding models this service calls
+    pub fn configured_models(&self) -> Vec<&str> {
+        let mut models = vec![self.generation_model.as_str()];
+        models.extend(self.moderation_model.as_deref());
+        models.push(&self.embedding_model);
+        models
+    }
+
     pub async fn health_check(&self) -> Result<(), MistralServiceError> {
         info!("Performing Mistral API health check");
 
@@ -211,6 +445,9 @@ impl MistralService {
 
         ModelValidationResponse {
             generation_model: generation_status,
+            moderation: moderation_status
+                .is_none()
+                .then(|| MODERATION_NOT_CONFIGURED.to_owned()),
             moderation_model: moderation_status,
             embedding_model: embedding_status,
             overall_status,
@@ -264,4 +501,27 @@ pub enum MistralServiceError {
     Client(#[from] MistralClientError),
     #[error("configured generation model is unavailable: {0}")]
     UnknownModel(String),
+    #[error("no free {} slot within {}ms", operation.as_str(), max_wait.as_millis())]
+    ConcurrencyLimitTimeout {
+        operation: LimitedOperation,
+        max_wait: Duration,
+    },
+}
+
+impl MistralServiceError {
+    /// How long a caller should back off before retrying, for transient overload
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::ConcurrencyLimitTimeout { max_wait, .. } => Some(*max_wait),
+            _ => None,
+        }
+    }
+
+    /// The provider refused the input as too large (HTTP 413)
+    pub fn is_payload_too_large(&self) -> bool {
+        matches!(
+            self,
+            Self::Client(MistralClientError::ApiError { status: 413, .. })
+        )
+    }
 }
@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::dtos::ModerationResponse;
+
+/// Severity per flagged category under [`SeverityMode::Legacy`]: five flags make 1.0
+const LEGACY_CATEGORY_SEVERITY: f32 = 0.2;
+
+/// How the severities of flagged categories combine into one
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeverityMode {
+    /// The most severe flagged category
+    #[default]
+    Max,
+    /// Sum of the flagged categories' weights, capped at 1.0
+    WeightedSum,
+    /// Flagged category count divided by five, ignoring the weights
+    Legacy,
+}
+
+impl SeverityMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Max => "max",
+            Self::WeightedSum => "weighted_sum",
+            Self::Legacy => "legacy",
+        }
+    }
+}
+
+impl fmt::Display for SeverityMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SeverityMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "max" => Ok(Self::Max),
+            "weighted_sum" => Ok(Self::WeightedSum),
+            "legacy" => Ok(Self::Legacy),
+            other => Err(format!(
+                "unknown moderation severity mode '{other}' (expected max, weighted_sum or legacy)"
+            )),
+        }
+    }
+}
+
+/// Weight of each moderation category, loaded from JSON
+///
+/// ```json
+/// { "default_weight": 0.5, "weights": { "selfharm": 1.0, "law": 0.3 } }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SeverityWeights {
+    /// Weight of categories missing from `weights`
+    #[serde(default = "default_weight")]
+    pub default_weight: f32,
+    pub weights: BTreeMap<String, f32>,
+}
+
+fn default_weight() -> f32 {
+    0.5
+}
+
+impl Default for SeverityWeights {
+    /// Mistral's moderation categories, ordered by the harm a miss can do
+    fn default() -> Self {
+        let weights = [
+            ("selfharm", 1.0),
+            ("dangerous_and_criminal_content", 0.95),
+            ("violence_and_threats", 0.9),
+            ("sexual", 0.85),
+            ("hate_and_discrimination", 0.85),
+            ("pii", 0.6),
+            ("health", 0.4),
+            ("financial", 0.4),
+            ("law", 0.3),
+        ];
+        Self {
+            default_weight: default_weight(),
+            weights: weights
+                .into_iter()
+                .map(|(category, weight)| (category.to_owned(), weight))
+                .collect(),
+        }
+    }
+}
+
+impl SeverityWeights {
+    /// Read a weight table from a JSON file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let weights: Self = serde_json::from_str(&text)
+            .map_err(|e| format!("cannot parse {}: {e}", path.display()))?;
+        weights
+            .validate()
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(weights)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |weight: f32| (0.0..=1.0).contains(&weight);
+        if !in_range(self.default_weight) {
+            return Err(format!(
+                "default moderation severity weight {} is outside 0.0-1.0",
+                self.default_weight
+            ));
+        }
+        if let Some((category, weight)) = self.weights.iter().find(|(_, w)| !in_range(**w)) {
+            return Err(format!(
+                "moderation severity weight {weight} for '{category}' is outside 0.0-1.0"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn weight(&self, category: &str) -> f32 {
+        self.weights
+            .get(category)
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+/// Turns the categories moderation flagged into per-category and overall severities
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ModerationSeverity {
+    pub mode: SeverityMode,
+    pub weights: SeverityWeights,
+}
+
+impl ModerationSeverity {
+    /// The original `categories / 5` formula
+    pub fn legacy() -> Self {
+        Self {
+            mode: SeverityMode::Legacy,
+            ..Self::default()
+        }
+    }
+
+    pub fn category_severity(&self, category: &str) -> f32 {
+        match self.mode {
+            SeverityMode::Legacy => LEGACY_CATEGORY_SEVERITY,
+            SeverityMode::Max | SeverityMode::WeightedSum => self.weights.weight(category),
+        }
+    }
+
+    /// Fill in `severity` and `category_severities`; unflagged responses score 0.0
+    pub fn apply(&self, response: &mut ModerationResponse) {
+        response.category_severities = response
+            .categories
+            .iter()
+            .map(|category| (category.clone(), self.category_severity(category)))
+            .collect();
+        if !response.flagged {
+            response.severity = 0.0;
+            return;
+        }
+        let severities = response.category_severities.values().copied();
+        response.severity = match self.mode {
+            SeverityMode::Max => severities.fold(0.0, f32::max),
+            SeverityMode::WeightedSum | SeverityMode::Legacy => severities.sum::<f32>().min(1.0),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flagged(categories: &[&str]) -> ModerationResponse {
+        ModerationResponse {
+            flagged: true,
+            categories: categories.iter().map(|c| (*c).to_owned()).collect(),
+            ..ModerationResponse::default()
+        }
+    }
+
+    fn severity(model: &ModerationSeverity, categories: &[&str]) -> f32 {
+        let mut response = flagged(categories);
+        model.apply(&mut response);
+        response.severity
+    }
+
+    #[test]
+    fn max_mode_ranks_categories_by_weight() {
+        let model = ModerationSeverity::default();
+        assert_eq!(severity(&model, &["law"]), 0.3);
+        assert_eq!(severity(&model, &["violence_and_threats"]), 0.9);
+        assert_eq!(severity(&model, &["law", "selfharm", "pii"]), 1.0);
+        assert_eq!(severity(&model, &["unlisted_category"]), 0.5);
+        assert_eq!(severity(&model, &[]), 0.0);
+    }
+
+    #[test]
+    fn weighted_sum_adds_weights_up_to_one() {
+        let model = ModerationSeverity {
+            mode: SeverityMode::WeightedSum,
+            ..ModerationSeverity::default()
+        };
+        assert!((severity(&model, &["law", "health"]) - 0.7).abs() < 1e-6);
+        assert_eq!(severity(&model, &["selfharm", "violence_and_threats"]), 1.0);
+    }
+
+    #[test]
+    fn custom_weight_tables_replace_the_defaults() {
+        let weights: SeverityWeights =
+            serde_json::from_str(r#"{"default_weight": 0.1, "weights": {"law": 0.9}}"#).unwrap();
+        let model = ModerationSeverity {
+            mode: SeverityMode::Max,
+            weights,
+        };
+        assert_eq!(severity(&model, &["law"]), 0.9);
+        assert_eq!(severity(&model, &["selfharm"]), 0.1);
+
+        let mut response = flagged(&["law", "selfharm"]);
+        model.apply(&mut response);
+        assert_eq!(
+            response.category_severities,
+            BTreeMap::from([("law".to_owned(), 0.9), ("selfharm".to_owned(), 0.1)])
+        );
+    }
+
+    #[test]
+    fn legacy_mode_reproduces_the_category_count_formula() {
+        let model = ModerationSeverity::legacy();
+        for count in 0..8 {
+            let categories: Vec<String> = (0..count).map(|i| format!("category_{i}")).collect();
+            let names: Vec<&str> = categories.iter().map(String::as_str).collect();
+            let expected = (count as f32 / 5.0).min(1.0);
+            assert!(
+                (severity(&model, &names) - expected).abs() < 1e-6,
+                "{count}"
+            );
+        }
+        // Weights play no part
+        assert_eq!(severity(&model, &["law"]), severity(&model, &["selfharm"]));
+    }
+
+    #[test]
+    fn unflagged_responses_have_no_severity() {
+        let mut response = ModerationResponse {
+            flagged: false,
+            categories: vec!["law".to_owned()],
+            ..ModerationResponse::default()
+        };
+        ModerationSeverity::default().apply(&mut response);
+        assert_eq!(response.severity, 0.0);
+    }
+
+    #[test]
+    fn weights_outside_the_unit_range_are_rejected() {
+        assert!(SeverityWeights::default().validate().is_ok());
+        let mut weights = SeverityWeights::default();
+        weights.weights.insert("law".to_owned(), 1.5);
+        assert!(weights.validate().is_err());
+        weights.weights.clear();
+        weights.default_weight = -0.1;
+        assert!(weights.validate().is_err());
+        assert_eq!("Weighted_Sum".parse(), Ok(SeverityMode::WeightedSum));
+        assert!("sum".parse::<SeverityMode>().is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Response bodies of the Mistral API, as far as the client reads them
+//!
+//! Fields the client does not use are ignored, so additions upstream are harmless. A body
+//! that does not fit is reported with the endpoint, the path of the offending field and
+//! a shortened copy of the body in which long strings and arrays are cut, so generated
+//! text and prompts never reach the logs beyond a few characters.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::client::{MistralClientError, MistralEndpoint};
+use super::dtos::TokenUsage;
+
+/// Longest response snippet quoted in an error, in characters
+pub const SNIPPET_MAX_CHARS: usize = 400;
+/// Characters of each string value kept in a snippet
+pub const SNIPPET_STRING_CHARS: usize = 24;
+/// Items of each array kept in a snippet
+pub const SNIPPET_ARRAY_ITEMS: usize = 3;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionBody {
+    #[serde(default)]
+    pub model: Option<String>,
+    pub choices: Vec<ChatChoice>,
+    #[serde(default)]
+    pub usage: Option<UsageBody>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatChoice {
+    pub message: ChatChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatChoiceMessage {
+    /// `null` when the model answered with tool calls only
+    #[serde(default)]
+    pub content: Option<MessageContent>,
+}
+
+/// Message content: a plain string, or a list of typed parts
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// One part of a message; parts without text, such as images, are skipped
+#[derive(Debug, Deserialize)]
+pub struct ContentPart {
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// Token counts; usage missing any of them is ignored
+#[derive(Debug, Deserialize)]
+pub struct UsageBody {
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    #[serde(default)]
+    pub total_tokens: Option<u32>,
+}
+
+impl UsageBody {
+    pub fn token_usage(&self) -> Option<TokenUsage> {
+        Some(TokenUsage {
+            prompt_tokens: self.prompt_tokens?,
+            completion_tokens: self.completion_tokens?,
+            total_tokens: self.total_tokens?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationBody {
+    pub results: Vec<ModerationResultBody>,
+}
+
+/// Moderation of one input
+#[derive(Debug, Deserialize)]
+pub struct ModerationResultBody {
+    /// Left out by Mistral's own moderation endpoint, which reports categories only;
+    /// the input is then flagged when any category is
+    #[serde(default)]
+    pub flagged: Option<bool>,
+    /// `null` counts as not flagged
+    #[serde(default)]
+    pub categories: BTreeMap<String, Option<bool>>,
+    /// Categories scored `null` are left out
+    #[serde(default)]
+    pub category_scores: BTreeMap<String, Option<f32>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingsBody {
+    pub data: Vec<EmbeddingItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingItem {
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelListBody {
+    pub data: Vec<ModelItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelItem {
+    pub id: String,
+}
+
+impl ChatCompletionBody {
+    /// Text of the first choice, with the text parts of array content joined by newlines
+    pub fn output_text(&self, body: &str) -> Result<String, MistralClientError> {
+        let content = self
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.as_ref())
+            .ok_or_else(|| {
+                invalid_response(MistralEndpoint::Chat, "missing response content", body)
+            })?;
+        match content {
+            MessageContent::Text(text) => Ok(text.clone()),
+            MessageContent::Parts(parts) => {
+                let combined = parts
+                    .iter()
+                    .filter_map(|part| part.text.as_deref())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if combined.is_empty() {
+                    return Err(invalid_response(
+                        MistralEndpoint::Chat,
+                        "response content has no text parts",
+                        body,
+                    ));
+                }
+                Ok(combined)
+            }
+        }
+    }
+}
+
+/// Decode the response `body` of `endpoint`
+pub fn decode<T: DeserializeOwned>(
+    endpoint: MistralEndpoint,
+    body: &str,
+) -> Result<T, MistralClientError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|error| {
+        let path = error.path().to_string();
+        invalid_response(
+            endpoint,
+            &format!("unexpected shape at {path}: {}", error.into_inner()),
+            body,
+        )
+    })
+}
+
+/// [`MistralClientError::InvalidResponse`] naming the endpoint and quoting a snippet of
+/// `body`
+pub fn invalid_response(endpoint: MistralEndpoint, reason: &str, body: &str) -> MistralClientError {
+    MistralClientError::InvalidResponse(format!(
+        "{} response: {reason} (body: {})",
+        endpoint.as_str(),
+        snippet(body)
+    ))
+}
+
+/// A copy of `body` short enough to log, keeping its structure
+pub fn snippet(body: &str) -> String {
+    let shortened = match serde_json::from_str::<Value>(body) {
+        Ok(value) => shorten(value).to_string(),
+        Err(_) => format!(
+            "not JSON, {} bytes: {:?}",
+            body.len(),
+            cut(body, SNIPPET_STRING_CHARS)
+        ),
+    };
+    cut(&shortened, SNIPPET_MAX_CHARS)
+}
+
+fn shorten(value: Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(cut(&text, SNIPPET_STRING_CHARS)),
+        Value::Array(items) => {
+            let total = items.len();
+            let mut kept = items
+                .into_iter()
+                .take(SNIPPET_ARRAY_ITEMS)
+                .map(shorten)
+                .collect::<Vec<_>>();
+            if total > SNIPPET_ARRAY_ITEMS {
+                kept.push(Value::String(format!(
+                    "…{} more",
+                    total - SNIPPET_ARRAY_ITEMS
+                )));
+            }
+            Value::Array(kept)
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (cut(&key, SNIPPET_STRING_CHARS), shorten(value)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// The first `max` characters of `text`, with an ellipsis when it was longer
+fn cut(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_cut_long_strings_and_arrays() {
+        let secret = "the user's prompt ".repeat(20);
+        let body = serde_json::json!({
+            "choices": [{ "message": { "content": secret } }],
+            "data": [1, 2, 3, 4, 5],
+        })
+        .to_string();
+        let snippet = snippet(&body);
+        assert!(!snippet.contains(&secret[..40]), "{snippet}");
+        assert!(
+            snippet.contains("\"the user's prompt the us…\""),
+            "{snippet}"
+        );
+        assert!(snippet.contains("[1,2,3,\"…2 more\"]"), "{snippet}");
+
+        let long = format!("[{}]", vec!["\"x\""; 2000].join(","));
+        assert!(super::snippet(&long).chars().count() <= SNIPPET_MAX_CHARS + 1);
+        assert_eq!(
+            super::snippet("<html>Bad Gateway</html>"),
+            "not JSON, 24 bytes: \"<html>Bad Gateway</html>\""
+        );
+    }
+}
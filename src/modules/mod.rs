@@ -1,7 +1,29 @@
+pub mod admission;
+pub mod alerting;
+pub mod appeals;
 pub mod audit;
 pub mod bias_detection;
+pub mod caches;
+pub mod chaos;
+pub mod config_management;
+pub mod demo;
+pub mod dependencies;
+pub mod diagnostics;
 pub mod eu_law_compliance;
+pub mod evaluate;
+pub mod exemptions;
+pub mod http_cache;
+pub mod load_replay;
+pub mod maintenance;
 pub mod mistral_ai;
+pub mod model_watch;
+pub mod preprocessing;
 pub mod prompt_firewall;
+pub mod repeat_offender;
+pub mod request_fingerprint;
+pub mod sanitize_probing;
+pub mod self_test;
 pub mod semantic_detection;
+pub mod slo;
 pub mod telemetry;
+pub mod usage;
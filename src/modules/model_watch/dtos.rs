@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How often the upstream model list is checked and which model ids read as deprecated
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelWatchConfig {
+    /// Seconds between checks; 0 disables the watch (default: 3600)
+    pub interval_secs: u64,
+    /// Case-insensitive substrings of model ids that announce a deprecation
+    /// (default: `deprecated`, `legacy`)
+    pub deprecation_patterns: Vec<String>,
+    /// Post new alerts to the alert webhook (default: off)
+    pub notify: bool,
+}
+
+impl Default for ModelWatchConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 3_600,
+            deprecation_patterns: vec!["deprecated".to_owned(), "legacy".to_owned()],
+            notify: false,
+        }
+    }
+}
+
+impl ModelWatchConfig {
+    /// Whether `model` matches one of the deprecation patterns
+    pub fn is_deprecation(&self, model: &str) -> bool {
+        let model = model.to_lowercase();
+        self.deprecation_patterns
+            .iter()
+            .any(|pattern| model.contains(&pattern.to_lowercase()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelEventKind {
+    Appeared,
+    Disappeared,
+}
+
+/// A change of the model list seen by one check
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelEvent {
+    pub kind: ModelEventKind,
+    pub at: DateTime<Utc>,
+}
+
+/// When one model id appeared in and disappeared from the upstream list
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelHistory {
+    pub model: String,
+    /// Listed by the last successful check
+    pub present: bool,
+    /// Oldest first
+    pub events: Vec<ModelEvent>,
+}
+
+/// Body of `GET /api/models/history`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelHistoryResponse {
+    /// Ordered by model id
+    pub models: Vec<ModelHistory>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelAlertKind {
+    /// A model the server is configured with is no longer listed
+    ConfiguredModelMissing,
+    /// A model whose id matches a deprecation pattern appeared
+    DeprecationAnnounced,
+}
+
+impl ModelAlertKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ConfiguredModelMissing => "configured_model_missing",
+            Self::DeprecationAnnounced => "deprecation_announced",
+        }
+    }
+}
+
+/// A condition the model watch found, active until a later check no longer finds it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelWatchAlert {
+    pub kind: ModelAlertKind,
+    pub model: String,
+    pub detail: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+/// The model watch in `GET /ready` and the system summary
+///
+/// Alerts are warnings: they never fail readiness.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModelWatchStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    /// Last check whose listing succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Checks in a row that could not list the models
+    pub consecutive_failures: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub alerts: Vec<ModelWatchAlert>,
+}
@@ -0,0 +1,290 @@
+//! Periodic comparison of the upstream model list with the one seen before
+//!
+//! A pinned model that disappears, or a model id announcing a deprecation, is logged as
+//! a warning, counted in the `model_watch_alerts` gauge, shown in `GET /ready` and the
+//! system summary and, when configured, posted once to the alert webhook. Listing
+//! failures only delay the next check: they never raise or clear an alert.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use super::dtos::{
+    ModelAlertKind, ModelEvent, ModelEventKind, ModelHistory, ModelWatchAlert, ModelWatchConfig,
+    ModelWatchStatus,
+};
+use super::storage::{ModelHistoryError, ModelHistoryStore};
+use crate::modules::alerting::dtos::{Alert, AlertLevel};
+use crate::modules::alerting::service::WebhookNotifier;
+use crate::modules::mistral_ai::service::MistralService;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Longest wait between checks after failures, in intervals
+const MAX_BACKOFF_INTERVALS: u32 = 8;
+
+#[derive(Default)]
+struct WatchState {
+    status: ModelWatchStatus,
+    /// Active alerts by kind and model
+    alerts: BTreeMap<(ModelAlertKind, String), ModelWatchAlert>,
+}
+
+/// Lists the upstream models on a schedule and warns before a change breaks startup
+#[derive(Clone)]
+pub struct ModelWatch {
+    mistral: MistralService,
+    store: Arc<dyn ModelHistoryStore>,
+    config: ModelWatchConfig,
+    notifier: Option<WebhookNotifier>,
+    state: Arc<Mutex<WatchState>>,
+}
+
+impl ModelWatch {
+    pub fn new(
+        mistral: MistralService,
+        store: Arc<dyn ModelHistoryStore>,
+        config: ModelWatchConfig,
+    ) -> Self {
+        Self {
+            mistral,
+            store,
+            config,
+            notifier: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Post new alerts to `notifier`; `None` only logs them
+    pub fn with_notifier(mut self, notifier: Option<WebhookNotifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn config(&self) -> &ModelWatchConfig {
+        &self.config
+    }
+
+    pub fn status(&self) -> ModelWatchStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    /// Every model seen so far with its appearances and disappearances
+    pub fn history(&self) -> Result<Vec<ModelHistory>, ModelHistoryError> {
+        self.store.list()
+    }
+
+    /// Run [`check`](Self::check) now and then every `interval`
+    ///
+    /// Each listing goes through the client's own retries. A check that still fails
+    /// doubles the wait before the next one, up to eight intervals, so an outage is
+    /// neither hammered nor reported on every tick.
+    pub fn spawn(&self, interval: Duration) -> JoinHandle<()> {
+        let watch = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let failures = watch.check().await.consecutive_failures;
+                let factor = 2u32.saturating_pow(failures).min(MAX_BACKOFF_INTERVALS);
+                tokio::time::sleep(interval * factor).await;
+            }
+        })
+    }
+
+    /// List the models, record what changed since the last successful check and update
+    /// the alerts
+    ///
+    /// The first check only records the list: every model would otherwise count as new.
+    pub async fn check(&self) -> ModelWatchStatus {
+        let checked_at = Utc::now();
+        let listed = self.mistral.list_models().await;
+        let models = match listed {
+            Ok(models) => models.into_iter().collect::<BTreeSet<_>>(),
+            Err(e) => {
+                let mut state = self.state.lock().unwrap();
+                let status = &mut state.status;
+                status.last_checked_at = Some(checked_at);
+                status.consecutive_failures += 1;
+                status.last_error = Some(e.to_string());
+                // Once per outage; the alerts keep what the last listing showed
+                if status.consecutive_failures == 1 {
+                    warn!("Model watch could not list Mistral models: {}", e);
+                } else {
+                    debug!(
+                        "Model watch listing failed {} times in a row: {}",
+                        status.consecutive_failures, e
+                    );
+                }
+                return status.clone();
+            }
+        };
+
+        let appeared = self.record_changes(&models, checked_at);
+        let (status, raised) = {
+            let mut state = self.state.lock().unwrap();
+            let raised = self.update_alerts(&mut state, &models, &appeared, checked_at);
+            let status = &mut state.status;
+            status.last_checked_at = Some(checked_at);
+            status.last_success_at = Some(checked_at);
+            status.consecutive_failures = 0;
+            status.last_error = None;
+            get_metrics().set_model_watch_alerts(status.alerts.len());
+            (status.clone(), raised)
+        };
+
+        for alert in raised {
+            warn!(
+                kind = alert.kind.as_str(),
+                model = %alert.model,
+                "Model watch: {}",
+                alert.detail
+            );
+            if let Some(notifier) = &self.notifier
+                && let Err(e) = notifier.notify(&webhook_alert(&alert)).await
+            {
+                error!("Failed to post the model watch alert to the webhook: {}", e);
+            }
+        }
+        status
+    }
+
+    /// Diff `models` with the stored list and persist the changes; returns the models
+    /// that appeared, empty on the first check
+    fn record_changes(
+        &self,
+        models: &BTreeSet<String>,
+        checked_at: DateTime<Utc>,
+    ) -> BTreeSet<String> {
+        let known = match self.store.list() {
+            Ok(known) => known,
+            Err(e) => {
+                error!("Model watch could not read the model history: {}", e);
+                return BTreeSet::new();
+            }
+        };
+        let baseline = known.is_empty();
+        let mut known: BTreeMap<String, ModelHistory> = known
+            .into_iter()
+            .map(|history| (history.model.clone(), history))
+            .collect();
+
+        let mut appeared = BTreeSet::new();
+        let mut changed = Vec::new();
+        for model in models {
+            let history = known.remove(model).unwrap_or_else(|| ModelHistory {
+                model: model.clone(),
+                present: false,
+                events: Vec::new(),
+            });
+            if !history.present {
+                if !baseline {
+                    info!("Model {} appeared in the Mistral model list", model);
+                    appeared.insert(model.clone());
+                }
+                changed.push(with_event(history, ModelEventKind::Appeared, checked_at));
+            }
+        }
+        for history in known.into_values().filter(|history| history.present) {
+            info!(
+                "Model {} disappeared from the Mistral model list",
+                history.model
+            );
+            changed.push(with_event(history, ModelEventKind::Disappeared, checked_at));
+        }
+
+        for history in changed {
+            if let Err(e) = self.store.upsert(history) {
+                error!("Model watch could not record a model change: {}", e);
+            }
+        }
+        appeared
+    }
+
+    /// Replace the active alerts with what `models` shows; returns the alerts new to
+    /// this check
+    fn update_alerts(
+        &self,
+        state: &mut WatchState,
+        models: &BTreeSet<String>,
+        appeared: &BTreeSet<String>,
+        checked_at: DateTime<Utc>,
+    ) -> Vec<ModelWatchAlert> {
+        let mut active = BTreeMap::new();
+        for model in self.mistral.configured_models() {
+            if !models.contains(model) {
+                active.insert(
+                    (ModelAlertKind::ConfiguredModelMissing, model.to_owned()),
+                    format!("configured model {model} is no longer listed by Mistral"),
+                );
+            }
+        }
+        // An announced deprecation stays active while the model is listed
+        let announced = state
+            .alerts
+            .keys()
+            .filter(|(kind, _)| *kind == ModelAlertKind::DeprecationAnnounced)
+            .map(|(_, model)| model)
+            .chain(
+                appeared
+                    .iter()
+                    .filter(|model| self.config.is_deprecation(model)),
+            )
+            .filter(|model| models.contains(*model))
+            .cloned()
+            .collect::<Vec<_>>();
+        for model in announced {
+            let detail = format!("model {model} appeared with a deprecation name");
+            active.insert((ModelAlertKind::DeprecationAnnounced, model), detail);
+        }
+
+        let mut raised = Vec::new();
+        let alerts = active
+            .into_iter()
+            .map(|(key, detail)| {
+                let alert = match state.alerts.remove(&key) {
+                    Some(existing) => existing,
+                    None => {
+                        let alert = ModelWatchAlert {
+                            kind: key.0,
+                            model: key.1.clone(),
+                            detail,
+                            raised_at: checked_at,
+                        };
+                        raised.push(alert.clone());
+                        alert
+                    }
+                };
+                (key, alert)
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (_, cleared) in std::mem::take(&mut state.alerts) {
+            info!(
+                "Model watch alert cleared ({} {})",
+                cleared.kind.as_str(),
+                cleared.model
+            );
+        }
+        state.status.alerts = alerts.values().cloned().collect();
+        state.alerts = alerts;
+        raised
+    }
+}
+
+fn with_event(mut history: ModelHistory, kind: ModelEventKind, at: DateTime<Utc>) -> ModelHistory {
+    history.present = kind == ModelEventKind::Appeared;
+    history.events.push(ModelEvent { kind, at });
+    history
+}
+
+fn webhook_alert(alert: &ModelWatchAlert) -> Alert {
+    Alert {
+        alert: alert.kind.as_str().to_owned(),
+        level: AlertLevel::Warning,
+        summary: alert.detail.clone(),
+        details: json!({ "model": alert.model }),
+        raised_at: alert.raised_at,
+    }
+}
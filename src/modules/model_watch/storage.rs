@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::ModelHistory;
+
+#[cfg(feature = "sled-storage")]
+const MODEL_HISTORY_TREE: &str = "model_history";
+
+/// Appearances and disappearances of upstream models, one entry per model id
+///
+/// The entries marked present are the model list the next check is compared with, so
+/// a restart does not report every model as new.
+pub trait ModelHistoryStore: Send + Sync {
+    /// Insert a model's history, replacing any kept for the same id
+    fn upsert(&self, history: ModelHistory) -> Result<(), ModelHistoryError>;
+    /// Every model ever seen, ordered by id
+    fn list(&self) -> Result<Vec<ModelHistory>, ModelHistoryError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryModelHistory {
+    inner: Arc<Mutex<BTreeMap<String, ModelHistory>>>,
+}
+
+impl InMemoryModelHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ModelHistoryStore for InMemoryModelHistory {
+    fn upsert(&self, history: ModelHistory) -> Result<(), ModelHistoryError> {
+        self.inner
+            .lock()
+            .map_err(|_| ModelHistoryError::LockPoisoned)?
+            .insert(history.model.clone(), history);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<ModelHistory>, ModelHistoryError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| ModelHistoryError::LockPoisoned)?
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+/// Model history kept in its own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledModelHistory {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledModelHistory {
+    pub fn new(db: &Db) -> Result<Self, ModelHistoryError> {
+        let tree = db
+            .open_tree(MODEL_HISTORY_TREE)
+            .map_err(|e| ModelHistoryError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl ModelHistoryStore for SledModelHistory {
+    fn upsert(&self, history: ModelHistory) -> Result<(), ModelHistoryError> {
+        let serialized = serde_json::to_vec(&history)
+            .map_err(|e| ModelHistoryError::SerializationError(e.to_string()))?;
+        self.tree
+            .insert(history.model.as_bytes(), serialized)
+            .map_err(|e| ModelHistoryError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| ModelHistoryError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<ModelHistory>, ModelHistoryError> {
+        self.tree
+            .iter()
+            .values()
+            .map(|entry| {
+                let data = entry.map_err(|e| ModelHistoryError::DatabaseError(e.to_string()))?;
+                serde_json::from_slice(&data)
+                    .map_err(|e| ModelHistoryError::SerializationError(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ModelHistoryError {
+    #[error("model history lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// A named rewrite applied to prompts before the firewall sees them
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptTransform {
+    /// Decode named and numeric HTML character references (`&lt;`, `&#60;`, `&#x3c;`)
+    HtmlEntityDecode,
+    /// Replace `data:` URIs with a short `[data:<media type>]` marker
+    StripDataUris,
+    /// Collapse runs of spaces and blank lines, and unify line endings
+    NormalizeWhitespace,
+    /// Remove soft hyphens (U+00AD)
+    StripSoftHyphens,
+    /// Remove bold, strikethrough, heading and blockquote markers and unwrap links
+    StripMarkdown,
+}
+
+impl PromptTransform {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::HtmlEntityDecode => "html_entity_decode",
+            Self::StripDataUris => "strip_data_uris",
+            Self::NormalizeWhitespace => "normalize_whitespace",
+            Self::StripSoftHyphens => "strip_soft_hyphens",
+            Self::StripMarkdown => "strip_markdown",
+        }
+    }
+}
+
+impl std::str::FromStr for PromptTransform {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "html_entity_decode" => Ok(Self::HtmlEntityDecode),
+            "strip_data_uris" => Ok(Self::StripDataUris),
+            "normalize_whitespace" => Ok(Self::NormalizeWhitespace),
+            "strip_soft_hyphens" => Ok(Self::StripSoftHyphens),
+            "strip_markdown" => Ok(Self::StripMarkdown),
+            other => Err(format!(
+                "unknown prompt preprocessor '{other}' (expected html_entity_decode, \
+                 strip_data_uris, normalize_whitespace, strip_soft_hyphens or strip_markdown)"
+            )),
+        }
+    }
+}
+
+/// One configured transform and whether it changed the prompt
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppliedTransform {
+    pub transform: PromptTransform,
+    pub changed: bool,
+}
+
+/// Prompt after every configured transform has run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessedPrompt {
+    pub text: String,
+    /// In configured order
+    pub applied: Vec<AppliedTransform>,
+}
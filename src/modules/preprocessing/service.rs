@@ -0,0 +1,109 @@
+use super::dtos::{AppliedTransform, PreprocessedPrompt, PromptTransform};
+use super::transforms;
+
+/// Ordered list of transforms run on every prompt before the firewall
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PromptPreprocessor {
+    transforms: Vec<PromptTransform>,
+}
+
+impl PromptPreprocessor {
+    pub fn new(transforms: Vec<PromptTransform>) -> Self {
+        Self { transforms }
+    }
+
+    pub fn transforms(&self) -> &[PromptTransform] {
+        &self.transforms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Run the transforms in order, each on the previous one's output
+    pub fn apply(&self, prompt: &str) -> PreprocessedPrompt {
+        let mut text = prompt.to_owned();
+        let mut applied = Vec::with_capacity(self.transforms.len());
+        for &transform in &self.transforms {
+            let output = match transform {
+                PromptTransform::HtmlEntityDecode => transforms::html_entity_decode(&text),
+                PromptTransform::StripDataUris => transforms::strip_data_uris(&text),
+                PromptTransform::NormalizeWhitespace => transforms::normalize_whitespace(&text),
+                PromptTransform::StripSoftHyphens => transforms::strip_soft_hyphens(&text),
+                PromptTransform::StripMarkdown => transforms::strip_markdown(&text),
+            };
+            applied.push(AppliedTransform {
+                transform,
+                changed: output != text,
+            });
+            text = output;
+        }
+        PreprocessedPrompt { text, applied }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_run_in_configured_order() {
+        let prompt = "Hi&nbsp;&nbsp;there";
+        let decode_first = PromptPreprocessor::new(vec![
+            PromptTransform::HtmlEntityDecode,
+            PromptTransform::NormalizeWhitespace,
+        ]);
+        let normalize_first = PromptPreprocessor::new(vec![
+            PromptTransform::NormalizeWhitespace,
+            PromptTransform::HtmlEntityDecode,
+        ]);
+
+        assert_eq!(decode_first.apply(prompt).text, "Hi there");
+        assert_eq!(normalize_first.apply(prompt).text, "Hi\u{a0}\u{a0}there");
+    }
+
+    #[test]
+    fn records_which_transforms_changed_the_prompt() {
+        let preprocessor = PromptPreprocessor::new(vec![
+            PromptTransform::StripSoftHyphens,
+            PromptTransform::HtmlEntityDecode,
+        ]);
+        let result = preprocessor.apply("a &amp; b");
+
+        assert_eq!(result.text, "a & b");
+        assert_eq!(
+            result.applied,
+            vec![
+                AppliedTransform {
+                    transform: PromptTransform::StripSoftHyphens,
+                    changed: false,
+                },
+                AppliedTransform {
+                    transform: PromptTransform::HtmlEntityDecode,
+                    changed: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_pipeline_passes_the_prompt_through() {
+        let result = PromptPreprocessor::default().apply("  &lt;b&gt;  ");
+        assert_eq!(result.text, "  &lt;b&gt;  ");
+        assert!(result.applied.is_empty());
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for transform in [
+            PromptTransform::HtmlEntityDecode,
+            PromptTransform::StripDataUris,
+            PromptTransform::NormalizeWhitespace,
+            PromptTransform::StripSoftHyphens,
+            PromptTransform::StripMarkdown,
+        ] {
+            assert_eq!(transform.as_str().parse(), Ok(transform));
+        }
+        assert!("rot13".parse::<PromptTransform>().is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Pure text rewrites behind each `PromptTransform`
+
+use std::fmt::Write;
+
+/// Named references worth decoding in chat prompts; anything else is left as written
+const NAMED_ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("AMP", '&'),
+    ("lt", '<'),
+    ("LT", '<'),
+    ("gt", '>'),
+    ("GT", '>'),
+    ("quot", '"'),
+    ("QUOT", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("shy", '\u{ad}'),
+    ("sol", '/'),
+    ("bsol", '\\'),
+    ("colon", ':'),
+    ("semi", ';'),
+    ("equals", '='),
+    ("grave", '`'),
+    ("lpar", '('),
+    ("rpar", ')'),
+    ("lsqb", '['),
+    ("rsqb", ']'),
+    ("lbrace", '{'),
+    ("rbrace", '}'),
+    ("Tab", '\t'),
+    ("NewLine", '\n'),
+    ("ndash", '\u{2013}'),
+    ("mdash", '\u{2014}'),
+    ("hellip", '\u{2026}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("ldquo", '\u{201c}'),
+    ("rdquo", '\u{201d}'),
+];
+
+/// Longest `data:` header (media type and parameters) recognised
+const MAX_DATA_URI_HEADER: usize = 128;
+
+/// Decode HTML character references in a single pass
+///
+/// Numeric references may omit the trailing semicolon, as browsers accept; named ones may
+/// not. `&amp;lt;` decodes to `&lt;`, not `<`, matching how the text would render.
+pub fn html_entity_decode(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let reference = &rest[start + 1..];
+        match decode_reference(reference) {
+            Some((decoded, consumed)) => {
+                output.push(decoded);
+                rest = &reference[consumed..];
+            }
+            None => {
+                output.push('&');
+                rest = reference;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Decode the reference following an `&`, returning the character and the bytes consumed
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    if let Some(numeric) = text.strip_prefix('#') {
+        let (digits, radix, prefix_len) = match numeric.strip_prefix(['x', 'X']) {
+            Some(hex) => (hex, 16, 2),
+            None => (numeric, 10, 1),
+        };
+        let length = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        if length == 0 {
+            return None;
+        }
+        let code = u32::from_str_radix(&digits[..length], radix).ok()?;
+        let decoded = char::from_u32(code).filter(|c| *c != '\0')?;
+        let terminated = digits[length..].starts_with(';');
+        return Some((decoded, prefix_len + length + usize::from(terminated)));
+    }
+
+    let length = text
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(text.len());
+    if !text[length..].starts_with(';') {
+        return None;
+    }
+    let name = &text[..length];
+    NAMED_ENTITIES
+        .iter()
+        .find(|(entity, _)| *entity == name)
+        .map(|(_, decoded)| (*decoded, length + 1))
+}
+
+/// Replace each `data:` URI with `[data:<media type>]`, dropping the payload
+pub fn strip_data_uris(text: &str) -> String {
+    // ASCII lowercasing keeps byte offsets identical to `text`
+    let lowered = text.to_ascii_lowercase();
+    let mut output = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some(found) = lowered[search..].find("data:") {
+        let start = search + found;
+        let header_start = start + "data:".len();
+        let starts_word = !text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        match parse_data_uri(&text[header_start..]).filter(|_| starts_word) {
+            Some((media_type, consumed)) => {
+                output.push_str(&text[copied..start]);
+                let _ = write!(output, "[data:{media_type}]");
+                copied = header_start + consumed;
+                search = copied;
+            }
+            None => search = header_start,
+        }
+    }
+    output.push_str(&text[copied..]);
+    output
+}
+
+/// Parse the part of a data URI after `data:`, returning its media type and length
+fn parse_data_uri(text: &str) -> Option<(&str, usize)> {
+    let header_len = text.find(',')?;
+    let header = &text[..header_len];
+    if header_len > MAX_DATA_URI_HEADER
+        || !header
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/;=.+-_".contains(c))
+    {
+        return None;
+    }
+    let media_type = header.split(';').next().unwrap_or_default();
+    let media_type = match media_type.split_once('/') {
+        Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty() => media_type,
+        // RFC 2397 default
+        None if media_type.is_empty() => "text/plain",
+        _ => return None,
+    };
+    let payload = &text[header_len + 1..];
+    let payload_len = payload
+        .find(|c: char| c.is_whitespace() || "()[]<>\"'".contains(c))
+        .unwrap_or(payload.len());
+    Some((media_type, header_len + 1 + payload_len))
+}
+
+/// Unify line endings, collapse whitespace runs within lines to one space, trim every
+/// line and keep at most one blank line between paragraphs
+pub fn normalize_whitespace(text: &str) -> String {
+    let unified = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<String> = Vec::new();
+    for line in unified.split('\n') {
+        let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let after_blank = lines.last().is_none_or(String::is_empty);
+        if collapsed.is_empty() && after_blank {
+            continue;
+        }
+        lines.push(collapsed);
+    }
+    if lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Remove soft hyphens, which render invisibly but split words for matching
+pub fn strip_soft_hyphens(text: &str) -> String {
+    text.replace('\u{ad}', "")
+}
+
+/// Remove bold and strikethrough markers, heading and blockquote prefixes, and reduce
+/// links and images to their text
+///
+/// Code fences and inline code are left untouched so the firewall can still tell quoted
+/// material apart. Single `*` and `_` are kept since they are too often literal.
+pub fn strip_markdown(text: &str) -> String {
+    let mut in_fence = false;
+    let mut lines = Vec::new();
+    for line in text.split('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            lines.push(line.to_owned());
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_owned());
+            continue;
+        }
+        let body = strip_line_prefixes(line);
+        // Odd segments sit between backticks
+        let stripped = body
+            .split('`')
+            .enumerate()
+            .map(|(index, segment)| {
+                if index % 2 == 1 {
+                    segment.to_owned()
+                } else {
+                    unwrap_links(segment)
+                        .replace("**", "")
+                        .replace("__", "")
+                        .replace("~~", "")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("`");
+        lines.push(stripped);
+    }
+    lines.join("\n")
+}
+
+/// Drop leading blockquote markers and an ATX heading marker
+fn strip_line_prefixes(line: &str) -> &str {
+    let mut rest = line.trim_start();
+    while let Some(quoted) = rest.strip_prefix('>') {
+        rest = quoted.trim_start();
+    }
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes) {
+        let after = &rest[hashes..];
+        if after.is_empty() || after.starts_with(char::is_whitespace) {
+            return after.trim_start();
+        }
+    }
+    if rest.len() == line.trim_start().len() {
+        line
+    } else {
+        rest
+    }
+}
+
+/// Replace `[text](target)` and `![text](target)` with `text`
+fn unwrap_links(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let label_start = open + 1;
+        let link = rest[label_start..].find(']').and_then(|close| {
+            let label_end = label_start + close;
+            let target = rest[label_end + 1..].strip_prefix('(')?;
+            let target_len = target.find(')')?;
+            Some((label_end, label_end + 2 + target_len + 1))
+        });
+        match link {
+            Some((label_end, end)) => {
+                let before = &rest[..open];
+                output.push_str(before.strip_suffix('!').unwrap_or(before));
+                output.push_str(&rest[label_start..label_end]);
+                rest = &rest[end..];
+            }
+            None => {
+                output.push_str(&rest[..label_start]);
+                rest = &rest[label_start..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_named_and_numeric_references() {
+        assert_eq!(
+            html_entity_decode("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "<script>alert(1)</script>"
+        );
+        assert_eq!(html_entity_decode("&#60;b&#x3E; &#X3c;i&#62"), "<b> <i>");
+        assert_eq!(
+            html_entity_decode("Tom &amp; Jerry&apos;s"),
+            "Tom & Jerry's"
+        );
+        assert_eq!(html_entity_decode("&#0000060;"), "<");
+    }
+
+    #[test]
+    fn leaves_unknown_and_malformed_references_alone() {
+        assert_eq!(html_entity_decode("AT&T & co"), "AT&T & co");
+        assert_eq!(
+            html_entity_decode("&bogus; &lt &#; &#xZZ;"),
+            "&bogus; &lt &#; &#xZZ;"
+        );
+        assert_eq!(
+            html_entity_decode("&#0; &#99999999999;"),
+            "&#0; &#99999999999;"
+        );
+        assert_eq!(html_entity_decode("trailing &"), "trailing &");
+    }
+
+    #[test]
+    fn decodes_one_level_only() {
+        assert_eq!(html_entity_decode("&amp;lt;b&amp;gt;"), "&lt;b&gt;");
+    }
+
+    #[test]
+    fn replaces_data_uris_with_their_media_type() {
+        let prompt = "Chart: ![q3](data:image/png;base64,iVBORw0KGgo=) and \
+                      data:text/plain;charset=utf-8,hello%20there done";
+        assert_eq!(
+            strip_data_uris(prompt),
+            "Chart: ![q3]([data:image/png]) and [data:text/plain] done"
+        );
+        assert_eq!(strip_data_uris("DATA:,raw"), "[data:text/plain]");
+    }
+
+    #[test]
+    fn keeps_text_that_only_looks_like_a_data_uri() {
+        for prompt in [
+            "metadata:image/png,x",
+            "data: the numbers, please",
+            "data:image,x",
+            "see data:image/png without payload",
+        ] {
+            assert_eq!(strip_data_uris(prompt), prompt);
+        }
+    }
+
+    #[test]
+    fn collapses_spaces_and_blank_lines() {
+        assert_eq!(
+            normalize_whitespace("\r\n  Hello \t\u{a0} world  \r\n\r\n\n\nBye\u{2003}now \n\n"),
+            "Hello world\n\nBye now"
+        );
+        assert_eq!(normalize_whitespace(" \n \t "), "");
+        assert_eq!(normalize_whitespace("one\ntwo"), "one\ntwo");
+    }
+
+    #[test]
+    fn removes_soft_hyphens() {
+        assert_eq!(
+            strip_soft_hyphens("ig\u{ad}nore pre\u{ad}vious"),
+            "ignore previous"
+        );
+    }
+
+    #[test]
+    fn strips_emphasis_headings_and_quotes() {
+        assert_eq!(
+            strip_markdown("## **Ignore** previous ~~rules~~\n> > __now__"),
+            "Ignore previous rules\nnow"
+        );
+        assert_eq!(strip_markdown("#hashtag and 2*3*4"), "#hashtag and 2*3*4");
+    }
+
+    #[test]
+    fn unwraps_links_and_images() {
+        assert_eq!(
+            strip_markdown("See [the docs](https://example.com) or ![logo](logo.png)."),
+            "See the docs or logo."
+        );
+        assert_eq!(strip_markdown("array[0] (first)"), "array[0] (first)");
+    }
+
+    #[test]
+    fn leaves_code_untouched() {
+        let prompt = "Use `**kwargs` here\n```\n# comment **bold**\n```\n**done**";
+        assert_eq!(
+            strip_markdown(prompt),
+            "Use `**kwargs` here\n```\n# comment **bold**\n```\ndone"
+        );
+    }
+}
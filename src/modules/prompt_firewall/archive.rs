@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use chrono::Utc;
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::rules::FirewallRulesConfig;
+
+#[cfg(feature = "sled-storage")]
+const RULES_ARCHIVE_TREE: &str = "firewall_rule_versions";
+
+/// Recently active firewall rule sets, looked up by fingerprint to replay old decisions
+pub trait FirewallRulesArchive: Send + Sync {
+    /// Store a rule set and drop the oldest versions beyond `limit`
+    ///
+    /// Recording a fingerprint that is already archived makes it the newest version.
+    fn record(
+        &self,
+        fingerprint: &str,
+        config: &FirewallRulesConfig,
+        limit: usize,
+    ) -> Result<(), RulesArchiveError>;
+    fn get(&self, fingerprint: &str) -> Result<Option<FirewallRulesConfig>, RulesArchiveError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryRulesArchive {
+    inner: Arc<Mutex<Vec<(String, FirewallRulesConfig)>>>,
+}
+
+impl InMemoryRulesArchive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FirewallRulesArchive for InMemoryRulesArchive {
+    fn record(
+        &self,
+        fingerprint: &str,
+        config: &FirewallRulesConfig,
+        limit: usize,
+    ) -> Result<(), RulesArchiveError> {
+        let mut guard = self
+            .inner
+            .lock()
+            .map_err(|_| RulesArchiveError::LockPoisoned)?;
+        guard.retain(|(archived, _)| archived != fingerprint);
+        guard.push((fingerprint.to_owned(), config.clone()));
+        let excess = guard.len().saturating_sub(limit);
+        guard.drain(..excess);
+        Ok(())
+    }
+
+    fn get(&self, fingerprint: &str) -> Result<Option<FirewallRulesConfig>, RulesArchiveError> {
+        let guard = self
+            .inner
+            .lock()
+            .map_err(|_| RulesArchiveError::LockPoisoned)?;
+        Ok(guard
+            .iter()
+            .find(|(archived, _)| archived == fingerprint)
+            .map(|(_, config)| config.clone()))
+    }
+}
+
+/// Rule set versions kept in their own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledRulesArchive {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledRulesArchive {
+    pub fn new(db: &Db) -> Result<Self, RulesArchiveError> {
+        let tree = db
+            .open_tree(RULES_ARCHIVE_TREE)
+            .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+
+    fn find_key(&self, fingerprint: &str) -> Result<Option<sled::IVec>, RulesArchiveError> {
+        for entry in self.tree.iter().keys() {
+            let key = entry.map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+            if key.ends_with(fingerprint.as_bytes()) {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl FirewallRulesArchive for SledRulesArchive {
+    fn record(
+        &self,
+        fingerprint: &str,
+        config: &FirewallRulesConfig,
+        limit: usize,
+    ) -> Result<(), RulesArchiveError> {
+        let serialized = serde_json::to_vec(config)
+            .map_err(|e| RulesArchiveError::SerializationError(e.to_string()))?;
+        if let Some(key) = self.find_key(fingerprint)? {
+            self.tree
+                .remove(key)
+                .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+        }
+
+        // Timestamp-prefixed keys keep the tree in the order versions became active
+        let key = format!(
+            "{:020}_{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            fingerprint
+        );
+        self.tree
+            .insert(key, serialized)
+            .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+
+        let excess = self.tree.len().saturating_sub(limit);
+        for entry in self.tree.iter().keys().take(excess).collect::<Vec<_>>() {
+            let key = entry.map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+            self.tree
+                .remove(key)
+                .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+        }
+
+        self.tree
+            .flush()
+            .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get(&self, fingerprint: &str) -> Result<Option<FirewallRulesConfig>, RulesArchiveError> {
+        let Some(key) = self.find_key(fingerprint)? else {
+            return Ok(None);
+        };
+        let Some(data) = self
+            .tree
+            .get(key)
+            .map_err(|e| RulesArchiveError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| RulesArchiveError::SerializationError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RulesArchiveError {
+    #[error("rules archive lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
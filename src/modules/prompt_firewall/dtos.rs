@@ -1,31 +1,116 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub use crate::firewall_core::dtos::{
+    ExtractedUrl, FirewallAction, FirewallSeverity, InputTruncation, LengthOverflowPolicy,
+    MatchedBlockRule, PromptFirewallResult, SanitizationEdit, UnfurlStatus, UrlAction, UrlUnfurl,
+};
+
+use super::rules::{
+    AssertionFailure, ControlCharacterConfig, FuzzyMatchingConfig, QuotedMentionConfig, RuleEntry,
+    SanitizeLimitsConfig,
+};
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PromptFirewallRequest {
     pub prompt: String,
     pub correlation_id: Option<String>,
+    /// Language the caller already detected for `prompt`; when set, the service does no
+    /// detection or translation of its own and evaluates `pre_translated_text`, if any
+    ///
+    /// Set by the workflow after its own detection. A client able to send it could claim
+    /// English and keep a foreign-language injection away from the rules.
+    #[serde(skip)]
+    pub detected_language: Option<String>,
+    /// English rendering of `prompt` made by the caller
+    #[serde(skip)]
+    pub pre_translated_text: Option<String>,
+}
+
+/// A configured rule together with whether it is still enforced
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FirewallRuleStatus {
+    #[serde(flatten)]
+    pub rule: RuleEntry,
+    pub expired: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FirewallRulesResponse {
+    pub block_rules: Vec<FirewallRuleStatus>,
+    pub sanitize_patterns: Vec<FirewallRuleStatus>,
+    pub fuzzy_matching: FuzzyMatchingConfig,
+    pub quoted_mentions: QuotedMentionConfig,
+    pub control_characters: ControlCharacterConfig,
+    pub sanitize_limits: SanitizeLimitsConfig,
+    /// Active rules that lapse within the next seven days
+    pub expiring_soon: usize,
+    /// Rule counts of the base rules, then of each pack in load order
+    #[serde(default)]
+    pub packs: Vec<RulePackSummary>,
 }
 
+/// How many rules the base rules file or one rule pack contributes
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
-pub enum FirewallAction {
-    Allow,
-    Sanitize,
-    Block,
+pub struct RulePackSummary {
+    /// `None` for the base rules
+    pub namespace: Option<String>,
+    /// Whether the rules match; a disabled pack's rules are listed but never match
+    pub enabled: bool,
+    pub block_rules: usize,
+    pub sanitize_patterns: usize,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
-pub enum FirewallSeverity {
-    Low,
-    Medium,
-    High,
-    Critical,
+/// Outcome of running the rule assertions against the active rules
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RuleAssertionReport {
+    /// Content hash of the rule set the assertions ran against
+    pub rules_fingerprint: String,
+    pub total: usize,
+    pub passed: usize,
+    pub failures: Vec<AssertionFailure>,
+}
+
+/// Later stage that blocked a prompt the firewall allowed
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallMissSource {
+    Semantic,
+    InputModeration,
+}
+
+impl FirewallMissSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Semantic => "semantic",
+            Self::InputModeration => "input_moderation",
+        }
+    }
+}
+
+/// A prompt the firewall allowed and a later stage blocked; a candidate for a new rule
+///
+/// Holds a hash of the prompt, never its text.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FirewallMiss {
+    pub correlation_id: String,
+    pub caught_by: FirewallMissSource,
+    /// Category of the nearest attack template, or the most severe moderation category
+    pub category: Option<String>,
+    /// `v1:` fingerprint of the prompt as received
+    pub prompt_hash: String,
+    pub recorded_at: DateTime<Utc>,
 }
 
+/// Returned by `GET /api/stats/firewall-misses`
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
-pub struct PromptFirewallResult {
-    pub action: FirewallAction,
-    pub severity: FirewallSeverity,
-    pub sanitized_prompt: String,
-    pub reasons: Vec<String>,
-    pub matched_rules: Vec<String>,
+pub struct FirewallMissesResponse {
+    /// Misses since startup, by the stage that caught them
+    pub misses_total: BTreeMap<FirewallMissSource, u64>,
+    /// Firewall blocks of prompts the semantic scan scored low, since startup
+    pub overblocks_total: u64,
+    /// Most recent misses, newest first
+    pub recent: Vec<FirewallMiss>,
 }
@@ -10,6 +10,8 @@ pub async fn handle_prompt(
         .inspect(PromptFirewallRequest {
             prompt: prompt.into(),
             correlation_id,
+            detected_language: None,
+            pre_translated_text: None,
         })
         .await
 }
@@ -0,0 +1,105 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use super::dtos::{FirewallMiss, FirewallMissSource, FirewallMissesResponse};
+use crate::modules::telemetry::metrics::get_metrics;
+
+pub const DEFAULT_FIREWALL_MISS_CAPACITY: usize = 100;
+
+#[derive(Default)]
+struct Tally {
+    misses_total: BTreeMap<FirewallMissSource, u64>,
+    overblocks_total: u64,
+    recent: VecDeque<FirewallMiss>,
+}
+
+/// Disagreements between the firewall and the stages after it, for rule authors
+///
+/// Shared by clones. Keeps the most recent misses in a ring buffer and running totals
+/// of misses and over-blocks, which are also exported as metrics.
+#[derive(Clone)]
+pub struct FirewallMissLog {
+    capacity: usize,
+    tally: Arc<Mutex<Tally>>,
+}
+
+impl Default for FirewallMissLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_FIREWALL_MISS_CAPACITY)
+    }
+}
+
+impl FirewallMissLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tally: Arc::new(Mutex::new(Tally::default())),
+        }
+    }
+
+    /// Count `miss` in `firewall_misses_total` and keep it, dropping the oldest if full
+    pub fn record_miss(&self, miss: FirewallMiss) {
+        get_metrics().increment_firewall_misses(miss.caught_by.as_str());
+        let mut tally = self.tally.lock().unwrap();
+        *tally.misses_total.entry(miss.caught_by).or_default() += 1;
+        if tally.recent.len() == self.capacity {
+            tally.recent.pop_front();
+        }
+        tally.recent.push_back(miss);
+    }
+
+    /// Count a firewall block of a prompt the semantic scan scored low
+    pub fn record_overblock(&self) {
+        get_metrics().increment_firewall_overblocks();
+        self.tally.lock().unwrap().overblocks_total += 1;
+    }
+
+    pub fn summary(&self) -> FirewallMissesResponse {
+        let tally = self.tally.lock().unwrap();
+        FirewallMissesResponse {
+            misses_total: tally.misses_total.clone(),
+            overblocks_total: tally.overblocks_total,
+            recent: tally.recent.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn miss(correlation_id: &str, caught_by: FirewallMissSource) -> FirewallMiss {
+        FirewallMiss {
+            correlation_id: correlation_id.to_owned(),
+            caught_by,
+            category: None,
+            prompt_hash: "sha256:00".to_owned(),
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_newest_misses_and_counts_all_of_them() {
+        let log = FirewallMissLog::new(2);
+        log.record_miss(miss("a", FirewallMissSource::Semantic));
+        log.record_miss(miss("b", FirewallMissSource::InputModeration));
+        log.record_miss(miss("c", FirewallMissSource::Semantic));
+        log.record_overblock();
+
+        let summary = log.summary();
+        let kept: Vec<_> = summary
+            .recent
+            .iter()
+            .map(|miss| miss.correlation_id.as_str())
+            .collect();
+        assert_eq!(kept, ["c", "b"]);
+        assert_eq!(summary.misses_total[&FirewallMissSource::Semantic], 2);
+        assert_eq!(
+            summary.misses_total[&FirewallMissSource::InputModeration],
+            1
+        );
+        assert_eq!(summary.overblocks_total, 1);
+    }
+}
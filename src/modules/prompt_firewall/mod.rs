@@ -1,4 +1,9 @@
+pub mod archive;
 pub mod dtos;
 pub mod handler;
+pub mod language_mix;
+pub mod misses;
 pub mod rules;
 pub mod service;
+pub mod unfurl;
+pub mod urls;
@@ -0,0 +1,383 @@
+//! Fetching the pages behind allowlisted URLs so their content can be scanned
+//!
+//! Off unless `URL_UNFURL_ENABLED` is set, since it makes the firewall call out to the
+//! network. Every hop is checked before it is made: only `http` and `https`, only
+//! allowlisted hosts, and only public addresses, with the connection pinned to the
+//! address that was checked so a second DNS answer cannot point it elsewhere.
+//! Redirects are followed by hand under the same checks.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use thiserror::Error;
+use url::{Host, Url};
+
+use super::urls::UrlPolicy;
+
+pub const DEFAULT_UNFURL_MAX_URLS: usize = 3;
+pub const DEFAULT_UNFURL_MAX_BYTES: usize = 16 * 1024;
+pub const DEFAULT_UNFURL_TIMEOUT_MS: u64 = 2000;
+pub const DEFAULT_UNFURL_MAX_REDIRECTS: usize = 3;
+
+/// Which linked pages are fetched and how much of each
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UrlUnfurlConfig {
+    /// Fetch allowlisted URLs at all (default: off)
+    pub enabled: bool,
+    /// URLs fetched per prompt, in order of appearance (default: 3)
+    pub max_urls: usize,
+    /// Bytes of each page read and scanned (default: 16 KiB)
+    pub max_bytes: usize,
+    /// Time allowed for one URL, redirects and address lookups included
+    /// (default: 2000ms)
+    pub timeout_ms: u64,
+    /// Redirects followed per URL (default: 3)
+    pub max_redirects: usize,
+    /// Private or reserved networks fetches may reach anyway, e.g. an internal
+    /// documentation host (default: none)
+    pub allowed_networks: Vec<IpNetwork>,
+}
+
+impl Default for UrlUnfurlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_urls: DEFAULT_UNFURL_MAX_URLS,
+            max_bytes: DEFAULT_UNFURL_MAX_BYTES,
+            timeout_ms: DEFAULT_UNFURL_TIMEOUT_MS,
+            max_redirects: DEFAULT_UNFURL_MAX_REDIRECTS,
+            allowed_networks: Vec::new(),
+        }
+    }
+}
+
+/// An address range in CIDR notation, or a single address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid network '{value}' (expected an address or CIDR)");
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let address: IpAddr = address.parse().map_err(|_| invalid())?;
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { address, prefix })
+    }
+}
+
+/// Start of a linked page, at most `max_bytes` of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FetchedPage {
+    pub text: String,
+    pub bytes: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum UnfurlError {
+    /// The URL, an address it resolved to or a redirect target was not allowed
+    #[error("{0}")]
+    Refused(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// Fetches linked pages within [`UrlUnfurlConfig`] limits
+#[derive(Clone, Debug)]
+pub struct UrlUnfurler {
+    config: UrlUnfurlConfig,
+}
+
+impl UrlUnfurler {
+    pub fn new(config: UrlUnfurlConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &UrlUnfurlConfig {
+        &self.config
+    }
+
+    /// The start of the page at `url`, which `policy` must allowlist, as must every
+    /// redirect target
+    pub async fn fetch(&self, url: &Url, policy: &UrlPolicy) -> Result<FetchedPage, UnfurlError> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        tokio::time::timeout(timeout, self.follow(url.clone(), policy, timeout))
+            .await
+            .unwrap_or_else(|_| {
+                Err(UnfurlError::Failed(format!(
+                    "timed out after {}ms",
+                    self.config.timeout_ms
+                )))
+            })
+    }
+
+    async fn follow(
+        &self,
+        mut url: Url,
+        policy: &UrlPolicy,
+        timeout: Duration,
+    ) -> Result<FetchedPage, UnfurlError> {
+        for _ in 0..=self.config.max_redirects {
+            let address = self.checked_address(&url, policy).await?;
+            let mut client = reqwest::Client::builder()
+                .redirect(Policy::none())
+                .no_proxy()
+                .connect_timeout(timeout)
+                .timeout(timeout);
+            if let Some(Host::Domain(domain)) = url.host() {
+                client = client.resolve(domain, address);
+            }
+            let client = client
+                .build()
+                .map_err(|e| UnfurlError::Failed(e.to_string()))?;
+            let mut response = client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| UnfurlError::Failed(format!("request failed: {e}")))?;
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| {
+                        UnfurlError::Failed(format!("redirect ({status}) without a location"))
+                    })?;
+                url = url
+                    .join(location)
+                    .map_err(|e| UnfurlError::Failed(format!("invalid redirect location: {e}")))?;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(UnfurlError::Failed(format!("server answered {status}")));
+            }
+
+            let mut body = Vec::new();
+            let mut truncated = false;
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| UnfurlError::Failed(format!("reading the page failed: {e}")))?
+            {
+                let room = self.config.max_bytes - body.len();
+                if chunk.len() > room {
+                    body.extend_from_slice(&chunk[..room]);
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+            return Ok(FetchedPage {
+                text: String::from_utf8_lossy(&body).into_owned(),
+                bytes: body.len(),
+                truncated,
+            });
+        }
+        Err(UnfurlError::Refused(format!(
+            "more than {} redirects",
+            self.config.max_redirects
+        )))
+    }
+
+    /// Address to connect to for `url`, once the URL and every address its host
+    /// resolves to have passed the checks
+    async fn checked_address(
+        &self,
+        url: &Url,
+        policy: &UrlPolicy,
+    ) -> Result<SocketAddr, UnfurlError> {
+        let refused = |reason: String| Err(UnfurlError::Refused(reason));
+        if !matches!(url.scheme(), "http" | "https") {
+            return refused(format!("{} links are not fetched", url.scheme()));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return refused("links with credentials are not fetched".to_owned());
+        }
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        if !policy.is_allowlisted(&host) {
+            return refused(format!("{host} is not on the domain allowlist"));
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addresses = match url.host() {
+            Some(Host::Ipv4(address)) => vec![SocketAddr::new(address.into(), port)],
+            Some(Host::Ipv6(address)) => vec![SocketAddr::new(address.into(), port)],
+            Some(Host::Domain(domain)) => {
+                let domain = domain.to_owned();
+                tokio::task::spawn_blocking(move || {
+                    (domain.as_str(), port)
+                        .to_socket_addrs()
+                        .map(Iterator::collect::<Vec<_>>)
+                })
+                .await
+                .map_err(|e| UnfurlError::Failed(e.to_string()))?
+                .map_err(|e| UnfurlError::Failed(format!("cannot resolve {host}: {e}")))?
+            }
+            None => return refused("link has no host".to_owned()),
+        };
+        // One private answer is enough to refuse, so a host cannot mix a public address
+        // that passes with an internal one that gets used
+        if let Some(denied) = addresses.iter().find(|address| {
+            is_restricted(address.ip())
+                && !self
+                    .config
+                    .allowed_networks
+                    .iter()
+                    .any(|network| network.contains(address.ip()))
+        }) {
+            return refused(format!(
+                "{host} resolves to a private or reserved address ({})",
+                denied.ip()
+            ));
+        }
+        addresses
+            .first()
+            .copied()
+            .ok_or_else(|| UnfurlError::Failed(format!("{host} has no addresses")))
+    }
+}
+
+/// Loopback, private, link-local, shared, documentation, multicast and other addresses
+/// that are not on the public internet
+pub fn is_restricted(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_restricted_v4(address),
+        IpAddr::V6(address) => {
+            if let Some(mapped) = address.to_ipv4_mapped() {
+                return is_restricted_v4(mapped);
+            }
+            let segments = address.segments();
+            let embedded_v4 =
+                Ipv4Addr::from((u32::from(segments[6]) << 16) | u32::from(segments[7]));
+            // Deprecated IPv4-compatible ::a.b.c.d, which `::1` and `::` also fall under
+            if segments[..6] == [0; 6] {
+                return is_restricted_v4(embedded_v4);
+            }
+            address.is_loopback()
+                || address.is_unspecified()
+                || address.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || segments[0] & 0xfe00 == 0xfc00
+                || segments[0] & 0xffc0 == 0xfe80
+                // Documentation 2001:db8::/32
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+                // NAT64 64:ff9b::/96 embeds an IPv4 address
+                || (segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] && is_restricted_v4(embedded_v4))
+        }
+    }
+}
+
+fn is_restricted_v4(address: Ipv4Addr) -> bool {
+    let [a, b, c, _] = address.octets();
+    address.is_unspecified()
+        || address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_broadcast()
+        || address.is_documentation()
+        || address.is_multicast()
+        // "This network" 0.0.0.0/8
+        || a == 0
+        // Shared address space 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved 240.0.0.0/4
+        || a >= 240
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_and_reserved_addresses_are_restricted() {
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "198.18.0.1",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(is_restricted(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "93.184.216.34",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+            "::1.1.1.1",
+        ] {
+            assert!(!is_restricted(address.parse().unwrap()), "{address}");
+        }
+    }
+
+    #[test]
+    fn networks_parse_and_match() {
+        let network: IpNetwork = "10.20.0.0/16".parse().unwrap();
+        assert!(network.contains("10.20.3.4".parse().unwrap()));
+        assert!(!network.contains("10.21.0.1".parse().unwrap()));
+        let single: IpNetwork = "fd00::5".parse().unwrap();
+        assert!(single.contains("fd00::5".parse().unwrap()));
+        assert!(!single.contains("fd00::6".parse().unwrap()));
+        assert!(
+            "0.0.0.0/0"
+                .parse::<IpNetwork>()
+                .unwrap()
+                .contains("8.8.8.8".parse().unwrap())
+        );
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("intranet".parse::<IpNetwork>().is_err());
+    }
+}
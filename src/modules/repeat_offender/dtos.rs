@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// What to do when a prompt closely resembles a recently blocked one
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationMode {
+    /// Do not track blocked prompts
+    #[default]
+    Off,
+    /// Block the prompt outright
+    Block,
+    /// Add `risk_bonus` to the semantic risk score and reclassify
+    RiskBonus,
+}
+
+impl std::str::FromStr for EscalationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "block" => Ok(Self::Block),
+            "risk_bonus" => Ok(Self::RiskBonus),
+            other => Err(format!(
+                "unknown repeat offender mode '{other}' (expected off, block or risk_bonus)"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RepeatOffenderConfig {
+    pub mode: EscalationMode,
+    /// Number of blocked prompts remembered
+    pub window_size: usize,
+    /// Similarity at or above which a prompt counts as a repeat
+    pub similarity_threshold: f32,
+    /// Added to the semantic risk score in `RiskBonus` mode
+    pub risk_bonus: f32,
+}
+
+impl Default for RepeatOffenderConfig {
+    fn default() -> Self {
+        Self {
+            mode: EscalationMode::Off,
+            window_size: 100,
+            similarity_threshold: 0.92,
+            risk_bonus: 0.15,
+        }
+    }
+}
+
+impl RepeatOffenderConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_size == 0 {
+            return Err("repeat offender window size must be greater than zero".to_owned());
+        }
+        let in_unit_range = |value: f32| value.is_finite() && (0.0..=1.0).contains(&value);
+        if !in_unit_range(self.similarity_threshold) {
+            return Err("repeat offender similarity threshold must be within 0.0..=1.0".to_owned());
+        }
+        if !in_unit_range(self.risk_bonus) {
+            return Err("repeat offender risk bonus must be within 0.0..=1.0".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// A recently blocked prompt the current one resembles
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RepeatMatch {
+    /// Correlation id of the earlier blocked request
+    pub correlation_id: String,
+    pub similarity: f32,
+    /// "embedding" or "simhash", depending on what both prompts had available
+    pub method: String,
+}
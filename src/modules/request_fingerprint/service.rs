@@ -0,0 +1,106 @@
+//! One stable hash of a prompt or request, for every feature that needs to recognise the
+//! same request again
+//!
+//! Canonicalization is deliberately light, so two prompts share a fingerprint only when
+//! a person would call them the same text:
+//!
+//! 1. `\r\n` and lone `\r` line breaks become `\n`
+//! 2. the text is put in Unicode NFC, so composed and decomposed accents agree
+//! 3. leading and trailing whitespace is trimmed
+//!
+//! Case, inner whitespace, homoglyphs and invisible characters are kept. The firewall's
+//! much more aggressive canonicalization exists to catch evasion and changes with its
+//! rules; a fingerprint must not.
+//!
+//! Every fingerprint starts with its algorithm version, currently `v1:`, followed by the
+//! lowercase hex SHA-256 of the canonical form. A change to the canonicalization or the
+//! encoding gets a new version, so stored fingerprints are never silently compared
+//! against ones computed differently.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::modules::audit::proof::hash_record;
+
+/// Version prefixed to every fingerprint this module emits
+pub const FINGERPRINT_VERSION: &str = "v1";
+
+/// `text` in the canonical form fingerprints are computed from
+pub fn canonicalize(text: &str) -> String {
+    let unified = text.replace("\r\n", "\n").replace('\r', "\n");
+    let composed: String = unified.nfc().collect();
+    composed.trim().to_owned()
+}
+
+/// Fingerprint of a prompt on its own
+pub fn prompt_fingerprint(prompt: &str) -> String {
+    versioned(&canonicalize(prompt))
+}
+
+/// What a full-request fingerprint is the hash of, as JSON with keys in a fixed order
+#[derive(Serialize)]
+struct CanonicalRequest<'a> {
+    prompt: String,
+    overrides: BTreeMap<&'a str, Value>,
+    tenant: Option<&'a str>,
+}
+
+/// Fingerprint of a prompt together with the options that change how it is answered and
+/// the tenant it was sent for
+///
+/// Strings inside `overrides` are canonicalized like the prompt and object keys are
+/// sorted, so only the values matter, not how they were written. An absent override
+/// differs from one set to its default; callers leave defaults out.
+pub fn request_fingerprint(
+    prompt: &str,
+    overrides: &BTreeMap<String, Value>,
+    tenant: Option<&str>,
+) -> String {
+    let request = CanonicalRequest {
+        prompt: canonicalize(prompt),
+        overrides: overrides
+            .iter()
+            .map(|(name, value)| (name.as_str(), canonical_value(value)))
+            .collect(),
+        tenant,
+    };
+    versioned(&serde_json::to_string(&request).unwrap_or_default())
+}
+
+/// Whether `value` looks like a fingerprint of the current version
+pub fn is_fingerprint(value: &str) -> bool {
+    value
+        .strip_prefix(FINGERPRINT_VERSION)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .is_some_and(|hex| {
+            hex.len() == 64
+                && hex
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        })
+}
+
+fn versioned(canonical: &str) -> String {
+    format!("{FINGERPRINT_VERSION}:{}", hash_record(canonical))
+}
+
+fn canonical_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(canonicalize(text)),
+        Value::Array(items) => Value::Array(items.iter().map(canonical_value).collect()),
+        Value::Object(fields) => {
+            let mut sorted: Vec<_> = fields.iter().collect();
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                sorted
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical_value(value)))
+                    .collect(),
+            )
+        }
+        other => other.clone(),
+    }
+}
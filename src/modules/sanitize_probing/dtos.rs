@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest session id accepted, in bytes
+pub const MAX_SESSION_ID_LENGTH: usize = 128;
+
+/// When repeated sanitization of the same content within a session counts as probing
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SanitizeProbingConfig {
+    pub enabled: bool,
+    /// Similar sanitized prompts a session may send within the window; the next similar
+    /// one is blocked
+    pub threshold: usize,
+    /// Seconds a sanitized prompt counts against its session
+    pub window_secs: u64,
+    /// Share of the longer text's characters that may be edited for two sets of removed
+    /// fragments to still count as similar; `0` compares their hashes only
+    pub max_edit_ratio: f32,
+    /// Sanitized prompts remembered per session
+    pub ring_size: usize,
+}
+
+impl Default for SanitizeProbingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 3,
+            window_secs: 600,
+            max_edit_ratio: 0.2,
+            ring_size: 16,
+        }
+    }
+}
+
+impl SanitizeProbingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold == 0 {
+            return Err("sanitize probing threshold must be greater than zero".to_owned());
+        }
+        if self.window_secs == 0 {
+            return Err("sanitize probing window must be greater than zero".to_owned());
+        }
+        if !self.max_edit_ratio.is_finite() || !(0.0..=1.0).contains(&self.max_edit_ratio) {
+            return Err("sanitize probing edit ratio must be within 0.0..=1.0".to_owned());
+        }
+        if self.ring_size < self.threshold {
+            return Err(format!(
+                "sanitize probing ring size ({}) must be at least the threshold ({})",
+                self.ring_size, self.threshold
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A sanitized prompt escalated to a block as part of a probing sequence
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProbingEscalation {
+    pub session_id: String,
+    /// Hash of the fragments sanitization removed from this prompt
+    pub removed_content_hash: String,
+    /// Earlier sanitized requests of the session that removed similar content, oldest first
+    pub prior_correlation_ids: Vec<String>,
+}
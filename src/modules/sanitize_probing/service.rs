@@ -0,0 +1,363 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde_json::json;
+use tracing::{error, warn};
+
+use super::dtos::{ProbingEscalation, SanitizeProbingConfig};
+use crate::modules::alerting::dtos::{Alert, AlertLevel};
+use crate::modules::alerting::service::WebhookNotifier;
+use crate::modules::caches::service::ManagedCache;
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Alert name posted when a session is escalated
+pub const PROBING_ALERT: &str = "sanitize_probing";
+/// Characters of removed content compared by edit distance; the hash covers all of it
+const MAX_COMPARED_CHARS: usize = 256;
+/// Sessions tracked before those idle for a whole window are dropped
+const MAX_SESSIONS: usize = 10_000;
+
+/// Spots sessions resubmitting the same sanitized payload until sanitization misses it
+///
+/// Each session keeps a small ring of what sanitization removed from its recent prompts.
+/// Once `threshold` earlier prompts within the window removed similar content, the next
+/// similar one is escalated.
+#[derive(Clone)]
+pub struct SanitizeProbingService {
+    config: SanitizeProbingConfig,
+    sessions: Arc<Mutex<HashMap<String, SessionRing>>>,
+    notifier: Option<WebhookNotifier>,
+}
+
+/// What sanitization removed from a prompt, reduced to what probing checks compare
+#[derive(Clone, Debug)]
+pub struct RemovedContent {
+    hash: u64,
+    /// Lowercased, whitespace-collapsed and cut to [`MAX_COMPARED_CHARS`]
+    text: Vec<char>,
+}
+
+impl RemovedContent {
+    pub fn new(fragments: &[String]) -> Self {
+        let normalized = fragments
+            .iter()
+            .map(|fragment| {
+                fragment
+                    .to_lowercase()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut hasher = DefaultHasher::new();
+        normalized.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            text: normalized.chars().take(MAX_COMPARED_CHARS).collect(),
+        }
+    }
+
+    pub fn hash_hex(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+
+    fn is_similar(&self, other: &Self, max_edit_ratio: f32) -> bool {
+        let longer = self.text.len().max(other.text.len());
+        let max_edits = (longer as f32 * max_edit_ratio) as usize;
+        self.hash == other.hash
+            || (max_edits > 0 && within_edit_distance(&self.text, &other.text, max_edits))
+    }
+}
+
+impl SanitizeProbingService {
+    pub fn new(config: SanitizeProbingConfig) -> Self {
+        Self {
+            config,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            notifier: None,
+        }
+    }
+
+    /// Post escalations to `notifier`; `None` only logs and counts them
+    pub fn with_notifier(mut self, notifier: Option<WebhookNotifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn config(&self) -> SanitizeProbingConfig {
+        self.config
+    }
+
+    /// Remember a sanitized prompt of `session_id`, escalating it when enough earlier
+    /// prompts of the session removed similar content
+    pub fn observe(
+        &self,
+        session_id: &str,
+        correlation_id: &str,
+        content: RemovedContent,
+    ) -> Option<ProbingEscalation> {
+        let removed_content_hash = content.hash_hex();
+        let prior = self.observe_at(session_id, correlation_id, content, Instant::now());
+        if prior.len() < self.config.threshold {
+            return None;
+        }
+        let escalation = ProbingEscalation {
+            session_id: session_id.to_owned(),
+            removed_content_hash,
+            prior_correlation_ids: prior,
+        };
+        self.raise(correlation_id, &escalation);
+        Some(escalation)
+    }
+
+    /// Correlation ids of the similar prompts already in the window, oldest first
+    fn observe_at(
+        &self,
+        session_id: &str,
+        correlation_id: &str,
+        content: RemovedContent,
+        now: Instant,
+    ) -> Vec<String> {
+        let window = Duration::from_secs(self.config.window_secs);
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(session_id) {
+            sessions.retain(|_, ring| {
+                ring.expire(now, window);
+                !ring.entries.is_empty()
+            });
+        }
+        let ring = sessions.entry(session_id.to_owned()).or_default();
+        ring.expire(now, window);
+        let prior = ring
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .content
+                    .is_similar(&content, self.config.max_edit_ratio)
+            })
+            .map(|entry| entry.correlation_id.clone())
+            .collect();
+        ring.entries.push_back(SanitizedPrompt {
+            correlation_id: correlation_id.to_owned(),
+            content,
+            seen_at: now,
+        });
+        while ring.entries.len() > self.config.ring_size {
+            ring.entries.pop_front();
+        }
+        prior
+    }
+
+    fn raise(&self, correlation_id: &str, escalation: &ProbingEscalation) {
+        warn!(
+            "Session {} escalated to block for probing the sanitizer ({} similar sanitized prompts)",
+            escalation.session_id,
+            escalation.prior_correlation_ids.len()
+        );
+        get_metrics().increment_sanitize_probing_escalations();
+        let Some(notifier) = self.notifier.clone() else {
+            return;
+        };
+        let alert = Alert {
+            alert: PROBING_ALERT.to_owned(),
+            level: AlertLevel::Critical,
+            summary: format!(
+                "Session {} blocked for probing the sanitizer after {} similar sanitized prompts",
+                escalation.session_id,
+                escalation.prior_correlation_ids.len()
+            ),
+            details: json!({
+                "correlation_id": correlation_id,
+                "session_id": escalation.session_id,
+                "removed_content_hash": escalation.removed_content_hash,
+                "prior_correlation_ids": escalation.prior_correlation_ids,
+            }),
+            raised_at: Utc::now(),
+        };
+        // The request is answered without waiting for the webhook, and the alert is
+        // still posted if the caller disconnects before then
+        tokio::spawn(async move {
+            if let Err(e) = notifier.notify(&alert).await {
+                error!(
+                    "Failed to post the sanitize probing alert to the webhook: {}",
+                    e
+                );
+            }
+        });
+    }
+}
+
+/// Sessions are the entries; shedding forgets those idle the longest, whose next
+/// sanitized prompt then starts a fresh count
+impl ManagedCache for SanitizeProbingService {
+    fn entries(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    fn estimated_bytes(&self) -> usize {
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(session_id, ring)| session_id.len() + ring.estimated_bytes())
+            .sum()
+    }
+
+    fn shed(&self, count: usize) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut by_idle: Vec<(Option<Instant>, String)> = sessions
+            .iter()
+            .map(|(session_id, ring)| (ring.last_seen(), session_id.clone()))
+            .collect();
+        by_idle.sort_unstable();
+        let mut shed = 0;
+        for (_, session_id) in by_idle.into_iter().take(count) {
+            sessions.remove(&session_id);
+            shed += 1;
+        }
+        shed
+    }
+}
+
+struct SanitizedPrompt {
+    correlation_id: String,
+    content: RemovedContent,
+    seen_at: Instant,
+}
+
+/// Sanitized prompts of one session, oldest first
+#[derive(Default)]
+struct SessionRing {
+    entries: VecDeque<SanitizedPrompt>,
+}
+
+impl SessionRing {
+    fn last_seen(&self) -> Option<Instant> {
+        self.entries.back().map(|entry| entry.seen_at)
+    }
+
+    fn estimated_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .entries
+                .iter()
+                .map(|entry| {
+                    std::mem::size_of::<SanitizedPrompt>()
+                        + entry.correlation_id.len()
+                        + std::mem::size_of_val(entry.content.text.as_slice())
+                })
+                .sum::<usize>()
+    }
+
+    fn expire(&mut self, now: Instant, window: Duration) {
+        while self
+            .entries
+            .front()
+            .is_some_and(|entry| now.duration_since(entry.seen_at) > window)
+        {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// Whether `a` becomes `b` in at most `max` character insertions, deletions or
+/// substitutions
+fn within_edit_distance(a: &[char], b: &[char], max: usize) -> bool {
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, left) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, right) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left != right);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        // Every later row is at least this row's minimum
+        if current.iter().min().is_some_and(|&distance| distance > max) {
+            return false;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()] <= max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(fragment: &str) -> RemovedContent {
+        RemovedContent::new(&[fragment.to_owned()])
+    }
+
+    fn service() -> SanitizeProbingService {
+        SanitizeProbingService::new(SanitizeProbingConfig {
+            enabled: true,
+            ..SanitizeProbingConfig::default()
+        })
+    }
+
+    #[test]
+    fn edit_distance_is_bounded() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        assert!(within_edit_distance(
+            &chars("<script>steal()</script>"),
+            &chars("<script>steal2()</script>"),
+            1
+        ));
+        assert!(!within_edit_distance(
+            &chars("<script>steal()</script>"),
+            &chars("<iframe src=x>"),
+            8
+        ));
+        assert!(within_edit_distance(&chars(""), &chars("abc"), 3));
+    }
+
+    #[test]
+    fn only_similar_prompts_within_the_window_count() {
+        let service = service();
+        let start = Instant::now();
+        let payload = "<script>steal()</script>";
+        // Two edits in 24 characters
+        let tweaked = "<script>steal(1)</script >";
+        assert!(
+            service
+                .observe_at("s1", "a", content(payload), start)
+                .is_empty()
+        );
+        service.observe_at("s1", "b", content("<b onclick=x>"), start);
+        service.observe_at("s2", "c", content(payload), start);
+        let prior = service.observe_at("s1", "d", content(tweaked), start + Duration::from_secs(1));
+        assert_eq!(prior, ["a"]);
+
+        let later = start + Duration::from_secs(service.config.window_secs + 2);
+        assert_eq!(
+            service.observe_at("s1", "e", content(payload), later),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn ring_keeps_the_newest_prompts() {
+        let service = SanitizeProbingService::new(SanitizeProbingConfig {
+            enabled: true,
+            threshold: 1,
+            ring_size: 2,
+            ..SanitizeProbingConfig::default()
+        });
+        let now = Instant::now();
+        for id in ["a", "b", "c"] {
+            service.observe_at("s1", id, content("<script>x</script>"), now);
+        }
+        assert_eq!(
+            service.observe_at("s1", "d", content("<script>x</script>"), now),
+            ["b", "c"]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::WorkflowStatus;
+
+/// A canned prompt and the outcome a healthy pipeline produces for it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SelfTestProbe {
+    pub name: String,
+    pub prompt: String,
+    pub expected_status: WorkflowStatus,
+    /// When set, whether bias detection must rate the prompt above `Low`
+    #[serde(default)]
+    pub expect_bias_flagged: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ProbeResult {
+    pub name: String,
+    pub correlation_id: String,
+    pub expected_status: WorkflowStatus,
+    /// Missing when the pipeline returned an error
+    pub actual_status: Option<WorkflowStatus>,
+    pub passed: bool,
+    /// Why the probe failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    pub total_ms: u64,
+    /// Time spent in each pipeline stage that ran
+    pub stage_latencies_ms: BTreeMap<String, u64>,
+    pub mistral_stages_exercised: Vec<String>,
+    pub mistral_stages_skipped: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    /// Whether probe runs were written to the audit trail (marked as self-tests)
+    pub audit_recorded: bool,
+    pub probes: Vec<ProbeResult>,
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::AttackCandidate;
+
+#[cfg(feature = "sled-storage")]
+const CANDIDATES_TREE: &str = "semantic_attack_candidates";
+
+/// Review queue for attack templates mined from blocked prompts
+///
+/// Nothing here is used for detection; a candidate only reaches the live bank when it is
+/// approved.
+pub trait CandidateStore: Send + Sync {
+    /// Insert a candidate, replacing any with the same id
+    fn upsert(&self, candidate: AttackCandidate) -> Result<(), CandidateStoreError>;
+    fn get(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError>;
+    fn remove(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError>;
+    /// Pending candidates, ordered by id
+    fn list(&self) -> Result<Vec<AttackCandidate>, CandidateStoreError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryCandidateStore {
+    inner: Arc<Mutex<BTreeMap<String, AttackCandidate>>>,
+}
+
+impl InMemoryCandidateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CandidateStore for InMemoryCandidateStore {
+    fn upsert(&self, candidate: AttackCandidate) -> Result<(), CandidateStoreError> {
+        self.inner
+            .lock()
+            .map_err(|_| CandidateStoreError::LockPoisoned)?
+            .insert(candidate.id.clone(), candidate);
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| CandidateStoreError::LockPoisoned)?
+            .get(id)
+            .cloned())
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| CandidateStoreError::LockPoisoned)?
+            .remove(id))
+    }
+
+    fn list(&self) -> Result<Vec<AttackCandidate>, CandidateStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| CandidateStoreError::LockPoisoned)?
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+/// Candidates kept in their own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledCandidateStore {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledCandidateStore {
+    pub fn new(db: &Db) -> Result<Self, CandidateStoreError> {
+        let tree = db
+            .open_tree(CANDIDATES_TREE)
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl CandidateStore for SledCandidateStore {
+    fn upsert(&self, candidate: AttackCandidate) -> Result<(), CandidateStoreError> {
+        let serialized = serde_json::to_vec(&candidate)
+            .map_err(|e| CandidateStoreError::SerializationError(e.to_string()))?;
+        self.tree
+            .insert(candidate.id.as_bytes(), serialized)
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError> {
+        self.tree
+            .get(id.as_bytes())
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| CandidateStoreError::SerializationError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn remove(&self, id: &str) -> Result<Option<AttackCandidate>, CandidateStoreError> {
+        let removed = self
+            .tree
+            .remove(id.as_bytes())
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+        self.tree
+            .flush()
+            .map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+        removed
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| CandidateStoreError::SerializationError(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn list(&self) -> Result<Vec<AttackCandidate>, CandidateStoreError> {
+        self.tree
+            .iter()
+            .values()
+            .map(|entry| {
+                let data = entry.map_err(|e| CandidateStoreError::DatabaseError(e.to_string()))?;
+                serde_json::from_slice(&data)
+                    .map_err(|e| CandidateStoreError::SerializationError(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CandidateStoreError {
+    #[error("candidate store lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
@@ -1,8 +1,26 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use ring::rand::SystemRandom;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::firewall_core::taxonomy::{CategoryRef, ThreatCategory, resolve_category};
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct SemanticScanRequest {
     pub text: String,
+    /// Language the caller already detected for `text`; when set, the service does no
+    /// detection or translation of its own and embeds `pre_translated_text`, if any
+    ///
+    /// `POST /api/semantic/scan` always detects for itself, so a caller cannot choose the
+    /// text that gets embedded.
+    #[serde(skip)]
+    pub detected_language: Option<String>,
+    /// English rendering of `text` made by the caller
+    #[serde(skip)]
+    pub pre_translated_text: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -17,12 +35,23 @@ pub struct SemanticScanResult {
     pub similarity: f32,
     /// Category of the matched attack template
     pub category: Option<String>,
+    /// `category` resolved to the threat taxonomy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_category: Option<CategoryRef>,
+    /// Character range `[start, end)` of the window that matched, when a long prompt was
+    /// scanned in windows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_window: Option<(usize, usize)>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum SemanticRiskLevel {
+    #[serde(alias = "Low")]
     Low,
+    #[serde(alias = "Medium")]
     Medium,
+    #[serde(alias = "High")]
     High,
 }
 
@@ -34,11 +63,183 @@ impl SemanticScanResult {
             nearest_template_id: None,
             similarity: 0.0,
             category: None,
+            threat_category: None,
+            matched_window: None,
         }
     }
 }
 
 /// Attack template loaded from JSON
+/// Similarity cutoffs used to classify semantic risk
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SemanticThresholds {
+    /// Threshold for Low/Medium boundary
+    pub medium_threshold: f32,
+    /// Threshold for Medium/High boundary
+    pub high_threshold: f32,
+    /// Extra buffer added to semantic thresholds to reduce borderline false positives
+    pub decision_margin: f32,
+}
+
+impl SemanticThresholds {
+    /// Reject thresholds that are out of range or inverted
+    pub fn validate(&self) -> Result<(), String> {
+        let in_unit_range = |value: f32| value.is_finite() && (0.0..=1.0).contains(&value);
+        if !in_unit_range(self.medium_threshold) || !in_unit_range(self.high_threshold) {
+            return Err("semantic thresholds must be within 0.0..=1.0".to_owned());
+        }
+        if self.medium_threshold > self.high_threshold {
+            return Err("semantic medium threshold must not exceed the high threshold".to_owned());
+        }
+        if !self.decision_margin.is_finite() || !(0.0..=0.20).contains(&self.decision_margin) {
+            return Err("semantic decision margin must be within 0.0..=0.20".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Lets a fraction of short, clean prompts skip the semantic scan
+///
+/// Only prompts the firewall allowed without matching any rule, written in English and no
+/// longer than `max_prompt_chars` are eligible; everything else is always scanned.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SemanticSamplingPolicy {
+    /// Fraction of eligible prompts that are still scanned (1.0 scans everything)
+    pub rate: f32,
+    /// Longest eligible prompt, in characters
+    pub max_prompt_chars: usize,
+}
+
+impl Default for SemanticSamplingPolicy {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            max_prompt_chars: 280,
+        }
+    }
+}
+
+impl SemanticSamplingPolicy {
+    /// Reject a rate outside 0.0..=1.0 or a zero length cap
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.rate.is_finite() || !(0.0..=1.0).contains(&self.rate) {
+            return Err("semantic sampling rate must be within 0.0..=1.0".to_owned());
+        }
+        if self.max_prompt_chars == 0 {
+            return Err("semantic sampling max prompt length must be positive".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Whether any eligible prompt can skip the scan
+    pub fn is_enabled(&self) -> bool {
+        self.rate < 1.0
+    }
+
+    /// Whether the request falls in the scanned fraction
+    ///
+    /// Derived from an HMAC of the correlation id under `key`, so retries of a request are
+    /// treated alike while a client, who picks the id, cannot tell which ids skip the scan.
+    pub fn samples_in(&self, key: &SamplingKey, correlation_id: &str) -> bool {
+        let tag = hmac::sign(&key.0, correlation_id.as_bytes());
+        let mut bucket = [0u8; 8];
+        bucket.copy_from_slice(&tag.as_ref()[..8]);
+        (u64::from_be_bytes(bucket) as f64 / u64::MAX as f64) < f64::from(self.rate)
+    }
+}
+
+/// Server-side secret that keys the sampling choice
+#[derive(Clone, Debug)]
+pub struct SamplingKey(hmac::Key);
+
+impl SamplingKey {
+    /// Key derived from a configured secret, so instances sharing it sample alike
+    pub fn from_secret(secret: &str) -> Self {
+        Self(hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()))
+    }
+
+    /// Key known only to this process
+    pub fn random() -> Self {
+        hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .map(Self)
+            // The system generator only fails on platforms without one; v4 uuids are
+            // random too
+            .unwrap_or_else(|_| Self::from_secret(&Uuid::new_v4().to_string()))
+    }
+}
+
+impl Default for SamplingKey {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+/// Splits long prompts into overlapping windows that are embedded separately
+///
+/// One embedding of a long document averages an injected instruction away; a window
+/// around it keeps it close to the attack template it resembles.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SemanticChunkingPolicy {
+    /// Prompts up to this many characters get a single embedding; 0 disables chunking
+    pub min_prompt_chars: usize,
+    /// Characters per window
+    pub window_chars: usize,
+    /// Characters shared by consecutive windows
+    pub overlap_chars: usize,
+    /// Window embeddings requested at once for a single prompt
+    pub max_concurrency: usize,
+}
+
+impl Default for SemanticChunkingPolicy {
+    fn default() -> Self {
+        Self {
+            min_prompt_chars: 1_024,
+            window_chars: 512,
+            overlap_chars: 128,
+            max_concurrency: 4,
+        }
+    }
+}
+
+impl SemanticChunkingPolicy {
+    /// Reject an empty window, an overlap that would not advance, or zero concurrency
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_chars == 0 {
+            return Err("semantic chunking window length must be positive".to_owned());
+        }
+        if self.overlap_chars >= self.window_chars {
+            return Err(
+                "semantic chunking overlap must be shorter than the window length".to_owned(),
+            );
+        }
+        if self.max_concurrency == 0 {
+            return Err("semantic chunking concurrency must be positive".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Character ranges `[start, end)` to embed for a text of `text_chars` characters
+    ///
+    /// Empty when the text is short enough for a single embedding. The last window ends at
+    /// the end of the text and may be shorter than the others.
+    pub fn windows(&self, text_chars: usize) -> Vec<(usize, usize)> {
+        if self.min_prompt_chars == 0 || text_chars <= self.min_prompt_chars {
+            return Vec::new();
+        }
+        let step = self.window_chars.saturating_sub(self.overlap_chars).max(1);
+        let mut windows = Vec::new();
+        let mut start = 0;
+        loop {
+            let end = (start + self.window_chars).min(text_chars);
+            windows.push((start, end));
+            if end == text_chars {
+                return windows;
+            }
+            start += step;
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AttackTemplate {
     pub id: String,
@@ -53,6 +254,143 @@ pub struct AttackTemplateBank {
     #[serde(default)]
     pub description: Option<String>,
     pub templates: Vec<AttackTemplate>,
+    /// Template categories outside the threat taxonomy and the entries they stand for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub category_aliases: BTreeMap<String, ThreatCategory>,
+}
+
+impl AttackTemplateBank {
+    /// Threat category of `template`, through this bank's aliases
+    pub fn threat_category(&self, template: &AttackTemplate) -> Result<CategoryRef, String> {
+        resolve_category(&template.category, &self.category_aliases)
+            .map_err(|e| format!("template {}: {e}", template.id))
+    }
+}
+
+/// Proposed attack template mined from blocked prompts, awaiting review
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttackCandidate {
+    /// Stable across regenerations: derived from the canonical representative text
+    pub id: String,
+    /// Template added to the bank on approval; the id is only a suggestion
+    pub template: AttackTemplate,
+    /// Firewall rule ids or moderation categories that blocked the cluster's prompts
+    pub source_rules: Vec<String>,
+    /// Blocked requests folded into this candidate
+    pub correlation_ids: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttackCandidatesResponse {
+    pub candidates: Vec<AttackCandidate>,
+    pub total: usize,
+}
+
+/// Outcome of mining the audit trail for candidates
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CandidateGenerationReport {
+    /// Start of the audit window that was searched
+    pub since: DateTime<Utc>,
+    /// Firewall- and input-moderation-blocked prompts found in the window
+    pub blocked_prompts: usize,
+    /// Blocked prompts left out because only their hash was stored
+    pub skipped_unstored: usize,
+    /// Candidates created or refreshed by this run
+    pub candidates: Vec<AttackCandidate>,
+}
+
+/// Template merged into the live bank
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApprovedCandidate {
+    pub candidate_id: String,
+    pub template: AttackTemplate,
+    /// Templates in the live bank after the merge
+    pub template_count: usize,
+    pub bank_fingerprint: Option<String>,
+}
+
+/// Duplicate checks run over the attack bank whenever it is loaded
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BankHygienePolicy {
+    /// Embedding similarity at or above which two templates count as near-duplicates
+    pub near_duplicate_threshold: f32,
+    /// Largest bank compared pairwise; bigger banks only get the exact-duplicate check
+    pub max_pairwise_templates: usize,
+    /// Refuse to initialize when the bank holds exact duplicates
+    pub strict: bool,
+}
+
+impl Default for BankHygienePolicy {
+    fn default() -> Self {
+        Self {
+            near_duplicate_threshold: 0.95,
+            max_pairwise_templates: 2_000,
+            strict: false,
+        }
+    }
+}
+
+impl BankHygienePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.near_duplicate_threshold.is_finite()
+            || self.near_duplicate_threshold <= 0.0
+            || self.near_duplicate_threshold > 1.0
+        {
+            return Err(
+                "semantic bank near-duplicate threshold must be within 0.0..=1.0 and above zero"
+                    .to_owned(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Templates whose canonical text is identical
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DuplicateGroup {
+    /// Hash of the shared canonical text
+    pub text_hash: String,
+    /// In bank order
+    pub template_ids: Vec<String>,
+    /// Distinct categories in the group, sorted; more than one is a conflict
+    pub categories: Vec<String>,
+}
+
+/// Two templates compared by embedding
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TemplatePair {
+    pub first_id: String,
+    pub first_category: String,
+    pub second_id: String,
+    pub second_category: String,
+    pub similarity: f32,
+}
+
+/// Outcome of the bank duplicate checks, returned by `GET /api/semantic/bank/report`
+///
+/// Findings are only reported; no template is ever removed automatically.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BankHygieneReport {
+    pub generated_at: DateTime<Utc>,
+    pub bank_fingerprint: String,
+    pub template_count: usize,
+    pub near_duplicate_threshold: f32,
+    /// False when the bank exceeded `max_pairwise_templates` and was not compared pairwise
+    pub near_duplicates_checked: bool,
+    pub exact_duplicates: Vec<DuplicateGroup>,
+    /// Pairs at or above the threshold that are not exact duplicates of each other
+    pub near_duplicates: Vec<TemplatePair>,
+    /// Exact or near-duplicate pairs filed under different categories
+    pub category_conflicts: Vec<TemplatePair>,
+}
+
+impl BankHygieneReport {
+    pub fn is_clean(&self) -> bool {
+        self.exact_duplicates.is_empty()
+            && self.near_duplicates.is_empty()
+            && self.category_conflicts.is_empty()
+    }
 }
 
 /// Cached template with pre-computed embedding
@@ -60,6 +398,80 @@ pub struct AttackTemplateBank {
 pub struct CachedTemplate {
     pub id: String,
     pub category: String,
+    pub threat_category: CategoryRef,
     pub text: String,
     pub embedding: Vec<f32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(rate: f32) -> SemanticSamplingPolicy {
+        SemanticSamplingPolicy {
+            rate,
+            ..SemanticSamplingPolicy::default()
+        }
+    }
+
+    #[test]
+    fn sampling_is_deterministic_per_correlation_id() {
+        let half = policy(0.5);
+        let key = SamplingKey::random();
+        for index in 0..50 {
+            let id = format!("req-{index}");
+            assert_eq!(
+                half.samples_in(&key, &id),
+                half.samples_in(&key, &id),
+                "{id}"
+            );
+        }
+    }
+
+    #[test]
+    fn rate_bounds_sample_everything_or_nothing() {
+        let key = SamplingKey::random();
+        for index in 0..50 {
+            let id = format!("req-{index}");
+            assert!(policy(1.0).samples_in(&key, &id));
+            assert!(!policy(0.0).samples_in(&key, &id));
+        }
+    }
+
+    #[test]
+    fn rate_sets_the_scanned_fraction() {
+        let quarter = policy(0.25);
+        let key = SamplingKey::from_secret("quarter");
+        let sampled_in = (0..4000)
+            .filter(|index| quarter.samples_in(&key, &format!("req-{index}")))
+            .count();
+        assert!((800..1200).contains(&sampled_in), "{sampled_in}");
+    }
+
+    #[test]
+    fn the_sampled_ids_depend_on_the_key() {
+        let half = policy(0.5);
+        let ids: Vec<String> = (0..200).map(|index| format!("req-{index}")).collect();
+        let sampled = |key: &SamplingKey| -> Vec<bool> {
+            ids.iter().map(|id| half.samples_in(key, id)).collect()
+        };
+        let shared = SamplingKey::from_secret("shared secret");
+        assert_eq!(
+            sampled(&shared),
+            sampled(&SamplingKey::from_secret("shared secret"))
+        );
+        assert_ne!(sampled(&shared), sampled(&SamplingKey::random()));
+    }
+
+    #[test]
+    fn validation_rejects_out_of_range_values() {
+        assert!(policy(0.3).validate().is_ok());
+        assert!(policy(1.5).validate().is_err());
+        assert!(policy(f32::NAN).validate().is_err());
+        let uncapped = SemanticSamplingPolicy {
+            max_prompt_chars: 0,
+            ..policy(0.3)
+        };
+        assert!(uncapped.validate().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::modules::caches::dtos::CacheLookups;
+use crate::modules::caches::service::{HitCounter, ManagedCache};
+
+/// Entries kept when `SEMANTIC_EMBEDDING_CACHE_SIZE` is not set
+pub const DEFAULT_EMBEDDING_CACHE_SIZE: usize = 1024;
+/// Bookkeeping per entry besides its key and vector, roughly
+const ENTRY_OVERHEAD_BYTES: usize = 64;
+
+struct CachedEmbedding {
+    vector: Vec<f32>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CachedEmbedding>,
+    /// Bumped on every use, so the smallest `last_used` is the least recently used
+    clock: u64,
+    bytes: usize,
+}
+
+fn entry_bytes(text: &str, vector: &[f32]) -> usize {
+    text.len() + std::mem::size_of_val(vector) + ENTRY_OVERHEAD_BYTES
+}
+
+/// Embeddings of recently scanned text, so a repeated prompt costs no embedding call
+///
+/// Shared by clones. Holds at most `capacity` entries, dropping the least recently used
+/// beyond that; 0 turns the cache off. The cache registry may shed or flush it at any
+/// time, which only costs the embedding calls it was saving.
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    capacity: usize,
+    state: Arc<Mutex<CacheState>>,
+    lookups: HitCounter,
+}
+
+impl Default for EmbeddingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_EMBEDDING_CACHE_SIZE)
+    }
+}
+
+impl EmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Arc::new(Mutex::new(CacheState::default())),
+            lookups: HitCounter::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get(&self, text: &str) -> Option<Vec<f32>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let Some(entry) = state.entries.get_mut(text) else {
+            self.lookups.miss();
+            return None;
+        };
+        entry.last_used = clock;
+        self.lookups.hit();
+        Some(entry.vector.clone())
+    }
+
+    pub fn insert(&self, text: &str, vector: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let added = entry_bytes(text, &vector);
+        let entry = CachedEmbedding {
+            vector,
+            last_used: state.clock,
+        };
+        let replaced = state.entries.insert(text.to_owned(), entry);
+        state.bytes += added;
+        if let Some(replaced) = replaced {
+            state.bytes -= entry_bytes(text, &replaced.vector);
+        }
+        if state.entries.len() > self.capacity {
+            evict_least_recent(&mut state, 1);
+        }
+    }
+}
+
+/// Drop the `count` least recently used entries and return how many went
+fn evict_least_recent(state: &mut CacheState, count: usize) -> usize {
+    let mut by_age: Vec<(u64, String)> = state
+        .entries
+        .iter()
+        .map(|(text, entry)| (entry.last_used, text.clone()))
+        .collect();
+    by_age.sort_unstable();
+    let mut evicted = 0;
+    for (_, text) in by_age.into_iter().take(count) {
+        if let Some(entry) = state.entries.remove(&text) {
+            state.bytes -= entry_bytes(&text, &entry.vector);
+            evicted += 1;
+        }
+    }
+    evicted
+}
+
+impl ManagedCache for EmbeddingCache {
+    fn entries(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    fn estimated_bytes(&self) -> usize {
+        self.state.lock().unwrap().bytes
+    }
+
+    fn shed(&self, count: usize) -> usize {
+        evict_least_recent(&mut self.state.lock().unwrap(), count)
+    }
+
+    fn lookups(&self) -> Option<CacheLookups> {
+        Some(self.lookups.lookups())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recently_used_and_sheds_the_rest() {
+        let cache = EmbeddingCache::new(2);
+        cache.insert("first", vec![1.0; 4]);
+        cache.insert("second", vec![2.0; 4]);
+        assert!(cache.get("first").is_some());
+        cache.insert("third", vec![3.0; 4]);
+
+        assert_eq!(cache.entries(), 2);
+        assert!(cache.get("second").is_none());
+        assert_eq!(cache.estimated_bytes(), 2 * (5 + 16 + ENTRY_OVERHEAD_BYTES));
+        assert_eq!(cache.shed(1), 1);
+        assert_eq!(cache.get("first"), None);
+        assert_eq!(cache.get("third"), Some(vec![3.0; 4]));
+        assert_eq!(cache.shed(5), 1);
+        assert_eq!(cache.estimated_bytes(), 0);
+        assert_eq!(cache.lookups(), Some(CacheLookups { hits: 2, misses: 2 }));
+    }
+}
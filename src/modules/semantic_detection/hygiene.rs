@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use tracing::{info, warn};
+
+use super::dtos::{
+    AttackTemplate, BankHygienePolicy, BankHygieneReport, CachedTemplate, DuplicateGroup,
+    TemplatePair,
+};
+use super::service::cosine_similarity;
+use crate::modules::audit::proof::{content_hash, hash_record};
+use crate::modules::prompt_firewall::rules::canonicalize_for_block_match;
+
+/// Hash of a template's canonical text, so case, spacing and punctuation changes collide
+fn text_hash(text: &str) -> String {
+    hash_record(&canonicalize_for_block_match(text))
+}
+
+/// Groups of templates sharing a canonical text, in bank order
+pub(crate) fn exact_duplicates(templates: &[AttackTemplate]) -> Vec<DuplicateGroup> {
+    let mut groups: BTreeMap<String, Vec<&AttackTemplate>> = BTreeMap::new();
+    for template in templates {
+        groups
+            .entry(text_hash(&template.text))
+            .or_default()
+            .push(template);
+    }
+    let mut duplicates: Vec<DuplicateGroup> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(text_hash, members)| {
+            let mut categories: Vec<String> = members
+                .iter()
+                .map(|template| template.category.clone())
+                .collect();
+            categories.sort();
+            categories.dedup();
+            DuplicateGroup {
+                text_hash,
+                template_ids: members.iter().map(|template| template.id.clone()).collect(),
+                categories,
+            }
+        })
+        .collect();
+    let position = |id: &str| templates.iter().position(|template| template.id == id);
+    duplicates.sort_by_key(|group| position(&group.template_ids[0]));
+    duplicates
+}
+
+/// Check a loaded bank for exact duplicates, near-duplicates and category conflicts
+///
+/// Near-duplicates need every pair of embeddings compared, so banks larger than
+/// `max_pairwise_templates` skip that part.
+pub(crate) fn analyze(
+    templates: &[CachedTemplate],
+    policy: &BankHygienePolicy,
+) -> BankHygieneReport {
+    let plain: Vec<AttackTemplate> = templates
+        .iter()
+        .map(|cached| AttackTemplate {
+            id: cached.id.clone(),
+            category: cached.category.clone(),
+            text: cached.text.clone(),
+        })
+        .collect();
+    let exact = exact_duplicates(&plain);
+    let hashes: Vec<String> = templates
+        .iter()
+        .map(|template| text_hash(&template.text))
+        .collect();
+    let pair = |first: usize, second: usize| TemplatePair {
+        first_id: templates[first].id.clone(),
+        first_category: templates[first].category.clone(),
+        second_id: templates[second].id.clone(),
+        second_category: templates[second].category.clone(),
+        similarity: cosine_similarity(&templates[first].embedding, &templates[second].embedding),
+    };
+
+    let mut category_conflicts = Vec::new();
+    for group in exact.iter().filter(|group| group.categories.len() > 1) {
+        let members: Vec<usize> = templates
+            .iter()
+            .enumerate()
+            .filter(|(_, template)| group.template_ids.contains(&template.id))
+            .map(|(index, _)| index)
+            .collect();
+        for (offset, &first) in members.iter().enumerate() {
+            for &second in &members[offset + 1..] {
+                if templates[first].category != templates[second].category {
+                    category_conflicts.push(pair(first, second));
+                }
+            }
+        }
+    }
+
+    let near_duplicates_checked = templates.len() <= policy.max_pairwise_templates;
+    let mut near_duplicates = Vec::new();
+    if near_duplicates_checked {
+        for first in 0..templates.len() {
+            for second in first + 1..templates.len() {
+                if hashes[first] == hashes[second] {
+                    continue;
+                }
+                let candidate = pair(first, second);
+                if candidate.similarity < policy.near_duplicate_threshold {
+                    continue;
+                }
+                if candidate.first_category != candidate.second_category {
+                    category_conflicts.push(candidate.clone());
+                }
+                near_duplicates.push(candidate);
+            }
+        }
+    }
+
+    BankHygieneReport {
+        generated_at: Utc::now(),
+        bank_fingerprint: content_hash(&plain),
+        template_count: templates.len(),
+        near_duplicate_threshold: policy.near_duplicate_threshold,
+        near_duplicates_checked,
+        exact_duplicates: exact,
+        near_duplicates,
+        category_conflicts,
+    }
+}
+
+pub(crate) fn log_report(report: &BankHygieneReport) {
+    if !report.near_duplicates_checked {
+        warn!(
+            "Attack bank has {} templates; near-duplicate check skipped",
+            report.template_count
+        );
+    }
+    for group in &report.exact_duplicates {
+        warn!(
+            "Attack bank templates {} have the same text",
+            group.template_ids.join(", ")
+        );
+    }
+    for pair in &report.near_duplicates {
+        warn!(
+            "Attack bank templates {} and {} are near-duplicates (similarity {:.3})",
+            pair.first_id, pair.second_id, pair.similarity
+        );
+    }
+    for pair in &report.category_conflicts {
+        warn!(
+            "Attack bank templates {} ({}) and {} ({}) are duplicates in different categories",
+            pair.first_id, pair.first_category, pair.second_id, pair.second_category
+        );
+    }
+    if report.is_clean() {
+        info!(
+            "Attack bank hygiene check passed for {} templates",
+            report.template_count
+        );
+    }
+}
@@ -1,4 +1,7 @@
+pub mod candidates;
 pub mod dtos;
+pub mod embeddings;
+pub mod hygiene;
 pub mod service;
 
 pub use dtos::{SemanticRiskLevel, SemanticScanRequest, SemanticScanResult};
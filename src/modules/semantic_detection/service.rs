@@ -1,26 +1,45 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
+use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 
 use super::dtos::{
-    AttackTemplate, AttackTemplateBank, CachedTemplate, SemanticRiskLevel, SemanticScanRequest,
-    SemanticScanResult,
+    AttackTemplate, AttackTemplateBank, BankHygienePolicy, BankHygieneReport, CachedTemplate,
+    SemanticChunkingPolicy, SemanticRiskLevel, SemanticScanRequest, SemanticScanResult,
+    SemanticThresholds,
 };
+use super::embeddings::EmbeddingCache;
+use super::hygiene;
+use crate::config::layers::configured_path;
+use crate::firewall_core::taxonomy::CategoryRef;
+use crate::modules::audit::proof::content_hash;
 use crate::modules::mistral_ai::service::{MistralService, MistralServiceError};
 
+pub(crate) const DEFAULT_ATTACK_BANK_PATH: &str = "config/semantic_attack_bank.json";
+
+/// Skip reason of a semantic scan before the attack template bank is loaded
+pub const SEMANTIC_NOT_INITIALIZED: &str = "not_initialized";
+
+/// Character range `[start, end)` of a scanned window
+type Window = (usize, usize);
+
 #[derive(Clone)]
 pub struct SemanticDetectionService {
     mistral_service: MistralService,
     cached_templates: Arc<RwLock<Vec<CachedTemplate>>>,
     initialized: Arc<RwLock<bool>>,
-    /// Threshold for Low/Medium boundary
-    medium_threshold: f32,
-    /// Threshold for Medium/High boundary
-    high_threshold: f32,
-    /// Extra buffer added to semantic thresholds to reduce borderline false positives
-    decision_margin: f32,
+    thresholds: Arc<std::sync::RwLock<SemanticThresholds>>,
+    bank_fingerprint: Arc<std::sync::RwLock<Option<String>>>,
+    /// Overrides `SEMANTIC_ATTACK_BANK_PATH`
+    bank_path: Option<PathBuf>,
+    hygiene: BankHygienePolicy,
+    hygiene_report: Arc<std::sync::RwLock<Option<BankHygieneReport>>>,
+    chunking: SemanticChunkingPolicy,
+    /// Embeddings of recently scanned prompts and windows
+    embedding_cache: EmbeddingCache,
 }
 
 impl SemanticDetectionService {
@@ -34,22 +53,86 @@ impl SemanticDetectionService {
             mistral_service,
             cached_templates: Arc::new(RwLock::new(Vec::new())),
             initialized: Arc::new(RwLock::new(false)),
-            medium_threshold,
-            high_threshold,
-            decision_margin: normalize_margin(decision_margin),
+            thresholds: Arc::new(std::sync::RwLock::new(SemanticThresholds {
+                medium_threshold,
+                high_threshold,
+                decision_margin: normalize_margin(decision_margin),
+            })),
+            bank_fingerprint: Arc::new(std::sync::RwLock::new(None)),
+            bank_path: None,
+            hygiene: BankHygienePolicy::default(),
+            hygiene_report: Arc::new(std::sync::RwLock::new(None)),
+            chunking: SemanticChunkingPolicy::default(),
+            embedding_cache: EmbeddingCache::default(),
         }
     }
 
+    /// Load the attack template bank from this file instead of the configured one
+    pub fn with_bank_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.bank_path = Some(path.into());
+        self
+    }
+
+    /// Duplicate checks run when the bank is loaded
+    pub fn with_bank_hygiene(mut self, policy: BankHygienePolicy) -> Self {
+        self.hygiene = policy;
+        self
+    }
+
+    /// How long prompts are split into windows before embedding
+    pub fn with_chunking(mut self, policy: SemanticChunkingPolicy) -> Self {
+        self.chunking = policy;
+        self
+    }
+
+    /// Keep the embeddings of up to `capacity` recently scanned texts; 0 embeds every
+    /// scan afresh
+    pub fn with_embedding_cache(mut self, capacity: usize) -> Self {
+        self.embedding_cache = EmbeddingCache::new(capacity);
+        self
+    }
+
+    /// Embeddings reused across scans, registered with the cache registry by the server
+    pub fn embedding_cache(&self) -> &EmbeddingCache {
+        &self.embedding_cache
+    }
+
     /// Initialize the service by loading templates and computing embeddings
+    ///
+    /// The bank is checked for duplicates once the embeddings are in; findings are logged
+    /// and kept for [`Self::bank_report`]. In strict mode exact duplicates fail
+    /// initialization before any embedding is requested.
     pub async fn initialize(&self) -> Result<(), SemanticDetectionError> {
-        let templates = self.load_templates()?;
+        let bank = self.load_bank()?;
+        // Categories are checked before any embedding is requested
+        let categories = bank
+            .templates
+            .iter()
+            .map(|template| resolved_category(&bank, template))
+            .collect::<Result<Vec<_>, _>>()?;
+        let templates = bank.templates;
         info!("Loaded {} attack templates from bank", templates.len());
+        let fingerprint = content_hash(&templates);
+        if self.hygiene.strict {
+            let duplicates = hygiene::exact_duplicates(&templates);
+            if !duplicates.is_empty() {
+                let groups: Vec<String> = duplicates
+                    .iter()
+                    .map(|group| group.template_ids.join("/"))
+                    .collect();
+                error!("Attack bank has exact duplicates: {}", groups.join(", "));
+                return Err(SemanticDetectionError::DuplicateTemplateText(
+                    groups.join(", "),
+                ));
+            }
+        }
 
         let mut cached = Vec::with_capacity(templates.len());
-        for template in templates {
+        for (template, threat_category) in templates.into_iter().zip(categories) {
             debug!("Computing embedding for template {}", template.id);
             let embedding = self.compute_embedding(&template.text).await?;
             cached.push(CachedTemplate {
+                threat_category,
                 id: template.id,
                 category: template.category,
                 text: template.text,
@@ -57,8 +140,13 @@ impl SemanticDetectionService {
             });
         }
 
+        let report = hygiene::analyze(&cached, &self.hygiene);
+        hygiene::log_report(&report);
+        *self.hygiene_report.write().unwrap() = Some(report);
+
         let mut cache = self.cached_templates.write().await;
         *cache = cached;
+        *self.bank_fingerprint.write().unwrap() = Some(fingerprint);
         let mut init = self.initialized.write().await;
         *init = true;
 
@@ -69,12 +157,130 @@ impl SemanticDetectionService {
         Ok(())
     }
 
+    /// Thresholds currently in effect
+    pub fn thresholds(&self) -> SemanticThresholds {
+        *self.thresholds.read().unwrap()
+    }
+
+    pub(crate) fn thresholds_lock(&self) -> &std::sync::RwLock<SemanticThresholds> {
+        &self.thresholds
+    }
+
+    /// Content hash of the loaded attack template bank, once initialized
+    pub fn bank_fingerprint(&self) -> Option<String> {
+        self.bank_fingerprint.read().unwrap().clone()
+    }
+
+    /// Latest duplicate report, from initialization or [`Self::analyze_bank`]
+    pub fn bank_report(&self) -> Option<BankHygieneReport> {
+        self.hygiene_report.read().unwrap().clone()
+    }
+
+    /// Re-run the duplicate checks against the bank file
+    ///
+    /// Embeddings already cached for an unchanged template are reused; only new or edited
+    /// templates are sent to Mistral. The live bank is left as it is.
+    pub async fn analyze_bank(&self) -> Result<BankHygieneReport, SemanticDetectionError> {
+        let bank = self.load_bank()?;
+        let mut analyzed = Vec::with_capacity(bank.templates.len());
+        for template in bank.templates.iter().cloned() {
+            let cached = self
+                .cached_templates
+                .read()
+                .await
+                .iter()
+                .find(|cached| cached.id == template.id && cached.text == template.text)
+                .map(|cached| cached.embedding.clone());
+            let embedding = match cached {
+                Some(embedding) => embedding,
+                None => self.compute_embedding(&template.text).await?,
+            };
+            analyzed.push(CachedTemplate {
+                threat_category: resolved_category(&bank, &template)?,
+                id: template.id,
+                category: template.category,
+                text: template.text,
+                embedding,
+            });
+        }
+
+        let report = hygiene::analyze(&analyzed, &self.hygiene);
+        hygiene::log_report(&report);
+        *self.hygiene_report.write().unwrap() = Some(report.clone());
+        Ok(report)
+    }
+
     /// Check if service is initialized
     pub async fn is_initialized(&self) -> bool {
         *self.initialized.read().await
     }
 
+    /// Templates in the live bank
+    pub async fn templates(&self) -> Vec<AttackTemplate> {
+        self.cached_templates
+            .read()
+            .await
+            .iter()
+            .map(|cached| AttackTemplate {
+                id: cached.id.clone(),
+                category: cached.category.clone(),
+                text: cached.text.clone(),
+            })
+            .collect()
+    }
+
+    pub async fn template_count(&self) -> usize {
+        self.cached_templates.read().await.len()
+    }
+
+    /// Add a template to the live bank and to the bank file
+    ///
+    /// The embedding is computed before anything is written, so a Mistral failure leaves
+    /// both unchanged. Returns the number of templates in the bank afterwards.
+    pub async fn add_template(
+        &self,
+        template: AttackTemplate,
+    ) -> Result<usize, SemanticDetectionError> {
+        if !self.is_initialized().await {
+            return Err(SemanticDetectionError::NotInitialized);
+        }
+        let embedding = self.compute_embedding(&template.text).await?;
+
+        // Holding the cache lock keeps concurrent additions from losing each other's writes
+        let mut cache = self.cached_templates.write().await;
+        let path = self.resolved_bank_path();
+        let mut bank = read_bank(&path)?;
+        if bank
+            .templates
+            .iter()
+            .any(|existing| existing.id == template.id)
+            || cache.iter().any(|cached| cached.id == template.id)
+        {
+            return Err(SemanticDetectionError::DuplicateTemplate(template.id));
+        }
+        let threat_category = resolved_category(&bank, &template)?;
+        bank.templates.push(template.clone());
+        let content = serde_json::to_string_pretty(&bank)
+            .map_err(|e| SemanticDetectionError::ParseError(e.to_string()))?;
+        std::fs::write(&path, content)
+            .map_err(|e| SemanticDetectionError::IoError(e.to_string()))?;
+
+        info!("Added attack template {} to the bank", template.id);
+        cache.push(CachedTemplate {
+            threat_category,
+            id: template.id,
+            category: template.category,
+            text: template.text,
+            embedding,
+        });
+        *self.bank_fingerprint.write().unwrap() = Some(content_hash(&bank.templates));
+        Ok(cache.len())
+    }
+
     /// Scan text for semantic similarity to attack templates
+    ///
+    /// Text longer than the chunking policy's `min_prompt_chars` is embedded window by
+    /// window and scored by its closest window, which `matched_window` then reports.
     pub async fn scan(
         &self,
         request: SemanticScanRequest,
@@ -85,9 +291,18 @@ impl SemanticDetectionService {
         }
 
         // Translate to English if needed for semantic analysis
-        let text_to_analyze = self.translate_if_needed(&request.text).await;
+        let text_to_analyze = match &request.detected_language {
+            // The caller detected the language and translated where needed
+            Some(_) => request.pre_translated_text.unwrap_or(request.text),
+            None => self.translate_if_needed(&request.text).await,
+        };
 
-        let input_embedding = self.compute_embedding(&text_to_analyze).await?;
+        let windows = self.chunking.windows(text_to_analyze.chars().count());
+        let input_embeddings = if windows.is_empty() {
+            vec![(None, self.embed_input(&text_to_analyze).await?)]
+        } else {
+            self.embed_windows(&text_to_analyze, &windows).await?
+        };
         let cache = self.cached_templates.read().await;
 
         if cache.is_empty() {
@@ -95,22 +310,24 @@ impl SemanticDetectionService {
             return Ok(SemanticScanResult::low_risk());
         }
 
-        // Find highest similarity match
-        let mut best_match: Option<(&CachedTemplate, f32)> = None;
-        for template in cache.iter() {
-            let similarity = cosine_similarity(&input_embedding, &template.embedding);
-            if best_match.is_none() || similarity > best_match.unwrap().1 {
-                best_match = Some((template, similarity));
+        // Find highest similarity match; on ties the earliest window wins
+        let mut best_match: Option<(&CachedTemplate, f32, Option<Window>)> = None;
+        for (window, input_embedding) in &input_embeddings {
+            for template in cache.iter() {
+                let similarity = cosine_similarity(input_embedding, &template.embedding);
+                if best_match.is_none_or(|(_, best, _)| similarity > best) {
+                    best_match = Some((template, similarity, *window));
+                }
             }
         }
 
-        let (template, similarity) = best_match.unwrap();
+        let (template, similarity, matched_window) = best_match.unwrap();
         let risk_level = self.classify_risk(similarity);
         let risk_score = similarity;
 
         debug!(
-            "Semantic scan: similarity={:.3}, template={}, category={}, risk={:?}",
-            similarity, template.id, template.category, risk_level
+            "Semantic scan: similarity={:.3}, template={}, category={}, risk={:?}, window={:?}",
+            similarity, template.id, template.category, risk_level, matched_window
         );
 
         Ok(SemanticScanResult {
@@ -119,40 +336,109 @@ impl SemanticDetectionService {
             nearest_template_id: Some(template.id.clone()),
             similarity,
             category: Some(template.category.clone()),
+            threat_category: Some(template.threat_category.clone()),
+            matched_window,
         })
     }
 
-    fn load_templates(&self) -> Result<Vec<AttackTemplate>, SemanticDetectionError> {
-        let config_path = std::env::var("SEMANTIC_ATTACK_BANK_PATH")
-            .unwrap_or_else(|_| "config/semantic_attack_bank.json".to_string());
+    /// Embed each window of `text`, at most `max_concurrency` at a time
+    ///
+    /// Windows with the same text are embedded once.
+    async fn embed_windows(
+        &self,
+        text: &str,
+        windows: &[Window],
+    ) -> Result<Vec<(Option<Window>, Vec<f32>)>, SemanticDetectionError> {
+        let mut byte_offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+        byte_offsets.push(text.len());
+        let chunks: Vec<&str> = windows
+            .iter()
+            .map(|&(start, end)| &text[byte_offsets[start]..byte_offsets[end]])
+            .collect();
+
+        let mut unique: HashMap<&str, usize> = HashMap::new();
+        let mut tasks = JoinSet::new();
+        let permits = Arc::new(Semaphore::new(self.chunking.max_concurrency));
+        for chunk in &chunks {
+            if unique.contains_key(chunk) {
+                continue;
+            }
+            let index = unique.len();
+            unique.insert(chunk, index);
+            let service = self.clone();
+            let permits = permits.clone();
+            let chunk = chunk.to_string();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, service.embed_input(&chunk).await)
+            });
+        }
+        debug!(
+            "Semantic scan of {} characters split into {} windows",
+            byte_offsets.len() - 1,
+            windows.len()
+        );
 
-        let path = Path::new(&config_path);
-        if !path.exists() {
-            error!("Attack template bank not found at {:?}", path);
-            return Err(SemanticDetectionError::ConfigNotFound(config_path));
+        let mut embeddings = vec![Vec::new(); unique.len()];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, embedding) =
+                joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            // Returning early drops the set, which aborts the remaining requests
+            embeddings[index] = embedding?;
         }
+        Ok(windows
+            .iter()
+            .zip(&chunks)
+            .map(|(&window, chunk)| (Some(window), embeddings[unique[chunk]].clone()))
+            .collect())
+    }
 
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| SemanticDetectionError::IoError(e.to_string()))?;
+    fn load_bank(&self) -> Result<AttackTemplateBank, SemanticDetectionError> {
+        read_bank(&self.resolved_bank_path())
+    }
 
-        let bank: AttackTemplateBank = serde_json::from_str(&content)
-            .map_err(|e| SemanticDetectionError::ParseError(e.to_string()))?;
+    fn resolved_bank_path(&self) -> PathBuf {
+        self.bank_path.clone().unwrap_or_else(|| {
+            configured_path("SEMANTIC_ATTACK_BANK_PATH", DEFAULT_ATTACK_BANK_PATH).into()
+        })
+    }
 
-        Ok(bank.templates)
+    /// Embed scanned text, reusing the embedding of the same text scanned recently
+    async fn embed_input(&self, text: &str) -> Result<Vec<f32>, SemanticDetectionError> {
+        if let Some(embedding) = self.embedding_cache.get(text) {
+            return Ok(embedding);
+        }
+        let embedding = self.compute_embedding(text).await?;
+        self.embedding_cache.insert(text, embedding.clone());
+        Ok(embedding)
     }
 
-    async fn compute_embedding(&self, text: &str) -> Result<Vec<f32>, SemanticDetectionError> {
+    pub(crate) async fn compute_embedding(
+        &self,
+        text: &str,
+    ) -> Result<Vec<f32>, SemanticDetectionError> {
         let response = self.mistral_service.embed_text(text).await?;
         Ok(response.vector)
     }
 
     /// Classify risk level based on similarity score using configured thresholds
-    fn classify_risk(&self, similarity: f32) -> SemanticRiskLevel {
+    pub fn classify_risk(&self, similarity: f32) -> SemanticRiskLevel {
+        let thresholds = self.thresholds();
         classify_risk_with_margin(
             similarity,
-            self.medium_threshold,
-            self.high_threshold,
-            self.decision_margin,
+            thresholds.medium_threshold,
+            thresholds.high_threshold,
+            thresholds.decision_margin,
+        )
+    }
+
+    /// Scores at which risk becomes Medium and High, decision margin included
+    pub fn risk_cutoffs(&self) -> (f32, f32) {
+        let thresholds = self.thresholds();
+        risk_cutoffs(
+            thresholds.medium_threshold,
+            thresholds.high_threshold,
+            thresholds.decision_margin,
         )
     }
 
@@ -180,8 +466,30 @@ impl SemanticDetectionService {
     }
 }
 
+fn read_bank(path: &Path) -> Result<AttackTemplateBank, SemanticDetectionError> {
+    if !path.exists() {
+        error!("Attack template bank not found at {:?}", path);
+        return Err(SemanticDetectionError::ConfigNotFound(
+            path.display().to_string(),
+        ));
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| SemanticDetectionError::IoError(e.to_string()))?;
+
+    serde_json::from_str(&content).map_err(|e| SemanticDetectionError::ParseError(e.to_string()))
+}
+
+fn resolved_category(
+    bank: &AttackTemplateBank,
+    template: &AttackTemplate,
+) -> Result<CategoryRef, SemanticDetectionError> {
+    bank.threat_category(template)
+        .map_err(SemanticDetectionError::UnknownCategory)
+}
+
 /// Compute cosine similarity between two vectors
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() || a.is_empty() {
         return 0.0;
     }
@@ -203,10 +511,7 @@ fn classify_risk_with_margin(
     high_threshold: f32,
     margin: f32,
 ) -> SemanticRiskLevel {
-    let margin = normalize_margin(margin);
-    let medium_cutoff = (medium_threshold + margin).clamp(0.0, 1.0);
-    let high_base = high_threshold.max(medium_threshold);
-    let high_cutoff = (high_base + margin).clamp(medium_cutoff, 1.0);
+    let (medium_cutoff, high_cutoff) = risk_cutoffs(medium_threshold, high_threshold, margin);
 
     if similarity >= high_cutoff {
         SemanticRiskLevel::High
@@ -217,6 +522,14 @@ fn classify_risk_with_margin(
     }
 }
 
+fn risk_cutoffs(medium_threshold: f32, high_threshold: f32, margin: f32) -> (f32, f32) {
+    let margin = normalize_margin(margin);
+    let medium_cutoff = (medium_threshold + margin).clamp(0.0, 1.0);
+    let high_base = high_threshold.max(medium_threshold);
+    let high_cutoff = (high_base + margin).clamp(medium_cutoff, 1.0);
+    (medium_cutoff, high_cutoff)
+}
+
 fn normalize_margin(margin: f32) -> f32 {
     if !margin.is_finite() {
         return 0.0;
@@ -232,6 +545,14 @@ pub enum SemanticDetectionError {
     IoError(String),
     #[error("Failed to parse config: {0}")]
     ParseError(String),
+    #[error("Semantic detection service is not initialized")]
+    NotInitialized,
+    #[error("Attack template {0} already exists")]
+    DuplicateTemplate(String),
+    #[error("Attack bank has templates with the same text: {0}")]
+    DuplicateTemplateText(String),
+    #[error("Attack template category is not in the threat taxonomy: {0}")]
+    UnknownCategory(String),
     #[error("Embedding service error: {0}")]
     Embedding(#[from] MistralServiceError),
 }
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Service level objectives the sentinel measures itself against
+///
+/// Defaults: 99% of successful requests finish within 2s and 99.9% of requests do not
+/// fail with a 5xx status.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SloObjectives {
+    /// Successful requests slower than this count against the latency objective
+    pub latency_threshold_ms: u64,
+    /// Fraction of successful requests that must finish within the threshold
+    pub latency_target: f64,
+    /// Fraction of requests that must not fail with a 5xx status
+    pub availability_target: f64,
+}
+
+impl Default for SloObjectives {
+    fn default() -> Self {
+        Self {
+            latency_threshold_ms: 2_000,
+            latency_target: 0.99,
+            availability_target: 0.999,
+        }
+    }
+}
+
+impl SloObjectives {
+    /// Reject a zero threshold or targets outside the open interval 0.0..1.0
+    pub fn validate(&self) -> Result<(), String> {
+        if self.latency_threshold_ms == 0 {
+            return Err("SLO latency threshold must be greater than zero".to_owned());
+        }
+        for (name, target) in [
+            ("latency", self.latency_target),
+            ("availability", self.availability_target),
+        ] {
+            if !target.is_finite() || target <= 0.0 || target >= 1.0 {
+                return Err(format!(
+                    "SLO {name} target must be greater than 0.0 and less than 1.0"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn target(&self, objective: SloObjective) -> f64 {
+        match objective {
+            SloObjective::Latency => self.latency_target,
+            SloObjective::Availability => self.availability_target,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SloObjective {
+    Latency,
+    Availability,
+}
+
+impl SloObjective {
+    pub const ALL: [SloObjective; 2] = [SloObjective::Latency, SloObjective::Availability];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SloObjective::Latency => "latency",
+            SloObjective::Availability => "availability",
+        }
+    }
+}
+
+/// Urgency of a burn-rate alert
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    /// Budget burning fast enough to be gone within days
+    Page,
+    /// Sustained slower burn worth a look during working hours
+    Ticket,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Page => "page",
+            AlertSeverity::Ticket => "ticket",
+        }
+    }
+}
+
+/// SLIs and burn rates over one sliding window
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SloWindowStatus {
+    /// `5m`, `1h` or `6h`
+    pub window: String,
+    pub requests: u64,
+    /// Requests answered with a 5xx status
+    pub failures: u64,
+    /// Successful requests slower than the latency threshold
+    pub slow_requests: u64,
+    /// Fraction of successful requests within the threshold; `None` without any
+    pub latency_sli: Option<f64>,
+    /// Fraction of requests that did not fail; `None` without any
+    pub availability_sli: Option<f64>,
+    /// How many times faster than sustainable the latency budget is being spent
+    pub latency_burn_rate: f64,
+    pub availability_burn_rate: f64,
+    /// Upper bound of the histogram bucket holding the latency-target percentile;
+    /// `None` without successful requests or beyond the last bucket
+    pub latency_percentile_ms: Option<u64>,
+}
+
+impl SloWindowStatus {
+    pub fn burn_rate(&self, objective: SloObjective) -> f64 {
+        match objective {
+            SloObjective::Latency => self.latency_burn_rate,
+            SloObjective::Availability => self.availability_burn_rate,
+        }
+    }
+}
+
+/// A burn-rate alert whose long and short windows both exceed the threshold
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BurnRateAlert {
+    pub objective: SloObjective,
+    pub severity: AlertSeverity,
+    pub long_window: String,
+    pub short_window: String,
+    /// Burn rate over the long window
+    pub burn_rate: f64,
+    pub threshold: f64,
+}
+
+/// SLO state of one route, or of all of them together
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EndpointSloStatus {
+    /// Route template, or `all` for the overall figures
+    pub endpoint: String,
+    pub windows: Vec<SloWindowStatus>,
+    /// Share of the latency error budget left over the longest window; negative once
+    /// overspent
+    pub latency_budget_remaining: f64,
+    pub availability_budget_remaining: f64,
+    /// Burn-rate alerts currently firing
+    pub alerts: Vec<BurnRateAlert>,
+}
+
+impl EndpointSloStatus {
+    pub fn window(&self, window: &str) -> Option<&SloWindowStatus> {
+        self.windows.iter().find(|status| status.window == window)
+    }
+
+    pub fn budget_remaining(&self, objective: SloObjective) -> f64 {
+        match objective {
+            SloObjective::Latency => self.latency_budget_remaining,
+            SloObjective::Availability => self.availability_budget_remaining,
+        }
+    }
+}
+
+/// Response of `GET /api/slo/status`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SloStatusResponse {
+    pub objectives: SloObjectives,
+    pub generated_at: DateTime<Utc>,
+    pub overall: EndpointSloStatus,
+    /// Routes that served traffic within the longest window, ordered by route
+    pub endpoints: Vec<EndpointSloStatus>,
+}
@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use super::dtos::{
+    AlertSeverity, BurnRateAlert, EndpointSloStatus, SloObjective, SloObjectives,
+    SloStatusResponse, SloWindowStatus,
+};
+use crate::modules::telemetry::metrics::get_metrics;
+
+/// Resolution of the sliding windows
+const SLOT_SECS: u64 = 60;
+
+/// Sliding windows, longest last; it bounds how many slots a series keeps
+const WINDOWS: [(&str, u64); 3] = [("5m", 5), ("1h", 60), ("6h", 360)];
+const LONGEST_WINDOW_SLOTS: u64 = WINDOWS[WINDOWS.len() - 1].1;
+
+/// Upper bounds of the latency histogram buckets; slower requests land in an overflow
+/// bucket
+const LATENCY_BUCKETS_MS: [u64; 14] = [
+    5, 10, 25, 50, 100, 250, 500, 1_000, 2_000, 2_500, 5_000, 10_000, 30_000, 60_000,
+];
+const BUCKET_COUNT: usize = LATENCY_BUCKETS_MS.len() + 1;
+
+/// Multiwindow burn-rate alerts: `(severity, long window, short window, threshold)`
+///
+/// A page fires when 2% of a 30-day budget would go within an hour, a ticket when 5%
+/// would go within six hours. The short window stops the alert once the burn is over.
+const ALERT_RULES: [(AlertSeverity, &str, &str, f64); 2] = [
+    (AlertSeverity::Page, "1h", "5m", 14.4),
+    (AlertSeverity::Ticket, "6h", "1h", 6.0),
+];
+
+/// Route label of the figures covering every route
+pub const OVERALL_ENDPOINT: &str = "all";
+
+/// Counts for one minute of traffic
+#[derive(Clone)]
+struct Slot {
+    index: u64,
+    requests: u64,
+    failures: u64,
+    slow: u64,
+    /// Latencies of successful requests
+    histogram: [u64; BUCKET_COUNT],
+}
+
+impl Slot {
+    fn new(index: u64) -> Self {
+        Self {
+            index,
+            requests: 0,
+            failures: 0,
+            slow: 0,
+            histogram: [0; BUCKET_COUNT],
+        }
+    }
+
+    fn add(&mut self, other: &Slot) {
+        self.requests += other.requests;
+        self.failures += other.failures;
+        self.slow += other.slow;
+        for (total, count) in self.histogram.iter_mut().zip(other.histogram) {
+            *total += count;
+        }
+    }
+}
+
+/// Minute slots of one route, holding at most the longest window
+#[derive(Default)]
+struct Series {
+    slots: VecDeque<Slot>,
+}
+
+impl Series {
+    fn record(&mut self, index: u64, latency_ms: u64, failed: bool, slow: bool) {
+        let position = match self.slots.iter().rposition(|slot| slot.index <= index) {
+            Some(position) if self.slots[position].index == index => position,
+            Some(position) => {
+                self.slots.insert(position + 1, Slot::new(index));
+                position + 1
+            }
+            None => {
+                self.slots.push_front(Slot::new(index));
+                0
+            }
+        };
+        let slot = &mut self.slots[position];
+        slot.requests += 1;
+        if failed {
+            slot.failures += 1;
+        } else {
+            if slow {
+                slot.slow += 1;
+            }
+            slot.histogram[bucket_for(latency_ms)] += 1;
+        }
+        self.prune(index);
+    }
+
+    /// Drop slots that fell out of the longest window
+    fn prune(&mut self, current: u64) {
+        let oldest = (current + 1).saturating_sub(LONGEST_WINDOW_SLOTS);
+        while self.slots.front().is_some_and(|slot| slot.index < oldest) {
+            self.slots.pop_front();
+        }
+    }
+
+    /// Totals over the `slots` minutes ending with `current`
+    fn aggregate(&self, current: u64, slots: u64) -> Slot {
+        let oldest = (current + 1).saturating_sub(slots);
+        let mut total = Slot::new(current);
+        for slot in self
+            .slots
+            .iter()
+            .filter(|slot| slot.index >= oldest && slot.index <= current)
+        {
+            total.add(slot);
+        }
+        total
+    }
+
+    fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct SloState {
+    overall: Series,
+    endpoints: BTreeMap<String, Series>,
+}
+
+/// Sliding-window SLI and error-budget tracking, fed by the request-context middleware
+///
+/// Memory stays bounded: each route keeps at most one fixed-size histogram per minute of
+/// the longest window, whatever the traffic.
+#[derive(Clone)]
+pub struct SloTracker {
+    objectives: SloObjectives,
+    origin: Instant,
+    state: Arc<Mutex<SloState>>,
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self::new(SloObjectives::default())
+    }
+}
+
+impl SloTracker {
+    pub fn new(objectives: SloObjectives) -> Self {
+        Self {
+            objectives,
+            origin: Instant::now(),
+            state: Arc::new(Mutex::new(SloState::default())),
+        }
+    }
+
+    pub fn objectives(&self) -> SloObjectives {
+        self.objectives
+    }
+
+    /// Record a finished request; `failed` marks a 5xx response
+    pub fn record(&self, endpoint: &str, latency: Duration, failed: bool) {
+        self.record_at(endpoint, latency, failed, Instant::now());
+    }
+
+    /// Record a request that finished at `at` and refresh the route's gauges
+    pub fn record_at(&self, endpoint: &str, latency: Duration, failed: bool, at: Instant) {
+        let index = self.slot_index(at);
+        let latency_ms = latency.as_millis().try_into().unwrap_or(u64::MAX);
+        let slow = latency_ms > self.objectives.latency_threshold_ms;
+        let (overall, route) = {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            state.overall.record(index, latency_ms, failed, slow);
+            let series = state.endpoints.entry(endpoint.to_owned()).or_default();
+            series.record(index, latency_ms, failed, slow);
+            (
+                self.endpoint_status(OVERALL_ENDPOINT, &state.overall, index),
+                self.endpoint_status(endpoint, series, index),
+            )
+        };
+        publish(&overall);
+        publish(&route);
+    }
+
+    pub fn status(&self) -> SloStatusResponse {
+        self.status_at(Instant::now())
+    }
+
+    /// SLIs, budgets and alerts as of `at`
+    ///
+    /// Routes without traffic in the longest window are left out.
+    pub fn status_at(&self, at: Instant) -> SloStatusResponse {
+        let index = self.slot_index(at);
+        let mut state = self.state.lock().unwrap();
+        state.overall.prune(index);
+        for series in state.endpoints.values_mut() {
+            series.prune(index);
+        }
+        SloStatusResponse {
+            objectives: self.objectives,
+            generated_at: Utc::now(),
+            overall: self.endpoint_status(OVERALL_ENDPOINT, &state.overall, index),
+            endpoints: state
+                .endpoints
+                .iter()
+                .filter(|(_, series)| !series.is_empty())
+                .map(|(endpoint, series)| self.endpoint_status(endpoint, series, index))
+                .collect(),
+        }
+    }
+
+    /// Recompute every gauge, so windows keep sliding while no requests arrive
+    ///
+    /// Idle routes are published too, so their gauges return to a clean state.
+    pub fn refresh_metrics(&self) {
+        let index = self.slot_index(Instant::now());
+        let statuses: Vec<EndpointSloStatus> = {
+            let mut state = self.state.lock().unwrap();
+            state.overall.prune(index);
+            let overall = self.endpoint_status(OVERALL_ENDPOINT, &state.overall, index);
+            std::iter::once(overall)
+                .chain(state.endpoints.iter_mut().map(|(endpoint, series)| {
+                    series.prune(index);
+                    self.endpoint_status(endpoint, series, index)
+                }))
+                .collect()
+        };
+        for status in &statuses {
+            publish(status);
+        }
+    }
+
+    pub fn spawn_refresher(&self, interval: Duration) -> JoinHandle<()> {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                tracker.refresh_metrics();
+            }
+        })
+    }
+
+    fn slot_index(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.origin).as_secs() / SLOT_SECS
+    }
+
+    fn endpoint_status(&self, endpoint: &str, series: &Series, index: u64) -> EndpointSloStatus {
+        let windows: Vec<SloWindowStatus> = WINDOWS
+            .iter()
+            .map(|(name, slots)| self.window_status(name, &series.aggregate(index, *slots)))
+            .collect();
+        let longest = &windows[windows.len() - 1];
+        let alerts = ALERT_RULES
+            .iter()
+            .flat_map(|&(severity, long, short, threshold)| {
+                let long = windows.iter().find(|window| window.window == long);
+                let short = windows.iter().find(|window| window.window == short);
+                SloObjective::ALL
+                    .into_iter()
+                    .filter_map(move |objective| match (long, short) {
+                        (Some(long), Some(short))
+                            if long.burn_rate(objective) >= threshold
+                                && short.burn_rate(objective) >= threshold =>
+                        {
+                            Some(BurnRateAlert {
+                                objective,
+                                severity,
+                                long_window: long.window.clone(),
+                                short_window: short.window.clone(),
+                                burn_rate: long.burn_rate(objective),
+                                threshold,
+                            })
+                        }
+                        _ => None,
+                    })
+            })
+            .collect();
+        EndpointSloStatus {
+            endpoint: endpoint.to_owned(),
+            latency_budget_remaining: 1.0 - longest.latency_burn_rate,
+            availability_budget_remaining: 1.0 - longest.availability_burn_rate,
+            alerts,
+            windows,
+        }
+    }
+
+    fn window_status(&self, window: &str, totals: &Slot) -> SloWindowStatus {
+        let successes = totals.requests - totals.failures;
+        let latency_bad = ratio(totals.slow, successes);
+        let availability_bad = ratio(totals.failures, totals.requests);
+        SloWindowStatus {
+            window: window.to_owned(),
+            requests: totals.requests,
+            failures: totals.failures,
+            slow_requests: totals.slow,
+            latency_sli: latency_bad.map(|bad| 1.0 - bad),
+            availability_sli: availability_bad.map(|bad| 1.0 - bad),
+            latency_burn_rate: burn_rate(latency_bad, self.objectives.latency_target),
+            availability_burn_rate: burn_rate(
+                availability_bad,
+                self.objectives.availability_target,
+            ),
+            latency_percentile_ms: percentile(
+                &totals.histogram,
+                successes,
+                self.objectives.latency_target,
+            ),
+        }
+    }
+}
+
+fn bucket_for(latency_ms: u64) -> usize {
+    LATENCY_BUCKETS_MS
+        .iter()
+        .position(|bound| latency_ms <= *bound)
+        .unwrap_or(LATENCY_BUCKETS_MS.len())
+}
+
+fn ratio(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+/// Observed bad fraction over the fraction the target allows
+fn burn_rate(bad: Option<f64>, target: f64) -> f64 {
+    bad.map_or(0.0, |bad| bad / (1.0 - target))
+}
+
+fn percentile(histogram: &[u64; BUCKET_COUNT], total: u64, quantile: f64) -> Option<u64> {
+    if total == 0 {
+        return None;
+    }
+    let rank = ((quantile * total as f64).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (bucket, count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return LATENCY_BUCKETS_MS.get(bucket).copied();
+        }
+    }
+    None
+}
+
+fn publish(status: &EndpointSloStatus) {
+    let metrics = get_metrics();
+    for window in &status.windows {
+        metrics.set_slo_sli(
+            SloObjective::Latency.as_str(),
+            &status.endpoint,
+            &window.window,
+            window.latency_sli.unwrap_or(1.0),
+        );
+        metrics.set_slo_sli(
+            SloObjective::Availability.as_str(),
+            &status.endpoint,
+            &window.window,
+            window.availability_sli.unwrap_or(1.0),
+        );
+        for objective in SloObjective::ALL {
+            metrics.set_slo_burn_rate(
+                objective.as_str(),
+                &status.endpoint,
+                &window.window,
+                window.burn_rate(objective),
+            );
+        }
+    }
+    for objective in SloObjective::ALL {
+        metrics.set_slo_error_budget_remaining(
+            objective.as_str(),
+            &status.endpoint,
+            status.budget_remaining(objective),
+        );
+        for (severity, ..) in ALERT_RULES {
+            let firing = status
+                .alerts
+                .iter()
+                .any(|alert| alert.objective == objective && alert.severity == severity);
+            metrics.set_slo_burn_rate_alert(
+                objective.as_str(),
+                severity.as_str(),
+                &status.endpoint,
+                firing,
+            );
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::net::IpAddr;
+use std::time::Instant;
+
+/// Header carrying the correlation id on requests and responses
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Header carrying the API version on responses
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// W3C Trace Context header naming the caller's trace and span
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+tokio::task_local! {
+    /// Client the current request came from, for per-client log sampling
+    pub(crate) static CLIENT_ID: String;
+}
+
+/// Client of the request being handled on this task, when its peer address is known
+pub fn current_client_id() -> Option<String> {
+    CLIENT_ID.try_with(Clone::clone).ok()
+}
+
+/// Per-request metadata inserted as an axum `Extension` for every route
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    /// Caller-supplied `X-Correlation-Id` when the correlation id policy accepts it,
+    /// otherwise generated
+    pub correlation_id: String,
+    pub started_at: Instant,
+    /// Matched route template such as `/api/config/history`
+    pub route: String,
+    /// Peer address, when the server was started with connect info
+    pub client_ip: Option<IpAddr>,
+    /// Caller's trace from a well-formed `traceparent` header
+    pub trace_parent: Option<TraceParent>,
+}
+
+/// Parsed W3C `traceparent`: `{version}-{trace-id}-{parent-id}-{flags}` in lowercase hex
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceParent {
+    /// 32 hex digits
+    pub trace_id: String,
+    /// 16 hex digits naming the caller's span
+    pub parent_span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    /// `None` for malformed headers, unknown version `ff` and all-zero ids, which the
+    /// specification says to ignore
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_span_id = parts.next()?;
+        let flags = parts.next()?;
+        // Version 00 has exactly four fields; later versions may append more
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        let is_hex = |field: &str, len: usize| {
+            field.len() == len
+                && field
+                    .bytes()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        let is_zero = |field: &str| field.bytes().all(|b| b == b'0');
+        if !is_hex(version, 2)
+            || version == "ff"
+            || !is_hex(trace_id, 32)
+            || is_zero(trace_id)
+            || !is_hex(parent_span_id, 16)
+            || is_zero(parent_span_id)
+            || !is_hex(flags, 2)
+        {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_owned(),
+            parent_span_id: parent_span_id.to_owned(),
+            sampled: flags & 0x01 == 1,
+        })
+    }
+}
+
+impl RequestContext {
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.started_at.elapsed().as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_w3c_traceparent() {
+        let parent =
+            TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(parent.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.parent_span_id, "00f067aa0ba902b7");
+        assert!(parent.sampled);
+        assert!(
+            !TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00")
+                .unwrap()
+                .sampled
+        );
+        // Later versions may carry extra fields
+        assert!(
+            TraceParent::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra")
+                .is_some()
+        );
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceParent::parse(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -1,12 +1,52 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
-static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(1);
+/// Default longest caller-supplied correlation id
+pub const DEFAULT_MAX_CORRELATION_ID_LENGTH: usize = 128;
+
+/// Longest configurable `INSTANCE_ID`
+pub const MAX_INSTANCE_ID_LENGTH: usize = 32;
+
+/// Counter values reserved by each write of the high-water mark
+///
+/// A restart skips what was left of the last block, so the counter never goes back
+/// without a write per generated id.
+pub const COUNTER_BLOCK: u64 = 1_000;
+
+#[cfg(feature = "sled-storage")]
+const CORRELATION_META_TREE: &str = "correlation_meta";
+#[cfg(feature = "sled-storage")]
+const INSTANCE_ID_KEY: &str = "instance_id";
+#[cfg(feature = "sled-storage")]
+const HIGH_WATER_KEY: &str = "counter_high_water";
 
+static GENERATOR: LazyLock<RwLock<Arc<CorrelationIdGenerator>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(CorrelationIdGenerator::ephemeral())));
+
+/// A new id from the generator installed with [`install_correlation_id_generator`]
+///
+/// Until one is installed ids come from a generator with a random instance id whose
+/// counter starts over with the process.
 pub fn generate_correlation_id() -> String {
-    let counter = REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let uuid = Uuid::new_v4();
-    format!("{}-{}", uuid, counter)
+    let generator = GENERATOR.read().unwrap().clone();
+    generator.generate()
+}
+
+/// Generate every later id with `generator`
+pub fn install_correlation_id_generator(generator: CorrelationIdGenerator) {
+    *GENERATOR.write().unwrap() = Arc::new(generator);
+}
+
+/// Instance id stamped into the ids this process generates
+pub fn instance_id() -> String {
+    GENERATOR.read().unwrap().instance_id().to_owned()
 }
 
 pub fn generate_correlation_id_from_request(request_id: Option<String>) -> String {
@@ -15,3 +55,464 @@ pub fn generate_correlation_id_from_request(request_id: Option<String>) -> Strin
         _ => generate_correlation_id(),
     }
 }
+
+/// Generates `<unix millis>-<instance id>-<counter>` correlation ids
+///
+/// The 13-digit timestamp makes ids sort by creation time, the instance id tells
+/// replicas apart and the counter keeps increasing across restarts of an instance that
+/// persists its state.
+pub struct CorrelationIdGenerator {
+    instance_id: String,
+    counter: Mutex<CounterBlock>,
+    store: Arc<dyn CorrelationIdStore>,
+}
+
+/// Next counter value and the end of the block reserved in the store
+struct CounterBlock {
+    next: u64,
+    reserved: u64,
+}
+
+impl CorrelationIdGenerator {
+    /// Resume the instance id and counter kept in `store`
+    ///
+    /// `instance_id` replaces the stored id when given. Without either a random id is
+    /// made up and stored, so the instance keeps it across restarts.
+    pub fn open(
+        store: Arc<dyn CorrelationIdStore>,
+        instance_id: Option<&str>,
+    ) -> Result<Self, CorrelationIdStoreError> {
+        let instance_id = match instance_id {
+            Some(id) => id.to_owned(),
+            None => match store.instance_id()? {
+                Some(id) => id,
+                None => {
+                    let id = random_instance_id();
+                    store.set_instance_id(&id)?;
+                    id
+                }
+            },
+        };
+        let high_water = store.high_water()?;
+        Ok(Self {
+            instance_id,
+            counter: Mutex::new(CounterBlock {
+                next: high_water.max(1),
+                reserved: high_water,
+            }),
+            store,
+        })
+    }
+
+    fn ephemeral() -> Self {
+        Self {
+            instance_id: random_instance_id(),
+            counter: Mutex::new(CounterBlock {
+                next: 1,
+                reserved: 0,
+            }),
+            store: Arc::new(InMemoryCorrelationIdStore::new()),
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn generate(&self) -> String {
+        let mut counter = self.counter.lock().unwrap();
+        if counter.next >= counter.reserved {
+            counter.reserved = counter.next + COUNTER_BLOCK;
+            // The timestamp still keeps ids unique if the reservation is lost
+            if let Err(e) = self.store.set_high_water(counter.reserved) {
+                warn!("Failed to persist the correlation id counter: {}", e);
+            }
+        }
+        let value = counter.next;
+        counter.next += 1;
+        // Taken under the lock so an instance's ids sort in counter order
+        let millis = Utc::now().timestamp_millis();
+        format!("{:013}-{}-{}", millis, self.instance_id, value)
+    }
+}
+
+fn random_instance_id() -> String {
+    Uuid::new_v4().simple().to_string()[..8].to_owned()
+}
+
+/// Reject instance ids that would not fit or parse back out of a correlation id
+pub fn validate_instance_id(instance_id: &str) -> Result<(), String> {
+    if instance_id.is_empty()
+        || instance_id.len() > MAX_INSTANCE_ID_LENGTH
+        || !is_plain_token(instance_id)
+    {
+        return Err(format!(
+            "instance id {instance_id:?} must be 1 to {MAX_INSTANCE_ID_LENGTH} plain characters"
+        ));
+    }
+    Ok(())
+}
+
+/// Where a generator keeps its instance id and counter high-water mark across restarts
+pub trait CorrelationIdStore: Send + Sync {
+    fn instance_id(&self) -> Result<Option<String>, CorrelationIdStoreError>;
+    fn set_instance_id(&self, instance_id: &str) -> Result<(), CorrelationIdStoreError>;
+    /// Lowest counter value not yet handed out, or 0 for a new instance
+    fn high_water(&self) -> Result<u64, CorrelationIdStoreError>;
+    fn set_high_water(&self, high_water: u64) -> Result<(), CorrelationIdStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum CorrelationIdStoreError {
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("stored correlation id state is corrupt: {0}")]
+    Corrupt(String),
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryCorrelationIdStore {
+    inner: Arc<Mutex<(Option<String>, u64)>>,
+}
+
+impl InMemoryCorrelationIdStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CorrelationIdStore for InMemoryCorrelationIdStore {
+    fn instance_id(&self) -> Result<Option<String>, CorrelationIdStoreError> {
+        Ok(self.inner.lock().unwrap().0.clone())
+    }
+
+    fn set_instance_id(&self, instance_id: &str) -> Result<(), CorrelationIdStoreError> {
+        self.inner.lock().unwrap().0 = Some(instance_id.to_owned());
+        Ok(())
+    }
+
+    fn high_water(&self) -> Result<u64, CorrelationIdStoreError> {
+        Ok(self.inner.lock().unwrap().1)
+    }
+
+    fn set_high_water(&self, high_water: u64) -> Result<(), CorrelationIdStoreError> {
+        self.inner.lock().unwrap().1 = high_water;
+        Ok(())
+    }
+}
+
+/// Generator state kept in its own tree of the audit database
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledCorrelationIdStore {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledCorrelationIdStore {
+    pub fn new(db: &Db) -> Result<Self, CorrelationIdStoreError> {
+        let tree = db
+            .open_tree(CORRELATION_META_TREE)
+            .map_err(|e| CorrelationIdStoreError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+
+    fn get(&self, key: &str) -> Result<Option<sled::IVec>, CorrelationIdStoreError> {
+        self.tree
+            .get(key)
+            .map_err(|e| CorrelationIdStoreError::DatabaseError(e.to_string()))
+    }
+
+    /// Write `value` and flush, since a lost high-water mark lets the counter go back
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), CorrelationIdStoreError> {
+        self.tree
+            .insert(key, value)
+            .and_then(|_| self.tree.flush())
+            .map_err(|e| CorrelationIdStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl CorrelationIdStore for SledCorrelationIdStore {
+    fn instance_id(&self) -> Result<Option<String>, CorrelationIdStoreError> {
+        self.get(INSTANCE_ID_KEY)?
+            .map(|value| {
+                String::from_utf8(value.to_vec())
+                    .map_err(|e| CorrelationIdStoreError::Corrupt(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn set_instance_id(&self, instance_id: &str) -> Result<(), CorrelationIdStoreError> {
+        self.put(INSTANCE_ID_KEY, instance_id.as_bytes())
+    }
+
+    fn high_water(&self) -> Result<u64, CorrelationIdStoreError> {
+        match self.get(HIGH_WATER_KEY)? {
+            Some(value) => value
+                .as_ref()
+                .try_into()
+                .map(u64::from_be_bytes)
+                .map_err(|_| {
+                    CorrelationIdStoreError::Corrupt("counter high-water mark".to_owned())
+                }),
+            None => Ok(0),
+        }
+    }
+
+    fn set_high_water(&self, high_water: u64) -> Result<(), CorrelationIdStoreError> {
+        self.put(HIGH_WATER_KEY, &high_water.to_be_bytes())
+    }
+}
+
+/// What a generated correlation id tells about where and when it was made
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedId<'a> {
+    /// `None` for ids in the older `<uuid>-<counter>` format
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` for ids in the older `<uuid>-<counter>` format
+    pub instance_id: Option<&'a str>,
+    pub counter: u64,
+}
+
+/// Parse an id in either the current `<unix millis>-<instance id>-<counter>` format or
+/// the older `<uuid>-<counter>` one
+pub fn parse_generated_id(id: &str) -> Option<GeneratedId<'_>> {
+    if let Some((uuid, counter)) = id.split_at_checked(36)
+        && Uuid::parse_str(uuid).is_ok()
+    {
+        return Some(GeneratedId {
+            created_at: None,
+            instance_id: None,
+            counter: parse_counter(counter.strip_prefix('-')?)?,
+        });
+    }
+    let (millis, rest) = id.split_once('-')?;
+    let (instance_id, counter) = rest.rsplit_once('-')?;
+    if millis.len() != 13 || !millis.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    validate_instance_id(instance_id).ok()?;
+    Some(GeneratedId {
+        created_at: Some(DateTime::from_timestamp_millis(millis.parse().ok()?)?),
+        instance_id: Some(instance_id),
+        counter: parse_counter(counter)?,
+    })
+}
+
+fn parse_counter(digits: &str) -> Option<u64> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Which caller-supplied correlation ids are kept as is
+///
+/// Ids end up in logs, spans and the audit trail, so only short plain tokens are
+/// accepted: ASCII letters, digits and `-_.:`. Anything else is replaced by a generated
+/// id. Ids this service generated itself are always accepted, so an id handed out by the
+/// request middleware survives the workflow's own check.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CorrelationIdPolicy {
+    pub max_length: usize,
+    /// Accepted prefixes such as `checkout-`; empty accepts any well-formed id
+    pub allowed_prefixes: Vec<String>,
+}
+
+impl Default for CorrelationIdPolicy {
+    fn default() -> Self {
+        Self {
+            max_length: DEFAULT_MAX_CORRELATION_ID_LENGTH,
+            allowed_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl CorrelationIdPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_length == 0 {
+            return Err("correlation id max length must be positive".to_owned());
+        }
+        if let Some(prefix) = self
+            .allowed_prefixes
+            .iter()
+            .find(|prefix| prefix.is_empty() || !is_plain_token(prefix))
+        {
+            return Err(format!(
+                "correlation id prefix {prefix:?} must be a non-empty plain token"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn accepts(&self, id: &str) -> bool {
+        if is_generated(id) {
+            return true;
+        }
+        !id.is_empty()
+            && id.len() <= self.max_length
+            && is_plain_token(id)
+            && (self.allowed_prefixes.is_empty()
+                || self
+                    .allowed_prefixes
+                    .iter()
+                    .any(|prefix| id.starts_with(prefix.as_str())))
+    }
+
+    /// The supplied id when the policy accepts it, otherwise a generated one
+    ///
+    /// A rejected id is logged once at debug level, cut to the length limit, so a client
+    /// sending junk ids can still be traced without its ids flooding the logs.
+    pub fn normalize(&self, supplied: Option<String>) -> String {
+        match supplied {
+            Some(id) if self.accepts(&id) => id,
+            Some(id) if !id.is_empty() => {
+                let generated = generate_correlation_id();
+                let shown: String = id.chars().take(self.max_length).collect();
+                debug!(
+                    correlation_id = %generated,
+                    "Replaced out-of-policy correlation id {:?} ({} bytes)",
+                    shown,
+                    id.len()
+                );
+                generated
+            }
+            _ => generate_correlation_id(),
+        }
+    }
+}
+
+fn is_plain_token(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// Shape of [`generate_correlation_id`], current or older
+fn is_generated(id: &str) -> bool {
+    parse_generated_id(id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_short_plain_correlation_ids() {
+        let policy = CorrelationIdPolicy::default();
+        assert!(policy.accepts("550e8400-e29b-41d4-a716-446655440000-42"));
+        assert!(policy.accepts("trace:abc_1.2"));
+        assert!(!policy.accepts(""));
+        assert!(!policy.accepts("has space"));
+        assert!(!policy.accepts("line\nbreak"));
+        assert!(!policy.accepts(&"a".repeat(DEFAULT_MAX_CORRELATION_ID_LENGTH + 1)));
+    }
+
+    #[test]
+    fn prefixes_restrict_supplied_but_not_generated_ids() {
+        let policy = CorrelationIdPolicy {
+            max_length: 16,
+            allowed_prefixes: vec!["checkout-".to_owned()],
+        };
+        assert!(policy.accepts("checkout-7f3a"));
+        assert!(!policy.accepts("billing-7f3a"));
+        assert!(!policy.accepts("checkout-7f3a-too-long"));
+        // Generated ids are longer than the limit and carry no prefix
+        assert!(policy.accepts(&generate_correlation_id()));
+        assert!(!policy.accepts("550e8400-e29b-41d4-a716-446655440000-"));
+        assert!(!policy.accepts("550e8400-e29b-41d4-a716-446655440000-4x"));
+    }
+
+    #[test]
+    fn normalize_replaces_rejected_ids() {
+        let policy = CorrelationIdPolicy::default();
+        assert_eq!(
+            policy.normalize(Some("checkout-7f3a".to_owned())),
+            "checkout-7f3a"
+        );
+        for rejected in [Some("has space".to_owned()), Some(String::new()), None] {
+            let id = policy.normalize(rejected);
+            assert!(is_generated(&id), "{id}");
+        }
+    }
+
+    #[test]
+    fn parses_current_and_older_generated_ids() {
+        let parsed = parse_generated_id("1760600000123-pod.a_1-42").unwrap();
+        assert_eq!(
+            parsed.created_at,
+            DateTime::from_timestamp_millis(1_760_600_000_123)
+        );
+        assert_eq!(parsed.instance_id, Some("pod.a_1"));
+        assert_eq!(parsed.counter, 42);
+        // Instance ids may contain dashes; the counter follows the last one
+        let parsed = parse_generated_id("1760600000123-sentinel-7f9c-3").unwrap();
+        assert_eq!(parsed.instance_id, Some("sentinel-7f9c"));
+
+        let legacy = parse_generated_id("550e8400-e29b-41d4-a716-446655440000-7").unwrap();
+        assert_eq!((legacy.created_at, legacy.instance_id), (None, None));
+        assert_eq!(legacy.counter, 7);
+
+        for malformed in [
+            "176060000012-pod-1",
+            "1760600000123--1",
+            "1760600000123-pod-",
+            "1760600000123-pod",
+            "1760600000123-a b-1",
+            "550e8400-e29b-41d4-a716-446655440000",
+        ] {
+            assert_eq!(parse_generated_id(malformed), None, "{malformed}");
+        }
+    }
+
+    #[test]
+    fn counter_resumes_past_the_last_reserved_block() {
+        let store = Arc::new(InMemoryCorrelationIdStore::new());
+        let first = CorrelationIdGenerator::open(store.clone(), None).unwrap();
+        let counters: Vec<u64> = (0..3)
+            .map(|_| parse_generated_id(&first.generate()).unwrap().counter)
+            .collect();
+        assert_eq!(counters, [1, 2, 3]);
+        assert_eq!(store.high_water().unwrap(), 1 + COUNTER_BLOCK);
+
+        // A restart keeps the instance id and never reuses a counter value
+        let restarted = CorrelationIdGenerator::open(store.clone(), None).unwrap();
+        assert_eq!(restarted.instance_id(), first.instance_id());
+        let id = restarted.generate();
+        assert_eq!(parse_generated_id(&id).unwrap().counter, 1 + COUNTER_BLOCK);
+        assert!(CorrelationIdPolicy::default().accepts(&id));
+
+        let renamed = CorrelationIdGenerator::open(store, Some("replica-2")).unwrap();
+        assert_eq!(
+            parse_generated_id(&renamed.generate()).unwrap().instance_id,
+            Some("replica-2")
+        );
+    }
+
+    #[test]
+    fn instance_ids_must_be_short_plain_tokens() {
+        assert!(validate_instance_id("sentinel-7f9c.eu_1").is_ok());
+        for invalid in ["", "has space", &"a".repeat(MAX_INSTANCE_ID_LENGTH + 1)] {
+            assert!(validate_instance_id(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn prefixes_must_be_plain_tokens() {
+        assert!(CorrelationIdPolicy::default().validate().is_ok());
+        for prefixes in [vec![String::new()], vec!["a b".to_owned()]] {
+            let policy = CorrelationIdPolicy {
+                allowed_prefixes: prefixes,
+                ..CorrelationIdPolicy::default()
+            };
+            assert!(policy.validate().is_err());
+        }
+        let zero = CorrelationIdPolicy {
+            max_length: 0,
+            ..CorrelationIdPolicy::default()
+        };
+        assert!(zero.validate().is_err());
+    }
+}
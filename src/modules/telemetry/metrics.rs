@@ -1,10 +1,18 @@
 use std::time::Instant;
 
+use http::StatusCode;
 use metrics::{counter, gauge, histogram};
-use metrics_exporter_prometheus::PrometheusBuilder;
 use once_cell::sync::Lazy;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use super::correlation::parse_generated_id;
+
+/// Prometheus counters, gauges and histograms for the service
+///
+/// Label values must come from small fixed sets: methods, route templates, stage names,
+/// rule ids. A per-request value such as a correlation id would create a new time series
+/// for every request, so values shaped like one are refused: debug builds panic and
+/// release builds record them under `redacted`.
 pub struct TelemetryMetrics {
     request_counter: AtomicU64,
     error_counter: AtomicU64,
@@ -28,16 +36,21 @@ impl TelemetryMetrics {
 
     pub fn increment_requests(&self, method: &str, endpoint: &str) {
         self.request_counter.fetch_add(1, Ordering::SeqCst);
-        counter!("requests_total", "method" => method.to_string(), "endpoint" => endpoint.to_string()).increment(1);
+        counter!("requests_total", "method" => label(method), "endpoint" => label(endpoint))
+            .increment(1);
+    }
+
+    pub fn increment_responses(&self, method: &str, endpoint: &str, status_class: &str) {
+        counter!("responses_total", "method" => label(method), "endpoint" => label(endpoint), "status_class" => label(status_class)).increment(1);
     }
 
     pub fn increment_errors(&self, error_type: &str) {
         self.error_counter.fetch_add(1, Ordering::SeqCst);
-        counter!("errors_total", "error_type" => error_type.to_string()).increment(1);
+        counter!("errors_total", "error_type" => label(error_type)).increment(1);
     }
 
     pub fn record_latency(&self, method: &str, endpoint: &str, duration: f64) {
-        histogram!("request_latency_seconds", "method" => method.to_string(), "endpoint" => endpoint.to_string()).record(duration);
+        histogram!("request_latency_seconds", "method" => label(method), "endpoint" => label(endpoint)).record(duration);
     }
 
     pub fn increment_active_requests(&self) {
@@ -50,15 +63,240 @@ impl TelemetryMetrics {
         gauge!("active_requests").decrement(1.0);
     }
 
-    pub fn start_metrics_server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let builder = PrometheusBuilder::new();
-        let socket_addr: std::net::SocketAddr = addr.parse()?;
-        let builder = builder.with_http_listener(socket_addr);
+    pub fn set_maintenance_queue_depth(&self, depth: usize) {
+        gauge!("maintenance_queue_depth").set(depth as f64);
+    }
+
+    pub fn record_maintenance_wait(&self, duration: f64) {
+        histogram!("maintenance_queue_wait_seconds").record(duration);
+    }
+
+    pub fn increment_maintenance_rejections(&self, reason: &str) {
+        counter!("maintenance_rejections_total", "reason" => label(reason)).increment(1);
+    }
+
+    pub fn set_admission_in_flight(&self, count: usize) {
+        gauge!("admission_in_flight").set(count as f64);
+    }
+
+    pub fn set_admission_queue_depth(&self, depth: usize) {
+        gauge!("admission_queue_depth").set(depth as f64);
+    }
+
+    pub fn record_admission_wait(&self, duration: f64) {
+        histogram!("admission_wait_seconds").record(duration);
+    }
+
+    pub fn increment_admission_shed(&self, reason: &str) {
+        counter!("admission_shed_total", "reason" => label(reason)).increment(1);
+    }
+
+    pub fn set_audit_unflushed_records(&self, count: usize) {
+        gauge!("audit_unflushed_records").set(count as f64);
+    }
+
+    pub fn set_audit_chain_broken(&self, broken: bool) {
+        gauge!("audit_chain_broken").set(if broken { 1.0 } else { 0.0 });
+    }
+
+    pub fn set_model_watch_alerts(&self, count: usize) {
+        gauge!("model_watch_alerts").set(count as f64);
+    }
+
+    pub fn set_sled_disk_usage(&self, directory_bytes: u64, size_on_disk_bytes: u64) {
+        gauge!("sled_directory_bytes").set(directory_bytes as f64);
+        gauge!("sled_size_on_disk_bytes").set(size_on_disk_bytes as f64);
+    }
+
+    pub fn set_audit_hash_only(&self, hash_only: bool) {
+        gauge!("audit_hash_only_mode").set(if hash_only { 1.0 } else { 0.0 });
+    }
+
+    pub fn set_expiring_rules(&self, count: usize) {
+        gauge!("expiring_rules_total").set(count as f64);
+    }
+
+    pub fn set_mistral_in_flight(&self, operation: &str, count: usize) {
+        gauge!("mistral_in_flight_calls", "operation" => label(operation)).set(count as f64);
+    }
+
+    pub fn set_mistral_queued(&self, operation: &str, count: usize) {
+        gauge!("mistral_queued_calls", "operation" => label(operation)).set(count as f64);
+    }
+
+    pub fn increment_mistral_concurrency_timeouts(&self, operation: &str) {
+        counter!("mistral_concurrency_timeouts_total", "operation" => label(operation))
+            .increment(1);
+    }
+
+    /// One HTTP attempt against the Mistral API; `status_class` is `error` when no
+    /// response arrived
+    pub fn increment_mistral_api_calls(&self, endpoint: &str, status_class: &str) {
+        counter!(
+            "mistral_api_calls_total",
+            "endpoint" => label(endpoint),
+            "status_class" => label(status_class)
+        )
+        .increment(1);
+    }
+
+    pub fn increment_mistral_api_retries(&self, endpoint: &str) {
+        counter!("mistral_api_retries_total", "endpoint" => label(endpoint)).increment(1);
+    }
+
+    /// `scope` is `attempt` for one HTTP exchange or `call` for the whole retry loop
+    pub fn record_mistral_api_latency(&self, endpoint: &str, scope: &str, duration: f64) {
+        histogram!(
+            "mistral_api_latency_seconds",
+            "endpoint" => label(endpoint),
+            "scope" => label(scope)
+        )
+        .record(duration);
+    }
+
+    pub fn set_mistral_api_failure_streak(&self, endpoint: &str, streak: u64) {
+        gauge!("mistral_api_consecutive_failures", "endpoint" => label(endpoint))
+            .set(streak as f64);
+    }
+
+    pub fn increment_semantic_sampling(&self, outcome: &str) {
+        counter!("semantic_sampling_total", "outcome" => label(outcome)).increment(1);
+    }
+
+    pub fn increment_exemptions_applied(&self, rule_id: &str) {
+        counter!("exemptions_applied_total", "rule_id" => label(rule_id)).increment(1);
+    }
+
+    pub fn increment_exemptions_archived(&self, reason: &str) {
+        counter!("exemptions_archived_total", "reason" => label(reason)).increment(1);
+    }
+
+    pub fn set_active_exemptions(&self, count: usize) {
+        gauge!("active_exemptions").set(count as f64);
+    }
+
+    /// `slo_latency_sli` or `slo_availability_sli` for one route and window
+    pub fn set_slo_sli(&self, objective: &str, endpoint: &str, window: &str, value: f64) {
+        gauge!(
+            format!("slo_{objective}_sli"),
+            "endpoint" => label(endpoint),
+            "window" => label(window)
+        )
+        .set(value);
+    }
+
+    /// `slo_burn_rate_5m`, `slo_burn_rate_1h` or `slo_burn_rate_6h`
+    pub fn set_slo_burn_rate(&self, objective: &str, endpoint: &str, window: &str, rate: f64) {
+        gauge!(
+            format!("slo_burn_rate_{window}"),
+            "endpoint" => label(endpoint),
+            "objective" => label(objective)
+        )
+        .set(rate);
+    }
+
+    pub fn set_slo_error_budget_remaining(&self, objective: &str, endpoint: &str, value: f64) {
+        gauge!(
+            "slo_error_budget_remaining",
+            "endpoint" => label(endpoint),
+            "objective" => label(objective)
+        )
+        .set(value);
+    }
+
+    pub fn set_slo_burn_rate_alert(
+        &self,
+        objective: &str,
+        severity: &str,
+        endpoint: &str,
+        firing: bool,
+    ) {
+        gauge!(
+            "slo_burn_rate_alert",
+            "endpoint" => label(endpoint),
+            "objective" => label(objective),
+            "severity" => label(severity)
+        )
+        .set(if firing { 1.0 } else { 0.0 });
+    }
+
+    pub fn increment_slow_requests(&self, endpoint: &str) {
+        counter!("slow_requests_total", "endpoint" => label(endpoint)).increment(1);
+    }
 
-        // Install the recorder
-        builder.install()?;
+    pub fn increment_chaos_faults(&self, target: &str, kind: &str) {
+        counter!("chaos_faults_injected_total", "target" => label(target), "kind" => label(kind))
+            .increment(1);
+    }
+
+    pub fn increment_audit_suppressed_repeats(&self, rule: &str) {
+        counter!("audit_suppressed_repeats_total", "rule" => label(rule)).increment(1);
+    }
+
+    pub fn increment_threat_detections(&self, category: &str, layer: &str) {
+        counter!("threat_detections_total", "category" => label(category), "layer" => label(layer))
+            .increment(1);
+    }
+
+    pub fn increment_llm_judge_verdicts(&self, trigger: &str, verdict: &str) {
+        counter!("llm_judge_verdicts_total", "trigger" => label(trigger), "verdict" => label(verdict))
+            .increment(1);
+    }
 
-        Ok(())
+    pub fn set_cache_usage(&self, cache: &str, entries: usize, estimated_bytes: u64) {
+        gauge!("cache_entries", "cache" => label(cache)).set(entries as f64);
+        gauge!("cache_estimated_bytes", "cache" => label(cache)).set(estimated_bytes as f64);
+    }
+
+    pub fn set_cache_hit_rate(&self, cache: &str, rate: f64) {
+        gauge!("cache_hit_rate", "cache" => label(cache)).set(rate);
+    }
+
+    pub fn increment_cache_evictions(&self, cache: &str, reason: &str, count: usize) {
+        counter!("cache_evictions_total", "cache" => label(cache), "reason" => label(reason))
+            .increment(count as u64);
+    }
+
+    pub fn increment_api_key_requests(&self, key_id: &str) {
+        counter!("api_key_requests_total", "key_id" => label(key_id)).increment(1);
+    }
+
+    pub fn increment_quota_rejections(&self, key_id: &str, quota: &str) {
+        counter!("api_key_quota_rejections_total", "key_id" => label(key_id), "quota" => label(quota))
+            .increment(1);
+    }
+
+    pub fn increment_firewall_misses(&self, caught_by: &str) {
+        counter!("firewall_misses_total", "caught_by" => label(caught_by)).increment(1);
+    }
+
+    pub fn increment_firewall_monitor_matches(&self, rule_id: &str) {
+        counter!("firewall_monitor_matches_total", "rule_id" => label(rule_id)).increment(1);
+    }
+
+    pub fn increment_url_unfurls(&self, status: &str) {
+        counter!("firewall_url_unfurls_total", "status" => label(status)).increment(1);
+    }
+
+    pub fn increment_firewall_overblocks(&self) {
+        counter!("firewall_overblocks_total").increment(1);
+    }
+
+    pub fn increment_sanitize_probing_escalations(&self) {
+        counter!("sanitize_probing_escalations_total").increment(1);
+    }
+
+    pub fn increment_abandoned_requests(&self, stage: &str) {
+        counter!("abandoned_requests_total", "stage" => label(stage)).increment(1);
+    }
+
+    pub fn increment_stage_failures(&self, stage: &str, policy: &str) {
+        counter!(
+            "stage_failures_total",
+            "stage" => label(stage),
+            "policy" => label(policy)
+        )
+        .increment(1);
     }
 }
 
@@ -84,8 +322,74 @@ impl RequestTimer {
     }
 }
 
+/// `status_class` label for an HTTP status: `2xx`, `4xx`, ...
+pub(crate) fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Recorded instead of a label value that looks like a correlation id
+pub const REDACTED_LABEL: &str = "redacted";
+
+/// Hex digits in a row, dashes aside, that mark a value as a generated identifier
+const ID_HEX_RUN: usize = 16;
+
+/// Whether `value` carries a UUID or a similar long hex identifier, or is a generated
+/// correlation id
+///
+/// Dashes do not break a run, so `550e8400-e29b-41d4-…` counts as one.
+pub fn looks_like_correlation_id(value: &str) -> bool {
+    if parse_generated_id(value).is_some() {
+        return true;
+    }
+    let mut run = 0;
+    for b in value.bytes() {
+        match b {
+            b'-' => {}
+            b if b.is_ascii_hexdigit() => {
+                run += 1;
+                if run >= ID_HEX_RUN {
+                    return true;
+                }
+            }
+            _ => run = 0,
+        }
+    }
+    false
+}
+
+fn label(value: &str) -> String {
+    let id_like = looks_like_correlation_id(value);
+    debug_assert!(
+        !id_like,
+        "metric label {value:?} looks like a correlation id"
+    );
+    if id_like {
+        REDACTED_LABEL.to_owned()
+    } else {
+        value.to_owned()
+    }
+}
+
 static METRICS: Lazy<TelemetryMetrics> = Lazy::new(TelemetryMetrics::new);
 
 pub fn get_metrics() -> &'static TelemetryMetrics {
     &METRICS
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_classes() {
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::UNPROCESSABLE_ENTITY), "4xx");
+        assert_eq!(status_class(StatusCode::SERVICE_UNAVAILABLE), "5xx");
+    }
+}
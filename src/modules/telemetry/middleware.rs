@@ -0,0 +1,148 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use axum::body::HttpBody;
+use axum::extract::{ConnectInfo, MatchedPath, Request};
+use axum::http::HeaderValue;
+use axum::http::header::CONTENT_LENGTH;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+
+use crate::modules::diagnostics::service::{CompletedRequest, SlowRequestLog, collect_timings};
+use crate::modules::slo::service::SloTracker;
+use crate::modules::telemetry::context::{
+    API_VERSION_HEADER, CLIENT_ID, CORRELATION_ID_HEADER, RequestContext, TRACEPARENT_HEADER,
+    TraceParent,
+};
+use crate::modules::telemetry::correlation::CorrelationIdPolicy;
+use crate::modules::telemetry::metrics::{get_metrics, status_class};
+use crate::modules::telemetry::tracing::{log_with_correlation, request_span};
+use crate::workflow::API_VERSION;
+
+/// Build the [`RequestContext`], log the request and record route metrics
+///
+/// The correlation id is echoed in the `X-Correlation-Id` response header. Supplied ids
+/// are checked against the router's [`CorrelationIdPolicy`] extension, or the default
+/// policy without one. When the router carries an [`SloTracker`] extension, every
+/// response also feeds the SLOs, and with a [`SlowRequestLog`] extension requests over
+/// their latency threshold leave a diagnostic built from the stage timings they reported.
+pub async fn request_context_middleware(mut request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let supplied_id = request
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+    let correlation_id = match request.extensions().get::<CorrelationIdPolicy>() {
+        Some(policy) => policy.normalize(supplied_id),
+        None => CorrelationIdPolicy::default().normalize(supplied_id),
+    };
+    let context = RequestContext {
+        correlation_id,
+        started_at: Instant::now(),
+        // Route templates keep the metric label set bounded
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_owned())
+            .unwrap_or_else(|| request.uri().path().to_owned()),
+        client_ip: request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+        trace_parent: request
+            .headers()
+            .get(TRACEPARENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceParent::parse),
+    };
+    request.extensions_mut().insert(context.clone());
+    let slo = request.extensions().get::<SloTracker>().cloned();
+    let slow_requests = request.extensions().get::<SlowRequestLog>().cloned();
+    let request_bytes = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+
+    let metrics = get_metrics();
+    metrics.increment_active_requests();
+    metrics.increment_requests(method.as_str(), &context.route);
+    log_with_correlation(
+        &context.correlation_id,
+        tracing::Level::INFO,
+        &format!("Request started: {} {}", method, context.route),
+    );
+
+    let span = request_span(
+        &context.correlation_id,
+        method.as_str(),
+        &context.route,
+        context
+            .trace_parent
+            .as_ref()
+            .map(|parent| parent.trace_id.as_str()),
+        context
+            .trace_parent
+            .as_ref()
+            .map(|parent| parent.parent_span_id.as_str()),
+    );
+    let handler = next.run(request).instrument(span.clone());
+    let handler = async {
+        match context.client_ip {
+            Some(ip) => CLIENT_ID.scope(ip.to_string(), handler).await,
+            None => handler.await,
+        }
+    };
+    let (mut response, timings) = collect_timings(handler).await;
+
+    let duration = context.elapsed_seconds();
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    metrics.record_latency(method.as_str(), &context.route, duration);
+    metrics.increment_responses(method.as_str(), &context.route, status_class(status));
+    metrics.decrement_active_requests();
+    if let Some(slo) = slo {
+        slo.record(
+            &context.route,
+            context.started_at.elapsed(),
+            status.is_server_error(),
+        );
+    }
+    if let Some(slow_requests) = slow_requests {
+        slow_requests.observe(
+            CompletedRequest {
+                correlation_id: &context.correlation_id,
+                method: method.as_str(),
+                route: &context.route,
+                status: status.as_u16(),
+                duration: context.started_at.elapsed(),
+                request_bytes,
+                response_bytes: response.body().size_hint().exact(),
+            },
+            timings,
+        );
+    }
+    log_with_correlation(
+        &context.correlation_id,
+        tracing::Level::INFO,
+        &format!(
+            "Request completed: {} {} -> {} in {:.3}s",
+            method,
+            context.route,
+            status.as_u16(),
+            duration
+        ),
+    );
+
+    if let Ok(value) = HeaderValue::from_str(&context.correlation_id) {
+        response.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+    response
+        .headers_mut()
+        .insert(API_VERSION_HEADER, HeaderValue::from(API_VERSION));
+    response
+}
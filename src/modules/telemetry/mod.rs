@@ -1,3 +1,9 @@
+pub mod context;
 pub mod correlation;
 pub mod metrics;
+#[cfg(feature = "server")]
+pub mod middleware;
+#[cfg(feature = "metrics-prometheus")]
+pub mod prometheus;
+pub mod sampling;
 pub mod tracing;
@@ -0,0 +1,144 @@
+//! The Prometheus recorder behind the `metrics` facade, and the listener that serves it
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use thiserror::Error;
+use tracing::debug;
+
+/// How often histogram samples are folded into the recorder's summaries
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Handle of the recorder [`install_prometheus_recorder`] installed
+static RECORDER: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+
+#[derive(Debug, Error)]
+pub enum MetricsSetupError {
+    /// A recorder was installed some other way, e.g. by an embedding application
+    #[error("another metrics recorder is already installed")]
+    RecorderInUse,
+    #[error("invalid metrics address {addr}: {reason}")]
+    InvalidAddress { addr: String, reason: String },
+    #[error("cannot listen for metrics scrapes on {addr}: {source}")]
+    Bind {
+        addr: String,
+        source: std::io::Error,
+    },
+}
+
+/// Install the Prometheus recorder as the global `metrics` recorder
+///
+/// Only the first call installs one; later calls return its handle, so tests and servers
+/// started more than once in a process share it. Fails without panicking when a
+/// recorder was installed outside this function.
+pub fn install_prometheus_recorder() -> Result<PrometheusHandle, MetricsSetupError> {
+    let mut installed = RECORDER.lock().unwrap();
+    if let Some(handle) = installed.as_ref() {
+        return Ok(handle.clone());
+    }
+    let recorder = PrometheusBuilder::new().build_recorder();
+    let handle = recorder.handle();
+    metrics::set_global_recorder(recorder).map_err(|_| MetricsSetupError::RecorderInUse)?;
+
+    let upkeep = handle.clone();
+    std::thread::Builder::new()
+        .name("metrics-upkeep".to_owned())
+        .spawn(move || {
+            loop {
+                std::thread::sleep(UPKEEP_INTERVAL);
+                upkeep.run_upkeep();
+            }
+        })
+        .map_err(|e| debug!("Metrics upkeep thread not started: {}", e))
+        .ok();
+    *installed = Some(handle.clone());
+    Ok(handle)
+}
+
+/// Every recorded metric in the Prometheus text format; `None` until
+/// [`install_prometheus_recorder`] has succeeded
+pub fn render_prometheus() -> Option<String> {
+    RECORDER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(PrometheusHandle::render)
+}
+
+/// Serves `GET /metrics` from the shared recorder until shut down or dropped
+#[cfg(feature = "server")]
+pub struct MetricsListener {
+    addr: SocketAddr,
+    stop: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+#[cfg(feature = "server")]
+impl MetricsListener {
+    /// Listen on `addr`, such as `0.0.0.0:9090`; port `0` picks a free port
+    pub async fn bind(addr: &str, handle: PrometheusHandle) -> Result<Self, MetricsSetupError> {
+        let socket_addr: SocketAddr = addr.parse().map_err(|e: std::net::AddrParseError| {
+            MetricsSetupError::InvalidAddress {
+                addr: addr.to_owned(),
+                reason: e.to_string(),
+            }
+        })?;
+        let bind_error = |source| MetricsSetupError::Bind {
+            addr: addr.to_owned(),
+            source,
+        };
+        let listener = tokio::net::TcpListener::bind(socket_addr)
+            .await
+            .map_err(bind_error)?;
+        let local_addr = listener.local_addr().map_err(bind_error)?;
+
+        let router = axum::Router::new().route(
+            "/metrics",
+            axum::routing::get(move || {
+                let handle = handle.clone();
+                async move { handle.render() }
+            }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let served = axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    stopped.await.ok();
+                })
+                .await;
+            if let Err(e) = served {
+                tracing::warn!("Metrics listener stopped: {}", e);
+            }
+        });
+        Ok(Self {
+            addr: local_addr,
+            stop: Some(stop),
+            task: Some(task),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop serving and wait until the port is released
+    pub async fn shutdown(mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop.send(()).ok();
+        }
+        if let Some(task) = self.task.take() {
+            task.await.ok();
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl Drop for MetricsListener {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop.send(()).ok();
+        }
+    }
+}
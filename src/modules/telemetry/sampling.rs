@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default window in which repeats of one WARN line from one client are collapsed
+pub const DEFAULT_LOG_SAMPLING_WINDOW_SECS: u64 = 60;
+
+/// Distinct `(client, message)` pairs tracked at once; past this, lines are written
+/// unsampled rather than growing the table without bound
+const MAX_TRACKED_MESSAGES: usize = 10_000;
+
+/// What to do with one WARN line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// Write the line; `suppressed` identical lines were dropped since it was last written
+    Log { suppressed: u64 },
+    /// Drop the line, counting it toward the next summary
+    Suppress,
+}
+
+struct Window {
+    started_at: Instant,
+    suppressed: u64,
+}
+
+/// Collapses repeated identical WARN lines from one client into one line per window
+///
+/// The first occurrence in a window is written; repeats are only counted, and the
+/// first occurrence after the window closes is written with that count. A client
+/// retrying a blocked prompt in a loop therefore costs one line a minute, not one per
+/// request.
+pub struct LogSampler {
+    window_secs: AtomicU64,
+    windows: Mutex<HashMap<(String, String), Window>>,
+}
+
+impl LogSampler {
+    /// A zero `window` disables sampling
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_secs: AtomicU64::new(window.as_secs()),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs.load(Ordering::Relaxed))
+    }
+
+    /// Open windows are discarded when the length changes
+    pub fn set_window(&self, window: Duration) {
+        if self.window_secs.swap(window.as_secs(), Ordering::Relaxed) != window.as_secs() {
+            self.windows.lock().unwrap().clear();
+        }
+    }
+
+    pub fn sample(&self, client_id: &str, message: &str, now: Instant) -> Sampling {
+        let window = self.window();
+        if window.is_zero() {
+            return Sampling::Log { suppressed: 0 };
+        }
+        let mut windows = self.windows.lock().unwrap();
+        let key = (client_id.to_owned(), message.to_owned());
+        if let Some(current) = windows.get_mut(&key) {
+            if now.saturating_duration_since(current.started_at) < window {
+                current.suppressed += 1;
+                return Sampling::Suppress;
+            }
+            let suppressed = current.suppressed;
+            *current = Window {
+                started_at: now,
+                suppressed: 0,
+            };
+            return Sampling::Log { suppressed };
+        }
+        if windows.len() >= MAX_TRACKED_MESSAGES {
+            windows.retain(|_, open| now.saturating_duration_since(open.started_at) < window);
+        }
+        if windows.len() < MAX_TRACKED_MESSAGES {
+            windows.insert(
+                key,
+                Window {
+                    started_at: now,
+                    suppressed: 0,
+                },
+            );
+        }
+        Sampling::Log { suppressed: 0 }
+    }
+}
+
+static LOG_SAMPLER: LazyLock<LogSampler> =
+    LazyLock::new(|| LogSampler::new(Duration::from_secs(DEFAULT_LOG_SAMPLING_WINDOW_SECS)));
+
+/// Sampler consulted by `log_with_correlation` for WARN lines logged within a request
+pub fn get_log_sampler() -> &'static LogSampler {
+    &LOG_SAMPLER
+}
@@ -1,26 +1,149 @@
 use std::sync::Once;
-use tracing::{Level, debug, error, info, span, warn};
-use tracing_subscriber::{EnvFilter, fmt};
+use std::time::Instant;
+
+use tracing::field::Empty;
+use tracing::{Level, Span, debug, error, info, span, warn};
+use tracing_subscriber::layer::{Identity, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt};
+
+use crate::modules::telemetry::context::current_client_id;
+use crate::modules::telemetry::sampling::{Sampling, get_log_sampler};
 
 static INIT: Once = Once::new();
 
+const FILTER_DIRECTIVES: &str = "info,prompt_sentinel=debug,tower_http=debug";
+
+/// Standard OpenTelemetry variable naming the collector to export spans to
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
 pub fn init_tracing() {
-    INIT.call_once(|| {
-        let filter = EnvFilter::new("info,prompt_sentinel=debug,tower_http=debug");
+    install(Identity::new());
+    if let Ok(endpoint) = std::env::var(OTLP_ENDPOINT_ENV) {
+        if cfg!(feature = "telemetry-otlp") {
+            warn!(
+                "{} is set to {} but no span exporter is installed; pass a \
+                 tracing-opentelemetry layer to init_tracing_with to export spans",
+                OTLP_ENDPOINT_ENV, endpoint
+            );
+        } else {
+            warn!(
+                "{} is set to {} but this build lacks the telemetry-otlp feature; spans \
+                 are not exported",
+                OTLP_ENDPOINT_ENV, endpoint
+            );
+        }
+    }
+}
+
+/// Install the log output together with `layer`, e.g. a `tracing-opentelemetry` layer
+/// exporting the workflow spans over OTLP
+///
+/// Both see the same level filter. Spans carry `otel.name`, `otel.kind` and
+/// `otel.status_code`, so an OpenTelemetry layer names and classifies them as is.
+#[cfg(feature = "telemetry-otlp")]
+pub fn init_tracing_with<L>(layer: L)
+where
+    L: Layer<Registry> + Send + Sync + 'static,
+{
+    install(layer);
+}
 
-        fmt()
-            .with_env_filter(filter)
-            .with_target(false)
-            .with_thread_ids(true)
-            .with_thread_names(true)
+fn install<L>(layer: L)
+where
+    L: Layer<Registry> + Send + Sync + 'static,
+{
+    INIT.call_once(|| {
+        Registry::default()
+            .with(layer.with_filter(EnvFilter::new(FILTER_DIRECTIVES)))
+            .with(
+                fmt::layer()
+                    .with_target(false)
+                    .with_thread_ids(true)
+                    .with_thread_names(true)
+                    .with_filter(EnvFilter::new(FILTER_DIRECTIVES)),
+            )
             .init();
     });
 }
 
+/// Span for an HTTP request, joining the caller's trace when it sent a `traceparent`
+pub fn request_span(
+    correlation_id: &str,
+    method: &str,
+    route: &str,
+    trace_id: Option<&str>,
+    parent_span_id: Option<&str>,
+) -> Span {
+    tracing::info_span!(
+        "request",
+        otel.name = %format!("{method} {route}"),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        correlation_id = %correlation_id,
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = Empty,
+        trace_id = trace_id,
+        parent_span_id = parent_span_id,
+    )
+}
+
+/// Span for one compliance workflow run; `status` is recorded when it finishes
+pub fn workflow_span(correlation_id: &str) -> Span {
+    tracing::info_span!(
+        "compliance_workflow",
+        otel.name = "compliance_workflow",
+        correlation_id = %correlation_id,
+        status = Empty,
+    )
+}
+
+/// Span for one pipeline stage, nested in the current workflow span
+///
+/// `action`, `score`, `flagged` and `status` start empty and are recorded once the stage
+/// has a result. With no subscriber interested the span is disabled and costs a single
+/// check.
+pub fn stage_span(stage: &'static str) -> Span {
+    tracing::info_span!(
+        "stage",
+        otel.name = stage,
+        otel.status_code = Empty,
+        stage = stage,
+        action = Empty,
+        score = Empty,
+        flagged = Empty,
+        status = Empty,
+    )
+}
+
+/// Log `message` tagged with the correlation id
+///
+/// WARN lines logged while serving a request go through the log sampler, so a client
+/// repeating the same blocked prompt produces periodic summaries instead of one line
+/// per request.
 pub fn log_with_correlation(correlation_id: &str, level: Level, message: &str) {
     match level {
         Level::ERROR => error!(correlation_id = %correlation_id, "{}", message),
-        Level::WARN => warn!(correlation_id = %correlation_id, "{}", message),
+        Level::WARN => match current_client_id() {
+            Some(client_id) => {
+                match get_log_sampler().sample(&client_id, message, Instant::now()) {
+                    Sampling::Log { suppressed: 0 } => {
+                        warn!(correlation_id = %correlation_id, "{}", message)
+                    }
+                    Sampling::Log { suppressed } => warn!(
+                        correlation_id = %correlation_id,
+                        client_id = %client_id,
+                        suppressed,
+                        "{} ({} identical messages from this client suppressed)",
+                        message,
+                        suppressed
+                    ),
+                    Sampling::Suppress => {}
+                }
+            }
+            None => warn!(correlation_id = %correlation_id, "{}", message),
+        },
         Level::INFO => info!(correlation_id = %correlation_id, "{}", message),
         Level::DEBUG => debug!(correlation_id = %correlation_id, "{}", message),
         Level::TRACE => tracing::trace!(correlation_id = %correlation_id, "{}", message),
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Daily limits of one API key; an absent limit does not apply
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct KeyQuota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_day: Option<u64>,
+    /// Generation tokens; checked before each request, so the request that crosses the
+    /// limit still completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_day: Option<u64>,
+}
+
+/// One API key as the registry file lists it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyEntry {
+    /// Name usage is reported under, such as a team or service
+    pub key_id: String,
+    /// Hex SHA-256 of the bearer token; the token itself is never configured
+    pub token_sha256: String,
+    #[serde(default)]
+    pub quota: KeyQuota,
+}
+
+/// The API keys callers may present, read from `API_KEYS_PATH`
+///
+/// Without keys every request is anonymous and nothing is metered.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyRegistryConfig {
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+impl ApiKeyRegistryConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parse and validate a registry; unknown keys are errors
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let registry: Self =
+            serde_json::from_str(text).map_err(|e| format!("cannot parse: {e}"))?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = BTreeSet::new();
+        let mut hashes = BTreeSet::new();
+        for key in &self.keys {
+            if key.key_id.trim().is_empty() {
+                return Err("API key ids must not be empty".to_owned());
+            }
+            if !ids.insert(key.key_id.as_str()) {
+                return Err(format!("API key id {} is listed twice", key.key_id));
+            }
+            let hash = &key.token_sha256;
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "token_sha256 of API key {} is not a SHA-256 hex digest",
+                    key.key_id
+                ));
+            }
+            if !hashes.insert(hash.to_ascii_lowercase()) {
+                return Err(format!(
+                    "API key {} shares its token with another key",
+                    key.key_id
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How usage is priced and how often it is persisted
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UsageConfig {
+    /// Price of 1000 generation tokens, for `estimated_cost` (default: 0)
+    pub cost_per_1k_tokens: f64,
+    /// Seconds between writes of the counters to storage, and so the most usage a
+    /// crash can lose; 0 writes only on shutdown (default: 10)
+    pub flush_interval_secs: u64,
+    /// Refuse `POST`s without a bearer token on metered routes once keys are
+    /// registered, so no caller escapes metering (default: true)
+    pub require_key: bool,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            cost_per_1k_tokens: 0.0,
+            flush_interval_secs: 10,
+            require_key: true,
+        }
+    }
+}
+
+/// What one key used over some days
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct UsageCounters {
+    /// Requests admitted under the key
+    pub requests: u64,
+    /// Blocked checks, by final status
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocked: BTreeMap<String, u64>,
+    /// Generation tokens, as Mistral reported them
+    pub tokens: u64,
+    /// `tokens` priced at `USAGE_COST_PER_1K_TOKENS`
+    pub estimated_cost: f64,
+}
+
+impl UsageCounters {
+    pub fn add(&mut self, other: &UsageCounters) {
+        self.requests += other.requests;
+        for (status, count) in &other.blocked {
+            *self.blocked.entry(status.clone()).or_default() += count;
+        }
+        self.tokens += other.tokens;
+        self.estimated_cost += other.estimated_cost;
+    }
+}
+
+/// One key's counters for one UTC day, as storage keeps them
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DailyUsage {
+    pub key_id: String,
+    pub date: NaiveDate,
+    pub counters: UsageCounters,
+}
+
+/// Days a usage report covers, ending today
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl UsagePeriod {
+    pub fn days(self) -> u32 {
+        match self {
+            Self::Day => 1,
+            Self::Week => 7,
+            Self::Month => 30,
+        }
+    }
+}
+
+/// What is left of a key's quota today; `None` where no limit applies
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct QuotaRemaining {
+    pub requests: Option<u64>,
+    pub tokens: Option<u64>,
+}
+
+/// Body of `GET /api/usage/self`, and one entry of `GET /api/usage`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UsageReport {
+    pub key_id: String,
+    pub period: UsagePeriod,
+    /// First and last UTC day counted
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub usage: UsageCounters,
+    pub quota: KeyQuota,
+    /// Left of today's quota, whatever the period
+    pub remaining_today: QuotaRemaining,
+    /// When today's counts start over
+    pub resets_at: DateTime<Utc>,
+}
+
+/// Query of `GET /api/usage`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct UsageQuery {
+    /// Report one key instead of all
+    #[serde(default)]
+    pub key_id: Option<String>,
+    #[serde(default)]
+    pub period: UsagePeriod,
+}
+
+/// Body of `GET /api/usage`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UsageResponse {
+    /// In key id order
+    pub keys: Vec<UsageReport>,
+}
+
+/// Body of a `429` for a key over its quota
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct QuotaExceededError {
+    /// Human-readable summary
+    pub error: String,
+    /// `quota_requests_exceeded` or `quota_tokens_exceeded`
+    pub code: String,
+    pub key_id: String,
+    pub limit: u64,
+    pub used: u64,
+    pub resets_at: DateTime<Utc>,
+}
+
+/// The key a request was authenticated with, attached to the request by the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeyIdentity {
+    pub key_id: String,
+}
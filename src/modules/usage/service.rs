@@ -0,0 +1,372 @@
+//! Per-key accounting and daily quotas
+//!
+//! Requests that present a registered API key are counted in memory under the key and
+//! the UTC day, so the hot path takes one lock and writes nothing. The counters are
+//! written to a [`UsageStore`] every flush interval and on shutdown, and read back at
+//! startup; a crash loses at most one interval of usage. Thirty days are kept.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use super::dtos::{
+    ApiKeyRegistryConfig, DailyUsage, KeyQuota, QuotaExceededError, QuotaRemaining, UsageConfig,
+    UsageCounters, UsagePeriod, UsageReport,
+};
+use super::storage::{UsageStore, UsageStoreError};
+use crate::modules::audit::logger::{AuditError, AuditLogger, ConfigChangeEvent};
+use crate::modules::audit::proof::{content_hash, hash_record};
+use crate::modules::telemetry::metrics::get_metrics;
+use crate::workflow::{ComplianceResponse, WorkflowStatus};
+
+/// Days of counters kept in memory and read back at startup
+const RETAINED_DAYS: u64 = 30;
+
+struct KeyState {
+    /// Lowercase hex SHA-256 of the key's token
+    token_sha256: String,
+    quota: KeyQuota,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    keys: BTreeMap<String, KeyState>,
+    counters: BTreeMap<(String, NaiveDate), UsageCounters>,
+    /// Counters changed since the last flush
+    dirty: BTreeSet<(String, NaiveDate)>,
+}
+
+/// Counts what each API key uses and holds it to its quota
+///
+/// Shared by clones.
+#[derive(Clone)]
+pub struct UsageTracker {
+    config: UsageConfig,
+    state: Arc<Mutex<TrackerState>>,
+    store: Arc<dyn UsageStore>,
+    audit_logger: AuditLogger,
+}
+
+impl UsageTracker {
+    /// Track the keys of `registry`, starting from the counters `store` kept
+    ///
+    /// Counters that cannot be read are logged and started over, like the usage a crash
+    /// loses.
+    pub fn new(
+        registry: &ApiKeyRegistryConfig,
+        config: UsageConfig,
+        store: Arc<dyn UsageStore>,
+        audit_logger: AuditLogger,
+    ) -> Self {
+        let keys = registry
+            .keys
+            .iter()
+            .map(|entry| {
+                let state = KeyState {
+                    token_sha256: entry.token_sha256.to_ascii_lowercase(),
+                    quota: entry.quota,
+                };
+                (entry.key_id.clone(), state)
+            })
+            .collect();
+        let counters = match store.since(first_retained_day(Utc::now())) {
+            Ok(stored) => stored
+                .into_iter()
+                .map(|daily| ((daily.key_id, daily.date), daily.counters))
+                .collect(),
+            Err(e) => {
+                warn!(
+                    "Starting API key usage over: stored counters unreadable: {}",
+                    e
+                );
+                BTreeMap::new()
+            }
+        };
+        Self {
+            config,
+            state: Arc::new(Mutex::new(TrackerState {
+                keys,
+                counters,
+                dirty: BTreeSet::new(),
+            })),
+            store,
+            audit_logger,
+        }
+    }
+
+    pub fn config(&self) -> UsageConfig {
+        self.config
+    }
+
+    /// Whether any key is registered; without keys nothing is metered
+    pub fn is_enabled(&self) -> bool {
+        !self.state.lock().unwrap().keys.is_empty()
+    }
+
+    /// Id of the key whose token is `token`
+    pub fn authenticate(&self, token: &str) -> Option<String> {
+        let hash = hash_record(token);
+        let state = self.state.lock().unwrap();
+        // Every key is compared, so the time taken does not tell which one came close
+        let mut found = None;
+        for (key_id, key) in &state.keys {
+            if constant_time_eq(hash.as_bytes(), key.token_sha256.as_bytes()) {
+                found = Some(key_id.clone());
+            }
+        }
+        found
+    }
+
+    /// Count a request under `key_id`, refusing it once today's quota is used up
+    pub fn admit(&self, key_id: &str) -> Result<(), QuotaExceededError> {
+        self.admit_at(key_id, Utc::now())
+    }
+
+    fn admit_at(&self, key_id: &str, now: DateTime<Utc>) -> Result<(), QuotaExceededError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(quota) = state.keys.get(key_id).map(|key| key.quota) else {
+            return Ok(());
+        };
+        let day = (key_id.to_owned(), now.date_naive());
+        let used = state.counters.get(&day).cloned().unwrap_or_default();
+        let exceeded = |quota: &str, limit: u64, used: u64| {
+            get_metrics().increment_quota_rejections(key_id, quota);
+            info!(
+                "API key {} is over its {} quota of {}",
+                key_id, quota, limit
+            );
+            QuotaExceededError {
+                error: format!("API key {key_id} used its {limit} {quota} for today"),
+                code: format!("quota_{quota}_exceeded"),
+                key_id: key_id.to_owned(),
+                limit,
+                used,
+                resets_at: next_reset(now),
+            }
+        };
+        if let Some(limit) = quota.requests_per_day
+            && used.requests >= limit
+        {
+            return Err(exceeded("requests", limit, used.requests));
+        }
+        if let Some(limit) = quota.tokens_per_day
+            && used.tokens >= limit
+        {
+            return Err(exceeded("tokens", limit, used.tokens));
+        }
+        state.counters.entry(day.clone()).or_default().requests += 1;
+        state.dirty.insert(day);
+        get_metrics().increment_api_key_requests(key_id);
+        Ok(())
+    }
+
+    /// Add what a compliance check under `key_id` blocked and generated
+    pub fn record_check(&self, key_id: &str, response: &ComplianceResponse) {
+        let tokens = response
+            .stages
+            .generation
+            .ran()
+            .and_then(|generation| generation.tokens_used)
+            .map_or(0, u64::from);
+        let blocked = !matches!(
+            response.status,
+            WorkflowStatus::Completed | WorkflowStatus::Sanitized
+        );
+        let mut state = self.state.lock().unwrap();
+        if !state.keys.contains_key(key_id) {
+            return;
+        }
+        let day = (key_id.to_owned(), Utc::now().date_naive());
+        let counters = state.counters.entry(day.clone()).or_default();
+        if blocked {
+            *counters.blocked.entry(response.status.name()).or_default() += 1;
+        }
+        counters.tokens += tokens;
+        counters.estimated_cost += tokens as f64 / 1000.0 * self.config.cost_per_1k_tokens;
+        state.dirty.insert(day);
+    }
+
+    /// Usage of `key_id` over `period`, or `None` for a key that is not registered
+    pub fn report(&self, key_id: &str, period: UsagePeriod) -> Option<UsageReport> {
+        let state = self.state.lock().unwrap();
+        state
+            .keys
+            .get(key_id)
+            .map(|key| build_report(&state, key_id, key.quota, period, Utc::now()))
+    }
+
+    /// Usage of every registered key over `period`, in key id order
+    pub fn reports(&self, period: UsagePeriod) -> Vec<UsageReport> {
+        let state = self.state.lock().unwrap();
+        let now = Utc::now();
+        state
+            .keys
+            .iter()
+            .map(|(key_id, key)| build_report(&state, key_id, key.quota, period, now))
+            .collect()
+    }
+
+    /// Replace the quota of `key_id`, recording the change in the audit trail
+    ///
+    /// The change lasts until restart; the registry file is read again at startup.
+    pub fn set_quota(
+        &self,
+        correlation_id: &str,
+        key_id: &str,
+        quota: KeyQuota,
+    ) -> Result<UsageReport, UsageError> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state
+            .keys
+            .get(key_id)
+            .map(|key| key.quota)
+            .ok_or_else(|| UsageError::UnknownKey(key_id.to_owned()))?;
+        self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: "api_key_quota_changed".to_owned(),
+            previous_hash: content_hash(&previous),
+            new_hash: content_hash(&quota),
+            snapshot_version: None,
+            detail: Some(format!(
+                "{key_id}: {}",
+                serde_json::to_string(&quota).unwrap_or_default()
+            )),
+        })?;
+        info!("Quota of API key {} changed to {:?}", key_id, quota);
+        if let Some(key) = state.keys.get_mut(key_id) {
+            key.quota = quota;
+        }
+        Ok(build_report(
+            &state,
+            key_id,
+            quota,
+            UsagePeriod::Day,
+            Utc::now(),
+        ))
+    }
+
+    /// Write the counters changed since the last flush and forget days no longer kept,
+    /// returning how many counters were written
+    pub fn flush(&self) -> Result<usize, UsageStoreError> {
+        let dirty: Vec<DailyUsage> = {
+            let mut state = self.state.lock().unwrap();
+            let first_kept = first_retained_day(Utc::now());
+            state.counters.retain(|(_, date), _| *date >= first_kept);
+            let dirty = std::mem::take(&mut state.dirty);
+            dirty
+                .into_iter()
+                .filter_map(|day| {
+                    let counters = state.counters.get(&day)?.clone();
+                    Some(DailyUsage {
+                        key_id: day.0,
+                        date: day.1,
+                        counters,
+                    })
+                })
+                .collect()
+        };
+        if dirty.is_empty() {
+            return Ok(0);
+        }
+        if let Err(e) = self.store.upsert(&dirty) {
+            // Written again on the next flush
+            let mut state = self.state.lock().unwrap();
+            state
+                .dirty
+                .extend(dirty.into_iter().map(|daily| (daily.key_id, daily.date)));
+            return Err(e);
+        }
+        Ok(dirty.len())
+    }
+
+    /// Run [`flush`](Self::flush) every `interval`
+    pub fn spawn_flusher(&self, interval: Duration) -> JoinHandle<()> {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let flushing = tracker.clone();
+                match tokio::task::spawn_blocking(move || flushing.flush()).await {
+                    Ok(Err(e)) => warn!("Could not write API key usage: {}", e),
+                    Err(e) => warn!("API key usage flush panicked: {}", e),
+                    Ok(Ok(_)) => {}
+                }
+            }
+        })
+    }
+}
+
+fn build_report(
+    state: &TrackerState,
+    key_id: &str,
+    quota: KeyQuota,
+    period: UsagePeriod,
+    now: DateTime<Utc>,
+) -> UsageReport {
+    let to = now.date_naive();
+    let from = to - Days::new(u64::from(period.days()) - 1);
+    let mut usage = UsageCounters::default();
+    for counters in state
+        .counters
+        .range((key_id.to_owned(), from)..=(key_id.to_owned(), to))
+        .map(|(_, counters)| counters)
+    {
+        usage.add(counters);
+    }
+    let today = state
+        .counters
+        .get(&(key_id.to_owned(), to))
+        .cloned()
+        .unwrap_or_default();
+    UsageReport {
+        key_id: key_id.to_owned(),
+        period,
+        from,
+        to,
+        usage,
+        quota,
+        remaining_today: QuotaRemaining {
+            requests: quota
+                .requests_per_day
+                .map(|limit| limit.saturating_sub(today.requests)),
+            tokens: quota
+                .tokens_per_day
+                .map(|limit| limit.saturating_sub(today.tokens)),
+        },
+        resets_at: next_reset(now),
+    }
+}
+
+fn first_retained_day(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive() - Days::new(RETAINED_DAYS - 1)
+}
+
+/// Next UTC midnight, when a new day's counts start
+fn next_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+    (now.date_naive() + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight exists")
+        .and_utc()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Debug, Error)]
+pub enum UsageError {
+    #[error("no API key is registered as {0}")]
+    UnknownKey(String),
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+#[cfg(feature = "sled-storage")]
+use sled::{Db, Tree};
+use thiserror::Error;
+
+use super::dtos::DailyUsage;
+
+#[cfg(feature = "sled-storage")]
+const USAGE_TREE: &str = "api_key_usage";
+
+/// Per-key daily usage counters, written periodically by the usage tracker
+pub trait UsageStore: Send + Sync {
+    /// Insert the counters of one key and day, replacing those stored before
+    fn upsert(&self, usage: &[DailyUsage]) -> Result<(), UsageStoreError>;
+    /// Counters of every key from `since` on
+    fn since(&self, since: NaiveDate) -> Result<Vec<DailyUsage>, UsageStoreError>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryUsageStore {
+    inner: Arc<Mutex<BTreeMap<(String, NaiveDate), DailyUsage>>>,
+}
+
+impl InMemoryUsageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsageStore for InMemoryUsageStore {
+    fn upsert(&self, usage: &[DailyUsage]) -> Result<(), UsageStoreError> {
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| UsageStoreError::LockPoisoned)?;
+        for daily in usage {
+            inner.insert((daily.key_id.clone(), daily.date), daily.clone());
+        }
+        Ok(())
+    }
+
+    fn since(&self, since: NaiveDate) -> Result<Vec<DailyUsage>, UsageStoreError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|_| UsageStoreError::LockPoisoned)?
+            .values()
+            .filter(|daily| daily.date >= since)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Usage counters kept in their own tree of the audit database, keyed by key id and day
+#[cfg(feature = "sled-storage")]
+#[derive(Clone)]
+pub struct SledUsageStore {
+    tree: Tree,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledUsageStore {
+    pub fn new(db: &Db) -> Result<Self, UsageStoreError> {
+        let tree = db
+            .open_tree(USAGE_TREE)
+            .map_err(|e| UsageStoreError::DatabaseError(e.to_string()))?;
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl UsageStore for SledUsageStore {
+    fn upsert(&self, usage: &[DailyUsage]) -> Result<(), UsageStoreError> {
+        for daily in usage {
+            let serialized = serde_json::to_vec(daily)
+                .map_err(|e| UsageStoreError::SerializationError(e.to_string()))?;
+            let key = format!("{}\u{0}{}", daily.key_id, daily.date);
+            self.tree
+                .insert(key.as_bytes(), serialized)
+                .map_err(|e| UsageStoreError::DatabaseError(e.to_string()))?;
+        }
+        self.tree
+            .flush()
+            .map_err(|e| UsageStoreError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn since(&self, since: NaiveDate) -> Result<Vec<DailyUsage>, UsageStoreError> {
+        let mut usage = Vec::new();
+        for entry in self.tree.iter().values() {
+            let data = entry.map_err(|e| UsageStoreError::DatabaseError(e.to_string()))?;
+            let daily: DailyUsage = serde_json::from_slice(&data)
+                .map_err(|e| UsageStoreError::SerializationError(e.to_string()))?;
+            if daily.date >= since {
+                usage.push(daily);
+            }
+        }
+        Ok(usage)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UsageStoreError {
+    #[error("usage store lock poisoned")]
+    LockPoisoned,
+    #[error("database error: {0}")]
+    DatabaseError(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+}
@@ -1,95 +1,404 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::{
-    Json, Router,
-    extract::State,
-    http::StatusCode,
-    routing::{get, post},
+    Extension, Json, Router,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
 };
 use serde_json;
 use tokio::net::TcpListener;
-use tower_http::cors::{Any, CorsLayer};
-use tracing::{debug, error, info};
+use tokio_util::sync::CancellationToken;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::CorsLayer;
+use tracing::{debug, error, info, warn};
 
-use crate::config::settings::{
-    AppSettings, DEFAULT_MISTRAL_BASE_URL, DEFAULT_MISTRAL_EMBEDDING_MODEL,
-    DEFAULT_MISTRAL_GENERATION_MODEL, DEFAULT_MISTRAL_MODERATION_MODEL,
+use crate::config::cors::{CorsPolicy, CorsSettings};
+use crate::config::layers::EffectiveConfig;
+use crate::config::settings::{AppSettings, SettingsError};
+use crate::modules::admission::dtos::{AdmissionLimits, AdmissionStatus};
+use crate::modules::admission::service::{AdmissionController, AdmissionPermit};
+use crate::modules::alerting::service::WebhookNotifier;
+use crate::modules::appeals::dtos::{
+    Appeal, AppealRequest, AppealState, AppealsResponse, ResolveAppealRequest,
 };
-use crate::modules::audit::logger::AuditLogger;
+#[cfg(feature = "sled-storage")]
+use crate::modules::appeals::storage::SledAppealStore;
+use crate::modules::appeals::storage::{AppealStore, InMemoryAppealStore};
+use crate::modules::audit::batched::BatchedAuditStorage;
+use crate::modules::audit::disk::{DiskFootprint, DiskMonitor, DiskStatus};
+use crate::modules::audit::integrity::{
+    AuditIntegrityChecker, IntegrityStatus, VerificationReport,
+};
+use crate::modules::audit::logger::{AuditLogger, ConfigFingerprint};
+#[cfg(feature = "sqlite-storage")]
+use crate::modules::audit::sqlite::SqliteAuditStorage;
+#[cfg(feature = "sled-storage")]
+use crate::modules::audit::storage::SledAuditStorage;
 use crate::modules::audit::storage::{
-    AuditStorage, AuditTrailRequest, AuditTrailResponse, SledAuditStorage,
+    AuditBackend, AuditStorage, AuditStorageError, AuditTrailRequest, AuditTrailResponse,
+    InMemoryAuditStorage,
 };
+use crate::modules::audit::suppression::{self, SYNTHETIC_HEADER};
 use crate::modules::bias_detection::service::BiasDetectionService;
+use crate::modules::caches::dtos::{CacheFlush, CachesResponse};
+use crate::modules::caches::service::CacheRegistry;
+use crate::modules::chaos::client::ChaosMistralClient;
+use crate::modules::chaos::dtos::{ChaosConfig, ChaosStatus};
+use crate::modules::chaos::service::{ChaosController, ChaosError};
+use crate::modules::chaos::storage::ChaosAuditStorage;
+use crate::modules::config_management::dtos::{
+    ConfigHistoryResponse, ConfigRestoreResponse, ConfigSnapshot, RulePromotionResponse,
+};
+use crate::modules::config_management::service::{ConfigManagementError, ConfigManagementService};
+#[cfg(feature = "sled-storage")]
+use crate::modules::config_management::storage::SledConfigHistory;
+use crate::modules::config_management::storage::{ConfigHistoryStorage, InMemoryConfigHistory};
+use crate::modules::demo::client::DemoMistralClient;
+use crate::modules::demo::service::DemoQuota;
+use crate::modules::dependencies::dtos::{Dependency, DependencyStatus, StartupConfig};
+use crate::modules::dependencies::service::{DependencyHealth, StartupError, probe};
+use crate::modules::diagnostics::dtos::SlowRequestsResponse;
+use crate::modules::diagnostics::service::SlowRequestLog;
 use crate::modules::eu_law_compliance::dtos::{
-    ComplianceConfigurationRequest, ComplianceConfigurationResponse, ComplianceReportRequest,
-    ComplianceReportResponse,
+    ComplianceConfigurationRequest, ComplianceConfigurationResponse, ComplianceReportList,
+    ComplianceReportRequest, ComplianceReportResponse, StoredComplianceReport,
 };
+use crate::modules::eu_law_compliance::reports::ComplianceReportService;
 use crate::modules::eu_law_compliance::service::EuLawComplianceService;
+#[cfg(feature = "sled-storage")]
+use crate::modules::eu_law_compliance::storage::SledReportStore;
+use crate::modules::eu_law_compliance::storage::{
+    ComplianceReportStore, InMemoryReportStore, ReportStoreError,
+};
+use crate::modules::exemptions::dtos::{Exemption, ExemptionRequest, ExemptionsResponse};
+use crate::modules::exemptions::service::ExemptionError;
+use crate::modules::http_cache::dtos::CachePolicy;
+use crate::modules::http_cache::service as http_cache;
+use crate::modules::load_replay::dtos::{
+    REPLAY_HEADER, REPLAY_TARGET_HEADER, REPLAY_TARGET_STAGING,
+};
+use crate::modules::maintenance::dtos::{MaintenanceRequest, MaintenanceStatus};
+use crate::modules::maintenance::service::{MaintenanceError, MaintenanceService};
 use crate::modules::mistral_ai::client::{HttpMistralClient, MistralClient};
 use crate::modules::mistral_ai::dtos::ModelValidationResponse;
-use crate::modules::mistral_ai::service::MistralService;
+use crate::modules::mistral_ai::service::{MODERATION_NOT_CONFIGURED, MistralService};
+use crate::modules::model_watch::dtos::{ModelHistoryResponse, ModelWatchStatus};
+use crate::modules::model_watch::service::ModelWatch;
+#[cfg(feature = "sled-storage")]
+use crate::modules::model_watch::storage::SledModelHistory;
+use crate::modules::model_watch::storage::{InMemoryModelHistory, ModelHistoryStore};
+#[cfg(feature = "sled-storage")]
+use crate::modules::prompt_firewall::archive::SledRulesArchive;
+use crate::modules::prompt_firewall::archive::{FirewallRulesArchive, InMemoryRulesArchive};
+use crate::modules::prompt_firewall::dtos::{
+    FirewallMissesResponse, FirewallRulesResponse, RuleAssertionReport,
+};
+use crate::modules::prompt_firewall::rules;
 use crate::modules::prompt_firewall::service::PromptFirewallService;
-use crate::modules::semantic_detection::service::SemanticDetectionService;
-use crate::modules::telemetry::correlation::generate_correlation_id;
-use crate::modules::telemetry::metrics::{RequestTimer, get_metrics};
-use crate::modules::telemetry::tracing::{create_span_with_correlation, log_with_correlation};
-use crate::workflow::{ComplianceEngine, ComplianceRequest, ComplianceResponse};
+use crate::modules::self_test::dtos::SelfTestReport;
+use crate::modules::self_test::service::SelfTestService;
+#[cfg(feature = "sled-storage")]
+use crate::modules::semantic_detection::candidates::SledCandidateStore;
+use crate::modules::semantic_detection::candidates::{CandidateStore, InMemoryCandidateStore};
+use crate::modules::semantic_detection::dtos::{
+    ApprovedCandidate, AttackCandidate, AttackCandidatesResponse, BankHygieneReport,
+    CandidateGenerationReport, SemanticScanRequest, SemanticScanResult,
+};
+use crate::modules::semantic_detection::service::{
+    SemanticDetectionError, SemanticDetectionService,
+};
+use crate::modules::slo::dtos::SloStatusResponse;
+use crate::modules::slo::service::SloTracker;
+use crate::modules::telemetry::context::RequestContext;
+#[cfg(feature = "sled-storage")]
+use crate::modules::telemetry::correlation::SledCorrelationIdStore;
+use crate::modules::telemetry::correlation::{
+    CorrelationIdGenerator, CorrelationIdStore, InMemoryCorrelationIdStore,
+    install_correlation_id_generator,
+};
+use crate::modules::telemetry::metrics::get_metrics;
+use crate::modules::telemetry::middleware::request_context_middleware;
+#[cfg(feature = "metrics-prometheus")]
+use crate::modules::telemetry::prometheus::{MetricsListener, install_prometheus_recorder};
+use crate::modules::telemetry::sampling::get_log_sampler;
+use crate::modules::telemetry::tracing::log_with_correlation;
+use crate::modules::usage::dtos::{
+    ApiKeyIdentity, KeyQuota, UsageQuery, UsageReport, UsageResponse,
+};
+use crate::modules::usage::service::{UsageError, UsageTracker};
+#[cfg(feature = "sled-storage")]
+use crate::modules::usage::storage::SledUsageStore;
+use crate::modules::usage::storage::{InMemoryUsageStore, UsageStore};
+use crate::workflow::{
+    API_VERSION, AppealError, CandidateError, ComplianceEngine, ComplianceOptions,
+    ComplianceRequest, ComplianceResponse, DecisionLineage, DocumentScanRequest,
+    DocumentScanResponse, ExchangeValidationResponse, ExplainError, ExplanationAudience,
+    LineageError, PolicyDryRunRequest, PolicyDryRunResponse, PromptTemplate, ReplayError,
+    ReplayMode, ReplayReport, RequestValidationError, ResponseProfile, StageState,
+    StageToggleError, StageToggleRequest, StageTogglesResponse, TemplateRegistration,
+    TemplatesResponse, ThreatCategoryStats, ToggleableStage, ValidateExchangeRequest,
+    WorkflowError, WorkflowPolicy, parse_window,
+};
+
+/// Seconds between recomputations of the SLO gauges while traffic is idle
+const SLO_REFRESH_INTERVAL_SECS: u64 = 15;
 
 #[derive(Clone)]
 pub struct AppState {
     pub engine: Arc<ComplianceEngine>,
+    pub config_management: ConfigManagementService,
+    pub maintenance: MaintenanceService,
+    /// Bounds concurrent workflows; `None` admits every request at once
+    pub admission: Option<AdmissionController>,
+    pub self_test: SelfTestService,
+    /// Bearer token for admin routes; `None` disables them
+    pub admin_token: Option<String>,
+    /// Bearer token for support staff, accepted only by the decision explanation route
+    pub support_token: Option<String>,
+    /// Cross-origin policies applied by the router
+    pub cors: CorsSettings,
+    /// Fed by the request-context middleware for every route
+    pub slo: SloTracker,
+    /// Diagnostics of requests over their latency threshold, fed by the same middleware
+    pub slow_requests: SlowRequestLog,
+    /// In-memory caches of the engine under one memory budget
+    pub caches: CacheRegistry,
+    /// Settings in effect and their sources, with secrets masked
+    pub effective_config: Arc<EffectiveConfig>,
+    /// `Cache-Control` lifetimes of the read-only endpoints that send an `ETag`
+    pub http_cache: CachePolicy,
+    /// Unavailable unless the server was started with `CHAOS_MODE=true`
+    pub chaos: ChaosController,
+    /// Verifies the audit chain for `GET /ready` and `POST /api/audit/verify`
+    pub audit_integrity: AuditIntegrityChecker,
+    /// Measures the sled database; `None` when nothing is stored in sled
+    pub disk: Option<DiskMonitor>,
+    /// Startup validation of Mistral and the semantic bank, which gates `GET /ready`
+    pub dependencies: DependencyHealth,
+    /// Per-address quota of a public demo; `None` outside demo mode
+    pub demo_quota: Option<DemoQuota>,
+    /// Compares the Mistral model list with the one seen before
+    pub model_watch: ModelWatch,
+    /// Generated EU compliance reports, one per correlation id
+    pub reports: ComplianceReportService,
+    /// Staging instance that accepts traffic replayed by `sentinel-replay`
+    pub accept_replay_traffic: bool,
+    /// Counts what each API key uses and enforces its daily quota
+    pub usage: UsageTracker,
+}
+
+/// Operational overview returned by `GET /api/admin/summary`
+#[derive(Debug, serde::Serialize)]
+struct SystemSummary {
+    version: &'static str,
+    api_version: u32,
+    maintenance: MaintenanceStatus,
+    /// Concurrent workflows, queue depth and shed requests when admission control is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    admission: Option<AdmissionStatus>,
+    cors: CorsSettings,
+    /// Content hashes of the rule sets currently in effect
+    config_fingerprint: ConfigFingerprint,
+    /// Shown here so injected faults are never forgotten
+    chaos: ChaosStatus,
+    /// Pipeline stages an administrator paused, which every request currently skips
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    disabled_stages: Vec<ToggleableStage>,
+    /// Size of the sled database and whether audit writes are hash-only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<DiskStatus>,
+    /// Each dependency's startup policy and whether it has validated
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<DependencyStatus>,
+    /// Upstream model changes that need attention; `None` while the watch is off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model_watch: Option<ModelWatchStatus>,
+}
+
+/// Body of `GET /ready`
+#[derive(Debug, serde::Serialize)]
+struct ReadinessReport {
+    /// False while a lazily validated dependency has not yet validated, or while the
+    /// audit chain is broken and `AUDIT_INTEGRITY_FAIL_READINESS` is set
+    ready: bool,
+    audit_chain: IntegrityStatus,
+    /// Shows `audit_mode: hash_only` while the database is over its high-water mark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<DiskStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<DependencyStatus>,
+    /// Model watch alerts are warnings and never make the server unready
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model_watch: Option<ModelWatchStatus>,
 }
 
-/// Telemetry middleware for request tracking
-async fn telemetry_middleware(
+/// Refuse demo requests over the caller's quota; only requests that run checks count
+async fn enforce_demo_quota(
+    State(quota): State<DemoQuota>,
+    Extension(context): Extension<RequestContext>,
     request: axum::http::Request<axum::body::Body>,
     next: axum::middleware::Next,
-) -> axum::response::Response {
-    let method = request.method().clone();
-    let path = request.uri().path().to_string();
-    let endpoint = format!("{}:{}", method, path);
-
-    // Generate correlation ID
-    let correlation_id = generate_correlation_id();
-
-    // Start timer and increment active requests
-    let timer = RequestTimer::new();
-    get_metrics().increment_active_requests();
-    get_metrics().increment_requests(method.as_str(), &endpoint);
-
-    // Add correlation ID to request headers
-    let mut request = request;
-    request.headers_mut().insert(
-        "X-Correlation-ID",
-        axum::http::HeaderValue::from_str(&correlation_id)
-            .expect("correlation ID should be valid header value"),
-    );
-
-    // Create span with correlation ID
-    let span = create_span_with_correlation(&correlation_id, "request");
-    let _enter = span.enter();
+) -> Response {
+    if request.method() != axum::http::Method::POST {
+        return next.run(request).await;
+    }
+    // Without a peer address every caller shares one quota
+    let client = context
+        .client_ip
+        .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED));
+    match quota.admit(client) {
+        Ok(()) => next.run(request).await,
+        Err(exceeded) => {
+            info!("Demo quota exceeded by {}", client);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, exceeded.retry_after_secs.to_string())],
+                exceeded.to_string(),
+            )
+                .into_response()
+        }
+    }
+}
 
-    log_with_correlation(
-        &correlation_id,
-        tracing::Level::INFO,
-        &format!("Request started: {} {}", method, path),
-    );
+/// Identify callers by their API key and hold them to its daily quota
+///
+/// Only requests that run checks count against the quota. Admin and support tokens are
+/// not metered. Without a bearer token a `POST` is refused, unless the usage config
+/// lets it through anonymously, and other requests stay anonymous; any other token must
+/// belong to a registered key. Nothing is checked while no key is registered.
+async fn meter_api_key(
+    State(state): State<AppState>,
+    mut request: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Response {
+    if !state.usage.is_enabled() {
+        return next.run(request).await;
+    }
+    let headers = request.headers();
+    let Some(token) = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        if request.method() == axum::http::Method::POST && state.usage.config().require_key {
+            return (
+                StatusCode::UNAUTHORIZED,
+                "an API key is required".to_owned(),
+            )
+                .into_response();
+        }
+        return next.run(request).await;
+    };
+    if bearer_matches(headers, state.admin_token.as_deref())
+        || bearer_matches(headers, state.support_token.as_deref())
+    {
+        return next.run(request).await;
+    }
+    let Some(key_id) = state.usage.authenticate(token) else {
+        return (StatusCode::UNAUTHORIZED, "unknown API key".to_owned()).into_response();
+    };
+    if request.method() == axum::http::Method::POST
+        && let Err(exceeded) = state.usage.admit(&key_id)
+    {
+        let retry_after = (exceeded.resets_at - chrono::Utc::now())
+            .num_seconds()
+            .max(1);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(exceeded),
+        )
+            .into_response();
+    }
+    request.extensions_mut().insert(ApiKeyIdentity { key_id });
+    next.run(request).await
+}
 
-    // Process request
-    let response = next.run(request).await;
+/// Reject admin requests that do not carry the configured bearer token
+async fn require_admin_token(
+    State(state): State<AppState>,
+    request: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "admin endpoints are disabled; set ADMIN_API_TOKEN to enable them".to_owned(),
+        ));
+    };
+    let provided = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid admin token".to_owned(),
+        )),
+    }
+}
 
-    // Record metrics
-    let duration = timer.elapsed_seconds();
-    get_metrics().record_latency(method.as_str(), &endpoint, duration);
-    get_metrics().decrement_active_requests();
+/// Accept the admin or support token on the decision explanation route, and pass on
+/// which audience the caller belongs to
+async fn require_explain_token(
+    State(state): State<AppState>,
+    mut request: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    if state.admin_token.is_none() && state.support_token.is_none() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "decision explanations are disabled; set ADMIN_API_TOKEN or SUPPORT_API_TOKEN \
+             to enable them"
+                .to_owned(),
+        ));
+    }
+    let provided = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let matches = |expected: &Option<String>| {
+        expected
+            .as_deref()
+            .is_some_and(|expected| constant_time_eq(provided.as_bytes(), expected.as_bytes()))
+    };
+    let audience = if matches(&state.admin_token) {
+        ExplanationAudience::Admin
+    } else if matches(&state.support_token) {
+        ExplanationAudience::Support
+    } else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid admin or support token".to_owned(),
+        ));
+    };
+    request.extensions_mut().insert(audience);
+    Ok(next.run(request).await)
+}
 
-    log_with_correlation(
-        &correlation_id,
-        tracing::Level::INFO,
-        &format!("Request completed: {} {} in {:.3}s", method, path, duration),
-    );
+/// Whether `headers` carry `expected` as their bearer token
+fn bearer_matches(headers: &HeaderMap, expected: Option<&str>) -> bool {
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (provided, expected) {
+        (Some(token), Some(expected)) => constant_time_eq(token.as_bytes(), expected.as_bytes()),
+        _ => false,
+    }
+}
 
-    response
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 /// Framework server builder
@@ -101,63 +410,505 @@ pub struct PromptSentinelServer {
 impl PromptSentinelServer {
     /// Create a new server instance
     pub fn new(config: AppSettings, engine: ComplianceEngine) -> Self {
+        let config_management = ConfigManagementService::new(
+            &engine,
+            Arc::new(InMemoryConfigHistory::new()),
+            config.config_history_limit,
+        );
+        let maintenance = MaintenanceService::new(engine.audit_logger().clone());
+        let admin_token = config.admin_token.clone();
+        let support_token = config.support_token.clone();
+        let cors = config.cors.clone();
+        let slo = SloTracker::new(config.slo);
+        let slow_requests = SlowRequestLog::new(config.slow_requests.clone());
+        let caches = CacheRegistry::new(config.cache_budget);
+        engine.register_caches(&caches);
+        let http_cache = config.http_cache.clone();
+        let audit_integrity = AuditIntegrityChecker::new(
+            engine.audit_logger().storage().clone(),
+            config.audit_integrity,
+        )
+        .with_notifier(config.alert_webhook_url.clone().map(WebhookNotifier::new));
+        get_log_sampler().set_window(std::time::Duration::from_secs(
+            config.log_sampling_window_secs,
+        ));
+        let demo_quota = config.demo.enabled.then(|| DemoQuota::new(&config.demo));
+        let model_watch =
+            Self::build_model_watch(&config, &engine, Arc::new(InMemoryModelHistory::new()));
+        let accept_replay_traffic = config.accept_replay_traffic;
+        let reports = ComplianceReportService::new(
+            Arc::new(InMemoryReportStore::new()),
+            config.compliance_report_retention_days,
+        );
+        let usage = UsageTracker::new(
+            &config.api_keys,
+            config.usage,
+            Arc::new(InMemoryUsageStore::new()),
+            engine.audit_logger().clone(),
+        );
         Self {
             config,
             state: AppState {
                 engine: Arc::new(engine),
+                config_management,
+                maintenance,
+                admission: None,
+                self_test: SelfTestService::new(),
+                admin_token,
+                support_token,
+                cors,
+                slo,
+                slow_requests,
+                caches,
+                effective_config: Arc::new(EffectiveConfig::default()),
+                http_cache,
+                chaos: ChaosController::default(),
+                audit_integrity,
+                disk: None,
+                dependencies: DependencyHealth::default(),
+                demo_quota,
+                model_watch,
+                reports,
+                accept_replay_traffic,
+                usage,
             },
         }
     }
 
+    fn build_model_watch(
+        config: &AppSettings,
+        engine: &ComplianceEngine,
+        history: Arc<dyn ModelHistoryStore>,
+    ) -> ModelWatch {
+        let notifier = config
+            .alert_webhook_url
+            .clone()
+            .filter(|_| config.model_watch.notify)
+            .map(WebhookNotifier::new);
+        ModelWatch::new(
+            engine.mistral_service().clone(),
+            history,
+            config.model_watch.clone(),
+        )
+        .with_notifier(notifier)
+    }
+
+    /// Keep configuration snapshots in `history` instead of memory
+    pub fn with_config_history(mut self, history: Arc<dyn ConfigHistoryStorage>) -> Self {
+        self.state.config_management = ConfigManagementService::new(
+            &self.state.engine,
+            history,
+            self.config.config_history_limit,
+        );
+        self
+    }
+
+    /// Keep the model watch's history in `history` instead of memory
+    pub fn with_model_history(mut self, history: Arc<dyn ModelHistoryStore>) -> Self {
+        self.state.model_watch = Self::build_model_watch(&self.config, &self.state.engine, history);
+        self
+    }
+
+    /// Keep generated compliance reports in `store` instead of memory
+    pub fn with_report_store(mut self, store: Arc<dyn ComplianceReportStore>) -> Self {
+        self.state.reports =
+            ComplianceReportService::new(store, self.config.compliance_report_retention_days);
+        self
+    }
+
+    /// Keep per-key usage counters in `store` instead of memory, starting from those it
+    /// already holds
+    pub fn with_usage_store(mut self, store: Arc<dyn UsageStore>) -> Self {
+        self.state.usage = UsageTracker::new(
+            &self.config.api_keys,
+            self.config.usage,
+            store,
+            self.state.engine.audit_logger().clone(),
+        );
+        self
+    }
+
+    /// Take fault injection settings from `chaos`, which wraps the engine's dependencies
+    pub fn with_chaos(mut self, chaos: ChaosController) -> Self {
+        self.state.chaos = chaos;
+        self
+    }
+
+    /// Measure the database with `monitor`, whose high-water policy applies to the
+    /// engine's audit writes
+    pub fn with_disk_monitor(mut self, monitor: Option<DiskMonitor>) -> Self {
+        let logger = self.state.engine.audit_logger().clone();
+        self.state.disk = monitor.map(|monitor| monitor.with_audit_logger(logger));
+        self
+    }
+
+    /// Bound the compliance workflows run at once by `limits`, queueing and then
+    /// shedding requests over them; `None` leaves requests unbounded
+    pub fn with_admission_control(mut self, limits: Option<AdmissionLimits>) -> Self {
+        self.state.admission = limits.map(AdmissionController::new);
+        self
+    }
+
+    /// Report the startup validation tracked by `dependencies` from `GET /ready`
+    pub fn with_dependency_health(mut self, dependencies: DependencyHealth) -> Self {
+        self.state.dependencies = dependencies;
+        self
+    }
+
+    /// Report `effective` from `GET /api/config/effective`
+    pub fn with_effective_config(mut self, effective: EffectiveConfig) -> Self {
+        self.state.effective_config = Arc::new(effective);
+        self
+    }
+
+    /// The model watch the server runs, for checks outside its schedule
+    pub fn model_watch(&self) -> &ModelWatch {
+        &self.state.model_watch
+    }
+
+    /// The per-key usage tracker, for flushing its counters outside the schedule
+    pub fn usage(&self) -> &UsageTracker {
+        &self.state.usage
+    }
+
     /// Build the axum router with all endpoints
-    fn build_router(&self) -> Router {
-        Router::new()
+    ///
+    /// Public compliance endpoints use the public CORS policy; audit, configuration and
+    /// admin endpoints use the stricter admin policy. A demo serves the public
+    /// compliance endpoints only, under a per-address quota.
+    pub fn build_router(&self) -> Router {
+        let public_routes = Router::new()
             .route("/api/compliance/check", post(check_compliance))
+            .route("/api/compliance/options", get(get_compliance_options))
+            .route("/api/compliance/scan-documents", post(scan_documents))
+            .route("/api/compliance/validate-exchange", post(validate_exchange))
+            .route("/api/semantic/scan", post(semantic_scan))
             .route("/health", get(health_check))
+            .route("/ready", get(readiness_check))
             .route("/api/mistral/health", get(mistral_health_check))
             .route("/v1/models", get(validate_models))
-            .route("/api/audit/trail", post(get_audit_trail))
             .route("/api/compliance/report", post(generate_compliance_report))
+            .route("/api/usage/self", get(get_own_usage))
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                meter_api_key,
+            ));
+        let router = match &self.state.demo_quota {
+            // Only the checks themselves; appeals are left out, since nobody reviews them
+            Some(quota) => Router::new().merge(
+                public_routes
+                    .route_layer(axum::middleware::from_fn_with_state(
+                        quota.clone(),
+                        enforce_demo_quota,
+                    ))
+                    .layer(cors_layer(&self.state.cors.public)),
+            ),
+            None => Router::new()
+                .merge(
+                    public_routes
+                        .route("/api/appeals", post(file_appeal))
+                        .layer(cors_layer(&self.state.cors.public)),
+                )
+                .merge(self.private_routes()),
+        };
+
+        router
+            .route_layer(axum::middleware::from_fn(request_context_middleware))
+            .layer(Extension(self.state.slo.clone()))
+            .layer(Extension(self.state.slow_requests.clone()))
+            .layer(Extension(self.config.correlation_ids.clone()))
+            .with_state(self.state.clone())
+    }
+
+    /// Audit, configuration and admin endpoints, which a demo never serves
+    fn private_routes(&self) -> Router<AppState> {
+        // Bulk exports are compressed with zstd or gzip when `Accept-Encoding` asks for it
+        let operator_routes = Router::new()
+            .route(
+                "/api/audit/trail",
+                post(get_audit_trail).layer(CompressionLayer::new()),
+            )
             .route("/api/compliance/config", get(get_compliance_config))
             .route("/api/compliance/config", post(update_compliance_config))
-            .layer(
-                CorsLayer::new()
-                    .allow_origin(Any)
-                    .allow_methods(Any)
-                    .allow_headers(Any),
+            .route(
+                "/api/compliance/reports",
+                get(list_compliance_reports).layer(CompressionLayer::new()),
             )
-            .route_layer(axum::middleware::from_fn(telemetry_middleware))
-            .with_state(self.state.clone())
+            .route("/api/compliance/reports/{id}", get(get_compliance_report))
+            .route("/api/models/history", get(get_model_history))
+            .route("/api/slo/status", get(get_slo_status))
+            .layer(cors_layer(&self.state.cors.admin));
+
+        let explain_routes = Router::new()
+            .route(
+                "/api/decisions/{correlation_id}/explain",
+                get(explain_decision),
+            )
+            .route(
+                "/api/decisions/{correlation_id}/lineage",
+                get(get_decision_lineage),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                require_explain_token,
+            ))
+            .layer(cors_layer(&self.state.cors.admin));
+
+        // CORS sits outside the token check so preflight requests get an answer
+        let admin_routes = Router::new()
+            .route("/api/admin/maintenance", get(get_maintenance_status))
+            .route("/api/admin/maintenance", post(update_maintenance))
+            .route("/api/admin/stages", get(get_stage_toggles))
+            .route("/api/admin/stages", post(toggle_stage))
+            .route("/api/admin/summary", get(get_system_summary))
+            .route("/api/firewall/rules", get(get_firewall_rules))
+            .route("/api/firewall/rules/test", post(test_firewall_rules))
+            .route("/api/policy/dry-run", post(dry_run_policy))
+            .route("/api/config/snapshot", get(get_config_snapshot))
+            .route("/api/config/restore", post(restore_config))
+            .route("/api/config/history", get(get_config_history))
+            .route("/api/config/effective", get(get_effective_config))
+            .route(
+                "/api/firewall/rules/{id}/promote",
+                post(promote_firewall_rule),
+            )
+            .route("/api/exemptions", get(list_exemptions))
+            .route("/api/exemptions", post(grant_exemption))
+            .route("/api/exemptions/{id}", delete(revoke_exemption))
+            .route("/api/appeals", get(list_appeals))
+            .route("/api/appeals/{id}/resolve", post(resolve_appeal))
+            .route("/api/templates", get(list_templates))
+            .route("/api/templates", post(register_template))
+            .route("/api/selftest", post(run_self_test))
+            .route("/api/audit/verify", post(verify_audit_chain))
+            .route("/api/audit/replay/{correlation_id}", post(replay_decision))
+            .route("/api/debug/slow-requests", get(get_slow_requests))
+            .route("/api/debug/caches", get(get_caches))
+            .route("/api/debug/caches/{name}/flush", post(flush_cache))
+            .route("/api/stats/firewall-misses", get(get_firewall_misses))
+            .route("/api/usage", get(get_usage))
+            .route("/api/usage/keys/{key_id}/quota", put(set_key_quota))
+            .route(
+                "/api/stats/threat-categories",
+                get(get_threat_category_stats),
+            )
+            .route("/api/chaos/config", post(configure_chaos))
+            .route(
+                "/api/semantic/candidates/generate",
+                post(generate_attack_candidates),
+            )
+            .route("/api/semantic/candidates", get(list_attack_candidates))
+            .route("/api/semantic/bank/report", get(get_bank_report))
+            .route(
+                "/api/semantic/candidates/{id}/approve",
+                post(approve_attack_candidate),
+            )
+            .route(
+                "/api/semantic/candidates/{id}",
+                delete(reject_attack_candidate),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                require_admin_token,
+            ))
+            .layer(cors_layer(&self.state.cors.admin));
+
+        Router::new()
+            .merge(operator_routes)
+            .merge(explain_routes)
+            .merge(admin_routes)
     }
 
     /// Start the server
     pub async fn start(self) -> Result<(), std::io::Error> {
+        self.start_with_shutdown(std::future::pending()).await
+    }
+
+    /// Start the server and stop it once `signal` completes
+    ///
+    /// The Prometheus metrics listener starts and stops with it. Requests in flight are
+    /// answered first, then API key usage is written and audit records still queued by
+    /// write-behind storage are flushed.
+    pub async fn start_with_shutdown(
+        self,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<(), std::io::Error> {
         let app = self.build_router();
         let addr = format!("0.0.0.0:{}", self.config.server_port);
 
         info!("Prompt Sentinel Server starting on {}", addr);
-        info!("Using sled for audit storage");
         info!("Framework version: {}", env!("CARGO_PKG_VERSION"));
 
+        let sweep_interval =
+            std::time::Duration::from_secs(self.config.exemption_sweep_interval_secs);
+        self.state.engine.exemptions().spawn_sweeper(sweep_interval);
+        self.state.engine.audit_logger().spawn_suppression_sweeper();
+        self.state
+            .slo
+            .spawn_refresher(std::time::Duration::from_secs(SLO_REFRESH_INTERVAL_SECS));
+        let integrity = self.config.audit_integrity;
+        if integrity.tail_interval_secs > 0 {
+            self.state
+                .audit_integrity
+                .spawn_tail_checks(std::time::Duration::from_secs(integrity.tail_interval_secs));
+        }
+        if integrity.full_interval_secs > 0 {
+            self.state
+                .audit_integrity
+                .spawn_full_checks(std::time::Duration::from_secs(integrity.full_interval_secs));
+        }
+        if self.config.model_watch.interval_secs > 0 {
+            self.state.model_watch.spawn(std::time::Duration::from_secs(
+                self.config.model_watch.interval_secs,
+            ));
+        }
+        if self.config.cache_budget.check_interval_secs > 0 {
+            self.state
+                .caches
+                .spawn_enforcer(std::time::Duration::from_secs(
+                    self.config.cache_budget.check_interval_secs,
+                ));
+        }
+        if self.state.usage.is_enabled() && self.config.usage.flush_interval_secs > 0 {
+            self.state
+                .usage
+                .spawn_flusher(std::time::Duration::from_secs(
+                    self.config.usage.flush_interval_secs,
+                ));
+        }
+        if let Some(disk) = &self.state.disk
+            && self.config.audit_disk.check_interval_secs > 0
+        {
+            disk.spawn_checks(std::time::Duration::from_secs(
+                self.config.audit_disk.check_interval_secs,
+            ));
+        }
+
         let listener = TcpListener::bind(&addr).await?;
-        axum::serve(listener, app).await
+        #[cfg(feature = "metrics-prometheus")]
+        let metrics = self.start_metrics().await;
+        #[cfg(not(feature = "metrics-prometheus"))]
+        warn!("Built without metrics-prometheus; METRICS_ADDR is ignored");
+        let served = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .with_graceful_shutdown(signal)
+        .await;
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = metrics {
+            metrics.shutdown().await;
+        }
+        served?;
+
+        let usage = self.state.usage.clone();
+        match tokio::task::spawn_blocking(move || usage.flush()).await {
+            Ok(Err(e)) => warn!("Could not write API key usage: {}", e),
+            Err(e) => warn!("API key usage flush panicked: {}", e),
+            Ok(Ok(_)) => {}
+        }
+        info!("Flushing queued audit records");
+        let audit_logger = self.state.engine.audit_logger().clone();
+        tokio::task::spawn_blocking(move || audit_logger.flush())
+            .await
+            .map_err(std::io::Error::other)?
+            .map_err(std::io::Error::other)
+    }
+}
+
+impl PromptSentinelServer {
+    /// Serve Prometheus metrics on `METRICS_ADDR`, unless it is empty
+    ///
+    /// Metrics are not worth failing startup over: when the recorder cannot be installed
+    /// or the address cannot be bound, the server runs without them.
+    #[cfg(feature = "metrics-prometheus")]
+    async fn start_metrics(&self) -> Option<MetricsListener> {
+        let handle = match install_prometheus_recorder() {
+            Ok(handle) => handle,
+            Err(e) => {
+                warn!("Metrics disabled: {}", e);
+                return None;
+            }
+        };
+        if self.config.metrics_addr.is_empty() {
+            info!("METRICS_ADDR is empty; metrics are recorded but not served");
+            return None;
+        }
+        match MetricsListener::bind(&self.config.metrics_addr, handle).await {
+            Ok(listener) => {
+                info!("Serving metrics on {}", listener.local_addr());
+                Some(listener)
+            }
+            Err(e) => {
+                warn!("Metrics not served: {}", e);
+                None
+            }
+        }
     }
 }
 
-async fn health_check() -> &'static str {
-    let correlation_id = generate_correlation_id();
+/// Layer for `policy`, falling back to same-origin only if it is invalid
+fn cors_layer(policy: &CorsPolicy) -> CorsLayer {
+    policy.layer().unwrap_or_else(|e| {
+        error!(
+            "Invalid CORS policy, allowing same-origin requests only: {}",
+            e
+        );
+        CorsLayer::new()
+    })
+}
+
+/// `OK`, with the staging marker `sentinel-replay` checks for on a replay target
+async fn health_check(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+) -> Response {
     log_with_correlation(
-        &correlation_id,
+        &context.correlation_id,
         tracing::Level::INFO,
         "Health check requested",
     );
-    "OK"
+    if state.accept_replay_traffic {
+        ([(REPLAY_TARGET_HEADER, REPLAY_TARGET_STAGING)], "OK").into_response()
+    } else {
+        "OK".into_response()
+    }
+}
+
+/// Ready once every lazily validated dependency has validated, unless the audit chain
+/// is broken and readiness is configured to fail with it
+///
+/// The integrity status is reported either way, so a broken chain is visible to
+/// whoever polls readiness.
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<ReadinessReport>) {
+    let ready = !state.audit_integrity.blocks_readiness() && state.dependencies.is_ready();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(ReadinessReport {
+            ready,
+            audit_chain: state.audit_integrity.status(),
+            disk: state.disk.as_ref().map(DiskMonitor::status),
+            dependencies: state.dependencies.statuses(),
+            model_watch: model_watch_status(&state),
+        }),
+    )
+}
+
+/// Status of the model watch, unless it is switched off
+fn model_watch_status(state: &AppState) -> Option<ModelWatchStatus> {
+    (state.model_watch.config().interval_secs > 0).then(|| state.model_watch.status())
 }
 
 async fn mistral_health_check(
     State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let correlation_id = generate_correlation_id();
+    let correlation_id = context.correlation_id;
     log_with_correlation(
         &correlation_id,
         tracing::Level::DEBUG,
@@ -180,7 +931,12 @@ async fn mistral_health_check(
                     mistral_service.generation_model(),
                     mistral_service.moderation_model(),
                     mistral_service.embedding_model()
-                ]
+                ],
+                "moderation": if mistral_service.moderation_model().is_some() {
+                    "configured"
+                } else {
+                    MODERATION_NOT_CONFIGURED
+                }
             })))
         }
         Err(e) => {
@@ -216,20 +972,35 @@ async fn get_audit_trail(
 ) -> Result<Json<AuditTrailResponse>, (StatusCode, String)> {
     debug!("Received audit trail request");
 
+    request
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
     let audit_logger = state.engine.audit_logger();
     let storage = audit_logger.storage();
 
-    match storage.get_with_filters(
-        request.limit,
-        request.offset,
-        request.start_time,
-        request.end_time,
-        request.correlation_id,
-    ) {
-        Ok(response) => {
+    let page = match request.cursor.as_deref() {
+        Some(cursor) => storage.get_page(
+            Some(cursor),
+            request.limit,
+            request.start_time,
+            request.end_time,
+            request.correlation_id,
+        ),
+        None => storage.get_with_filters(
+            request.limit,
+            request.offset,
+            request.start_time,
+            request.end_time,
+            request.correlation_id,
+        ),
+    };
+    match page {
+        Ok(mut response) => {
             info!("Audit trail retrieved successfully");
+            response.suppressed_repeats = suppression::repeats_in(&response.records);
             Ok(Json(response))
         }
+        Err(e @ AuditStorageError::InvalidCursor) => Err((StatusCode::BAD_REQUEST, e.to_string())),
         Err(e) => {
             error!("Failed to retrieve audit trail: {}", e);
             Err((
@@ -240,35 +1011,271 @@ async fn get_audit_trail(
     }
 }
 
+/// Query parameters accepted by the replay endpoint
+#[derive(Debug, Default, serde::Deserialize)]
+struct ReplayQuery {
+    #[serde(default)]
+    mode: ReplayMode,
+}
+
+async fn replay_decision(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(correlation_id): Path<String>,
+    Query(query): Query<ReplayQuery>,
+) -> Result<Json<ReplayReport>, Response> {
+    debug!("Received replay request for {}", correlation_id);
+
+    // Regeneration calls Mistral, so it waits for a slot like a compliance check
+    let _admitted = if query.mode == ReplayMode::Regenerate {
+        state.maintenance.admit().await.map_err(|rejection| {
+            info!("Replay rejected during maintenance: {:?}", rejection.reason);
+            (StatusCode::SERVICE_UNAVAILABLE, rejection.message).into_response()
+        })?;
+        admit(&state).await?
+    } else {
+        None
+    };
+
+    match state
+        .engine
+        .replay(&correlation_id, &context.correlation_id, query.mode)
+        .await
+    {
+        Ok(report) => {
+            info!(
+                "Replayed {}: {} -> {}",
+                correlation_id, report.original_status, report.replayed_status
+            );
+            Ok(Json(report))
+        }
+        Err(e) => {
+            let status = match e {
+                ReplayError::NotFound(_) => StatusCode::NOT_FOUND,
+                ReplayError::PromptNotStored(_)
+                | ReplayError::RulesNotArchived(_)
+                | ReplayError::NotRegenerable(_) => StatusCode::CONFLICT,
+                ReplayError::Generation(_) => StatusCode::BAD_GATEWAY,
+                ReplayError::Archive(_) | ReplayError::Storage(_) | ReplayError::Audit(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            };
+            warn!("Replay of {} failed: {}", correlation_id, e);
+            Err((status, e.to_string()).into_response())
+        }
+    }
+}
+
+async fn get_decision_lineage(
+    State(state): State<AppState>,
+    Extension(caller): Extension<ExplanationAudience>,
+    Path(correlation_id): Path<String>,
+    Query(query): Query<LineageQuery>,
+) -> Result<Json<DecisionLineage>, (StatusCode, String)> {
+    debug!("Received lineage request for {}", correlation_id);
+
+    let audience = requested_audience(caller, query.audience)?;
+    state
+        .engine
+        .decision_lineage(&correlation_id, audience)
+        .map(Json)
+        .map_err(|e| {
+            let status = match e {
+                LineageError::NotFound(_) => StatusCode::NOT_FOUND,
+                LineageError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            warn!("Lineage of {} failed: {}", correlation_id, e);
+            (status, e.to_string())
+        })
+}
+
+/// Query parameters accepted by the decision lineage endpoint
+#[derive(Debug, Default, serde::Deserialize)]
+struct LineageQuery {
+    /// Lets an admin preview what support staff see; support cannot ask for more
+    audience: Option<ExplanationAudience>,
+}
+
+/// Audience `requested` by a `caller`, who may ask for less than their token allows but
+/// never more
+fn requested_audience(
+    caller: ExplanationAudience,
+    requested: Option<ExplanationAudience>,
+) -> Result<ExplanationAudience, (StatusCode, String)> {
+    let audience = requested.unwrap_or(caller);
+    if audience > caller {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "a {} token cannot request an {} view",
+                caller.as_str(),
+                audience.as_str()
+            ),
+        ));
+    }
+    Ok(audience)
+}
+
+/// Query parameters accepted by the decision explanation endpoint
+#[derive(Debug, Default, serde::Deserialize)]
+struct ExplainQuery {
+    /// `json` (default) or `markdown`
+    #[serde(default)]
+    format: ExplainFormat,
+    /// Lets an admin preview what support staff see; support cannot ask for more
+    audience: Option<ExplanationAudience>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExplainFormat {
+    #[default]
+    Json,
+    Markdown,
+}
+
+async fn explain_decision(
+    State(state): State<AppState>,
+    Extension(caller): Extension<ExplanationAudience>,
+    Path(correlation_id): Path<String>,
+    Query(query): Query<ExplainQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    debug!("Received explanation request for {}", correlation_id);
+
+    let audience = requested_audience(caller, query.audience)?;
+    let explanation = state
+        .engine
+        .explain(&correlation_id, audience)
+        .map_err(|e| {
+            let status = match e {
+                ExplainError::NotFound(_) => StatusCode::NOT_FOUND,
+                ExplainError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            warn!("Explanation of {} failed: {}", correlation_id, e);
+            (status, e.to_string())
+        })?;
+    Ok(match query.format {
+        ExplainFormat::Json => Json(explanation).into_response(),
+        ExplainFormat::Markdown => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            explanation.markdown,
+        )
+            .into_response(),
+    })
+}
+
 async fn generate_compliance_report(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<ComplianceReportRequest>,
 ) -> Result<Json<ComplianceReportResponse>, (StatusCode, String)> {
     debug!("Received compliance report generation request");
 
-    let eu_service = EuLawComplianceService;
-    let response = eu_service.generate_compliance_report(request);
+    if request.regenerate {
+        if state.admin_token.is_none() {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "regenerating reports is disabled; set ADMIN_API_TOKEN to enable it".to_owned(),
+            ));
+        }
+        if !bearer_matches(&headers, state.admin_token.as_deref()) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "regenerating a report needs the admin token".to_owned(),
+            ));
+        }
+    }
+
+    let response = state
+        .reports
+        .generate(request)
+        .map_err(report_store_error)?;
 
-    info!("Compliance report generated successfully");
+    info!("Compliance report {} ready", response.report_id);
     Ok(Json(response))
 }
 
-async fn get_compliance_config(
-    State(_state): State<AppState>,
-) -> Result<Json<ComplianceConfigurationResponse>, (StatusCode, String)> {
+async fn get_compliance_report(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<StoredComplianceReport>, (StatusCode, String)> {
+    debug!("Received compliance report request for {}", id);
+
+    state
+        .reports
+        .get(&id)
+        .map_err(report_store_error)?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no compliance report {id}")))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ComplianceReportsQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+async fn list_compliance_reports(
+    State(state): State<AppState>,
+    Query(query): Query<ComplianceReportsQuery>,
+) -> Result<Json<ComplianceReportList>, (StatusCode, String)> {
+    debug!("Received compliance report listing request");
+
+    state
+        .reports
+        .list(query.limit, query.offset)
+        .map(Json)
+        .map_err(report_store_error)
+}
+
+fn report_store_error(e: ReportStoreError) -> (StatusCode, String) {
+    if let ReportStoreError::RequestMismatch(_) = e {
+        return (StatusCode::CONFLICT, e.to_string());
+    }
+    error!("Compliance report storage failed: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+async fn get_compliance_config(State(state): State<AppState>, headers: HeaderMap) -> Response {
     debug!("Received compliance configuration request");
 
     let eu_service = EuLawComplianceService;
-    let response = eu_service.get_current_configuration();
-
-    let config_response = ComplianceConfigurationResponse {
-        status: "success".to_string(),
-        message: "Current compliance configuration retrieved".to_string(),
-        current_configuration: response,
-    };
+    // Read the version before the lists, so a concurrent update can only make the tag older
+    let etag = http_cache::version_etag("eu-keywords", eu_service.risk_keyword_version());
+    cached_json(
+        &headers,
+        etag,
+        state.http_cache.compliance_config_max_age_secs,
+        || {
+            let config_response = ComplianceConfigurationResponse {
+                status: "success".to_string(),
+                message: "Current compliance configuration retrieved".to_string(),
+                current_configuration: eu_service.get_current_configuration(),
+            };
+            info!("Compliance configuration retrieved successfully");
+            config_response
+        },
+    )
+}
 
-    info!("Compliance configuration retrieved successfully");
-    Ok(Json(config_response))
+/// Answer `304 Not Modified` when the client already holds `etag`, otherwise the JSON body
+fn cached_json<T: serde::Serialize>(
+    headers: &HeaderMap,
+    etag: String,
+    max_age_secs: u64,
+    body: impl FnOnce() -> T,
+) -> Response {
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            http_cache::cache_control(max_age_secs),
+        ),
+    ];
+    if http_cache::is_not_modified(headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (cache_headers, Json(body())).into_response()
 }
 
 async fn update_compliance_config(
@@ -284,109 +1291,1339 @@ async fn update_compliance_config(
     Ok(Json(response))
 }
 
-async fn check_compliance(
-    State(state): State<AppState>,
-    Json(request): Json<ComplianceRequest>,
-) -> Result<Json<ComplianceResponse>, (StatusCode, String)> {
-    state
-        .engine
-        .process(request)
-        .await
-        .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+async fn get_firewall_rules(State(state): State<AppState>) -> Json<FirewallRulesResponse> {
+    debug!("Received firewall rules request");
+    Json(state.engine.firewall_service().rules_listing())
 }
 
-/// Framework configuration for easy setup
-pub struct FrameworkConfig {
-    pub server_port: u16,
-    pub sled_db_path: String,
-    pub mistral_api_key: Option<String>,
+async fn test_firewall_rules(State(state): State<AppState>) -> Json<RuleAssertionReport> {
+    debug!("Received firewall rule assertion run");
+    let report = state.engine.firewall_service().test_rules();
+    for failure in &report.failures {
+        warn!(
+            "Firewall rule assertion failed: expected {:?}, got {:?} for {:?}",
+            failure.expected, failure.actual, failure.prompt
+        );
+    }
+    Json(report)
 }
 
-impl Default for FrameworkConfig {
-    fn default() -> Self {
-        Self {
-            server_port: 3000,
-            sled_db_path: "prompt_sentinel_data".to_string(),
-            mistral_api_key: std::env::var("MISTRAL_API_KEY").ok(),
-        }
-    }
+async fn dry_run_policy(
+    State(state): State<AppState>,
+    Json(request): Json<PolicyDryRunRequest>,
+) -> Result<Json<PolicyDryRunResponse>, (StatusCode, String)> {
+    debug!("Received decision policy dry run");
+
+    let policy = request
+        .policy
+        .as_ref()
+        .unwrap_or(state.engine.decision_policy());
+    policy
+        .validate()
+        .and_then(|()| request.evidence.validate())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    Ok(Json(PolicyDryRunResponse::new(
+        policy.decide(&request.evidence),
+    )))
 }
 
-impl FrameworkConfig {
-    /// Initialize the framework with default or custom configuration
-    pub async fn initialize(self) -> Result<PromptSentinelServer, Box<dyn std::error::Error>> {
-        let settings = AppSettings::from_env().unwrap_or_else(|_| AppSettings {
-            server_port: self.server_port,
-            mistral_api_key: self.mistral_api_key.clone(),
-            mistral_base_url: DEFAULT_MISTRAL_BASE_URL.to_string(),
-            generation_model: DEFAULT_MISTRAL_GENERATION_MODEL.to_string(),
-            moderation_model: Some(DEFAULT_MISTRAL_MODERATION_MODEL.to_string()),
-            embedding_model: DEFAULT_MISTRAL_EMBEDDING_MODEL.to_string(),
-            bias_threshold: 0.35,
-            max_input_length: 4096,
-            semantic_medium_threshold: 0.70,
-            semantic_high_threshold: 0.80,
-            semantic_decision_margin: 0.02,
-        });
+async fn get_config_snapshot(
+    State(state): State<AppState>,
+) -> Result<Json<ConfigSnapshot>, (StatusCode, String)> {
+    debug!("Received configuration snapshot request");
 
-        let audit_storage: Arc<dyn AuditStorage> =
-            Arc::new(SledAuditStorage::new(&self.sled_db_path)?);
-        let audit_logger = AuditLogger::new(audit_storage);
+    state.config_management.snapshot().map(Json).map_err(|e| {
+        error!("Failed to snapshot configuration: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })
+}
 
-        let mistral_client: Arc<dyn MistralClient> =
-            if settings.mistral_api_key.as_deref() == Some("mock") {
-                Arc::new(crate::modules::mistral_ai::client::MockMistralClient::default())
+async fn restore_config(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(snapshot): Json<ConfigSnapshot>,
+) -> Result<Json<ConfigRestoreResponse>, Response> {
+    debug!("Received configuration restore request");
+
+    state
+        .config_management
+        .restore(&context.correlation_id, snapshot)
+        .map(Json)
+        .map_err(|e| {
+            error!("Configuration restore rejected: {}", e);
+            if let ConfigManagementError::RuleAssertionsFailed(failures) = &e {
+                // Editors need the failing prompts, not just a count
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(serde_json::json!({
+                        "error": e.to_string(),
+                        "failed_assertions": failures,
+                    })),
+                )
+                    .into_response();
+            }
+            let status = if e.is_rejected_input() {
+                StatusCode::UNPROCESSABLE_ENTITY
             } else {
-                Arc::new(HttpMistralClient::new(
-                    settings.mistral_base_url.clone(),
-                    settings.mistral_api_key.clone().unwrap_or_default(),
-                ))
+                StatusCode::INTERNAL_SERVER_ERROR
             };
-        let mistral_service = MistralService::new(
-            mistral_client.clone(),
-            settings.generation_model.clone(),
-            settings.moderation_model.clone(),
-            settings.embedding_model.clone(),
-        );
+            (status, e.to_string()).into_response()
+        })
+}
 
-        let firewall_service = PromptFirewallService::new_with_mistral(
-            settings.max_input_length,
-            mistral_client.clone(),
-        );
-        let bias_service =
-            BiasDetectionService::new_with_mistral(settings.bias_threshold, mistral_client.clone());
+async fn promote_firewall_rule(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<Json<RulePromotionResponse>, Response> {
+    debug!("Received promotion request for firewall rule {}", id);
 
-        // Perform model validation at startup
-        info!("Validating Mistral models at startup...");
-        mistral_service.validate_all_models().await.map_err(|e| {
-            error!("Model validation failed: {}", e);
-            Box::new(e) as Box<dyn std::error::Error>
-        })?;
-        info!("All Mistral models validated successfully");
+    state
+        .config_management
+        .promote_rule(&context.correlation_id, &id)
+        .map(Json)
+        .map_err(|e| {
+            error!("Firewall rule promotion rejected: {}", e);
+            let status = match &e {
+                ConfigManagementError::UnknownRule(_) => StatusCode::NOT_FOUND,
+                ConfigManagementError::NotMonitored(_) => StatusCode::CONFLICT,
+                ConfigManagementError::RuleAssertionsFailed(failures) => {
+                    return (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        Json(serde_json::json!({
+                            "error": e.to_string(),
+                            "failed_assertions": failures,
+                        })),
+                    )
+                        .into_response();
+                }
+                _ if e.is_rejected_input() => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string()).into_response()
+        })
+}
 
-        // Initialize semantic detection service
-        let semantic_service = SemanticDetectionService::new(
+async fn get_config_history(
+    State(state): State<AppState>,
+) -> Result<Json<ConfigHistoryResponse>, (StatusCode, String)> {
+    debug!("Received configuration history request");
+
+    state
+        .config_management
+        .history()
+        .map(|snapshots| Json(ConfigHistoryResponse { snapshots }))
+        .map_err(|e| {
+            error!("Failed to read configuration history: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+async fn get_model_history(
+    State(state): State<AppState>,
+) -> Result<Json<ModelHistoryResponse>, (StatusCode, String)> {
+    debug!("Received model history request");
+
+    state
+        .model_watch
+        .history()
+        .map(|models| Json(ModelHistoryResponse { models }))
+        .map_err(|e| {
+            error!("Failed to read the model history: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+/// Today's usage and quota of the caller's API key, or over `period`
+async fn get_own_usage(
+    State(state): State<AppState>,
+    api_key: Option<Extension<ApiKeyIdentity>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, (StatusCode, String)> {
+    debug!("Received own usage request");
+
+    let Some(Extension(identity)) = api_key else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "usage is reported per API key; present one as the bearer token".to_owned(),
+        ));
+    };
+    state
+        .usage
+        .report(&identity.key_id, query.period)
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("no API key is registered as {}", identity.key_id),
+            )
+        })
+}
+
+/// Usage of every API key, or of `key_id`, over `period`
+async fn get_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageResponse>, (StatusCode, String)> {
+    debug!("Received usage request");
+
+    let keys = match &query.key_id {
+        Some(key_id) => vec![state.usage.report(key_id, query.period).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("no API key is registered as {key_id}"),
+            )
+        })?],
+        None => state.usage.reports(query.period),
+    };
+    Ok(Json(UsageResponse { keys }))
+}
+
+async fn set_key_quota(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(key_id): Path<String>,
+    Json(quota): Json<KeyQuota>,
+) -> Result<Json<UsageReport>, (StatusCode, String)> {
+    debug!("Received quota update for API key {}", key_id);
+
+    state
+        .usage
+        .set_quota(&context.correlation_id, &key_id, quota)
+        .map(Json)
+        .map_err(|e| {
+            error!("Quota update rejected: {}", e);
+            let status = match e {
+                UsageError::UnknownKey(_) => StatusCode::NOT_FOUND,
+                UsageError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })
+}
+
+async fn get_caches(State(state): State<AppState>) -> Json<CachesResponse> {
+    debug!("Received cache registry request");
+    Json(state.caches.status())
+}
+
+async fn flush_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<CacheFlush>, (StatusCode, String)> {
+    debug!("Received flush request for the {} cache", name);
+    state.caches.flush(&name).map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!(
+                "no cache named {name} (registered: {})",
+                state.caches.names().join(", ")
+            ),
+        )
+    })
+}
+
+async fn get_slow_requests(State(state): State<AppState>) -> Json<SlowRequestsResponse> {
+    debug!("Received slow request diagnostics request");
+    Json(state.slow_requests.recent())
+}
+
+async fn configure_chaos(
+    State(state): State<AppState>,
+    Json(config): Json<ChaosConfig>,
+) -> Result<Json<ChaosStatus>, (StatusCode, String)> {
+    debug!("Received chaos configuration request");
+
+    state.chaos.configure(config).map(Json).map_err(|e| {
+        error!("Chaos configuration rejected: {}", e);
+        let status = match e {
+            ChaosError::Unavailable => StatusCode::FORBIDDEN,
+            ChaosError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, e.to_string())
+    })
+}
+
+async fn get_firewall_misses(State(state): State<AppState>) -> Json<FirewallMissesResponse> {
+    debug!("Received firewall miss statistics request");
+    Json(state.engine.firewall_misses().summary())
+}
+
+/// Query parameters accepted by the threat category statistics endpoint
+#[derive(Debug, serde::Deserialize)]
+struct ThreatStatsQuery {
+    #[serde(default = "default_candidate_window")]
+    window: String,
+}
+
+async fn get_threat_category_stats(
+    State(state): State<AppState>,
+    Query(query): Query<ThreatStatsQuery>,
+) -> Result<Json<ThreatCategoryStats>, (StatusCode, String)> {
+    debug!(
+        "Received threat category statistics request over {}",
+        query.window
+    );
+
+    let window = parse_window(&query.window).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    state
+        .engine
+        .threat_category_stats(window)
+        .map(Json)
+        .map_err(|e| {
+            error!("Threat category statistics failed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+async fn get_slo_status(State(state): State<AppState>) -> Json<SloStatusResponse> {
+    debug!("Received SLO status request");
+    Json(state.slo.status())
+}
+
+async fn get_maintenance_status(State(state): State<AppState>) -> Json<MaintenanceStatus> {
+    debug!("Received maintenance status request");
+    Json(state.maintenance.status())
+}
+
+async fn update_maintenance(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>, (StatusCode, String)> {
+    debug!("Received maintenance update request");
+
+    state
+        .maintenance
+        .update(&context.correlation_id, request)
+        .map(Json)
+        .map_err(|e| {
+            error!("Maintenance update rejected: {}", e);
+            let status = match e {
+                MaintenanceError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                MaintenanceError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })
+}
+
+async fn get_stage_toggles(State(state): State<AppState>) -> Json<StageTogglesResponse> {
+    debug!("Received stage toggle listing request");
+    Json(state.engine.stage_toggles())
+}
+
+async fn toggle_stage(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<StageToggleRequest>,
+) -> Result<Json<StageState>, (StatusCode, String)> {
+    debug!("Received stage toggle for {}", request.stage);
+
+    state
+        .engine
+        .toggle_stage(&context.correlation_id, request)
+        .map(Json)
+        .map_err(|e| {
+            error!("Stage toggle rejected: {}", e);
+            let status = match e {
+                StageToggleError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                StageToggleError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })
+}
+
+async fn list_exemptions(State(state): State<AppState>) -> Json<ExemptionsResponse> {
+    debug!("Received exemption listing request");
+    Json(state.engine.exemptions().list())
+}
+
+async fn grant_exemption(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<ExemptionRequest>,
+) -> Result<(StatusCode, Json<Exemption>), (StatusCode, String)> {
+    debug!("Received exemption grant for rule {}", request.rule_id);
+
+    if !state
+        .engine
+        .firewall_service()
+        .has_rule(request.rule_id.trim())
+    {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("unknown firewall rule '{}'", request.rule_id),
+        ));
+    }
+    state
+        .engine
+        .exemptions()
+        .grant(&context.correlation_id, request)
+        .map(|exemption| (StatusCode::CREATED, Json(exemption)))
+        .map_err(exemption_error)
+}
+
+async fn revoke_exemption(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Exemption>, (StatusCode, String)> {
+    debug!("Received exemption revocation for {}", id);
+
+    state
+        .engine
+        .exemptions()
+        .revoke(&context.correlation_id, &id)
+        .map(Json)
+        .map_err(exemption_error)
+}
+
+fn exemption_error(e: ExemptionError) -> (StatusCode, String) {
+    warn!("Exemption change rejected: {}", e);
+    let status = match e {
+        ExemptionError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        ExemptionError::NotFound(_) => StatusCode::NOT_FOUND,
+        ExemptionError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn file_appeal(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<AppealRequest>,
+) -> Result<(StatusCode, Json<Appeal>), (StatusCode, String)> {
+    debug!(
+        "Received appeal against decision {}",
+        request.correlation_id
+    );
+
+    state
+        .engine
+        .file_appeal(&context.correlation_id, request)
+        .map(|appeal| (StatusCode::CREATED, Json(appeal)))
+        .map_err(appeal_error)
+}
+
+/// Query parameters accepted by the appeal listing endpoint
+#[derive(Debug, serde::Deserialize)]
+struct AppealsQuery {
+    #[serde(default)]
+    state: Option<AppealState>,
+}
+
+async fn list_appeals(
+    State(state): State<AppState>,
+    Query(query): Query<AppealsQuery>,
+) -> Result<Json<AppealsResponse>, (StatusCode, String)> {
+    debug!("Received appeal listing request");
+
+    state
+        .engine
+        .appeals(query.state)
+        .map(|appeals| Json(AppealsResponse { appeals }))
+        .map_err(appeal_error)
+}
+
+async fn resolve_appeal(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+    Json(request): Json<ResolveAppealRequest>,
+) -> Result<Json<Appeal>, (StatusCode, String)> {
+    debug!("Received appeal resolution for {}", id);
+
+    state
+        .engine
+        .resolve_appeal(&id, &context.correlation_id, request)
+        .map(Json)
+        .map_err(appeal_error)
+}
+
+fn appeal_error(e: AppealError) -> (StatusCode, String) {
+    warn!("Appeal request failed: {}", e);
+    let status = match e {
+        AppealError::Invalid(_) | AppealError::NotBlocked { .. } => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        AppealError::DecisionNotFound(_) | AppealError::NotFound(_) => StatusCode::NOT_FOUND,
+        AppealError::AlreadyOpen { .. } | AppealError::AlreadyResolved { .. } => {
+            StatusCode::CONFLICT
+        }
+        AppealError::Storage(_)
+        | AppealError::Store(_)
+        | AppealError::Exemption(_)
+        | AppealError::Audit(_)
+        | AppealError::LockPoisoned => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn list_templates(State(state): State<AppState>) -> Json<TemplatesResponse> {
+    debug!("Received template listing request");
+    Json(state.engine.templates())
+}
+
+async fn register_template(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(request): Json<TemplateRegistration>,
+) -> Result<(StatusCode, Json<PromptTemplate>), (StatusCode, String)> {
+    debug!("Received template registration for {}", request.id);
+
+    state
+        .engine
+        .register_template(&context.correlation_id, request)
+        .await
+        .map(|template| (StatusCode::CREATED, Json(template)))
+        .map_err(|e| {
+            warn!("Template registration rejected: {}", e);
+            let status = if e.is_rejected_input() {
+                StatusCode::UNPROCESSABLE_ENTITY
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, e.to_string())
+        })
+}
+
+/// Query parameters accepted by the candidate generation endpoint
+#[derive(Debug, serde::Deserialize)]
+struct CandidateWindowQuery {
+    #[serde(default = "default_candidate_window")]
+    window: String,
+}
+
+fn default_candidate_window() -> String {
+    "7d".to_owned()
+}
+
+async fn generate_attack_candidates(
+    State(state): State<AppState>,
+    Query(query): Query<CandidateWindowQuery>,
+) -> Result<Json<CandidateGenerationReport>, (StatusCode, String)> {
+    debug!("Received attack candidate generation over {}", query.window);
+
+    let window = parse_window(&query.window).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let report = state
+        .engine
+        .generate_attack_candidates(window)
+        .await
+        .map_err(candidate_error)?;
+    info!(
+        "Generated {} attack template candidates from {} blocked prompts",
+        report.candidates.len(),
+        report.blocked_prompts
+    );
+    Ok(Json(report))
+}
+
+async fn list_attack_candidates(
+    State(state): State<AppState>,
+) -> Result<Json<AttackCandidatesResponse>, (StatusCode, String)> {
+    debug!("Received attack candidate listing request");
+
+    let candidates = state.engine.attack_candidates().map_err(candidate_error)?;
+    Ok(Json(AttackCandidatesResponse {
+        total: candidates.len(),
+        candidates,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BankReportQuery {
+    /// Re-run the checks against the bank file instead of returning the last report
+    #[serde(default)]
+    refresh: bool,
+}
+
+async fn get_bank_report(
+    State(state): State<AppState>,
+    Query(query): Query<BankReportQuery>,
+) -> Result<Json<BankHygieneReport>, (StatusCode, String)> {
+    debug!("Received attack bank report request");
+
+    let semantic = state.engine.semantic_service();
+    if !query.refresh
+        && let Some(report) = semantic.bank_report()
+    {
+        return Ok(Json(report));
+    }
+    semantic.analyze_bank().await.map(Json).map_err(|e| {
+        error!("Attack bank analysis failed: {}", e);
+        let status = match e {
+            SemanticDetectionError::Embedding(_) => StatusCode::BAD_GATEWAY,
+            SemanticDetectionError::UnknownCategory(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, e.to_string())
+    })
+}
+
+async fn approve_attack_candidate(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Path(id): Path<String>,
+) -> Result<Json<ApprovedCandidate>, (StatusCode, String)> {
+    debug!("Received attack candidate approval for {}", id);
+
+    let approved = state
+        .engine
+        .approve_attack_candidate(&id, &context.correlation_id)
+        .await
+        .map_err(candidate_error)?;
+    info!(
+        "Attack candidate {} added to the bank as {}",
+        id, approved.template.id
+    );
+    Ok(Json(approved))
+}
+
+async fn reject_attack_candidate(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AttackCandidate>, (StatusCode, String)> {
+    debug!("Received attack candidate rejection for {}", id);
+
+    state
+        .engine
+        .reject_attack_candidate(&id)
+        .map(Json)
+        .map_err(candidate_error)
+}
+
+fn candidate_error(e: CandidateError) -> (StatusCode, String) {
+    warn!("Attack candidate request failed: {}", e);
+    let status = match e {
+        CandidateError::NotFound(_) => StatusCode::NOT_FOUND,
+        CandidateError::PromptsNotStored
+        | CandidateError::Semantic(
+            SemanticDetectionError::NotInitialized | SemanticDetectionError::DuplicateTemplate(_),
+        ) => StatusCode::CONFLICT,
+        CandidateError::Semantic(SemanticDetectionError::Embedding(_)) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        CandidateError::Semantic(SemanticDetectionError::UnknownCategory(_)) => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        CandidateError::Storage(_)
+        | CandidateError::Store(_)
+        | CandidateError::Semantic(_)
+        | CandidateError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn get_system_summary(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    debug!("Received system summary request");
+    let summary = SystemSummary {
+        version: env!("CARGO_PKG_VERSION"),
+        api_version: API_VERSION,
+        maintenance: state.maintenance.status(),
+        admission: state.admission.as_ref().map(AdmissionController::status),
+        cors: state.cors.clone(),
+        config_fingerprint: state.engine.config_fingerprint(),
+        chaos: state.chaos.status(),
+        disabled_stages: state
+            .engine
+            .stage_toggles()
+            .stages
+            .into_iter()
+            .filter(|stage| !stage.enabled)
+            .map(|stage| stage.stage)
+            .collect(),
+        disk: state.disk.as_ref().map(DiskMonitor::status),
+        dependencies: state.dependencies.statuses(),
+        model_watch: model_watch_status(&state),
+    };
+    // The maintenance queue counters move with traffic rather than with updates,
+    // so the tag follows the content instead of a version counter
+    let etag = http_cache::content_etag(&summary);
+    cached_json(
+        &headers,
+        etag,
+        state.http_cache.summary_max_age_secs,
+        || summary,
+    )
+}
+
+async fn get_effective_config(State(state): State<AppState>) -> Json<EffectiveConfig> {
+    debug!("Received effective configuration request");
+    Json(state.effective_config.as_ref().clone())
+}
+
+/// Query parameters accepted by the self-test endpoint
+#[derive(Debug, serde::Deserialize)]
+struct SelfTestQuery {
+    #[serde(default = "default_record_audit")]
+    record_audit: bool,
+}
+
+fn default_record_audit() -> bool {
+    true
+}
+
+async fn run_self_test(
+    State(state): State<AppState>,
+    Query(query): Query<SelfTestQuery>,
+) -> (StatusCode, Json<SelfTestReport>) {
+    debug!("Received self-test request");
+
+    let report = state.self_test.run(&state.engine, query.record_audit).await;
+    let status = if report.passed {
+        info!("Self-test passed");
+        StatusCode::OK
+    } else {
+        error!(
+            "Self-test failed: {:?}",
+            report
+                .probes
+                .iter()
+                .filter(|probe| !probe.passed)
+                .map(|probe| probe.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// Verify the whole audit chain now instead of waiting for the daily run
+///
+/// A failure is alerted on as if the scheduled run had found it and comes back in
+/// `failure`.
+async fn verify_audit_chain(State(state): State<AppState>) -> Json<VerificationReport> {
+    debug!("Received audit chain verification request");
+    Json(state.audit_integrity.check_full().await)
+}
+
+/// Query parameters accepted by the compliance check endpoint
+#[derive(Debug, Default, serde::Deserialize)]
+struct ComplianceCheckQuery {
+    #[serde(default)]
+    profile: ResponseProfile,
+    /// Answer a block with a refusal message; unset follows `REFUSAL_MESSAGES`
+    #[serde(default)]
+    refusal: Option<bool>,
+}
+
+async fn check_compliance(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Query(query): Query<ComplianceCheckQuery>,
+    api_key: Option<Extension<ApiKeyIdentity>>,
+    headers: HeaderMap,
+    body: Result<Json<ComplianceRequest>, JsonRejection>,
+) -> Result<Json<ComplianceResponse>, Response> {
+    // Recorded prompts only go to instances marked as staging
+    if headers.contains_key(REPLAY_HEADER) && !state.accept_replay_traffic {
+        warn!("Refused a replayed request: ACCEPT_REPLAY_TRAFFIC is off");
+        return Err((
+            StatusCode::FORBIDDEN,
+            "this instance does not accept replayed traffic".to_owned(),
+        )
+            .into_response());
+    }
+    // Only administrators may keep their traffic out of the audit trail
+    let synthetic = headers
+        .get(SYNTHETIC_HEADER)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"true"));
+    if synthetic && !bearer_matches(&headers, state.admin_token.as_deref()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("{SYNTHETIC_HEADER} needs the admin token"),
+        )
+            .into_response());
+    }
+    let Json(mut request) = body.map_err(|rejection| {
+        (
+            rejection.status(),
+            Json(RequestValidationError::invalid_body(rejection.body_text())),
+        )
+            .into_response()
+    })?;
+    // Malformed requests are answered before they are audited or reach Mistral
+    state
+        .engine
+        .validate_request(&request)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(e)).into_response())?;
+
+    // An id in the body wins so existing callers keep their own correlation scheme
+    if request.correlation_id.is_none() {
+        request.correlation_id = Some(context.correlation_id);
+    }
+
+    // Prompts the local checks already reject never reach Mistral, so they skip the queue
+    if !state.engine.can_serve_locally(&request.prompt) {
+        state.maintenance.admit().await.map_err(|rejection| {
+            info!(
+                "Request rejected during maintenance: {:?}",
+                rejection.reason
+            );
+            (StatusCode::SERVICE_UNAVAILABLE, rejection.message).into_response()
+        })?;
+    }
+    let _admitted = admit(&state).await?;
+
+    // axum drops this handler, and the run with it, when the client disconnects; the
+    // guard cancels the token too, so nothing holding it outlives the request
+    let cancel = CancellationToken::new();
+    let _cancel_on_disconnect = cancel.clone().drop_guard();
+    suppression::scope(
+        synthetic,
+        state
+            .engine
+            .process_cancellable(request, query.refusal, cancel),
+    )
+    .await
+    .map(|response| {
+        if let Some(Extension(identity)) = &api_key {
+            state.usage.record_check(&identity.key_id, &response);
+        }
+        Json(response.with_profile(query.profile))
+    })
+    .map_err(workflow_error_response)
+}
+
+/// Limits and vocabulary of `POST /api/compliance/check`, from the live settings
+async fn get_compliance_options(State(state): State<AppState>) -> Json<ComplianceOptions> {
+    Json(state.engine.options())
+}
+
+/// Check a prompt and a response the caller generated with their own model
+async fn validate_exchange(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Query(query): Query<ComplianceCheckQuery>,
+    Json(mut request): Json<ValidateExchangeRequest>,
+) -> Result<Json<ExchangeValidationResponse>, Response> {
+    if request.correlation_id.is_none() {
+        request.correlation_id = Some(context.correlation_id);
+    }
+
+    // Output moderation needs Mistral even when generation does not
+    if !state.engine.can_serve_locally(&request.prompt) {
+        state.maintenance.admit().await.map_err(|rejection| {
+            info!(
+                "Exchange validation rejected during maintenance: {:?}",
+                rejection.reason
+            );
+            (StatusCode::SERVICE_UNAVAILABLE, rejection.message).into_response()
+        })?;
+    }
+    let _admitted = admit(&state).await?;
+
+    state
+        .engine
+        .validate_exchange(request)
+        .await
+        .map(|mut response| {
+            response.compliance = response.compliance.with_profile(query.profile);
+            Json(response)
+        })
+        .map_err(workflow_error_response)
+}
+
+/// Wait for a workflow slot when admission control is on
+///
+/// The permit is held until the handler returns. Shed requests get `503` with
+/// `Retry-After`.
+async fn admit(state: &AppState) -> Result<Option<AdmissionPermit>, Response> {
+    let Some(admission) = &state.admission else {
+        return Ok(None);
+    };
+    admission.admit().await.map(Some).map_err(|rejection| {
+        info!("Request shed by admission control: {:?}", rejection.reason);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, rejection.retry_after_secs.to_string())],
+            rejection.to_string(),
+        )
+            .into_response()
+    })
+}
+
+fn workflow_error_response(e: WorkflowError) -> Response {
+    match e.retry_after() {
+        Some(retry_after) => {
+            get_metrics().increment_errors("mistral_concurrency_limit");
+            // Retry-After is whole seconds; round up so clients never retry early
+            let seconds = retry_after.as_millis().div_ceil(1000).max(1);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, seconds.to_string())],
+                e.to_string(),
+            )
+                .into_response()
+        }
+        // A template replaced between validation and the run is still the caller's mistake
+        None => match &e {
+            WorkflowError::Template(template) if template.is_rejected_input() => {
+                (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response()
+            }
+            // Nobody is left to read it; 499 is what proxies log for a client that left
+            WorkflowError::Cancelled => StatusCode::from_u16(499)
+                .expect("499 is a valid status code")
+                .into_response(),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+    }
+}
+
+async fn scan_documents(
+    State(state): State<AppState>,
+    Extension(context): Extension<RequestContext>,
+    Json(mut request): Json<DocumentScanRequest>,
+) -> Result<Json<DocumentScanResponse>, Response> {
+    debug!(
+        "Received document scan request for {} documents",
+        request.documents.len()
+    );
+    if request.correlation_id.is_none() {
+        request.correlation_id = Some(context.correlation_id);
+    }
+
+    // The semantic detector needs Mistral embeddings for every document
+    state.maintenance.admit().await.map_err(|rejection| {
+        info!(
+            "Document scan rejected during maintenance: {:?}",
+            rejection.reason
+        );
+        (StatusCode::SERVICE_UNAVAILABLE, rejection.message).into_response()
+    })?;
+    // A batch runs as one workflow, however many documents it holds
+    let _admitted = admit(&state).await?;
+
+    state
+        .engine
+        .scan_documents(request)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Document scan rejected: {}", e);
+            let status = if e.is_over_limit() {
+                StatusCode::PAYLOAD_TOO_LARGE
+            } else if e.is_rejected_input() {
+                StatusCode::UNPROCESSABLE_ENTITY
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, e.to_string()).into_response()
+        })
+}
+
+async fn semantic_scan(
+    State(state): State<AppState>,
+    Json(request): Json<SemanticScanRequest>,
+) -> Result<Json<SemanticScanResult>, (StatusCode, String)> {
+    debug!("Received semantic scan request");
+    if request.text.trim().is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "text must not be empty".to_owned(),
+        ));
+    }
+
+    // Scoring needs a Mistral embedding
+    state.maintenance.admit().await.map_err(|rejection| {
+        info!(
+            "Semantic scan rejected during maintenance: {:?}",
+            rejection.reason
+        );
+        (StatusCode::SERVICE_UNAVAILABLE, rejection.message)
+    })?;
+
+    state
+        .engine
+        .semantic_service()
+        .scan(request)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Semantic scan failed: {}", e);
+            get_metrics().increment_errors("semantic_scan");
+            (StatusCode::BAD_GATEWAY, e.to_string())
+        })
+}
+
+/// Framework configuration for easy setup
+///
+/// Fields left as `None` come from the configuration file, the environment or the
+/// built-in defaults, in that order of increasing precedence; fields set here win.
+#[derive(Default)]
+pub struct FrameworkConfig {
+    pub server_port: Option<u16>,
+    pub sled_db_path: Option<String>,
+    pub mistral_api_key: Option<String>,
+    /// Configuration file to read instead of `PROMPT_SENTINEL_CONFIG` or `sentinel.toml`
+    pub config_path: Option<PathBuf>,
+}
+
+/// Audit trail, configuration history, firewall rule archive, attack candidate queue,
+/// appeals, correlation id generator state, model history, compliance reports and API key
+/// usage
+type StorageBackends = (
+    Arc<dyn AuditStorage>,
+    Arc<dyn ConfigHistoryStorage>,
+    Arc<dyn FirewallRulesArchive>,
+    Arc<dyn CandidateStore>,
+    Arc<dyn AppealStore>,
+    Arc<dyn CorrelationIdStore>,
+    Arc<dyn ModelHistoryStore>,
+    Arc<dyn ComplianceReportStore>,
+    Arc<dyn UsageStore>,
+    Option<Arc<dyn DiskFootprint>>,
+);
+
+/// Open the storage selected by `AUDIT_BACKEND`
+///
+/// Configuration history, firewall rule versions, attack candidates, appeals, the
+/// correlation id counter, the model history, compliance reports and API key usage stay
+/// in sled unless everything is kept in memory, or the crate is built without
+/// `sled-storage`.
+fn open_storage(settings: &AppSettings) -> Result<StorageBackends, Box<dyn std::error::Error>> {
+    let backends: StorageBackends = match settings.audit_backend {
+        #[cfg(feature = "sled-storage")]
+        AuditBackend::Sled => {
+            let db = sled::open(&settings.sled_db_path)?;
+            let mut audit_storage = SledAuditStorage::from_db(db.clone())?;
+            if let Some(key) = settings.audit_encryption_key.clone() {
+                info!("Encrypting audit records with key {}", key.key_id());
+                audit_storage = audit_storage.with_encryption(key);
+            }
+            audit_storage = audit_storage.with_compression(settings.audit_compression);
+            audit_storage.verify_key()?;
+            (
+                Arc::new(audit_storage),
+                Arc::new(SledConfigHistory::new(&db)?),
+                Arc::new(SledRulesArchive::new(&db)?),
+                Arc::new(SledCandidateStore::new(&db)?),
+                Arc::new(SledAppealStore::new(&db)?),
+                Arc::new(SledCorrelationIdStore::new(&db)?),
+                Arc::new(SledModelHistory::new(&db)?),
+                Arc::new(SledReportStore::new(&db)?),
+                Arc::new(SledUsageStore::new(&db)?),
+                Some(Arc::new(db)),
+            )
+        }
+        #[cfg(all(feature = "sqlite-storage", feature = "sled-storage"))]
+        AuditBackend::Sqlite => {
+            let db = sled::open(&settings.sled_db_path)?;
+            (
+                Arc::new(SqliteAuditStorage::open(&settings.audit_sqlite_path)?),
+                Arc::new(SledConfigHistory::new(&db)?),
+                Arc::new(SledRulesArchive::new(&db)?),
+                Arc::new(SledCandidateStore::new(&db)?),
+                Arc::new(SledAppealStore::new(&db)?),
+                Arc::new(SledCorrelationIdStore::new(&db)?),
+                Arc::new(SledModelHistory::new(&db)?),
+                Arc::new(SledReportStore::new(&db)?),
+                Arc::new(SledUsageStore::new(&db)?),
+                Some(Arc::new(db)),
+            )
+        }
+        #[cfg(all(feature = "sqlite-storage", not(feature = "sled-storage")))]
+        AuditBackend::Sqlite => (
+            Arc::new(SqliteAuditStorage::open(&settings.audit_sqlite_path)?),
+            Arc::new(InMemoryConfigHistory::new()),
+            Arc::new(InMemoryRulesArchive::new()),
+            Arc::new(InMemoryCandidateStore::new()),
+            Arc::new(InMemoryAppealStore::new()),
+            Arc::new(InMemoryCorrelationIdStore::new()),
+            Arc::new(InMemoryModelHistory::new()),
+            Arc::new(InMemoryReportStore::new()),
+            Arc::new(InMemoryUsageStore::new()),
+            None,
+        ),
+        AuditBackend::Memory => (
+            Arc::new(InMemoryAuditStorage::new()),
+            Arc::new(InMemoryConfigHistory::new()),
+            Arc::new(InMemoryRulesArchive::new()),
+            Arc::new(InMemoryCandidateStore::new()),
+            Arc::new(InMemoryAppealStore::new()),
+            Arc::new(InMemoryCorrelationIdStore::new()),
+            Arc::new(InMemoryModelHistory::new()),
+            Arc::new(InMemoryReportStore::new()),
+            Arc::new(InMemoryUsageStore::new()),
+            None,
+        ),
+        // Settings reject backends that are not compiled in
+        #[allow(unreachable_patterns)]
+        unavailable => {
+            return Err(format!(
+                "{:?} audit storage needs a build with the {} feature",
+                unavailable,
+                unavailable.required_feature().unwrap_or_default()
+            )
+            .into());
+        }
+    };
+    Ok(backends)
+}
+
+impl FrameworkConfig {
+    /// Resolve settings from the configuration file, the environment and this config
+    ///
+    /// Errors are fatal: a malformed file or variable never falls back to defaults.
+    pub fn load_settings(&self) -> Result<(AppSettings, EffectiveConfig), SettingsError> {
+        let (mut settings, mut effective) = match &self.config_path {
+            Some(path) => AppSettings::load_from(Some(path), &|key| std::env::var(key).ok())?,
+            None => AppSettings::load()?,
+        };
+        if let Some(port) = self.server_port {
+            settings.server_port = port;
+            effective.set_override("server.port", port);
+        }
+        if let Some(path) = &self.sled_db_path {
+            settings.sled_db_path = path.clone();
+            effective.set_override("paths.sled_db", path);
+        }
+        if let Some(key) = &self.mistral_api_key {
+            settings.mistral_api_key = Some(key.clone());
+            effective.set_override("mistral.api_key", key);
+        }
+        settings.install_file_paths();
+        Ok((settings, effective))
+    }
+
+    /// Initialize the framework with default or custom configuration
+    pub async fn initialize(self) -> Result<PromptSentinelServer, Box<dyn std::error::Error>> {
+        let loaded = self.load_settings().map_err(|e| {
+            error!("Invalid configuration: {}", e);
+            Box::new(e) as Box<dyn std::error::Error>
+        })?;
+        Self::initialize_with(loaded).await
+    }
+
+    /// Initialize the framework from settings already resolved by [`Self::load_settings`]
+    pub async fn initialize_with(
+        loaded: (AppSettings, EffectiveConfig),
+    ) -> Result<PromptSentinelServer, Box<dyn std::error::Error>> {
+        let settings = &loaded.0;
+        let mistral_client: Arc<dyn MistralClient> =
+            if settings.mistral_api_key.as_deref() == Some("mock") {
+                Arc::new(crate::modules::mistral_ai::client::MockMistralClient::default())
+            } else {
+                Arc::new(
+                    HttpMistralClient::new(
+                        settings.mistral_base_url.clone(),
+                        settings.mistral_api_key.clone().unwrap_or_default(),
+                    )?
+                    .with_paths(settings.mistral_paths.clone()),
+                )
+            };
+        Self::initialize_with_client(loaded, mistral_client).await
+    }
+
+    /// Initialize the framework against the given Mistral client
+    ///
+    /// Builds exactly what [`Self::initialize_with`] serves, so tests can boot the
+    /// production wiring over a scripted mock.
+    pub async fn initialize_with_client(
+        (settings, effective): (AppSettings, EffectiveConfig),
+        mistral_client: Arc<dyn MistralClient>,
+    ) -> Result<PromptSentinelServer, Box<dyn std::error::Error>> {
+        effective.log();
+
+        let (
+            audit_storage,
+            config_history,
+            rules_archive,
+            attack_candidates,
+            appeals,
+            correlation_ids,
+            model_history,
+            reports,
+            usage,
+            footprint,
+        ) = open_storage(&settings)?;
+        info!("Using {:?} audit storage", settings.audit_backend);
+        let generator =
+            CorrelationIdGenerator::open(correlation_ids, settings.instance_id.as_deref())?;
+        info!(
+            "Generating correlation ids as instance {}",
+            generator.instance_id()
+        );
+        install_correlation_id_generator(generator);
+        // Without CHAOS_MODE nothing is wrapped, so no fault can ever be injected
+        let chaos = ChaosController::new(settings.chaos_mode);
+        let (mistral_client, audit_storage) = if chaos.is_available() {
+            warn!("CHAOS_MODE is on: faults can be injected through POST /api/chaos/config");
+            let client: Arc<dyn MistralClient> =
+                Arc::new(ChaosMistralClient::new(mistral_client, chaos.clone()));
+            let storage: Arc<dyn AuditStorage> =
+                Arc::new(ChaosAuditStorage::new(audit_storage, chaos.clone()));
+            (client, storage)
+        } else {
+            (mistral_client, audit_storage)
+        };
+        let mistral_client: Arc<dyn MistralClient> = if settings.demo.enabled {
+            warn!(
+                "DEMO_MODE is on: answers are canned and only the public compliance endpoints \
+                 are served"
+            );
+            Arc::new(DemoMistralClient::new(mistral_client, settings.demo))
+        } else {
+            mistral_client
+        };
+        let audit_storage: Arc<dyn AuditStorage> = match settings.audit_write_behind {
+            Some(config) => {
+                info!(
+                    "Writing audit records behind: every {} ms or {} records, up to {} queued",
+                    config.flush_interval_ms, config.max_batch_size, config.queue_capacity
+                );
+                Arc::new(BatchedAuditStorage::new(audit_storage, config))
+            }
+            None => audit_storage,
+        };
+        let audit_logger = AuditLogger::new(audit_storage);
+        let disk_monitor = footprint.map(|footprint| {
+            DiskMonitor::new(&settings.sled_db_path, footprint, settings.audit_disk)
+        });
+
+        let mistral_service = MistralService::new(
+            mistral_client.clone(),
+            settings.generation_model.clone(),
+            settings.moderation_model.clone(),
+            settings.embedding_model.clone(),
+        )
+        .with_concurrency_limits(settings.mistral_concurrency)
+        .with_moderation_severity(settings.moderation_severity.clone());
+
+        if settings.strict_firewall_rules {
+            let path = rules::configured_rules_path();
+            if let Err(e) = rules::compile_rules_file(&path) {
+                for failure in e.failures() {
+                    error!(
+                        "Firewall rule assertion failed: expected {:?}, got {:?} for {:?}",
+                        failure.expected, failure.actual, failure.prompt
+                    );
+                }
+                error!("Firewall rules file {} rejected: {}", path.display(), e);
+                return Err(Box::new(e));
+            }
+        }
+        let firewall_service = PromptFirewallService::new_with_mistral(
+            settings.max_input_length,
+            mistral_client.clone(),
+        )
+        .with_rules_archive(rules_archive, settings.firewall_rules_history_limit)
+        .with_length_overflow_policy(settings.length_overflow_policy)
+        .with_url_policy(settings.url_policy.clone())
+        .with_url_unfurling(settings.url_unfurl.clone());
+        let bias_service =
+            BiasDetectionService::new_with_mistral(settings.bias_threshold, mistral_client.clone())
+                .with_rewrite_suggestions(mistral_service.clone(), settings.bias_rewrite);
+
+        let semantic_service = SemanticDetectionService::new(
             mistral_service.clone(),
             settings.semantic_medium_threshold,
             settings.semantic_high_threshold,
             settings.semantic_decision_margin,
-        );
-        info!("Initializing semantic detection service...");
-        semantic_service.initialize().await.map_err(|e| {
-            error!("Semantic detection initialization failed: {}", e);
-            Box::new(e) as Box<dyn std::error::Error>
-        })?;
-        info!("Semantic detection service initialized successfully");
+        )
+        .with_bank_hygiene(settings.semantic_bank_hygiene)
+        .with_chunking(settings.semantic_chunking)
+        .with_embedding_cache(settings.semantic_embedding_cache_size);
+        let dependencies =
+            schedule_startup_probes(settings.startup, &mistral_service, &semantic_service).await?;
 
-        let engine = ComplianceEngine::new(
+        let mut engine = ComplianceEngine::new(
             firewall_service,
             semantic_service,
             bias_service,
             mistral_service,
             audit_logger,
-        );
+        )
+        .with_policy(WorkflowPolicy {
+            moderate_removed_content: settings.moderate_removed_content,
+            moderate_translated_output: settings.moderate_translated_output
+                && settings.moderation_model.is_some(),
+            unmoderated: settings.unmoderated_requests,
+        })
+        .with_repeat_offender_config(settings.repeat_offender)
+        .with_sanitize_probing(
+            settings.sanitize_probing,
+            settings.alert_webhook_url.clone().map(WebhookNotifier::new),
+        )
+        .with_document_scan_limits(settings.document_scan_limits)
+        .with_preprocessors(settings.prompt_preprocessors.clone())
+        .with_semantic_sampling(settings.semantic_sampling)
+        .with_demo_mode(settings.demo.enabled)
+        .with_prompt_storage(settings.audit_prompt_storage)
+        .with_audit_suppression(settings.audit_suppression.clone())
+        .with_generation_preamble(settings.generation_preamble.clone())
+        .with_deterministic_generation(
+            settings.deterministic_generation,
+            settings.deterministic_seed,
+        )
+        .with_stage_failure_policy(settings.stage_failure_policy)
+        .with_output_moderation_chunking(settings.output_moderation_chunking)
+        .with_output_analysis(settings.output_analysis)
+        .with_llm_judge(settings.llm_judge.clone())
+        .with_explanation_policy(settings.explanation.clone())
+        .with_correlation_id_policy(settings.correlation_ids.clone())
+        .with_risk_weights(settings.risk_weights)
+        .with_decision_policy(settings.decision_policy.clone())
+        .with_refusals(settings.refusal.clone())
+        .with_firewall_miss_capacity(settings.firewall_miss_buffer_size)
+        .with_attack_candidate_store(attack_candidates)
+        .with_appeal_store(appeals)
+        .with_appeal_policy(settings.appeals);
+        if let Some(secret) = &settings.semantic_sampling_secret {
+            engine = engine.with_semantic_sampling_secret(secret);
+        }
 
-        Ok(PromptSentinelServer::new(settings, engine))
+        let admission = settings.admission;
+        Ok(PromptSentinelServer::new(settings, engine)
+            .with_chaos(chaos)
+            .with_config_history(config_history)
+            .with_model_history(model_history)
+            .with_report_store(reports)
+            .with_usage_store(usage)
+            .with_disk_monitor(disk_monitor)
+            .with_admission_control(admission)
+            .with_dependency_health(dependencies)
+            .with_effective_config(effective))
     }
 }
+
+/// Validate the Mistral models, then embed the semantic attack bank, each as its
+/// startup policy says
+///
+/// The bank is embedded through Mistral, so a lazy bank waits for Mistral to validate.
+async fn schedule_startup_probes(
+    config: StartupConfig,
+    mistral: &MistralService,
+    semantic: &SemanticDetectionService,
+) -> Result<DependencyHealth, StartupError> {
+    let dependencies = DependencyHealth::new(config);
+    let models = mistral.clone();
+    dependencies
+        .schedule(
+            Dependency::Mistral,
+            config.mistral,
+            None,
+            probe(move || {
+                let models = models.clone();
+                async move {
+                    models
+                        .validate_all_models()
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+            }),
+        )
+        .await?;
+    let bank = semantic.clone();
+    dependencies
+        .schedule(
+            Dependency::Semantic,
+            config.semantic,
+            Some(Dependency::Mistral),
+            probe(move || {
+                let bank = bank.clone();
+                async move { bank.initialize().await.map_err(|e| e.to_string()) }
+            }),
+        )
+        .await?;
+    Ok(dependencies)
+}
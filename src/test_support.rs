@@ -0,0 +1,374 @@
+//! Harness for tests that exercise the HTTP API end to end
+//!
+//! [`TestApp`] wires the complete [`PromptSentinelServer`] router to an in-memory audit
+//! store and a programmable [`MockMistralClient`]. Requests can go through
+//! [`TestApp::router`] with `tower::ServiceExt::oneshot`, or over a real socket with
+//! [`TestApp::serve`], which also hands out a `reqwest` client; workflow tests can call
+//! [`TestApp::engine`] directly. Like the mock client it
+//! is part of the public API so downstream crates can test their own deployments.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::config::settings::AppSettings;
+use crate::modules::audit::disk::DiskMonitor;
+use crate::modules::audit::logger::AuditLogger;
+use crate::modules::audit::storage::InMemoryAuditStorage;
+use crate::modules::bias_detection::service::BiasDetectionService;
+use crate::modules::chaos::client::ChaosMistralClient;
+use crate::modules::chaos::service::ChaosController;
+use crate::modules::demo::client::DemoMistralClient;
+use crate::modules::mistral_ai::client::{MistralClient, MockMistralClient};
+use crate::modules::mistral_ai::service::MistralService;
+use crate::modules::model_watch::service::ModelWatch;
+use crate::modules::prompt_firewall::service::PromptFirewallService;
+use crate::modules::semantic_detection::service::{
+    SemanticDetectionError, SemanticDetectionService,
+};
+use crate::{ComplianceEngine, PromptSentinelServer};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Hook<T> = Box<dyn FnOnce(T) -> T>;
+
+/// Builder for a [`TestApp`]; every part defaults to what a plain test needs
+pub struct TestAppBuilder {
+    settings: AppSettings,
+    mock: MockMistralClient,
+    /// Built from the settings' input length limit when not given
+    firewall: Option<PromptFirewallService>,
+    bias: Option<BiasDetectionService>,
+    bias_rewrites: bool,
+    initialize_semantic: bool,
+    disk: Option<DiskMonitor>,
+    client: Vec<Hook<Arc<dyn MistralClient>>>,
+    mistral: Vec<Hook<MistralService>>,
+    semantic: Vec<Hook<SemanticDetectionService>>,
+    engine: Vec<Hook<ComplianceEngine>>,
+    server: Vec<Hook<PromptSentinelServer>>,
+}
+
+impl TestAppBuilder {
+    pub fn with_settings(mut self, settings: AppSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Token admin requests must carry; admin endpoints answer 403 without one
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.settings.admin_token = Some(token.into());
+        self
+    }
+
+    /// Mistral mock the engine talks to; keep a clone to script it or read its calls
+    pub fn with_mock(mut self, mock: MockMistralClient) -> Self {
+        self.mock = mock;
+        self
+    }
+
+    pub fn with_firewall(mut self, firewall: PromptFirewallService) -> Self {
+        self.firewall = Some(firewall);
+        self
+    }
+
+    pub fn with_bias(mut self, bias: BiasDetectionService) -> Self {
+        self.bias = Some(bias);
+        self
+    }
+
+    /// Have the bias scan suggest rewrites through the mock, configured by the settings'
+    /// `bias_rewrite`
+    pub fn with_bias_rewrites(mut self) -> Self {
+        self.bias_rewrites = true;
+        self
+    }
+
+    /// Load the attack bank before serving, embedding it through the mock
+    ///
+    /// Left out by default, since the mock's constant embedding makes an initialized
+    /// bank match every prompt; see [`MockMistralClient::with_deterministic_embeddings`].
+    pub fn with_semantic_bank(mut self) -> Self {
+        self.initialize_semantic = true;
+        self
+    }
+
+    /// Measure a database with `monitor`; its high-water policy applies to the app's
+    /// audit writes even though they go to memory
+    pub fn with_disk_monitor(mut self, monitor: DiskMonitor) -> Self {
+        self.disk = Some(monitor);
+        self
+    }
+
+    /// Wrap the Mistral client, e.g. to observe or delay calls on their way to the mock
+    pub fn with_client(
+        mut self,
+        wrap: impl FnOnce(Arc<dyn MistralClient>) -> Arc<dyn MistralClient> + 'static,
+    ) -> Self {
+        self.client.push(Box::new(wrap));
+        self
+    }
+
+    /// Configure the Mistral service beyond what the settings cover
+    pub fn with_mistral(
+        mut self,
+        configure: impl FnOnce(MistralService) -> MistralService + 'static,
+    ) -> Self {
+        self.mistral.push(Box::new(configure));
+        self
+    }
+
+    /// Configure the semantic service beyond what the settings cover; runs before the
+    /// attack bank is loaded
+    pub fn with_semantic(
+        mut self,
+        configure: impl FnOnce(SemanticDetectionService) -> SemanticDetectionService + 'static,
+    ) -> Self {
+        self.semantic.push(Box::new(configure));
+        self
+    }
+
+    /// Configure the engine beyond what the settings cover
+    pub fn with_engine(
+        mut self,
+        configure: impl FnOnce(ComplianceEngine) -> ComplianceEngine + 'static,
+    ) -> Self {
+        self.engine.push(Box::new(configure));
+        self
+    }
+
+    /// Configure the server beyond what the settings cover, e.g. with a store
+    pub fn with_server(
+        mut self,
+        configure: impl FnOnce(PromptSentinelServer) -> PromptSentinelServer + 'static,
+    ) -> Self {
+        self.server.push(Box::new(configure));
+        self
+    }
+
+    pub async fn build(self) -> Result<TestApp, SemanticDetectionError> {
+        // Wrapped as the server wraps its client, so faults reach the mock's callers
+        let chaos = ChaosController::new(self.settings.chaos_mode);
+        let client: Arc<dyn MistralClient> = if chaos.is_available() {
+            Arc::new(ChaosMistralClient::new(
+                Arc::new(self.mock.clone()),
+                chaos.clone(),
+            ))
+        } else {
+            Arc::new(self.mock.clone())
+        };
+        let client: Arc<dyn MistralClient> = if self.settings.demo.enabled {
+            Arc::new(DemoMistralClient::new(client, self.settings.demo))
+        } else {
+            client
+        };
+        let client = self
+            .client
+            .into_iter()
+            .fold(client, |client, wrap| wrap(client));
+        let mistral = MistralService::new(
+            client,
+            &self.settings.generation_model,
+            self.settings.moderation_model.clone(),
+            &self.settings.embedding_model,
+        )
+        .with_moderation_severity(self.settings.moderation_severity.clone())
+        .with_concurrency_limits(self.settings.mistral_concurrency);
+        let mistral = apply(mistral, self.mistral);
+        let semantic = SemanticDetectionService::new(
+            mistral.clone(),
+            self.settings.semantic_medium_threshold,
+            self.settings.semantic_high_threshold,
+            self.settings.semantic_decision_margin,
+        )
+        .with_embedding_cache(self.settings.semantic_embedding_cache_size)
+        .with_chunking(self.settings.semantic_chunking);
+        let semantic = apply(semantic, self.semantic);
+        if self.initialize_semantic {
+            semantic.initialize().await?;
+        }
+        let storage = Arc::new(InMemoryAuditStorage::new());
+        let firewall = self.firewall.unwrap_or_else(|| {
+            PromptFirewallService::new(self.settings.max_input_length)
+                .with_length_overflow_policy(self.settings.length_overflow_policy)
+        });
+        let bias = self.bias.unwrap_or_default();
+        let bias = if self.bias_rewrites {
+            bias.with_rewrite_suggestions(mistral.clone(), self.settings.bias_rewrite)
+        } else {
+            bias
+        };
+        let mut engine = ComplianceEngine::new(
+            firewall,
+            semantic,
+            bias,
+            mistral,
+            AuditLogger::new(storage.clone()),
+        )
+        .with_decision_policy(self.settings.decision_policy.clone())
+        .with_refusals(self.settings.refusal.clone())
+        .with_preprocessors(self.settings.prompt_preprocessors.clone())
+        .with_demo_mode(self.settings.demo.enabled)
+        .with_prompt_storage(self.settings.audit_prompt_storage)
+        .with_audit_suppression(self.settings.audit_suppression.clone())
+        .with_generation_preamble(self.settings.generation_preamble.clone())
+        .with_deterministic_generation(
+            self.settings.deterministic_generation,
+            self.settings.deterministic_seed,
+        )
+        .with_stage_failure_policy(self.settings.stage_failure_policy)
+        .with_output_moderation_chunking(self.settings.output_moderation_chunking)
+        .with_output_analysis(self.settings.output_analysis)
+        .with_llm_judge(self.settings.llm_judge.clone())
+        .with_sanitize_probing(self.settings.sanitize_probing, None)
+        .with_explanation_policy(self.settings.explanation.clone())
+        .with_appeal_policy(self.settings.appeals)
+        .with_firewall_miss_capacity(self.settings.firewall_miss_buffer_size)
+        .with_document_scan_limits(self.settings.document_scan_limits)
+        .with_correlation_id_policy(self.settings.correlation_ids.clone())
+        .with_semantic_sampling(self.settings.semantic_sampling)
+        .with_repeat_offender_config(self.settings.repeat_offender)
+        .with_risk_weights(self.settings.risk_weights);
+        if let Some(secret) = &self.settings.semantic_sampling_secret {
+            engine = engine.with_semantic_sampling_secret(secret);
+        }
+        let engine = apply(engine, self.engine);
+        let audit_logger = engine.audit_logger().clone();
+        let admin_token = self.settings.admin_token.clone();
+        let admission = self.settings.admission;
+        let server = PromptSentinelServer::new(self.settings, engine.clone())
+            .with_chaos(chaos)
+            .with_disk_monitor(self.disk)
+            .with_admission_control(admission);
+        let server = apply(server, self.server);
+        Ok(TestApp {
+            mock: self.mock,
+            storage,
+            audit_logger,
+            model_watch: server.model_watch().clone(),
+            engine,
+            admin_token,
+            router: server.build_router(),
+        })
+    }
+}
+
+fn apply<T>(value: T, hooks: Vec<Hook<T>>) -> T {
+    hooks.into_iter().fold(value, |value, hook| hook(value))
+}
+
+/// The full HTTP API over mock dependencies
+pub struct TestApp {
+    /// Shares its script and recorded calls with the client inside the engine
+    pub mock: MockMistralClient,
+    /// Audit store the engine writes to
+    pub storage: Arc<InMemoryAuditStorage>,
+    /// The engine's logger; `flush` records repeats counted in open suppression windows
+    pub audit_logger: AuditLogger,
+    /// Never scheduled here; run [`ModelWatch::check`] to compare the mock's models
+    pub model_watch: ModelWatch,
+    /// The engine behind the router, for tests that run the workflow directly
+    pub engine: ComplianceEngine,
+    admin_token: Option<String>,
+    router: Router,
+}
+
+impl TestApp {
+    pub fn builder() -> TestAppBuilder {
+        TestAppBuilder {
+            settings: AppSettings::default(),
+            mock: MockMistralClient::default(),
+            firewall: None,
+            bias: None,
+            bias_rewrites: false,
+            initialize_semantic: false,
+            disk: None,
+            client: Vec::new(),
+            mistral: Vec::new(),
+            semantic: Vec::new(),
+            engine: Vec::new(),
+            server: Vec::new(),
+        }
+    }
+
+    /// App with default settings, the default mock and no admin token
+    pub async fn new() -> Self {
+        Self::builder()
+            .build()
+            .await
+            .expect("no attack bank is loaded by default")
+    }
+
+    /// A clone of the router, ready for `oneshot`
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// Serve the router on an ephemeral localhost port until the returned handle drops
+    pub async fn serve(&self) -> std::io::Result<TestServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let service = self
+            .router
+            .clone()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        let task = tokio::spawn(async move {
+            axum::serve(listener, service).await.ok();
+        });
+
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.admin_token {
+            let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(std::io::Error::other)?;
+        Ok(TestServer {
+            base_url: format!("http://{address}"),
+            client,
+            task,
+        })
+    }
+}
+
+/// A [`TestApp`] listening on a local port
+///
+/// The client sends the admin token, when the app has one, with every request.
+pub struct TestServer {
+    pub base_url: String,
+    pub client: reqwest::Client,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.get(self.url(path))
+    }
+
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.post(self.url(path))
+    }
+
+    pub fn delete(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.delete(self.url(path))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,78 @@
+//! `wasm-bindgen` bindings to the lexical firewall, for pre-screening prompts in the browser
+//!
+//! The verdicts match the server's because this is the server's own matching code
+//! ([`crate::firewall_core`]); only translation, the semantic scan and moderation are
+//! left to the server. `scripts/build_wasm.sh` compiles the crate for
+//! `wasm32-unknown-unknown` as a `cdylib` with nothing but this feature and runs
+//! `wasm-bindgen` over the result, leaving a package in `pkg/`:
+//!
+//! ```js
+//! import init, { inspect, Firewall } from "./pkg/prompt_sentinel.js";
+//!
+//! await init();
+//! const rules = await (await fetch("/firewall_rules.json")).text();
+//!
+//! // One-off check; the rules are compiled on every call
+//! const result = inspect(prompt, rules);
+//! if (result.action === "block") {
+//!   showWarning(result.reasons);
+//! }
+//!
+//! // Compile once when checking as the user types
+//! const firewall = new Firewall(rules);
+//! input.addEventListener("input", () => render(firewall.inspect(input.value)));
+//! ```
+//!
+//! Results have the shape of `firewall` in a compliance response. Rule sets the server
+//! would refuse throw an `Error`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::firewall_core::dtos::PromptFirewallResult;
+use crate::firewall_core::rules::{CompiledFirewallRules, evaluate_with_rules};
+use crate::firewall_core::{self, DEFAULT_MAX_INPUT_LENGTH};
+
+/// Evaluate `prompt` against the rule set in `rules_json`
+#[wasm_bindgen]
+pub fn inspect(prompt: &str, rules_json: &str) -> Result<JsValue, JsError> {
+    to_js(firewall_core::inspect(prompt, rules_json))
+}
+
+/// A rule set compiled once and evaluated many times
+#[wasm_bindgen]
+pub struct Firewall {
+    rules: CompiledFirewallRules,
+    max_input_length: usize,
+}
+
+#[wasm_bindgen]
+impl Firewall {
+    /// Compile `rules_json`; `max_input_length` defaults to the server's 4096
+    #[wasm_bindgen(constructor)]
+    pub fn new(rules_json: &str, max_input_length: Option<usize>) -> Result<Firewall, JsError> {
+        Ok(Self {
+            rules: firewall_core::compile_rules_json(rules_json).map_err(|e| JsError::new(&e))?,
+            max_input_length: max_input_length.unwrap_or(DEFAULT_MAX_INPUT_LENGTH),
+        })
+    }
+
+    pub fn inspect(&self, prompt: &str) -> Result<JsValue, JsError> {
+        to_js(Ok(evaluate_with_rules(
+            prompt,
+            self.max_input_length,
+            &self.rules,
+        )))
+    }
+
+    /// Content hash of the rule set, as the server reports it
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.rules.fingerprint().to_owned()
+    }
+}
+
+fn to_js(result: Result<PromptFirewallResult, String>) -> Result<JsValue, JsError> {
+    let result = result.map_err(|e| JsError::new(&e))?;
+    let json = serde_json::to_string(&result).map_err(|e| JsError::new(&e.to_string()))?;
+    js_sys::JSON::parse(&json).map_err(|_| JsError::new("firewall result is not valid JSON"))
+}
@@ -0,0 +1,229 @@
+//! Appeals against blocked decisions: filed by the user, resolved by a reviewer, and
+//! audited under the appealed decision's correlation id at every step
+
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+use tracing::info;
+use uuid::Uuid;
+
+use super::replay::current_name;
+use super::{ComplianceEngine, ReplayError, WorkflowStatus};
+use crate::modules::appeals::dtos::{
+    Appeal, AppealOutcome, AppealRequest, AppealResolution, AppealState, ResolveAppealRequest,
+};
+use crate::modules::appeals::storage::AppealStoreError;
+use crate::modules::audit::logger::{AppealEvent, AuditError, AuditEvent};
+use crate::modules::audit::storage::{AuditStorageError, PromptStorageMode};
+use crate::modules::exemptions::dtos::ExemptionRequest;
+use crate::modules::exemptions::service::ExemptionError;
+
+#[derive(Debug, Error)]
+pub enum AppealError {
+    #[error("invalid appeal: {0}")]
+    Invalid(String),
+    #[error("no audited decision found for correlation id {0}")]
+    DecisionNotFound(String),
+    #[error(
+        "decision {correlation_id} was not blocked (status {status}), so it cannot be appealed"
+    )]
+    NotBlocked {
+        correlation_id: String,
+        status: String,
+    },
+    #[error("decision {correlation_id} already has open appeal {appeal_id}")]
+    AlreadyOpen {
+        correlation_id: String,
+        appeal_id: String,
+    },
+    #[error("no appeal with id {0}")]
+    NotFound(String),
+    #[error("appeal {id} was already resolved as {state}")]
+    AlreadyResolved { id: String, state: &'static str },
+    #[error("failed to read the audit trail: {0}")]
+    Storage(#[from] AuditStorageError),
+    #[error("appeal store failure: {0}")]
+    Store(#[from] AppealStoreError),
+    #[error("failed to grant the appeal's exemption: {0}")]
+    Exemption(#[from] ExemptionError),
+    #[error("failed to audit the appeal: {0}")]
+    Audit(#[from] AuditError),
+    #[error("appeal transition lock poisoned")]
+    LockPoisoned,
+}
+
+impl ComplianceEngine {
+    /// Appeal the blocked decision `request.correlation_id`
+    ///
+    /// The filing is audited under the appealed decision's correlation id before the
+    /// appeal is stored; `request_correlation_id` identifies the call that filed it.
+    pub fn file_appeal(
+        &self,
+        request_correlation_id: &str,
+        request: AppealRequest,
+    ) -> Result<Appeal, AppealError> {
+        request.validate().map_err(AppealError::Invalid)?;
+        let correlation_id = request.correlation_id.trim().to_owned();
+        let (_, event) = self.appealed_decision(&correlation_id)?;
+        let status = current_name::<WorkflowStatus>(&event.final_status);
+        if !status.starts_with("blocked_by_") {
+            return Err(AppealError::NotBlocked {
+                correlation_id,
+                status,
+            });
+        }
+
+        let _transition = self
+            .appeal_transitions
+            .lock()
+            .map_err(|_| AppealError::LockPoisoned)?;
+        if let Some(open) = self.appeals.list()?.into_iter().find(|appeal| {
+            appeal.correlation_id == correlation_id && appeal.state == AppealState::Open
+        }) {
+            return Err(AppealError::AlreadyOpen {
+                correlation_id,
+                appeal_id: open.id,
+            });
+        }
+        let appeal = Appeal {
+            id: Uuid::new_v4().to_string(),
+            correlation_id,
+            decision_status: status,
+            justification: request.justification,
+            state: AppealState::Open,
+            filed_at: Utc::now(),
+            resolution: None,
+        };
+        self.audit_appeal(request_correlation_id, "filed", &appeal)?;
+        self.appeals.upsert(appeal.clone())?;
+        info!(
+            "Appeal {} filed against decision {}",
+            appeal.id, appeal.correlation_id
+        );
+        Ok(appeal)
+    }
+
+    /// Appeals in `state`, or all of them, oldest first
+    pub fn appeals(&self, state: Option<AppealState>) -> Result<Vec<Appeal>, AppealError> {
+        let mut appeals: Vec<Appeal> = self
+            .appeals
+            .list()?
+            .into_iter()
+            .filter(|appeal| state.is_none_or(|state| appeal.state == state))
+            .collect();
+        appeals.sort_by_key(|appeal| appeal.filed_at);
+        Ok(appeals)
+    }
+
+    /// Record a reviewer's finding on an open appeal
+    ///
+    /// Overturning a firewall block grants an exemption per blocking rule, limited to
+    /// prompts containing the appealed one and bounded by the engine's
+    /// [`AppealPolicy`](crate::modules::appeals::dtos::AppealPolicy). Other blocks, and
+    /// decisions whose prompt was not stored, are overturned on the record only.
+    pub fn resolve_appeal(
+        &self,
+        id: &str,
+        request_correlation_id: &str,
+        request: ResolveAppealRequest,
+    ) -> Result<Appeal, AppealError> {
+        request.validate().map_err(AppealError::Invalid)?;
+        let _transition = self
+            .appeal_transitions
+            .lock()
+            .map_err(|_| AppealError::LockPoisoned)?;
+        let mut appeal = self
+            .appeals
+            .get(id)?
+            .ok_or_else(|| AppealError::NotFound(id.to_owned()))?;
+        if appeal.state != AppealState::Open {
+            return Err(AppealError::AlreadyResolved {
+                id: appeal.id,
+                state: appeal.state.as_str(),
+            });
+        }
+
+        let exemption_ids = match request.outcome {
+            AppealOutcome::Upheld => Vec::new(),
+            AppealOutcome::Overturned => self.grant_appeal_exemptions(&appeal, &request)?,
+        };
+        appeal.state = request.outcome.into();
+        appeal.resolution = Some(AppealResolution {
+            outcome: request.outcome,
+            note: request.note,
+            resolved_by: request.resolved_by,
+            resolved_at: Utc::now(),
+            exemption_ids,
+        });
+        self.audit_appeal(request_correlation_id, appeal.state.as_str(), &appeal)?;
+        self.appeals.upsert(appeal.clone())?;
+        info!("Appeal {} resolved: {}", appeal.id, appeal.state.as_str());
+        Ok(appeal)
+    }
+
+    fn appealed_decision(
+        &self,
+        correlation_id: &str,
+    ) -> Result<(DateTime<Utc>, AuditEvent), AppealError> {
+        self.recorded_event(correlation_id).map_err(|e| match e {
+            ReplayError::Storage(e) => AppealError::Storage(e),
+            _ => AppealError::DecisionNotFound(correlation_id.to_owned()),
+        })
+    }
+
+    fn grant_appeal_exemptions(
+        &self,
+        appeal: &Appeal,
+        request: &ResolveAppealRequest,
+    ) -> Result<Vec<String>, AppealError> {
+        let (_, event) = self.appealed_decision(&appeal.correlation_id)?;
+        if event.prompt_storage != PromptStorageMode::Full {
+            return Ok(Vec::new());
+        }
+        let blocking_rules: Vec<String> = event
+            .decision_trace
+            .iter()
+            .filter(|step| step.stage == "firewall" && step.verdict == "block")
+            .flat_map(|step| step.rule_refs.iter())
+            .filter(|rule| !rule.starts_with("exemption:"))
+            .cloned()
+            .collect();
+
+        let expires_at = Duration::try_seconds(self.appeal_policy.exemption_ttl_secs as i64)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let mut exemption_ids = Vec::with_capacity(blocking_rules.len());
+        for rule_id in blocking_rules {
+            // Granted for the decision, so the trail links the two
+            let exemption = self.exemptions.grant_for_decision(
+                &appeal.correlation_id,
+                ExemptionRequest {
+                    rule_id,
+                    content_match: Some(event.original_prompt.clone()),
+                    expires_at: Some(expires_at),
+                    max_uses: Some(self.appeal_policy.exemption_max_uses),
+                    granted_by: request.resolved_by.clone(),
+                    reason: format!("appeal {} overturned: {}", appeal.id, request.note),
+                },
+            )?;
+            exemption_ids.push(exemption.id);
+        }
+        Ok(exemption_ids)
+    }
+
+    fn audit_appeal(
+        &self,
+        request_correlation_id: &str,
+        action: &str,
+        appeal: &Appeal,
+    ) -> Result<(), AuditError> {
+        self.audit_logger
+            .log_appeal(AppealEvent {
+                correlation_id: appeal.correlation_id.clone(),
+                event_type: "appeal".to_owned(),
+                action: action.to_owned(),
+                request_correlation_id: request_correlation_id.to_owned(),
+                appeal: appeal.clone(),
+            })
+            .map(drop)
+    }
+}
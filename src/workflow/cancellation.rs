@@ -0,0 +1,154 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Instant;
+
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use super::WorkflowError;
+use crate::modules::audit::logger::{AbandonmentEvent, AuditLogger};
+use crate::modules::telemetry::metrics::get_metrics;
+
+const ABANDONED_EVENT: &str = "abandoned_by_client";
+
+/// Part of the pipeline a request had reached when its caller went away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum AbandonedStage {
+    /// Preprocessing through input moderation; nothing has been generated yet
+    InputChecks,
+    /// From the end of the input checks until the answer is translated back
+    Generation,
+    /// Output analysis and moderation of a generated answer
+    OutputChecks,
+}
+
+impl AbandonedStage {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::InputChecks => "input_checks",
+            Self::Generation => "generation",
+            Self::OutputChecks => "output_checks",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::InputChecks,
+            1 => Self::Generation,
+            _ => Self::OutputChecks,
+        }
+    }
+}
+
+/// Tracks how far a live run got, so a run that never finishes is reported as abandoned
+///
+/// A run ends early either when its token is cancelled, at the next checkpoint, or when
+/// its future is dropped, which is what axum does when the client disconnects. Both are
+/// counted in `abandoned_requests_total` when the guard drops; past the input checks an
+/// `abandoned_by_client` audit record stands in for the decision record never written.
+pub(super) struct Abandonment {
+    token: CancellationToken,
+    stage: AtomicU8,
+    generation_started: AtomicBool,
+    started: Instant,
+    correlation_id: String,
+    audit_logger: AuditLogger,
+    /// Cleared once the run returns anything but a cancellation; self-tests never arm it
+    armed: bool,
+}
+
+impl Abandonment {
+    pub(super) fn new(
+        token: CancellationToken,
+        correlation_id: String,
+        audit_logger: AuditLogger,
+        armed: bool,
+    ) -> Self {
+        Self {
+            token,
+            stage: AtomicU8::new(AbandonedStage::InputChecks as u8),
+            generation_started: AtomicBool::new(false),
+            started: Instant::now(),
+            correlation_id,
+            audit_logger,
+            armed,
+        }
+    }
+
+    pub(super) fn enter(&self, stage: AbandonedStage) {
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    /// Called right before the generation request goes out
+    pub(super) fn generation_started(&self) {
+        self.generation_started.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop here if the caller has gone away
+    pub(super) fn checkpoint(&self) -> Result<(), WorkflowError> {
+        if self.token.is_cancelled() {
+            return Err(WorkflowError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Await `future` unless the token is cancelled first, which drops it mid-call
+    pub(super) async fn unless_cancelled<T>(
+        &self,
+        future: impl Future<Output = T>,
+    ) -> Result<T, WorkflowError> {
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => Err(WorkflowError::Cancelled),
+            output = future => Ok(output),
+        }
+    }
+
+    /// Disarm unless the run stopped because it was cancelled
+    pub(super) fn finish<T>(mut self, result: &Result<T, WorkflowError>) {
+        if !matches!(result, Err(WorkflowError::Cancelled)) {
+            self.armed = false;
+        }
+    }
+}
+
+impl Drop for Abandonment {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let stage = AbandonedStage::from_u8(self.stage.load(Ordering::Relaxed));
+        let generation_started = self.generation_started.load(Ordering::Relaxed);
+        warn!(
+            correlation_id = %self.correlation_id,
+            stage = stage.as_str(),
+            generation_started,
+            "Request abandoned by the client"
+        );
+        get_metrics().increment_abandoned_requests(stage.as_str());
+        if stage == AbandonedStage::InputChecks {
+            return;
+        }
+
+        let event = AbandonmentEvent {
+            correlation_id: self.correlation_id.clone(),
+            event_type: ABANDONED_EVENT.to_owned(),
+            stage: stage.as_str().to_owned(),
+            generation_started,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+        };
+        let logger = self.audit_logger.clone();
+        let record = move || {
+            if let Err(e) = logger.log_abandonment(event) {
+                error!("Failed to audit an abandoned request: {}", e);
+            }
+        };
+        // Storage I/O stays off the runtime worker that is dropping the run
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(record);
+            }
+            Err(_) => record(),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use chrono::{Duration, Utc};
+use thiserror::Error;
+use tracing::warn;
+
+use super::ComplianceEngine;
+use crate::firewall_core::taxonomy::{moderation_category, resolve_category};
+use crate::modules::audit::logger::{AuditError, AuditEvent, ConfigChangeEvent};
+use crate::modules::audit::proof::hash_record;
+use crate::modules::audit::storage::{AuditStorageError, PromptStorageMode};
+use crate::modules::prompt_firewall::rules::{FirewallRulesConfig, canonicalize_for_block_match};
+use crate::modules::semantic_detection::candidates::CandidateStoreError;
+use crate::modules::semantic_detection::dtos::{
+    ApprovedCandidate, AttackCandidate, AttackTemplate, CandidateGenerationReport,
+};
+use crate::modules::semantic_detection::service::{SemanticDetectionError, cosine_similarity};
+
+/// Canonical token overlap (Jaccard) at which two blocked prompts count as one attack
+const TOKEN_SIMILARITY_THRESHOLD: f32 = 0.8;
+/// Embedding similarity at which two blocked prompts count as one attack
+const EMBEDDING_SIMILARITY_THRESHOLD: f32 = 0.95;
+/// Outcomes whose prompts are mined; output moderation blocks say nothing about the prompt
+const MINED_STATUSES: &[&str] = &["blocked_by_firewall", "blocked_by_input_moderation"];
+/// Namespaced, so an approved candidate without a better category still loads
+const UNCATEGORIZED: &str = "candidates:uncategorized";
+
+/// Parse a look-back window such as `7d`, `24h`, `30m` or `90s`
+pub fn parse_window(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let invalid = || format!("invalid window '{value}' (expected e.g. 7d, 24h or 30m)");
+    let split = value.len().checked_sub(1).ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    if amount <= 0 {
+        return Err(invalid());
+    }
+    match unit {
+        "d" => Duration::try_days(amount),
+        "h" => Duration::try_hours(amount),
+        "m" => Duration::try_minutes(amount),
+        "s" => Duration::try_seconds(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+/// A blocked prompt read back from the audit trail
+struct BlockedPrompt {
+    correlation_id: String,
+    text: String,
+    canonical: String,
+    tokens: BTreeSet<String>,
+    firewall_rules: Vec<String>,
+    moderation_categories: Vec<String>,
+    semantic_category: Option<String>,
+    embedding: Option<Vec<f32>>,
+}
+
+impl BlockedPrompt {
+    fn from_event(event: AuditEvent) -> Self {
+        let canonical = canonicalize_for_block_match(&event.original_prompt);
+        let refs = |matches: fn(&str) -> bool| -> Vec<String> {
+            event
+                .decision_trace
+                .iter()
+                .filter(|step| matches(&step.stage) && step.verdict == "block")
+                .flat_map(|step| step.rule_refs.iter())
+                // Exemptions are listed with the rules but never caused the block
+                .filter(|rule| !rule.starts_with("exemption:"))
+                .cloned()
+                .collect()
+        };
+        let firewall_rules = refs(|stage| stage == "firewall");
+        let moderation_categories =
+            refs(|stage| stage == "input_moderation" || stage == "removed_content_moderation");
+        Self {
+            correlation_id: event.correlation_id,
+            tokens: tokens(&canonical),
+            canonical,
+            firewall_rules,
+            moderation_categories,
+            semantic_category: event.semantic_category,
+            embedding: None,
+            text: event.original_prompt,
+        }
+    }
+
+    fn resembles(&self, other: &Self) -> bool {
+        if self.canonical == other.canonical
+            || jaccard(&self.tokens, &other.tokens) >= TOKEN_SIMILARITY_THRESHOLD
+        {
+            return true;
+        }
+        match (&self.embedding, &other.embedding) {
+            (Some(a), Some(b)) => cosine_similarity(a, b) >= EMBEDDING_SIMILARITY_THRESHOLD,
+            _ => false,
+        }
+    }
+}
+
+impl ComplianceEngine {
+    /// Mine the audit trail for prompts blocked within `window` and queue them for review
+    ///
+    /// Near-duplicates are folded into one candidate whose representative is the shortest
+    /// prompt of the cluster. Candidates keep their id across runs, so regenerating
+    /// refreshes rather than duplicates them. Prompts already in the bank are skipped.
+    pub async fn generate_attack_candidates(
+        &self,
+        window: Duration,
+    ) -> Result<CandidateGenerationReport, CandidateError> {
+        if self.prompt_storage != PromptStorageMode::Full {
+            return Err(CandidateError::PromptsNotStored);
+        }
+        let since = Utc::now() - window;
+        let records = self
+            .audit_logger
+            .storage()
+            .get_with_filters(None, None, Some(since), None, None)?
+            .records;
+
+        let mut blocked_prompts = 0;
+        let mut skipped_unstored = 0;
+        let mut prompts = Vec::new();
+        for record in records {
+            let Ok(event) = serde_json::from_str::<AuditEvent>(&record.payload) else {
+                continue;
+            };
+            if event.self_test || !MINED_STATUSES.contains(&event.final_status.as_str()) {
+                continue;
+            }
+            blocked_prompts += 1;
+            // Records written before full storage was enabled only hold a hash
+            if event.prompt_storage != PromptStorageMode::Full {
+                skipped_unstored += 1;
+                continue;
+            }
+            prompts.push(BlockedPrompt::from_event(event));
+        }
+        self.embed_prompts(&mut prompts).await;
+
+        let bank = self.semantic_service.templates().await;
+        let bank_texts: HashSet<String> = bank
+            .iter()
+            .map(|template| canonicalize_for_block_match(&template.text))
+            .collect();
+        let pending = self.attack_candidates.list()?;
+        let mut taken_ids: HashSet<String> = bank
+            .iter()
+            .map(|template| template.id.clone())
+            .chain(
+                pending
+                    .iter()
+                    .map(|candidate| candidate.template.id.clone()),
+            )
+            .collect();
+        let rules = self.firewall_service.rules_config();
+
+        let generated_at = Utc::now();
+        let mut candidates = Vec::new();
+        for cluster in cluster(prompts) {
+            let representative = cluster
+                .iter()
+                .min_by_key(|prompt| prompt.text.chars().count())
+                .expect("clusters are never empty");
+            if bank_texts.contains(&representative.canonical) {
+                continue;
+            }
+            let id = format!("cand-{}", &hash_record(&representative.canonical)[..12]);
+            let template_id = match pending.iter().find(|candidate| candidate.id == id) {
+                Some(existing) => existing.template.id.clone(),
+                None => {
+                    let next = next_template_id(&taken_ids);
+                    taken_ids.insert(next.clone());
+                    next
+                }
+            };
+            let source_rules: BTreeSet<String> = cluster
+                .iter()
+                .flat_map(|prompt| {
+                    prompt
+                        .firewall_rules
+                        .iter()
+                        .chain(prompt.moderation_categories.iter())
+                })
+                .cloned()
+                .collect();
+            let candidate = AttackCandidate {
+                id,
+                template: AttackTemplate {
+                    id: template_id,
+                    category: infer_category(&cluster, &rules, &bank),
+                    text: representative.text.clone(),
+                },
+                source_rules: source_rules.into_iter().collect(),
+                correlation_ids: cluster
+                    .iter()
+                    .map(|prompt| prompt.correlation_id.clone())
+                    .collect(),
+                generated_at,
+            };
+            self.attack_candidates.upsert(candidate.clone())?;
+            candidates.push(candidate);
+        }
+
+        Ok(CandidateGenerationReport {
+            since,
+            blocked_prompts,
+            skipped_unstored,
+            candidates,
+        })
+    }
+
+    /// Candidates awaiting review
+    pub fn attack_candidates(&self) -> Result<Vec<AttackCandidate>, CandidateError> {
+        Ok(self.attack_candidates.list()?)
+    }
+
+    /// Discard a candidate without touching the bank
+    pub fn reject_attack_candidate(&self, id: &str) -> Result<AttackCandidate, CandidateError> {
+        self.attack_candidates
+            .remove(id)?
+            .ok_or_else(|| CandidateError::NotFound(id.to_owned()))
+    }
+
+    /// Merge a candidate into the live attack bank and audit the change
+    ///
+    /// The suggested template id is replaced with the next free one if the bank has
+    /// taken it since the candidate was generated.
+    pub async fn approve_attack_candidate(
+        &self,
+        id: &str,
+        correlation_id: &str,
+    ) -> Result<ApprovedCandidate, CandidateError> {
+        let candidate = self
+            .attack_candidates
+            .get(id)?
+            .ok_or_else(|| CandidateError::NotFound(id.to_owned()))?;
+        let mut template = candidate.template;
+        let bank_ids: HashSet<String> = self
+            .semantic_service
+            .templates()
+            .await
+            .into_iter()
+            .map(|template| template.id)
+            .collect();
+        if bank_ids.contains(&template.id) {
+            template.id = next_template_id(&bank_ids);
+        }
+
+        let previous_hash = self.semantic_service.bank_fingerprint().unwrap_or_default();
+        let template_count = self.semantic_service.add_template(template.clone()).await?;
+        self.attack_candidates.remove(id)?;
+        let bank_fingerprint = self.semantic_service.bank_fingerprint();
+        self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: "attack_template_approved".to_owned(),
+            previous_hash,
+            new_hash: bank_fingerprint.clone().unwrap_or_default(),
+            snapshot_version: None,
+            detail: Some(format!("{} from candidate {}", template.id, id)),
+        })?;
+
+        Ok(ApprovedCandidate {
+            candidate_id: id.to_owned(),
+            template,
+            template_count,
+            bank_fingerprint,
+        })
+    }
+
+    /// Attach embeddings when the semantic detector is running; clustering falls back to
+    /// canonical text alone if any embedding fails
+    async fn embed_prompts(&self, prompts: &mut [BlockedPrompt]) {
+        if !self.semantic_service.is_initialized().await {
+            return;
+        }
+        for index in 0..prompts.len() {
+            match self
+                .semantic_service
+                .compute_embedding(&prompts[index].text)
+                .await
+            {
+                Ok(embedding) => prompts[index].embedding = Some(embedding),
+                Err(e) => {
+                    warn!("Clustering attack candidates without embeddings: {}", e);
+                    for prompt in prompts.iter_mut() {
+                        prompt.embedding = None;
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Group prompts with the first cluster whose seed they resemble
+fn cluster(prompts: Vec<BlockedPrompt>) -> Vec<Vec<BlockedPrompt>> {
+    let mut clusters: Vec<Vec<BlockedPrompt>> = Vec::new();
+    for prompt in prompts {
+        match clusters
+            .iter_mut()
+            .find(|cluster| cluster[0].resembles(&prompt))
+        {
+            Some(cluster) => cluster.push(prompt),
+            None => clusters.push(vec![prompt]),
+        }
+    }
+    clusters
+}
+
+/// Category for a cluster, from the rules that blocked it
+///
+/// A firewall rule with a category lends it to the cluster; otherwise a rule takes the
+/// category of the bank template sharing the most words with its pattern. Moderation
+/// blocks use the flagged category, namespaced when the taxonomy has no entry for it,
+/// then the semantic category recorded with the request, if any.
+fn infer_category(
+    cluster: &[BlockedPrompt],
+    rules: &FirewallRulesConfig,
+    bank: &[AttackTemplate],
+) -> String {
+    let blocking_rules = || {
+        cluster
+            .iter()
+            .flat_map(|prompt| prompt.firewall_rules.iter())
+            .filter_map(|rule_id| rules.block_rules.iter().find(|rule| &rule.id == rule_id))
+    };
+    if let Some(category) =
+        blocking_rules().find_map(|rule| rules.rule_category(rule).ok().flatten())
+    {
+        return category.to_string();
+    }
+
+    let bank_tokens: Vec<(BTreeSet<String>, &str)> = bank
+        .iter()
+        .map(|template| {
+            (
+                tokens(&canonicalize_for_block_match(&template.text)),
+                template.category.as_str(),
+            )
+        })
+        .collect();
+    let from_rules = blocking_rules().find_map(|rule| {
+        let pattern = tokens(&canonicalize_for_block_match(&rule.pattern));
+        bank_tokens
+            .iter()
+            .map(|(template, category)| (template.intersection(&pattern).count(), *category))
+            .filter(|(overlap, _)| *overlap > 0)
+            // Earliest template wins ties
+            .rev()
+            .max_by_key(|(overlap, _)| *overlap)
+            .map(|(_, category)| category.to_owned())
+    });
+    from_rules
+        .or_else(|| {
+            cluster
+                .iter()
+                .find_map(|prompt| prompt.moderation_categories.first())
+                // Names the taxonomy resolves are kept as the moderation reported them
+                .map(
+                    |category| match resolve_category(category, &BTreeMap::new()) {
+                        Ok(_) => category.clone(),
+                        Err(_) => moderation_category(category).to_string(),
+                    },
+                )
+        })
+        .or_else(|| {
+            cluster
+                .iter()
+                .find_map(|prompt| prompt.semantic_category.clone())
+        })
+        .unwrap_or_else(|| UNCATEGORIZED.to_owned())
+}
+
+/// Next `SEM-NNN` id after the highest one taken
+fn next_template_id(taken: &HashSet<String>) -> String {
+    let highest = taken
+        .iter()
+        .filter_map(|id| id.strip_prefix("SEM-")?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("SEM-{:03}", highest + 1)
+}
+
+fn tokens(canonical: &str) -> BTreeSet<String> {
+    canonical.split(' ').map(str::to_owned).collect()
+}
+
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+#[derive(Debug, Error)]
+pub enum CandidateError {
+    #[error(
+        "prompts are not stored (AUDIT_PROMPT_STORAGE is not 'full'), so no candidates can be generated"
+    )]
+    PromptsNotStored,
+    #[error("no attack template candidate with id {0}")]
+    NotFound(String),
+    #[error("failed to read the audit trail: {0}")]
+    Storage(#[from] AuditStorageError),
+    #[error("failed to access the candidate store: {0}")]
+    Store(#[from] CandidateStoreError),
+    #[error("failed to update the attack bank: {0}")]
+    Semantic(#[from] SemanticDetectionError),
+    #[error("failed to audit the bank change: {0}")]
+    Audit(#[from] AuditError),
+}
@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{ComplianceEngine, content_ref};
+use crate::modules::audit::logger::{AuditError, DocumentAuditEntry, DocumentScanEvent};
+use crate::modules::audit::proof::AuditProof;
+use crate::modules::semantic_detection::dtos::{
+    SemanticRiskLevel, SemanticScanRequest, SemanticScanResult,
+};
+use crate::modules::telemetry::tracing::log_with_correlation;
+
+/// A retrieved passage or tool output about to be placed in the model's context
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ScannedDocument {
+    /// Caller-chosen identifier, unique within the request
+    pub id: String,
+    pub text: String,
+    /// Where the document came from (URL, index name, tool name)
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DocumentScanRequest {
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    pub documents: Vec<ScannedDocument>,
+    /// The question the documents were retrieved for; only its hash is audited
+    #[serde(default)]
+    pub context: Option<String>,
+}
+
+/// Outcome for one document or a whole batch, ordered by severity
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DocumentVerdict {
+    Allow,
+    /// Elevated semantic risk; usable with caution
+    Flag,
+    Block,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DocumentScanResult {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Hash of the document text, as recorded in the audit trail
+    pub content_hash: String,
+    pub verdict: DocumentVerdict,
+    /// Firewall block rules matched in the text
+    pub matched_rules: Vec<String>,
+    pub reasons: Vec<String>,
+    /// Absent when the semantic detector could not score the document
+    pub semantic: Option<SemanticScanResult>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DocumentScanResponse {
+    pub correlation_id: String,
+    /// `Block` when any document is blocked, otherwise the most severe document verdict
+    pub verdict: DocumentVerdict,
+    pub blocked_document_ids: Vec<String>,
+    /// Per-document results in request order
+    pub documents: Vec<DocumentScanResult>,
+    pub audit_proof: AuditProof,
+}
+
+/// Bounds on a single document scan request
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DocumentScanLimits {
+    pub max_documents: usize,
+    /// Combined size of all document texts in bytes
+    pub max_total_bytes: usize,
+}
+
+impl Default for DocumentScanLimits {
+    fn default() -> Self {
+        Self {
+            max_documents: 32,
+            max_total_bytes: 256 * 1024,
+        }
+    }
+}
+
+impl DocumentScanLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_documents == 0 {
+            return Err("document scan max documents must be greater than zero".to_owned());
+        }
+        if self.max_total_bytes == 0 {
+            return Err("document scan max total bytes must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+
+    fn check(&self, documents: &[ScannedDocument]) -> Result<(), DocumentScanError> {
+        if documents.is_empty() {
+            return Err(DocumentScanError::NoDocuments);
+        }
+        if documents.len() > self.max_documents {
+            return Err(DocumentScanError::TooManyDocuments {
+                count: documents.len(),
+                max: self.max_documents,
+            });
+        }
+        let total_bytes = documents.iter().map(|document| document.text.len()).sum();
+        if total_bytes > self.max_total_bytes {
+            return Err(DocumentScanError::TooLarge {
+                total_bytes,
+                max: self.max_total_bytes,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for (index, document) in documents.iter().enumerate() {
+            if document.id.trim().is_empty() {
+                return Err(DocumentScanError::EmptyId(index));
+            }
+            if !seen.insert(document.id.as_str()) {
+                return Err(DocumentScanError::DuplicateId(document.id.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ComplianceEngine {
+    /// Scan retrieved documents or tool outputs for indirect prompt injection
+    ///
+    /// Each document only goes through the firewall block rules and the semantic detector;
+    /// nothing is generated or moderated. One audit record summarizes the batch by
+    /// document id and content hash.
+    pub async fn scan_documents(
+        &self,
+        request: DocumentScanRequest,
+    ) -> Result<DocumentScanResponse, DocumentScanError> {
+        self.document_limits.check(&request.documents)?;
+        let correlation_id = self.correlation_ids.normalize(request.correlation_id);
+        log_with_correlation(
+            &correlation_id,
+            tracing::Level::INFO,
+            &format!("Scanning {} documents", request.documents.len()),
+        );
+
+        let mut documents = Vec::with_capacity(request.documents.len());
+        for document in request.documents {
+            documents.push(self.scan_document(document).await);
+        }
+
+        let verdict = documents
+            .iter()
+            .map(|document| document.verdict)
+            .max()
+            .unwrap_or(DocumentVerdict::Allow);
+        let blocked_document_ids: Vec<String> = documents
+            .iter()
+            .filter(|document| document.verdict == DocumentVerdict::Block)
+            .map(|document| document.id.clone())
+            .collect();
+        if !blocked_document_ids.is_empty() {
+            log_with_correlation(
+                &correlation_id,
+                tracing::Level::WARN,
+                &format!("Documents blocked: {}", blocked_document_ids.join(", ")),
+            );
+        }
+
+        let audit_proof = self
+            .audit_logger
+            .log_document_scan(DocumentScanEvent {
+                correlation_id: correlation_id.clone(),
+                event_type: "document_scan".to_owned(),
+                verdict: verdict_label(verdict).to_owned(),
+                context_hash: request.context.as_deref().map(content_ref),
+                documents: documents
+                    .iter()
+                    .map(|document| DocumentAuditEntry {
+                        id: document.id.clone(),
+                        source: document.source.clone(),
+                        content_hash: document.content_hash.clone(),
+                        verdict: verdict_label(document.verdict).to_owned(),
+                        matched_rules: document.matched_rules.clone(),
+                        semantic_risk_score: document.semantic.as_ref().map(|s| s.risk_score),
+                        semantic_template_id: document
+                            .semantic
+                            .as_ref()
+                            .and_then(|s| s.nearest_template_id.clone()),
+                    })
+                    .collect(),
+            })
+            .await?;
+
+        Ok(DocumentScanResponse {
+            correlation_id,
+            verdict,
+            blocked_document_ids,
+            documents,
+            audit_proof,
+        })
+    }
+
+    async fn scan_document(&self, document: ScannedDocument) -> DocumentScanResult {
+        let matches = self.firewall_service.match_block_rules(&document.text);
+        let content_hash = content_ref(&document.text);
+        let semantic = self
+            .semantic_service
+            .scan(SemanticScanRequest {
+                text: document.text,
+                detected_language: None,
+                pre_translated_text: None,
+            })
+            .await
+            .ok();
+
+        let mut reasons: Vec<String> = matches
+            .iter()
+            .map(|rule| format!("matched high-risk injection pattern: {}", rule.pattern))
+            .collect();
+        let semantic_level = semantic.as_ref().map(|s| &s.risk_level);
+        if let Some(sem) = &semantic
+            && sem.risk_level != SemanticRiskLevel::Low
+        {
+            reasons.push(format!(
+                "semantic similarity to attack pattern {} (category: {}, score: {:.2})",
+                sem.nearest_template_id.as_deref().unwrap_or("unknown"),
+                sem.category.as_deref().unwrap_or("unknown"),
+                sem.similarity
+            ));
+        }
+
+        let verdict = if !matches.is_empty() || semantic_level == Some(&SemanticRiskLevel::High) {
+            DocumentVerdict::Block
+        } else if semantic_level == Some(&SemanticRiskLevel::Medium) {
+            DocumentVerdict::Flag
+        } else {
+            DocumentVerdict::Allow
+        };
+
+        DocumentScanResult {
+            id: document.id,
+            source: document.source,
+            content_hash,
+            verdict,
+            matched_rules: matches.into_iter().map(|rule| rule.id).collect(),
+            reasons,
+            semantic,
+        }
+    }
+}
+
+fn verdict_label(verdict: DocumentVerdict) -> &'static str {
+    match verdict {
+        DocumentVerdict::Allow => "allow",
+        DocumentVerdict::Flag => "flag",
+        DocumentVerdict::Block => "block",
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DocumentScanError {
+    #[error("no documents to scan")]
+    NoDocuments,
+    #[error("too many documents: {count} exceeds the limit of {max}")]
+    TooManyDocuments { count: usize, max: usize },
+    #[error("documents too large: {total_bytes} bytes exceeds the limit of {max} bytes")]
+    TooLarge { total_bytes: usize, max: usize },
+    #[error("document at index {0} has an empty id")]
+    EmptyId(usize),
+    #[error("duplicate document id '{0}'")]
+    DuplicateId(String),
+    #[error("audit workflow failure: {0}")]
+    Audit(#[from] AuditError),
+}
+
+impl DocumentScanError {
+    /// Whether the error was caused by the submitted batch rather than the server
+    pub fn is_rejected_input(&self) -> bool {
+        !matches!(self, Self::Audit(_))
+    }
+
+    /// Whether the batch exceeded a size limit
+    pub fn is_over_limit(&self) -> bool {
+        matches!(self, Self::TooManyDocuments { .. } | Self::TooLarge { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(id: &str, text: &str) -> ScannedDocument {
+        ScannedDocument {
+            id: id.to_owned(),
+            text: text.to_owned(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn limits_reject_oversized_and_malformed_batches() {
+        let limits = DocumentScanLimits {
+            max_documents: 2,
+            max_total_bytes: 10,
+        };
+        assert!(limits.check(&[document("a", "12345")]).is_ok());
+        assert!(matches!(
+            limits.check(&[]),
+            Err(DocumentScanError::NoDocuments)
+        ));
+        assert!(matches!(
+            limits.check(&[document("a", ""), document("b", ""), document("c", "")]),
+            Err(DocumentScanError::TooManyDocuments { count: 3, max: 2 })
+        ));
+        assert!(matches!(
+            limits.check(&[document("a", "123456"), document("b", "123456")]),
+            Err(DocumentScanError::TooLarge {
+                total_bytes: 12,
+                max: 10
+            })
+        ));
+        assert!(matches!(
+            limits.check(&[document("a", "x"), document("a", "y")]),
+            Err(DocumentScanError::DuplicateId(id)) if id == "a"
+        ));
+        assert!(matches!(
+            limits.check(&[document(" ", "x")]),
+            Err(DocumentScanError::EmptyId(0))
+        ));
+    }
+
+    #[test]
+    fn verdicts_order_by_severity() {
+        assert!(DocumentVerdict::Block > DocumentVerdict::Flag);
+        assert!(DocumentVerdict::Flag > DocumentVerdict::Allow);
+    }
+}
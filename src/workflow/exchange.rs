@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    ComplianceEngine, ComplianceRequest, ComplianceResponse, RunKind, WorkflowError, WorkflowStatus,
+};
+
+/// A prompt and the response the caller's own model produced for it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ValidateExchangeRequest {
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    pub prompt: String,
+    pub generated_text: String,
+}
+
+/// What validating an exchange concluded, from the caller's side of the call
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeVerdict {
+    /// Both the prompt and the response pass
+    Passed,
+    /// The prompt would have been sanitized before generation; the response passes
+    InputWouldSanitize,
+    /// The prompt would have been refused before generation, so the response was not checked
+    InputWouldBlock,
+    /// The prompt passes but the response does not
+    OutputViolatesPolicy,
+    /// A stage whose failure policy is `closed` could not complete
+    Unverified,
+}
+
+impl ExchangeVerdict {
+    pub fn from_status(status: &WorkflowStatus) -> Self {
+        match status {
+            WorkflowStatus::Completed => Self::Passed,
+            WorkflowStatus::Sanitized => Self::InputWouldSanitize,
+            WorkflowStatus::BlockedByFirewall
+            | WorkflowStatus::BlockedBySemantic
+            | WorkflowStatus::BlockedByInputModeration
+            | WorkflowStatus::BlockedByEuCompliance => Self::InputWouldBlock,
+            WorkflowStatus::BlockedByOutputModeration => Self::OutputViolatesPolicy,
+            WorkflowStatus::BlockedByStageFailure { .. } => Self::Unverified,
+        }
+    }
+}
+
+/// A compliance response for a caller-generated exchange, with its verdict
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExchangeValidationResponse {
+    pub verdict: ExchangeVerdict,
+    #[serde(flatten)]
+    pub compliance: ComplianceResponse,
+}
+
+impl ComplianceEngine {
+    /// Check a prompt and the response the caller already generated for it
+    ///
+    /// The prompt goes through the input-side stages as in [`ComplianceEngine::process`]
+    /// and `generated_text` takes the place of generation in the output-side stages, so
+    /// Mistral is never asked to generate. The audit record carries hashes of both texts.
+    pub async fn validate_exchange(
+        &self,
+        request: ValidateExchangeRequest,
+    ) -> Result<ExchangeValidationResponse, WorkflowError> {
+        let ValidateExchangeRequest {
+            correlation_id,
+            prompt,
+            generated_text,
+        } = request;
+        let compliance = self
+            .run(
+                ComplianceRequest {
+                    correlation_id,
+                    prompt,
+                    suggest_rewrite: false,
+                    template: None,
+                    deterministic: None,
+                    session_id: None,
+                },
+                Some(generated_text),
+                RunKind::Live,
+                None,
+                CancellationToken::new(),
+            )
+            .await?;
+        Ok(ExchangeValidationResponse {
+            verdict: ExchangeVerdict::from_status(&compliance.status),
+            compliance,
+        })
+    }
+}
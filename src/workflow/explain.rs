@@ -0,0 +1,606 @@
+//! Plain-language explanations of audited decisions, for support teams answering
+//! "why was my prompt rejected?"
+
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::warn;
+
+use super::{ComplianceEngine, DEFAULT_REASON_LOCALE, ReasonCode, ReplayError, render_reason};
+use crate::modules::audit::logger::AuditEvent;
+use crate::modules::audit::storage::AuditStorageError;
+use crate::modules::prompt_firewall::rules::FirewallRulesConfig;
+
+/// Who an explanation is written for, decided by the token the caller presented
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplanationAudience {
+    /// Holders of `SUPPORT_API_TOKEN`
+    Support,
+    /// Holders of `ADMIN_API_TOKEN`
+    Admin,
+}
+
+impl ExplanationAudience {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Support => "support",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+/// How much of the rules behind a decision an explanation reveals
+///
+/// Firewall patterns and attack template ids are withheld from anyone who could pass
+/// them on to an attacker probing for the rule list.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplanationRedaction {
+    /// Rule ids, patterns, template ids and the recorded reason
+    Full,
+    /// No rule ids; each matched phrase is cut down to its first and last words
+    #[default]
+    Generalized,
+    /// Only the deciding layer, a summary and generic advice
+    Minimal,
+}
+
+impl ExplanationRedaction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Generalized => "generalized",
+            Self::Minimal => "minimal",
+        }
+    }
+}
+
+impl std::str::FromStr for ExplanationRedaction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "generalized" => Ok(Self::Generalized),
+            "minimal" => Ok(Self::Minimal),
+            other => Err(format!(
+                "unknown explanation redaction '{other}' (expected full, generalized or minimal)"
+            )),
+        }
+    }
+}
+
+/// Redaction per audience and where users can contest a decision
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExplanationPolicy {
+    /// Redaction of explanations for support staff (default: generalized)
+    pub support_redaction: ExplanationRedaction,
+    /// Redaction of explanations for administrators (default: full)
+    pub admin_redaction: ExplanationRedaction,
+    /// Where users can send feedback on or appeal a decision; none by default
+    pub feedback_url: Option<String>,
+}
+
+impl Default for ExplanationPolicy {
+    fn default() -> Self {
+        Self {
+            support_redaction: ExplanationRedaction::Generalized,
+            admin_redaction: ExplanationRedaction::Full,
+            feedback_url: None,
+        }
+    }
+}
+
+impl ExplanationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .feedback_url
+            .as_deref()
+            .is_some_and(|url| url.trim().is_empty())
+        {
+            return Err("explanation feedback URL must not be empty when set".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn redaction(&self, audience: ExplanationAudience) -> ExplanationRedaction {
+        match audience {
+            ExplanationAudience::Support => self.support_redaction,
+            ExplanationAudience::Admin => self.admin_redaction,
+        }
+    }
+}
+
+/// A firewall rule the prompt matched, as far as the redaction allows
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MatchedPhrase {
+    /// Only with `full` redaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    /// The rule's pattern, or its first and last words under `generalized`; absent for
+    /// rules no longer configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phrase: Option<String>,
+    /// `block` for phrases that stop the prompt, `sanitize` for phrases removed from it
+    pub effect: String,
+}
+
+/// Whether a user can contest the decision, and where
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AppealPath {
+    pub available: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Response of `GET /api/decisions/{correlation_id}/explain`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DecisionExplanation {
+    pub correlation_id: String,
+    pub audience: ExplanationAudience,
+    pub redaction: ExplanationRedaction,
+    /// When the decision was audited
+    pub decided_at: DateTime<Utc>,
+    /// `allow`, `sanitize` or `block`
+    pub decision: String,
+    /// Workflow status recorded for the request
+    pub status: String,
+    /// Pipeline stage that decided (e.g. "firewall", "semantic", "bias")
+    pub decided_by: String,
+    /// The deciding stage in words
+    pub decided_by_label: String,
+    /// Why, in plain language and without rule details the redaction withholds
+    pub summary: String,
+    /// The recorded reason, rule ids included; only with `full` redaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub reason_code: ReasonCode,
+    /// Decision policy rule that decided; only with `full` redaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_rule: Option<String>,
+    /// What the user could change to get a different outcome
+    pub suggestions: Vec<String>,
+    /// Firewall rules the prompt matched; empty under `minimal`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matched_phrases: Vec<MatchedPhrase>,
+    pub appeal: AppealPath,
+    /// Everything above, pre-rendered for pasting into a ticket
+    pub markdown: String,
+}
+
+#[derive(Debug, Error)]
+pub enum ExplainError {
+    #[error("no audited decision found for correlation id {0}")]
+    NotFound(String),
+    #[error("failed to read the audit trail: {0}")]
+    Storage(#[from] AuditStorageError),
+}
+
+impl ComplianceEngine {
+    /// Explain the audited decision for `correlation_id` to `audience`
+    ///
+    /// The redaction comes from the engine's [`ExplanationPolicy`], never from the caller.
+    pub fn explain(
+        &self,
+        correlation_id: &str,
+        audience: ExplanationAudience,
+    ) -> Result<DecisionExplanation, ExplainError> {
+        let (decided_at, event) = self.recorded_event(correlation_id).map_err(|e| match e {
+            ReplayError::Storage(e) => ExplainError::Storage(e),
+            _ => ExplainError::NotFound(correlation_id.to_owned()),
+        })?;
+        let redaction = self.explanation.redaction(audience);
+        let decided_by = deciding_stage(&event);
+        let matched_phrases = match redaction {
+            ExplanationRedaction::Minimal => Vec::new(),
+            _ => matched_phrases(&event, &self.rules_for(&event), redaction),
+        };
+        let full = redaction == ExplanationRedaction::Full;
+
+        let mut explanation = DecisionExplanation {
+            correlation_id: event.correlation_id.clone(),
+            audience,
+            redaction,
+            decided_at,
+            decision: decision(&event.final_status).to_owned(),
+            status: event.final_status.clone(),
+            decided_by_label: stage_label(&decided_by).to_owned(),
+            summary: summary(&event, &decided_by, redaction),
+            reason: full.then(|| match event.final_reason_code {
+                ReasonCode::Unspecified => event.final_reason.clone(),
+                code => render_reason(code, &event.reason_params, DEFAULT_REASON_LOCALE),
+            }),
+            reason_code: event.final_reason_code,
+            policy_rule: event.policy_rule.clone().filter(|_| full),
+            suggestions: suggestions(&event, &decided_by, !matched_phrases.is_empty()),
+            decided_by,
+            matched_phrases,
+            appeal: AppealPath {
+                available: self.explanation.feedback_url.is_some(),
+                url: self.explanation.feedback_url.clone(),
+            },
+            markdown: String::new(),
+        };
+        explanation.markdown = render_markdown(&explanation);
+        Ok(explanation)
+    }
+
+    /// The firewall rules the decision was made with, or the current ones when those
+    /// are no longer archived
+    fn rules_for(&self, event: &AuditEvent) -> FirewallRulesConfig {
+        let fingerprint = &event.config_fingerprint.firewall_rules;
+        match self.firewall_service.archived_rules(fingerprint) {
+            Ok(Some(rules)) => rules.config().clone(),
+            Ok(None) => self.firewall_service.rules_config(),
+            Err(e) => {
+                warn!("Explaining with the current firewall rules: {}", e);
+                self.firewall_service.rules_config()
+            }
+        }
+    }
+}
+
+fn decision(final_status: &str) -> &'static str {
+    match final_status {
+        "completed" => "allow",
+        "sanitized" => "sanitize",
+        _ => "block",
+    }
+}
+
+/// Stage of the decisive trace step, or the one the status and reason point to for
+/// records that did not keep it
+fn deciding_stage(event: &AuditEvent) -> String {
+    if let Some(step) = event
+        .decisive_step
+        .and_then(|index| event.decision_trace.get(index))
+    {
+        return step.stage.clone();
+    }
+    let stage = match (event.final_status.as_str(), event.final_reason_code) {
+        ("blocked_by_eu_compliance", _) | (_, ReasonCode::EuProhibitedPractice) => "eu_compliance",
+        ("blocked_by_semantic", ReasonCode::RepeatOfBlockedPrompt) => "repeat_offender",
+        ("blocked_by_semantic", ReasonCode::LlmJudgeEscalation) => "llm_judge",
+        (_, ReasonCode::SanitizerProbing) => "sanitize_probing",
+        ("blocked_by_semantic", _) | (_, ReasonCode::ElevatedSemanticRisk) => "semantic",
+        ("blocked_by_input_moderation", ReasonCode::RemovedContentModerationFlag) => {
+            "removed_content_moderation"
+        }
+        ("blocked_by_input_moderation", _) | (_, ReasonCode::ModerationNotConfigured) => {
+            "input_moderation"
+        }
+        ("blocked_by_output_moderation", ReasonCode::OutputFirewallEcho) => "output_firewall_echo",
+        ("blocked_by_output_moderation", _) => "output_moderation",
+        ("blocked_by_stage_failure", _) => "stage_failure",
+        ("blocked_by_firewall", _) | (_, ReasonCode::Sanitized) => "firewall",
+        _ => "workflow",
+    };
+    stage.to_owned()
+}
+
+fn stage_label(stage: &str) -> &'static str {
+    match stage {
+        "firewall" => "Prompt firewall",
+        "semantic" => "Semantic attack detection",
+        "repeat_offender" => "Repeat-offender detection",
+        "sanitize_probing" => "Sanitizer probing detection",
+        "llm_judge" => "Second-opinion check",
+        "bias" => "Bias detection",
+        "input_moderation" => "Input moderation",
+        "removed_content_moderation" => "Moderation of removed content",
+        "output_moderation" | "translated_output_moderation" => "Output moderation",
+        "output_bias" | "output_firewall_echo" | "output_semantic" => "Output analysis",
+        "eu_compliance" => "EU AI Act compliance check",
+        "stage_failure" => "A safety check that could not complete",
+        _ => "The compliance workflow",
+    }
+}
+
+fn summary(event: &AuditEvent, stage: &str, redaction: ExplanationRedaction) -> String {
+    let detailed = redaction != ExplanationRedaction::Minimal;
+    let param = |key: &str| {
+        event
+            .reason_params
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|_| detailed)
+    };
+    let categories = event
+        .reason_params
+        .get("categories")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|categories| detailed && !categories.is_empty());
+    let with = |text: &str, detail: Option<String>| match detail {
+        Some(detail) => format!("{text}: {detail}."),
+        None => format!("{text}."),
+    };
+    match event.final_reason_code {
+        ReasonCode::EuProhibitedPractice => with(
+            "The request falls under a practice the EU AI Act prohibits",
+            param("finding").map(str::to_owned),
+        ),
+        ReasonCode::FirewallRuleMatch => {
+            "The prompt contains phrasing the prompt firewall does not accept, such as \
+             attempts to override the assistant's instructions."
+                .to_owned()
+        }
+        ReasonCode::ExcessiveSanitization => {
+            "Removing the phrasing the firewall does not accept would have left too little of \
+             the prompt, so it was blocked instead."
+                .to_owned()
+        }
+        ReasonCode::RepeatOfBlockedPrompt => {
+            "The prompt closely resembles one that was blocked shortly before.".to_owned()
+        }
+        ReasonCode::SanitizerProbing => {
+            "The prompt is one of several sent in the same session that resend content the \
+             prompt firewall removes, which looks like probing of its filters."
+                .to_owned()
+        }
+        ReasonCode::SemanticSimilarity => with(
+            "The prompt closely resembles a known attempt to manipulate the assistant",
+            param("category").map(|category| format!("category {category}")),
+        ),
+        ReasonCode::LlmJudgeEscalation => {
+            "The prompt partly resembles attempts to manipulate the assistant, and a second \
+             check judged it to be one."
+                .to_owned()
+        }
+        ReasonCode::StageFailure => with(
+            "A safety check could not complete, and the service does not answer without it",
+            param("stage").map(|stage| format!("the {stage} check failed")),
+        ),
+        ReasonCode::InputModerationFlag => {
+            with("Content moderation flagged the prompt", categories)
+        }
+        ReasonCode::RemovedContentModerationFlag => with(
+            "Content moderation flagged the part of the prompt the firewall removed",
+            categories,
+        ),
+        ReasonCode::OutputModerationFlag => with(
+            "Content moderation flagged the generated answer, not the prompt itself",
+            categories,
+        ),
+        ReasonCode::OutputFirewallEcho => {
+            "The generated answer repeated phrasing the prompt firewall blocks, so it was \
+             withheld."
+                .to_owned()
+        }
+        ReasonCode::Sanitized => {
+            "Phrasing the prompt firewall does not accept was removed before the prompt was \
+             answered."
+                .to_owned()
+        }
+        ReasonCode::ElevatedSemanticRisk => {
+            "The prompt was answered with extra caution because it partly resembles known \
+             attempts to manipulate the assistant."
+                .to_owned()
+        }
+        ReasonCode::ModerationNotConfigured => {
+            "The prompt was answered with extra caution because content moderation is not \
+             configured."
+                .to_owned()
+        }
+        ReasonCode::AllChecksPassed => "The prompt passed every check.".to_owned(),
+        ReasonCode::PolicyRule => format!(
+            "A decision rule set by the operators applied to the result of the {} stage.",
+            stage_label(stage).to_lowercase()
+        ),
+        ReasonCode::Unspecified if redaction == ExplanationRedaction::Full => {
+            event.final_reason.clone()
+        }
+        ReasonCode::Unspecified => "No reason was recorded for this decision.".to_owned(),
+    }
+}
+
+fn suggestions(event: &AuditEvent, stage: &str, has_phrases: bool) -> Vec<String> {
+    let mut suggestions = Vec::new();
+    match stage {
+        "firewall" if has_phrases => {
+            suggestions.push("Remove or rephrase the phrases listed below.".to_owned())
+        }
+        "firewall" => suggestions.push(
+            "Remove instructions addressed to the assistant itself, such as asking it to \
+             ignore its rules or reveal its configuration."
+                .to_owned(),
+        ),
+        "semantic" | "repeat_offender" | "llm_judge" => suggestions.push(
+            "Ask for the task directly, without role-play framing or instructions about how \
+             the assistant should behave."
+                .to_owned(),
+        ),
+        "sanitize_probing" => suggestions.push(
+            "Send the request without the markup or instructions the firewall removes; \
+             variations of them in the same session are blocked."
+                .to_owned(),
+        ),
+        "input_moderation" | "removed_content_moderation" => {
+            suggestions.push("Remove content that falls under the flagged categories.".to_owned())
+        }
+        "output_moderation" | "translated_output_moderation" => suggestions.push(
+            "Rephrase the request so the answer does not need to touch on the flagged \
+             categories."
+                .to_owned(),
+        ),
+        "output_firewall_echo" => suggestions.push(
+            "Ask for the task itself rather than for the assistant's instructions or \
+             configuration."
+                .to_owned(),
+        ),
+        "eu_compliance" => suggestions.push(
+            "Requests for this purpose cannot be served; rephrasing them will not change the \
+             outcome."
+                .to_owned(),
+        ),
+        "stage_failure" => suggestions.push("Send the request again later.".to_owned()),
+        _ => {}
+    }
+    if event.bias_level != "low" {
+        suggestions.extend(event.bias_mitigation_hints.iter().cloned());
+        if stage == "bias" && event.bias_mitigation_hints.is_empty() {
+            suggestions.push(
+                "Use neutral wording that does not generalize about groups of people.".to_owned(),
+            );
+        }
+    }
+    suggestions
+}
+
+fn matched_phrases(
+    event: &AuditEvent,
+    rules: &FirewallRulesConfig,
+    redaction: ExplanationRedaction,
+) -> Vec<MatchedPhrase> {
+    let Some(firewall) = event
+        .decision_trace
+        .iter()
+        .find(|step| step.stage == "firewall")
+    else {
+        return Vec::new();
+    };
+    firewall
+        .rule_refs
+        .iter()
+        .filter(|rule_ref| !rule_ref.starts_with("exemption:"))
+        .filter_map(|rule_id| {
+            let (rule, effect) = match rules.block_rules.iter().find(|rule| rule.id == *rule_id) {
+                Some(rule) => (Some(rule), "block"),
+                None => (
+                    rules
+                        .sanitize_patterns
+                        .iter()
+                        .find(|rule| rule.id == *rule_id),
+                    "sanitize",
+                ),
+            };
+            match redaction {
+                ExplanationRedaction::Full => Some(MatchedPhrase {
+                    rule_id: Some(rule_id.clone()),
+                    phrase: rule.map(|rule| rule.pattern.clone()),
+                    effect: effect.to_owned(),
+                }),
+                // Built-in checks without a pattern (control characters, the sanitize
+                // limit) have no phrase to hint at
+                _ => rule.map(|rule| MatchedPhrase {
+                    rule_id: None,
+                    phrase: Some(generalize(&rule.pattern)),
+                    effect: effect.to_owned(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// A pattern cut down to its first and last words, enough for a user to find the
+/// phrase in their own prompt without spelling out the rule
+fn generalize(pattern: &str) -> String {
+    let words: Vec<&str> = pattern.split_whitespace().collect();
+    match words.as_slice() {
+        [] => String::new(),
+        [only] => format!("{}…", only.chars().next().unwrap_or_default()),
+        [first, _] => format!("{first} …"),
+        [first, .., last] => format!("{first} … {last}"),
+    }
+}
+
+fn render_markdown(explanation: &DecisionExplanation) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "### Decision `{}`", explanation.correlation_id);
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "- **Outcome:** {} (`{}`)",
+        explanation.decision, explanation.status
+    );
+    let _ = writeln!(out, "- **Decided by:** {}", explanation.decided_by_label);
+    let _ = writeln!(
+        out,
+        "- **When:** {}",
+        explanation.decided_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if let Some(rule) = &explanation.policy_rule {
+        let _ = writeln!(out, "- **Policy rule:** `{rule}`");
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "**Why:** {}", explanation.summary);
+    if let Some(reason) = &explanation.reason {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "**Recorded reason:** {reason}");
+    }
+    if !explanation.suggestions.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "**What could be changed:**");
+        for suggestion in &explanation.suggestions {
+            let _ = writeln!(out, "- {suggestion}");
+        }
+    }
+    if !explanation.matched_phrases.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "**Matched phrases:**");
+        for matched in &explanation.matched_phrases {
+            let phrase = matched
+                .phrase
+                .as_deref()
+                .map_or_else(|| "(no longer configured)".to_owned(), |p| format!("`{p}`"));
+            match &matched.rule_id {
+                Some(rule_id) => {
+                    let _ = writeln!(out, "- {phrase}: {} (rule `{rule_id}`)", matched.effect);
+                }
+                None => {
+                    let _ = writeln!(out, "- {phrase}: {}", matched.effect);
+                }
+            }
+        }
+    }
+    let _ = writeln!(out);
+    match &explanation.appeal.url {
+        Some(url) => {
+            let _ = writeln!(
+                out,
+                "**Appeal:** if this decision looks wrong, send feedback at {url} quoting \
+                 `{}`.",
+                explanation.correlation_id
+            );
+        }
+        None => {
+            let _ = writeln!(out, "**Appeal:** no feedback path is configured.");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generalized_patterns_keep_only_their_outer_words() {
+        assert_eq!(
+            generalize("ignore all previous instructions"),
+            "ignore … instructions"
+        );
+        assert_eq!(generalize("jailbreak mode"), "jailbreak …");
+        assert_eq!(generalize("DAN"), "D…");
+        assert_eq!(generalize("  "), "");
+    }
+
+    #[test]
+    fn redaction_parses_case_insensitively() {
+        assert_eq!(
+            " Minimal ".parse::<ExplanationRedaction>(),
+            Ok(ExplanationRedaction::Minimal)
+        );
+        assert!("everything".parse::<ExplanationRedaction>().is_err());
+    }
+}
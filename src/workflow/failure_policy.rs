@@ -0,0 +1,123 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Pipeline stages that depend on Mistral and can therefore fail independently
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    /// Detecting the prompt's language
+    Language,
+    /// Translating non-English prompts for the bias term rules
+    Bias,
+    Semantic,
+    /// Input, removed-content and output moderation
+    Moderation,
+    /// Translating the generated answer back to the prompt's language
+    Translation,
+}
+
+impl PipelineStage {
+    pub const ALL: [Self; 5] = [
+        Self::Language,
+        Self::Bias,
+        Self::Semantic,
+        Self::Moderation,
+        Self::Translation,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Language => "language",
+            Self::Bias => "bias",
+            Self::Semantic => "semantic",
+            Self::Moderation => "moderation",
+            Self::Translation => "translation",
+        }
+    }
+}
+
+impl fmt::Display for PipelineStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a request does when a stage fails
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureMode {
+    /// Carry on without the stage's result and record the failure
+    Open,
+    /// Block the request
+    Closed,
+}
+
+impl FailureMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+impl FromStr for FailureMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "open" => Ok(Self::Open),
+            "closed" => Ok(Self::Closed),
+            other => Err(format!(
+                "unknown stage failure policy '{other}' (expected open or closed)"
+            )),
+        }
+    }
+}
+
+/// Failure behaviour of each Mistral-backed stage
+///
+/// The defaults keep moderation mandatory and let the other stages degrade.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageFailurePolicy {
+    pub language: FailureMode,
+    pub bias: FailureMode,
+    pub semantic: FailureMode,
+    pub moderation: FailureMode,
+    pub translation: FailureMode,
+}
+
+impl Default for StageFailurePolicy {
+    fn default() -> Self {
+        Self {
+            language: FailureMode::Open,
+            bias: FailureMode::Open,
+            semantic: FailureMode::Open,
+            moderation: FailureMode::Closed,
+            translation: FailureMode::Open,
+        }
+    }
+}
+
+impl StageFailurePolicy {
+    pub fn mode(&self, stage: PipelineStage) -> FailureMode {
+        match stage {
+            PipelineStage::Language => self.language,
+            PipelineStage::Bias => self.bias,
+            PipelineStage::Semantic => self.semantic,
+            PipelineStage::Moderation => self.moderation,
+            PipelineStage::Translation => self.translation,
+        }
+    }
+}
+
+/// A stage that failed while processing a request
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageFailure {
+    pub stage: PipelineStage,
+    /// Policy applied: `open` requests carried on, `closed` ones were blocked
+    pub policy: FailureMode,
+    pub error: String,
+}
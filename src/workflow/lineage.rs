@@ -0,0 +1,276 @@
+//! The story of one decision across the audit trail: the original record, retries
+//! under the same correlation id, appeals and their resolutions, exemptions granted
+//! for it and the later decisions they let through, and replays
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::ComplianceEngine;
+use super::explain::{ExplanationAudience, ExplanationRedaction};
+use crate::modules::audit::logger::{AppealEvent, AuditEvent, ExemptionEvent, ReplayEvent};
+use crate::modules::audit::storage::{AuditStorageError, StoredAuditRecord};
+
+/// What an entry of a decision lineage records
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineageEntryKind {
+    /// The first decision recorded under the correlation id
+    Decision,
+    /// A later decision under the same correlation id, from a client retrying
+    Retry,
+    AppealFiled,
+    /// An appeal upheld or overturned
+    AppealResolved,
+    ExemptionGranted,
+    /// An exemption granted for the decision that expired, ran out or was revoked
+    ExemptionArchived,
+    /// A re-evaluation of the decision, in any replay mode
+    Replay,
+    /// A decision under another correlation id that an exemption granted for this one
+    /// let through
+    ExemptedDecision,
+}
+
+/// How an entry follows from an earlier one
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineageRelation {
+    Retries,
+    Appeals,
+    Resolves,
+    Grants,
+    Exempts,
+    Archives,
+    Replays,
+    AppliesExemption,
+}
+
+/// Link from an entry to the earlier entry it follows from
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LineageLink {
+    pub relation: LineageRelation,
+    /// `chain_hash` of the linked entry; unlike record hashes, it tells identical
+    /// retries apart
+    pub chain_hash: String,
+}
+
+/// One audit record of a decision lineage
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LineageEntry {
+    pub kind: LineageEntryKind,
+    pub timestamp: DateTime<Utc>,
+    /// Correlation id the record was written under
+    pub correlation_id: String,
+    pub record_hash: String,
+    pub chain_hash: String,
+    /// Final status of a decision, action of an appeal or exemption, or replayed status
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appeal_id: Option<String>,
+    /// Exemptions granted, archived, applied or granted by an appeal resolution
+    ///
+    /// Only with `full` redaction, since an exemption names the rule it waives.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exemption_ids: Vec<String>,
+    /// "current", "historical" or "regenerate", for replays
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<LineageLink>,
+}
+
+/// Response of `GET /api/decisions/{correlation_id}/lineage`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DecisionLineage {
+    pub correlation_id: String,
+    pub audience: ExplanationAudience,
+    pub redaction: ExplanationRedaction,
+    /// Oldest first
+    pub entries: Vec<LineageEntry>,
+}
+
+#[derive(Debug, Error)]
+pub enum LineageError {
+    #[error("no audit records found for correlation id {0}")]
+    NotFound(String),
+    #[error("failed to read the audit trail: {0}")]
+    Storage(#[from] AuditStorageError),
+}
+
+/// A record of the lineage, parsed by its `event_type`
+enum Related {
+    Decision(Box<AuditEvent>),
+    Appeal(AppealEvent),
+    Exemption(ExemptionEvent),
+    Replay(ReplayEvent),
+}
+
+impl ComplianceEngine {
+    /// Every audit record about `correlation_id`, oldest first and linked together
+    ///
+    /// Records belong to the lineage when they were written under the correlation id
+    /// or name it as their `related_correlation_id`. `audience` is redacted like a
+    /// decision explanation.
+    pub fn decision_lineage(
+        &self,
+        correlation_id: &str,
+        audience: ExplanationAudience,
+    ) -> Result<DecisionLineage, LineageError> {
+        let mut records: Vec<(StoredAuditRecord, Related)> = self
+            .audit_logger
+            .storage()
+            .all()?
+            .into_iter()
+            // Cheap test before parsing every record of the trail
+            .filter(|record| record.payload.contains(correlation_id))
+            .filter_map(|record| {
+                let related = related_record(&record.payload, correlation_id)?;
+                Some((record, related))
+            })
+            .collect();
+        if records.is_empty() {
+            return Err(LineageError::NotFound(correlation_id.to_owned()));
+        }
+        records.sort_by_key(|(record, _)| record.timestamp);
+
+        let mut entries = Vec::with_capacity(records.len());
+        let mut last_decision: Option<String> = None;
+        let mut appeals_filed = HashMap::new();
+        let mut exemptions_granted = HashMap::new();
+        for (record, related) in records {
+            let link = |relation, hash: Option<&String>| {
+                hash.map(|hash| LineageLink {
+                    relation,
+                    chain_hash: hash.clone(),
+                })
+            };
+            let mut entry = LineageEntry {
+                kind: LineageEntryKind::Decision,
+                timestamp: record.timestamp,
+                correlation_id: record.correlation_id,
+                record_hash: record.proof.record_hash,
+                chain_hash: record.proof.chain_hash,
+                status: String::new(),
+                appeal_id: None,
+                exemption_ids: Vec::new(),
+                replay_mode: None,
+                links: Vec::new(),
+            };
+            match related {
+                Related::Decision(event) if event.correlation_id == correlation_id => {
+                    if let Some(previous) = link(LineageRelation::Retries, last_decision.as_ref()) {
+                        entry.kind = LineageEntryKind::Retry;
+                        entry.links.push(previous);
+                    }
+                    entry.status = event.final_status;
+                    last_decision = Some(entry.chain_hash.clone());
+                }
+                Related::Decision(event) => {
+                    entry.kind = LineageEntryKind::ExemptedDecision;
+                    entry.status = event.final_status;
+                    for applied in event.applied_exemptions {
+                        if let Some(granted) = link(
+                            LineageRelation::AppliesExemption,
+                            exemptions_granted.get(&applied.exemption_id),
+                        ) {
+                            entry.links.push(granted);
+                            entry.exemption_ids.push(applied.exemption_id);
+                        }
+                    }
+                }
+                Related::Appeal(event) => {
+                    entry.appeal_id = Some(event.appeal.id.clone());
+                    entry.status = event.action;
+                    if entry.status == "filed" {
+                        entry.kind = LineageEntryKind::AppealFiled;
+                        entry
+                            .links
+                            .extend(link(LineageRelation::Appeals, last_decision.as_ref()));
+                        appeals_filed.insert(event.appeal.id, entry.chain_hash.clone());
+                    } else {
+                        entry.kind = LineageEntryKind::AppealResolved;
+                        entry.links.extend(link(
+                            LineageRelation::Resolves,
+                            appeals_filed.get(&event.appeal.id),
+                        ));
+                        for id in event
+                            .appeal
+                            .resolution
+                            .map(|resolution| resolution.exemption_ids)
+                            .unwrap_or_default()
+                        {
+                            entry
+                                .links
+                                .extend(link(LineageRelation::Grants, exemptions_granted.get(&id)));
+                            entry.exemption_ids.push(id);
+                        }
+                    }
+                }
+                Related::Exemption(event) => {
+                    entry.exemption_ids.push(event.exemption.id.clone());
+                    entry.status = event.action;
+                    if entry.status == "granted" {
+                        entry.kind = LineageEntryKind::ExemptionGranted;
+                        entry
+                            .links
+                            .extend(link(LineageRelation::Exempts, last_decision.as_ref()));
+                        exemptions_granted.insert(event.exemption.id, entry.chain_hash.clone());
+                    } else {
+                        entry.kind = LineageEntryKind::ExemptionArchived;
+                        entry.links.extend(link(
+                            LineageRelation::Archives,
+                            exemptions_granted.get(&event.exemption.id),
+                        ));
+                    }
+                }
+                Related::Replay(event) => {
+                    entry.kind = LineageEntryKind::Replay;
+                    entry.status = event.replayed_status;
+                    entry.replay_mode = Some(event.mode);
+                    entry
+                        .links
+                        .extend(link(LineageRelation::Replays, last_decision.as_ref()));
+                }
+            }
+            entries.push(entry);
+        }
+
+        let redaction = self.explanation.redaction(audience);
+        if redaction != ExplanationRedaction::Full {
+            for entry in &mut entries {
+                entry.exemption_ids.clear();
+            }
+        }
+        Ok(DecisionLineage {
+            correlation_id: correlation_id.to_owned(),
+            audience,
+            redaction,
+            entries,
+        })
+    }
+}
+
+/// `payload` parsed by its `event_type`, when it is a record of the lineage of
+/// `correlation_id`
+///
+/// Configuration changes, abandonments, suppressed repeats and document scans are not
+/// about a decision and are left out.
+fn related_record(payload: &str, correlation_id: &str) -> Option<Related> {
+    let value: Value = serde_json::from_str(payload).ok()?;
+    let names = |key: &str| value.get(key).and_then(Value::as_str) == Some(correlation_id);
+    if !names("correlation_id") && !names("related_correlation_id") {
+        return None;
+    }
+    let related = match value.get("event_type").and_then(Value::as_str) {
+        None => Related::Decision(serde_json::from_value(value).ok()?),
+        Some("appeal") => Related::Appeal(serde_json::from_value(value).ok()?),
+        Some("exemption") => Related::Exemption(serde_json::from_value(value).ok()?),
+        Some("replay") => Related::Replay(serde_json::from_value(value).ok()?),
+        Some(_) => return None,
+    };
+    Some(related)
+}
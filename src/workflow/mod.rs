@@ -1,43 +1,284 @@
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-use crate::modules::audit::logger::{AuditError, AuditEvent, AuditLogger};
-use crate::modules::audit::proof::AuditProof;
+use crate::firewall_core::taxonomy::ResolvedThreat;
+use crate::modules::alerting::service::WebhookNotifier;
+use crate::modules::appeals::dtos::AppealPolicy;
+use crate::modules::appeals::storage::{AppealStore, InMemoryAppealStore};
+use crate::modules::audit::logger::{
+    AuditError, AuditEvent, AuditLogger, ConfigChangeEvent, ConfigFingerprint, ExchangeValidation,
+    GenerationInputDigest, GenerationParameters,
+};
+use crate::modules::audit::proof::{AuditProof, content_hash, hash_record};
+use crate::modules::audit::storage::PromptStorageMode;
+use crate::modules::audit::suppression::AuditSuppressionPolicy;
 use crate::modules::bias_detection::dtos::{BiasScanRequest, BiasScanResult};
+use crate::modules::bias_detection::model::BiasLevel;
 use crate::modules::bias_detection::service::BiasDetectionService;
+use crate::modules::caches::service::CacheRegistry;
+use crate::modules::diagnostics::service::record_stage;
 use crate::modules::eu_law_compliance::model::{AiRiskTier, EuComplianceResult};
 use crate::modules::eu_law_compliance::service::EuLawComplianceService;
-use crate::modules::mistral_ai::dtos::ModerationResponse;
+use crate::modules::exemptions::dtos::AppliedExemption;
+use crate::modules::exemptions::service::ExemptionService;
+use crate::modules::mistral_ai::dtos::{
+    ChatMessage, DeterministicSampling, ModerationCategory, ModerationResponse,
+};
+use crate::modules::mistral_ai::sampling;
 use crate::modules::mistral_ai::service::{MistralService, MistralServiceError};
+use crate::modules::preprocessing::dtos::{AppliedTransform, PromptTransform};
+use crate::modules::preprocessing::service::PromptPreprocessor;
 use crate::modules::prompt_firewall::dtos::{
-    FirewallAction, PromptFirewallRequest, PromptFirewallResult,
+    ExtractedUrl, FirewallAction, FirewallMiss, FirewallMissSource, InputTruncation,
+    MatchedBlockRule, PromptFirewallResult,
 };
+use crate::modules::prompt_firewall::language_mix;
+use crate::modules::prompt_firewall::misses::FirewallMissLog;
+use crate::modules::prompt_firewall::rules::SANITIZE_LIMIT_RULE_ID;
 use crate::modules::prompt_firewall::service::PromptFirewallService;
+use crate::modules::repeat_offender::dtos::{RepeatMatch, RepeatOffenderConfig};
+use crate::modules::repeat_offender::service::{PromptFingerprint, RepeatOffenderService};
+use crate::modules::request_fingerprint::service::{prompt_fingerprint, request_fingerprint};
+use crate::modules::sanitize_probing::dtos::{ProbingEscalation, SanitizeProbingConfig};
+use crate::modules::sanitize_probing::service::SanitizeProbingService;
+use crate::modules::semantic_detection::candidates::{CandidateStore, InMemoryCandidateStore};
 use crate::modules::semantic_detection::dtos::{
-    SemanticRiskLevel, SemanticScanRequest, SemanticScanResult,
+    SamplingKey, SemanticRiskLevel, SemanticSamplingPolicy, SemanticScanRequest, SemanticScanResult,
 };
 use crate::modules::semantic_detection::service::{
     SemanticDetectionError, SemanticDetectionService,
 };
-use crate::modules::telemetry::correlation::generate_correlation_id_from_request;
-use crate::modules::telemetry::tracing::{create_span_with_correlation, log_with_correlation};
+use crate::modules::telemetry::correlation::{
+    CorrelationIdPolicy, generate_correlation_id_from_request,
+};
+use crate::modules::telemetry::metrics::get_metrics;
+use crate::modules::telemetry::tracing::{log_with_correlation, stage_span, workflow_span};
+
+mod appeals;
+mod cancellation;
+mod candidates;
+mod documents;
+mod exchange;
+mod explain;
+mod failure_policy;
+mod judge;
+mod lineage;
+mod options;
+mod output_analysis;
+mod output_chunking;
+mod policy;
+mod reasons;
+mod refusal;
+mod replay;
+mod risk;
+mod stage_outcome;
+mod stages;
+mod templates;
+mod threats;
+mod toggles;
+
+use cancellation::Abandonment;
+use reasons::DecisionReason;
+use stage_outcome::StageEvidence;
+use templates::{ResolvedTemplate, TemplateRegistry};
+
+pub use appeals::AppealError;
+pub use candidates::{CandidateError, parse_window};
+pub use documents::{
+    DocumentScanError, DocumentScanLimits, DocumentScanRequest, DocumentScanResponse,
+    DocumentScanResult, DocumentVerdict, ScannedDocument,
+};
+pub use exchange::{ExchangeValidationResponse, ExchangeVerdict, ValidateExchangeRequest};
+pub use explain::{
+    AppealPath, DecisionExplanation, ExplainError, ExplanationAudience, ExplanationPolicy,
+    ExplanationRedaction, MatchedPhrase,
+};
+pub use failure_policy::{FailureMode, PipelineStage, StageFailure, StageFailurePolicy};
+pub use judge::{JudgeEvidence, JudgePromptTemplate, JudgeTrigger, JudgeVerdict, LlmJudgeConfig};
+pub use lineage::{
+    DecisionLineage, LineageEntry, LineageEntryKind, LineageError, LineageLink, LineageRelation,
+};
+pub use options::{
+    ComplianceOptions, CorrelationIdOptions, FieldViolation, RequestOverride,
+    RequestValidationError,
+};
+pub use output_analysis::{OutputAnalysis, OutputAnalysisConfig, OutputAnalysisSummary};
+pub use output_chunking::{OutputModerationChunking, OutputModerationChunks};
+pub use policy::{
+    BlockStatus, Condition, DecisionPolicy, PolicyAction, PolicyDryRunRequest,
+    PolicyDryRunResponse, PolicyEvidence, PolicyField, PolicyRule, PolicyValue,
+};
+pub use reasons::{
+    DEFAULT_REASON_LOCALE, ReasonCode, ReasonParams, ReasonRenderer, register_reason_locale,
+    render_reason,
+};
+pub use refusal::{
+    RefusalKind, RefusalMessage, RefusalPolicy, RefusalSource, RefusalTemplateSet, RefusalTemplates,
+};
+pub use replay::{EvidenceChange, Regeneration, ReplayError, ReplayMode, ReplayReport};
+pub use risk::{DEFAULT_BLOCKED_FLOOR, RiskInputs, RiskWeights, firewall_signal, risk_score};
+pub use stage_outcome::{
+    GenerationOutcome, StageOutcome, StageOutcomes, StageStatus, StageStatuses,
+};
+pub use templates::{
+    PromptTemplate, ScaffoldScan, SlotScan, SlotVerdict, TemplateError, TemplateEvidence,
+    TemplateInvocation, TemplateRegistration, TemplateSlot, TemplatesResponse,
+};
+pub use threats::{ThreatCategoryCount, ThreatCategoryStats};
+pub use toggles::{
+    DISABLED_BY_ADMIN, StageState, StageToggleError, StageToggleRequest, StageToggles,
+    StageTogglesResponse, ToggleableStage,
+};
 
+use threats::LayerFindings;
+
+/// Version of the JSON the API speaks, sent in the `x-api-version` header
+///
+/// Version 2 serializes every enum in snake_case; version 1 used the PascalCase variant
+/// names, which requests and stored audit records may still carry.
+pub const API_VERSION: u32 = 2;
+
+/// Serialized in snake_case; the PascalCase names of API version 1 are still accepted
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkflowStatus {
+    #[serde(alias = "Completed")]
     Completed,
+    #[serde(alias = "BlockedByFirewall")]
     BlockedByFirewall,
+    #[serde(alias = "BlockedBySemantic")]
     BlockedBySemantic,
+    #[serde(alias = "BlockedByInputModeration")]
     BlockedByInputModeration,
+    #[serde(alias = "BlockedByOutputModeration")]
     BlockedByOutputModeration,
+    #[serde(alias = "BlockedByEuCompliance")]
     BlockedByEuCompliance,
+    /// A stage whose failure policy is `closed` could not complete
+    #[serde(alias = "BlockedByStageFailure")]
+    BlockedByStageFailure { stage: PipelineStage },
+    #[serde(alias = "Sanitized")]
     Sanitized,
 }
 
+impl WorkflowStatus {
+    /// The serialized name, without the stage of `BlockedByStageFailure`
+    ///
+    /// Audit records store it as `final_status`, so it always matches the `status` of
+    /// the response.
+    pub fn name(&self) -> String {
+        serialized_name(self)
+    }
+}
+
+/// The name an enum variant serializes as; for a variant with fields, the key it is
+/// tagged with
+pub(crate) fn serialized_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(serde_json::Value::Object(variant)) => variant
+            .into_iter()
+            .next()
+            .map(|(name, _)| name)
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "ComplianceRequestBody")]
 pub struct ComplianceRequest {
     pub correlation_id: Option<String>,
+    /// Empty when the request fills in a template instead
     pub prompt: String,
+    /// Ask for a debiased rephrasing of a biased prompt, returned as
+    /// `bias.suggested_rewrite`
+    pub suggest_rewrite: bool,
+    /// A registered template and its slot values, sent as top-level `template_id` and
+    /// `variables` in place of `prompt`
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateInvocation>,
+    /// Generate with temperature 0 and a fixed seed so the answer can be reproduced;
+    /// `None` follows the `DETERMINISTIC_GENERATION` setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deterministic: Option<bool>,
+    /// Caller's conversation or client session; requests sharing one are checked together
+    /// for probing of the sanitizer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// A compliance request as sent, which needs a prompt, a template or both
+#[derive(Deserialize)]
+struct ComplianceRequestBody {
+    correlation_id: Option<String>,
+    prompt: Option<String>,
+    #[serde(default)]
+    suggest_rewrite: bool,
+    #[serde(default, flatten)]
+    template: Option<TemplateInvocation>,
+    #[serde(default)]
+    deterministic: Option<bool>,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+impl TryFrom<ComplianceRequestBody> for ComplianceRequest {
+    type Error = &'static str;
+
+    fn try_from(body: ComplianceRequestBody) -> Result<Self, Self::Error> {
+        // Sending both is left to validation, which reports it as a field violation
+        if body.prompt.is_none() && body.template.is_none() {
+            return Err("missing field `prompt` (or `template_id` with `variables`)");
+        }
+        Ok(Self {
+            correlation_id: body.correlation_id,
+            prompt: body.prompt.unwrap_or_default(),
+            suggest_rewrite: body.suggest_rewrite,
+            template: body.template,
+            deterministic: body.deterministic,
+            session_id: body.session_id,
+        })
+    }
+}
+
+impl ComplianceRequest {
+    /// The options that change how the prompt is answered, defaults left out
+    ///
+    /// Correlation and session ids name the request rather than change it, so they are
+    /// not among them.
+    pub fn fingerprint_overrides(&self) -> BTreeMap<String, serde_json::Value> {
+        let mut overrides = BTreeMap::new();
+        if self.suggest_rewrite {
+            overrides.insert("suggest_rewrite".to_owned(), true.into());
+        }
+        if let Some(deterministic) = self.deterministic {
+            overrides.insert("deterministic".to_owned(), deterministic.into());
+        }
+        if let Some(template) = &self.template {
+            overrides.insert(
+                "template_id".to_owned(),
+                template.template_id.clone().into(),
+            );
+            overrides.insert(
+                "variables".to_owned(),
+                serde_json::to_value(&template.variables).unwrap_or_default(),
+            );
+        }
+        overrides
+    }
+
+    /// Full-request fingerprint of the prompt and its overrides, see
+    /// [`request_fingerprint`]
+    pub fn fingerprint(&self, tenant: Option<&str>) -> String {
+        request_fingerprint(&self.prompt, &self.fingerprint_overrides(), tenant)
+    }
 }
 
 /// Evidence explaining how the final decision was made
@@ -55,29 +296,244 @@ pub struct DecisionEvidence {
     pub semantic_category: Option<String>,
     /// Whether moderation flagged the input
     pub moderation_flagged: bool,
-    /// Categories flagged by moderation
-    pub moderation_categories: Vec<String>,
+    /// Categories flagged by moderation, each with its severity and raw score
+    pub moderation_categories: Vec<ModerationCategory>,
+    /// Which moderation pass flagged the request: "sanitized" or "removed_content" for
+    /// input, "english" or "translated" for output
+    #[serde(default)]
+    pub moderation_scope: Option<String>,
     /// Final decision
     pub final_decision: String,
-    /// Human-readable explanation
+    /// Human-readable explanation, rendered in English from `final_reason_code`
     pub final_reason: String,
+    /// Stable machine-readable cause of the decision
+    #[serde(default)]
+    pub final_reason_code: ReasonCode,
+    /// Structured values behind `final_reason` (rule ids, scores, categories)
+    #[serde(default)]
+    pub reason_params: ReasonParams,
+    /// Index into the decision trace of the step that determined the decision
+    #[serde(default)]
+    pub decisive_step: Option<usize>,
+    /// Id of the decision policy rule that decided the request; `None` when no rule
+    /// matched or a failed stage blocked it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_rule: Option<String>,
+    /// Earlier blocked request this prompt closely resembled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similar_blocked_correlation_id: Option<String>,
+    /// Versions of the rule sets the decision was made with (only in the `full` profile)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_fingerprint: Option<ConfigFingerprint>,
+    /// `v1:` fingerprint of the prompt and its overrides, for matching a request to
+    /// support tickets and logs (only in the `full` profile)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_fingerprint: Option<String>,
+    /// Preprocessing transforms run before the firewall, and whether each changed the prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preprocessing: Vec<AppliedTransform>,
+    /// Why the semantic scan did not run ("sampled_out", "disabled_by_admin")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic_skipped_reason: Option<String>,
+    /// Why input and output moderation did not run ("not_configured"); skipped
+    /// moderation is not a pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation_skipped_reason: Option<String>,
+    /// Stages an administrator had paused, skipped rather than failed or sampled out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_stages: Vec<ToggleableStage>,
+    /// How a generated answer too long for one moderation call was split, and which
+    /// chunks were flagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_moderation_chunks: Option<OutputModerationChunks>,
+    /// Exemptions that suppressed firewall rules for this request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_exemptions: Vec<AppliedExemption>,
+    /// Stages that failed, with the policy applied: `open` carried on without them,
+    /// `closed` blocked the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub degraded_stages: Vec<StageFailure>,
+    /// Composite risk score (0-100), see [`RiskWeights`]
+    #[serde(default)]
+    pub risk_score: u8,
+    /// Signals the risk score was computed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk_inputs: Option<RiskInputs>,
+    /// The prompt switched between languages, so the firewall kept the stricter of its
+    /// results for the prompt as written and translated
+    #[serde(default)]
+    pub language_mix_detected: bool,
+    /// Languages found in a mixed-language prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mixed_languages: Vec<String>,
+    /// Threat category of the decision in the shared taxonomy, whichever layer found it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_category: Option<ResolvedThreat>,
+    /// The template the request filled in, with per-slot firewall verdicts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateEvidence>,
+    /// How a prompt over the input limit was cut down before the firewall ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_truncation: Option<InputTruncation>,
+    /// The second opinion of the judge model, when the prompt was in the gray zone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_judge: Option<JudgeEvidence>,
+    /// Block rules in `monitor` mode the prompt matched; kept apart from
+    /// `firewall_matched_rules` because they never change the decision
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_firewall_matches: Vec<MatchedBlockRule>,
+}
+
+/// One stage of the decision trace
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TraceStep {
+    /// Pipeline stage (e.g. "firewall", "semantic", "output_moderation")
+    pub stage: String,
+    /// Inputs considered, as content hashes rather than raw text
+    pub inputs: Vec<String>,
+    /// Stage verdict: "allow", "sanitize", "flag", "block" or "skip"
+    pub verdict: String,
+    /// Machine-readable references (rule ids, template ids, categories) behind the verdict
+    pub rule_refs: Vec<String>,
+    /// Thresholds and configuration values in effect for the stage
+    pub parameters: BTreeMap<String, f64>,
+    /// Time spent in the stage
+    pub duration_ms: u64,
+}
+
+/// How much detail a compliance response carries
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseProfile {
+    /// Decision, stage results and flat evidence
+    #[default]
+    Standard,
+    /// Everything in `Standard` plus the full decision trace and config fingerprint
+    Full,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct ComplianceResponse {
     pub correlation_id: String,
     pub status: WorkflowStatus,
+    /// Composite risk score from 0 (clean) to 100; blocked requests score at least the
+    /// configured floor (80 by default) and requests that went through score below it
+    #[serde(default)]
+    pub risk_score: u8,
     pub firewall: PromptFirewallResult,
+    /// What happened to each stage, including why a result is missing
+    #[serde(default)]
+    pub stages: StageOutcomes,
+    /// Deprecated view of `stages.semantic`: its result when the scan ran
     pub semantic: Option<SemanticScanResult>,
+    /// Deprecated view of `stages.bias`; also set when the stage failed and fell back
     pub bias: BiasScanResult,
+    /// Deprecated view of `stages.input_moderation`: its result when moderation ran
     pub input_moderation: Option<ModerationResponse>,
+    /// Deprecated view of `stages.output_moderation`: its result when moderation ran
     pub output_moderation: Option<ModerationResponse>,
+    /// Moderation of the translated output, when it differed from the English output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translated_output_moderation: Option<ModerationResponse>,
+    /// Every check run on the generated answer; absent when nothing was generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_analysis: Option<OutputAnalysis>,
     pub generated_text: Option<String>,
+    /// What to show the user in place of an answer when the request was blocked and
+    /// refusal messages are on; never model output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal_message: Option<RefusalMessage>,
     pub audit_proof: AuditProof,
     /// Evidence explaining the decision
     pub decision_evidence: Option<DecisionEvidence>,
     /// EU AI Act compliance result
     pub eu_compliance: Option<EuComplianceResult>,
+    /// Step-by-step explanation of the decision (only in the `full` profile)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decision_trace: Vec<TraceStep>,
+    /// Answered by a public demo, whose `generated_text` is canned
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub demo_mode: bool,
+}
+
+impl ComplianceResponse {
+    /// Drop the fields that are not part of the requested profile
+    pub fn with_profile(mut self, profile: ResponseProfile) -> Self {
+        if profile == ResponseProfile::Standard {
+            self.decision_trace.clear();
+            if let Some(evidence) = &mut self.decision_evidence {
+                evidence.config_fingerprint = None;
+                evidence.request_fingerprint = None;
+            }
+        }
+        self
+    }
+}
+
+/// Tunable behaviour of the compliance workflow
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WorkflowPolicy {
+    /// When sanitization removed content, also run input moderation on the
+    /// removed fragments and block if they are flagged
+    #[serde(default)]
+    pub moderate_removed_content: bool,
+    /// When the output was translated, also moderate the translation and block if it
+    /// is flagged; skipped when the translation equals the English output
+    #[serde(default = "default_moderate_translated_output")]
+    pub moderate_translated_output: bool,
+    /// What a request that passed every other check gets when no moderation model is
+    /// configured
+    #[serde(default)]
+    pub unmoderated: UnmoderatedPolicy,
+}
+
+impl Default for WorkflowPolicy {
+    fn default() -> Self {
+        Self {
+            moderate_removed_content: false,
+            moderate_translated_output: default_moderate_translated_output(),
+            unmoderated: UnmoderatedPolicy::default(),
+        }
+    }
+}
+
+fn default_moderate_translated_output() -> bool {
+    true
+}
+
+/// Outcome of a request that could not be moderated because moderation is disabled
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmoderatedPolicy {
+    /// Answer `Completed` as if moderation had passed
+    Allow,
+    /// Answer `Sanitized`, so callers handle the output with the same caution as other
+    /// partially trusted answers
+    #[default]
+    Sanitize,
+}
+
+impl UnmoderatedPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Sanitize => "sanitize",
+        }
+    }
+}
+
+impl std::str::FromStr for UnmoderatedPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "sanitize" => Ok(Self::Sanitize),
+            other => Err(format!(
+                "unknown unmoderated request policy '{other}' (expected allow or sanitize)"
+            )),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -88,9 +544,50 @@ pub struct ComplianceEngine {
     mistral_service: MistralService,
     audit_logger: AuditLogger,
     eu_compliance_service: EuLawComplianceService,
+    repeat_offenders: RepeatOffenderService,
+    sanitize_probing: SanitizeProbingService,
+    document_limits: DocumentScanLimits,
+    preprocessor: PromptPreprocessor,
+    semantic_sampling: SemanticSamplingPolicy,
+    /// Keys the sampling choice; random per process unless a secret is configured
+    semantic_sampling_key: SamplingKey,
+    exemptions: ExemptionService,
+    prompt_storage: PromptStorageMode,
+    generation_preamble: Option<String>,
+    /// Answer with canned responses instead of generating
+    demo_mode: bool,
+    /// Whether a request that does not say is deterministic
+    deterministic_by_default: bool,
+    deterministic_seed: u64,
+    stage_failures: StageFailurePolicy,
+    output_chunking: OutputModerationChunking,
+    output_analysis: OutputAnalysisConfig,
+    explanation: ExplanationPolicy,
+    attack_candidates: Arc<dyn CandidateStore>,
+    appeals: Arc<dyn AppealStore>,
+    appeal_policy: AppealPolicy,
+    /// Held across the check and write of an appeal transition, so an appeal is never
+    /// filed twice or resolved twice
+    appeal_transitions: Arc<Mutex<()>>,
+    correlation_ids: CorrelationIdPolicy,
+    risk_weights: RiskWeights,
+    decision_policy: Arc<DecisionPolicy>,
+    refusals: Arc<RefusalPolicy>,
+    /// Refusals already translated, by language and English text, so each is only
+    /// translated once and always reads the same
+    refusal_translations: Arc<RwLock<HashMap<(String, String), String>>>,
+    llm_judge: Arc<LlmJudgeConfig>,
+    firewall_misses: FirewallMissLog,
+    policy: Arc<RwLock<WorkflowPolicy>>,
+    stage_toggles: Arc<RwLock<StageToggles>>,
+    templates: Arc<RwLock<TemplateRegistry>>,
 }
 
 impl ComplianceEngine {
+    /// Assemble an engine from every stage the workflow runs
+    ///
+    /// Each stage is a required argument, so an engine cannot be built without one;
+    /// the `with_*` methods only tune stages that are already present.
     pub fn new(
         firewall_service: PromptFirewallService,
         semantic_service: SemanticDetectionService,
@@ -98,14 +595,292 @@ impl ComplianceEngine {
         mistral_service: MistralService,
         audit_logger: AuditLogger,
     ) -> Self {
+        let repeat_offenders =
+            RepeatOffenderService::new(mistral_service.clone(), RepeatOffenderConfig::default());
+        let exemptions = ExemptionService::new(audit_logger.clone());
         Self {
             firewall_service,
             semantic_service,
             bias_service,
             mistral_service,
             audit_logger,
-            eu_compliance_service: EuLawComplianceService::default(),
+            eu_compliance_service: EuLawComplianceService,
+            repeat_offenders,
+            sanitize_probing: SanitizeProbingService::new(SanitizeProbingConfig::default()),
+            document_limits: DocumentScanLimits::default(),
+            preprocessor: PromptPreprocessor::default(),
+            semantic_sampling: SemanticSamplingPolicy::default(),
+            semantic_sampling_key: SamplingKey::random(),
+            exemptions,
+            prompt_storage: PromptStorageMode::default(),
+            generation_preamble: None,
+            demo_mode: false,
+            deterministic_by_default: false,
+            deterministic_seed: 0,
+            stage_failures: StageFailurePolicy::default(),
+            output_chunking: OutputModerationChunking::default(),
+            output_analysis: OutputAnalysisConfig::default(),
+            explanation: ExplanationPolicy::default(),
+            attack_candidates: Arc::new(InMemoryCandidateStore::new()),
+            appeals: Arc::new(InMemoryAppealStore::new()),
+            appeal_policy: AppealPolicy::default(),
+            appeal_transitions: Arc::default(),
+            correlation_ids: CorrelationIdPolicy::default(),
+            risk_weights: RiskWeights::default(),
+            decision_policy: Arc::new(DecisionPolicy::default()),
+            refusals: Arc::new(RefusalPolicy::default()),
+            refusal_translations: Arc::default(),
+            llm_judge: Arc::new(LlmJudgeConfig::default()),
+            firewall_misses: FirewallMissLog::default(),
+            policy: Arc::new(RwLock::new(WorkflowPolicy::default())),
+            stage_toggles: Arc::new(RwLock::new(StageToggles::default())),
+            templates: Arc::new(RwLock::new(TemplateRegistry::default())),
+        }
+    }
+
+    /// Replace the workflow policy
+    pub fn with_policy(mut self, policy: WorkflowPolicy) -> Self {
+        self.policy = Arc::new(RwLock::new(policy));
+        self
+    }
+
+    /// Escalate prompts that closely resemble recently blocked ones
+    pub fn with_repeat_offender_config(mut self, config: RepeatOffenderConfig) -> Self {
+        self.repeat_offenders = RepeatOffenderService::new(self.mistral_service.clone(), config);
+        self
+    }
+
+    /// Block sessions that keep resending content sanitization removes, posting each
+    /// escalation to `notifier`
+    pub fn with_sanitize_probing(
+        mut self,
+        config: SanitizeProbingConfig,
+        notifier: Option<WebhookNotifier>,
+    ) -> Self {
+        self.sanitize_probing = SanitizeProbingService::new(config).with_notifier(notifier);
+        self
+    }
+
+    /// Bound the size of document scan requests
+    pub fn with_document_scan_limits(mut self, limits: DocumentScanLimits) -> Self {
+        self.document_limits = limits;
+        self
+    }
+
+    /// Rewrite prompts with these transforms, in order, before the firewall sees them
+    pub fn with_preprocessors(mut self, transforms: Vec<PromptTransform>) -> Self {
+        self.preprocessor = PromptPreprocessor::new(transforms);
+        self
+    }
+
+    /// Scan only a fraction of low-risk prompts semantically
+    pub fn with_semantic_sampling(mut self, policy: SemanticSamplingPolicy) -> Self {
+        self.semantic_sampling = policy;
+        self
+    }
+
+    /// Key the sampling choice with `secret`, so every instance given it samples the
+    /// same requests
+    pub fn with_semantic_sampling_secret(mut self, secret: &str) -> Self {
+        self.semantic_sampling_key = SamplingKey::from_secret(secret);
+        self
+    }
+
+    /// Choose whether audit records keep prompt text or only its hash
+    ///
+    /// Demo mode always keeps the hash.
+    pub fn with_prompt_storage(mut self, mode: PromptStorageMode) -> Self {
+        if !self.demo_mode {
+            self.prompt_storage = mode;
+        }
+        self
+    }
+
+    /// Collapse repeated synthetic decisions in the audit trail as `policy` says
+    pub fn with_audit_suppression(mut self, policy: AuditSuppressionPolicy) -> Self {
+        self.audit_logger = self.audit_logger.with_suppression(policy);
+        self
+    }
+
+    /// Run as a public demo: every check runs, but the answer is a canned response
+    /// chosen by the outcome, and audit records keep prompt hashes only
+    pub fn with_demo_mode(mut self, enabled: bool) -> Self {
+        self.demo_mode = enabled;
+        if enabled {
+            self.prompt_storage = PromptStorageMode::Redacted;
+        }
+        self
+    }
+
+    pub fn is_demo(&self) -> bool {
+        self.demo_mode
+    }
+
+    /// Send `preamble` as a system message ahead of every prompt at generation
+    pub fn with_generation_preamble(mut self, preamble: Option<String>) -> Self {
+        self.generation_preamble = preamble;
+        self
+    }
+
+    /// Make requests that do not set `deterministic` deterministic when `by_default` is
+    /// set, and send `random_seed` with every deterministic request
+    pub fn with_deterministic_generation(mut self, by_default: bool, random_seed: u64) -> Self {
+        self.deterministic_by_default = by_default;
+        self.deterministic_seed = random_seed;
+        self
+    }
+
+    /// Choose per stage whether a Mistral failure blocks the request or is tolerated
+    pub fn with_stage_failure_policy(mut self, policy: StageFailurePolicy) -> Self {
+        self.stage_failures = policy;
+        self
+    }
+
+    /// Choose which checks besides moderation run on generated answers
+    pub fn with_output_analysis(mut self, config: OutputAnalysisConfig) -> Self {
+        self.output_analysis = config;
+        self
+    }
+
+    /// Moderate generated answers longer than the policy's chunk length in chunks
+    pub fn with_output_moderation_chunking(mut self, policy: OutputModerationChunking) -> Self {
+        self.output_chunking = policy;
+        self
+    }
+
+    /// Redact decision explanations per audience and point users at this feedback path
+    pub fn with_explanation_policy(mut self, policy: ExplanationPolicy) -> Self {
+        self.explanation = policy;
+        self
+    }
+
+    /// Keep attack template candidates awaiting review in this store
+    pub fn with_attack_candidate_store(mut self, store: Arc<dyn CandidateStore>) -> Self {
+        self.attack_candidates = store;
+        self
+    }
+
+    /// Keep appeals against blocked decisions in this store
+    pub fn with_appeal_store(mut self, store: Arc<dyn AppealStore>) -> Self {
+        self.appeals = store;
+        self
+    }
+
+    /// Scope of the firewall exemption an overturned appeal grants
+    pub fn with_appeal_policy(mut self, policy: AppealPolicy) -> Self {
+        self.appeal_policy = policy;
+        self
+    }
+
+    /// Replace caller-supplied correlation ids this policy rejects with generated ones
+    pub fn with_correlation_id_policy(mut self, policy: CorrelationIdPolicy) -> Self {
+        self.correlation_ids = policy;
+        self
+    }
+
+    /// Weigh the signals behind the composite risk score with these weights
+    pub fn with_risk_weights(mut self, weights: RiskWeights) -> Self {
+        self.risk_weights = weights;
+        self
+    }
+
+    /// Decide requests with these rules instead of the built-in precedence
+    pub fn with_decision_policy(mut self, policy: DecisionPolicy) -> Self {
+        self.decision_policy = Arc::new(policy);
+        self
+    }
+
+    /// Whether blocked requests get a refusal message by default, and its templates
+    pub fn with_refusals(mut self, policy: RefusalPolicy) -> Self {
+        self.refusals = Arc::new(policy);
+        self
+    }
+
+    /// When the generation model is asked for a second opinion on gray-zone prompts
+    pub fn with_llm_judge(mut self, config: LlmJudgeConfig) -> Self {
+        self.llm_judge = Arc::new(config);
+        self
+    }
+
+    /// Rules that turn stage evidence into a decision
+    /// Keep the `capacity` most recent firewall misses
+    pub fn with_firewall_miss_capacity(mut self, capacity: usize) -> Self {
+        self.firewall_misses = FirewallMissLog::new(capacity);
+        self
+    }
+
+    /// Prompts the firewall let through that later stages blocked, and the reverse
+    pub fn firewall_misses(&self) -> &FirewallMissLog {
+        &self.firewall_misses
+    }
+
+    pub fn decision_policy(&self) -> &DecisionPolicy {
+        &self.decision_policy
+    }
+
+    /// Get the active workflow policy
+    pub fn policy(&self) -> WorkflowPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    pub(crate) fn policy_lock(&self) -> &Arc<RwLock<WorkflowPolicy>> {
+        &self.policy
+    }
+
+    /// Whether each stage an administrator can pause is running, and its last change
+    pub fn stage_toggles(&self) -> StageTogglesResponse {
+        StageTogglesResponse {
+            stages: self.stage_toggles.read().unwrap().states(),
+        }
+    }
+
+    /// Pause or resume a stage, auditing the change before it takes effect
+    ///
+    /// Requests already past the stage are unaffected; each request sees the toggles as
+    /// they were when it started.
+    pub fn toggle_stage(
+        &self,
+        correlation_id: &str,
+        request: StageToggleRequest,
+    ) -> Result<StageState, StageToggleError> {
+        request.validate().map_err(StageToggleError::Invalid)?;
+        let mut toggles = self.stage_toggles.write().unwrap();
+        let previous_hash = content_hash(&toggles.states());
+        let mut next = toggles.clone();
+        let state = next.apply(correlation_id, request);
+        self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: if state.enabled {
+                "stage_enabled"
+            } else {
+                "stage_disabled"
+            }
+            .to_owned(),
+            previous_hash,
+            new_hash: content_hash(&next.states()),
+            snapshot_version: None,
+            detail: Some(format!(
+                "{} by {}: {}",
+                state.stage,
+                state.changed_by.as_deref().unwrap_or("unknown"),
+                state.reason.as_deref().unwrap_or_default()
+            )),
+        })?;
+        let message = format!(
+            "Stage {} {} by {}: {}",
+            state.stage,
+            if state.enabled { "enabled" } else { "disabled" },
+            state.changed_by.as_deref().unwrap_or("unknown"),
+            state.reason.as_deref().unwrap_or_default()
+        );
+        if state.enabled {
+            tracing::info!("{}", message);
+        } else {
+            tracing::warn!("{}", message);
         }
+        *toggles = next;
+        Ok(state)
     }
 
     /// Initialize the semantic detection service (call at startup)
@@ -123,559 +898,1140 @@ impl ComplianceEngine {
         &self.audit_logger
     }
 
-    /// Detect the language of the original prompt
-    async fn detect_original_language(&self, prompt: &str) -> String {
-        // Default to English if detection fails
-        let Ok(lang_detection) = self.mistral_service.detect_language(prompt.to_owned()).await
-        else {
-            return "English".to_string();
-        };
-        
-        lang_detection.language
+    /// Get a reference to the prompt firewall
+    pub fn firewall_service(&self) -> &PromptFirewallService {
+        &self.firewall_service
     }
 
-    /// Translate text back to the original language
-    async fn translate_to_original_language(&self, text: &str, target_language: &str) -> String {
-        // If translation fails, return original English text
-        let Ok(translation) = self.mistral_service
-            .translate_text(text.to_owned(), target_language.to_owned())
-            .await
-        else {
-            return text.to_owned();
+    /// Get a reference to the firewall exemptions
+    pub fn exemptions(&self) -> &ExemptionService {
+        &self.exemptions
+    }
+
+    /// Put the in-memory caches and trackers of the engine under `registry`'s budget
+    pub fn register_caches(&self, registry: &CacheRegistry) {
+        registry.register(
+            "semantic_embeddings",
+            Arc::new(self.semantic_service.embedding_cache().clone()),
+        );
+        registry.register("repeat_offenders", Arc::new(self.repeat_offenders.clone()));
+        registry.register(
+            "sanitize_probing_sessions",
+            Arc::new(self.sanitize_probing.clone()),
+        );
+    }
+
+    /// Get a reference to the semantic detection service
+    pub fn semantic_service(&self) -> &SemanticDetectionService {
+        &self.semantic_service
+    }
+
+    /// Get a reference to the bias detection service
+    pub fn bias_service(&self) -> &BiasDetectionService {
+        &self.bias_service
+    }
+
+    /// Content hashes of the rule sets and policies currently in effect
+    pub fn config_fingerprint(&self) -> ConfigFingerprint {
+        // Restores swap the firewall rules and the policy under one set of write locks, so
+        // holding the firewall lock while reading the policy sees both from the same side
+        let firewall = self.firewall_service.runtime().read().unwrap();
+        let policy = self.policy.read().unwrap();
+        ConfigFingerprint {
+            firewall_rules: firewall.rules.fingerprint().to_owned(),
+            bias_rules: self.bias_service.rules_fingerprint(),
+            attack_bank: self.semantic_service.bank_fingerprint(),
+            moderation_policy: content_hash(&serde_json::json!({
+                "model": self.mistral_service.moderation_model(),
+                "policy": &*policy,
+            })),
+        }
+    }
+
+    /// Whether the prompt will be rejected before any Mistral call is needed
+    ///
+    /// Only the deterministic checks are consulted, so a `false` here does not mean the
+    /// prompt will pass, just that answering it depends on Mistral.
+    pub fn can_serve_locally(&self, prompt: &str) -> bool {
+        let prompt = &self.preprocessor.apply(prompt).text;
+        if matches!(
+            self.eu_compliance_service.check_prompt(prompt).risk_tier,
+            AiRiskTier::Unacceptable
+        ) {
+            return true;
+        }
+        let firewall = self.firewall_service.inspect_local(prompt);
+        // An exemption may lift the block, and then the request does need Mistral
+        firewall.action == FirewallAction::Block
+            && !self.exemptions.covers_any(&firewall.matched_rules, prompt)
+    }
+
+    /// Whether the sampling policy lets this run skip the semantic scan
+    ///
+    /// Only prompts the firewall allowed untouched, in English, short enough and not
+    /// resembling an earlier blocked prompt are eligible.
+    fn semantic_sampled_out(&self, run: &WorkflowRun) -> bool {
+        let policy = self.semantic_sampling;
+        if !policy.is_enabled() {
+            return false;
+        }
+        let eligible = run.firewall.action == FirewallAction::Allow
+            && run.firewall.matched_rules.is_empty()
+            && run.repeat_match.is_none()
+            && run.is_english()
+            && run.firewall.sanitized_prompt.chars().count() <= policy.max_prompt_chars;
+        if !eligible {
+            return false;
+        }
+        let sampled_in = policy.samples_in(&self.semantic_sampling_key, &run.correlation_id);
+        get_metrics().increment_semantic_sampling(if sampled_in {
+            "sampled_in"
+        } else {
+            "sampled_out"
+        });
+        !sampled_in
+    }
+
+    /// English rendering of `prompt` shared by the firewall, bias and semantic stages
+    ///
+    /// Made at most once per request, and only for a prompt that is not English or that
+    /// mixes languages, since a prompt detected as English can still hide a foreign span.
+    /// `None` when no translation is needed or it failed; the stages then read the prompt
+    /// as written.
+    async fn translate_prompt(
+        &self,
+        correlation_id: &str,
+        prompt: &str,
+        is_english: bool,
+    ) -> Option<String> {
+        if is_english && language_mix::mixed_languages(prompt).is_empty() {
+            return None;
+        }
+        match self.mistral_service.translate_text(prompt, "English").await {
+            Ok(translation) => Some(translation.translated_text),
+            Err(e) => {
+                log_with_correlation(
+                    correlation_id,
+                    tracing::Level::WARN,
+                    &format!("Prompt translation failed, using the original text: {e}"),
+                );
+                None
+            }
+        }
+    }
+
+    /// Moderate the prompt and, when given, the fragments sanitization removed from it
+    ///
+    /// With fragments, everything goes in one batch call and the fragment results are
+    /// merged into a single response.
+    async fn moderate_input(
+        &self,
+        prompt: &str,
+        removed_fragments: &[String],
+    ) -> Result<(ModerationResponse, Option<ModerationResponse>), MistralServiceError> {
+        if removed_fragments.is_empty() {
+            let moderation = self.mistral_service.moderate_text(prompt).await?;
+            return Ok((moderation, None));
+        }
+        let inputs = std::iter::once(prompt.to_owned())
+            .chain(removed_fragments.iter().cloned())
+            .collect();
+        let mut responses = self.mistral_service.moderate_batch(inputs).await?;
+        let removed = responses.split_off(1);
+        let prompt_moderation = responses.remove(0);
+        Ok((prompt_moderation, Some(merge_moderation(removed))))
+    }
+
+    /// Block if the first decision policy rule matching the evidence so far blocks
+    fn policy_block(&self, run: &mut WorkflowRun) -> Option<Verdict> {
+        let rule = self
+            .decision_policy
+            .blocking_rule(&run.policy_evidence)?
+            .clone();
+        // Log lines are sampled by their text, so each cause keeps its own wording
+        let message = match rule.reason_code {
+            ReasonCode::EuProhibitedPractice => format!(
+                "Prompt blocked by EU AI Act compliance: {:?}",
+                run.eu_compliance.risk_tier
+            ),
+            ReasonCode::FirewallRuleMatch | ReasonCode::ExcessiveSanitization => {
+                "Prompt blocked by firewall".to_owned()
+            }
+            ReasonCode::RepeatOfBlockedPrompt => {
+                "Prompt blocked as a repeat of a recently blocked prompt".to_owned()
+            }
+            ReasonCode::SanitizerProbing => "Prompt blocked for probing the sanitizer".to_owned(),
+            ReasonCode::SemanticSimilarity => "Prompt blocked by semantic detection".to_owned(),
+            ReasonCode::InputModerationFlag => "Input flagged by moderation".to_owned(),
+            ReasonCode::RemovedContentModerationFlag => {
+                "Sanitized-away content flagged by moderation".to_owned()
+            }
+            ReasonCode::OutputModerationFlag
+                if rule.last_field() == Some(PolicyField::TranslatedOutputModerationFlagged) =>
+            {
+                "Translated output flagged by moderation".to_owned()
+            }
+            ReasonCode::OutputModerationFlag => "Output flagged by moderation".to_owned(),
+            ReasonCode::OutputFirewallEcho => "Output repeats a firewall block rule".to_owned(),
+            _ => format!("Prompt blocked by policy rule {}", rule.id),
         };
-        
-        translation.translated_text
+        log_with_correlation(&run.correlation_id, tracing::Level::WARN, &message);
+        Some(policy_verdict(run, &rule, None))
+    }
+
+    /// Record a failed stage and return the policy that applies to it
+    fn stage_failed(
+        &self,
+        correlation_id: &str,
+        failures: &mut Vec<StageFailure>,
+        stage: PipelineStage,
+        error: &dyn std::fmt::Display,
+    ) -> FailureMode {
+        let policy = self.stage_failures.mode(stage);
+        log_with_correlation(
+            correlation_id,
+            tracing::Level::WARN,
+            &format!(
+                "{} stage failed (policy {}): {}",
+                stage,
+                policy.as_str(),
+                error
+            ),
+        );
+        get_metrics().increment_stage_failures(stage.as_str(), policy.as_str());
+        failures.push(StageFailure {
+            stage,
+            policy,
+            error: error.to_string(),
+        });
+        policy
     }
 
     pub async fn process(
         &self,
         request: ComplianceRequest,
     ) -> Result<ComplianceResponse, WorkflowError> {
+        self.run(request, None, RunKind::Live, None, CancellationToken::new())
+            .await
+    }
+
+    /// [`process`](Self::process), with a refusal message for a blocked request when
+    /// `refusal` says so; `None` follows the `REFUSAL_MESSAGES` setting
+    pub async fn process_with_refusal(
+        &self,
+        request: ComplianceRequest,
+        refusal: Option<bool>,
+    ) -> Result<ComplianceResponse, WorkflowError> {
+        self.run(
+            request,
+            None,
+            RunKind::Live,
+            refusal,
+            CancellationToken::new(),
+        )
+        .await
+    }
+
+    /// [`process_with_refusal`](Self::process_with_refusal), stopped by `cancel`
+    ///
+    /// The run checks the token between stages and drops an in-flight generation when it
+    /// fires, returning [`WorkflowError::Cancelled`]. A cancelled run, or one whose future
+    /// is dropped before it answers, is counted in `abandoned_requests_total`; once the
+    /// input checks have passed it is also audited as `abandoned_by_client`.
+    pub async fn process_cancellable(
+        &self,
+        request: ComplianceRequest,
+        refusal: Option<bool>,
+        cancel: CancellationToken,
+    ) -> Result<ComplianceResponse, WorkflowError> {
+        self.run(request, None, RunKind::Live, refusal, cancel)
+            .await
+    }
+
+    /// Run a canned probe through the pipeline
+    ///
+    /// The audit record is marked as a self-test, or skipped entirely when `record_audit` is
+    /// false, and the prompt is never remembered as a repeat offender.
+    pub async fn process_self_test(
+        &self,
+        request: ComplianceRequest,
+        record_audit: bool,
+    ) -> Result<ComplianceResponse, WorkflowError> {
+        self.run(
+            request,
+            None,
+            RunKind::SelfTest { record_audit },
+            None,
+            CancellationToken::new(),
+        )
+        .await
+    }
+
+    /// Run the pipeline, checking `provided_output` in place of generating a response
+    /// when the caller already has one
+    async fn run(
+        &self,
+        request: ComplianceRequest,
+        provided_output: Option<String>,
+        kind: RunKind,
+        refusal: Option<bool>,
+        cancel: CancellationToken,
+    ) -> Result<ComplianceResponse, WorkflowError> {
+        let request_fingerprint = request.fingerprint(None);
         let ComplianceRequest {
             correlation_id: request_correlation_id,
-            prompt: original_prompt,
+            prompt,
+            suggest_rewrite,
+            template,
+            deterministic,
+            session_id,
         } = request;
-        let correlation_id = generate_correlation_id_from_request(request_correlation_id);
-        let span = create_span_with_correlation(&correlation_id, "compliance_workflow");
-        let _enter = span.enter();
+        // A templated request is audited as the prompt its values make, and only the
+        // values are scanned
+        let template = template
+            .map(|invocation| self.resolve_template(&invocation))
+            .transpose()?;
+        let original_prompt = match &template {
+            Some(template) => template.reconstruct(),
+            None => prompt,
+        };
+        // Self-test ids are minted here, so only caller ids are held to the policy
+        let correlation_id = match kind {
+            RunKind::Live => self.correlation_ids.normalize(request_correlation_id),
+            RunKind::SelfTest { .. } => {
+                generate_correlation_id_from_request(request_correlation_id)
+            }
+        };
+        // Every chat call of a deterministic request, translations and language
+        // detection included, is pinned to the same sampling
+        let sampling = deterministic
+            .unwrap_or(self.deterministic_by_default)
+            .then_some(DeterministicSampling {
+                random_seed: self.deterministic_seed,
+            });
+        let span = workflow_span(&correlation_id);
+        let abandonment = Abandonment::new(
+            cancel,
+            correlation_id.clone(),
+            self.audit_logger.clone(),
+            kind == RunKind::Live,
+        );
+        let result = sampling::scope(
+            sampling,
+            async move {
+                let decided = self
+                    .run_stages(
+                        correlation_id,
+                        original_prompt,
+                        template,
+                        CallerOptions {
+                            suggest_rewrite,
+                            session_id,
+                            request_fingerprint,
+                            refusal: refusal.unwrap_or(self.refusals.enabled),
+                            provided_output,
+                        },
+                        kind,
+                        &abandonment,
+                    )
+                    .await;
+                // Once decided the run is audited in full, even if the caller leaves
+                // while the record is written
+                abandonment.finish(&decided);
+                let (run, verdict) = decided?;
+                self.finish(run, verdict).await
+            }
+            .instrument(span.clone()),
+        )
+        .await;
+        match &result {
+            Ok(response) => span.record("status", response.status.name()),
+            Err(_) => span
+                .record("status", "error")
+                .record("otel.status_code", "ERROR"),
+        };
+        result
+    }
 
+    async fn run_stages(
+        &self,
+        correlation_id: String,
+        original_prompt: String,
+        template: Option<ResolvedTemplate>,
+        caller: CallerOptions,
+        kind: RunKind,
+        abandonment: &Abandonment,
+    ) -> Result<(WorkflowRun, Verdict), WorkflowError> {
+        let CallerOptions {
+            suggest_rewrite,
+            session_id,
+            request_fingerprint,
+            refusal,
+            provided_output,
+        } = caller;
         log_with_correlation(
             &correlation_id,
             tracing::Level::INFO,
             "Starting compliance workflow",
         );
+        let mut run = WorkflowRun {
+            kind,
+            correlation_id,
+            session_id,
+            prompt: original_prompt.clone(),
+            original_prompt,
+            english_prompt: None,
+            request_fingerprint,
+            suggest_rewrite,
+            refusal,
+            provided_output,
+            original_language: String::new(),
+            config_fingerprint: self.config_fingerprint(),
+            preprocessing: Vec::new(),
+            template,
+            firewall: PromptFirewallResult::default(),
+            eu_compliance: EuComplianceResult::default(),
+            bias: BiasScanResult::not_scanned(),
+            applied_exemptions: Vec::new(),
+            semantic: None,
+            semantic_skipped_reason: None,
+            moderation_skipped_reason: None,
+            // One snapshot per request, so a toggle flipped mid-request applies to the next
+            disabled_stages: self.stage_toggles.read().unwrap().disabled(),
+            input_moderation: None,
+            output_analysis: None,
+            generation: None,
+            repeat_config: self.repeat_offenders.config(),
+            repeat_fingerprint: None,
+            repeat_match: None,
+            repeat_bonus: None,
+            sanitize_probing: None,
+            removed_content_moderation: None,
+            llm_judge: None,
+            stage_failures: Vec::new(),
+            policy_evidence: PolicyEvidence::default(),
+            trace: Vec::new(),
+        };
 
-        // Detect original language for response translation
-        let original_language = self.detect_original_language(&original_prompt).await;
-        log_with_correlation(
-            &correlation_id,
-            tracing::Level::DEBUG,
-            &format!("Detected original language: {}", original_language),
-        );
+        // Step 0: Preprocessing, then language detection and translation
+        self.preprocess(&mut run);
+        self.detect_language(&mut run).await;
+        abandonment.checkpoint()?;
 
-        // Step 1: Firewall check (fast, deterministic)
-        let firewall = self
-            .firewall_service
-            .inspect(PromptFirewallRequest {
-                prompt: original_prompt.clone(),
-                correlation_id: Some(correlation_id.clone()),
-            })
-            .await;
+        // Step 1: Firewall check (fast, deterministic), less any exempted rules
+        self.check_firewall(&mut run).await;
 
         // Step 2: EU AI Act compliance check
-        log_with_correlation(
-            &correlation_id,
-            tracing::Level::INFO,
-            "Performing EU AI Act compliance check",
-        );
-        let eu_compliance = self.eu_compliance_service.check_prompt(&original_prompt);
+        self.check_eu_compliance(&mut run);
 
         // Step 3: Bias detection
-        let bias = self
-            .bias_service
-            .scan(BiasScanRequest {
-                text: firewall.sanitized_prompt.clone(),
-                threshold: None,
-            })
-            .await;
-
-        // Policy combiner: Apply precedence rules
-        // 0. EU Compliance Unacceptable -> Block (Article 5 prohibited practices)
-        if matches!(eu_compliance.risk_tier, AiRiskTier::Unacceptable) {
-            let evidence = DecisionEvidence {
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_matched_rules: firewall.matched_rules.clone(),
-                semantic_risk_score: None,
-                semantic_matched_template: None,
-                semantic_category: None,
-                moderation_flagged: false,
-                moderation_categories: vec![],
-                final_decision: "block".to_string(),
-                final_reason: format!(
-                    "Blocked by EU AI Act Article 5 (Prohibited Practices): {}",
-                    eu_compliance
-                        .findings
-                        .first()
-                        .map(|f| f.detail.as_str())
-                        .unwrap_or("Unacceptable risk tier detected")
-                ),
-            };
+        self.scan_bias(&mut run).await;
 
-            log_with_correlation(
-                &correlation_id,
-                tracing::Level::WARN,
-                &format!(
-                    "Prompt blocked by EU AI Act compliance: {:?}",
-                    eu_compliance.risk_tier
-                ),
-            );
+        // Step 3b: Compare against recently blocked prompts
+        self.check_repeat_offender(&mut run).await;
 
-            let proof = self.audit_logger.log_event(AuditEvent {
-                correlation_id: correlation_id.clone(),
-                original_prompt: original_prompt.clone(),
-                sanitized_prompt: firewall.sanitized_prompt.clone(),
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_reasons: firewall.reasons.clone(),
-                semantic_risk_score: None,
-                semantic_template_id: None,
-                semantic_category: None,
-                bias_score: bias.score,
-                bias_level: format!("{:?}", bias.level),
-                input_moderation_flagged: false,
-                output_moderation_flagged: false,
-                final_status: "blocked_by_eu_compliance".to_owned(),
-                final_reason: evidence.final_reason.clone(),
-                model_used: None,
-                output_preview: None,
-                full_output_text: None,
-                output_moderation_categories: vec![],
-                eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
-                eu_findings: Some(
-                    eu_compliance
-                        .findings
-                        .iter()
-                        .map(|f| f.detail.clone())
-                        .collect(),
-                ),
-                tokens_used: None,
-                response_latency_ms: None,
-                detected_language: Some(original_language.clone()),
-                was_translated: false,
-            })?;
-
-            return Ok(ComplianceResponse {
-                correlation_id,
-                status: WorkflowStatus::BlockedByEuCompliance,
-                firewall,
-                semantic: None,
-                bias,
-                input_moderation: None,
-                output_moderation: None,
-                generated_text: None,
-                audit_proof: proof,
-                decision_evidence: Some(evidence),
-                eu_compliance: Some(eu_compliance),
-            });
+        // Step 3c: Count sanitized prompts against their session
+        self.check_sanitize_probing(&mut run);
+
+        // Decision policy: a blocking rule ends the request at the first checkpoint its
+        // evidence is known. The built-in policy blocks on EU prohibited practices, then
+        // firewall blocks, then close variants of recently blocked prompts and sessions
+        // probing the sanitizer.
+        if let Some(verdict) = self.policy_block(&mut run) {
+            return Ok((run, verdict));
         }
 
-        // 1. Firewall Block -> Block
-        if firewall.action == FirewallAction::Block {
-            let evidence = DecisionEvidence {
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_matched_rules: firewall.matched_rules.clone(),
-                semantic_risk_score: None,
-                semantic_matched_template: None,
-                semantic_category: None,
-                moderation_flagged: false,
-                moderation_categories: vec![],
-                final_decision: "block".to_string(),
-                final_reason: format!(
-                    "Blocked by firewall rule: {}",
-                    firewall.matched_rules.join(", ")
-                ),
-            };
+        // 1c. Language detection or bias translation failed with a closed policy -> Block
+        if let Some(verdict) = stage_failure_verdict(&mut run) {
+            return Ok((run, verdict));
+        }
 
-            log_with_correlation(
-                &correlation_id,
-                tracing::Level::WARN,
-                "Prompt blocked by firewall",
-            );
+        // Step 4: Run semantic scan and input moderation concurrently
+        let input = self.scan_input(&mut run, abandonment).await?;
 
-            let proof = self.audit_logger.log_event(AuditEvent {
-                correlation_id: correlation_id.clone(),
-                original_prompt: original_prompt.clone(),
-                sanitized_prompt: firewall.sanitized_prompt.clone(),
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_reasons: firewall.reasons.clone(),
-                semantic_risk_score: None,
-                semantic_template_id: None,
-                semantic_category: None,
-                bias_score: bias.score,
-                bias_level: format!("{:?}", bias.level),
-                input_moderation_flagged: false,
-                output_moderation_flagged: false,
-                final_status: "blocked_by_firewall".to_owned(),
-                final_reason: evidence.final_reason.clone(),
-                model_used: None,
-                output_preview: None,
-                full_output_text: None,
-                output_moderation_categories: vec![],
-                eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
-                eu_findings: Some(
-                    eu_compliance
-                        .findings
-                        .iter()
-                        .map(|f| f.detail.clone())
-                        .collect(),
-                ),
-                tokens_used: None,
-                response_latency_ms: None,
-                detected_language: Some(original_language.clone()),
-                was_translated: false,
-            })?;
-
-            return Ok(ComplianceResponse {
-                correlation_id,
-                status: WorkflowStatus::BlockedByFirewall,
-                firewall,
-                semantic: None,
-                bias,
-                input_moderation: None,
-                output_moderation: None,
-                generated_text: None,
-                audit_proof: proof,
-                decision_evidence: Some(evidence),
-                eu_compliance: Some(eu_compliance),
-            });
+        // 2. Semantic evidence, e.g. high risk -> Block
+        if let Some(verdict) = self.policy_block(&mut run) {
+            return Ok((run, verdict));
         }
 
-        // Step 4: Run semantic scan and input moderation concurrently.
-        log_with_correlation(
-            &correlation_id,
-            tracing::Level::INFO,
-            "Performing semantic scan and input moderation",
-        );
-        let (semantic_result, input_moderation_result) = tokio::join!(
-            self.semantic_service.scan(SemanticScanRequest {
-                text: firewall.sanitized_prompt.clone(),
-            }),
-            self.mistral_service
-                .moderate_text(firewall.sanitized_prompt.clone())
-        );
-        let semantic = semantic_result.ok();
-        let input_moderation = input_moderation_result?;
+        // 2b. Semantic scan or input moderation failed with a closed policy -> Block
+        if let Some(verdict) = stage_failure_verdict(&mut run) {
+            return Ok((run, verdict));
+        }
 
-        // 2. Semantic High -> Block
-        if let Some(ref sem) = semantic
-            && sem.risk_level == SemanticRiskLevel::High
-        {
-            let evidence = DecisionEvidence {
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_matched_rules: firewall.matched_rules.clone(),
-                semantic_risk_score: Some(sem.risk_score),
-                semantic_matched_template: sem.nearest_template_id.clone(),
-                semantic_category: sem.category.clone(),
-                moderation_flagged: false,
-                moderation_categories: vec![],
-                final_decision: "block".to_string(),
-                final_reason: format!(
-                    "Semantic similarity to attack pattern {} (category: {}, score: {:.2})",
-                    sem.nearest_template_id.as_deref().unwrap_or("unknown"),
-                    sem.category.as_deref().unwrap_or("unknown"),
-                    sem.similarity
-                ),
-            };
+        // 3. Input moderation check
+        if let Some(input_moderation) = &run.input_moderation {
+            run.policy_evidence
+                .set(PolicyField::ModerationFlagged, input_moderation.flagged);
+        }
+        if let Some(verdict) = self.policy_block(&mut run) {
+            return Ok((run, verdict));
+        }
 
-            log_with_correlation(
-                &correlation_id,
-                tracing::Level::WARN,
-                "Prompt blocked by semantic detection",
+        // 3b. Optionally block on the content that sanitization stripped out
+        if let Some((removed_moderation, step)) = input.removed_content {
+            run.record(step);
+            run.policy_evidence.set(
+                PolicyField::RemovedContentFlagged,
+                removed_moderation.flagged,
             );
-
-            let proof = self.audit_logger.log_event(AuditEvent {
-                correlation_id: correlation_id.clone(),
-                original_prompt: original_prompt.clone(),
-                sanitized_prompt: firewall.sanitized_prompt.clone(),
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_reasons: firewall.reasons.clone(),
-                semantic_risk_score: Some(sem.risk_score),
-                semantic_template_id: sem.nearest_template_id.clone(),
-                semantic_category: sem.category.clone(),
-                bias_score: bias.score,
-                bias_level: format!("{:?}", bias.level),
-                input_moderation_flagged: false,
-                output_moderation_flagged: false,
-                final_status: "blocked_by_semantic".to_owned(),
-                final_reason: evidence.final_reason.clone(),
-                model_used: None,
-                output_preview: None,
-                full_output_text: None,
-                output_moderation_categories: vec![],
-                eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
-                eu_findings: Some(
-                    eu_compliance
-                        .findings
-                        .iter()
-                        .map(|f| f.detail.clone())
-                        .collect(),
-                ),
-                tokens_used: None,
-                response_latency_ms: None,
-                detected_language: Some(original_language.clone()),
-                was_translated: false,
-            })?;
-
-            return Ok(ComplianceResponse {
-                correlation_id,
-                status: WorkflowStatus::BlockedBySemantic,
-                firewall,
-                semantic,
-                bias,
-                input_moderation: None,
-                output_moderation: None,
-                generated_text: None,
-                audit_proof: proof,
-                decision_evidence: Some(evidence),
-                eu_compliance: Some(eu_compliance),
-            });
+            run.removed_content_moderation = Some(removed_moderation);
+            if let Some(verdict) = self.policy_block(&mut run) {
+                return Ok((run, verdict));
+            }
         }
 
-        // 3. Input moderation check
-        if input_moderation.flagged {
-            let evidence = DecisionEvidence {
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_matched_rules: firewall.matched_rules.clone(),
-                semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
-                semantic_matched_template: semantic
-                    .as_ref()
-                    .and_then(|s| s.nearest_template_id.clone()),
-                semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
-                moderation_flagged: true,
-                moderation_categories: input_moderation.categories.clone(),
-                final_decision: "block".to_string(),
-                final_reason: format!(
-                    "Flagged by content moderation: {}",
-                    input_moderation.categories.join(", ")
-                ),
-            };
+        // 3c. Ask the generation model about prompts in the gray zone; it can only block
+        if let Some(verdict) = self.llm_judge(&mut run).await {
+            return Ok((run, verdict));
+        }
 
-            log_with_correlation(
-                &correlation_id,
-                tracing::Level::WARN,
-                "Input flagged by moderation",
-            );
+        // Step 5: Generation, then the checks on its output
+        let generated_text = match self.produce_output(&mut run, abandonment).await? {
+            Some((generation, generated_text)) => {
+                let blocked = self
+                    .check_output(&mut run, generation, &generated_text, abandonment)
+                    .await?;
+                if let Some(verdict) = blocked {
+                    return Ok((run, verdict));
+                }
+                Some(generated_text)
+            }
+            None => None,
+        };
 
-            let proof = self.audit_logger.log_event(AuditEvent {
-                correlation_id: correlation_id.clone(),
-                original_prompt: original_prompt.clone(),
-                sanitized_prompt: firewall.sanitized_prompt.clone(),
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_reasons: firewall.reasons.clone(),
-                semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
-                semantic_template_id: semantic
-                    .as_ref()
-                    .and_then(|s| s.nearest_template_id.clone()),
-                semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
-                bias_score: bias.score,
-                bias_level: format!("{:?}", bias.level),
-                input_moderation_flagged: true,
-                output_moderation_flagged: false,
-                final_status: "blocked_by_input_moderation".to_owned(),
-                final_reason: evidence.final_reason.clone(),
-                model_used: None,
-                output_preview: None,
-                full_output_text: None,
-                output_moderation_categories: input_moderation.categories.clone(),
-                eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
-                eu_findings: Some(
-                    eu_compliance
-                        .findings
-                        .iter()
-                        .map(|f| f.detail.clone())
-                        .collect(),
-                ),
-                tokens_used: None,
-                response_latency_ms: None,
-                detected_language: Some(original_language.clone()),
-                was_translated: false,
-            })?;
-
-            return Ok(ComplianceResponse {
-                correlation_id,
-                status: WorkflowStatus::BlockedByInputModeration,
-                firewall,
-                semantic,
-                bias,
-                input_moderation: Some(input_moderation),
-                output_moderation: None,
-                generated_text: None,
-                audit_proof: proof,
-                decision_evidence: Some(evidence),
-                eu_compliance: Some(eu_compliance),
-            });
+        // 5. Output moderation or translation failed with a closed policy -> Block,
+        // withholding the generated text
+        if let Some(verdict) = stage_failure_verdict(&mut run) {
+            return Ok((run, verdict));
         }
 
-        // 4. Semantic Medium or Firewall Sanitize -> Sanitize (proceed with caution)
-        let is_sanitized = firewall.action == FirewallAction::Sanitize
-            || semantic
-                .as_ref()
-                .map(|s| s.risk_level == SemanticRiskLevel::Medium)
-                .unwrap_or(false);
+        let verdict = self.decide(&mut run, generated_text, input.moderation_step);
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::INFO,
+            "Workflow completed successfully",
+        );
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::DEBUG,
+            &format!(
+                "Generated text preview: {}",
+                verdict
+                    .generated_text
+                    .as_deref()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(160)
+                    .collect::<String>()
+            ),
+        );
 
+        Ok((run, verdict))
+    }
+
+    /// Generate a response to the sanitized prompt and translate it back to the prompt's
+    /// language, returning the generation and the text for the caller
+    async fn generate(
+        &self,
+        run: &mut WorkflowRun,
+        sanitized_ref: String,
+        abandonment: &Abandonment,
+    ) -> Result<(GenerationRecord, String), WorkflowError> {
         // Generate text with timing
         log_with_correlation(
-            &correlation_id,
+            &run.correlation_id,
             tracing::Level::INFO,
             "Generating text with Mistral AI",
         );
+        let (messages, input_digest) = self.generation_input(run);
+        let request = self.mistral_service.generation_request(messages, true);
+        let parameters = GenerationParameters::of(&request);
         let generation_start = Instant::now();
-        let generation = self
-            .mistral_service
-            .generate_text(firewall.sanitized_prompt.clone(), true)
+        // The rewrite suggestion runs alongside generation and is bounded by its own
+        // time budget, so it never holds the response back for long
+        let rewrite = async {
+            if run.suggest_rewrite {
+                self.bias_service
+                    .suggest_rewrite(&run.original_prompt, &run.bias)
+                    .instrument(stage_span("bias_rewrite"))
+                    .await
+            } else {
+                None
+            }
+        };
+        let generation = async {
+            let result = self
+                .mistral_service
+                .complete(request)
+                .instrument(stage_span("generation"))
+                .await;
+            (result, generation_start.elapsed())
+        };
+        abandonment.generation_started();
+        let ((generation, generation_elapsed), suggested_rewrite) = abandonment
+            .unless_cancelled(async { tokio::join!(generation, rewrite) })
             .await?;
-        let generation_latency_ms = generation_start.elapsed().as_millis() as u64;
+        let generation = generation?;
+        let generation_latency_ms = generation_elapsed.as_millis() as u64;
+        run.bias.suggested_rewrite = suggested_rewrite;
+        run.record(TraceStep {
+            stage: "generation".to_owned(),
+            inputs: vec![sanitized_ref],
+            verdict: "allow".to_owned(),
+            rule_refs: vec![generation.model.clone()],
+            parameters: BTreeMap::new(),
+            duration_ms: generation_latency_ms,
+        });
 
         // Clone the English output for moderation and audit logging
         let english_output = generation.output_text.clone();
         let tokens_used = generation.usage.as_ref().map(|u| u.total_tokens);
 
         // Translate generated text back to original language if needed
-        let was_translated = original_language.to_lowercase() != "english";
+        let was_translated = run.original_language.to_lowercase() != "english"
+            && !run.is_disabled(ToggleableStage::Translation);
         let generated_text = if was_translated {
-            self.translate_to_original_language(&english_output, &original_language).await
+            // A failed translation falls back to the English output when allowed
+            let translation_span = stage_span("translation");
+            let translation = self
+                .mistral_service
+                .translate_text(english_output.clone(), run.original_language.clone())
+                .instrument(translation_span.clone());
+            match abandonment.unless_cancelled(translation).await? {
+                Ok(translation) => translation.translated_text,
+                Err(e) => {
+                    translation_span.record("status", "failed");
+                    self.stage_failed(
+                        &run.correlation_id,
+                        &mut run.stage_failures,
+                        PipelineStage::Translation,
+                        &e,
+                    );
+                    english_output.clone()
+                }
+            }
         } else {
             english_output.clone()
         };
 
-        // Output moderation (moderate the English version before translation)
+        let record = GenerationRecord {
+            model: Some(generation.model),
+            english_output,
+            tokens_used,
+            latency_ms: Some(generation_latency_ms),
+            was_translated,
+            input_digest: Some(input_digest),
+            parameters: Some(parameters),
+        };
+        Ok((record, generated_text))
+    }
+
+    /// Messages sent to generation for `run`, and their digest for the audit record
+    fn generation_input(&self, run: &WorkflowRun) -> (Vec<ChatMessage>, GenerationInputDigest) {
+        let mut transformations: Vec<String> = run
+            .preprocessing
+            .iter()
+            .filter(|applied| applied.changed)
+            .map(|applied| applied.transform.as_str().to_owned())
+            .collect();
+        if run.firewall.translated {
+            transformations.push("translation".to_owned());
+        }
+        if !run.firewall.sanitization_edits.is_empty()
+            || run.firewall.action == FirewallAction::Sanitize
+        {
+            transformations.push("sanitization".to_owned());
+        }
+        // A template goes out as written, with the values the firewall let through
+        let user_content = match run.template.as_ref().and_then(|t| t.generation_prompt()) {
+            Some(prompt) => {
+                transformations.push("template".to_owned());
+                prompt.to_owned()
+            }
+            None => run.firewall.sanitized_prompt.clone(),
+        };
+
+        let mut messages = Vec::with_capacity(2);
+        if let Some(preamble) = &self.generation_preamble {
+            transformations.push("hardening_preamble".to_owned());
+            messages.push(ChatMessage {
+                role: "system".to_owned(),
+                content: preamble.clone(),
+            });
+        }
+        let digest_content =
+            (self.prompt_storage == PromptStorageMode::Full).then(|| user_content.clone());
+        messages.push(ChatMessage {
+            role: "user".to_owned(),
+            content: user_content,
+        });
+        let digest = GenerationInputDigest {
+            sha256: GenerationInputDigest::messages_hash(&messages),
+            transformations,
+            user_content: digest_content,
+        };
+        (messages, digest)
+    }
+
+    /// Run the checks on the English answer and add their evidence for the policy
+    ///
+    /// Moderation runs unless it is skipped; the bias, firewall echo and semantic checks
+    /// only when [`OutputAnalysisConfig`] enables them.
+    async fn analyze_output(
+        &self,
+        run: &mut WorkflowRun,
+        english_output: &str,
+    ) -> Result<(), WorkflowError> {
         log_with_correlation(
-            &correlation_id,
+            &run.correlation_id,
             tracing::Level::INFO,
             "Performing output moderation",
         );
-        let output_moderation = self
-            .mistral_service
-            .moderate_text(english_output.clone())
+        let output_ref = content_ref(english_output);
+        let stage_start = Instant::now();
+        let (moderation, moderation_chunks) = self
+            .moderate_output(run, english_output, "output_moderation")
             .await?;
+        let mut step = moderation_step(
+            "output_moderation",
+            output_ref.clone(),
+            moderation.as_ref(),
+            run.moderation_skip(ToggleableStage::OutputModeration)
+                .as_deref(),
+            elapsed_ms(stage_start),
+        );
+        if let Some(chunks) = &moderation_chunks {
+            step.parameters
+                .insert("chunks".to_owned(), chunks.count as f64);
+        }
+        run.record(step);
+        let mut analysis = OutputAnalysis {
+            moderation,
+            moderation_chunks,
+            ..OutputAnalysis::default()
+        };
 
-        if output_moderation.flagged {
-            let evidence = DecisionEvidence {
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_matched_rules: firewall.matched_rules.clone(),
-                semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
-                semantic_matched_template: semantic
+        if self.output_analysis.bias {
+            let stage_start = Instant::now();
+            let bias = self
+                .bias_service
+                .scan(BiasScanRequest {
+                    text: english_output.to_owned(),
+                    threshold: None,
+                    language_hint: Some("English".to_owned()),
+                    pre_translated_text: None,
+                })
+                .instrument(stage_span("output_bias"))
+                .await;
+            run.record(TraceStep {
+                stage: "output_bias".to_owned(),
+                inputs: vec![output_ref.clone()],
+                verdict: if bias.level == BiasLevel::Low {
+                    "allow"
+                } else {
+                    "flag"
+                }
+                .to_owned(),
+                rule_refs: bias
+                    .categories
+                    .iter()
+                    .map(|category| format!("{category:?}"))
+                    .collect(),
+                parameters: BTreeMap::from([("score".to_owned(), f64::from(bias.score))]),
+                duration_ms: elapsed_ms(stage_start),
+            });
+            analysis.bias = Some(bias);
+        }
+
+        if self.output_analysis.firewall_echo {
+            let stage_start = Instant::now();
+            let echoed = self.firewall_service.match_block_rules(english_output);
+            run.record(TraceStep {
+                stage: "output_firewall_echo".to_owned(),
+                inputs: vec![output_ref.clone()],
+                verdict: if echoed.is_empty() { "allow" } else { "block" }.to_owned(),
+                rule_refs: echoed.iter().map(|matched| matched.id.clone()).collect(),
+                parameters: BTreeMap::new(),
+                duration_ms: elapsed_ms(stage_start),
+            });
+            analysis.firewall_echo = Some(echoed);
+        }
+
+        if self.output_analysis.semantic {
+            let stage_start = Instant::now();
+            let span = stage_span("output_semantic");
+            // The answer is already English, so the scan neither detects nor translates
+            let semantic = match self
+                .semantic_service
+                .scan(SemanticScanRequest {
+                    text: english_output.to_owned(),
+                    detected_language: Some("English".to_owned()),
+                    pre_translated_text: None,
+                })
+                .instrument(span.clone())
+                .await
+            {
+                Ok(semantic) => Some(semantic),
+                Err(e) => {
+                    span.record("status", "failed");
+                    self.stage_failed(
+                        &run.correlation_id,
+                        &mut run.stage_failures,
+                        PipelineStage::Semantic,
+                        &e,
+                    );
+                    None
+                }
+            };
+            run.record(TraceStep {
+                stage: "output_semantic".to_owned(),
+                inputs: vec![output_ref],
+                verdict: match semantic.as_ref().map(|s| &s.risk_level) {
+                    Some(SemanticRiskLevel::High) => "flag",
+                    Some(_) => "allow",
+                    None => "skip",
+                }
+                .to_owned(),
+                rule_refs: semantic
+                    .as_ref()
+                    .and_then(|s| s.nearest_template_id.clone())
+                    .into_iter()
+                    .collect(),
+                parameters: semantic
                     .as_ref()
-                    .and_then(|s| s.nearest_template_id.clone()),
-                semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
-                moderation_flagged: true,
-                moderation_categories: output_moderation.categories.clone(),
-                final_decision: "block".to_string(),
-                final_reason: format!(
-                    "Output flagged by moderation: {}",
-                    output_moderation.categories.join(", ")
+                    .map(|s| BTreeMap::from([("score".to_owned(), f64::from(s.risk_score))]))
+                    .unwrap_or_default(),
+                duration_ms: elapsed_ms(stage_start),
+            });
+            analysis.semantic = semantic;
+        }
+
+        analysis.record_evidence(&mut run.policy_evidence);
+        run.output_analysis = Some(analysis);
+        Ok(())
+    }
+
+    /// Moderate generated text; `None` when the call failed and the failure was recorded
+    /// Moderate a generated answer, in chunks when it is longer than one call takes
+    ///
+    /// An answer the provider refuses as too large is retried in chunks rather than
+    /// failing the stage.
+    async fn moderate_output(
+        &self,
+        run: &mut WorkflowRun,
+        text: &str,
+        stage: &'static str,
+    ) -> Result<(Option<ModerationResponse>, Option<OutputModerationChunks>), WorkflowError> {
+        if run
+            .moderation_skip(ToggleableStage::OutputModeration)
+            .is_some()
+        {
+            return Ok((None, None));
+        }
+        let span = stage_span(stage);
+        let text_chars = text.chars().count();
+        let chunks = self.output_chunking.chunks(text_chars);
+        let result = if chunks.is_empty() {
+            match self
+                .mistral_service
+                .moderate_text(text)
+                .instrument(span.clone())
+                .await
+            {
+                Err(e) if e.is_payload_too_large() => {
+                    let chunks = self.output_chunking.fallback_chunks(text_chars);
+                    if chunks.len() > 1 {
+                        log_with_correlation(
+                            &run.correlation_id,
+                            tracing::Level::WARN,
+                            &format!(
+                                "Moderation refused {text_chars} characters as too large; \
+                                 moderating them in {} chunks",
+                                chunks.len()
+                            ),
+                        );
+                        self.moderate_chunks(text, &chunks, true)
+                            .instrument(span.clone())
+                            .await
+                    } else {
+                        Err(e)
+                    }
+                }
+                result => result.map(|moderation| (moderation, None)),
+            }
+        } else {
+            self.moderate_chunks(text, &chunks, false)
+                .instrument(span.clone())
+                .await
+        };
+        match result {
+            Ok((moderation, chunks)) => {
+                span.record("flagged", moderation.flagged);
+                Ok((Some(moderation), chunks))
+            }
+            Err(e) if e.retry_after().is_some() => Err(e.into()),
+            Err(e) => {
+                span.record("status", "failed");
+                self.stage_failed(
+                    &run.correlation_id,
+                    &mut run.stage_failures,
+                    PipelineStage::Moderation,
+                    &e,
+                );
+                Ok((None, None))
+            }
+        }
+    }
+
+    /// Moderate the character ranges `chunks` of `text` and combine the results
+    async fn moderate_chunks(
+        &self,
+        text: &str,
+        chunks: &[(usize, usize)],
+        payload_too_large: bool,
+    ) -> Result<(ModerationResponse, Option<OutputModerationChunks>), MistralServiceError> {
+        let mut byte_offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+        byte_offsets.push(text.len());
+        let inputs = chunks
+            .iter()
+            .map(|&(start, end)| text[byte_offsets[start]..byte_offsets[end]].to_owned())
+            .collect();
+        let responses = self
+            .mistral_service
+            .moderate_batch_concurrently(inputs, self.output_chunking.max_concurrency)
+            .await?;
+        let flagged_chunks: Vec<usize> = responses
+            .iter()
+            .enumerate()
+            .filter(|(_, response)| response.flagged)
+            .map(|(index, _)| index)
+            .collect();
+        let summary = OutputModerationChunks {
+            count: chunks.len(),
+            flagged_ranges: flagged_chunks.iter().map(|&index| chunks[index]).collect(),
+            flagged_chunks,
+            payload_too_large,
+        };
+        Ok((merge_moderation(responses), Some(summary)))
+    }
+
+    /// Derive the decision evidence from the trace, write the audit record and
+    /// assemble the response for a finished run.
+    async fn finish(
+        &self,
+        run: WorkflowRun,
+        verdict: Verdict,
+    ) -> Result<ComplianceResponse, WorkflowError> {
+        let stages = run.stage_outcomes();
+        let refusal_message = match RefusalKind::of(&verdict.status) {
+            Some(kind) if run.refusal => Some(self.refusal_message(&run, &verdict, kind).await),
+            _ => None,
+        };
+        let WorkflowRun {
+            kind,
+            correlation_id,
+            session_id,
+            original_prompt,
+            prompt: _,
+            english_prompt: _,
+            request_fingerprint,
+            suggest_rewrite: _,
+            refusal: _,
+            provided_output,
+            original_language,
+            config_fingerprint,
+            preprocessing,
+            firewall,
+            applied_exemptions,
+            eu_compliance,
+            bias,
+            semantic,
+            semantic_skipped_reason,
+            moderation_skipped_reason,
+            disabled_stages,
+            input_moderation,
+            output_analysis,
+            generation,
+            repeat_config: _,
+            repeat_fingerprint,
+            repeat_match,
+            repeat_bonus: _,
+            sanitize_probing,
+            removed_content_moderation: _,
+            llm_judge,
+            stage_failures,
+            policy_evidence: _,
+            trace,
+            template,
+        } = run;
+        let template = template.map(|template| template.evidence());
+        let (output_moderation, output_moderation_chunks, translated_output_moderation) =
+            match &output_analysis {
+                Some(analysis) => (
+                    analysis.moderation.clone(),
+                    analysis.moderation_chunks.clone(),
+                    analysis.translated_moderation.clone(),
                 ),
+                None => (None, None, None),
             };
+        // Slow-request diagnostics reuse the stage timings of the trace
+        for step in &trace {
+            record_stage(&step.stage, step.duration_ms);
+        }
 
-            log_with_correlation(
-                &correlation_id,
-                tracing::Level::WARN,
-                "Output flagged by moderation",
-            );
+        let blocked = !matches!(
+            verdict.status,
+            WorkflowStatus::Completed | WorkflowStatus::Sanitized
+        );
+        if blocked
+            && kind == RunKind::Live
+            && let Some(fingerprint) = repeat_fingerprint
+        {
+            self.repeat_offenders.remember(&correlation_id, fingerprint);
+        }
+        let similar_blocked_correlation_id = repeat_match.map(|repeat| repeat.correlation_id);
 
-            let proof = self.audit_logger.log_event(AuditEvent {
-                correlation_id: correlation_id.clone(),
-                original_prompt: original_prompt.clone(),
-                sanitized_prompt: firewall.sanitized_prompt.clone(),
-                firewall_action: format!("{:?}", firewall.action),
-                firewall_reasons: firewall.reasons.clone(),
-                semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
-                semantic_template_id: semantic
-                    .as_ref()
-                    .and_then(|s| s.nearest_template_id.clone()),
-                semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
-                bias_score: bias.score,
-                bias_level: format!("{:?}", bias.level),
-                input_moderation_flagged: false,
-                output_moderation_flagged: true,
-                final_status: "blocked_by_output_moderation".to_owned(),
-                final_reason: evidence.final_reason.clone(),
-                model_used: Some(generation.model),
-                output_preview: Some(english_output.chars().take(160).collect()),
-                full_output_text: Some(english_output.clone()),
-                output_moderation_categories: output_moderation.categories.clone(),
-                eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
-                eu_findings: Some(
-                    eu_compliance
-                        .findings
+        // A later stage catching what the firewall allowed suggests a missing rule; the
+        // firewall blocking what the semantic scan scored low suggests an overly broad one
+        let firewall_allowed = firewall.action == FirewallAction::Allow;
+        let firewall_miss = match verdict.status {
+            WorkflowStatus::BlockedBySemantic if firewall_allowed => {
+                Some(FirewallMissSource::Semantic)
+            }
+            WorkflowStatus::BlockedByInputModeration if firewall_allowed => {
+                Some(FirewallMissSource::InputModeration)
+            }
+            _ => None,
+        };
+        let firewall_overblock = verdict.status == WorkflowStatus::BlockedByFirewall
+            && semantic
+                .as_ref()
+                .is_some_and(|s| s.risk_level == SemanticRiskLevel::Low);
+        if kind == RunKind::Live {
+            if let Some(caught_by) = firewall_miss {
+                let category = match caught_by {
+                    FirewallMissSource::Semantic => {
+                        semantic.as_ref().and_then(|s| s.category.clone())
+                    }
+                    FirewallMissSource::InputModeration => verdict
+                        .moderation_categories
                         .iter()
-                        .map(|f| f.detail.clone())
-                        .collect(),
-                ),
-                tokens_used,
-                response_latency_ms: Some(generation_latency_ms),
-                detected_language: Some(original_language.clone()),
-                was_translated,
-            })?;
-
-            return Ok(ComplianceResponse {
-                correlation_id,
-                status: WorkflowStatus::BlockedByOutputModeration,
-                firewall,
-                semantic,
-                bias,
-                input_moderation: Some(input_moderation),
-                output_moderation: Some(output_moderation),
-                generated_text: None,
-                audit_proof: proof,
-                decision_evidence: Some(evidence),
-                eu_compliance: Some(eu_compliance),
-            });
+                        .max_by(|a, b| a.severity.total_cmp(&b.severity))
+                        .map(|c| c.category.clone()),
+                };
+                self.firewall_misses.record_miss(FirewallMiss {
+                    correlation_id: correlation_id.clone(),
+                    caught_by,
+                    category,
+                    prompt_hash: prompt_fingerprint(&original_prompt),
+                    recorded_at: Utc::now(),
+                });
+            }
+            if firewall_overblock {
+                self.firewall_misses.record_overblock();
+            }
         }
 
-        // Build final evidence
-        let (final_decision, final_reason, final_status) = if is_sanitized {
-            let reason = if firewall.action == FirewallAction::Sanitize {
-                "Input sanitized by firewall".to_string()
-            } else {
-                format!(
-                    "Elevated risk (semantic score: {:.2}), proceeded with caution",
-                    semantic.as_ref().map(|s| s.similarity).unwrap_or(0.0)
-                )
-            };
-            ("sanitize".to_string(), reason, WorkflowStatus::Sanitized)
-        } else {
-            (
-                "allow".to_string(),
-                "All checks passed".to_string(),
-                WorkflowStatus::Completed,
-            )
+        let final_decision = verdict
+            .decisive_step
+            .and_then(|index| trace.get(index))
+            .map(|step| step.verdict.clone())
+            .unwrap_or_else(|| "allow".to_owned());
+        let input_moderation_flagged = verdict.status == WorkflowStatus::BlockedByInputModeration;
+        // A block for repeating a firewall rule is not a moderation flag
+        let output_moderation_flagged = verdict.status == WorkflowStatus::BlockedByOutputModeration
+            && verdict.final_reason.code != ReasonCode::OutputFirewallEcho;
+
+        let (semantic_medium_cutoff, semantic_high_cutoff) = self.semantic_service.risk_cutoffs();
+        let risk_inputs = RiskInputs {
+            firewall: firewall_signal(&firewall),
+            semantic_score: semantic.as_ref().map(|s| s.risk_score),
+            semantic_medium_cutoff,
+            semantic_high_cutoff,
+            bias: bias.score,
+            moderation: [
+                &input_moderation,
+                &output_moderation,
+                &translated_output_moderation,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|m| m.severity)
+            .fold(0.0, f32::max),
+            degraded_stages: stage_failures.len(),
+            blocked,
         };
+        let risk_score = risk_score(&risk_inputs, &self.risk_weights);
+        let threat_category = LayerFindings {
+            firewall: &firewall,
+            semantic: semantic.as_ref(),
+            input_moderation: input_moderation.as_ref(),
+            output_moderation: [
+                output_moderation.as_ref(),
+                translated_output_moderation.as_ref(),
+            ],
+            bias: &bias,
+        }
+        .resolve(&verdict.status);
+        if kind == RunKind::Live
+            && let Some(threat) = &threat_category
+        {
+            get_metrics()
+                .increment_threat_detections(threat.category.as_str(), threat.layer.as_str());
+        }
 
         let evidence = DecisionEvidence {
-            firewall_action: format!("{:?}", firewall.action),
+            firewall_action: serialized_name(&firewall.action),
             firewall_matched_rules: firewall.matched_rules.clone(),
             semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
             semantic_matched_template: semantic
                 .as_ref()
                 .and_then(|s| s.nearest_template_id.clone()),
             semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
-            moderation_flagged: false,
-            moderation_categories: vec![],
+            moderation_flagged: input_moderation_flagged || output_moderation_flagged,
+            moderation_categories: verdict.moderation_categories.clone(),
+            moderation_scope: verdict.moderation_scope.clone(),
             final_decision,
-            final_reason: final_reason.clone(),
+            final_reason: verdict.final_reason.text(),
+            final_reason_code: verdict.final_reason.code,
+            reason_params: verdict.final_reason.params,
+            decisive_step: verdict.decisive_step,
+            policy_rule: verdict.policy_rule,
+            similar_blocked_correlation_id: similar_blocked_correlation_id.clone(),
+            config_fingerprint: Some(config_fingerprint.clone()),
+            request_fingerprint: Some(request_fingerprint),
+            preprocessing: preprocessing.clone(),
+            semantic_skipped_reason: semantic_skipped_reason.clone(),
+            moderation_skipped_reason: moderation_skipped_reason.clone(),
+            disabled_stages: disabled_stages.clone(),
+            output_moderation_chunks: output_moderation_chunks.clone(),
+            applied_exemptions: applied_exemptions.clone(),
+            degraded_stages: stage_failures.clone(),
+            risk_score,
+            risk_inputs: Some(risk_inputs.clone()),
+            language_mix_detected: !firewall.mixed_languages.is_empty(),
+            mixed_languages: firewall.mixed_languages.clone(),
+            template: template.clone(),
+            input_truncation: firewall.truncation,
+            threat_category: threat_category.clone(),
+            llm_judge: llm_judge.clone(),
+            monitored_firewall_matches: firewall.monitored_matches.clone(),
         };
 
-        log_with_correlation(
-            &correlation_id,
-            tracing::Level::INFO,
-            "Workflow completed successfully",
-        );
-
-        let proof = self.audit_logger.log_event(AuditEvent {
+        let stored_prompt = |text: &str| match self.prompt_storage {
+            PromptStorageMode::Full => text.to_owned(),
+            PromptStorageMode::Redacted => prompt_fingerprint(text),
+        };
+        let event = AuditEvent {
             correlation_id: correlation_id.clone(),
-            original_prompt,
-            sanitized_prompt: firewall.sanitized_prompt.clone(),
-            firewall_action: format!("{:?}", firewall.action),
+            related_correlation_id: applied_exemptions
+                .iter()
+                .find_map(|applied| applied.related_correlation_id.clone()),
+            session_id,
+            original_prompt: stored_prompt(&original_prompt),
+            sanitized_prompt: stored_prompt(&firewall.sanitized_prompt),
+            firewall_action: serialized_name(&firewall.action),
             firewall_reasons: firewall.reasons.clone(),
             semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
             semantic_template_id: semantic
@@ -683,21 +2039,20 @@ impl ComplianceEngine {
                 .and_then(|s| s.nearest_template_id.clone()),
             semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
             bias_score: bias.score,
-            bias_level: format!("{:?}", bias.level),
-            input_moderation_flagged: false,
-            output_moderation_flagged: false,
-            final_status: if is_sanitized {
-                "sanitized"
-            } else {
-                "completed"
-            }
-            .to_owned(),
+            bias_level: serialized_name(&bias.level),
+            input_moderation_flagged,
+            output_moderation_flagged,
+            final_status: verdict.status.name(),
             final_reason: evidence.final_reason.clone(),
-            model_used: Some(generation.model.clone()),
-            output_preview: Some(english_output.chars().take(160).collect()),
-            full_output_text: Some(english_output),
-            output_moderation_categories: vec![],
-            eu_risk_tier: Some(format!("{:?}", eu_compliance.risk_tier)),
+            final_reason_code: evidence.final_reason_code,
+            reason_params: evidence.reason_params.clone(),
+            model_used: generation.as_ref().and_then(|g| g.model.clone()),
+            output_preview: generation
+                .as_ref()
+                .map(|g| g.english_output.chars().take(160).collect()),
+            full_output_text: generation.as_ref().map(|g| g.english_output.clone()),
+            output_moderation_categories: verdict.moderation_categories,
+            eu_risk_tier: Some(serialized_name(&eu_compliance.risk_tier)),
             eu_findings: Some(
                 eu_compliance
                     .findings
@@ -705,35 +2060,642 @@ impl ComplianceEngine {
                     .map(|f| f.detail.clone())
                     .collect(),
             ),
-            tokens_used,
-            response_latency_ms: Some(generation_latency_ms),
+            tokens_used: generation.as_ref().and_then(|g| g.tokens_used),
+            response_latency_ms: generation.as_ref().and_then(|g| g.latency_ms),
             detected_language: Some(original_language),
-            was_translated,
-        })?;
-
-        log_with_correlation(
-            &correlation_id,
-            tracing::Level::DEBUG,
-            &format!(
-                "Generated text preview: {}",
-                generated_text.chars().take(160).collect::<String>()
-            ),
-        );
+            was_translated: generation.as_ref().is_some_and(|g| g.was_translated),
+            decision_trace: trace.clone(),
+            similar_blocked_correlation_id,
+            sanitize_probing,
+            self_test: kind != RunKind::Live,
+            config_fingerprint,
+            preprocessing,
+            semantic_skipped_reason,
+            moderation_skipped_reason,
+            disabled_stages,
+            applied_exemptions,
+            prompt_storage: self.prompt_storage,
+            degraded_stages: stage_failures,
+            output_moderation: output_moderation.clone(),
+            output_moderation_chunks,
+            translated_output_moderation: translated_output_moderation.clone(),
+            output_analysis: output_analysis.as_ref().map(OutputAnalysis::summary),
+            decisive_step: evidence.decisive_step,
+            policy_rule: evidence.policy_rule.clone(),
+            bias_mitigation_hints: bias.mitigation_hints.clone(),
+            risk_score: Some(risk_score),
+            risk_inputs: Some(risk_inputs),
+            firewall_miss: firewall_miss.is_some(),
+            firewall_overblock,
+            language_mix_detected: evidence.language_mix_detected,
+            mixed_languages: evidence.mixed_languages.clone(),
+            exchange_validation: provided_output.map(|output| ExchangeValidation {
+                prompt_hash: content_ref(&original_prompt),
+                generated_text_hash: content_ref(&output),
+            }),
+            generation_parameters: generation.as_ref().and_then(|g| g.parameters.clone()),
+            generation_input_digest: generation.and_then(|g| g.input_digest),
+            template,
+            input_truncation: firewall.truncation,
+            stage_statuses: Some(stages.statuses()),
+            threat_category,
+            llm_judge,
+            monitored_firewall_matches: evidence.monitored_firewall_matches.clone(),
+            firewall_urls: firewall
+                .urls
+                .iter()
+                .map(|url| ExtractedUrl {
+                    url: stored_prompt(&url.url),
+                    ..url.clone()
+                })
+                .collect(),
+        };
+        let proof = match kind {
+            RunKind::SelfTest {
+                record_audit: false,
+            } => AuditProof {
+                algorithm: "none".to_owned(),
+                record_hash: String::new(),
+                chain_hash: String::new(),
+            },
+            _ => {
+                self.audit_logger
+                    .log_event(event)
+                    .instrument(stage_span("audit"))
+                    .await?
+            }
+        };
 
         Ok(ComplianceResponse {
             correlation_id,
-            status: final_status,
+            status: verdict.status,
+            risk_score,
             firewall,
-            semantic,
+            semantic: stages.semantic.ran().cloned(),
             bias,
-            input_moderation: Some(input_moderation),
-            output_moderation: Some(output_moderation),
-            generated_text: Some(generated_text),
+            input_moderation: stages.input_moderation.ran().cloned(),
+            output_moderation: stages.output_moderation.ran().cloned(),
+            stages,
+            translated_output_moderation,
+            output_analysis,
+            generated_text: verdict.generated_text,
+            refusal_message,
             audit_proof: proof,
             decision_evidence: Some(evidence),
             eu_compliance: Some(eu_compliance),
+            decision_trace: trace,
+            demo_mode: self.demo_mode,
+        })
+    }
+}
+
+/// What the caller sent along with the prompt
+struct CallerOptions {
+    suggest_rewrite: bool,
+    session_id: Option<String>,
+    /// Fingerprint of the request as the caller sent it
+    request_fingerprint: String,
+    /// Answer a block with a refusal message
+    refusal: bool,
+    /// Response the caller generated, checked instead of generating one
+    provided_output: Option<String>,
+}
+
+/// Why a request is being processed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunKind {
+    Live,
+    SelfTest { record_audit: bool },
+}
+
+/// Intermediate results accumulated while a request moves through the pipeline
+struct WorkflowRun {
+    kind: RunKind,
+    correlation_id: String,
+    session_id: Option<String>,
+    original_prompt: String,
+    /// The prompt the stages check, once preprocessing has rewritten it
+    prompt: String,
+    /// English rendering of the prompt, when it needed and got one
+    english_prompt: Option<String>,
+    /// Fingerprint of the request as the caller sent it
+    request_fingerprint: String,
+    /// The caller asked for a debiased rephrasing of a biased prompt
+    suggest_rewrite: bool,
+    /// A block is answered with a refusal message
+    refusal: bool,
+    /// Response the caller generated, checked instead of generating one
+    provided_output: Option<String>,
+    original_language: String,
+    /// Rule set versions captured before the firewall ran
+    config_fingerprint: ConfigFingerprint,
+    preprocessing: Vec<AppliedTransform>,
+    firewall: PromptFirewallResult,
+    /// Exemptions used up by the firewall stage
+    applied_exemptions: Vec<AppliedExemption>,
+    eu_compliance: EuComplianceResult,
+    bias: BiasScanResult,
+    semantic: Option<SemanticScanResult>,
+    /// Set when the semantic scan was deliberately not run
+    semantic_skipped_reason: Option<String>,
+    /// Set when moderation is disabled and so never runs
+    moderation_skipped_reason: Option<String>,
+    /// Stages an administrator had paused when the request started
+    disabled_stages: Vec<ToggleableStage>,
+    input_moderation: Option<ModerationResponse>,
+    /// Checks run on the generated answer, once there is one
+    output_analysis: Option<OutputAnalysis>,
+    generation: Option<GenerationRecord>,
+    /// Repeat-offender settings captured when the request started
+    repeat_config: RepeatOffenderConfig,
+    /// Present when repeat-offender tracking is enabled
+    repeat_fingerprint: Option<PromptFingerprint>,
+    repeat_match: Option<RepeatMatch>,
+    /// Set when a repeat match raised the semantic risk
+    repeat_bonus: Option<RepeatMatch>,
+    /// Set when the session's earlier sanitized prompts made this one a probing attempt
+    sanitize_probing: Option<ProbingEscalation>,
+    /// Moderation of the content sanitization stripped out, when it was checked
+    removed_content_moderation: Option<ModerationResponse>,
+    /// The judge's answer, when the prompt was in the gray zone
+    llm_judge: Option<JudgeEvidence>,
+    /// Stages that failed so far, whatever their policy
+    stage_failures: Vec<StageFailure>,
+    /// What the decision policy is evaluated against, filled in as stages finish
+    policy_evidence: PolicyEvidence,
+    trace: Vec<TraceStep>,
+    /// Set when the request filled in a registered template
+    template: Option<ResolvedTemplate>,
+}
+
+impl WorkflowRun {
+    /// Append a step to the trace and return its index
+    fn record(&mut self, step: TraceStep) -> usize {
+        self.trace.push(step);
+        self.trace.len() - 1
+    }
+
+    fn is_disabled(&self, stage: ToggleableStage) -> bool {
+        self.disabled_stages.contains(&stage)
+    }
+
+    /// Why a moderation pass does not run: moderation is not configured, or an
+    /// administrator paused the pass
+    fn moderation_skip(&self, stage: ToggleableStage) -> Option<String> {
+        self.moderation_skipped_reason.clone().or_else(|| {
+            self.is_disabled(stage)
+                .then(|| DISABLED_BY_ADMIN.to_owned())
         })
     }
+
+    /// What happened to each stage, read off the results, trace steps, skip reasons and
+    /// failures the run collected
+    fn stage_outcomes(&self) -> StageOutcomes {
+        let reached = |stage: &str| self.trace.iter().any(|step| step.stage == stage);
+        let generation_step = self.trace.iter().find(|step| step.stage == "generation");
+        let failures = &self.stage_failures;
+
+        // A failed bias stage still falls back to a result, which the outcome leaves out
+        let bias_failure = failures
+            .iter()
+            .find(|failure| failure.stage == PipelineStage::Bias);
+        let bias = match bias_failure {
+            _ if self.is_disabled(ToggleableStage::Bias) => StageOutcome::Skipped {
+                reason: DISABLED_BY_ADMIN.to_owned(),
+            },
+            Some(failure) => StageOutcome::Failed {
+                error: failure.error.clone(),
+            },
+            None => StageOutcome::Ran {
+                result: self.bias.clone(),
+            },
+        };
+        let semantic = StageEvidence {
+            result: self.semantic.clone(),
+            reached: reached("semantic"),
+            skipped_reason: self.semantic_skipped_reason.clone(),
+        }
+        .outcome(failures, PipelineStage::Semantic, false);
+        let input_moderation = StageEvidence {
+            result: self.input_moderation.clone(),
+            reached: reached("input_moderation"),
+            skipped_reason: self.moderation_skip(ToggleableStage::InputModeration),
+        }
+        .outcome(failures, PipelineStage::Moderation, false);
+        let output_moderation = StageEvidence {
+            result: self
+                .output_analysis
+                .as_ref()
+                .and_then(|analysis| analysis.moderation.clone()),
+            reached: reached("output_moderation"),
+            skipped_reason: self.moderation_skip(ToggleableStage::OutputModeration),
+        }
+        .outcome(failures, PipelineStage::Moderation, true);
+        // Generation failures end the request with an error, so the stage either produced
+        // an answer or was skipped, naming why in its trace step
+        let generation = match (generation_step, &self.generation) {
+            (None, _) => StageOutcome::NotReached,
+            (Some(step), _) if step.verdict == "skip" => StageOutcome::Skipped {
+                reason: step.rule_refs.first().cloned().unwrap_or_default(),
+            },
+            (Some(_), Some(generation)) => StageOutcome::Ran {
+                result: GenerationOutcome {
+                    model: generation.model.clone().unwrap_or_default(),
+                    tokens_used: generation.tokens_used,
+                    latency_ms: generation.latency_ms,
+                    translated: generation.was_translated,
+                },
+            },
+            (Some(_), None) => StageOutcome::NotReached,
+        };
+        StageOutcomes {
+            semantic,
+            bias,
+            input_moderation,
+            output_moderation,
+            generation,
+        }
+    }
+
+    /// The prompt was detected as English, so no stage translates it
+    fn is_english(&self) -> bool {
+        self.original_language.eq_ignore_ascii_case("english")
+    }
+
+    /// The content sanitization stripped from the prompt
+    fn removed_fragments(&self) -> Vec<String> {
+        self.firewall
+            .sanitization_edits
+            .iter()
+            .map(|edit| edit.removed.clone())
+            .collect()
+    }
+
+    /// Language of the prompt the firewall evaluated, which is English once translated
+    fn firewall_language(&self) -> &str {
+        if self.firewall.translated {
+            "English"
+        } else {
+            &self.original_language
+        }
+    }
+}
+
+struct GenerationRecord {
+    /// `None` for a response the caller provided
+    model: Option<String>,
+    english_output: String,
+    tokens_used: Option<u32>,
+    latency_ms: Option<u64>,
+    was_translated: bool,
+    /// `None` for a response the caller provided
+    input_digest: Option<GenerationInputDigest>,
+    /// `None` for a response the caller provided
+    parameters: Option<GenerationParameters>,
+}
+
+/// Outcome of the policy combiner for a run
+struct Verdict {
+    status: WorkflowStatus,
+    final_reason: DecisionReason,
+    decisive_step: Option<usize>,
+    policy_rule: Option<String>,
+    moderation_categories: Vec<ModerationCategory>,
+    moderation_scope: Option<String>,
+    generated_text: Option<String>,
+}
+
+impl Verdict {
+    fn blocked(status: WorkflowStatus, final_reason: DecisionReason, decisive_step: usize) -> Self {
+        Self {
+            status,
+            final_reason,
+            decisive_step: Some(decisive_step),
+            policy_rule: None,
+            moderation_categories: vec![],
+            moderation_scope: None,
+            generated_text: None,
+        }
+    }
+
+    fn with_moderation<'a>(
+        mut self,
+        categories: Vec<ModerationCategory>,
+        scope: impl Into<Option<&'a str>>,
+    ) -> Self {
+        self.moderation_categories = categories;
+        self.moderation_scope = scope.into().map(str::to_owned);
+        self
+    }
+}
+
+/// Which text an output moderation pass checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputVariant {
+    English,
+    Translated,
+}
+
+impl OutputVariant {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::English => "english",
+            Self::Translated => "translated",
+        }
+    }
+}
+
+/// Verdict for a request `rule` decided, with the reason params and decisive step
+/// taken from the evidence behind it
+fn policy_verdict(
+    run: &mut WorkflowRun,
+    rule: &PolicyRule,
+    generated_text: Option<String>,
+) -> Verdict {
+    let mut moderation: Option<(Vec<ModerationCategory>, &str)> = None;
+    let mut field = rule.last_field();
+    let final_reason = match rule.reason_code {
+        ReasonCode::EuProhibitedPractice => {
+            field = Some(PolicyField::EuRiskTier);
+            eu_block_reason(&run.eu_compliance)
+        }
+        ReasonCode::FirewallRuleMatch | ReasonCode::ExcessiveSanitization => {
+            field = Some(PolicyField::FirewallAction);
+            firewall_block_reason(&run.firewall)
+        }
+        ReasonCode::Sanitized => {
+            field = Some(PolicyField::FirewallAction);
+            DecisionReason::new(ReasonCode::Sanitized)
+        }
+        ReasonCode::RepeatOfBlockedPrompt => {
+            field = Some(PolicyField::RepeatAction);
+            let reason = DecisionReason::new(ReasonCode::RepeatOfBlockedPrompt);
+            match &run.repeat_match {
+                Some(repeat) => reason
+                    .with("correlation_id", repeat.correlation_id.clone())
+                    .with_score("similarity", repeat.similarity),
+                None => reason,
+            }
+        }
+        ReasonCode::SanitizerProbing => {
+            field = Some(PolicyField::SanitizeProbing);
+            let reason = DecisionReason::new(ReasonCode::SanitizerProbing);
+            match &run.sanitize_probing {
+                Some(escalation) => reason
+                    .with("session_id", escalation.session_id.clone())
+                    .with(
+                        "prior_correlation_ids",
+                        escalation.prior_correlation_ids.clone(),
+                    ),
+                None => reason,
+            }
+        }
+        ReasonCode::SemanticSimilarity => {
+            field = Some(PolicyField::SemanticLevel);
+            match (&run.semantic, &run.repeat_bonus) {
+                (Some(sem), Some(repeat)) => semantic_block_reason(sem)
+                    .with_score("raised_score", sem.risk_score)
+                    .with("repeat_correlation_id", repeat.correlation_id.clone()),
+                (Some(sem), None) => semantic_block_reason(sem),
+                (None, _) => DecisionReason::new(ReasonCode::SemanticSimilarity),
+            }
+        }
+        ReasonCode::ElevatedSemanticRisk => {
+            field = Some(PolicyField::SemanticLevel);
+            DecisionReason::new(ReasonCode::ElevatedSemanticRisk).with_score(
+                "score",
+                run.semantic.as_ref().map(|s| s.similarity).unwrap_or(0.0),
+            )
+        }
+        ReasonCode::InputModerationFlag => {
+            field = Some(PolicyField::ModerationFlagged);
+            let reason = DecisionReason::new(ReasonCode::InputModerationFlag);
+            match &run.input_moderation {
+                Some(input) => {
+                    moderation = Some((input.flagged_categories(), "sanitized"));
+                    reason.with("categories", input.categories.clone())
+                }
+                None => reason,
+            }
+        }
+        ReasonCode::RemovedContentModerationFlag => {
+            field = Some(PolicyField::RemovedContentFlagged);
+            let reason = DecisionReason::new(ReasonCode::RemovedContentModerationFlag);
+            match run.removed_content_moderation.take() {
+                Some(removed) => {
+                    moderation = Some((removed.flagged_categories(), "removed_content"));
+                    let reason = reason.with("categories", removed.categories.clone());
+                    // The flagged pass is the one the audit record keeps
+                    run.input_moderation = Some(removed);
+                    reason
+                }
+                None => reason,
+            }
+        }
+        ReasonCode::OutputModerationFlag => {
+            let analysis = run.output_analysis.as_ref();
+            let (variant, output) = if field == Some(PolicyField::TranslatedOutputModerationFlagged)
+            {
+                (
+                    OutputVariant::Translated,
+                    analysis.and_then(|a| a.translated_moderation.as_ref()),
+                )
+            } else {
+                field = Some(PolicyField::OutputModerationFlagged);
+                (
+                    OutputVariant::English,
+                    analysis.and_then(|a| a.moderation.as_ref()),
+                )
+            };
+            let reason = DecisionReason::new(ReasonCode::OutputModerationFlag);
+            let reason = match output {
+                Some(output) => {
+                    moderation = Some((output.flagged_categories(), variant.as_str()));
+                    reason.with("categories", output.categories.clone())
+                }
+                None => reason,
+            };
+            reason.with("variant", variant.as_str())
+        }
+        ReasonCode::OutputFirewallEcho => {
+            field = Some(PolicyField::OutputFirewallEcho);
+            let echoed = run
+                .output_analysis
+                .as_ref()
+                .map(OutputAnalysis::echoed_rules)
+                .unwrap_or_default();
+            DecisionReason::new(ReasonCode::OutputFirewallEcho).with("rule_ids", echoed)
+        }
+        ReasonCode::AllChecksPassed => {
+            field = None;
+            DecisionReason::new(ReasonCode::AllChecksPassed)
+        }
+        code => DecisionReason::new(code).with("rule_id", rule.id.clone()),
+    };
+    let decisive_step = field.and_then(|field| {
+        run.trace
+            .iter()
+            .rposition(|step| step.stage == field.stage())
+    });
+    let status = rule.status();
+    let blocked = !matches!(
+        status,
+        WorkflowStatus::Completed | WorkflowStatus::Sanitized
+    );
+    let verdict = Verdict {
+        status,
+        final_reason,
+        decisive_step,
+        policy_rule: Some(rule.id.clone()),
+        moderation_categories: vec![],
+        moderation_scope: None,
+        generated_text: if blocked { None } else { generated_text },
+    };
+    match moderation {
+        Some((categories, scope)) if blocked => verdict.with_moderation(categories, scope),
+        _ => verdict,
+    }
+}
+
+/// Block on the first failed stage whose policy is `closed`, if any
+///
+/// Closed failures are acted on at the next checkpoint, so at most one is ever recorded.
+fn stage_failure_verdict(run: &mut WorkflowRun) -> Option<Verdict> {
+    let failure = run
+        .stage_failures
+        .iter()
+        .find(|failure| failure.policy == FailureMode::Closed)?
+        .clone();
+    let step = run.record(TraceStep {
+        stage: "stage_failure".to_owned(),
+        inputs: Vec::new(),
+        verdict: "block".to_owned(),
+        rule_refs: vec![failure.stage.as_str().to_owned()],
+        parameters: BTreeMap::new(),
+        duration_ms: 0,
+    });
+    Some(Verdict::blocked(
+        WorkflowStatus::BlockedByStageFailure {
+            stage: failure.stage,
+        },
+        stage_failure_reason(&failure),
+        step,
+    ))
+}
+
+fn stage_failure_reason(failure: &StageFailure) -> DecisionReason {
+    DecisionReason::new(ReasonCode::StageFailure)
+        .with("stage", failure.stage.to_string())
+        .with("error", failure.error.clone())
+}
+
+fn eu_block_reason(eu_compliance: &EuComplianceResult) -> DecisionReason {
+    let reason = DecisionReason::new(ReasonCode::EuProhibitedPractice);
+    match eu_compliance.findings.first() {
+        Some(finding) => reason.with("finding", finding.detail.clone()),
+        None => reason,
+    }
+}
+
+fn firewall_block_reason(firewall: &PromptFirewallResult) -> DecisionReason {
+    let excessive = firewall
+        .matched_rules
+        .iter()
+        .any(|id| id == SANITIZE_LIMIT_RULE_ID);
+    if excessive {
+        let rule_ids: Vec<String> = firewall
+            .matched_rules
+            .iter()
+            .filter(|id| *id != SANITIZE_LIMIT_RULE_ID)
+            .cloned()
+            .collect();
+        return DecisionReason::new(ReasonCode::ExcessiveSanitization).with("rule_ids", rule_ids);
+    }
+    DecisionReason::new(ReasonCode::FirewallRuleMatch)
+        .with("rule_ids", firewall.matched_rules.clone())
+}
+
+fn semantic_block_reason(semantic: &SemanticScanResult) -> DecisionReason {
+    let reason = DecisionReason::new(ReasonCode::SemanticSimilarity);
+    let reason = match &semantic.nearest_template_id {
+        Some(template_id) => reason.with("template_id", template_id.clone()),
+        None => reason,
+    };
+    let reason = match &semantic.category {
+        Some(category) => reason.with("category", category.clone()),
+        None => reason,
+    };
+    reason.with_score("score", semantic.similarity)
+}
+
+/// Trace step for a moderation call; `None` means the call failed and was skipped
+/// `skipped_reason` goes into the rule refs of a pass that deliberately did not run
+fn moderation_step(
+    stage: &str,
+    input_ref: String,
+    moderation: Option<&ModerationResponse>,
+    skipped_reason: Option<&str>,
+    duration_ms: u64,
+) -> TraceStep {
+    TraceStep {
+        stage: stage.to_owned(),
+        inputs: vec![input_ref],
+        verdict: match moderation {
+            Some(moderation) if moderation.flagged => "block",
+            Some(_) => "allow",
+            None => "skip",
+        }
+        .to_owned(),
+        rule_refs: moderation
+            .map(|moderation| moderation.categories.clone())
+            .or_else(|| skipped_reason.map(|reason| vec![reason.to_owned()]))
+            .unwrap_or_default(),
+        parameters: BTreeMap::new(),
+        duration_ms,
+    }
+}
+
+/// Combine per-fragment moderation results: flagged if any fragment is, with every
+/// flagged category and the highest severity and score seen for each
+fn merge_moderation(responses: Vec<ModerationResponse>) -> ModerationResponse {
+    let mut merged = ModerationResponse::default();
+    for response in responses {
+        merged.flagged |= response.flagged;
+        merged.severity = merged.severity.max(response.severity);
+        for category in response.categories {
+            if !merged.categories.contains(&category) {
+                merged.categories.push(category);
+            }
+        }
+        for (target, source) in [
+            (&mut merged.category_scores, response.category_scores),
+            (
+                &mut merged.category_severities,
+                response.category_severities,
+            ),
+        ] {
+            for (category, value) in source {
+                let entry = target.entry(category).or_insert(value);
+                *entry = entry.max(value);
+            }
+        }
+    }
+    merged
+}
+
+/// Reference to a piece of text by content hash so traces never carry raw prompts
+fn content_ref(text: &str) -> String {
+    format!("sha256:{}", hash_record(text))
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+async fn timed<T>(future: impl Future<Output = T>) -> (T, u64) {
+    let start = Instant::now();
+    let output = future.await;
+    (output, elapsed_ms(start))
 }
 
 #[derive(Debug, Error)]
@@ -742,4 +2704,19 @@ pub enum WorkflowError {
     Mistral(#[from] MistralServiceError),
     #[error("audit workflow failure: {0}")]
     Audit(#[from] AuditError),
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+    /// The caller went away; see [`ComplianceEngine::process_cancellable`]
+    #[error("request cancelled by the client")]
+    Cancelled,
+}
+
+impl WorkflowError {
+    /// Back-off hint when the request failed only because Mistral calls were saturated
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Mistral(error) => error.retry_after(),
+            Self::Audit(_) | Self::Template(_) | Self::Cancelled => None,
+        }
+    }
 }
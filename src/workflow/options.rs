@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::modules::prompt_firewall::dtos::LengthOverflowPolicy;
+use crate::modules::sanitize_probing::dtos::MAX_SESSION_ID_LENGTH;
+
+use super::{
+    ComplianceEngine, ComplianceRequest, PipelineStage, ResponseProfile, TemplateError,
+    WorkflowStatus,
+};
+
+/// Characters a caller-supplied correlation id may contain
+const CORRELATION_ID_CHARACTERS: &str = "ASCII letters, digits and -_.:";
+
+/// One field of a request that failed validation
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FieldViolation {
+    pub field: String,
+    /// Stable machine-readable cause: `too_long`, `invalid_format`, `conflicting_fields`,
+    /// `unknown_template` or `slot_mismatch`
+    pub code: String,
+    pub message: String,
+    /// The bound the value broke, for length violations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// The value's length, for length violations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<usize>,
+}
+
+/// Body of the answer to a request rejected before it reached the workflow
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RequestValidationError {
+    /// Human-readable summary
+    pub error: String,
+    /// `validation_failed` when fields broke the contract, `invalid_body` when the body
+    /// could not be read as a request at all
+    pub code: String,
+    /// Every rejected field; empty for `invalid_body`
+    #[serde(default)]
+    pub violations: Vec<FieldViolation>,
+}
+
+impl RequestValidationError {
+    pub fn new(violations: Vec<FieldViolation>) -> Self {
+        let error = violations
+            .iter()
+            .map(|violation| violation.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self {
+            error,
+            code: "validation_failed".to_owned(),
+            violations,
+        }
+    }
+
+    pub fn invalid_body(message: impl Into<String>) -> Self {
+        Self {
+            error: message.into(),
+            code: "invalid_body".to_owned(),
+            violations: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for RequestValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.error)
+    }
+}
+
+impl std::error::Error for RequestValidationError {}
+
+/// Machine-readable contract of `POST /api/compliance/check`
+///
+/// Built from the engine's live configuration, so it follows runtime changes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ComplianceOptions {
+    /// See [`API_VERSION`](super::API_VERSION)
+    pub api_version: u32,
+    /// Longest prompt accepted, in bytes of UTF-8
+    pub max_prompt_length: usize,
+    /// Body fields a request must carry
+    pub required_fields: Vec<String>,
+    pub correlation_id: CorrelationIdOptions,
+    /// Optional per-request settings and the values they take
+    pub overrides: Vec<RequestOverride>,
+    /// Every status a response can carry, as it appears in the `status` field
+    pub statuses: Vec<WorkflowStatus>,
+    /// Values of the `profile` query parameter
+    pub response_profiles: Vec<ResponseProfile>,
+}
+
+/// Correlation ids a request body may supply; others are rejected
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CorrelationIdOptions {
+    pub max_length: usize,
+    pub allowed_characters: String,
+    /// An id must start with one of these; empty accepts any prefix
+    pub allowed_prefixes: Vec<String>,
+}
+
+/// A per-request setting of the compliance check
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RequestOverride {
+    pub name: String,
+    /// `body` or `query`
+    pub location: String,
+    pub values: Vec<Value>,
+    pub default: Value,
+    pub description: String,
+}
+
+impl WorkflowStatus {
+    /// Every status, with one `BlockedByStageFailure` per stage
+    pub fn all() -> Vec<Self> {
+        // Fails to compile when a status is added, until it is listed below
+        let _listed = |status: &Self| match status {
+            Self::Completed
+            | Self::BlockedByFirewall
+            | Self::BlockedBySemantic
+            | Self::BlockedByInputModeration
+            | Self::BlockedByOutputModeration
+            | Self::BlockedByEuCompliance
+            | Self::BlockedByStageFailure { .. }
+            | Self::Sanitized => {}
+        };
+        let mut statuses = vec![
+            Self::Completed,
+            Self::Sanitized,
+            Self::BlockedByFirewall,
+            Self::BlockedBySemantic,
+            Self::BlockedByInputModeration,
+            Self::BlockedByOutputModeration,
+            Self::BlockedByEuCompliance,
+        ];
+        statuses.extend(
+            PipelineStage::ALL
+                .into_iter()
+                .map(|stage| Self::BlockedByStageFailure { stage }),
+        );
+        statuses
+    }
+}
+
+impl ResponseProfile {
+    pub const ALL: [Self; 2] = [Self::Standard, Self::Full];
+}
+
+impl ComplianceEngine {
+    /// Check a request against the limits [`Self::options`] reports
+    ///
+    /// Callers that take requests from outside, like the HTTP API, run this first so
+    /// a malformed request is answered without auditing it or calling Mistral.
+    pub fn validate_request(
+        &self,
+        request: &ComplianceRequest,
+    ) -> Result<(), RequestValidationError> {
+        let mut violations = Vec::new();
+        let max_prompt_length = self.firewall_service.max_input_length();
+        let prompt_length = match &request.template {
+            Some(_) if !request.prompt.is_empty() => {
+                violations.push(FieldViolation {
+                    field: "prompt".to_owned(),
+                    code: "conflicting_fields".to_owned(),
+                    message: "send either a prompt or a template_id with variables, not both"
+                        .to_owned(),
+                    limit: None,
+                    actual: None,
+                });
+                None
+            }
+            Some(invocation) => match self.resolve_template(invocation) {
+                Ok(resolved) => Some(resolved.reconstruct().len()),
+                Err(e) => {
+                    violations.push(FieldViolation {
+                        field: match e {
+                            TemplateError::Unknown(_) => "template_id",
+                            _ => "variables",
+                        }
+                        .to_owned(),
+                        code: match e {
+                            TemplateError::Unknown(_) => "unknown_template",
+                            _ => "slot_mismatch",
+                        }
+                        .to_owned(),
+                        message: e.to_string(),
+                        limit: None,
+                        actual: None,
+                    });
+                    None
+                }
+            },
+            None => Some(request.prompt.len()),
+        };
+        // Under a truncating policy the firewall cuts long prompts down instead
+        if let Some(length) = prompt_length
+            && length > max_prompt_length
+            && self.firewall_service.length_overflow_policy() == LengthOverflowPolicy::Block
+        {
+            violations.push(FieldViolation {
+                field: "prompt".to_owned(),
+                code: "too_long".to_owned(),
+                message: format!(
+                    "prompt is {length} bytes, longer than the limit of {max_prompt_length}"
+                ),
+                limit: Some(max_prompt_length),
+                actual: Some(length),
+            });
+        }
+        if let Some(id) = &request.correlation_id
+            && !self.correlation_ids.accepts(id)
+        {
+            let max_length = self.correlation_ids.max_length;
+            let too_long = id.len() > max_length;
+            violations.push(FieldViolation {
+                field: "correlation_id".to_owned(),
+                code: if too_long {
+                    "too_long"
+                } else {
+                    "invalid_format"
+                }
+                .to_owned(),
+                message: if too_long {
+                    format!(
+                        "correlation_id is {} bytes, longer than the limit of {max_length}",
+                        id.len()
+                    )
+                } else {
+                    "correlation_id must be a plain token accepted by the correlation id policy"
+                        .to_owned()
+                },
+                limit: too_long.then_some(max_length),
+                actual: too_long.then_some(id.len()),
+            });
+        }
+        if let Some(session_id) = &request.session_id
+            && session_id.len() > MAX_SESSION_ID_LENGTH
+        {
+            violations.push(FieldViolation {
+                field: "session_id".to_owned(),
+                code: "too_long".to_owned(),
+                message: format!(
+                    "session_id is {} bytes, longer than the limit of {MAX_SESSION_ID_LENGTH}",
+                    session_id.len()
+                ),
+                limit: Some(MAX_SESSION_ID_LENGTH),
+                actual: Some(session_id.len()),
+            });
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(RequestValidationError::new(violations))
+        }
+    }
+
+    /// What a compliance request may contain and what can come back
+    pub fn options(&self) -> ComplianceOptions {
+        ComplianceOptions {
+            api_version: super::API_VERSION,
+            max_prompt_length: self.firewall_service.max_input_length(),
+            required_fields: vec!["prompt".to_owned()],
+            correlation_id: CorrelationIdOptions {
+                max_length: self.correlation_ids.max_length,
+                allowed_characters: CORRELATION_ID_CHARACTERS.to_owned(),
+                allowed_prefixes: self.correlation_ids.allowed_prefixes.clone(),
+            },
+            overrides: vec![
+                RequestOverride {
+                    name: "suggest_rewrite".to_owned(),
+                    location: "body".to_owned(),
+                    values: vec![json!(false), json!(true)],
+                    default: json!(false),
+                    description: "Return a debiased rephrasing of a biased prompt".to_owned(),
+                },
+                RequestOverride {
+                    name: "deterministic".to_owned(),
+                    location: "body".to_owned(),
+                    values: vec![json!(false), json!(true)],
+                    default: json!(self.deterministic_by_default),
+                    description: "Generate with temperature 0 and a fixed seed so the answer \
+                                  can be reproduced"
+                        .to_owned(),
+                },
+                RequestOverride {
+                    name: "profile".to_owned(),
+                    location: "query".to_owned(),
+                    values: ResponseProfile::ALL.iter().map(|p| json!(p)).collect(),
+                    default: json!(ResponseProfile::default()),
+                    description: "How much detail the response carries".to_owned(),
+                },
+                RequestOverride {
+                    name: "refusal".to_owned(),
+                    location: "query".to_owned(),
+                    values: vec![json!(false), json!(true)],
+                    default: json!(self.refusals.enabled),
+                    description: "Answer a blocked request with a refusal message to show \
+                                  the user"
+                        .to_owned(),
+                },
+            ],
+            statuses: WorkflowStatus::all(),
+            response_profiles: ResponseProfile::ALL.to_vec(),
+        }
+    }
+}
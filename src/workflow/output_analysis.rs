@@ -0,0 +1,117 @@
+//! Checks run on the generated answer, gathered in one report the decision policy reads
+
+use serde::{Deserialize, Serialize};
+
+use super::OutputModerationChunks;
+use super::policy::{PolicyEvidence, PolicyField};
+use crate::firewall_core::dtos::MatchedBlockRule;
+use crate::modules::bias_detection::dtos::BiasScanResult;
+use crate::modules::mistral_ai::dtos::ModerationResponse;
+use crate::modules::semantic_detection::dtos::SemanticScanResult;
+
+/// Which checks beyond moderation run on generated answers
+///
+/// Moderation follows the moderation settings and stage toggles. The other checks cost a
+/// scan each, and the semantic one an embedding call, so all are off by default.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OutputAnalysisConfig {
+    /// Scan the English answer for bias
+    pub bias: bool,
+    /// Match the English answer against the firewall's block rules
+    pub firewall_echo: bool,
+    /// Compare the English answer with the attack template bank
+    pub semantic: bool,
+}
+
+/// What the checks found in a generated answer
+///
+/// Every output-related decision is taken from this report: each check adds its policy
+/// evidence through [`OutputAnalysis::record_evidence`], and the decision policy blocks
+/// on it like on any other stage.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct OutputAnalysis {
+    /// Moderation of the English answer; `None` when moderation did not run
+    pub moderation: Option<ModerationResponse>,
+    /// How the English answer was split for moderation, when it was too long for one call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation_chunks: Option<OutputModerationChunks>,
+    /// Moderation of the translated answer, when a second pass ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translated_moderation: Option<ModerationResponse>,
+    /// Bias scan of the English answer, when enabled
+    pub bias: Option<BiasScanResult>,
+    /// Block rules the English answer repeats, when enabled; empty when it repeats none
+    pub firewall_echo: Option<Vec<MatchedBlockRule>>,
+    /// Semantic scan of the English answer, when enabled and the scan succeeded
+    pub semantic: Option<SemanticScanResult>,
+}
+
+impl OutputAnalysis {
+    /// Set the policy fields of every check that has run
+    pub(crate) fn record_evidence(&self, evidence: &mut PolicyEvidence) {
+        if let Some(moderation) = &self.moderation {
+            evidence.set(PolicyField::OutputModerationFlagged, moderation.flagged);
+        }
+        if let Some(bias) = &self.bias {
+            evidence.set(
+                PolicyField::OutputBiasLevel,
+                super::serialized_name(&bias.level).as_str(),
+            );
+        }
+        if let Some(echo) = &self.firewall_echo {
+            evidence.set(PolicyField::OutputFirewallEcho, !echo.is_empty());
+        }
+        if let Some(semantic) = &self.semantic {
+            evidence.set(
+                PolicyField::OutputSemanticLevel,
+                super::serialized_name(&semantic.risk_level).as_str(),
+            );
+        }
+        if let Some(moderation) = &self.translated_moderation {
+            evidence.set(
+                PolicyField::TranslatedOutputModerationFlagged,
+                moderation.flagged,
+            );
+        }
+    }
+
+    /// Ids of the block rules the answer repeats
+    pub fn echoed_rules(&self) -> Vec<String> {
+        self.firewall_echo
+            .iter()
+            .flatten()
+            .map(|matched| matched.id.clone())
+            .collect()
+    }
+
+    /// The verdict of each check, without the texts and scores behind it
+    pub fn summary(&self) -> OutputAnalysisSummary {
+        OutputAnalysisSummary {
+            moderation_flagged: self.moderation.as_ref().map(|m| m.flagged),
+            translated_moderation_flagged: self.translated_moderation.as_ref().map(|m| m.flagged),
+            bias_level: self
+                .bias
+                .as_ref()
+                .map(|bias| super::serialized_name(&bias.level)),
+            firewall_echo_rules: self.firewall_echo.as_ref().map(|_| self.echoed_rules()),
+            semantic_level: self
+                .semantic
+                .as_ref()
+                .map(|semantic| super::serialized_name(&semantic.risk_level)),
+        }
+    }
+}
+
+/// [`OutputAnalysis`] as the audit record keeps it; `None` marks a check that did not run
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OutputAnalysisSummary {
+    pub moderation_flagged: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translated_moderation_flagged: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bias_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firewall_echo_rules: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic_level: Option<String>,
+}
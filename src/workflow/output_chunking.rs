@@ -0,0 +1,102 @@
+//! Moderating generated answers longer than the moderation endpoint accepts
+
+use serde::{Deserialize, Serialize};
+
+/// Splits long generated answers into overlapping chunks that are moderated separately
+///
+/// The moderation endpoint bounds its input; an answer past the bound is either refused
+/// or only partly checked, so a violation near its end could go unnoticed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OutputModerationChunking {
+    /// Answers up to this many characters are moderated in one call; longer ones in
+    /// chunks of this length
+    pub max_chars: usize,
+    /// Characters shared by consecutive chunks, so a violation straddling a boundary
+    /// is seen whole by one of them
+    pub overlap_chars: usize,
+    /// Chunks moderated at once when the provider does not take them in one call
+    pub max_concurrency: usize,
+}
+
+impl Default for OutputModerationChunking {
+    fn default() -> Self {
+        Self {
+            max_chars: 16_000,
+            overlap_chars: 500,
+            max_concurrency: 4,
+        }
+    }
+}
+
+impl OutputModerationChunking {
+    /// Reject an empty chunk, an overlap that would not advance, or zero concurrency
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_chars == 0 {
+            return Err("output moderation chunk length must be positive".to_owned());
+        }
+        if self.overlap_chars >= self.max_chars {
+            return Err(
+                "output moderation chunk overlap must be shorter than the chunk length".to_owned(),
+            );
+        }
+        if self.max_concurrency == 0 {
+            return Err("output moderation chunk concurrency must be positive".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Character ranges `[start, end)` to moderate for a text of `text_chars` characters
+    ///
+    /// Empty when the text fits in one call. The last chunk ends at the end of the text
+    /// and may be shorter than the others.
+    pub fn chunks(&self, text_chars: usize) -> Vec<(usize, usize)> {
+        if text_chars <= self.max_chars {
+            return Vec::new();
+        }
+        split(text_chars, self.max_chars, self.overlap_chars)
+    }
+
+    /// Character ranges for a text the provider refused as too large
+    ///
+    /// A provider limit below `max_chars` still gets chunks of at most half the text,
+    /// overlapping by no more than a quarter of a chunk.
+    pub fn fallback_chunks(&self, text_chars: usize) -> Vec<(usize, usize)> {
+        let chunk_chars = self.max_chars.min(text_chars.div_ceil(2));
+        split(
+            text_chars,
+            chunk_chars,
+            self.overlap_chars.min(chunk_chars / 4),
+        )
+    }
+}
+
+fn split(text_chars: usize, chunk_chars: usize, overlap_chars: usize) -> Vec<(usize, usize)> {
+    let step = chunk_chars.saturating_sub(overlap_chars).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + chunk_chars).min(text_chars);
+        chunks.push((start, end));
+        if end == text_chars {
+            return chunks;
+        }
+        start += step;
+    }
+}
+
+/// How a generated answer too long for one moderation call was moderated
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OutputModerationChunks {
+    /// Chunks the answer was split into
+    pub count: usize,
+    /// Zero-based positions of the chunks moderation flagged, in order
+    #[serde(default)]
+    pub flagged_chunks: Vec<usize>,
+    /// `[start, end)` character offsets of the flagged chunks, in the same order
+    #[serde(default)]
+    pub flagged_ranges: Vec<(usize, usize)>,
+    /// The provider refused the answer in one call as too large (HTTP 413), rather than
+    /// it exceeding the configured chunk length
+    #[serde(default)]
+    pub payload_too_large: bool,
+}
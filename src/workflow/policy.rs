@@ -0,0 +1,768 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ReasonCode, WorkflowStatus};
+
+/// Evidence field a policy rule can test, named as in the policy file
+///
+/// Fields are declared in pipeline order; a request is checked against the policy each
+/// time a group of them becomes known, see [`DecisionPolicy::decide`].
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PolicyField {
+    /// `minimal`, `limited`, `high` or `unacceptable`
+    #[serde(rename = "eu.risk_tier")]
+    EuRiskTier,
+    /// `allow`, `flag`, `sanitize` or `block`
+    #[serde(rename = "firewall.action")]
+    FirewallAction,
+    /// `low`, `medium` or `high`
+    #[serde(rename = "bias.level")]
+    BiasLevel,
+    /// Verdict of the repeat-offender check: `allow`, `flag`, or `block` for a close
+    /// variant of a blocked prompt under `REPEAT_OFFENDER_MODE=block`; absent when
+    /// tracking is off
+    #[serde(rename = "repeat.action")]
+    RepeatAction,
+    /// The session already sent enough sanitized prompts removing similar content;
+    /// absent unless sanitize probing detection is on and the sanitized request has a
+    /// session id
+    #[serde(rename = "sanitize_probing.escalated")]
+    SanitizeProbing,
+    /// `low`, `medium` or `high`, after any repeat-offender risk bonus; absent when the
+    /// scan did not run
+    #[serde(rename = "semantic.level")]
+    SemanticLevel,
+    /// Category of the nearest attack template, compared ignoring case
+    #[serde(rename = "semantic.category")]
+    SemanticCategory,
+    /// Input moderation flagged the sanitized prompt
+    #[serde(rename = "moderation.flagged")]
+    ModerationFlagged,
+    /// Moderation flagged content the firewall stripped out
+    #[serde(rename = "moderation.removed_content_flagged")]
+    RemovedContentFlagged,
+    /// Moderation flagged the English output
+    #[serde(rename = "output_moderation.flagged")]
+    OutputModerationFlagged,
+    /// `low`, `medium` or `high` for the English output; absent unless output bias
+    /// analysis is on
+    #[serde(rename = "output_bias.level")]
+    OutputBiasLevel,
+    /// The English output repeats a firewall block rule; absent unless firewall echo
+    /// analysis is on
+    #[serde(rename = "output_firewall.echo")]
+    OutputFirewallEcho,
+    /// `low`, `medium` or `high` for the English output; absent unless output semantic
+    /// analysis is on
+    #[serde(rename = "output_semantic.level")]
+    OutputSemanticLevel,
+    /// Moderation flagged the translated output
+    #[serde(rename = "translated_output_moderation.flagged")]
+    TranslatedOutputModerationFlagged,
+}
+
+impl PolicyField {
+    /// Values an enumerated field takes; `None` for flags and free text
+    fn allowed_values(self) -> Option<&'static [&'static str]> {
+        match self {
+            Self::EuRiskTier => Some(&["minimal", "limited", "high", "unacceptable"]),
+            Self::FirewallAction => Some(&["allow", "flag", "sanitize", "block"]),
+            Self::RepeatAction => Some(&["allow", "flag", "block"]),
+            Self::BiasLevel
+            | Self::SemanticLevel
+            | Self::OutputBiasLevel
+            | Self::OutputSemanticLevel => Some(&["low", "medium", "high"]),
+            _ => None,
+        }
+    }
+
+    fn is_flag(self) -> bool {
+        matches!(
+            self,
+            Self::SanitizeProbing
+                | Self::ModerationFlagged
+                | Self::RemovedContentFlagged
+                | Self::OutputModerationFlagged
+                | Self::OutputFirewallEcho
+                | Self::TranslatedOutputModerationFlagged
+        )
+    }
+
+    /// Checkpoint at which the field becomes known; the engine checks the policy once
+    /// per checkpoint, between the stages that produce these fields
+    fn checkpoint(self) -> u8 {
+        match self {
+            Self::EuRiskTier
+            | Self::FirewallAction
+            | Self::BiasLevel
+            | Self::RepeatAction
+            | Self::SanitizeProbing => 0,
+            Self::SemanticLevel | Self::SemanticCategory => 1,
+            Self::ModerationFlagged => 2,
+            Self::RemovedContentFlagged => 3,
+            Self::OutputModerationFlagged
+            | Self::OutputBiasLevel
+            | Self::OutputFirewallEcho
+            | Self::OutputSemanticLevel => 4,
+            Self::TranslatedOutputModerationFlagged => 5,
+        }
+    }
+
+    fn validate(self, value: &PolicyValue) -> Result<(), String> {
+        let name = self.as_str();
+        match (value, self.allowed_values()) {
+            (PolicyValue::Flag(_), _) if self.is_flag() => Ok(()),
+            (_, _) if self.is_flag() => Err(format!("{name} takes true or false")),
+            (PolicyValue::Text(text), Some(allowed)) if allowed.contains(&text.as_str()) => Ok(()),
+            (_, Some(allowed)) => Err(format!(
+                "{name} takes one of {}, got {value}",
+                allowed.join(", ")
+            )),
+            (PolicyValue::Text(text), None) if !text.trim().is_empty() => Ok(()),
+            (_, None) => Err(format!("{name} takes a non-empty string, got {value}")),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::EuRiskTier => "eu.risk_tier",
+            Self::FirewallAction => "firewall.action",
+            Self::BiasLevel => "bias.level",
+            Self::RepeatAction => "repeat.action",
+            Self::SanitizeProbing => "sanitize_probing.escalated",
+            Self::SemanticLevel => "semantic.level",
+            Self::SemanticCategory => "semantic.category",
+            Self::ModerationFlagged => "moderation.flagged",
+            Self::RemovedContentFlagged => "moderation.removed_content_flagged",
+            Self::OutputModerationFlagged => "output_moderation.flagged",
+            Self::OutputBiasLevel => "output_bias.level",
+            Self::OutputFirewallEcho => "output_firewall.echo",
+            Self::OutputSemanticLevel => "output_semantic.level",
+            Self::TranslatedOutputModerationFlagged => "translated_output_moderation.flagged",
+        }
+    }
+
+    /// Pipeline stage whose trace step produced the field
+    pub(crate) fn stage(self) -> &'static str {
+        match self {
+            Self::EuRiskTier => "eu_compliance",
+            Self::FirewallAction => "firewall",
+            Self::BiasLevel => "bias",
+            Self::RepeatAction => "repeat_offender",
+            Self::SanitizeProbing => "sanitize_probing",
+            Self::SemanticLevel | Self::SemanticCategory => "semantic",
+            Self::ModerationFlagged => "input_moderation",
+            Self::RemovedContentFlagged => "removed_content_moderation",
+            Self::OutputModerationFlagged => "output_moderation",
+            Self::OutputBiasLevel => "output_bias",
+            Self::OutputFirewallEcho => "output_firewall_echo",
+            Self::OutputSemanticLevel => "output_semantic",
+            Self::TranslatedOutputModerationFlagged => "translated_output_moderation",
+        }
+    }
+}
+
+/// Value of an evidence field
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PolicyValue {
+    Flag(bool),
+    Text(String),
+}
+
+impl PolicyValue {
+    fn matches(&self, actual: &PolicyValue) -> bool {
+        match (self, actual) {
+            (Self::Text(expected), Self::Text(actual)) => expected.eq_ignore_ascii_case(actual),
+            _ => self == actual,
+        }
+    }
+}
+
+impl std::fmt::Display for PolicyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Flag(flag) => write!(f, "{flag}"),
+            Self::Text(text) => write!(f, "{text:?}"),
+        }
+    }
+}
+
+impl From<bool> for PolicyValue {
+    fn from(flag: bool) -> Self {
+        Self::Flag(flag)
+    }
+}
+
+impl From<&str> for PolicyValue {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+/// What a condition accepts: one value, or any of a list
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Condition {
+    Is(PolicyValue),
+    AnyOf(Vec<PolicyValue>),
+}
+
+impl Condition {
+    fn values(&self) -> &[PolicyValue] {
+        match self {
+            Self::Is(value) => std::slice::from_ref(value),
+            Self::AnyOf(values) => values,
+        }
+    }
+}
+
+/// Decision a rule makes
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    Allow,
+    Sanitize,
+    Block,
+}
+
+/// Status a blocking rule gives the request
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockStatus {
+    BlockedByEuCompliance,
+    BlockedByFirewall,
+    BlockedBySemantic,
+    BlockedByInputModeration,
+    BlockedByOutputModeration,
+}
+
+impl From<BlockStatus> for WorkflowStatus {
+    fn from(status: BlockStatus) -> Self {
+        match status {
+            BlockStatus::BlockedByEuCompliance => Self::BlockedByEuCompliance,
+            BlockStatus::BlockedByFirewall => Self::BlockedByFirewall,
+            BlockStatus::BlockedBySemantic => Self::BlockedBySemantic,
+            BlockStatus::BlockedByInputModeration => Self::BlockedByInputModeration,
+            BlockStatus::BlockedByOutputModeration => Self::BlockedByOutputModeration,
+        }
+    }
+}
+
+/// One rule: when every condition holds, make the decision
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Recorded as `decision_evidence.policy_rule` when the rule decides a request
+    pub id: String,
+    /// Conditions that must all hold; a field the request has no value for never matches
+    #[serde(default)]
+    pub when: BTreeMap<PolicyField, Condition>,
+    pub then: PolicyAction,
+    /// Required for `block`, not allowed otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<BlockStatus>,
+    pub reason_code: ReasonCode,
+}
+
+impl PolicyRule {
+    fn matches(&self, evidence: &PolicyEvidence) -> bool {
+        self.when.iter().all(|(field, condition)| {
+            evidence.values.get(field).is_some_and(|actual| {
+                condition
+                    .values()
+                    .iter()
+                    .any(|expected| expected.matches(actual))
+            })
+        })
+    }
+
+    /// Field of the last stage the rule looks at
+    pub(crate) fn last_field(&self) -> Option<PolicyField> {
+        self.when.keys().next_back().copied()
+    }
+
+    /// Status of a request this rule decides
+    pub fn status(&self) -> WorkflowStatus {
+        match (self.then, self.status) {
+            (PolicyAction::Block, Some(status)) => status.into(),
+            (PolicyAction::Sanitize, _) => WorkflowStatus::Sanitized,
+            _ => WorkflowStatus::Completed,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let id = &self.id;
+        for (field, condition) in &self.when {
+            if condition.values().is_empty() {
+                return Err(format!("rule {id}: {} lists no values", field.as_str()));
+            }
+            for value in condition.values() {
+                field
+                    .validate(value)
+                    .map_err(|e| format!("rule {id}: {e}"))?;
+            }
+        }
+        match (self.then, self.status) {
+            (PolicyAction::Block, None) => {
+                return Err(format!("rule {id}: block needs a status"));
+            }
+            (PolicyAction::Allow | PolicyAction::Sanitize, Some(_)) => {
+                return Err(format!("rule {id}: only block takes a status"));
+            }
+            _ => {}
+        }
+        if matches!(
+            self.reason_code,
+            ReasonCode::Unspecified
+                | ReasonCode::StageFailure
+                | ReasonCode::ModerationNotConfigured
+        ) {
+            return Err(format!(
+                "rule {id}: reason_code must be a known code other than stage_failure and moderation_not_configured"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Evidence a request has gathered so far, keyed by policy field
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct PolicyEvidence {
+    values: BTreeMap<PolicyField, PolicyValue>,
+}
+
+impl PolicyEvidence {
+    pub fn with(mut self, field: PolicyField, value: impl Into<PolicyValue>) -> Self {
+        self.set(field, value);
+        self
+    }
+
+    pub fn set(&mut self, field: PolicyField, value: impl Into<PolicyValue>) {
+        self.values.insert(field, value.into());
+    }
+
+    pub fn get(&self, field: PolicyField) -> Option<&PolicyValue> {
+        self.values.get(&field)
+    }
+
+    /// Reject values a field never takes
+    pub fn validate(&self) -> Result<(), String> {
+        self.values
+            .iter()
+            .try_for_each(|(field, value)| field.validate(value))
+    }
+
+    /// The fields known by `checkpoint`
+    fn until(&self, checkpoint: u8) -> Self {
+        Self {
+            values: self
+                .values
+                .iter()
+                .filter(|(field, _)| field.checkpoint() <= checkpoint)
+                .map(|(field, value)| (*field, value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Ordered rules turning stage evidence into a decision
+///
+/// The first rule whose conditions hold decides. The engine checks the policy each time
+/// more evidence is known, and a `block` decision ends the request there; `allow` and
+/// `sanitize` only take effect once every stage has run. Requests no rule matches are
+/// allowed with `all_checks_passed`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DecisionPolicy {
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for DecisionPolicy {
+    /// Precedence the workflow has always applied: EU prohibited practices, firewall
+    /// blocks, repeat offenders, sessions probing the sanitizer, high semantic risk, then moderation of the input, removed
+    /// content and output, and an output repeating a block rule; a sanitized prompt or
+    /// medium semantic risk takes the sanitized path
+    fn default() -> Self {
+        let rule = |id: &str, field, value: &str, then, status, reason_code| PolicyRule {
+            id: id.to_owned(),
+            when: BTreeMap::from([(field, Condition::Is(flag_or_text(value)))]),
+            then,
+            status,
+            reason_code,
+        };
+        let block = PolicyAction::Block;
+        Self {
+            rules: vec![
+                rule(
+                    "eu-prohibited-practice",
+                    PolicyField::EuRiskTier,
+                    "unacceptable",
+                    block,
+                    Some(BlockStatus::BlockedByEuCompliance),
+                    ReasonCode::EuProhibitedPractice,
+                ),
+                rule(
+                    "firewall-block",
+                    PolicyField::FirewallAction,
+                    "block",
+                    block,
+                    Some(BlockStatus::BlockedByFirewall),
+                    ReasonCode::FirewallRuleMatch,
+                ),
+                rule(
+                    "repeat-offender",
+                    PolicyField::RepeatAction,
+                    "block",
+                    block,
+                    Some(BlockStatus::BlockedBySemantic),
+                    ReasonCode::RepeatOfBlockedPrompt,
+                ),
+                rule(
+                    "sanitize-probing",
+                    PolicyField::SanitizeProbing,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByFirewall),
+                    ReasonCode::SanitizerProbing,
+                ),
+                rule(
+                    "semantic-high",
+                    PolicyField::SemanticLevel,
+                    "high",
+                    block,
+                    Some(BlockStatus::BlockedBySemantic),
+                    ReasonCode::SemanticSimilarity,
+                ),
+                rule(
+                    "input-moderation",
+                    PolicyField::ModerationFlagged,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByInputModeration),
+                    ReasonCode::InputModerationFlag,
+                ),
+                rule(
+                    "removed-content-moderation",
+                    PolicyField::RemovedContentFlagged,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByInputModeration),
+                    ReasonCode::RemovedContentModerationFlag,
+                ),
+                rule(
+                    "output-moderation",
+                    PolicyField::OutputModerationFlagged,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByOutputModeration),
+                    ReasonCode::OutputModerationFlag,
+                ),
+                rule(
+                    "output-firewall-echo",
+                    PolicyField::OutputFirewallEcho,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByOutputModeration),
+                    ReasonCode::OutputFirewallEcho,
+                ),
+                rule(
+                    "translated-output-moderation",
+                    PolicyField::TranslatedOutputModerationFlagged,
+                    "true",
+                    block,
+                    Some(BlockStatus::BlockedByOutputModeration),
+                    ReasonCode::OutputModerationFlag,
+                ),
+                rule(
+                    "firewall-sanitize",
+                    PolicyField::FirewallAction,
+                    "sanitize",
+                    PolicyAction::Sanitize,
+                    None,
+                    ReasonCode::Sanitized,
+                ),
+                rule(
+                    "semantic-medium",
+                    PolicyField::SemanticLevel,
+                    "medium",
+                    PolicyAction::Sanitize,
+                    None,
+                    ReasonCode::ElevatedSemanticRisk,
+                ),
+            ],
+        }
+    }
+}
+
+fn flag_or_text(value: &str) -> PolicyValue {
+    match value {
+        "true" => PolicyValue::Flag(true),
+        "false" => PolicyValue::Flag(false),
+        text => PolicyValue::from(text),
+    }
+}
+
+impl DecisionPolicy {
+    /// Read a policy from a JSON file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parse and validate a policy; unknown keys, fields and values are errors
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let policy: Self = serde_json::from_str(text).map_err(|e| format!("cannot parse: {e}"))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for rule in &self.rules {
+            if rule.id.trim().is_empty() {
+                return Err("every policy rule needs an id".to_owned());
+            }
+            if !ids.insert(rule.id.as_str()) {
+                return Err(format!("policy rule id {} is used twice", rule.id));
+            }
+            rule.validate()?;
+        }
+        Ok(())
+    }
+
+    /// First rule matching `evidence`
+    pub fn evaluate(&self, evidence: &PolicyEvidence) -> Option<&PolicyRule> {
+        self.rules.iter().find(|rule| rule.matches(evidence))
+    }
+
+    /// The rule that blocks a request with `evidence` so far, if the first match blocks
+    pub fn blocking_rule(&self, evidence: &PolicyEvidence) -> Option<&PolicyRule> {
+        self.evaluate(evidence)
+            .filter(|rule| rule.then == PolicyAction::Block)
+    }
+
+    /// Decide a request from a complete evidence snapshot as the engine would, checking
+    /// for a block each time another stage's fields become known
+    pub fn decide(&self, evidence: &PolicyEvidence) -> Option<&PolicyRule> {
+        let last = PolicyField::TranslatedOutputModerationFlagged.checkpoint();
+        (0..last)
+            .find_map(|checkpoint| self.blocking_rule(&evidence.until(checkpoint)))
+            .or_else(|| self.evaluate(evidence))
+    }
+}
+
+/// Body of `POST /api/policy/dry-run`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDryRunRequest {
+    /// Candidate policy; the configured policy when left out
+    #[serde(default)]
+    pub policy: Option<DecisionPolicy>,
+    pub evidence: PolicyEvidence,
+}
+
+/// Decision a policy makes for an evidence snapshot
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PolicyDryRunResponse {
+    pub decision: PolicyAction,
+    pub status: WorkflowStatus,
+    pub reason_code: ReasonCode,
+    /// Matched rule; `None` when no rule matched and the request is allowed
+    pub rule_id: Option<String>,
+}
+
+impl PolicyDryRunResponse {
+    pub fn new(rule: Option<&PolicyRule>) -> Self {
+        match rule {
+            Some(rule) => Self {
+                decision: rule.then,
+                status: rule.status(),
+                reason_code: rule.reason_code,
+                rule_id: Some(rule.id.clone()),
+            },
+            None => Self {
+                decision: PolicyAction::Allow,
+                status: WorkflowStatus::Completed,
+                reason_code: ReasonCode::AllChecksPassed,
+                rule_id: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The combiner the workflow hard-coded before decisions moved into a policy
+    fn legacy_decision(evidence: &PolicyEvidence) -> (WorkflowStatus, ReasonCode) {
+        let is = |field, value: &str| {
+            evidence
+                .get(field)
+                .is_some_and(|actual| flag_or_text(value).matches(actual))
+        };
+        use PolicyField::*;
+        if is(EuRiskTier, "unacceptable") {
+            (
+                WorkflowStatus::BlockedByEuCompliance,
+                ReasonCode::EuProhibitedPractice,
+            )
+        } else if is(FirewallAction, "block") {
+            (
+                WorkflowStatus::BlockedByFirewall,
+                ReasonCode::FirewallRuleMatch,
+            )
+        } else if is(RepeatAction, "block") {
+            (
+                WorkflowStatus::BlockedBySemantic,
+                ReasonCode::RepeatOfBlockedPrompt,
+            )
+        } else if is(SemanticLevel, "high") {
+            (
+                WorkflowStatus::BlockedBySemantic,
+                ReasonCode::SemanticSimilarity,
+            )
+        } else if is(ModerationFlagged, "true") {
+            (
+                WorkflowStatus::BlockedByInputModeration,
+                ReasonCode::InputModerationFlag,
+            )
+        } else if is(RemovedContentFlagged, "true") {
+            (
+                WorkflowStatus::BlockedByInputModeration,
+                ReasonCode::RemovedContentModerationFlag,
+            )
+        } else if is(OutputModerationFlagged, "true")
+            || is(TranslatedOutputModerationFlagged, "true")
+        {
+            (
+                WorkflowStatus::BlockedByOutputModeration,
+                ReasonCode::OutputModerationFlag,
+            )
+        } else if is(FirewallAction, "sanitize") {
+            (WorkflowStatus::Sanitized, ReasonCode::Sanitized)
+        } else if is(SemanticLevel, "medium") {
+            (WorkflowStatus::Sanitized, ReasonCode::ElevatedSemanticRisk)
+        } else {
+            (WorkflowStatus::Completed, ReasonCode::AllChecksPassed)
+        }
+    }
+
+    /// Every combination of values, with `None` for a field the request has no value for
+    fn evidence_grid() -> Vec<PolicyEvidence> {
+        let options: Vec<(PolicyField, Vec<Option<PolicyValue>>)> = [
+            PolicyField::EuRiskTier,
+            PolicyField::FirewallAction,
+            PolicyField::RepeatAction,
+            PolicyField::SemanticLevel,
+            PolicyField::ModerationFlagged,
+            PolicyField::RemovedContentFlagged,
+            PolicyField::OutputModerationFlagged,
+            PolicyField::TranslatedOutputModerationFlagged,
+        ]
+        .into_iter()
+        .map(|field| {
+            let values = match field.allowed_values() {
+                Some(values) => values.iter().map(|value| Some((*value).into())).collect(),
+                None => vec![Some(true.into()), Some(false.into())],
+            };
+            (field, [vec![None], values].concat())
+        })
+        .collect();
+        options
+            .iter()
+            .fold(vec![PolicyEvidence::default()], |grid, (field, values)| {
+                grid.iter()
+                    .flat_map(|evidence| {
+                        values.iter().map(move |value| match value {
+                            Some(value) => evidence.clone().with(*field, value.clone()),
+                            None => evidence.clone(),
+                        })
+                    })
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn default_policy_reproduces_the_legacy_combiner() {
+        let policy = DecisionPolicy::default();
+        policy.validate().unwrap();
+        let grid = evidence_grid();
+        assert!(grid.len() > 10_000, "{}", grid.len());
+        for evidence in grid {
+            let decided = PolicyDryRunResponse::new(policy.decide(&evidence));
+            assert_eq!(
+                (decided.status, decided.reason_code),
+                legacy_decision(&evidence),
+                "{evidence:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn shipped_policy_file_is_the_default() {
+        let shipped = DecisionPolicy::load(Path::new("config/decision_policy.json")).unwrap();
+        assert_eq!(shipped, DecisionPolicy::default());
+    }
+
+    #[test]
+    fn typos_fail_validation() {
+        let valid = r#"{"rules": [{"id": "a", "when": {"firewall.action": "block"},
+            "then": "block", "status": "blocked_by_firewall", "reason_code": "firewall_rule_match"}]}"#;
+        DecisionPolicy::parse(valid).unwrap();
+        for (typo, fixed) in [
+            ("firewall.action", "firewall.actoin"),
+            ("\"block\"}", "\"blok\"}"),
+            ("\"then\"", "\"than\""),
+            ("blocked_by_firewall", "blocked_by_firewal"),
+            ("firewall_rule_match", "firewall_rule_matches"),
+            ("\"rules\"", "\"rule\""),
+        ] {
+            let invalid = valid.replacen(typo, fixed, 1);
+            assert!(DecisionPolicy::parse(&invalid).is_err(), "{invalid}");
+        }
+        for invalid in [
+            r#"{"rules": [{"id": "a", "then": "block", "reason_code": "sanitized"}]}"#,
+            r#"{"rules": [{"id": "a", "then": "allow", "status": "blocked_by_firewall", "reason_code": "sanitized"}]}"#,
+            r#"{"rules": [{"id": "a", "when": {"moderation.flagged": "yes"}, "then": "allow", "reason_code": "sanitized"}]}"#,
+            r#"{"rules": [{"id": "a", "when": {"semantic.level": []}, "then": "allow", "reason_code": "sanitized"}]}"#,
+            r#"{"rules": [{"id": "a", "then": "allow", "reason_code": "sanitized"},
+                          {"id": "a", "then": "allow", "reason_code": "sanitized"}]}"#,
+            r#"{"rules": [{"id": "a", "then": "block", "status": "blocked_by_firewall", "reason_code": "stage_failure"}]}"#,
+        ] {
+            assert!(DecisionPolicy::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_decides_and_blocks_stop_at_their_checkpoint() {
+        let policy = DecisionPolicy::parse(
+            r#"{"rules": [
+                {"id": "trusted-output", "when": {"output_moderation.flagged": false},
+                 "then": "allow", "reason_code": "all_checks_passed"},
+                {"id": "biased-jailbreak",
+                 "when": {"bias.level": ["medium", "high"], "semantic.category": "Roleplay_Jailbreak"},
+                 "then": "block", "status": "blocked_by_semantic", "reason_code": "policy_rule"}
+            ]}"#,
+        )
+        .unwrap();
+        let evidence = PolicyEvidence::default()
+            .with(PolicyField::BiasLevel, "high")
+            .with(PolicyField::SemanticCategory, "roleplay_jailbreak")
+            .with(PolicyField::OutputModerationFlagged, false);
+        // The block is reached before output moderation has run
+        assert_eq!(policy.evaluate(&evidence).unwrap().id, "trusted-output");
+        let rule = policy.decide(&evidence).unwrap();
+        assert_eq!(rule.id, "biased-jailbreak");
+        assert_eq!(rule.last_field(), Some(PolicyField::SemanticCategory));
+
+        let low_bias = evidence.with(PolicyField::BiasLevel, "low");
+        assert_eq!(policy.decide(&low_bias).unwrap().id, "trusted-output");
+        assert_eq!(policy.decide(&PolicyEvidence::default()), None);
+    }
+}
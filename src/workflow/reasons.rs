@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Locale `render_reason` falls back to, and the only one built in
+pub const DEFAULT_REASON_LOCALE: &str = "en";
+
+/// Machine-readable cause of a decision
+///
+/// The serialized names are part of the API: clients match on them, so existing names
+/// never change and new causes get new codes.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonCode {
+    /// EU AI Act Article 5 prohibited practice; params: `finding` (optional)
+    EuProhibitedPractice,
+    /// Params: `rule_ids`
+    FirewallRuleMatch,
+    /// Sanitization would have removed more of the prompt than allowed; params:
+    /// `rule_ids` of the sanitize patterns involved
+    ExcessiveSanitization,
+    /// Close variant of a recently blocked prompt; params: `correlation_id`, `similarity`
+    RepeatOfBlockedPrompt,
+    /// A session kept resending content sanitization removes; params: `session_id`,
+    /// `prior_correlation_ids`
+    SanitizerProbing,
+    /// Params: `template_id`, `category`, `score`, and `raised_score` with
+    /// `repeat_correlation_id` when the risk was raised for resembling a blocked prompt
+    SemanticSimilarity,
+    /// The generation model, asked about a prompt in the gray zone, judged it an attempt
+    /// to override the assistant's instructions; params: `trigger`, `answer`,
+    /// `template_version`
+    LlmJudgeEscalation,
+    /// A stage with a closed failure policy failed; params: `stage`, `error`
+    StageFailure,
+    /// Params: `categories`
+    InputModerationFlag,
+    /// Content stripped by sanitization was flagged; params: `categories`
+    RemovedContentModerationFlag,
+    /// Params: `categories`, and `variant` ("english" or "translated") for the text
+    /// that was flagged
+    OutputModerationFlag,
+    /// The generated answer repeats firewall block rules; params: `rule_ids`
+    OutputFirewallEcho,
+    /// The firewall sanitized the prompt
+    Sanitized,
+    /// Medium semantic risk; params: `score`
+    ElevatedSemanticRisk,
+    /// No moderation model is configured and unmoderated requests take the sanitized path
+    ModerationNotConfigured,
+    AllChecksPassed,
+    /// A rule of a custom decision policy; params: `rule_id`
+    PolicyRule,
+    /// Records written before reason codes existed, or a code this build does not know
+    #[default]
+    #[serde(other)]
+    Unspecified,
+}
+
+/// Structured values behind a reason, keyed by parameter name
+pub type ReasonParams = BTreeMap<String, Value>;
+
+/// Renders reason codes as text in one locale
+///
+/// Returning `None` falls back to English, so a catalogue may cover only some codes.
+pub trait ReasonRenderer: Send + Sync {
+    fn render(&self, code: ReasonCode, params: &ReasonParams) -> Option<String>;
+}
+
+static LOCALES: LazyLock<RwLock<HashMap<String, Arc<dyn ReasonRenderer>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Make `renderer` available to [`render_reason`] under `locale`
+pub fn register_reason_locale(locale: impl Into<String>, renderer: Arc<dyn ReasonRenderer>) {
+    LOCALES.write().unwrap().insert(locale.into(), renderer);
+}
+
+/// Text for a reason in `locale`, falling back to English
+pub fn render_reason(code: ReasonCode, params: &ReasonParams, locale: &str) -> String {
+    let registered = LOCALES.read().unwrap().get(locale).cloned();
+    registered
+        .and_then(|renderer| renderer.render(code, params))
+        .unwrap_or_else(|| english(code, params))
+}
+
+fn english(code: ReasonCode, params: &ReasonParams) -> String {
+    let text = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or("unknown");
+    let score = |key: &str| {
+        params
+            .get(key)
+            .and_then(Value::as_f64)
+            .map(|value| format!("{:.2}", value as f32))
+            .unwrap_or_else(|| "unknown".to_owned())
+    };
+    let list = |key: &str| {
+        params
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    };
+    match code {
+        ReasonCode::EuProhibitedPractice => format!(
+            "Blocked by EU AI Act Article 5 (Prohibited Practices): {}",
+            params
+                .get("finding")
+                .and_then(Value::as_str)
+                .unwrap_or("Unacceptable risk tier detected")
+        ),
+        ReasonCode::FirewallRuleMatch => {
+            format!("Blocked by firewall rule: {}", list("rule_ids"))
+        }
+        ReasonCode::ExcessiveSanitization => format!(
+            "Blocked because sanitization removed excessive content: {}",
+            list("rule_ids")
+        ),
+        ReasonCode::RepeatOfBlockedPrompt => format!(
+            "Similar to recently blocked prompt {} (similarity: {})",
+            text("correlation_id"),
+            score("similarity")
+        ),
+        ReasonCode::SanitizerProbing => format!(
+            "Blocked for probing the sanitizer: session {} sent similar sanitized prompts {}",
+            text("session_id"),
+            list("prior_correlation_ids")
+        ),
+        ReasonCode::SemanticSimilarity => {
+            let mut reason = format!(
+                "Semantic similarity to attack pattern {} (category: {}, score: {})",
+                text("template_id"),
+                text("category"),
+                score("score")
+            );
+            if params.contains_key("repeat_correlation_id") {
+                reason.push_str(&format!(
+                    "; risk raised to {} as similar to recently blocked prompt {}",
+                    score("raised_score"),
+                    text("repeat_correlation_id")
+                ));
+            }
+            reason
+        }
+        ReasonCode::LlmJudgeEscalation => format!(
+            "Blocked on a second opinion from the judge model (trigger: {}, answer: {}, \
+             template: {})",
+            text("trigger"),
+            text("answer"),
+            text("template_version")
+        ),
+        ReasonCode::StageFailure => format!(
+            "The {} stage failed and its failure policy is closed: {}",
+            text("stage"),
+            text("error")
+        ),
+        ReasonCode::InputModerationFlag => {
+            format!("Flagged by content moderation: {}", list("categories"))
+        }
+        ReasonCode::RemovedContentModerationFlag => format!(
+            "Content removed by sanitization flagged by moderation: {}",
+            list("categories")
+        ),
+        ReasonCode::OutputModerationFlag => {
+            let output = match params.get("variant").and_then(Value::as_str) {
+                Some("translated") => "Translated output",
+                _ => "Output",
+            };
+            format!("{output} flagged by moderation: {}", list("categories"))
+        }
+        ReasonCode::OutputFirewallEcho => {
+            format!("Output repeats firewall block rules: {}", list("rule_ids"))
+        }
+        ReasonCode::Sanitized => "Input sanitized by firewall".to_owned(),
+        ReasonCode::ElevatedSemanticRisk => format!(
+            "Elevated risk (semantic score: {}), proceeded with caution",
+            score("score")
+        ),
+        ReasonCode::ModerationNotConfigured => {
+            "Content moderation is not configured, proceeded with caution".to_owned()
+        }
+        ReasonCode::AllChecksPassed => "All checks passed".to_owned(),
+        ReasonCode::PolicyRule => format!("Decided by policy rule {}", text("rule_id")),
+        ReasonCode::Unspecified => "No reason recorded".to_owned(),
+    }
+}
+
+/// A reason code with its parameters; the English text is always rendered from these
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DecisionReason {
+    pub(crate) code: ReasonCode,
+    pub(crate) params: ReasonParams,
+}
+
+impl DecisionReason {
+    pub(crate) fn new(code: ReasonCode) -> Self {
+        Self {
+            code,
+            params: ReasonParams::new(),
+        }
+    }
+
+    pub(crate) fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.params.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Scores go through their shortest decimal form so `0.85` is not stored as
+    /// `0.8500000238418579`
+    pub(crate) fn with_score(self, key: &str, score: f32) -> Self {
+        let value = score.to_string().parse::<f64>().unwrap_or(f64::from(score));
+        self.with(key, value)
+    }
+
+    pub(crate) fn text(&self) -> String {
+        render_reason(self.code, &self.params, DEFAULT_REASON_LOCALE)
+    }
+}
@@ -0,0 +1,376 @@
+//! Refusal messages for blocked requests, so clients need not write their own
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use super::{ComplianceEngine, ToggleableStage, Verdict, WorkflowRun, WorkflowStatus, stage_span};
+use crate::modules::mistral_ai::dtos::DeterministicSampling;
+use crate::modules::mistral_ai::sampling;
+use crate::modules::telemetry::tracing::log_with_correlation;
+
+/// The only placeholder a template may use
+const CATEGORY_PLACEHOLDER: &str = "{category}";
+
+/// Which kind of block a refusal answers
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalKind {
+    Firewall,
+    Semantic,
+    /// Input or output moderation
+    Moderation,
+    /// EU AI Act prohibited practices, failed stages and custom policy blocks
+    Other,
+}
+
+impl RefusalKind {
+    /// The kind answering `status`; `None` for requests that went through
+    pub fn of(status: &WorkflowStatus) -> Option<Self> {
+        match status {
+            WorkflowStatus::Completed | WorkflowStatus::Sanitized => None,
+            WorkflowStatus::BlockedByFirewall => Some(Self::Firewall),
+            WorkflowStatus::BlockedBySemantic => Some(Self::Semantic),
+            WorkflowStatus::BlockedByInputModeration
+            | WorkflowStatus::BlockedByOutputModeration => Some(Self::Moderation),
+            WorkflowStatus::BlockedByEuCompliance
+            | WorkflowStatus::BlockedByStageFailure { .. } => Some(Self::Other),
+        }
+    }
+}
+
+/// Refusal templates of one language
+///
+/// `{category}` stands for the reason category, e.g. the moderation category that was
+/// flagged; `default_category` replaces it when the block has none. Matched rule text
+/// never reaches a template.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RefusalTemplateSet {
+    pub firewall: String,
+    pub semantic: String,
+    pub moderation: String,
+    pub other: String,
+    pub default_category: String,
+}
+
+impl RefusalTemplateSet {
+    pub fn template(&self, kind: RefusalKind) -> &str {
+        match kind {
+            RefusalKind::Firewall => &self.firewall,
+            RefusalKind::Semantic => &self.semantic,
+            RefusalKind::Moderation => &self.moderation,
+            RefusalKind::Other => &self.other,
+        }
+    }
+
+    /// The template for `kind` with `category` filled in
+    pub fn render(&self, kind: RefusalKind, category: Option<&str>) -> String {
+        self.template(kind).replace(
+            CATEGORY_PLACEHOLDER,
+            category.unwrap_or(&self.default_category),
+        )
+    }
+
+    fn validate(&self, language: &str) -> Result<(), String> {
+        for (name, template) in [
+            ("firewall", &self.firewall),
+            ("semantic", &self.semantic),
+            ("moderation", &self.moderation),
+            ("other", &self.other),
+        ] {
+            if template.trim().is_empty() {
+                return Err(format!("{language} {name} refusal template is empty"));
+            }
+            // Any brace left once the placeholder is gone is a misspelt placeholder
+            if template
+                .replace(CATEGORY_PLACEHOLDER, "")
+                .contains(['{', '}'])
+            {
+                return Err(format!(
+                    "{language} {name} refusal template uses a placeholder other than \
+                     {CATEGORY_PLACEHOLDER}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Refusal templates by language, read from the file at `REFUSAL_TEMPLATES_PATH`
+///
+/// Languages are keyed by their English name, as language detection reports them. The
+/// English set is required; it is what other languages are translated from and what
+/// they fall back to.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RefusalTemplates {
+    pub languages: BTreeMap<String, RefusalTemplateSet>,
+}
+
+impl Default for RefusalTemplates {
+    fn default() -> Self {
+        let set =
+            |firewall: &str, semantic: &str, moderation: &str, other: &str, category: &str| {
+                RefusalTemplateSet {
+                    firewall: firewall.to_owned(),
+                    semantic: semantic.to_owned(),
+                    moderation: moderation.to_owned(),
+                    other: other.to_owned(),
+                    default_category: category.to_owned(),
+                }
+            };
+        Self {
+            languages: BTreeMap::from([
+                (
+                    "English".to_owned(),
+                    set(
+                        "Sorry, I can't help with that request because it contains instructions that aren't allowed here.",
+                        "Sorry, I can't help with that request because it resembles a known misuse pattern ({category}).",
+                        "Sorry, I can't help with that request because it involves content that isn't allowed here ({category}).",
+                        "Sorry, I can't help with that request right now.",
+                        "restricted content",
+                    ),
+                ),
+                (
+                    "Spanish".to_owned(),
+                    set(
+                        "Lo siento, no puedo ayudar con esa solicitud porque contiene instrucciones que no están permitidas aquí.",
+                        "Lo siento, no puedo ayudar con esa solicitud porque se parece a un patrón de uso indebido conocido.",
+                        "Lo siento, no puedo ayudar con esa solicitud porque incluye contenido que no está permitido aquí.",
+                        "Lo siento, no puedo ayudar con esa solicitud en este momento.",
+                        "contenido restringido",
+                    ),
+                ),
+                (
+                    "French".to_owned(),
+                    set(
+                        "Désolé, je ne peux pas répondre à cette demande car elle contient des instructions qui ne sont pas autorisées ici.",
+                        "Désolé, je ne peux pas répondre à cette demande car elle ressemble à un abus connu.",
+                        "Désolé, je ne peux pas répondre à cette demande car elle comporte un contenu qui n'est pas autorisé ici.",
+                        "Désolé, je ne peux pas répondre à cette demande pour le moment.",
+                        "contenu restreint",
+                    ),
+                ),
+                (
+                    "German".to_owned(),
+                    set(
+                        "Entschuldigung, bei dieser Anfrage kann ich nicht helfen, da sie hier nicht erlaubte Anweisungen enthält.",
+                        "Entschuldigung, bei dieser Anfrage kann ich nicht helfen, da sie einem bekannten Missbrauchsmuster ähnelt.",
+                        "Entschuldigung, bei dieser Anfrage kann ich nicht helfen, da sie hier nicht erlaubte Inhalte betrifft.",
+                        "Entschuldigung, bei dieser Anfrage kann ich gerade nicht helfen.",
+                        "eingeschränkte Inhalte",
+                    ),
+                ),
+            ]),
+        }
+    }
+}
+
+impl RefusalTemplates {
+    /// Read templates from a JSON file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Parse and validate templates; unknown keys are errors
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let templates: Self =
+            serde_json::from_str(text).map_err(|e| format!("cannot parse: {e}"))?;
+        templates.validate()?;
+        Ok(templates)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.english().is_none() {
+            return Err("refusal templates need an English set".to_owned());
+        }
+        for (language, set) in &self.languages {
+            set.validate(language)?;
+        }
+        Ok(())
+    }
+
+    /// The set for `language`, matched without regard to case
+    pub fn language(&self, language: &str) -> Option<&RefusalTemplateSet> {
+        self.languages
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(language))
+            .map(|(_, set)| set)
+    }
+
+    pub fn english(&self) -> Option<&RefusalTemplateSet> {
+        self.language("English")
+    }
+}
+
+/// Whether blocked requests get a refusal message, and its wording
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefusalPolicy {
+    /// Default for requests that do not pass `refusal` (`REFUSAL_MESSAGES`, default: off)
+    pub enabled: bool,
+    pub templates: RefusalTemplates,
+}
+
+/// Where the text of a refusal came from
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefusalSource {
+    /// A template written in the refusal's language
+    Template,
+    /// The English template, translated into the prompt's language
+    Translated,
+    /// The English template, since the prompt's language has no template and could not
+    /// be translated into
+    EnglishFallback,
+}
+
+/// A refusal to show instead of an answer; never model output
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RefusalMessage {
+    pub kind: RefusalKind,
+    pub text: String,
+    /// Language of `text`, by its English name
+    pub language: String,
+    pub source: RefusalSource,
+}
+
+impl ComplianceEngine {
+    /// The refusal for a run blocked as `kind`, in the prompt's language
+    ///
+    /// The English template is translated with fixed sampling and the translation kept,
+    /// so a refusal reads the same every time; the prompt language's own template is the
+    /// fallback when translation is paused or fails, and English the last resort.
+    pub(super) async fn refusal_message(
+        &self,
+        run: &WorkflowRun,
+        verdict: &Verdict,
+        kind: RefusalKind,
+    ) -> RefusalMessage {
+        let category = match kind {
+            RefusalKind::Semantic => run.semantic.as_ref().and_then(|s| s.category.clone()),
+            RefusalKind::Moderation => verdict
+                .moderation_categories
+                .iter()
+                .max_by(|a, b| a.severity.total_cmp(&b.severity))
+                .map(|c| c.category.clone()),
+            RefusalKind::Firewall | RefusalKind::Other => None,
+        }
+        .map(|category| display_category(&category));
+        let templates = &self.refusals.templates;
+        let english = templates
+            .english()
+            .map(|set| set.render(kind, category.as_deref()))
+            .unwrap_or_default();
+        let message = |text: String, language: &str, source| RefusalMessage {
+            kind,
+            text,
+            language: language.to_owned(),
+            source,
+        };
+
+        let language = run.original_language.as_str();
+        if language.eq_ignore_ascii_case("english") {
+            return message(english, "English", RefusalSource::Template);
+        }
+        if !run.is_disabled(ToggleableStage::Translation)
+            && let Some(translated) = self.translate_refusal(run, &english, language).await
+        {
+            return message(translated, language, RefusalSource::Translated);
+        }
+        match templates.language(language) {
+            Some(set) => message(
+                set.render(kind, category.as_deref()),
+                language,
+                RefusalSource::Template,
+            ),
+            None => message(english, "English", RefusalSource::EnglishFallback),
+        }
+    }
+
+    async fn translate_refusal(
+        &self,
+        run: &WorkflowRun,
+        english: &str,
+        language: &str,
+    ) -> Option<String> {
+        let key = (language.to_ascii_lowercase(), english.to_owned());
+        if let Some(translated) = self.refusal_translations.read().unwrap().get(&key) {
+            return Some(translated.clone());
+        }
+        let sampling = Some(DeterministicSampling {
+            random_seed: self.deterministic_seed,
+        });
+        let translation = sampling::scope(
+            sampling,
+            self.mistral_service
+                .translate_text(english.to_owned(), language.to_owned())
+                .instrument(stage_span("refusal_translation")),
+        )
+        .await;
+        match translation {
+            Ok(translation) if !translation.translated_text.trim().is_empty() => {
+                let translated = translation.translated_text;
+                self.refusal_translations
+                    .write()
+                    .unwrap()
+                    .insert(key, translated.clone());
+                Some(translated)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log_with_correlation(
+                    &run.correlation_id,
+                    tracing::Level::WARN,
+                    &format!("Could not translate the refusal into {language}: {e}"),
+                );
+                None
+            }
+        }
+    }
+}
+
+/// A reason category as templates show it: `hate_and_discrimination` reads
+/// `hate and discrimination`
+pub(crate) fn display_category(category: &str) -> String {
+    category.replace(['_', '-'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_templates_file_is_the_default() {
+        let shipped = RefusalTemplates::load(Path::new("config/refusal_templates.json")).unwrap();
+        assert_eq!(shipped, RefusalTemplates::default());
+    }
+
+    #[test]
+    fn templates_render_the_category_and_reject_other_placeholders() {
+        let templates = RefusalTemplates::default();
+        let english = templates.english().unwrap();
+        assert!(
+            english
+                .render(RefusalKind::Moderation, Some("violence"))
+                .ends_with("(violence).")
+        );
+        assert!(
+            english
+                .render(RefusalKind::Semantic, None)
+                .ends_with("(restricted content).")
+        );
+        assert_eq!(templates.language("spanish"), templates.language("Spanish"));
+
+        let mut invalid = templates.clone();
+        invalid.languages.get_mut("English").unwrap().firewall = "Blocked by {rule}".to_owned();
+        assert!(invalid.validate().is_err());
+        let mut invalid = templates;
+        invalid.languages.remove("English");
+        assert!(invalid.validate().is_err());
+    }
+}
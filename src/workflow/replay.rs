@@ -0,0 +1,711 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::threats::LayerFindings;
+use super::{
+    ComplianceEngine, DecisionEvidence, DecisionReason, FailureMode, PipelineStage, ReasonCode,
+    RiskInputs, StageFailure, WorkflowStatus, content_ref, eu_block_reason, firewall_block_reason,
+    firewall_signal, risk_score, semantic_block_reason, serialized_name, stage_failure_reason,
+};
+use crate::firewall_core::taxonomy::resolve_category;
+use crate::modules::audit::logger::{
+    AuditError, AuditEvent, GenerationInputDigest, GenerationParameters, ReplayEvent,
+};
+use crate::modules::audit::proof::AuditProof;
+use crate::modules::audit::storage::{AuditStorageError, PromptStorageMode};
+use crate::modules::bias_detection::dtos::BiasScanRequest;
+use crate::modules::bias_detection::model::BiasLevel;
+use crate::modules::eu_law_compliance::model::AiRiskTier;
+use crate::modules::mistral_ai::dtos::ChatMessage;
+use crate::modules::mistral_ai::service::MistralServiceError;
+use crate::modules::preprocessing::service::PromptPreprocessor;
+use crate::modules::prompt_firewall::archive::RulesArchiveError;
+use crate::modules::prompt_firewall::dtos::FirewallAction;
+use crate::modules::prompt_firewall::language_mix;
+use crate::modules::semantic_detection::dtos::{
+    SemanticRiskLevel, SemanticScanRequest, SemanticScanResult,
+};
+use crate::modules::telemetry::tracing::log_with_correlation;
+
+/// Which configuration a replay evaluates the recorded prompt against
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayMode {
+    /// The rules in effect now, with a fresh semantic scan
+    #[default]
+    Current,
+    /// The archived rules the decision was made with, reusing the recorded semantic score
+    Historical,
+    /// As `current`, and the recorded generation is reissued with its recorded parameters
+    Regenerate,
+}
+
+impl ReplayMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Current => "current",
+            Self::Historical => "historical",
+            Self::Regenerate => "regenerate",
+        }
+    }
+}
+
+/// A decision evidence field whose value changed on replay
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EvidenceChange {
+    pub field: String,
+    pub original: Value,
+    pub replayed: Value,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ReplayReport {
+    /// Request whose decision was replayed
+    pub correlation_id: String,
+    /// Correlation id of the replay's own audit record
+    pub replay_correlation_id: String,
+    pub mode: ReplayMode,
+    pub original_status: String,
+    pub replayed_status: String,
+    /// Whether the replay reached a different status
+    pub diverged: bool,
+    /// Evidence reconstructed from the audit record
+    pub original: DecisionEvidence,
+    pub replayed: DecisionEvidence,
+    /// Evidence fields that differ, plus `bias_level` when the bias scan changed
+    pub differences: Vec<EvidenceChange>,
+    /// Stages that could not be reproduced exactly, and why
+    pub notes: Vec<String>,
+    /// The reissued generation, in regenerate mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regeneration: Option<Regeneration>,
+    pub audit_proof: AuditProof,
+}
+
+/// Outcome of reissuing a recorded generation
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Regeneration {
+    /// Parameters the generation was reissued with, as recorded
+    pub parameters: GenerationParameters,
+    /// Whether the rebuilt messages hash to the recorded generation input
+    pub input_matched: bool,
+    pub original_output_hash: String,
+    pub regenerated_output_hash: String,
+    /// Whether the reissued generation returned the recorded output exactly
+    pub output_matched: bool,
+}
+
+impl ComplianceEngine {
+    /// Re-run the local stages on an audited request and compare the outcome
+    ///
+    /// Moderation is never called; a recorded moderation verdict is carried over
+    /// unchanged. Generation is reissued only in regenerate mode, and does not change the
+    /// replayed decision. Exemptions the original request used are honoured again
+    /// without using them up.
+    pub async fn replay(
+        &self,
+        correlation_id: &str,
+        replay_correlation_id: &str,
+        mode: ReplayMode,
+    ) -> Result<ReplayReport, ReplayError> {
+        let (recorded_at, event) = self.recorded_event(correlation_id)?;
+        if event.prompt_storage != PromptStorageMode::Full {
+            return Err(ReplayError::PromptNotStored(correlation_id.to_owned()));
+        }
+        if mode == ReplayMode::Regenerate
+            && (event.generation_parameters.is_none() || event.full_output_text.is_none())
+        {
+            return Err(ReplayError::NotRegenerable(correlation_id.to_owned()));
+        }
+        log_with_correlation(
+            replay_correlation_id,
+            tracing::Level::INFO,
+            &format!("Replaying {correlation_id} against {} rules", mode.as_str()),
+        );
+        let mut notes = Vec::new();
+        let mut degraded_stages = Vec::new();
+        if event.final_status == "blocked_by_stage_failure" {
+            notes.push(
+                "the original request was blocked by a failed stage; only the local stages \
+                 and the semantic scan are re-run"
+                    .to_owned(),
+            );
+        }
+
+        let (preprocessor, rules, evaluated_at) = match mode {
+            ReplayMode::Current | ReplayMode::Regenerate => (
+                self.preprocessor.clone(),
+                self.firewall_service
+                    .runtime()
+                    .read()
+                    .unwrap()
+                    .rules
+                    .clone(),
+                Utc::now(),
+            ),
+            ReplayMode::Historical => {
+                let fingerprint = &event.config_fingerprint.firewall_rules;
+                let rules = self
+                    .firewall_service
+                    .archived_rules(fingerprint)?
+                    .ok_or_else(|| ReplayError::RulesNotArchived(fingerprint.clone()))?;
+                notes.push(
+                    "EU risk keywords, bias rules and semantic thresholds are the current ones"
+                        .to_owned(),
+                );
+                (
+                    PromptPreprocessor::new(
+                        event
+                            .preprocessing
+                            .iter()
+                            .map(|applied| applied.transform)
+                            .collect(),
+                    ),
+                    Arc::new(rules),
+                    recorded_at,
+                )
+            }
+        };
+        let prompt = preprocessor.apply(&event.original_prompt).text;
+        let language = event
+            .detected_language
+            .clone()
+            .unwrap_or_else(|| "English".to_owned());
+        let is_english = language.eq_ignore_ascii_case("english");
+        if !is_english {
+            notes.push(format!(
+                "the {language} prompt was translated before the original firewall check; \
+                 the replay evaluates the original wording"
+            ));
+        }
+
+        if let Some(template) = &event.template {
+            notes.push(format!(
+                "the prompt was filled from template {}; the replay scans the filled prompt as a whole",
+                template.template_id
+            ));
+        }
+
+        if !event.firewall_urls.is_empty() {
+            notes.push(
+                "the URL policy is not applied again and linked pages are not fetched again"
+                    .to_owned(),
+            );
+        }
+
+        let exempted: Vec<String> = event
+            .applied_exemptions
+            .iter()
+            .map(|applied| applied.rule_id.clone())
+            .collect();
+        let rules = if exempted.is_empty() {
+            rules
+        } else {
+            notes.push(format!(
+                "rules exempted for the original request stay exempted: {}",
+                exempted.join(", ")
+            ));
+            Arc::new(rules.without_rules(&exempted))
+        };
+        let firewall = self
+            .firewall_service
+            .inspect_with_rules_at(&prompt, &rules, evaluated_at);
+        let mixed_languages = language_mix::mixed_languages(&prompt);
+        let eu_compliance = self.eu_compliance_service.check_prompt(&prompt);
+        let bias = self
+            .bias_service
+            .scan(BiasScanRequest {
+                text: if is_english {
+                    firewall.sanitized_prompt.clone()
+                } else {
+                    prompt.clone()
+                },
+                threshold: None,
+                language_hint: Some(language),
+                pre_translated_text: None,
+            })
+            .await;
+
+        let eu_blocked = matches!(eu_compliance.risk_tier, AiRiskTier::Unacceptable);
+        let firewall_blocked = firewall.action == FirewallAction::Block;
+        let semantic = if eu_blocked || firewall_blocked {
+            None
+        } else {
+            match mode {
+                ReplayMode::Current | ReplayMode::Regenerate => self
+                    .semantic_service
+                    .scan(SemanticScanRequest {
+                        text: firewall.sanitized_prompt.clone(),
+                        detected_language: None,
+                        pre_translated_text: None,
+                    })
+                    .await
+                    .inspect_err(|e| {
+                        degraded_stages.push(StageFailure {
+                            stage: PipelineStage::Semantic,
+                            policy: self.stage_failures.semantic,
+                            error: e.to_string(),
+                        })
+                    })
+                    .ok(),
+                ReplayMode::Historical => {
+                    event.semantic_risk_score.map(|score| SemanticScanResult {
+                        risk_score: score,
+                        risk_level: self.semantic_service.classify_risk(score),
+                        nearest_template_id: event.semantic_template_id.clone(),
+                        similarity: score,
+                        category: event.semantic_category.clone(),
+                        // Aliases of the bank at the time are not recorded, so only the
+                        // built-in ones apply
+                        threat_category: event
+                            .semantic_category
+                            .as_deref()
+                            .and_then(|category| resolve_category(category, &BTreeMap::new()).ok()),
+                        matched_window: None,
+                    })
+                }
+            }
+        };
+
+        let closed_failure = degraded_stages
+            .iter()
+            .find(|failure| failure.policy == FailureMode::Closed);
+        let moderation_blocked = matches!(
+            event.final_status.as_str(),
+            "blocked_by_input_moderation" | "blocked_by_output_moderation"
+        );
+        let (status, final_reason) = if eu_blocked {
+            (
+                WorkflowStatus::BlockedByEuCompliance,
+                eu_block_reason(&eu_compliance),
+            )
+        } else if firewall_blocked {
+            (
+                WorkflowStatus::BlockedByFirewall,
+                firewall_block_reason(&firewall),
+            )
+        } else if let Some(sem) = semantic
+            .as_ref()
+            .filter(|sem| sem.risk_level == SemanticRiskLevel::High)
+        {
+            (
+                WorkflowStatus::BlockedBySemantic,
+                semantic_block_reason(sem),
+            )
+        } else if let Some(failure) = closed_failure {
+            (
+                WorkflowStatus::BlockedByStageFailure {
+                    stage: failure.stage,
+                },
+                stage_failure_reason(failure),
+            )
+        } else if moderation_blocked {
+            notes.push("moderation is not re-run; its recorded verdict is kept".to_owned());
+            let status = if event.final_status == "blocked_by_input_moderation" {
+                WorkflowStatus::BlockedByInputModeration
+            } else {
+                WorkflowStatus::BlockedByOutputModeration
+            };
+            let recorded = DecisionReason {
+                code: event.final_reason_code,
+                params: event.reason_params.clone(),
+            };
+            (status, recorded)
+        } else if event.final_reason_code == ReasonCode::LlmJudgeEscalation {
+            notes.push("the LLM judge is not re-run; its recorded verdict is kept".to_owned());
+            let recorded = DecisionReason {
+                code: event.final_reason_code,
+                params: event.reason_params.clone(),
+            };
+            (WorkflowStatus::BlockedBySemantic, recorded)
+        } else if firewall.action == FirewallAction::Sanitize {
+            (
+                WorkflowStatus::Sanitized,
+                DecisionReason::new(ReasonCode::Sanitized),
+            )
+        } else if let Some(sem) = semantic
+            .as_ref()
+            .filter(|sem| sem.risk_level == SemanticRiskLevel::Medium)
+        {
+            (
+                WorkflowStatus::Sanitized,
+                DecisionReason::new(ReasonCode::ElevatedSemanticRisk)
+                    .with_score("score", sem.similarity),
+            )
+        } else if event.final_reason_code == ReasonCode::ModerationNotConfigured {
+            notes.push(
+                "moderation was not configured for the original request; its recorded verdict is kept"
+                    .to_owned(),
+            );
+            (
+                WorkflowStatus::Sanitized,
+                DecisionReason::new(ReasonCode::ModerationNotConfigured),
+            )
+        } else {
+            (
+                WorkflowStatus::Completed,
+                DecisionReason::new(ReasonCode::AllChecksPassed),
+            )
+        };
+        // Moderation keeps its recorded text too, which also covers records written
+        // before reason codes existed
+        let final_reason_text = if moderation_blocked {
+            event.final_reason.clone()
+        } else {
+            final_reason.text()
+        };
+        let replayed_status = status.name();
+
+        let original = recorded_evidence(&event);
+        let mut config_fingerprint = self.config_fingerprint();
+        config_fingerprint.firewall_rules = rules.fingerprint().to_owned();
+        // Moderation is not re-run, so its recorded severity carries over. Records written
+        // before risk scores existed keep their (absent) score rather than report a change.
+        let risk_inputs = original.risk_inputs.as_ref().map(|recorded| {
+            let (semantic_medium_cutoff, semantic_high_cutoff) =
+                self.semantic_service.risk_cutoffs();
+            RiskInputs {
+                firewall: firewall_signal(&firewall),
+                semantic_score: semantic.as_ref().map(|s| s.risk_score),
+                semantic_medium_cutoff,
+                semantic_high_cutoff,
+                bias: bias.score,
+                moderation: recorded.moderation,
+                degraded_stages: degraded_stages.len(),
+                blocked: final_decision(&replayed_status) == "block",
+            }
+        });
+        let replayed_risk_score = risk_inputs.as_ref().map_or(original.risk_score, |inputs| {
+            risk_score(inputs, &self.risk_weights)
+        });
+        let replayed = DecisionEvidence {
+            firewall_action: serialized_name(&firewall.action),
+            firewall_matched_rules: firewall.matched_rules.clone(),
+            semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
+            semantic_matched_template: semantic
+                .as_ref()
+                .and_then(|s| s.nearest_template_id.clone()),
+            semantic_category: semantic.as_ref().and_then(|s| s.category.clone()),
+            moderation_flagged: matches!(
+                status,
+                WorkflowStatus::BlockedByInputModeration
+                    | WorkflowStatus::BlockedByOutputModeration
+            ),
+            moderation_categories: if moderation_blocked {
+                original.moderation_categories.clone()
+            } else {
+                Vec::new()
+            },
+            moderation_scope: None,
+            final_decision: final_decision(&replayed_status).to_owned(),
+            final_reason: final_reason_text,
+            final_reason_code: final_reason.code,
+            reason_params: final_reason.params,
+            decisive_step: None,
+            policy_rule: None,
+            similar_blocked_correlation_id: original.similar_blocked_correlation_id.clone(),
+            config_fingerprint: Some(config_fingerprint),
+            request_fingerprint: None,
+            preprocessing: preprocessor.apply(&event.original_prompt).applied,
+            semantic_skipped_reason: None,
+            moderation_skipped_reason: event.moderation_skipped_reason.clone(),
+            disabled_stages: event.disabled_stages.clone(),
+            // Output moderation is not replayed
+            output_moderation_chunks: event.output_moderation_chunks.clone(),
+            applied_exemptions: event.applied_exemptions.clone(),
+            degraded_stages,
+            risk_score: replayed_risk_score,
+            risk_inputs,
+            language_mix_detected: !mixed_languages.is_empty(),
+            mixed_languages,
+            template: event.template.clone(),
+            input_truncation: firewall.truncation,
+            // The judge is not replayed
+            llm_judge: event.llm_judge.clone(),
+            monitored_firewall_matches: firewall.monitored_matches.clone(),
+            threat_category: if moderation_blocked {
+                original.threat_category.clone()
+            } else {
+                LayerFindings {
+                    firewall: &firewall,
+                    semantic: semantic.as_ref(),
+                    input_moderation: None,
+                    output_moderation: [None, None],
+                    bias: &bias,
+                }
+                .resolve(&status)
+            },
+        };
+
+        let mut differences = evidence_changes(&original, &replayed);
+        let recorded_bias_level = current_name::<BiasLevel>(&event.bias_level);
+        let replayed_bias_level = serialized_name(&bias.level);
+        if replayed_bias_level != recorded_bias_level {
+            differences.push(EvidenceChange {
+                field: "bias_level".to_owned(),
+                original: Value::from(recorded_bias_level),
+                replayed: Value::from(replayed_bias_level),
+            });
+        }
+
+        let regeneration = match mode {
+            ReplayMode::Regenerate => Some(self.regenerate(&event, &mut notes).await?),
+            ReplayMode::Current | ReplayMode::Historical => None,
+        };
+
+        let audit_proof = self
+            .audit_logger
+            .log_replay(ReplayEvent {
+                correlation_id: replay_correlation_id.to_owned(),
+                event_type: "replay".to_owned(),
+                replayed_correlation_id: correlation_id.to_owned(),
+                related_correlation_id: Some(correlation_id.to_owned()),
+                mode: mode.as_str().to_owned(),
+                firewall_rules: rules.fingerprint().to_owned(),
+                original_status: event.final_status.clone(),
+                replayed_status: replayed_status.clone(),
+                changed_fields: differences
+                    .iter()
+                    .map(|change| change.field.clone())
+                    .collect(),
+                output_matched: regeneration.as_ref().map(|r| r.output_matched),
+            })
+            .await?;
+
+        Ok(ReplayReport {
+            correlation_id: correlation_id.to_owned(),
+            replay_correlation_id: replay_correlation_id.to_owned(),
+            mode,
+            diverged: replayed_status != event.final_status,
+            original_status: event.final_status,
+            replayed_status,
+            original,
+            replayed,
+            differences,
+            notes,
+            regeneration,
+            audit_proof,
+        })
+    }
+
+    /// Resend the recorded generation input with the recorded parameters and compare the
+    /// output to the recorded one
+    async fn regenerate(
+        &self,
+        event: &AuditEvent,
+        notes: &mut Vec<String>,
+    ) -> Result<Regeneration, ReplayError> {
+        let not_regenerable = || ReplayError::NotRegenerable(event.correlation_id.clone());
+        let parameters = event
+            .generation_parameters
+            .clone()
+            .ok_or_else(not_regenerable)?;
+        let original_output = event
+            .full_output_text
+            .as_deref()
+            .ok_or_else(not_regenerable)?;
+        let digest = event
+            .generation_input_digest
+            .as_ref()
+            .ok_or_else(not_regenerable)?;
+        let user_content = digest.user_content.clone().ok_or_else(not_regenerable)?;
+
+        // The preamble is not recorded, only whether one was sent, so the current one
+        // stands in for it and the input hash tells whether it changed
+        let mut messages = Vec::with_capacity(2);
+        if digest
+            .transformations
+            .iter()
+            .any(|step| step == "hardening_preamble")
+            && let Some(preamble) = &self.generation_preamble
+        {
+            messages.push(ChatMessage {
+                role: "system".to_owned(),
+                content: preamble.clone(),
+            });
+        }
+        messages.push(ChatMessage {
+            role: "user".to_owned(),
+            content: user_content,
+        });
+        let input_matched = GenerationInputDigest::messages_hash(&messages) == digest.sha256;
+        if !input_matched {
+            notes.push(
+                "the hardening preamble changed since the original request, so the \
+                 generation input differs from the recorded one"
+                    .to_owned(),
+            );
+        }
+        if !parameters.deterministic {
+            notes.push(
+                "the original generation was not deterministic, so its output is not \
+                 expected to be reproduced"
+                    .to_owned(),
+            );
+        }
+
+        let response = self
+            .mistral_service
+            .complete(parameters.request(messages))
+            .await?;
+        let original_output_hash = content_ref(original_output);
+        let regenerated_output_hash = content_ref(&response.output_text);
+        Ok(Regeneration {
+            parameters,
+            input_matched,
+            output_matched: original_output_hash == regenerated_output_hash,
+            original_output_hash,
+            regenerated_output_hash,
+        })
+    }
+
+    /// Newest prompt decision recorded under `correlation_id`, with its timestamp
+    pub(super) fn recorded_event(
+        &self,
+        correlation_id: &str,
+    ) -> Result<(DateTime<Utc>, AuditEvent), ReplayError> {
+        let records = self
+            .audit_logger
+            .storage()
+            .get_with_filters(None, None, None, None, Some(correlation_id.to_owned()))?
+            .records;
+        records
+            .into_iter()
+            .filter_map(|record| {
+                // Configuration, exemption and replay records share correlation ids but
+                // are not prompt decisions
+                let event = serde_json::from_str::<AuditEvent>(&record.payload).ok()?;
+                Some((record.timestamp, event))
+            })
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .ok_or_else(|| ReplayError::NotFound(correlation_id.to_owned()))
+    }
+}
+
+/// Evidence as it can be rebuilt from an audit record
+///
+/// The moderation scope is not recorded, and older records lack the decisive step
+/// index, so both are left out on both sides of the comparison.
+fn recorded_evidence(event: &AuditEvent) -> DecisionEvidence {
+    let firewall_matched_rules = event
+        .decision_trace
+        .iter()
+        .find(|step| step.stage == "firewall")
+        .map(|step| {
+            step.rule_refs
+                .iter()
+                .filter(|rule_ref| !rule_ref.starts_with("exemption:"))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    DecisionEvidence {
+        firewall_action: current_name::<FirewallAction>(&event.firewall_action),
+        firewall_matched_rules,
+        semantic_risk_score: event.semantic_risk_score,
+        semantic_matched_template: event.semantic_template_id.clone(),
+        semantic_category: event.semantic_category.clone(),
+        moderation_flagged: event.input_moderation_flagged || event.output_moderation_flagged,
+        moderation_categories: event.output_moderation_categories.clone(),
+        moderation_scope: None,
+        final_decision: final_decision(&event.final_status).to_owned(),
+        final_reason: event.final_reason.clone(),
+        final_reason_code: event.final_reason_code,
+        reason_params: event.reason_params.clone(),
+        decisive_step: None,
+        policy_rule: None,
+        similar_blocked_correlation_id: event.similar_blocked_correlation_id.clone(),
+        config_fingerprint: Some(event.config_fingerprint.clone()),
+        request_fingerprint: None,
+        preprocessing: event.preprocessing.clone(),
+        semantic_skipped_reason: event.semantic_skipped_reason.clone(),
+        moderation_skipped_reason: event.moderation_skipped_reason.clone(),
+        disabled_stages: event.disabled_stages.clone(),
+        output_moderation_chunks: event.output_moderation_chunks.clone(),
+        applied_exemptions: event.applied_exemptions.clone(),
+        degraded_stages: event.degraded_stages.clone(),
+        risk_score: event.risk_score.unwrap_or_default(),
+        risk_inputs: event.risk_inputs.clone(),
+        language_mix_detected: event.language_mix_detected,
+        mixed_languages: event.mixed_languages.clone(),
+        template: event.template.clone(),
+        input_truncation: event.input_truncation,
+        threat_category: event.threat_category.clone(),
+        llm_judge: event.llm_judge.clone(),
+        monitored_firewall_matches: event.monitored_firewall_matches.clone(),
+    }
+}
+
+/// A recorded enum name in its current serialization; records written before API
+/// version 2 hold the PascalCase names
+pub(super) fn current_name<T: Serialize + DeserializeOwned>(recorded: &str) -> String {
+    serde_json::from_value::<T>(Value::from(recorded))
+        .map(|value| serialized_name(&value))
+        .unwrap_or_else(|_| recorded.to_owned())
+}
+
+fn final_decision(audit_status: &str) -> &'static str {
+    match audit_status {
+        "completed" => "allow",
+        "sanitized" => "sanitize",
+        _ => "block",
+    }
+}
+
+/// Top-level evidence fields whose serialized values differ
+fn evidence_changes(
+    original: &DecisionEvidence,
+    replayed: &DecisionEvidence,
+) -> Vec<EvidenceChange> {
+    let (Ok(Value::Object(original)), Ok(Value::Object(replayed))) = (
+        serde_json::to_value(original),
+        serde_json::to_value(replayed),
+    ) else {
+        return Vec::new();
+    };
+    let mut fields: Vec<&String> = original.keys().chain(replayed.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let before = original.get(field).cloned().unwrap_or(Value::Null);
+            let after = replayed.get(field).cloned().unwrap_or(Value::Null);
+            (before != after).then(|| EvidenceChange {
+                field: field.clone(),
+                original: before,
+                replayed: after,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("no audited decision found for correlation id {0}")]
+    NotFound(String),
+    #[error(
+        "the prompt for {0} was not stored (AUDIT_PROMPT_STORAGE is not 'full'), so it cannot be replayed"
+    )]
+    PromptNotStored(String),
+    #[error("firewall rules {0} are no longer archived; only current mode is available")]
+    RulesNotArchived(String),
+    #[error(
+        "{0} recorded no generation with its parameters and output, so it cannot be regenerated"
+    )]
+    NotRegenerable(String),
+    #[error("failed to regenerate: {0}")]
+    Generation(#[from] MistralServiceError),
+    #[error("failed to read archived firewall rules: {0}")]
+    Archive(#[from] RulesArchiveError),
+    #[error("failed to read the audit trail: {0}")]
+    Storage(#[from] AuditStorageError),
+    #[error("failed to audit the replay: {0}")]
+    Audit(#[from] AuditError),
+}
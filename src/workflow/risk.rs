@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+
+use crate::modules::prompt_firewall::dtos::{
+    FirewallAction, FirewallSeverity, PromptFirewallResult,
+};
+
+/// Lowest score of a blocked request unless configured otherwise
+pub const DEFAULT_BLOCKED_FLOOR: u8 = 80;
+
+/// Points each signal contributes to the composite risk score, set with `RISK_WEIGHT_*`
+///
+/// Every signal is first scaled to 0.0–1.0 and then multiplied by its weight; the sum,
+/// plus `degraded_stage` points per failed stage, is rounded and capped at 100. The
+/// defaults add up to 100, so a prompt that maxes out every signal scores 100.
+///
+/// The score never contradicts the decision: blocked requests score at least
+/// `blocked_floor` and requests that went through score below it.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RiskWeights {
+    /// Firewall severity of a flagged, sanitized or blocked prompt
+    pub firewall: f32,
+    /// Semantic risk score, relative to the Medium and High cutoffs
+    pub semantic: f32,
+    /// Bias score
+    pub bias: f32,
+    /// Highest moderation severity of any pass
+    pub moderation: f32,
+    /// Added per stage that failed, since its signal is missing
+    pub degraded_stage: f32,
+    pub blocked_floor: u8,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            firewall: 40.0,
+            semantic: 30.0,
+            bias: 10.0,
+            moderation: 20.0,
+            degraded_stage: 10.0,
+            blocked_floor: DEFAULT_BLOCKED_FLOOR,
+        }
+    }
+}
+
+impl RiskWeights {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, weight) in [
+            ("firewall", self.firewall),
+            ("semantic", self.semantic),
+            ("bias", self.bias),
+            ("moderation", self.moderation),
+            ("degraded stage", self.degraded_stage),
+        ] {
+            if !weight.is_finite() || !(0.0..=100.0).contains(&weight) {
+                return Err(format!("risk weight {name} must be within 0..=100"));
+            }
+        }
+        if !(1..=100).contains(&self.blocked_floor) {
+            return Err("risk blocked floor must be within 1..=100".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Signals a risk score was computed from, recorded with the decision
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RiskInputs {
+    /// Firewall severity scaled to 0.0–1.0; 0.0 when the firewall allowed the prompt
+    pub firewall: f32,
+    /// Semantic risk score, when the semantic scan ran
+    pub semantic_score: Option<f32>,
+    /// Semantic score at which risk becomes Medium, decision margin included
+    pub semantic_medium_cutoff: f32,
+    /// Semantic score at which risk becomes High and the request is blocked
+    pub semantic_high_cutoff: f32,
+    /// Bias score (0.0–1.0)
+    pub bias: f32,
+    /// Highest severity reported by input or output moderation
+    pub moderation: f32,
+    /// Stages that failed while the request was processed
+    pub degraded_stages: usize,
+    pub blocked: bool,
+}
+
+/// Firewall signal: severity scaled to 0.25–1.0 unless the prompt was allowed outright
+pub fn firewall_signal(firewall: &PromptFirewallResult) -> f32 {
+    if firewall.action == FirewallAction::Allow {
+        return 0.0;
+    }
+    match firewall.severity {
+        FirewallSeverity::Low => 0.25,
+        FirewallSeverity::Medium => 0.5,
+        FirewallSeverity::High => 0.75,
+        FirewallSeverity::Critical => 1.0,
+    }
+}
+
+/// Semantic signal: 0.0–0.5 up to the medium cutoff, 0.5–1.0 between the cutoffs and
+/// 1.0 from the high cutoff on
+fn semantic_signal(score: f32, medium_cutoff: f32, high_cutoff: f32) -> f32 {
+    if score >= high_cutoff {
+        1.0
+    } else if score >= medium_cutoff {
+        0.5 + 0.5 * (score - medium_cutoff) / (high_cutoff - medium_cutoff)
+    } else if medium_cutoff > 0.0 {
+        0.5 * score / medium_cutoff
+    } else {
+        0.0
+    }
+}
+
+/// Composite risk score from 0 to 100
+pub fn risk_score(inputs: &RiskInputs, weights: &RiskWeights) -> u8 {
+    let unit = |value: f32| {
+        if value.is_finite() {
+            value.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    let semantic = inputs.semantic_score.map_or(0.0, |score| {
+        semantic_signal(
+            unit(score),
+            inputs.semantic_medium_cutoff,
+            inputs.semantic_high_cutoff,
+        )
+    });
+    let points = weights.firewall * unit(inputs.firewall)
+        + weights.semantic * semantic
+        + weights.bias * unit(inputs.bias)
+        + weights.moderation * unit(inputs.moderation)
+        + weights.degraded_stage * inputs.degraded_stages as f32;
+    let score = points.round().clamp(0.0, 100.0) as u8;
+    let floor = weights.blocked_floor.max(1);
+    if inputs.blocked {
+        score.max(floor)
+    } else {
+        score.min(floor - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cutoffs of the default 0.70 / 0.80 thresholds with the 0.02 margin
+    fn clean() -> RiskInputs {
+        RiskInputs {
+            semantic_score: Some(0.2),
+            semantic_medium_cutoff: 0.72,
+            semantic_high_cutoff: 0.82,
+            bias: 0.05,
+            moderation: 0.01,
+            ..RiskInputs::default()
+        }
+    }
+
+    fn score(inputs: RiskInputs) -> u8 {
+        risk_score(&inputs, &RiskWeights::default())
+    }
+
+    fn firewall(action: FirewallAction, severity: FirewallSeverity) -> PromptFirewallResult {
+        PromptFirewallResult {
+            action,
+            severity,
+            sanitized_prompt: String::new(),
+            reasons: vec![],
+            matched_rules: vec![],
+            sanitization_edits: vec![],
+            downgrade_reason: None,
+            block_matches: vec![],
+            mixed_languages: vec![],
+            translated: false,
+            truncation: None,
+            monitored_matches: Vec::new(),
+            urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn clean_allow_scores_low() {
+        assert_eq!(score(clean()), 5);
+        assert_eq!(score(RiskInputs::default()), 0);
+        // A prompt the semantic scan skipped counts as no semantic risk
+        assert_eq!(
+            score(RiskInputs {
+                semantic_score: None,
+                ..clean()
+            }),
+            1
+        );
+    }
+
+    #[test]
+    fn sanitized_prompts_score_between_allow_and_block() {
+        let sanitized = score(RiskInputs {
+            firewall: firewall_signal(&firewall(
+                FirewallAction::Sanitize,
+                FirewallSeverity::Medium,
+            )),
+            ..clean()
+        });
+        assert_eq!(sanitized, 25);
+        // Elevated semantic risk sanitizes too: halfway between the cutoffs
+        let elevated = score(RiskInputs {
+            semantic_score: Some(0.77),
+            ..clean()
+        });
+        assert_eq!(elevated, 23);
+        assert!(sanitized < DEFAULT_BLOCKED_FLOOR && elevated < DEFAULT_BLOCKED_FLOOR);
+    }
+
+    #[test]
+    fn every_block_type_reaches_the_floor() {
+        let firewall_block = RiskInputs {
+            firewall: firewall_signal(&firewall(FirewallAction::Block, FirewallSeverity::Critical)),
+            semantic_score: None,
+            blocked: true,
+            ..clean()
+        };
+        let semantic_block = RiskInputs {
+            semantic_score: Some(0.9),
+            blocked: true,
+            ..clean()
+        };
+        let input_moderation_block = RiskInputs {
+            moderation: 0.9,
+            blocked: true,
+            ..clean()
+        };
+        let output_moderation_block = RiskInputs {
+            moderation: 0.6,
+            blocked: true,
+            ..clean()
+        };
+        // EU compliance blocks carry no other signal
+        let eu_block = RiskInputs {
+            semantic_score: None,
+            blocked: true,
+            ..clean()
+        };
+        let stage_failure_block = RiskInputs {
+            semantic_score: None,
+            degraded_stages: 1,
+            blocked: true,
+            ..clean()
+        };
+        for inputs in [
+            firewall_block,
+            semantic_block,
+            input_moderation_block,
+            output_moderation_block,
+            eu_block,
+            stage_failure_block,
+        ] {
+            assert_eq!(score(inputs.clone()), DEFAULT_BLOCKED_FLOOR, "{inputs:?}");
+        }
+
+        let everything = RiskInputs {
+            firewall: 1.0,
+            semantic_score: Some(1.0),
+            bias: 1.0,
+            moderation: 1.0,
+            blocked: true,
+            ..clean()
+        };
+        assert_eq!(score(everything), 100);
+    }
+
+    #[test]
+    fn degraded_stages_add_uncertainty_without_crossing_the_floor() {
+        let one = score(RiskInputs {
+            degraded_stages: 1,
+            ..clean()
+        });
+        assert_eq!(one, 15);
+        let many = score(RiskInputs {
+            degraded_stages: 20,
+            ..clean()
+        });
+        assert_eq!(many, DEFAULT_BLOCKED_FLOOR - 1);
+    }
+
+    #[test]
+    fn score_never_contradicts_the_decision() {
+        let steps = [0.0, 0.3, 0.71, 0.77, 0.85, 1.0];
+        for weights in [
+            RiskWeights::default(),
+            RiskWeights {
+                firewall: 100.0,
+                semantic: 100.0,
+                bias: 100.0,
+                moderation: 100.0,
+                degraded_stage: 100.0,
+                blocked_floor: 50,
+            },
+            RiskWeights {
+                firewall: 0.0,
+                semantic: 0.0,
+                bias: 0.0,
+                moderation: 0.0,
+                degraded_stage: 0.0,
+                blocked_floor: 100,
+            },
+        ] {
+            for firewall in steps {
+                for semantic in steps {
+                    for moderation in steps {
+                        for blocked in [false, true] {
+                            let inputs = RiskInputs {
+                                firewall,
+                                semantic_score: Some(semantic),
+                                moderation,
+                                blocked,
+                                ..clean()
+                            };
+                            let score = risk_score(&inputs, &weights);
+                            assert!(score <= 100);
+                            assert_eq!(score >= weights.blocked_floor, blocked, "{inputs:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn signals_raise_the_score_monotonically() {
+        let mut previous = 0;
+        for semantic in [0.0, 0.36, 0.72, 0.77, 0.82] {
+            let current = score(RiskInputs {
+                semantic_score: Some(semantic),
+                ..clean()
+            });
+            assert!(current >= previous, "{semantic}: {current} < {previous}");
+            previous = current;
+        }
+        let severities = [
+            FirewallSeverity::Low,
+            FirewallSeverity::Medium,
+            FirewallSeverity::High,
+            FirewallSeverity::Critical,
+        ];
+        let signals: Vec<f32> = severities
+            .into_iter()
+            .map(|severity| firewall_signal(&firewall(FirewallAction::Flag, severity)))
+            .collect();
+        assert_eq!(signals, [0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(
+            firewall_signal(&firewall(FirewallAction::Allow, FirewallSeverity::High)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn malformed_signals_are_clamped() {
+        let inputs = RiskInputs {
+            firewall: f32::NAN,
+            semantic_score: Some(-0.4),
+            bias: 7.0,
+            moderation: f32::INFINITY,
+            ..clean()
+        };
+        assert_eq!(score(inputs), 10);
+        // Equal cutoffs put everything at or above them in the high band
+        let collapsed = RiskInputs {
+            semantic_score: Some(0.8),
+            semantic_medium_cutoff: 0.8,
+            semantic_high_cutoff: 0.8,
+            ..RiskInputs::default()
+        };
+        assert_eq!(score(collapsed), 30);
+    }
+
+    #[test]
+    fn weights_are_validated() {
+        assert!(RiskWeights::default().validate().is_ok());
+        for weights in [
+            RiskWeights {
+                firewall: -1.0,
+                ..RiskWeights::default()
+            },
+            RiskWeights {
+                moderation: f32::NAN,
+                ..RiskWeights::default()
+            },
+            RiskWeights {
+                degraded_stage: 101.0,
+                ..RiskWeights::default()
+            },
+            RiskWeights {
+                blocked_floor: 0,
+                ..RiskWeights::default()
+            },
+            RiskWeights {
+                blocked_floor: 101,
+                ..RiskWeights::default()
+            },
+        ] {
+            assert!(weights.validate().is_err(), "{weights:?}");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! What happened to each stage of a request, so an absent result says why it is absent
+
+use serde::{Deserialize, Serialize};
+
+use super::failure_policy::{PipelineStage, StageFailure};
+use crate::modules::bias_detection::dtos::BiasScanResult;
+use crate::modules::mistral_ai::dtos::ModerationResponse;
+use crate::modules::semantic_detection::dtos::SemanticScanResult;
+
+/// Outcome of one stage: its result when it ran, otherwise why there is none
+///
+/// Serialized with a `status` tag: `{"status": "ran", "result": ...}`,
+/// `{"status": "skipped", "reason": ...}`, `{"status": "failed", "error": ...}` or
+/// `{"status": "not_reached"}`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StageOutcome<T> {
+    Ran {
+        result: T,
+    },
+    /// The stage was reached but deliberately not run, e.g. `sampled_out`
+    Skipped {
+        reason: String,
+    },
+    /// The stage ran and its call failed; the request carried on or was blocked as the
+    /// stage failure policy says
+    Failed {
+        error: String,
+    },
+    /// The request was decided before the stage
+    #[default]
+    NotReached,
+}
+
+impl<T> StageOutcome<T> {
+    /// The outcome of a stage that `reached` says the request got to
+    ///
+    /// A stage without a result was skipped when it has a skip reason, and otherwise
+    /// failed if a failure of its pipeline stage was recorded.
+    fn of(
+        result: Option<T>,
+        reached: bool,
+        skipped_reason: Option<String>,
+        failure: Option<&StageFailure>,
+    ) -> Self {
+        match (result, skipped_reason, failure) {
+            (Some(result), _, _) => Self::Ran { result },
+            _ if !reached => Self::NotReached,
+            (None, Some(reason), _) => Self::Skipped { reason },
+            (None, None, Some(failure)) => Self::Failed {
+                error: failure.error.clone(),
+            },
+            (None, None, None) => Self::NotReached,
+        }
+    }
+
+    /// The result, when the stage ran
+    pub fn ran(&self) -> Option<&T> {
+        match self {
+            Self::Ran { result } => Some(result),
+            _ => None,
+        }
+    }
+
+    pub fn into_ran(self) -> Option<T> {
+        match self {
+            Self::Ran { result } => Some(result),
+            _ => None,
+        }
+    }
+
+    /// The outcome without its result, as the audit record keeps it
+    pub fn status(&self) -> StageStatus {
+        match self {
+            Self::Ran { .. } => StageStatus::Ran,
+            Self::Skipped { reason } => StageStatus::Skipped {
+                reason: reason.clone(),
+            },
+            Self::Failed { error } => StageStatus::Failed {
+                error: error.clone(),
+            },
+            Self::NotReached => StageStatus::NotReached,
+        }
+    }
+}
+
+/// [`StageOutcome`] without the stage's result
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StageStatus {
+    Ran,
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        error: String,
+    },
+    #[default]
+    NotReached,
+}
+
+/// What generation produced; the text itself is the response's `generated_text`, which
+/// a blocked answer leaves out
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GenerationOutcome {
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_used: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// The answer was translated back into the prompt's language
+    pub translated: bool,
+}
+
+/// Outcome of every stage whose result a response may lack
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct StageOutcomes {
+    pub semantic: StageOutcome<SemanticScanResult>,
+    pub bias: StageOutcome<BiasScanResult>,
+    pub input_moderation: StageOutcome<ModerationResponse>,
+    pub output_moderation: StageOutcome<ModerationResponse>,
+    pub generation: StageOutcome<GenerationOutcome>,
+}
+
+impl StageOutcomes {
+    pub fn statuses(&self) -> StageStatuses {
+        StageStatuses {
+            semantic: self.semantic.status(),
+            bias: self.bias.status(),
+            input_moderation: self.input_moderation.status(),
+            output_moderation: self.output_moderation.status(),
+            generation: self.generation.status(),
+        }
+    }
+}
+
+/// [`StageOutcomes`] as the audit record keeps them
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageStatuses {
+    pub semantic: StageStatus,
+    pub bias: StageStatus,
+    pub input_moderation: StageStatus,
+    pub output_moderation: StageStatus,
+    pub generation: StageStatus,
+}
+
+/// One stage as a finished run left it, for [`StageOutcome::of`]
+pub(super) struct StageEvidence<T> {
+    pub result: Option<T>,
+    /// A trace step of the stage was recorded
+    pub reached: bool,
+    pub skipped_reason: Option<String>,
+}
+
+impl<T> StageEvidence<T> {
+    /// The outcome, taking the failure of `stage` from `failures`
+    ///
+    /// Input and output passes share a pipeline stage; `last` picks the failure recorded
+    /// last, which belongs to the output pass.
+    pub fn outcome(
+        self,
+        failures: &[StageFailure],
+        stage: PipelineStage,
+        last: bool,
+    ) -> StageOutcome<T> {
+        let mut matching = failures.iter().filter(|failure| failure.stage == stage);
+        let failure = if last {
+            matching.next_back()
+        } else {
+            matching.next()
+        };
+        StageOutcome::of(self.result, self.reached, self.skipped_reason, failure)
+    }
+}
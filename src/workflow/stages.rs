@@ -0,0 +1,775 @@
+//! The stages a request moves through, in the order `run_stages` calls them
+//!
+//! Each stage reads what the earlier ones left in the run, stores its result there and
+//! records its trace step. Whether a result blocks the request is left to the decision
+//! policy checkpoints in `run_stages`.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use tracing::Instrument;
+
+use super::cancellation::{AbandonedStage, Abandonment};
+use super::{
+    ComplianceEngine, DISABLED_BY_ADMIN, DecisionReason, GenerationRecord, PipelineStage,
+    PolicyAction, PolicyField, ReasonCode, RunKind, ToggleableStage, TraceStep, UnmoderatedPolicy,
+    Verdict, WorkflowError, WorkflowRun, WorkflowStatus, content_ref, elapsed_ms, moderation_step,
+    policy_verdict, timed,
+};
+use crate::modules::bias_detection::dtos::{BiasScanRequest, BiasScanResult};
+use crate::modules::bias_detection::model::BiasLevel;
+use crate::modules::demo::service::{DEMO_MODEL, canned_response};
+use crate::modules::eu_law_compliance::model::AiRiskTier;
+use crate::modules::mistral_ai::dtos::ModerationResponse;
+use crate::modules::mistral_ai::service::MODERATION_NOT_CONFIGURED;
+use crate::modules::prompt_firewall::dtos::{
+    FirewallAction, PromptFirewallRequest, PromptFirewallResult,
+};
+use crate::modules::repeat_offender::dtos::EscalationMode;
+use crate::modules::sanitize_probing::service::RemovedContent;
+use crate::modules::semantic_detection::dtos::{
+    SemanticRiskLevel, SemanticScanRequest, SemanticScanResult,
+};
+use crate::modules::semantic_detection::service::SEMANTIC_NOT_INITIALIZED;
+use crate::modules::telemetry::tracing::{log_with_correlation, stage_span};
+
+/// What the semantic scan and input moderation leave for the checkpoints after them
+pub(super) struct InputScan {
+    /// Trace index of the input moderation step
+    pub(super) moderation_step: usize,
+    /// Moderation of the content sanitization stripped out, with its trace step, when
+    /// it was checked
+    pub(super) removed_content: Option<(ModerationResponse, TraceStep)>,
+}
+
+impl ComplianceEngine {
+    /// Rewrite the prompt with the configured preprocessors
+    ///
+    /// Every later stage sees the rewritten prompt; the audit record keeps the original.
+    /// A template's values are preprocessed one by one and joined, so the later stages
+    /// see the values without the template text.
+    pub(super) fn preprocess(&self, run: &mut WorkflowRun) {
+        let stage_start = Instant::now();
+        let (prompt, preprocessing) = match &mut run.template {
+            Some(template) => template.preprocess(&self.preprocessor),
+            None => {
+                let preprocessed = self.preprocessor.apply(&run.original_prompt);
+                (preprocessed.text, preprocessed.applied)
+            }
+        };
+        run.prompt = prompt;
+        run.preprocessing = preprocessing;
+        if self.preprocessor.is_empty() {
+            return;
+        }
+        let step = TraceStep {
+            stage: "preprocessing".to_owned(),
+            inputs: vec![content_ref(&run.original_prompt)],
+            verdict: "allow".to_owned(),
+            rule_refs: run
+                .preprocessing
+                .iter()
+                .filter(|applied| applied.changed)
+                .map(|applied| applied.transform.as_str().to_owned())
+                .collect(),
+            parameters: BTreeMap::new(),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.record(step);
+    }
+
+    /// Detect the prompt's language, for translating the response back, and translate
+    /// the prompt for the checks that read English
+    ///
+    /// When detection fails and the stage fails open, the prompt is treated as English.
+    pub(super) async fn detect_language(&self, run: &mut WorkflowRun) {
+        run.original_language = match self
+            .mistral_service
+            .detect_language(run.prompt.clone())
+            .await
+        {
+            Ok(detection) => detection.language,
+            Err(e) => {
+                self.stage_failed(
+                    &run.correlation_id,
+                    &mut run.stage_failures,
+                    PipelineStage::Language,
+                    &e,
+                );
+                "English".to_owned()
+            }
+        };
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::DEBUG,
+            &format!("Detected original language: {}", run.original_language),
+        );
+        // The firewall translates a template's values one by one instead
+        if run.template.is_none() {
+            run.english_prompt = self
+                .translate_prompt(&run.correlation_id, &run.prompt, run.is_english())
+                .await;
+        }
+    }
+
+    /// Run the firewall, then evaluate the prompt again without the rules it holds
+    /// exemptions for
+    pub(super) async fn check_firewall(&self, run: &mut WorkflowRun) {
+        let stage_start = Instant::now();
+        let request = PromptFirewallRequest {
+            prompt: run.prompt.clone(),
+            correlation_id: Some(run.correlation_id.clone()),
+            detected_language: Some(run.original_language.clone()),
+            pre_translated_text: run.english_prompt.clone(),
+        };
+        let span = stage_span("firewall");
+        let firewall = self
+            .inspect_firewall(run, &request, &[])
+            .instrument(span.clone())
+            .await;
+        run.firewall = firewall;
+        self.apply_exemptions(run, &request)
+            .instrument(span.clone())
+            .await;
+        let action = format!("{:?}", run.firewall.action).to_lowercase();
+        span.record("action", action.as_str());
+        drop(span);
+        let step = TraceStep {
+            stage: "firewall".to_owned(),
+            inputs: vec![content_ref(&run.prompt)],
+            verdict: action.clone(),
+            rule_refs: run
+                .firewall
+                .matched_rules
+                .iter()
+                .cloned()
+                .chain(
+                    run.applied_exemptions
+                        .iter()
+                        .map(|applied| format!("exemption:{}", applied.exemption_id)),
+                )
+                .collect(),
+            parameters: BTreeMap::from([
+                (
+                    "max_input_length".to_owned(),
+                    self.firewall_service.max_input_length() as f64,
+                ),
+                (
+                    "fuzzy_max_distance".to_owned(),
+                    self.firewall_service.fuzzy_max_distance() as f64,
+                ),
+            ]),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.record(step);
+        run.policy_evidence
+            .set(PolicyField::FirewallAction, action.as_str());
+    }
+
+    /// Inspect the prompt, or each of a template's values, as if the rules in
+    /// `excluded_rules` were not configured
+    async fn inspect_firewall(
+        &self,
+        run: &mut WorkflowRun,
+        request: &PromptFirewallRequest,
+        excluded_rules: &[String],
+    ) -> PromptFirewallResult {
+        let is_english = run.is_english();
+        match &mut run.template {
+            Some(template) => {
+                template
+                    .inspect(self, request, is_english, excluded_rules)
+                    .await
+            }
+            None => {
+                self.firewall_service
+                    .inspect_excluding(request.clone(), excluded_rules)
+                    .await
+            }
+        }
+    }
+
+    /// Drop the matched rules the prompt holds exemptions for and evaluate it again, so
+    /// the remaining rules still apply. Self-tests never use up exemptions.
+    async fn apply_exemptions(&self, run: &mut WorkflowRun, request: &PromptFirewallRequest) {
+        if run.kind != RunKind::Live || run.firewall.matched_rules.is_empty() {
+            return;
+        }
+        run.applied_exemptions = self
+            .exemptions
+            .claim(&run.firewall.matched_rules, &run.prompt);
+        if run.applied_exemptions.is_empty() {
+            return;
+        }
+        let exempted: Vec<String> = run
+            .applied_exemptions
+            .iter()
+            .map(|applied| applied.rule_id.clone())
+            .collect();
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::INFO,
+            &format!("Firewall exemptions applied for {}", exempted.join(", ")),
+        );
+        let firewall = self.inspect_firewall(run, request, &exempted).await;
+        run.firewall = firewall;
+    }
+
+    /// Classify the prompt under the EU AI Act
+    pub(super) fn check_eu_compliance(&self, run: &mut WorkflowRun) {
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::INFO,
+            "Performing EU AI Act compliance check",
+        );
+        let stage_start = Instant::now();
+        run.eu_compliance = self.eu_compliance_service.check_prompt(&run.prompt);
+        let step = TraceStep {
+            stage: "eu_compliance".to_owned(),
+            inputs: vec![content_ref(&run.prompt)],
+            verdict: if matches!(run.eu_compliance.risk_tier, AiRiskTier::Unacceptable) {
+                "block"
+            } else {
+                "allow"
+            }
+            .to_owned(),
+            rule_refs: run
+                .eu_compliance
+                .findings
+                .iter()
+                .map(|f| f.code.clone())
+                .collect(),
+            parameters: BTreeMap::new(),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.record(step);
+        let tier = format!("{:?}", run.eu_compliance.risk_tier).to_lowercase();
+        run.policy_evidence
+            .set(PolicyField::EuRiskTier, tier.as_str());
+    }
+
+    /// Scan the prompt for bias
+    ///
+    /// The firewall hands back an English rendering of non-English prompts; the bias scan
+    /// gets the original wording so a native term pack can be used when one exists.
+    pub(super) async fn scan_bias(&self, run: &mut WorkflowRun) {
+        let text = match &run.template {
+            _ if run.is_english() => run.firewall.sanitized_prompt.clone(),
+            Some(template) => template.fully_scanned_text(),
+            None => run.prompt.clone(),
+        };
+        let text_ref = content_ref(&text);
+        let stage_start = Instant::now();
+        let span = stage_span("bias");
+        let disabled = run.is_disabled(ToggleableStage::Bias);
+        let result = if disabled {
+            Ok(BiasScanResult::not_scanned())
+        } else {
+            self.bias_service
+                .try_scan(BiasScanRequest {
+                    text,
+                    threshold: None,
+                    language_hint: Some(run.original_language.clone()),
+                    pre_translated_text: if run.is_english() || run.template.is_some() {
+                        None
+                    } else {
+                        run.english_prompt.clone()
+                    },
+                })
+                .instrument(span.clone())
+                .await
+        };
+        run.bias = match result {
+            Ok(bias) => bias,
+            Err(failure) => {
+                span.record("status", "failed");
+                self.stage_failed(
+                    &run.correlation_id,
+                    &mut run.stage_failures,
+                    PipelineStage::Bias,
+                    &failure,
+                );
+                failure.fallback
+            }
+        };
+        let level = format!("{:?}", run.bias.level).to_lowercase();
+        span.record("score", f64::from(run.bias.score))
+            .record("action", level.as_str());
+        if disabled {
+            span.record("status", "skipped");
+        }
+        drop(span);
+        let step = TraceStep {
+            stage: "bias".to_owned(),
+            inputs: vec![text_ref],
+            verdict: if disabled {
+                "skip"
+            } else if run.bias.level == BiasLevel::Low {
+                "allow"
+            } else {
+                "flag"
+            }
+            .to_owned(),
+            rule_refs: run
+                .bias
+                .categories
+                .iter()
+                .map(|category| format!("{category:?}"))
+                .chain(
+                    run.bias
+                        .term_pack
+                        .iter()
+                        .map(|pack| format!("term_pack:{pack}")),
+                )
+                .chain(disabled.then(|| DISABLED_BY_ADMIN.to_owned()))
+                .collect(),
+            parameters: BTreeMap::from([(
+                "bias_threshold".to_owned(),
+                f64::from(self.bias_service.default_threshold()),
+            )]),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.record(step);
+        run.policy_evidence
+            .set(PolicyField::BiasLevel, level.as_str());
+    }
+
+    /// Compare the prompt against recently blocked prompts, when tracking is on
+    pub(super) async fn check_repeat_offender(&self, run: &mut WorkflowRun) {
+        let stage_start = Instant::now();
+        run.repeat_fingerprint = self.repeat_offenders.fingerprint(&run.prompt).await;
+        let Some(fingerprint) = &run.repeat_fingerprint else {
+            return;
+        };
+        run.repeat_match = self.repeat_offenders.find_similar(fingerprint);
+        let config = run.repeat_config;
+        let verdict = match (&run.repeat_match, config.mode) {
+            (Some(_), EscalationMode::Block) => "block",
+            (Some(_), _) => "flag",
+            (None, _) => "allow",
+        };
+        let step = TraceStep {
+            stage: "repeat_offender".to_owned(),
+            inputs: vec![content_ref(&run.prompt)],
+            verdict: verdict.to_owned(),
+            rule_refs: run
+                .repeat_match
+                .iter()
+                .map(|repeat| repeat.correlation_id.clone())
+                .collect(),
+            parameters: BTreeMap::from([
+                (
+                    "similarity_threshold".to_owned(),
+                    f64::from(config.similarity_threshold),
+                ),
+                ("window_size".to_owned(), config.window_size as f64),
+            ]),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.record(step);
+        run.policy_evidence.set(PolicyField::RepeatAction, verdict);
+    }
+
+    /// Count the prompt against its session when sanitization removed content, so
+    /// resubmitting a tweaked payload until sanitization misses it is caught
+    pub(super) fn check_sanitize_probing(&self, run: &mut WorkflowRun) {
+        let config = self.sanitize_probing.config();
+        let stage_start = Instant::now();
+        let removed = run.removed_fragments();
+        let Some(session_id) = &run.session_id else {
+            return;
+        };
+        if !config.enabled
+            || run.kind != RunKind::Live
+            || run.firewall.action != FirewallAction::Sanitize
+            || removed.is_empty()
+        {
+            return;
+        }
+        let escalation = self.sanitize_probing.observe(
+            session_id,
+            &run.correlation_id,
+            RemovedContent::new(&removed),
+        );
+        let step = TraceStep {
+            stage: "sanitize_probing".to_owned(),
+            inputs: vec![content_ref(&removed.join("\n"))],
+            verdict: if escalation.is_some() {
+                "block"
+            } else {
+                "allow"
+            }
+            .to_owned(),
+            rule_refs: escalation
+                .iter()
+                .flat_map(|escalation| escalation.prior_correlation_ids.iter().cloned())
+                .collect(),
+            parameters: BTreeMap::from([
+                ("threshold".to_owned(), config.threshold as f64),
+                ("window_secs".to_owned(), config.window_secs as f64),
+                (
+                    "max_edit_ratio".to_owned(),
+                    f64::from(config.max_edit_ratio),
+                ),
+            ]),
+            duration_ms: elapsed_ms(stage_start),
+        };
+        run.policy_evidence
+            .set(PolicyField::SanitizeProbing, escalation.is_some());
+        run.sanitize_probing = escalation;
+        run.record(step);
+    }
+
+    /// Whether the semantic scan is skipped, recording why when it is
+    pub(super) async fn skip_semantic(&self, run: &mut WorkflowRun) -> bool {
+        let reason = if run.is_disabled(ToggleableStage::Semantic) {
+            Some(DISABLED_BY_ADMIN)
+        } else if self.semantic_sampled_out(run) {
+            Some("sampled_out")
+        } else if !self.semantic_service.is_initialized().await {
+            Some(SEMANTIC_NOT_INITIALIZED)
+        } else {
+            None
+        };
+        run.semantic_skipped_reason = reason.map(str::to_owned);
+        reason.is_some()
+    }
+
+    /// Run the semantic scan and input moderation concurrently
+    ///
+    /// A close variant of a recently blocked prompt raises the semantic risk when
+    /// repeat offenders are tracked as a risk bonus.
+    pub(super) async fn scan_input(
+        &self,
+        run: &mut WorkflowRun,
+        abandonment: &Abandonment,
+    ) -> Result<InputScan, WorkflowError> {
+        log_with_correlation(
+            &run.correlation_id,
+            tracing::Level::INFO,
+            "Performing semantic scan and input moderation",
+        );
+        let skip_semantic = self.skip_semantic(run).await;
+        if self.mistral_service.moderation_model().is_none() {
+            run.moderation_skipped_reason = Some(MODERATION_NOT_CONFIGURED.to_owned());
+        }
+        let input_skipped_reason = run.moderation_skip(ToggleableStage::InputModeration);
+        // Fragments stripped by sanitization are moderated in the same call as the prompt
+        let removed_fragments = if self.policy().moderate_removed_content
+            && run.firewall.action == FirewallAction::Sanitize
+        {
+            run.removed_fragments()
+        } else {
+            Vec::new()
+        };
+        let semantic_span = stage_span("semantic");
+        let moderation_span = stage_span("input_moderation");
+        let joined = abandonment.unless_cancelled(async {
+            tokio::join!(
+                timed(
+                    async {
+                        if skip_semantic {
+                            return Ok(None);
+                        }
+                        self.semantic_service
+                            .scan(SemanticScanRequest {
+                                text: run.firewall.sanitized_prompt.clone(),
+                                detected_language: Some(run.firewall_language().to_owned()),
+                                pre_translated_text: None,
+                            })
+                            .await
+                            .map(Some)
+                    }
+                    .instrument(semantic_span.clone())
+                ),
+                timed(
+                    async {
+                        if input_skipped_reason.is_some() {
+                            return Ok(None);
+                        }
+                        self.moderate_input(&run.firewall.sanitized_prompt, &removed_fragments)
+                            .await
+                            .map(Some)
+                    }
+                    .instrument(moderation_span.clone())
+                )
+            )
+        });
+        let ((semantic_result, semantic_ms), (input_moderation_result, moderation_ms)) =
+            joined.await?;
+        match &semantic_result {
+            Ok(Some(semantic)) => semantic_span
+                .record("score", f64::from(semantic.risk_score))
+                .record(
+                    "action",
+                    format!("{:?}", semantic.risk_level).to_lowercase().as_str(),
+                ),
+            Ok(None) => semantic_span.record("status", "skipped"),
+            Err(_) => semantic_span.record("status", "failed"),
+        };
+        match &input_moderation_result {
+            Ok(Some((moderation, _))) => moderation_span.record("flagged", moderation.flagged),
+            Ok(None) => moderation_span.record("status", "skipped"),
+            Err(_) => moderation_span.record("status", "failed"),
+        };
+        drop((semantic_span, moderation_span));
+        let mut semantic = match semantic_result {
+            Ok(semantic) => semantic,
+            Err(e) => {
+                self.stage_failed(
+                    &run.correlation_id,
+                    &mut run.stage_failures,
+                    PipelineStage::Semantic,
+                    &e,
+                );
+                None
+            }
+        };
+        let (input_moderation, removed_moderation) = match input_moderation_result {
+            Ok(Some((moderation, removed))) => (Some(moderation), removed),
+            Ok(None) => (None, None),
+            // Saturation is load shedding rather than an outage, so callers get the retry hint
+            Err(e) if e.retry_after().is_some() => return Err(e.into()),
+            Err(e) => {
+                self.stage_failed(
+                    &run.correlation_id,
+                    &mut run.stage_failures,
+                    PipelineStage::Moderation,
+                    &e,
+                );
+                (None, None)
+            }
+        };
+        let repeat_config = run.repeat_config;
+        let repeat_bonus = (repeat_config.mode == EscalationMode::RiskBonus)
+            .then(|| run.repeat_match.clone())
+            .flatten();
+        if repeat_bonus.is_some() {
+            let sem = semantic.get_or_insert_with(SemanticScanResult::low_risk);
+            sem.risk_score = (sem.risk_score + repeat_config.risk_bonus).min(1.0);
+            sem.risk_level = self.semantic_service.classify_risk(sem.risk_score);
+        }
+
+        let thresholds = self.semantic_service.thresholds();
+        let mut semantic_parameters = BTreeMap::from([
+            (
+                "medium_threshold".to_owned(),
+                f64::from(thresholds.medium_threshold),
+            ),
+            (
+                "high_threshold".to_owned(),
+                f64::from(thresholds.high_threshold),
+            ),
+            (
+                "decision_margin".to_owned(),
+                f64::from(thresholds.decision_margin),
+            ),
+        ]);
+        if self.semantic_sampling.is_enabled() {
+            semantic_parameters.insert(
+                "sampling_rate".to_owned(),
+                f64::from(self.semantic_sampling.rate),
+            );
+        }
+        let sanitized_ref = content_ref(&run.firewall.sanitized_prompt);
+        run.record(TraceStep {
+            stage: "semantic".to_owned(),
+            inputs: vec![sanitized_ref.clone()],
+            verdict: match semantic.as_ref().map(|s| &s.risk_level) {
+                Some(SemanticRiskLevel::High) => "block",
+                Some(SemanticRiskLevel::Medium) => "sanitize",
+                Some(SemanticRiskLevel::Low) => "allow",
+                None => "skip",
+            }
+            .to_owned(),
+            rule_refs: semantic
+                .as_ref()
+                .and_then(|s| s.nearest_template_id.clone())
+                .into_iter()
+                .chain(
+                    repeat_bonus
+                        .as_ref()
+                        .map(|repeat| format!("repeat_of:{}", repeat.correlation_id)),
+                )
+                .chain(run.semantic_skipped_reason.clone())
+                .collect(),
+            parameters: semantic_parameters,
+            duration_ms: semantic_ms,
+        });
+        let moderation_step_index = run.record(moderation_step(
+            "input_moderation",
+            sanitized_ref,
+            input_moderation.as_ref(),
+            input_skipped_reason.as_deref(),
+            moderation_ms,
+        ));
+        run.semantic = semantic;
+        // Kept even when the semantic evidence decides the request, since the call ran
+        run.input_moderation = input_moderation;
+
+        if let Some(sem) = &run.semantic {
+            let level = format!("{:?}", sem.risk_level).to_lowercase();
+            run.policy_evidence
+                .set(PolicyField::SemanticLevel, level.as_str());
+            if let Some(category) = sem.category.clone() {
+                run.policy_evidence
+                    .set(PolicyField::SemanticCategory, category.as_str());
+            }
+        }
+        run.repeat_bonus = repeat_bonus;
+
+        let removed_content = removed_moderation.map(|moderation| {
+            let step = moderation_step(
+                "removed_content_moderation",
+                content_ref(&removed_fragments.join("\n")),
+                Some(&moderation),
+                None,
+                moderation_ms,
+            );
+            (moderation, step)
+        });
+        Ok(InputScan {
+            moderation_step: moderation_step_index,
+            removed_content,
+        })
+    }
+
+    /// Produce the answer to check: the caller's own, the demo's canned one, or a
+    /// generation. `None` when an administrator paused generation.
+    pub(super) async fn produce_output(
+        &self,
+        run: &mut WorkflowRun,
+        abandonment: &Abandonment,
+    ) -> Result<Option<(GenerationRecord, String)>, WorkflowError> {
+        // Every input check passed: from here on the caller leaving wastes a generation
+        abandonment.enter(AbandonedStage::Generation);
+        abandonment.checkpoint()?;
+        let sanitized_ref = content_ref(&run.firewall.sanitized_prompt);
+        let skipped = |reason: &str| TraceStep {
+            stage: "generation".to_owned(),
+            inputs: vec![sanitized_ref.clone()],
+            verdict: "skip".to_owned(),
+            rule_refs: vec![reason.to_owned()],
+            parameters: BTreeMap::new(),
+            duration_ms: 0,
+        };
+        let (model, output) = match run.provided_output.clone() {
+            Some(output) => {
+                run.record(skipped("caller_provided"));
+                (None, output)
+            }
+            None if self.demo_mode => {
+                run.record(skipped(DEMO_MODEL));
+                let output = canned_response(&run.firewall.action).to_owned();
+                (Some(DEMO_MODEL.to_owned()), output)
+            }
+            None if run.is_disabled(ToggleableStage::Generation) => {
+                // Analysis only: the checks above stand, and there is no output to check
+                run.record(skipped(DISABLED_BY_ADMIN));
+                return Ok(None);
+            }
+            None => {
+                return self
+                    .generate(run, sanitized_ref, abandonment)
+                    .await
+                    .map(Some);
+            }
+        };
+        let generation = GenerationRecord {
+            model,
+            english_output: output.clone(),
+            tokens_used: None,
+            latency_ms: None,
+            was_translated: false,
+            input_digest: None,
+            parameters: None,
+        };
+        Ok(Some((generation, output)))
+    }
+
+    /// Check the answer, blocking as soon as the evidence on it decides the request
+    ///
+    /// Every check on the English version runs before the policy looks at any; the
+    /// translation is then moderated on its own, since the translator can add phrasing
+    /// the English pass never saw.
+    pub(super) async fn check_output(
+        &self,
+        run: &mut WorkflowRun,
+        generation: GenerationRecord,
+        generated_text: &str,
+        abandonment: &Abandonment,
+    ) -> Result<Option<Verdict>, WorkflowError> {
+        let english_output = generation.english_output.clone();
+        let moderate_translation = generation.was_translated
+            && run
+                .moderation_skip(ToggleableStage::OutputModeration)
+                .is_none()
+            && self.policy().moderate_translated_output
+            && generated_text != english_output;
+        run.generation = Some(generation);
+        abandonment.enter(AbandonedStage::OutputChecks);
+        abandonment.checkpoint()?;
+
+        self.analyze_output(run, &english_output).await?;
+        if let Some(verdict) = self.policy_block(run) {
+            return Ok(Some(verdict));
+        }
+        if !moderate_translation {
+            return Ok(None);
+        }
+        let stage_start = Instant::now();
+        let (translated_moderation, _) = self
+            .moderate_output(run, generated_text, "translated_output_moderation")
+            .await?;
+        run.record(moderation_step(
+            "translated_output_moderation",
+            content_ref(generated_text),
+            translated_moderation.as_ref(),
+            None,
+            elapsed_ms(stage_start),
+        ));
+        let analysis = run.output_analysis.get_or_insert_default();
+        analysis.translated_moderation = translated_moderation;
+        analysis.record_evidence(&mut run.policy_evidence);
+        Ok(self.policy_block(run))
+    }
+
+    /// Build the final verdict once nothing blocked: the built-in policy sanitizes on a
+    /// firewall sanitize, then on medium semantic risk
+    pub(super) fn decide(
+        &self,
+        run: &mut WorkflowRun,
+        generated_text: Option<String>,
+        input_moderation_step: usize,
+    ) -> Verdict {
+        let moderated = run.moderation_skipped_reason.is_none();
+        let rule = self.decision_policy.evaluate(&run.policy_evidence).cloned();
+        match rule {
+            Some(rule) if rule.then != PolicyAction::Allow => {
+                policy_verdict(run, &rule, generated_text)
+            }
+            _ if !moderated && self.policy().unmoderated == UnmoderatedPolicy::Sanitize => {
+                // Skipped moderation is not a pass; the answer goes out on the sanitized path
+                Verdict {
+                    status: WorkflowStatus::Sanitized,
+                    final_reason: DecisionReason::new(ReasonCode::ModerationNotConfigured),
+                    decisive_step: Some(input_moderation_step),
+                    policy_rule: None,
+                    moderation_categories: vec![],
+                    moderation_scope: None,
+                    generated_text,
+                }
+            }
+            Some(rule) => policy_verdict(run, &rule, generated_text),
+            None => Verdict {
+                status: WorkflowStatus::Completed,
+                final_reason: DecisionReason::new(ReasonCode::AllChecksPassed),
+                decisive_step: None,
+                policy_rule: None,
+                moderation_categories: vec![],
+                moderation_scope: None,
+                generated_text,
+            },
+        }
+    }
+}
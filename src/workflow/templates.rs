@@ -0,0 +1,639 @@
+//! Pre-approved prompt templates, whose variable values are the only part of a request
+//! the pipeline scans
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{ComplianceEngine, content_ref};
+use crate::firewall_core::dtos::{FirewallAction, PromptFirewallResult};
+use crate::modules::audit::logger::{AuditError, ConfigChangeEvent};
+use crate::modules::audit::proof::content_hash;
+use crate::modules::preprocessing::dtos::AppliedTransform;
+use crate::modules::preprocessing::service::PromptPreprocessor;
+use crate::modules::prompt_firewall::dtos::PromptFirewallRequest;
+use crate::modules::semantic_detection::dtos::{SemanticRiskLevel, SemanticScanRequest};
+
+/// Joins slot values into the text the scanning stages see
+const SLOT_SEPARATOR: &str = "\n";
+
+/// Which stages a slot's value goes through
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotScan {
+    /// Every stage: firewall, EU compliance, semantic scan, bias and input moderation
+    #[default]
+    Full,
+    /// Firewall and EU compliance only, for short structured values such as ids or
+    /// dates that the model-based stages only misread
+    FirewallOnly,
+}
+
+/// Body of `POST /api/templates`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TemplateRegistration {
+    /// Callers send this as `template_id`; registering an existing id replaces it
+    pub id: String,
+    /// Prompt text with named slots such as `{message}`; `{{` and `}}` are literal braces
+    pub text: String,
+    /// Scan policy per slot; slots left out are scanned in full
+    #[serde(default)]
+    pub slots: BTreeMap<String, SlotScan>,
+    pub registered_by: String,
+}
+
+impl TemplateRegistration {
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        {
+            return Err("template id must be non-empty ASCII letters, digits and -_.".to_owned());
+        }
+        if self.text.trim().is_empty() {
+            return Err("template text must not be empty".to_owned());
+        }
+        if self.registered_by.trim().is_empty() {
+            return Err("registered_by must not be empty".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// A slot of a registered template, in order of first appearance
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TemplateSlot {
+    pub name: String,
+    pub scan: SlotScan,
+}
+
+/// Result of scanning a template's static text once, when it was registered
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ScaffoldScan {
+    pub firewall_action: FirewallAction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matched_rules: Vec<String>,
+    /// Absent when the semantic detector could not score the text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic_risk_score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic_risk_level: Option<SemanticRiskLevel>,
+}
+
+/// A registered template
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub text: String,
+    pub slots: Vec<TemplateSlot>,
+    /// SHA-256 of the text and slot policies, recorded with every request using them
+    pub fingerprint: String,
+    pub scaffold_scan: ScaffoldScan,
+    pub registered_by: String,
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Response of `GET /api/templates`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TemplatesResponse {
+    /// Ordered by id
+    pub templates: Vec<PromptTemplate>,
+}
+
+/// The template a compliance request fills in instead of sending a prompt
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TemplateInvocation {
+    pub template_id: String,
+    /// One value per slot, by slot name
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// What the firewall found in one slot's value
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SlotVerdict {
+    pub slot: String,
+    pub scan: SlotScan,
+    /// Firewall action for the value on its own: allow, flag, sanitize or block
+    pub verdict: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matched_rules: Vec<String>,
+}
+
+/// The template a request used, as recorded in its evidence and audit record
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TemplateEvidence {
+    pub template_id: String,
+    pub fingerprint: String,
+    /// Per-slot firewall verdicts in slot order; the semantic scan, bias scan and
+    /// moderation see the fully scanned slots together
+    pub slots: Vec<SlotVerdict>,
+}
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("invalid template: {0}")]
+    Invalid(String),
+    #[error("template text is not safe to pre-approve: {0}")]
+    UnsafeScaffold(String),
+    #[error("no template registered with id {0}")]
+    Unknown(String),
+    #[error("missing values for template slots: {}", .0.join(", "))]
+    MissingVariables(Vec<String>),
+    #[error("template has no slots named: {}", .0.join(", "))]
+    UnexpectedVariables(Vec<String>),
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
+
+impl TemplateError {
+    /// Whether the error was caused by what the caller sent rather than the server
+    pub fn is_rejected_input(&self) -> bool {
+        !matches!(self, Self::Audit(_))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Slot(String),
+}
+
+/// Split template text into literals and slots
+fn parse(text: &str) -> Result<Vec<Segment>, TemplateError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_alphanumeric() || c == '_' => name.push(c),
+                        _ => {
+                            return Err(TemplateError::Invalid(
+                                "slots must be written {name}, with letters, digits and _ \
+                                 (use {{ and }} for literal braces)"
+                                    .to_owned(),
+                            ));
+                        }
+                    }
+                }
+                if name.is_empty() {
+                    return Err(TemplateError::Invalid("empty slot name {}".to_owned()));
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Slot(name));
+            }
+            '}' => {
+                return Err(TemplateError::Invalid(
+                    "unmatched } in template text (use }} for a literal brace)".to_owned(),
+                ));
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+#[derive(Clone)]
+struct RegisteredTemplate {
+    template: PromptTemplate,
+    segments: Vec<Segment>,
+}
+
+/// Templates registered since startup, by id
+#[derive(Clone, Default)]
+pub(crate) struct TemplateRegistry {
+    templates: BTreeMap<String, RegisteredTemplate>,
+}
+
+impl TemplateRegistry {
+    fn fingerprints(&self) -> BTreeMap<&str, &str> {
+        self.templates
+            .iter()
+            .map(|(id, entry)| (id.as_str(), entry.template.fingerprint.as_str()))
+            .collect()
+    }
+}
+
+/// A template filled in with a request's values
+pub(crate) struct ResolvedTemplate {
+    template_id: String,
+    fingerprint: String,
+    segments: Vec<Segment>,
+    /// Slots in order, each with the caller's value
+    values: Vec<(TemplateSlot, String)>,
+    verdicts: Vec<SlotVerdict>,
+    /// The template filled in with the sanitized values, once the firewall ran
+    sanitized: Option<String>,
+}
+
+impl ResolvedTemplate {
+    /// The template text with `values` in its slots
+    fn fill(&self, values: &[(TemplateSlot, String)]) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.as_str(),
+                Segment::Slot(name) => values
+                    .iter()
+                    .find(|(slot, _)| slot.name == *name)
+                    .map(|(_, value)| value.as_str())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// The prompt the caller's values make
+    pub(crate) fn reconstruct(&self) -> String {
+        self.fill(&self.values)
+    }
+
+    /// The prompt sent to generation: the template with the firewall's sanitized values
+    pub(crate) fn generation_prompt(&self) -> Option<&str> {
+        self.sanitized.as_deref()
+    }
+
+    /// Run the preprocessors on each value rather than on the reconstructed prompt,
+    /// returning the transforms applied and every value joined for the firewall
+    pub(crate) fn preprocess(
+        &mut self,
+        preprocessor: &PromptPreprocessor,
+    ) -> (String, Vec<AppliedTransform>) {
+        let mut applied: Vec<AppliedTransform> = Vec::new();
+        for (_, value) in &mut self.values {
+            let preprocessed = preprocessor.apply(value);
+            *value = preprocessed.text;
+            if applied.is_empty() {
+                applied = preprocessed.applied;
+            } else {
+                for (merged, current) in applied.iter_mut().zip(preprocessed.applied) {
+                    merged.changed |= current.changed;
+                }
+            }
+        }
+        if applied.is_empty() {
+            applied = preprocessor.apply("").applied;
+        }
+        (self.joined(|_| true), applied)
+    }
+
+    /// Values of the fully scanned slots, joined for the semantic, bias and moderation
+    /// stages
+    pub(crate) fn fully_scanned_text(&self) -> String {
+        self.joined(|slot| slot.scan == SlotScan::Full)
+    }
+
+    fn joined(&self, include: impl Fn(&TemplateSlot) -> bool) -> String {
+        self.values
+            .iter()
+            .filter(|(slot, _)| include(slot))
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(SLOT_SEPARATOR)
+    }
+
+    /// Firewall each value on its own and merge the results
+    ///
+    /// The merged result takes the strictest action, and its `sanitized_prompt` holds
+    /// the sanitized values of the fully scanned slots, which later stages scan.
+    pub(crate) async fn inspect(
+        &mut self,
+        engine: &ComplianceEngine,
+        request: &PromptFirewallRequest,
+        is_english: bool,
+        excluded_rules: &[String],
+    ) -> PromptFirewallResult {
+        let mut merged: Option<PromptFirewallResult> = None;
+        let mut sanitized = Vec::with_capacity(self.values.len());
+        self.verdicts.clear();
+        for (slot, value) in &self.values {
+            let result = engine
+                .firewall_service
+                .inspect_excluding(
+                    PromptFirewallRequest {
+                        prompt: value.clone(),
+                        correlation_id: request.correlation_id.clone(),
+                        // Non-English values are translated one by one by the firewall
+                        detected_language: if is_english {
+                            request.detected_language.clone()
+                        } else {
+                            None
+                        },
+                        pre_translated_text: None,
+                    },
+                    excluded_rules,
+                )
+                .await;
+            self.verdicts.push(SlotVerdict {
+                slot: slot.name.clone(),
+                scan: slot.scan,
+                verdict: format!("{:?}", result.action).to_lowercase(),
+                matched_rules: result.matched_rules.clone(),
+            });
+            sanitized.push((slot.clone(), result.sanitized_prompt.clone()));
+            merged = Some(match merged {
+                None => result,
+                Some(merged) => merge_firewall(merged, result),
+            });
+        }
+        let mut merged = merged.unwrap_or_else(|| engine.firewall_service.inspect_local(""));
+        merged.sanitized_prompt = sanitized
+            .iter()
+            .filter(|(slot, _)| slot.scan == SlotScan::Full)
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(SLOT_SEPARATOR);
+        self.sanitized = Some(self.fill(&sanitized));
+        merged
+    }
+
+    pub(crate) fn evidence(&self) -> TemplateEvidence {
+        TemplateEvidence {
+            template_id: self.template_id.clone(),
+            fingerprint: self.fingerprint.clone(),
+            slots: self.verdicts.clone(),
+        }
+    }
+}
+
+fn strictness(action: &FirewallAction) -> u8 {
+    match action {
+        FirewallAction::Allow => 0,
+        FirewallAction::Flag => 1,
+        FirewallAction::Sanitize => 2,
+        FirewallAction::Block => 3,
+    }
+}
+
+fn merge_firewall(
+    mut merged: PromptFirewallResult,
+    next: PromptFirewallResult,
+) -> PromptFirewallResult {
+    if strictness(&next.action) > strictness(&merged.action) {
+        merged.action = next.action;
+        merged.downgrade_reason = next.downgrade_reason;
+    }
+    merged.severity = merged.severity.max(next.severity);
+    for rule in next.matched_rules {
+        if !merged.matched_rules.contains(&rule) {
+            merged.matched_rules.push(rule);
+        }
+    }
+    merged.reasons.extend(next.reasons);
+    merged.sanitization_edits.extend(next.sanitization_edits);
+    merged.block_matches.extend(next.block_matches);
+    for language in next.mixed_languages {
+        if !merged.mixed_languages.contains(&language) {
+            merged.mixed_languages.push(language);
+        }
+    }
+    merged.translated |= next.translated;
+    merged.truncation = merged.truncation.or(next.truncation);
+    for url in next.urls {
+        if !merged.urls.iter().any(|seen| seen.url == url.url) {
+            merged.urls.push(url);
+        }
+    }
+    merged
+}
+
+impl ComplianceEngine {
+    /// Register a template, or replace the one with the same id
+    ///
+    /// The static text is scanned once, here; a template whose own text the firewall
+    /// blocks or the semantic detector scores high is refused.
+    pub async fn register_template(
+        &self,
+        correlation_id: &str,
+        registration: TemplateRegistration,
+    ) -> Result<PromptTemplate, TemplateError> {
+        registration.validate().map_err(TemplateError::Invalid)?;
+        let segments = parse(&registration.text)?;
+        let mut slots: Vec<TemplateSlot> = Vec::new();
+        for segment in &segments {
+            if let Segment::Slot(name) = segment
+                && !slots.iter().any(|slot| slot.name == *name)
+            {
+                slots.push(TemplateSlot {
+                    name: name.clone(),
+                    scan: registration.slots.get(name).copied().unwrap_or_default(),
+                });
+            }
+        }
+        if slots.is_empty() {
+            return Err(TemplateError::Invalid(
+                "template text has no slots".to_owned(),
+            ));
+        }
+        let unknown: Vec<String> = registration
+            .slots
+            .keys()
+            .filter(|name| !slots.iter().any(|slot| slot.name == **name))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(TemplateError::Invalid(format!(
+                "scan policies given for slots the text does not have: {}",
+                unknown.join(", ")
+            )));
+        }
+
+        let scaffold_scan = self.scan_scaffold(correlation_id, &segments).await;
+        if scaffold_scan.firewall_action == FirewallAction::Block {
+            return Err(TemplateError::UnsafeScaffold(format!(
+                "the firewall blocks it ({})",
+                scaffold_scan.matched_rules.join(", ")
+            )));
+        }
+        if scaffold_scan.semantic_risk_level == Some(SemanticRiskLevel::High) {
+            return Err(TemplateError::UnsafeScaffold(
+                "it closely resembles a known attack".to_owned(),
+            ));
+        }
+
+        let template = PromptTemplate {
+            id: registration.id,
+            fingerprint: content_hash(&(&registration.text, &slots)),
+            text: registration.text,
+            slots,
+            scaffold_scan,
+            registered_by: registration.registered_by,
+            registered_at: Utc::now(),
+        };
+        let mut templates = self.templates.write().unwrap();
+        let mut next = templates.clone();
+        let replaced = next
+            .templates
+            .insert(
+                template.id.clone(),
+                RegisteredTemplate {
+                    template: template.clone(),
+                    segments,
+                },
+            )
+            .is_some();
+        self.audit_logger.log_config_change(ConfigChangeEvent {
+            correlation_id: correlation_id.to_owned(),
+            event_type: "configuration_change".to_owned(),
+            action: if replaced {
+                "template_replaced"
+            } else {
+                "template_registered"
+            }
+            .to_owned(),
+            previous_hash: content_hash(&templates.fingerprints()),
+            new_hash: content_hash(&next.fingerprints()),
+            snapshot_version: None,
+            detail: Some(format!(
+                "{} ({}) by {}",
+                template.id, template.fingerprint, template.registered_by
+            )),
+        })?;
+        tracing::info!(
+            "Prompt template {} registered by {}",
+            template.id,
+            template.registered_by
+        );
+        *templates = next;
+        Ok(template)
+    }
+
+    pub fn templates(&self) -> TemplatesResponse {
+        TemplatesResponse {
+            templates: self
+                .templates
+                .read()
+                .unwrap()
+                .templates
+                .values()
+                .map(|entry| entry.template.clone())
+                .collect(),
+        }
+    }
+
+    /// Look up the template `invocation` names and check it supplies exactly its slots
+    pub(crate) fn resolve_template(
+        &self,
+        invocation: &TemplateInvocation,
+    ) -> Result<ResolvedTemplate, TemplateError> {
+        let templates = self.templates.read().unwrap();
+        let entry = templates
+            .templates
+            .get(&invocation.template_id)
+            .ok_or_else(|| TemplateError::Unknown(invocation.template_id.clone()))?;
+        let slots: BTreeSet<&str> = entry
+            .template
+            .slots
+            .iter()
+            .map(|slot| slot.name.as_str())
+            .collect();
+        let missing: Vec<String> = slots
+            .iter()
+            .filter(|name| !invocation.variables.contains_key(**name))
+            .map(|name| (*name).to_owned())
+            .collect();
+        if !missing.is_empty() {
+            return Err(TemplateError::MissingVariables(missing));
+        }
+        let unexpected: Vec<String> = invocation
+            .variables
+            .keys()
+            .filter(|name| !slots.contains(name.as_str()))
+            .cloned()
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(TemplateError::UnexpectedVariables(unexpected));
+        }
+        Ok(ResolvedTemplate {
+            template_id: entry.template.id.clone(),
+            fingerprint: entry.template.fingerprint.clone(),
+            segments: entry.segments.clone(),
+            values: entry
+                .template
+                .slots
+                .iter()
+                .map(|slot| (slot.clone(), invocation.variables[&slot.name].clone()))
+                .collect(),
+            verdicts: Vec::new(),
+            sanitized: None,
+        })
+    }
+
+    async fn scan_scaffold(&self, correlation_id: &str, segments: &[Segment]) -> ScaffoldScan {
+        let scaffold = segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Literal(text) => Some(text.as_str()),
+                Segment::Slot(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let firewall = self
+            .firewall_service
+            .inspect(PromptFirewallRequest {
+                prompt: scaffold.clone(),
+                correlation_id: Some(correlation_id.to_owned()),
+                detected_language: None,
+                pre_translated_text: None,
+            })
+            .await;
+        let semantic = self
+            .semantic_service
+            .scan(SemanticScanRequest {
+                text: scaffold.clone(),
+                detected_language: None,
+                pre_translated_text: None,
+            })
+            .await
+            .ok();
+        tracing::debug!("Scanned template text {}", content_ref(&scaffold));
+        ScaffoldScan {
+            firewall_action: firewall.action,
+            matched_rules: firewall.matched_rules,
+            semantic_risk_score: semantic.as_ref().map(|s| s.risk_score),
+            semantic_risk_level: semantic.map(|s| s.risk_level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_and_escaped_braces_parse() {
+        assert_eq!(
+            parse("Summarize {message} as {{json}}").unwrap(),
+            vec![
+                Segment::Literal("Summarize ".to_owned()),
+                Segment::Slot("message".to_owned()),
+                Segment::Literal(" as {json}".to_owned()),
+            ]
+        );
+        assert!(parse("Unclosed {message").is_err());
+        assert!(parse("Stray } brace").is_err());
+        assert!(parse("Spaced {two words}").is_err());
+        assert!(parse("Empty {}").is_err());
+    }
+}
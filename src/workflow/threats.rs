@@ -0,0 +1,215 @@
+//! The threat category of a decision, whichever layer found it
+//!
+//! Each layer reports what it caught in its own terms; they are resolved to the shared
+//! taxonomy of [`crate::firewall_core::taxonomy`] here, so a decision carries one
+//! category and the audit trail can be counted by it.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ComplianceEngine, WorkflowStatus, serialized_name};
+use crate::firewall_core::taxonomy::{
+    CategoryRef, ResolvedThreat, ThreatCategory, ThreatLayer, moderation_category,
+};
+use crate::modules::audit::logger::AuditEvent;
+use crate::modules::audit::storage::AuditStorageError;
+use crate::modules::bias_detection::dtos::BiasScanResult;
+use crate::modules::bias_detection::model::{BiasCategory, BiasLevel};
+use crate::modules::mistral_ai::dtos::ModerationResponse;
+use crate::modules::prompt_firewall::dtos::{FirewallAction, PromptFirewallResult};
+use crate::modules::prompt_firewall::rules::CONTROL_CHARACTER_RULE_ID;
+use crate::modules::semantic_detection::dtos::{SemanticRiskLevel, SemanticScanResult};
+
+/// What each layer found for one request
+pub(super) struct LayerFindings<'a> {
+    pub firewall: &'a PromptFirewallResult,
+    pub semantic: Option<&'a SemanticScanResult>,
+    pub input_moderation: Option<&'a ModerationResponse>,
+    /// Moderation of the answer as generated and, when it was, as translated
+    pub output_moderation: [Option<&'a ModerationResponse>; 2],
+    pub bias: &'a BiasScanResult,
+}
+
+impl LayerFindings<'_> {
+    /// The category found by the layer that decided `status`, or else by the first layer
+    /// in pipeline order that found one
+    pub fn resolve(&self, status: &WorkflowStatus) -> Option<ResolvedThreat> {
+        let deciding = match status {
+            WorkflowStatus::BlockedByFirewall => Some(ThreatLayer::Firewall),
+            WorkflowStatus::BlockedBySemantic => Some(ThreatLayer::Semantic),
+            WorkflowStatus::BlockedByInputModeration => Some(ThreatLayer::InputModeration),
+            WorkflowStatus::BlockedByOutputModeration => Some(ThreatLayer::OutputModeration),
+            _ => None,
+        };
+        let threats: Vec<_> = [
+            self.firewall(),
+            self.semantic(),
+            self.input_moderation
+                .and_then(|m| moderation_threat(m, ThreatLayer::InputModeration)),
+            self.output_moderation
+                .iter()
+                .flatten()
+                .find_map(|m| moderation_threat(m, ThreatLayer::OutputModeration)),
+            self.bias(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        threats
+            .iter()
+            .find(|threat| Some(threat.layer) == deciding)
+            .or(threats.first())
+            .cloned()
+    }
+
+    /// Category of the first matched block rule, sanitize pattern or control character
+    /// that has one
+    fn firewall(&self) -> Option<ResolvedThreat> {
+        if self.firewall.action == FirewallAction::Allow {
+            return None;
+        }
+        let threat = |source: &str, category: &CategoryRef| ResolvedThreat {
+            category: category.clone(),
+            layer: ThreatLayer::Firewall,
+            source: source.to_owned(),
+        };
+        self.firewall
+            .block_matches
+            .iter()
+            .find_map(|rule| Some(threat(&rule.id, rule.category.as_ref()?)))
+            .or_else(|| {
+                self.firewall
+                    .sanitization_edits
+                    .iter()
+                    .find_map(|edit| Some(threat(&edit.rule_id, edit.category.as_ref()?)))
+            })
+            .or_else(|| {
+                // A prompt blocked for its control characters has no edits to carry it
+                self.firewall
+                    .matched_rules
+                    .iter()
+                    .any(|rule| rule == CONTROL_CHARACTER_RULE_ID)
+                    .then(|| {
+                        threat(
+                            CONTROL_CHARACTER_RULE_ID,
+                            &CategoryRef::Known(ThreatCategory::EncodingObfuscation),
+                        )
+                    })
+            })
+    }
+
+    fn semantic(&self) -> Option<ResolvedThreat> {
+        let semantic = self.semantic?;
+        if semantic.risk_level == SemanticRiskLevel::Low {
+            return None;
+        }
+        Some(ResolvedThreat {
+            category: semantic.threat_category.clone()?,
+            layer: ThreatLayer::Semantic,
+            source: semantic.nearest_template_id.clone().unwrap_or_default(),
+        })
+    }
+
+    fn bias(&self) -> Option<ResolvedThreat> {
+        if self.bias.level == BiasLevel::Low {
+            return None;
+        }
+        let category = self.bias.categories.first();
+        Some(ResolvedThreat {
+            category: CategoryRef::Known(match category {
+                Some(BiasCategory::HarmfulLanguage) => ThreatCategory::HarmfulContent,
+                _ => ThreatCategory::BiasedLanguage,
+            }),
+            layer: ThreatLayer::Bias,
+            source: category.map(serialized_name).unwrap_or_default(),
+        })
+    }
+}
+
+/// The most severe category of a flagged moderation pass
+fn moderation_threat(
+    moderation: &ModerationResponse,
+    layer: ThreatLayer,
+) -> Option<ResolvedThreat> {
+    if !moderation.flagged {
+        return None;
+    }
+    let flagged = moderation
+        .flagged_categories()
+        .into_iter()
+        .max_by(|a, b| a.severity.total_cmp(&b.severity))?;
+    Some(ResolvedThreat {
+        category: moderation_category(&flagged.category),
+        layer,
+        source: flagged.category,
+    })
+}
+
+/// Decisions of a period counted by threat category
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ThreatCategoryStats {
+    pub since: DateTime<Utc>,
+    /// Decisions in the period with a resolved category
+    pub total: u64,
+    /// Most frequent first
+    pub categories: Vec<ThreatCategoryCount>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ThreatCategoryCount {
+    pub category: CategoryRef,
+    pub total: u64,
+    /// Decisions of the category by the layer that resolved it
+    pub by_layer: BTreeMap<ThreatLayer, u64>,
+}
+
+impl ComplianceEngine {
+    /// Count the decisions audited within `window` by their threat category
+    ///
+    /// Self-test runs are left out, as are records written before categories were
+    /// resolved.
+    pub fn threat_category_stats(
+        &self,
+        window: Duration,
+    ) -> Result<ThreatCategoryStats, AuditStorageError> {
+        let since = Utc::now() - window;
+        let records = self
+            .audit_logger
+            .storage()
+            .get_with_filters(None, None, Some(since), None, None)?
+            .records;
+
+        let mut counts: BTreeMap<CategoryRef, BTreeMap<ThreatLayer, u64>> = BTreeMap::new();
+        for record in records {
+            let Ok(event) = serde_json::from_str::<AuditEvent>(&record.payload) else {
+                continue;
+            };
+            let Some(threat) = event.threat_category.filter(|_| !event.self_test) else {
+                continue;
+            };
+            *counts
+                .entry(threat.category)
+                .or_default()
+                .entry(threat.layer)
+                .or_default() += 1;
+        }
+
+        let mut categories: Vec<_> = counts
+            .into_iter()
+            .map(|(category, by_layer)| ThreatCategoryCount {
+                category,
+                total: by_layer.values().sum(),
+                by_layer,
+            })
+            .collect();
+        // Stable, so ties stay in category order
+        categories.sort_by_key(|count| std::cmp::Reverse(count.total));
+        Ok(ThreatCategoryStats {
+            since,
+            total: categories.iter().map(|count| count.total).sum(),
+            categories,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! Stages an administrator can switch off while the service keeps running
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::modules::audit::logger::AuditError;
+
+/// Pipeline stages that `POST /api/admin/stages` can pause
+///
+/// The firewall and the EU compliance check are local, cheap and the last line of
+/// defence, so they are deliberately not listed and cannot be switched off.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ToggleableStage {
+    InputModeration,
+    OutputModeration,
+    Semantic,
+    Bias,
+    /// Without generation requests are analyzed only and get no generated text
+    Generation,
+    /// Translating the generated answer back to the prompt's language
+    Translation,
+}
+
+impl ToggleableStage {
+    pub const ALL: [Self; 6] = [
+        Self::InputModeration,
+        Self::OutputModeration,
+        Self::Semantic,
+        Self::Bias,
+        Self::Generation,
+        Self::Translation,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InputModeration => "input_moderation",
+            Self::OutputModeration => "output_moderation",
+            Self::Semantic => "semantic",
+            Self::Bias => "bias",
+            Self::Generation => "generation",
+            Self::Translation => "translation",
+        }
+    }
+}
+
+impl fmt::Display for ToggleableStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Reason recorded in the trace and skip reasons for a stage an administrator paused,
+/// as opposed to one that failed or was sampled out
+pub const DISABLED_BY_ADMIN: &str = "disabled_by_admin";
+
+/// Body of `POST /api/admin/stages`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageToggleRequest {
+    pub stage: ToggleableStage,
+    pub enabled: bool,
+    /// Why the stage is switched; kept in the audit trail
+    pub reason: String,
+    /// Who switched it
+    #[serde(default)]
+    pub changed_by: Option<String>,
+}
+
+impl StageToggleRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reason.trim().is_empty() {
+            return Err("reason must not be empty".to_owned());
+        }
+        if self
+            .changed_by
+            .as_deref()
+            .is_some_and(|who| who.trim().is_empty())
+        {
+            return Err("changed_by must not be empty when set".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Current state of one stage and its last change
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageState {
+    pub stage: ToggleableStage,
+    pub enabled: bool,
+    /// Absent until the stage is first switched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_at: Option<DateTime<Utc>>,
+    /// Request that made the last change, for finding it in the audit trail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// Response of `GET /api/admin/stages`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StageTogglesResponse {
+    pub stages: Vec<StageState>,
+}
+
+/// Which stages are running, every one of them until an administrator pauses it
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct StageToggles {
+    /// Stages that were ever switched; the rest are enabled
+    changes: BTreeMap<ToggleableStage, StageState>,
+}
+
+impl StageToggles {
+    pub fn is_enabled(&self, stage: ToggleableStage) -> bool {
+        self.changes.get(&stage).is_none_or(|state| state.enabled)
+    }
+
+    /// Stages currently switched off
+    pub fn disabled(&self) -> Vec<ToggleableStage> {
+        ToggleableStage::ALL
+            .into_iter()
+            .filter(|stage| !self.is_enabled(*stage))
+            .collect()
+    }
+
+    pub fn state(&self, stage: ToggleableStage) -> StageState {
+        self.changes.get(&stage).cloned().unwrap_or(StageState {
+            stage,
+            enabled: true,
+            changed_by: None,
+            reason: None,
+            changed_at: None,
+            correlation_id: None,
+        })
+    }
+
+    pub fn states(&self) -> Vec<StageState> {
+        ToggleableStage::ALL
+            .into_iter()
+            .map(|stage| self.state(stage))
+            .collect()
+    }
+
+    pub(crate) fn apply(
+        &mut self,
+        correlation_id: &str,
+        request: StageToggleRequest,
+    ) -> StageState {
+        let state = StageState {
+            stage: request.stage,
+            enabled: request.enabled,
+            changed_by: request.changed_by,
+            reason: Some(request.reason),
+            changed_at: Some(Utc::now()),
+            correlation_id: Some(correlation_id.to_owned()),
+        };
+        self.changes.insert(request.stage, state.clone());
+        state
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StageToggleError {
+    #[error("invalid stage toggle: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+}
@@ -0,0 +1,185 @@
+#![cfg(feature = "server")]
+
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::admission::dtos::AdmissionLimits;
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+/// Every admitted workflow holds its slot at least this long
+const CHAT_LATENCY: Duration = Duration::from_millis(400);
+
+async fn saturating_app(limits: Option<AdmissionLimits>) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            admission: limits,
+            ..AppSettings::default()
+        })
+        .with_admin_token("secret")
+        .with_mock(MockMistralClient::default().delay_chat(CHAT_LATENCY))
+        .build()
+        .await
+        .unwrap()
+}
+
+/// Send a compliance check in the background; resolves to its status, `Retry-After`
+/// and how long it took
+fn send_check(server: &TestServer) -> JoinHandle<(StatusCode, Option<String>, Duration)> {
+    let request = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": "What is the capital of France?" }));
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let response = request.send().await.unwrap();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .map(|value| value.to_str().unwrap().to_owned());
+        (response.status(), retry_after, started.elapsed())
+    })
+}
+
+async fn admission_summary(server: &TestServer) -> Value {
+    let summary: Value = server
+        .get("/api/admin/summary")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    summary["admission"].clone()
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_queue_and_overflow_is_shed_with_retry_after() {
+    let app = saturating_app(Some(AdmissionLimits {
+        max_concurrent: 2,
+        max_queue: 2,
+        max_wait_ms: 5_000,
+    }))
+    .await;
+    let server = app.serve().await.unwrap();
+
+    let admitted: Vec<_> = (0..2).map(|_| send_check(&server)).collect();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let queued: Vec<_> = (0..2).map(|_| send_check(&server)).collect();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let saturated = admission_summary(&server).await;
+    assert_eq!(saturated["in_flight"], 2);
+    assert_eq!(saturated["queue_depth"], 2);
+
+    // The queue is full, so these are turned away without waiting for a slot
+    for _ in 0..2 {
+        let (status, retry_after, took) = send_check(&server).await.unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(retry_after.as_deref(), Some("5"));
+        assert!(took < CHAT_LATENCY, "shed after {took:?}");
+    }
+
+    for handle in admitted {
+        let (status, _, took) = handle.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(took >= CHAT_LATENCY);
+    }
+    for handle in queued {
+        let (status, _, took) = handle.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        // Waited for one of the first two workflows, then ran its own
+        assert!(
+            took >= CHAT_LATENCY * 2 - Duration::from_millis(100),
+            "took {took:?}"
+        );
+    }
+
+    let drained = admission_summary(&server).await;
+    assert_eq!(drained["in_flight"], 0);
+    assert_eq!(drained["queue_depth"], 0);
+    assert_eq!(drained["admitted_total"], 4);
+    assert_eq!(drained["queued_total"], 2);
+    assert_eq!(drained["shed_total"], 2);
+    assert!(drained["last_wait_ms"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn queued_requests_are_shed_once_their_wait_runs_out() {
+    let app = saturating_app(Some(AdmissionLimits {
+        max_concurrent: 1,
+        max_queue: 4,
+        max_wait_ms: 100,
+    }))
+    .await;
+    let server = app.serve().await.unwrap();
+
+    let admitted = send_check(&server);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let (status, retry_after, _) = send_check(&server).await.unwrap();
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(retry_after.as_deref(), Some("1"));
+    assert_eq!(admitted.await.unwrap().0, StatusCode::OK);
+
+    let summary = admission_summary(&server).await;
+    assert_eq!(summary["queued_total"], 1);
+    assert_eq!(summary["shed_total"], 1);
+    assert_eq!(summary["queue_depth"], 0);
+}
+
+#[tokio::test]
+async fn without_admission_control_every_request_runs_at_once() {
+    let app = saturating_app(None).await;
+    let server = app.serve().await.unwrap();
+
+    let started = Instant::now();
+    let checks: Vec<_> = (0..6).map(|_| send_check(&server)).collect();
+    for handle in checks {
+        assert_eq!(handle.await.unwrap().0, StatusCode::OK);
+    }
+    assert!(started.elapsed() < CHAT_LATENCY * 2);
+    assert!(admission_summary(&server).await.is_null());
+}
+
+#[tokio::test]
+async fn regenerate_replays_wait_for_a_workflow_slot() {
+    let app = saturating_app(Some(AdmissionLimits {
+        max_concurrent: 1,
+        max_queue: 0,
+        max_wait_ms: 5_000,
+    }))
+    .await;
+    let server = app.serve().await.unwrap();
+    let response: Value = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": "What is the capital of France?", "deterministic": true }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let replay = |mode: &str| {
+        server.post(&format!(
+            "/api/audit/replay/{}?mode={mode}",
+            response["correlation_id"].as_str().unwrap()
+        ))
+    };
+
+    let admitted = send_check(&server);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let shed = replay("regenerate").send().await.unwrap();
+    assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(shed.headers().contains_key(RETRY_AFTER));
+    // Replays that stay local do not need a slot
+    let local = replay("current").send().await.unwrap();
+    assert_eq!(local.status(), StatusCode::OK);
+    assert_eq!(admitted.await.unwrap().0, StatusCode::OK);
+
+    let regenerated = replay("regenerate").send().await.unwrap();
+    assert_eq!(regenerated.status(), StatusCode::OK);
+}
@@ -0,0 +1,260 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::modules::appeals::dtos::{Appeal, AppealState, AppealsResponse};
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+/// Blocked by PFW-005 ("jailbreak")
+const PHONE_PROMPT: &str = "What does it mean to jailbreak an iPhone?";
+
+async fn check(server: &TestServer, prompt: &str) -> Value {
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": prompt }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+async fn file(server: &TestServer, correlation_id: &str) -> reqwest::Response {
+    server
+        .post("/api/appeals")
+        .json(&json!({
+            "correlation_id": correlation_id,
+            "justification": "I was asking about my own phone",
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn resolve(server: &TestServer, id: &str, outcome: &str) -> reqwest::Response {
+    server
+        .post(&format!("/api/appeals/{id}/resolve"))
+        .json(&json!({
+            "outcome": outcome,
+            "note": "consumer device question",
+            "resolved_by": "trust-and-safety",
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn list(server: &TestServer, query: &str) -> Vec<Appeal> {
+    let response = server
+        .get(&format!("/api/appeals{query}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json::<AppealsResponse>().await.unwrap().appeals
+}
+
+/// Payloads of every audit record written under `correlation_id`, oldest first
+fn trail(app: &TestApp, correlation_id: &str) -> Vec<Value> {
+    app.storage
+        .all()
+        .unwrap()
+        .iter()
+        .filter(|record| record.correlation_id == correlation_id)
+        .map(|record| serde_json::from_str(&record.payload).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn overturned_appeal_exempts_the_appealed_prompt_and_audits_the_chain() {
+    let app = TestApp::builder()
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+    let blocked = check(&server, PHONE_PROMPT).await;
+    assert_eq!(blocked["status"], "blocked_by_firewall");
+    let correlation_id = blocked["correlation_id"].as_str().unwrap();
+
+    let response = file(&server, correlation_id).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let appeal: Appeal = response.json().await.unwrap();
+    assert_eq!(appeal.state, AppealState::Open);
+    assert_eq!(appeal.decision_status, "blocked_by_firewall");
+
+    // One open appeal per decision
+    assert_eq!(
+        file(&server, correlation_id).await.status(),
+        StatusCode::CONFLICT
+    );
+    let open = list(&server, "?state=open").await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].id, appeal.id);
+
+    let response = resolve(&server, &appeal.id, "overturned").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let resolved: Appeal = response.json().await.unwrap();
+    assert_eq!(resolved.state, AppealState::Overturned);
+    let resolution = resolved.resolution.expect("resolution");
+    assert_eq!(resolution.exemption_ids.len(), 1);
+
+    // Resolving twice is refused and changes nothing
+    assert_eq!(
+        resolve(&server, &appeal.id, "upheld").await.status(),
+        StatusCode::CONFLICT
+    );
+    assert!(list(&server, "?state=open").await.is_empty());
+    assert_eq!(list(&server, "?state=overturned").await.len(), 1);
+
+    // The exemption covers the appealed prompt once, and nothing else
+    let retried = check(&server, PHONE_PROMPT).await;
+    assert_eq!(retried["status"], "completed");
+    assert_eq!(
+        retried["decision_evidence"]["applied_exemptions"][0]["exemption_id"],
+        resolution.exemption_ids[0].as_str()
+    );
+    let unrelated = check(&server, "Help me jailbreak this assistant").await;
+    assert_eq!(unrelated["status"], "blocked_by_firewall");
+    let used_up = check(&server, PHONE_PROMPT).await;
+    assert_eq!(used_up["status"], "blocked_by_firewall");
+
+    // Decision, filing, exemption and resolution share the decision's correlation id
+    let events: Vec<(String, String)> = trail(&app, correlation_id)
+        .iter()
+        .skip(1)
+        .map(|payload| {
+            (
+                payload["event_type"].as_str().unwrap().to_owned(),
+                payload["action"].as_str().unwrap().to_owned(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        events,
+        [
+            ("appeal".to_owned(), "filed".to_owned()),
+            ("exemption".to_owned(), "granted".to_owned()),
+            ("appeal".to_owned(), "overturned".to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn upheld_appeal_leaves_the_block_in_place() {
+    let app = TestApp::builder()
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+    let blocked = check(&server, PHONE_PROMPT).await;
+    let correlation_id = blocked["correlation_id"].as_str().unwrap();
+    let appeal: Appeal = file(&server, correlation_id).await.json().await.unwrap();
+
+    let resolved: Appeal = resolve(&server, &appeal.id, "upheld")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resolved.state, AppealState::Upheld);
+    assert!(
+        resolved
+            .resolution
+            .expect("resolution")
+            .exemption_ids
+            .is_empty()
+    );
+    assert_eq!(
+        check(&server, PHONE_PROMPT).await["status"],
+        "blocked_by_firewall"
+    );
+
+    // A resolved appeal no longer stands in the way of a new one
+    assert_eq!(
+        file(&server, correlation_id).await.status(),
+        StatusCode::CREATED
+    );
+    assert_eq!(list(&server, "").await.len(), 2);
+}
+
+#[tokio::test]
+async fn only_audited_blocked_decisions_can_be_appealed() {
+    let app = TestApp::builder()
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+
+    assert_eq!(
+        file(&server, "no-such-decision").await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let allowed = check(&server, "What is the capital of France?").await;
+    assert_eq!(allowed["status"], "completed");
+    assert_eq!(
+        file(&server, allowed["correlation_id"].as_str().unwrap())
+            .await
+            .status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let response = server
+        .post("/api/appeals")
+        .json(&json!({ "correlation_id": "anything", "justification": " " }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        resolve(&server, "no-such-appeal", "overturned")
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert!(list(&server, "").await.is_empty());
+}
+
+#[tokio::test]
+async fn reviewing_appeals_needs_the_admin_token() {
+    let app = TestApp::builder()
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+    let blocked = check(&server, PHONE_PROMPT).await;
+
+    // Filing is public; the caller only needs the correlation id of their decision
+    let response = reqwest::Client::new()
+        .post(server.url("/api/appeals"))
+        .json(&json!({
+            "correlation_id": blocked["correlation_id"],
+            "justification": "I was asking about my own phone",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let appeal: Appeal = response.json().await.unwrap();
+
+    let listing = reqwest::Client::new()
+        .get(server.url("/api/appeals"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(listing.status(), StatusCode::UNAUTHORIZED);
+    let resolution = reqwest::Client::new()
+        .post(server.url(&format!("/api/appeals/{}/resolve", appeal.id)))
+        .json(&json!({
+            "outcome": "overturned",
+            "note": "self-approved",
+            "resolved_by": "the-user",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resolution.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,256 @@
+#![cfg(feature = "sled-storage")]
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use sled::Db;
+
+use prompt_sentinel::modules::audit::compression::AuditCompression;
+use prompt_sentinel::modules::audit::encryption::AuditEncryptionKey;
+use prompt_sentinel::modules::audit::proof::{AuditProof, chain_hash, hash_record};
+use prompt_sentinel::modules::audit::storage::{
+    AuditStorage, RecompressionReport, SledAuditStorage, StoredAuditRecord,
+};
+
+const COMPRESSED_MAGIC: &[u8] = b"PSZST1";
+
+fn temp_db(prefix: &str) -> (PathBuf, Db) {
+    let path = std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4()));
+    let db = sled::open(&path).expect("open sled");
+    (path, db)
+}
+
+fn compressed_above(threshold_bytes: usize) -> AuditCompression {
+    AuditCompression {
+        threshold_bytes,
+        level: 3,
+    }
+}
+
+/// Audit-event-like JSON whose prompt and output previews are `words` words long
+fn payload(correlation_id: &str, words: usize) -> String {
+    const VOCABULARY: [&str; 12] = [
+        "summarise",
+        "the",
+        "quarterly",
+        "report",
+        "for",
+        "our",
+        "board",
+        "including",
+        "revenue",
+        "risks",
+        "and",
+        "outlook",
+    ];
+    let text = |seed: usize| {
+        (0..words)
+            .map(|i| VOCABULARY[(i * 7 + seed) % VOCABULARY.len()])
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    serde_json::json!({
+        "correlation_id": correlation_id,
+        "final_status": "Completed",
+        "prompt": text(correlation_id.len()),
+        "output_preview": text(correlation_id.len() + 3),
+        "firewall_matched_rules": [],
+        "semantic_risk_score": 0.12,
+    })
+    .to_string()
+}
+
+fn append(storage: &dyn AuditStorage, correlation_id: &str, words: usize) {
+    let payload = payload(correlation_id, words);
+    let record_hash = hash_record(&payload);
+    let previous = storage.latest_chain_hash().expect("chain head");
+    storage
+        .append(StoredAuditRecord {
+            correlation_id: correlation_id.to_owned(),
+            timestamp: Utc::now(),
+            payload,
+            proof: AuditProof {
+                algorithm: "sha256".to_owned(),
+                chain_hash: chain_hash(previous.as_deref(), &record_hash),
+                record_hash,
+            },
+        })
+        .expect("append");
+    // Keep timestamp-prefixed keys in append order
+    std::thread::sleep(Duration::from_millis(2));
+}
+
+fn assert_chain_verifies(storage: &dyn AuditStorage) {
+    let mut previous: Option<String> = None;
+    for record in storage.all().expect("records") {
+        assert_eq!(record.proof.record_hash, hash_record(&record.payload));
+        assert_eq!(
+            record.proof.chain_hash,
+            chain_hash(previous.as_deref(), &record.proof.record_hash)
+        );
+        previous = Some(record.proof.chain_hash);
+    }
+}
+
+/// Payload and proof of every record, as reads return them
+fn contents(storage: &dyn AuditStorage) -> Vec<(String, String, String)> {
+    storage
+        .all()
+        .expect("records")
+        .into_iter()
+        .map(|record| {
+            (
+                record.payload,
+                record.proof.record_hash,
+                record.proof.chain_hash,
+            )
+        })
+        .collect()
+}
+
+fn raw_values(db: &Db) -> Vec<Vec<u8>> {
+    db.iter()
+        .values()
+        .map(|value| value.expect("raw value").to_vec())
+        .collect()
+}
+
+#[test]
+fn records_above_the_threshold_round_trip_compressed() {
+    let (path, db) = temp_db("audit_compression_round_trip");
+    let storage = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_compression(compressed_above(1024));
+    append(&storage, "corr-short", 5);
+    append(&storage, "corr-long", 400);
+
+    let records = storage.all().expect("records");
+    assert_eq!(records[0].payload, payload("corr-short", 5));
+    assert_eq!(records[1].payload, payload("corr-long", 400));
+    assert_chain_verifies(&storage);
+    assert_eq!(
+        storage.tail(1).expect("tail")[0].payload,
+        records[1].payload
+    );
+
+    let raw = raw_values(&db);
+    assert!(raw[0].starts_with(b"{"));
+    assert!(raw[1].starts_with(COMPRESSED_MAGIC));
+    assert!(raw[1].len() < records[1].payload.len() / 2);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn hashes_are_stable_across_the_compression_boundary() {
+    let (path, db) = temp_db("audit_compression_mixed");
+    let plain = SledAuditStorage::from_db(db.clone()).expect("open sled");
+    append(&plain, "corr-old-1", 400);
+    append(&plain, "corr-old-2", 400);
+    append(&plain, "corr-old-short", 5);
+    let before = contents(&plain);
+
+    // New records are compressed, old ones still read as they are
+    let compressed = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_compression(compressed_above(1024));
+    append(&compressed, "corr-new", 400);
+    let mixed = contents(&compressed);
+    assert_eq!(mixed[..3], before[..]);
+    assert_chain_verifies(&compressed);
+    let raw = raw_values(&db);
+    assert!(!raw[0].starts_with(COMPRESSED_MAGIC));
+    assert!(raw[3].starts_with(COMPRESSED_MAGIC));
+
+    // An interrupted migration is finished by running it again
+    let partial = compressed.recompress_limited(1).expect("partial");
+    assert_eq!(
+        partial,
+        RecompressionReport {
+            compressed: 1,
+            already_compressed: 1,
+            below_threshold: 1,
+            remaining: 1,
+        }
+    );
+    let finished = compressed.recompress().expect("recompress");
+    assert_eq!(finished.compressed, 1);
+    assert_eq!(finished.already_compressed, 2);
+    assert_eq!(finished.remaining, 0);
+
+    assert_eq!(contents(&compressed), mixed);
+    assert_chain_verifies(&compressed);
+    let raw = raw_values(&db);
+    assert!(raw[0].starts_with(COMPRESSED_MAGIC));
+    assert!(raw[1].starts_with(COMPRESSED_MAGIC));
+    assert!(raw[2].starts_with(b"{"));
+
+    // A build with compression turned off still reads everything
+    let reopened = SledAuditStorage::from_db(db.clone()).expect("open sled");
+    assert_eq!(contents(&reopened), mixed);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn compression_combines_with_encryption() {
+    let (path, db) = temp_db("audit_compression_encrypted");
+    let key = AuditEncryptionKey::generate().expect("key");
+    let encrypted = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(key.clone());
+    append(&encrypted, "corr-sealed", 400);
+
+    let storage = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(key.clone())
+        .with_compression(compressed_above(1024));
+    let uncompressed_size = raw_values(&db)[0].len();
+    append(&storage, "corr-both", 400);
+    assert!(raw_values(&db)[1].len() < uncompressed_size / 2);
+
+    assert_eq!(storage.recompress().expect("recompress").compressed, 1);
+    assert!(raw_values(&db)[0].len() < uncompressed_size / 2);
+    storage.verify_key().expect("key");
+    let records = storage.all().expect("records");
+    assert_eq!(records[0].payload, payload("corr-sealed", 400));
+    assert_eq!(records[1].payload, payload("corr-both", 400));
+    assert_chain_verifies(&storage);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn a_long_payload_corpus_shrinks_on_disk() {
+    let total_stored = |compression: AuditCompression| {
+        let (path, db) = temp_db("audit_compression_corpus");
+        let storage = SledAuditStorage::from_db(db.clone())
+            .expect("open sled")
+            .with_compression(compression);
+        for index in 0..40 {
+            let correlation_id = format!("corr-{index:0>width$}", width = index % 9 + 1);
+            append(&storage, &correlation_id, 400 + index * 25);
+        }
+        let total: usize = raw_values(&db).iter().map(Vec::len).sum();
+        let _ = std::fs::remove_dir_all(path);
+        total
+    };
+
+    let plain = total_stored(AuditCompression::disabled());
+    let compressed = total_stored(AuditCompression::default());
+    assert!(
+        compressed * 4 < plain,
+        "compressed {compressed} bytes, plain {plain} bytes"
+    );
+}
+
+#[test]
+fn compression_levels_are_checked() {
+    assert!(AuditCompression::default().validate().is_ok());
+    assert!(compressed_above(1).validate().is_ok());
+    for level in [0, 23] {
+        let compression = AuditCompression {
+            level,
+            ..AuditCompression::default()
+        };
+        assert!(compression.validate().is_err());
+    }
+}
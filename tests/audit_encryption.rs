@@ -0,0 +1,294 @@
+#![cfg(feature = "sled-storage")]
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use sled::Db;
+
+use prompt_sentinel::modules::audit::encryption::AuditEncryptionKey;
+use prompt_sentinel::modules::audit::logger::AuditLogger;
+use prompt_sentinel::modules::audit::proof::{AuditProof, chain_hash, hash_record};
+use prompt_sentinel::modules::audit::storage::{
+    AuditStorage, AuditStorageError, SledAuditStorage, StoredAuditRecord,
+};
+use prompt_sentinel::modules::bias_detection::service::BiasDetectionService;
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::modules::semantic_detection::service::SemanticDetectionService;
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest, WorkflowStatus};
+
+const SECRET_PROMPT: &str = "My locker code is tangerine-4471, what rhymes with it?";
+
+fn temp_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4()))
+}
+
+fn open_db(path: &Path) -> Db {
+    sled::open(path).expect("open sled")
+}
+
+fn append(storage: &dyn AuditStorage, correlation_id: &str) {
+    let payload = format!(r#"{{"correlation_id":"{correlation_id}","prompt":"{SECRET_PROMPT}"}}"#);
+    let record_hash = hash_record(&payload);
+    let previous = storage.latest_chain_hash().expect("chain head");
+    storage
+        .append(StoredAuditRecord {
+            correlation_id: correlation_id.to_owned(),
+            timestamp: Utc::now(),
+            payload,
+            proof: AuditProof {
+                algorithm: "sha256".to_owned(),
+                chain_hash: chain_hash(previous.as_deref(), &record_hash),
+                record_hash,
+            },
+        })
+        .expect("append");
+    // Keep timestamp-prefixed keys in append order
+    std::thread::sleep(Duration::from_millis(2));
+}
+
+fn assert_chain_verifies(storage: &dyn AuditStorage) {
+    let mut previous: Option<String> = None;
+    for record in storage.all().expect("records") {
+        assert_eq!(record.proof.record_hash, hash_record(&record.payload));
+        assert_eq!(
+            record.proof.chain_hash,
+            chain_hash(previous.as_deref(), &record.proof.record_hash)
+        );
+        previous = Some(record.proof.chain_hash);
+    }
+}
+
+/// Raw values in the audit tree, as they are on disk
+fn raw_values(db: &Db) -> Vec<Vec<u8>> {
+    db.iter()
+        .values()
+        .map(|value| value.expect("raw value").to_vec())
+        .collect()
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+#[tokio::test]
+async fn encrypted_records_hide_prompt_text_at_rest() {
+    let path = temp_path("audit_encrypted");
+    let db = open_db(&path);
+    let storage = Arc::new(
+        SledAuditStorage::from_db(db.clone())
+            .expect("open sled")
+            .with_encryption(AuditEncryptionKey::generate().expect("key")),
+    );
+    let mistral = MistralService::new(
+        Arc::new(MockMistralClient::default()),
+        "mistral-large-latest",
+        Some("mistral-moderation-latest".to_owned()),
+        "mistral-embed",
+    );
+    let engine = ComplianceEngine::new(
+        PromptFirewallService::default(),
+        SemanticDetectionService::new(mistral.clone(), 0.70, 0.80, 0.02),
+        BiasDetectionService::default(),
+        mistral,
+        AuditLogger::new(storage.clone()),
+    );
+    let response = engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: SECRET_PROMPT.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+
+    // Reads decrypt transparently and the chain still verifies over the plaintext
+    let records = storage.all().expect("records");
+    assert_eq!(records.len(), 1);
+    assert!(records[0].payload.contains("tangerine-4471"));
+    assert_chain_verifies(storage.as_ref());
+
+    let raw = raw_values(&db);
+    assert_eq!(raw.len(), 1);
+    for value in &raw {
+        assert!(!contains(value, "tangerine"));
+        assert!(!contains(value, "Mock response"));
+        assert!(!contains(value, "chain_hash"));
+    }
+    db.flush().expect("flush");
+    drop(engine);
+    drop(storage);
+    drop(db);
+    for entry in std::fs::read_dir(&path).expect("sled dir").flatten() {
+        if entry.path().is_file() {
+            let bytes = std::fs::read(entry.path()).expect("sled file");
+            assert!(!contains(&bytes, "tangerine"), "{:?}", entry.path());
+        }
+    }
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn wrong_or_missing_key_is_reported_clearly() {
+    let path = temp_path("audit_wrong_key");
+    let db = open_db(&path);
+    let key = AuditEncryptionKey::generate().expect("key");
+    append(
+        &SledAuditStorage::from_db(db.clone())
+            .expect("open sled")
+            .with_encryption(key.clone()),
+        "corr-a",
+    );
+
+    let other = AuditEncryptionKey::generate().expect("key");
+    let wrong = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(other.clone());
+    match wrong.verify_key() {
+        Err(AuditStorageError::WrongEncryptionKey {
+            record_key,
+            configured,
+        }) => {
+            assert_eq!(record_key, key.key_id());
+            assert_eq!(configured, other.key_id());
+        }
+        other => panic!("expected a wrong key error, got {other:?}"),
+    }
+    assert!(matches!(
+        wrong.all(),
+        Err(AuditStorageError::WrongEncryptionKey { .. })
+    ));
+
+    let keyless = SledAuditStorage::from_db(db.clone()).expect("open sled");
+    assert!(matches!(
+        keyless.verify_key(),
+        Err(AuditStorageError::EncryptionKeyMissing)
+    ));
+    assert!(matches!(
+        keyless.latest_chain_hash(),
+        Err(AuditStorageError::EncryptionKeyMissing)
+    ));
+
+    let right = SledAuditStorage::from_db(db)
+        .expect("open sled")
+        .with_encryption(key);
+    right.verify_key().expect("right key");
+    assert_eq!(right.all().expect("records").len(), 1);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn plaintext_records_stay_readable_after_enabling_encryption() {
+    let path = temp_path("audit_mixed");
+    let db = open_db(&path);
+    let plain = SledAuditStorage::from_db(db.clone()).expect("open sled");
+    append(&plain, "corr-plain-1");
+    append(&plain, "corr-plain-2");
+
+    let encrypted = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(AuditEncryptionKey::generate().expect("key"));
+    encrypted.verify_key().expect("no encrypted records yet");
+    append(&encrypted, "corr-encrypted");
+
+    let records = encrypted.all().expect("records");
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| record.correlation_id.as_str())
+            .collect::<Vec<_>>(),
+        ["corr-plain-1", "corr-plain-2", "corr-encrypted"]
+    );
+    assert_chain_verifies(&encrypted);
+    let raw = raw_values(&db);
+    assert!(contains(&raw[0], "tangerine"));
+    assert!(!contains(&raw[2], "tangerine"));
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn interrupted_key_rotation_resumes() {
+    let path = temp_path("audit_rotation");
+    let db = open_db(&path);
+    append(
+        &SledAuditStorage::from_db(db.clone()).expect("open sled"),
+        "corr-plain",
+    );
+    let old = AuditEncryptionKey::generate().expect("key");
+    let storage = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(old.clone());
+    for index in 0..4 {
+        append(&storage, &format!("corr-{index}"));
+    }
+
+    let new = AuditEncryptionKey::generate().expect("key");
+    let partial = storage
+        .rotate_key_limited(&old, &new, 2)
+        .expect("partial rotation");
+    assert_eq!(partial.encrypted_plaintext, 1);
+    assert_eq!(partial.reencrypted, 1);
+    assert_eq!(partial.remaining, 3);
+    // The interrupted storage still reads both generations
+    assert_eq!(storage.all().expect("records").len(), 5);
+
+    // After a restart with only the new key, the leftovers are named clearly
+    let restarted = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(new.clone());
+    match restarted.all() {
+        Err(AuditStorageError::WrongEncryptionKey { record_key, .. }) => {
+            assert_eq!(record_key, old.key_id());
+        }
+        other => panic!("expected a wrong key error, got {other:?}"),
+    }
+
+    let finished = restarted.rotate_key(&old, &new).expect("rotation");
+    assert_eq!(finished.already_current, 2);
+    assert_eq!(finished.reencrypted, 3);
+    assert_eq!(finished.remaining, 0);
+
+    let only_new = SledAuditStorage::from_db(db.clone())
+        .expect("open sled")
+        .with_encryption(new);
+    only_new.verify_key().expect("new key");
+    assert_eq!(only_new.all().expect("records").len(), 5);
+    assert_chain_verifies(&only_new);
+    for value in raw_values(&db) {
+        assert!(!contains(&value, "tangerine"));
+    }
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn keys_load_from_base64_or_a_key_file() {
+    let key = AuditEncryptionKey::generate().expect("key");
+    let parsed = AuditEncryptionKey::from_config(&key.to_base64()).expect("base64 key");
+    assert_eq!(parsed.key_id(), key.key_id());
+
+    let path = temp_path("audit_key");
+    std::fs::write(&path, format!("{}\n", key.to_base64())).unwrap();
+    let from_file = AuditEncryptionKey::from_config(path.to_str().unwrap()).expect("key file");
+    assert_eq!(from_file.key_id(), key.key_id());
+    std::fs::write(&path, [7u8; 32]).unwrap();
+    let raw = AuditEncryptionKey::from_config(path.to_str().unwrap()).expect("raw key file");
+    assert_eq!(
+        raw.key_id(),
+        AuditEncryptionKey::from_bytes([7u8; 32]).key_id()
+    );
+    std::fs::remove_file(&path).ok();
+
+    assert!(AuditEncryptionKey::from_config("c2hvcnQ=").is_err());
+    assert!(AuditEncryptionKey::from_config("/no/such/key/file").is_err());
+    // Debug output never shows key material
+    assert!(!format!("{key:?}").contains(&key.to_base64()));
+}
@@ -0,0 +1,260 @@
+#![cfg(all(
+    feature = "server",
+    feature = "sled-storage",
+    feature = "metrics-prometheus"
+))]
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::Utc;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde_json::Value;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::alerting::service::WebhookNotifier;
+use prompt_sentinel::modules::audit::integrity::{
+    AuditIntegrityChecker, AuditIntegrityConfig, IntegrityFailureKind, VerificationScope,
+};
+use prompt_sentinel::modules::audit::proof::{AuditProof, chain_hash, hash_record};
+use prompt_sentinel::modules::audit::storage::{AuditStorage, SledAuditStorage, StoredAuditRecord};
+use prompt_sentinel::test_support::TestApp;
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+/// Every check sets the process-wide gauge, so tests take turns
+async fn turn() -> tokio::sync::MutexGuard<'static, ()> {
+    static CHECKS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    // Installed before any check, or the gauge goes unrecorded
+    recorder();
+    CHECKS.lock().await
+}
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+fn chain_broken_gauge() -> f64 {
+    recorder()
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix("audit_chain_broken "))
+        .and_then(|value| value.parse().ok())
+        .expect("audit_chain_broken gauge")
+}
+
+/// Collect every alert posted to the returned URL
+async fn webhook_receiver() -> (String, Received) {
+    let received = Received::default();
+    let router = Router::new()
+        .route(
+            "/alerts",
+            post(
+                |State(received): State<Received>, Json(alert): Json<Value>| async move {
+                    received.lock().unwrap().push(alert);
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{address}/alerts"), received)
+}
+
+fn temp_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4()))
+}
+
+/// Append a correctly chained record on top of the storage's head
+fn append(storage: &dyn AuditStorage, correlation_id: &str) -> StoredAuditRecord {
+    let payload = format!(r#"{{"correlation_id":"{correlation_id}"}}"#);
+    let record_hash = hash_record(&payload);
+    let previous = storage.latest_chain_hash().expect("chain head");
+    let record = StoredAuditRecord {
+        correlation_id: correlation_id.to_owned(),
+        timestamp: Utc::now(),
+        payload,
+        proof: AuditProof {
+            algorithm: "sha256".to_owned(),
+            chain_hash: chain_hash(previous.as_deref(), &record_hash),
+            record_hash,
+        },
+    };
+    storage.append(record.clone()).expect("append");
+    // Distinct timestamps keep the sled key order the append order
+    std::thread::sleep(Duration::from_millis(2));
+    record
+}
+
+/// A sled trail of `count` records, `corr-0` first, with the database handle kept for
+/// tampering
+fn sled_trail(count: usize) -> (PathBuf, sled::Db, Arc<SledAuditStorage>) {
+    let path = temp_path("audit_integrity");
+    let db = sled::open(&path).expect("sled db");
+    let storage = Arc::new(SledAuditStorage::from_db(db.clone()).expect("storage"));
+    for i in 0..count {
+        append(storage.as_ref(), &format!("corr-{i}"));
+    }
+    (path, db, storage)
+}
+
+/// Rewrite the payload of the `index`th record in place, leaving its proof alone
+fn tamper(db: &sled::Db, index: usize) {
+    let (key, value) = db.iter().nth(index).expect("record").expect("readable");
+    let mut record: StoredAuditRecord = serde_json::from_slice(&value).expect("plaintext");
+    record.payload = record.payload.replace("corr", "edited");
+    db.insert(key, serde_json::to_vec(&record).unwrap())
+        .expect("rewrite");
+}
+
+fn config(tail_records: usize) -> AuditIntegrityConfig {
+    AuditIntegrityConfig {
+        tail_records,
+        fail_readiness: true,
+        ..AuditIntegrityConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn tail_check_raises_every_alarm_for_a_corrupted_record() {
+    let _turn = turn().await;
+    let (url, received) = webhook_receiver().await;
+    let (path, db, storage) = sled_trail(6);
+    let checker = AuditIntegrityChecker::new(storage, config(3))
+        .with_notifier(Some(WebhookNotifier::new(url)));
+
+    let report = checker.check_tail().await;
+    assert_eq!(report.failure, None);
+    assert_eq!(report.records_verified, 3);
+    assert_eq!(chain_broken_gauge(), 0.0);
+    assert!(!checker.blocks_readiness());
+
+    tamper(&db, 4);
+    let report = checker.check_tail().await;
+    let failure = report.failure.expect("corruption is found");
+    assert_eq!(failure.kind, IntegrityFailureKind::RecordHash);
+    assert_eq!(failure.scope, VerificationScope::Tail);
+    assert_eq!(failure.correlation_id.as_deref(), Some("corr-4"));
+    assert_eq!(chain_broken_gauge(), 1.0);
+    assert!(checker.blocks_readiness());
+    assert_eq!(checker.status().failure, Some(failure));
+
+    {
+        let alerts = received.lock().unwrap();
+        let [alert] = alerts.as_slice() else {
+            panic!("expected one alert: {alerts:?}");
+        };
+        assert_eq!(alert["alert"], "audit_chain_broken");
+        assert_eq!(alert["level"], "critical");
+        assert_eq!(alert["details"]["kind"], "record_hash");
+        assert_eq!(alert["details"]["correlation_id"], "corr-4");
+    }
+
+    // The same break is not posted again
+    assert!(checker.check_tail().await.failure.is_some());
+    assert_eq!(received.lock().unwrap().len(), 1);
+
+    drop(db);
+    std::fs::remove_dir_all(path).ok();
+}
+
+#[tokio::test]
+async fn full_verification_finds_breaks_older_than_the_tail() {
+    let _turn = turn().await;
+    let (path, db, storage) = sled_trail(8);
+    let checker = AuditIntegrityChecker::new(storage, config(3));
+    tamper(&db, 1);
+
+    assert_eq!(checker.check_tail().await.failure, None);
+    let failure = checker
+        .check_full()
+        .await
+        .failure
+        .expect("corruption is found");
+    assert_eq!(failure.scope, VerificationScope::Full);
+    assert_eq!(failure.correlation_id.as_deref(), Some("corr-1"));
+
+    // Passing tail checks cannot vouch for the record they no longer cover
+    assert_eq!(checker.check_tail().await.failure, None);
+    assert!(checker.status().is_broken());
+    assert_eq!(chain_broken_gauge(), 1.0);
+
+    drop(db);
+    std::fs::remove_dir_all(path).ok();
+}
+
+#[tokio::test]
+async fn removing_the_verified_head_breaks_continuity() {
+    let _turn = turn().await;
+    let (path, db, storage) = sled_trail(4);
+    let checker = AuditIntegrityChecker::new(storage, config(10));
+    assert_eq!(checker.check_tail().await.failure, None);
+
+    // What remains still chains correctly
+    let (last, _) = db.last().unwrap().expect("newest record");
+    db.remove(last).unwrap();
+    let failure = checker
+        .check_tail()
+        .await
+        .failure
+        .expect("truncation is found");
+    assert_eq!(failure.kind, IntegrityFailureKind::HeadContinuity);
+    assert_eq!(failure.correlation_id.as_deref(), Some("corr-3"));
+
+    drop(db);
+    std::fs::remove_dir_all(path).ok();
+}
+
+#[tokio::test]
+async fn readiness_reports_a_broken_chain() {
+    let _turn = turn().await;
+    let app = TestApp::builder()
+        .with_settings(AppSettings {
+            audit_integrity: config(100),
+            ..AppSettings::default()
+        })
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+
+    let ready = server.get("/ready").send().await.unwrap();
+    assert_eq!(ready.status(), StatusCode::OK);
+    let body: Value = ready.json().await.unwrap();
+    assert_eq!(body["ready"], true);
+
+    append(app.storage.as_ref(), "corr-0");
+    let mut forged = append(app.storage.as_ref(), "corr-1");
+    forged.payload = r#"{"correlation_id":"forged"}"#.to_owned();
+    app.storage.append(forged).unwrap();
+
+    let report: Value = server
+        .post("/api/audit/verify")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["scope"], "full");
+    assert_eq!(report["failure"]["kind"], "record_hash");
+
+    let ready = server.get("/ready").send().await.unwrap();
+    assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = ready.json().await.unwrap();
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["audit_chain"]["failure"]["correlation_id"], "corr-1");
+}
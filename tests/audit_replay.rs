@@ -0,0 +1,172 @@
+#![cfg(feature = "server")]
+
+use serde_json::Value;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::{AuditStorage, PromptStorageMode};
+use prompt_sentinel::modules::prompt_firewall::rules::{
+    CompiledFirewallRules, FirewallRulesConfig, RuleEntry,
+};
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest, ReplayMode, WorkflowStatus};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+const CODENAME_PROMPT: &str = "Tell me about project bluefin";
+
+async fn app(prompt_storage: PromptStorageMode) -> TestApp {
+    let mut rules = FirewallRulesConfig::default();
+    rules
+        .block_rules
+        .push(RuleEntry::new("PFW-TEST-001", "project bluefin"));
+    TestApp::builder()
+        .with_settings(AppSettings {
+            audit_prompt_storage: prompt_storage,
+            ..AppSettings::default()
+        })
+        .with_admin_token(ADMIN_TOKEN)
+        .with_firewall(
+            PromptFirewallService::default()
+                .with_rules(CompiledFirewallRules::compile(rules).expect("valid rules")),
+        )
+        .build()
+        .await
+        .expect("test app")
+}
+
+async fn blocked_codename_request(engine: &ComplianceEngine) -> String {
+    let response = engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: CODENAME_PROMPT.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::BlockedByFirewall);
+    response.correlation_id
+}
+
+#[tokio::test]
+async fn replay_shows_what_a_rule_change_does_to_a_past_decision() {
+    let app = app(PromptStorageMode::default()).await;
+    let engine = &app.engine;
+    let correlation_id = blocked_codename_request(engine).await;
+
+    // Drop the codename rule again
+    let path = std::env::temp_dir().join(format!("replay_rules_{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        serde_json::to_string(&FirewallRulesConfig::default()).unwrap(),
+    )
+    .unwrap();
+    engine
+        .firewall_service()
+        .reload_rules(&path)
+        .expect("reload");
+    std::fs::remove_file(&path).ok();
+
+    let current = engine
+        .replay(&correlation_id, "replay-current", ReplayMode::Current)
+        .await
+        .expect("replay");
+    assert_eq!(current.original_status, "blocked_by_firewall");
+    assert_eq!(current.replayed_status, "completed");
+    assert!(current.diverged);
+    assert_eq!(
+        current.original.firewall_matched_rules,
+        vec!["PFW-TEST-001".to_owned()]
+    );
+    assert!(current.replayed.firewall_matched_rules.is_empty());
+    let changed: Vec<&str> = current
+        .differences
+        .iter()
+        .map(|change| change.field.as_str())
+        .collect();
+    for field in [
+        "final_decision",
+        "firewall_action",
+        "firewall_matched_rules",
+    ] {
+        assert!(changed.contains(&field), "{field} missing from {changed:?}");
+    }
+
+    let historical = engine
+        .replay(&correlation_id, "replay-historical", ReplayMode::Historical)
+        .await
+        .expect("replay");
+    assert_eq!(historical.replayed_status, "blocked_by_firewall");
+    assert!(!historical.diverged);
+    assert_eq!(
+        historical.replayed.firewall_matched_rules,
+        historical.original.firewall_matched_rules
+    );
+    assert_eq!(
+        historical.replayed.final_reason,
+        historical.original.final_reason
+    );
+    assert_eq!(
+        historical.replayed.final_reason_code,
+        historical.original.final_reason_code
+    );
+    assert_eq!(
+        historical.replayed.reason_params,
+        historical.original.reason_params
+    );
+
+    let replays: Vec<Value> = app
+        .storage
+        .all()
+        .expect("records")
+        .iter()
+        .map(|record| serde_json::from_str::<Value>(&record.payload).unwrap())
+        .filter(|payload| payload["event_type"] == "replay")
+        .collect();
+    assert_eq!(replays.len(), 2);
+    assert_eq!(
+        replays[0]["replayed_correlation_id"],
+        correlation_id.as_str()
+    );
+    assert_eq!(replays[0]["mode"], "current");
+    assert_eq!(replays[1]["mode"], "historical");
+}
+
+#[tokio::test]
+async fn replay_needs_a_stored_prompt() {
+    let app = app(PromptStorageMode::Redacted).await;
+    let correlation_id = blocked_codename_request(&app.engine).await;
+    let record = app.storage.all().expect("records").pop().expect("record");
+    let payload: Value = serde_json::from_str(&record.payload).unwrap();
+    assert!(
+        payload["original_prompt"]
+            .as_str()
+            .unwrap()
+            .starts_with("v1:")
+    );
+
+    let server = app.serve().await.unwrap();
+    let base_url = &server.base_url;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!(
+            "{base_url}/api/audit/replay/{correlation_id}?mode=historical"
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    assert!(response.text().await.unwrap().contains("was not stored"));
+
+    let response = client
+        .post(format!("{base_url}/api/audit/replay/unknown-request"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
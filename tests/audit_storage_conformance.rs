@@ -0,0 +1,415 @@
+//! Behaviour every `AuditStorage` backend must share, run against each implementation
+
+use std::collections::HashSet;
+#[cfg(any(feature = "sled-storage", feature = "sqlite-storage"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use prompt_sentinel::modules::audit::batched::{BatchedAuditStorage, WriteBehindConfig};
+#[cfg(feature = "sled-storage")]
+use prompt_sentinel::modules::audit::encryption::AuditEncryptionKey;
+use prompt_sentinel::modules::audit::proof::{AuditProof, chain_hash, hash_record};
+#[cfg(feature = "sqlite-storage")]
+use prompt_sentinel::modules::audit::sqlite::SqliteAuditStorage;
+#[cfg(feature = "sled-storage")]
+use prompt_sentinel::modules::audit::storage::SledAuditStorage;
+use prompt_sentinel::modules::audit::storage::{
+    AuditStorage, AuditStorageError, InMemoryAuditStorage, StoredAuditRecord,
+};
+
+/// Build the next record on top of the storage's current chain head
+fn next_record(
+    storage: &dyn AuditStorage,
+    correlation_id: &str,
+    status: &str,
+) -> StoredAuditRecord {
+    let payload = format!(r#"{{"correlation_id":"{correlation_id}","final_status":"{status}"}}"#);
+    let record_hash = hash_record(&payload);
+    let previous = storage.latest_chain_hash().expect("chain head");
+    StoredAuditRecord {
+        correlation_id: correlation_id.to_owned(),
+        timestamp: Utc::now(),
+        payload,
+        proof: AuditProof {
+            algorithm: "sha256".to_owned(),
+            chain_hash: chain_hash(previous.as_deref(), &record_hash),
+            record_hash,
+        },
+    }
+}
+
+fn append(storage: &dyn AuditStorage, correlation_id: &str, status: &str) -> StoredAuditRecord {
+    let record = next_record(storage, correlation_id, status);
+    storage.append(record.clone()).expect("append");
+    // Keep timestamps distinct so time-range filters have clear boundaries
+    std::thread::sleep(Duration::from_millis(5));
+    record
+}
+
+fn check_conformance(storage: &dyn AuditStorage) {
+    assert_eq!(storage.latest_chain_hash().unwrap(), None);
+    assert!(storage.all().unwrap().is_empty());
+
+    let first = append(storage, "corr-a", "completed");
+    let second = append(storage, "corr-b", "blocked_by_firewall");
+    let third = append(storage, "corr-a", "sanitized");
+
+    let all = storage.all().unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(
+        all.iter().map(|r| r.payload.as_str()).collect::<Vec<_>>(),
+        vec![
+            first.payload.as_str(),
+            second.payload.as_str(),
+            third.payload.as_str()
+        ]
+    );
+    assert_eq!(all[0].timestamp, first.timestamp);
+    assert_eq!(all[2].proof, third.proof);
+    assert_eq!(
+        storage.latest_chain_hash().unwrap(),
+        Some(third.proof.chain_hash.clone())
+    );
+
+    // The stored chain verifies from the first record onwards
+    let mut previous: Option<String> = None;
+    for record in &all {
+        assert_eq!(
+            record.proof.chain_hash,
+            chain_hash(previous.as_deref(), &hash_record(&record.payload))
+        );
+        previous = Some(record.proof.chain_hash.clone());
+    }
+
+    // The end of the trail, oldest first
+    let tail = storage.tail(2).unwrap();
+    assert_eq!(
+        tail.iter().map(|r| r.payload.as_str()).collect::<Vec<_>>(),
+        vec![second.payload.as_str(), third.payload.as_str()]
+    );
+    assert_eq!(storage.tail(10).unwrap().len(), 3);
+    assert!(storage.tail(0).unwrap().is_empty());
+
+    let by_correlation = storage
+        .get_with_filters(None, None, None, None, Some("corr-a".to_owned()))
+        .unwrap();
+    assert_eq!(by_correlation.total_count, 2);
+    assert_eq!(by_correlation.limit, 100);
+    assert_eq!(by_correlation.offset, 0);
+    assert!(
+        by_correlation
+            .records
+            .iter()
+            .all(|r| r.correlation_id == "corr-a")
+    );
+
+    let page = storage
+        .get_with_filters(Some(1), Some(1), None, None, None)
+        .unwrap();
+    assert_eq!(page.total_count, 3);
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].payload, second.payload);
+
+    let window = storage
+        .get_with_filters(
+            None,
+            None,
+            Some(second.timestamp),
+            Some(second.timestamp),
+            None,
+        )
+        .unwrap();
+    assert_eq!(window.total_count, 1);
+    assert_eq!(window.records[0].correlation_id, "corr-b");
+
+    let since_second = storage
+        .get_with_filters(None, None, Some(second.timestamp), None, None)
+        .unwrap();
+    assert_eq!(since_second.total_count, 2);
+
+    let combined = storage
+        .get_with_filters(
+            None,
+            None,
+            Some(second.timestamp),
+            None,
+            Some("corr-a".to_owned()),
+        )
+        .unwrap();
+    assert_eq!(combined.total_count, 1);
+    assert_eq!(combined.records[0].payload, third.payload);
+
+    // Cursor pages cover the trail once, in order
+    let payloads = |records: &[StoredAuditRecord]| {
+        records
+            .iter()
+            .map(|r| r.payload.clone())
+            .collect::<Vec<_>>()
+    };
+    let first_page = storage.get_page(None, Some(2), None, None, None).unwrap();
+    assert_eq!(first_page.total_count, 3);
+    assert_eq!(
+        payloads(&first_page.records),
+        vec![first.payload.clone(), second.payload.clone()]
+    );
+    let cursor = first_page.next_cursor.expect("more records");
+    let last_page = storage
+        .get_page(Some(&cursor), Some(2), None, None, None)
+        .unwrap();
+    assert_eq!(payloads(&last_page.records), vec![third.payload.clone()]);
+    assert_eq!(last_page.next_cursor, None);
+
+    // Offset pages hand over to cursor paging
+    let offset_page = storage
+        .get_with_filters(Some(1), None, None, None, None)
+        .unwrap();
+    let cursor = offset_page.next_cursor.expect("more records");
+    let rest = storage
+        .get_page(Some(&cursor), None, None, None, None)
+        .unwrap();
+    assert_eq!(
+        payloads(&rest.records),
+        vec![second.payload.clone(), third.payload.clone()]
+    );
+    let tail = storage
+        .get_with_filters(Some(5), Some(1), None, None, None)
+        .unwrap();
+    assert_eq!(tail.next_cursor, None);
+
+    let by_correlation = storage
+        .get_page(None, Some(1), None, None, Some("corr-a".to_owned()))
+        .unwrap();
+    assert_eq!(by_correlation.total_count, 2);
+    assert_eq!(payloads(&by_correlation.records), vec![first.payload]);
+    let next = storage
+        .get_page(
+            by_correlation.next_cursor.as_deref(),
+            Some(1),
+            None,
+            None,
+            Some("corr-a".to_owned()),
+        )
+        .unwrap();
+    assert_eq!(payloads(&next.records), vec![third.payload]);
+    assert_eq!(next.next_cursor, None);
+
+    let since_second = storage
+        .get_page(None, Some(10), Some(second.timestamp), None, None)
+        .unwrap();
+    assert_eq!(since_second.total_count, 2);
+    assert_eq!(since_second.records[0].correlation_id, "corr-b");
+
+    assert!(matches!(
+        storage.get_page(Some("not a cursor!"), None, None, None, None),
+        Err(AuditStorageError::InvalidCursor)
+    ));
+}
+
+/// Page through a seeded trail with cursors while another thread keeps appending
+fn check_paging_under_concurrent_appends(storage: &dyn AuditStorage) {
+    for i in 0..40 {
+        let record = next_record(storage, &format!("seed-{i:02}"), "completed");
+        storage.append(record).expect("seed");
+    }
+    let seeded: Vec<String> = storage
+        .all()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.correlation_id)
+        .collect();
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..40 {
+                let record = next_record(storage, &format!("live-{i:02}"), "completed");
+                storage.append(record).expect("concurrent append");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = storage
+                .get_page(cursor.as_deref(), Some(7), None, None, None)
+                .expect("page");
+            seen.extend(page.records.into_iter().map(|r| r.correlation_id));
+            std::thread::sleep(Duration::from_millis(1));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let unique: HashSet<&String> = seen.iter().collect();
+        assert_eq!(unique.len(), seen.len(), "duplicates in {seen:?}");
+        assert_eq!(seen[..seeded.len()], seeded[..]);
+    });
+}
+
+#[cfg(any(feature = "sled-storage", feature = "sqlite-storage"))]
+fn temp_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4()))
+}
+
+#[test]
+fn in_memory_storage_conforms() {
+    check_conformance(&InMemoryAuditStorage::new());
+    check_paging_under_concurrent_appends(&InMemoryAuditStorage::new());
+}
+
+fn write_behind() -> WriteBehindConfig {
+    WriteBehindConfig {
+        flush_interval_ms: 5,
+        max_batch_size: 8,
+        queue_capacity: 16,
+    }
+}
+
+#[test]
+fn batched_in_memory_storage_conforms() {
+    let batched = |inner| BatchedAuditStorage::new(Arc::new(inner), write_behind());
+    check_conformance(&batched(InMemoryAuditStorage::new()));
+    check_paging_under_concurrent_appends(&batched(InMemoryAuditStorage::new()));
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn sled_storage_conforms() {
+    let path = temp_path("audit_sled");
+    let storage = SledAuditStorage::new(path.to_str().unwrap()).expect("open sled");
+    check_conformance(&storage);
+    drop(storage);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn sled_cursor_paging_survives_concurrent_appends() {
+    let path = temp_path("audit_sled_paging");
+    let storage = SledAuditStorage::new(path.to_str().unwrap()).expect("open sled");
+    check_paging_under_concurrent_appends(&storage);
+    drop(storage);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn batched_sled_storage_conforms() {
+    for check in [check_conformance, check_paging_under_concurrent_appends] {
+        let path = temp_path("audit_sled_batched");
+        let sled = SledAuditStorage::new(path.to_str().unwrap()).expect("open sled");
+        let storage = BatchedAuditStorage::new(Arc::new(sled), write_behind());
+        check(&storage);
+        drop(storage);
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn encrypted_sled_storage_conforms() {
+    let path = temp_path("audit_sled_encrypted");
+    let storage = SledAuditStorage::new(path.to_str().unwrap())
+        .expect("open sled")
+        .with_encryption(AuditEncryptionKey::generate().expect("key"));
+    check_conformance(&storage);
+    drop(storage);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn sled_indexes_records_written_before_the_correlation_index() {
+    let path = temp_path("audit_sled_index");
+    let db = sled::open(&path).expect("open sled");
+    let storage = SledAuditStorage::from_db(db.clone()).expect("open sled");
+    append(&storage, "corr-a", "completed");
+    append(&storage, "corr-b", "completed");
+    append(&storage, "corr-a", "sanitized");
+    drop(storage);
+    // As a database from before the index existed
+    db.drop_tree("audit_correlation_index").expect("drop index");
+
+    let reopened = SledAuditStorage::from_db(db).expect("open sled");
+    let page = reopened
+        .get_with_filters(None, None, None, None, Some("corr-a".to_owned()))
+        .unwrap();
+    assert_eq!(page.total_count, 2);
+    assert!(page.records.iter().all(|r| r.correlation_id == "corr-a"));
+    let page = reopened
+        .get_page(None, Some(1), None, None, Some("corr-b".to_owned()))
+        .unwrap();
+    assert_eq!((page.total_count, page.records.len()), (1, 1));
+    drop(reopened);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn sqlite_storage_conforms() {
+    check_conformance(&SqliteAuditStorage::in_memory().expect("open sqlite"));
+    check_paging_under_concurrent_appends(&SqliteAuditStorage::in_memory().expect("open sqlite"));
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn batched_sqlite_storage_conforms() {
+    let sqlite = SqliteAuditStorage::in_memory().expect("open sqlite");
+    check_conformance(&BatchedAuditStorage::new(Arc::new(sqlite), write_behind()));
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn sqlite_file_storage_persists_across_reopen() {
+    let path = temp_path("audit_sqlite");
+    let storage = SqliteAuditStorage::open(&path).expect("open sqlite");
+    check_conformance(&storage);
+    let head = storage.latest_chain_hash().unwrap();
+    drop(storage);
+
+    let reopened = SqliteAuditStorage::open(&path).expect("reopen sqlite");
+    assert_eq!(reopened.all().unwrap().len(), 3);
+    assert_eq!(reopened.latest_chain_hash().unwrap(), head);
+
+    // Investigators can filter on the extracted status column with plain SQL
+    let conn = rusqlite::Connection::open(&path).expect("open for query");
+    let blocked: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM audit_records WHERE final_status = 'blocked_by_firewall'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(blocked, 1);
+    let journal_mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(journal_mode, "wal");
+
+    drop(reopened);
+    drop(conn);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[cfg(feature = "sqlite-storage")]
+#[test]
+fn sqlite_rejects_records_that_fork_the_chain() {
+    let storage = SqliteAuditStorage::in_memory().expect("open sqlite");
+    // Two writers build on the same head; only the first may land
+    let winner = next_record(&storage, "corr-1", "completed");
+    let loser = next_record(&storage, "corr-2", "completed");
+    storage.append(winner.clone()).expect("first append");
+
+    let error = storage.append(loser).expect_err("stale head");
+    assert!(matches!(error, AuditStorageError::ChainConflict));
+    assert_eq!(storage.all().unwrap().len(), 1);
+    assert_eq!(
+        storage.latest_chain_hash().unwrap(),
+        Some(winner.proof.chain_hash)
+    );
+}
@@ -0,0 +1,248 @@
+#![cfg(feature = "server")]
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::json;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::logger::SuppressedRepeatsEvent;
+use prompt_sentinel::modules::audit::proof::{chain_hash, hash_record};
+use prompt_sentinel::modules::audit::storage::{
+    AuditStorage, AuditTrailResponse, PromptStorageMode, StoredAuditRecord,
+};
+use prompt_sentinel::modules::audit::suppression::{
+    AuditSuppressionPolicy, SYNTHETIC_HEADER, SuppressionRule,
+};
+use prompt_sentinel::modules::request_fingerprint::service::prompt_fingerprint;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+const CANARY: &str = "Health check: reply with OK.";
+const REAL: &str = "Summarize the benefits of unit testing.";
+
+async fn app(policy: AuditSuppressionPolicy) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            audit_suppression: policy,
+            ..AppSettings::default()
+        })
+        .with_admin_token("admin-secret")
+        .build()
+        .await
+        .unwrap()
+}
+
+async fn check(server: &TestServer, body: serde_json::Value) -> serde_json::Value {
+    let response = server
+        .post("/api/compliance/check")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+fn aggregates(records: &[StoredAuditRecord]) -> Vec<SuppressedRepeatsEvent> {
+    records
+        .iter()
+        .filter_map(|record| serde_json::from_str::<SuppressedRepeatsEvent>(&record.payload).ok())
+        .collect()
+}
+
+fn assert_chain_intact(records: &[StoredAuditRecord]) {
+    let mut previous: Option<String> = None;
+    for record in records {
+        assert_eq!(
+            record.proof.chain_hash,
+            chain_hash(previous.as_deref(), &record.proof.record_hash)
+        );
+        previous = Some(record.proof.chain_hash.clone());
+    }
+}
+
+#[tokio::test]
+async fn canary_repeats_collapse_into_one_aggregate_per_window() {
+    let app = app(AuditSuppressionPolicy {
+        window_secs: 3600,
+        correlation_id_prefixes: vec!["synthetic-".to_owned()],
+        ..AuditSuppressionPolicy::default()
+    })
+    .await;
+    let server = app.serve().await.unwrap();
+
+    for i in 0..50 {
+        let body = check(
+            &server,
+            json!({ "prompt": CANARY, "correlation_id": format!("synthetic-canary-{i}") }),
+        )
+        .await;
+        let algorithm = body["audit_proof"]["algorithm"].as_str().unwrap();
+        assert_eq!(algorithm, if i == 0 { "sha256" } else { "suppressed" });
+        if i == 25 {
+            check(&server, json!({ "prompt": REAL })).await;
+        }
+    }
+    // Repeats are only counted until the window is closed
+    assert_eq!(app.storage.all().unwrap().len(), 2);
+    app.audit_logger.flush().unwrap();
+
+    let records = app.storage.all().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].correlation_id, "synthetic-canary-0");
+    assert!(records[1].payload.contains(REAL));
+    let aggregates = aggregates(&records);
+    assert_eq!(aggregates.len(), 1);
+    let aggregate = &aggregates[0];
+    assert_eq!(aggregate.correlation_id, "synthetic-canary-0");
+    assert_eq!(aggregate.count, 49);
+    assert_eq!(aggregate.rule, SuppressionRule::CorrelationPrefix);
+    assert_eq!(aggregate.prompt_hash, prompt_fingerprint(CANARY));
+    assert_eq!(aggregate.final_status, "completed");
+    assert!(aggregate.first_at <= aggregate.last_at);
+    assert_chain_intact(&records);
+
+    let trail: AuditTrailResponse = server
+        .post("/api/audit/trail")
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(trail.total_count, 3);
+    assert_eq!(trail.suppressed_repeats, 49);
+}
+
+#[tokio::test]
+async fn windows_close_on_the_next_repeat_and_quiet_groups_start_over() {
+    let app = app(AuditSuppressionPolicy {
+        window_secs: 2,
+        prompt_hashes: vec![format!("sha256:{}", hash_record(CANARY).to_uppercase())],
+        ..AuditSuppressionPolicy::default()
+    })
+    .await;
+    let server = app.serve().await.unwrap();
+    let canary = || json!({ "prompt": CANARY });
+    let pause = |millis| tokio::time::sleep(Duration::from_millis(millis));
+
+    for _ in 0..3 {
+        check(&server, canary()).await;
+    }
+    pause(1200).await;
+    check(&server, canary()).await;
+    pause(1200).await;
+    // Closes the first window and opens the next one with itself counted
+    check(&server, canary()).await;
+    pause(2200).await;
+    // Closes the second window; the group went quiet, so this one is stored in full
+    check(&server, canary()).await;
+
+    let records = app.storage.all().unwrap();
+    assert_eq!(records.len(), 4);
+    let counts = aggregates(&records)
+        .iter()
+        .map(|aggregate| (aggregate.rule, aggregate.count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        [
+            (SuppressionRule::PromptHash, 3),
+            (SuppressionRule::PromptHash, 1)
+        ]
+    );
+    assert!(aggregates(&records[3..]).is_empty());
+    assert_chain_intact(&records);
+}
+
+#[tokio::test]
+async fn fingerprints_match_redacted_prompts_and_plain_hashes_do_not() {
+    let policy = |hash: String| AuditSuppressionPolicy {
+        window_secs: 3600,
+        prompt_hashes: vec![hash],
+        ..AuditSuppressionPolicy::default()
+    };
+    let redacted = |policy| async move {
+        TestApp::builder()
+            .with_settings(AppSettings {
+                audit_suppression: policy,
+                audit_prompt_storage: PromptStorageMode::Redacted,
+                ..AppSettings::default()
+            })
+            .build()
+            .await
+            .unwrap()
+    };
+
+    let app = redacted(policy(prompt_fingerprint(CANARY))).await;
+    let server = app.serve().await.unwrap();
+    // Line endings and outer whitespace do not change the fingerprint
+    for prompt in [CANARY, "  Health check: reply with OK.\r\n", CANARY] {
+        check(&server, json!({ "prompt": prompt })).await;
+    }
+    app.audit_logger.flush().unwrap();
+    let records = app.storage.all().unwrap();
+    assert_eq!(records.len(), 2);
+    let aggregates = aggregates(&records);
+    assert_eq!(aggregates[0].count, 2);
+    assert_eq!(aggregates[0].prompt_hash, prompt_fingerprint(CANARY));
+
+    // A plain SHA-256 is of the prompt as sent, which a redacted record no longer has
+    let app = redacted(policy(format!("sha256:{}", hash_record(CANARY)))).await;
+    let server = app.serve().await.unwrap();
+    for _ in 0..2 {
+        check(&server, json!({ "prompt": CANARY })).await;
+    }
+    app.audit_logger.flush().unwrap();
+    assert_eq!(app.storage.all().unwrap().len(), 2);
+
+    assert!(
+        policy(prompt_fingerprint(CANARY).to_uppercase())
+            .validate()
+            .is_ok()
+    );
+    assert!(policy("v1:abc".to_owned()).validate().is_err());
+}
+
+#[tokio::test]
+async fn only_administrators_can_mark_traffic_as_synthetic() {
+    let app = app(AuditSuppressionPolicy {
+        window_secs: 3600,
+        ..AuditSuppressionPolicy::default()
+    })
+    .await;
+    let server = app.serve().await.unwrap();
+
+    // Without the admin token the server's client sends by default
+    let response = reqwest::Client::new()
+        .post(server.url("/api/compliance/check"))
+        .header(SYNTHETIC_HEADER, "true")
+        .json(&json!({ "prompt": CANARY }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(app.storage.all().unwrap().is_empty());
+
+    for _ in 0..3 {
+        let response = server
+            .post("/api/compliance/check")
+            .header(SYNTHETIC_HEADER, "true")
+            .json(&json!({ "prompt": CANARY }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    // Without the flag nothing matches, so the same prompt is stored in full
+    check(&server, json!({ "prompt": CANARY })).await;
+    app.audit_logger.flush().unwrap();
+
+    let records = app.storage.all().unwrap();
+    assert_eq!(records.len(), 3);
+    let aggregates = aggregates(&records);
+    assert_eq!(aggregates.len(), 1);
+    assert_eq!(aggregates[0].rule, SuppressionRule::AdminFlag);
+    assert_eq!(aggregates[0].count, 2);
+}
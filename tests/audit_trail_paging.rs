@@ -0,0 +1,128 @@
+#![cfg(feature = "server")]
+
+use std::io::Read;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::modules::audit::storage::MAX_AUDIT_TRAIL_OFFSET;
+use prompt_sentinel::test_support::TestApp;
+
+async fn seeded_router(prompts: usize) -> Router {
+    let app = TestApp::new().await;
+    for i in 0..prompts {
+        app.engine
+            .process(ComplianceRequest {
+                correlation_id: Some(format!("paging-{i}")),
+                prompt: "What is the capital of France?".to_owned(),
+                suggest_rewrite: false,
+                template: None,
+                deterministic: None,
+                session_id: None,
+            })
+            .await
+            .expect("workflow");
+    }
+    app.router()
+}
+
+async fn trail(router: &Router, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/audit/trail")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
+
+#[tokio::test]
+async fn cursor_pages_walk_the_whole_trail() {
+    let router = seeded_router(5).await;
+
+    let mut seen = Vec::new();
+    let (status, mut page) = trail(&router, json!({"limit": 2})).await;
+    loop {
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total_count"], 5);
+        for record in page["records"].as_array().unwrap() {
+            seen.push(record["correlation_id"].as_str().unwrap().to_owned());
+        }
+        let Some(cursor) = page["next_cursor"].as_str().map(str::to_owned) else {
+            break;
+        };
+        (_, page) = trail(&router, json!({"limit": 2, "cursor": cursor})).await;
+    }
+    let expected: Vec<String> = (0..5).map(|i| format!("paging-{i}")).collect();
+    assert_eq!(seen, expected);
+}
+
+#[tokio::test]
+async fn deep_offsets_and_bad_cursors_are_rejected() {
+    let router = seeded_router(1).await;
+
+    let (status, body) = trail(&router, json!({"offset": MAX_AUDIT_TRAIL_OFFSET + 1})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.as_str().unwrap().contains("cursor"), "{body}");
+    let (status, _) = trail(&router, json!({"offset": MAX_AUDIT_TRAIL_OFFSET})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = trail(&router, json!({"offset": 3, "cursor": "MA"})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = trail(&router, json!({"cursor": "%%%"})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn exports_are_compressed_when_the_client_accepts_it() {
+    let router = seeded_router(20).await;
+    let request = |encoding: Option<&str>| {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/api/audit/trail")
+            .header("content-type", "application/json");
+        if let Some(encoding) = encoding {
+            request = request.header("accept-encoding", encoding);
+        }
+        request
+            .body(Body::from(json!({"limit": 20}).to_string()))
+            .unwrap()
+    };
+
+    let plain = router.clone().oneshot(request(None)).await.unwrap();
+    assert!(plain.headers().get("content-encoding").is_none());
+    let plain = to_bytes(plain.into_body(), usize::MAX).await.unwrap();
+
+    for encoding in ["zstd", "gzip"] {
+        let response = router
+            .clone()
+            .oneshot(request(Some(encoding)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], encoding);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.len() < plain.len(), "{encoding}");
+        let decoded = match encoding {
+            "zstd" => zstd::stream::decode_all(&body[..]).unwrap(),
+            _ => {
+                let mut decoded = Vec::new();
+                flate2::read::GzDecoder::new(&body[..])
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+                decoded
+            }
+        };
+        let page: Value = serde_json::from_slice(&decoded).unwrap();
+        assert_eq!(page["total_count"], 20);
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use prompt_sentinel::modules::audit::batched::{BatchedAuditStorage, WriteBehindConfig};
+use prompt_sentinel::modules::audit::logger::{AuditLogger, ConfigChangeEvent};
+use prompt_sentinel::modules::audit::proof::{AuditProof, chain_hash, hash_record};
+use prompt_sentinel::modules::audit::storage::{
+    AuditStorage, AuditStorageError, AuditTrailResponse, InMemoryAuditStorage, StoredAuditRecord,
+};
+
+/// Long enough that nothing is written unless a batch fills or a flush is requested
+const IDLE: WriteBehindConfig = WriteBehindConfig {
+    flush_interval_ms: 60_000,
+    max_batch_size: 1_000,
+    queue_capacity: 1_000,
+};
+
+fn event(index: usize) -> ConfigChangeEvent {
+    ConfigChangeEvent {
+        correlation_id: format!("change-{index:04}"),
+        event_type: "configuration_change".to_owned(),
+        action: "restore".to_owned(),
+        previous_hash: format!("hash-{index}"),
+        new_hash: format!("hash-{}", index + 1),
+        snapshot_version: None,
+        detail: None,
+    }
+}
+
+fn log_events(logger: &AuditLogger, range: std::ops::Range<usize>) -> Vec<AuditProof> {
+    range
+        .map(|index| logger.log_config_change(event(index)).expect("audit"))
+        .collect()
+}
+
+fn assert_chain_verifies(records: &[StoredAuditRecord]) {
+    let mut previous: Option<String> = None;
+    for record in records {
+        assert_eq!(record.proof.record_hash, hash_record(&record.payload));
+        assert_eq!(
+            record.proof.chain_hash,
+            chain_hash(previous.as_deref(), &record.proof.record_hash),
+            "chain breaks at {}",
+            record.correlation_id
+        );
+        previous = Some(record.proof.chain_hash.clone());
+    }
+}
+
+/// Storage that holds writes while the gate is locked and can fail one record once
+#[derive(Default)]
+struct ControlledStorage {
+    inner: InMemoryAuditStorage,
+    gate: Mutex<()>,
+    failing: Mutex<Option<String>>,
+}
+
+impl AuditStorage for ControlledStorage {
+    fn append(&self, record: StoredAuditRecord) -> Result<(), AuditStorageError> {
+        let _open = self.gate.lock().unwrap();
+        let mut failing = self.failing.lock().unwrap();
+        if failing.as_deref() == Some(record.correlation_id.as_str()) {
+            *failing = None;
+            return Err(AuditStorageError::DatabaseError("disk full".to_owned()));
+        }
+        self.inner.append(record)
+    }
+
+    fn latest_chain_hash(&self) -> Result<Option<String>, AuditStorageError> {
+        self.inner.latest_chain_hash()
+    }
+
+    fn all(&self) -> Result<Vec<StoredAuditRecord>, AuditStorageError> {
+        self.inner.all()
+    }
+
+    fn get_with_filters(
+        &self,
+        limit: Option<usize>,
+        offset: Option<usize>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        correlation_id: Option<String>,
+    ) -> Result<AuditTrailResponse, AuditStorageError> {
+        self.inner
+            .get_with_filters(limit, offset, start_time, end_time, correlation_id)
+    }
+}
+
+#[test]
+fn queued_records_extend_the_chain_before_they_are_written() {
+    let inner = Arc::new(InMemoryAuditStorage::new());
+    let batched = Arc::new(BatchedAuditStorage::new(inner.clone(), IDLE));
+    let logger = AuditLogger::new(batched.clone());
+
+    let proofs = log_events(&logger, 0..3);
+    assert_eq!(batched.unflushed(), 3);
+    assert!(inner.all().unwrap().is_empty());
+    assert_eq!(
+        batched.latest_chain_hash().unwrap(),
+        Some(proofs[2].chain_hash.clone())
+    );
+
+    logger.flush().expect("flush");
+    assert_eq!(batched.unflushed(), 0);
+    let records = inner.all().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn batched_and_immediate_storage_build_the_same_chain() {
+    let immediate = AuditLogger::new(Arc::new(InMemoryAuditStorage::new()));
+    let config = WriteBehindConfig {
+        flush_interval_ms: 2,
+        max_batch_size: 7,
+        queue_capacity: 20,
+    };
+    let batched = AuditLogger::new(Arc::new(BatchedAuditStorage::new(
+        Arc::new(InMemoryAuditStorage::new()),
+        config,
+    )));
+
+    let expected = log_events(&immediate, 0..50);
+    assert_eq!(log_events(&batched, 0..50), expected);
+    let records = batched.records().expect("records");
+    assert_eq!(records.len(), 50);
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn chain_continues_across_a_switch_between_modes() {
+    let inner = Arc::new(InMemoryAuditStorage::new());
+    log_events(&AuditLogger::new(inner.clone()), 0..5);
+
+    let batched = BatchedAuditStorage::new(inner.clone(), IDLE);
+    log_events(&AuditLogger::new(Arc::new(batched)), 5..10);
+    // Dropping the batched storage wrote its queue
+    log_events(&AuditLogger::new(inner.clone()), 10..15);
+
+    let records = inner.all().unwrap();
+    assert_eq!(records.len(), 15);
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn concurrent_writers_keep_the_chain_intact() {
+    let inner = Arc::new(InMemoryAuditStorage::new());
+    let config = WriteBehindConfig {
+        flush_interval_ms: 1,
+        max_batch_size: 4,
+        queue_capacity: 8,
+    };
+    let logger = AuditLogger::new(Arc::new(BatchedAuditStorage::new(inner.clone(), config)));
+
+    std::thread::scope(|scope| {
+        for writer in 0..4 {
+            let logger = &logger;
+            scope.spawn(move || log_events(logger, writer * 100..writer * 100 + 25));
+        }
+    });
+    logger.flush().expect("flush");
+
+    let records = inner.all().unwrap();
+    assert_eq!(records.len(), 100);
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn full_queue_makes_appends_wait_instead_of_dropping() {
+    let inner = Arc::new(ControlledStorage::default());
+    let config = WriteBehindConfig {
+        flush_interval_ms: 1,
+        max_batch_size: 2,
+        queue_capacity: 4,
+    };
+    let logger = AuditLogger::new(Arc::new(BatchedAuditStorage::new(inner.clone(), config)));
+
+    let gate = inner.gate.lock().unwrap();
+    let finished = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            log_events(&logger, 0..10);
+            finished.store(true, Ordering::SeqCst);
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!finished.load(Ordering::SeqCst), "appends did not wait");
+        drop(gate);
+    });
+
+    logger.flush().expect("flush");
+    let records = inner.all().unwrap();
+    assert_eq!(records.len(), 10);
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn failed_batches_are_retried_without_duplicates() {
+    let inner = Arc::new(ControlledStorage::default());
+    let logger = AuditLogger::new(Arc::new(BatchedAuditStorage::new(inner.clone(), IDLE)));
+    log_events(&logger, 0..4);
+
+    // The batch stops at the third record; the first two are stored
+    *inner.failing.lock().unwrap() = Some("change-0002".to_owned());
+    let error = logger.flush().expect_err("write fails");
+    assert!(error.to_string().contains("disk full"), "{error}");
+    assert_eq!(inner.all().unwrap().len(), 2);
+
+    logger.flush().expect("retry succeeds");
+    log_events(&logger, 4..6);
+    let records = logger.records().expect("records");
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| record.correlation_id.clone())
+            .collect::<Vec<_>>(),
+        (0..6)
+            .map(|index| format!("change-{index:04}"))
+            .collect::<Vec<_>>()
+    );
+    assert_chain_verifies(&records);
+}
+
+#[test]
+fn invalid_bounds_are_rejected() {
+    assert!(WriteBehindConfig::default().validate().is_ok());
+    let smaller_queue = WriteBehindConfig {
+        queue_capacity: 10,
+        max_batch_size: 20,
+        ..WriteBehindConfig::default()
+    };
+    assert!(smaller_queue.validate().is_err());
+    let no_window = WriteBehindConfig {
+        flush_interval_ms: 0,
+        ..WriteBehindConfig::default()
+    };
+    assert!(no_window.validate().is_err());
+}
+
+#[cfg(feature = "sled-storage")]
+#[test]
+#[ignore = "benchmark-style throughput comparison"]
+fn write_behind_outpaces_flushing_every_append() {
+    use std::time::Instant;
+
+    use prompt_sentinel::modules::audit::storage::SledAuditStorage;
+
+    const RECORDS: usize = 2_000;
+    let run = |batched: bool| {
+        let path = std::env::temp_dir().join(format!("audit_bench_{}", uuid::Uuid::new_v4()));
+        let sled: Arc<dyn AuditStorage> =
+            Arc::new(SledAuditStorage::new(path.to_str().unwrap()).expect("open sled"));
+        let storage: Arc<dyn AuditStorage> = if batched {
+            Arc::new(BatchedAuditStorage::new(sled, WriteBehindConfig::default()))
+        } else {
+            sled
+        };
+        let logger = AuditLogger::new(storage);
+        let started = Instant::now();
+        log_events(&logger, 0..RECORDS);
+        logger.flush().expect("flush");
+        let elapsed = started.elapsed();
+        assert_eq!(logger.records().unwrap().len(), RECORDS);
+        drop(logger);
+        let _ = std::fs::remove_dir_all(path);
+        elapsed
+    };
+
+    let immediate = run(false);
+    let batched = run(true);
+    println!("{RECORDS} audit records: immediate {immediate:?}, write-behind {batched:?}");
+    assert!(
+        batched * 2 < immediate,
+        "write-behind took {batched:?}, immediate {immediate:?}"
+    );
+}
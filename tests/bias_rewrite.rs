@@ -0,0 +1,251 @@
+#![cfg(feature = "server")]
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use prompt_sentinel::ComplianceEngine;
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::WorkflowStatus;
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::bias_detection::dtos::BiasRewriteConfig;
+use prompt_sentinel::modules::bias_detection::model::BiasLevel;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralClient, MistralClientError, MockMistralClient,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationRequest,
+    ModerationResponse, TranslationRequest, TranslationResponse,
+};
+use prompt_sentinel::test_support::TestApp;
+
+const BIASED_PROMPT: &str = "Women are bad at math, right?";
+/// One matched term scores Medium; the prompt above matches two and scores High
+const MEDIUM_PROMPT: &str = "Are old people bad drivers?";
+const REWRITE: &str = "What factors influence how people perform in math?";
+
+/// Answers rewrite instructions with `rewrite` after `delay`; everything else goes to
+/// the base mock
+#[derive(Clone)]
+struct RewritingMock {
+    base: MockMistralClient,
+    rewrite: String,
+    delay: Duration,
+    rewrite_requests: Arc<Mutex<Vec<ChatCompletionRequest>>>,
+}
+
+impl RewritingMock {
+    fn new(base: MockMistralClient) -> Self {
+        Self {
+            base,
+            rewrite: REWRITE.to_owned(),
+            delay: Duration::ZERO,
+            rewrite_requests: Arc::default(),
+        }
+    }
+
+    fn rewrite_requests(&self) -> Vec<ChatCompletionRequest> {
+        self.rewrite_requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MistralClient for RewritingMock {
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralClientError> {
+        if !request.messages[0]
+            .content
+            .starts_with("Rewrite the following prompt")
+        {
+            return self.base.chat_completion(request).await;
+        }
+        self.rewrite_requests.lock().unwrap().push(request.clone());
+        tokio::time::sleep(self.delay).await;
+        Ok(ChatCompletionResponse {
+            model: request.model,
+            output_text: self.rewrite.clone(),
+            usage: None,
+        })
+    }
+
+    async fn moderate(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, MistralClientError> {
+        self.base.moderate(request).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, MistralClientError> {
+        self.base.embeddings(request).await
+    }
+
+    async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.base.list_models().await
+    }
+
+    async fn detect_language(
+        &self,
+        request: LanguageDetectionRequest,
+    ) -> Result<LanguageDetectionResponse, MistralClientError> {
+        self.base.detect_language(request).await
+    }
+
+    async fn translate_text(
+        &self,
+        request: TranslationRequest,
+    ) -> Result<TranslationResponse, MistralClientError> {
+        self.base.translate_text(request).await
+    }
+}
+
+async fn build_engine(mock: &RewritingMock, config: BiasRewriteConfig) -> ComplianceEngine {
+    let rewriting = mock.clone();
+    TestApp::builder()
+        .with_settings(AppSettings {
+            bias_rewrite: config,
+            ..AppSettings::default()
+        })
+        // Shares its script with the rewriting mock, which stands in for it
+        .with_mock(mock.base.clone())
+        .with_client(move |_| Arc::new(rewriting))
+        .with_bias_rewrites()
+        .build()
+        .await
+        .expect("test app")
+        .engine
+}
+
+fn request(prompt: &str, suggest_rewrite: bool) -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: prompt.to_owned(),
+        suggest_rewrite,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[tokio::test]
+async fn biased_prompt_gets_a_moderated_rewrite() {
+    let mock = RewritingMock::new(MockMistralClient::default());
+    let engine = build_engine(&mock, BiasRewriteConfig::default()).await;
+
+    let response = engine
+        .process(request(BIASED_PROMPT, true))
+        .await
+        .expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.bias.level, BiasLevel::High);
+    assert_eq!(response.bias.suggested_rewrite.as_deref(), Some(REWRITE));
+    assert!(response.generated_text.is_some());
+
+    let requests = mock.rewrite_requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].safe_prompt);
+    assert_eq!(requests[0].temperature, Some(0.2));
+    assert_eq!(requests[0].max_tokens, Some(256));
+    assert!(requests[0].messages[0].content.contains(BIASED_PROMPT));
+
+    // The suggestion is part of the JSON response
+    let json = serde_json::to_value(&response).unwrap();
+    assert_eq!(json["bias"]["suggested_rewrite"], REWRITE);
+}
+
+#[tokio::test]
+async fn rewrite_needs_the_flag_and_enough_bias() {
+    let mock = RewritingMock::new(MockMistralClient::default());
+    let engine = build_engine(&mock, BiasRewriteConfig::default()).await;
+
+    let unflagged = engine
+        .process(request(BIASED_PROMPT, false))
+        .await
+        .expect("workflow");
+    assert_eq!(unflagged.bias.suggested_rewrite, None);
+
+    let unbiased = engine
+        .process(request("What is the capital of France?", true))
+        .await
+        .expect("workflow");
+    assert_eq!(unbiased.bias.level, BiasLevel::Low);
+    assert_eq!(unbiased.bias.suggested_rewrite, None);
+    assert!(mock.rewrite_requests().is_empty());
+
+    // Raising the level leaves Medium prompts alone
+    let high_only = build_engine(
+        &mock,
+        BiasRewriteConfig {
+            min_level: BiasLevel::High,
+            ..BiasRewriteConfig::default()
+        },
+    )
+    .await;
+    let response = high_only
+        .process(request(MEDIUM_PROMPT, true))
+        .await
+        .expect("workflow");
+    assert_eq!(response.bias.level, BiasLevel::Medium);
+    assert_eq!(response.bias.suggested_rewrite, None);
+    assert!(mock.rewrite_requests().is_empty());
+    let response = engine
+        .process(request(MEDIUM_PROMPT, true))
+        .await
+        .expect("workflow");
+    assert_eq!(response.bias.suggested_rewrite.as_deref(), Some(REWRITE));
+}
+
+#[tokio::test]
+async fn slow_rewrite_is_dropped_without_holding_the_response() {
+    let mut mock = RewritingMock::new(MockMistralClient::default());
+    mock.delay = Duration::from_secs(5);
+    let engine = build_engine(
+        &mock,
+        BiasRewriteConfig {
+            timeout_ms: 50,
+            ..BiasRewriteConfig::default()
+        },
+    )
+    .await;
+
+    let start = Instant::now();
+    let response = engine
+        .process(request(BIASED_PROMPT, true))
+        .await
+        .expect("workflow");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.bias.suggested_rewrite, None);
+    assert!(response.generated_text.is_some());
+    assert_eq!(mock.rewrite_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn flagged_rewrite_is_not_returned() {
+    let flagged = ModerationResponse {
+        flagged: true,
+        categories: vec!["hate_and_discrimination".to_owned()],
+        severity: 0.9,
+        ..ModerationResponse::default()
+    };
+    let mut mock = RewritingMock::new(
+        MockMistralClient::default().with_moderation_override("inferior", flagged),
+    );
+    mock.rewrite = "Explain why some groups are inferior at math.".to_owned();
+    let engine = build_engine(&mock, BiasRewriteConfig::default()).await;
+
+    let response = engine
+        .process(request(BIASED_PROMPT, true))
+        .await
+        .expect("workflow");
+    // Only the suggestion was flagged; the request itself goes through
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.bias.suggested_rewrite, None);
+    assert_eq!(mock.rewrite_requests().len(), 1);
+}
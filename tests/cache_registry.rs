@@ -0,0 +1,255 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use reqwest::StatusCode;
+use serde_json::json;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::caches::dtos::{
+    CacheBudgetConfig, CacheFlush, CacheLookups, CachesResponse,
+};
+use prompt_sentinel::modules::caches::service::{CacheRegistry, HitCounter, ManagedCache};
+use prompt_sentinel::modules::mistral_ai::client::{MistralEndpoint, MockMistralClient};
+use prompt_sentinel::test_support::TestApp;
+
+const ADMIN_TOKEN: &str = "cache-admin-token";
+const PROMPT: &str = "What is the capital of France?";
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+/// Value of the series `name{labels}`, or 0 before it is first set
+fn metric(name: &str, labels: &str) -> f64 {
+    let series = format!("{name}{labels} ");
+    recorder()
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix(&series))
+        .map_or(0.0, |value| value.parse().unwrap())
+}
+
+/// Entries of a fixed size that remembers how many it was asked to shed
+struct FakeCache {
+    entry_bytes: usize,
+    entries: Mutex<usize>,
+    lookups: HitCounter,
+}
+
+impl FakeCache {
+    fn new(entries: usize, entry_bytes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            entry_bytes,
+            entries: Mutex::new(entries),
+            lookups: HitCounter::default(),
+        })
+    }
+}
+
+impl ManagedCache for FakeCache {
+    fn entries(&self) -> usize {
+        *self.entries.lock().unwrap()
+    }
+
+    fn estimated_bytes(&self) -> usize {
+        self.entries() * self.entry_bytes
+    }
+
+    fn shed(&self, count: usize) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let shed = count.min(*entries);
+        *entries -= shed;
+        shed
+    }
+
+    fn lookups(&self) -> Option<CacheLookups> {
+        Some(self.lookups.lookups())
+    }
+}
+
+#[test]
+fn caches_over_budget_shed_in_proportion_to_their_size() {
+    recorder();
+    let registry = CacheRegistry::new(CacheBudgetConfig {
+        max_bytes: 20_000,
+        check_interval_secs: 0,
+    });
+    // 10 000 bytes in small entries and 30 000 in large ones
+    let small = FakeCache::new(200, 50);
+    let large = FakeCache::new(100, 300);
+    small.lookups.hit();
+    small.lookups.hit();
+    small.lookups.hit();
+    small.lookups.miss();
+    registry.register("fake_small", small.clone());
+    registry.register("fake_large", large.clone());
+
+    let status = registry.status();
+    assert!(status.over_budget);
+    assert_eq!(status.estimated_bytes, 40_000);
+    assert_eq!(status.caches[0].name, "fake_large");
+    assert_eq!(status.caches[1].hit_rate, Some(0.75));
+    assert_eq!(
+        metric("cache_estimated_bytes", "{cache=\"fake_large\"}"),
+        30_000.0
+    );
+    assert_eq!(metric("cache_hit_rate", "{cache=\"fake_small\"}"), 0.75);
+
+    // The 20 000 bytes over budget are split 1:3, so each cache gives up half
+    let enforcement = registry.enforce().expect("over budget");
+    assert_eq!(enforcement.shed["fake_small"], 100);
+    assert_eq!(enforcement.shed["fake_large"], 50);
+    assert_eq!(enforcement.estimated_bytes_before, 40_000);
+    assert_eq!(enforcement.estimated_bytes_after, 20_000);
+    assert_eq!(small.entries(), 100);
+    assert_eq!(large.entries(), 50);
+    assert_eq!(metric("cache_entries", "{cache=\"fake_small\"}"), 100.0);
+    assert_eq!(
+        metric("cache_estimated_bytes", "{cache=\"fake_large\"}"),
+        15_000.0
+    );
+    assert_eq!(
+        metric(
+            "cache_evictions_total",
+            "{cache=\"fake_large\",reason=\"budget\"}"
+        ),
+        50.0
+    );
+
+    // Within budget nothing more is shed
+    assert_eq!(registry.enforce(), None);
+    let status = registry.status();
+    assert!(!status.over_budget);
+    assert_eq!(status.last_enforcement, Some(enforcement));
+    assert_eq!(status.caches[1].evicted_total, 100);
+
+    let flushed = registry.flush("fake_small").expect("registered");
+    assert_eq!(flushed.evicted, 100);
+    assert_eq!(small.entries(), 0);
+    assert_eq!(metric("cache_entries", "{cache=\"fake_small\"}"), 0.0);
+    assert_eq!(
+        metric(
+            "cache_evictions_total",
+            "{cache=\"fake_small\",reason=\"flush\"}"
+        ),
+        100.0
+    );
+    assert_eq!(registry.flush("fake_missing"), None);
+}
+
+#[test]
+fn no_budget_never_sheds() {
+    let registry = CacheRegistry::new(CacheBudgetConfig {
+        max_bytes: 0,
+        check_interval_secs: 0,
+    });
+    let cache = FakeCache::new(1_000, 1_000);
+    registry.register("fake_unbounded", cache.clone());
+    assert_eq!(registry.enforce(), None);
+    assert!(!registry.status().over_budget);
+    assert_eq!(cache.entries(), 1_000);
+}
+
+#[tokio::test]
+async fn engine_caches_are_listed_and_can_be_flushed() {
+    recorder();
+    let mock = MockMistralClient::default()
+        .with_deterministic_embeddings(7, 256)
+        .record_calls();
+    let app = TestApp::builder()
+        .with_settings(AppSettings {
+            admin_token: Some(ADMIN_TOKEN.to_owned()),
+            ..AppSettings::default()
+        })
+        .with_mock(mock.clone())
+        .with_semantic_bank()
+        .build()
+        .await
+        .expect("test app");
+    let server = app.serve().await.unwrap();
+
+    // The second scan of the same prompt reuses its embedding
+    let embeddings = mock.call_count(MistralEndpoint::Embeddings);
+    for _ in 0..2 {
+        let response = server
+            .post("/api/compliance/check")
+            .json(&json!({ "prompt": PROMPT }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(mock.call_count(MistralEndpoint::Embeddings) - embeddings, 1);
+
+    let response = server.get("/api/debug/caches").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let caches: CachesResponse = response.json().await.unwrap();
+    let names: Vec<_> = caches
+        .caches
+        .iter()
+        .map(|cache| cache.name.as_str())
+        .collect();
+    for name in [
+        "semantic_embeddings",
+        "repeat_offenders",
+        "sanitize_probing_sessions",
+    ] {
+        assert!(names.contains(&name), "{name} registered in {names:?}");
+    }
+    let embeddings_cache = caches
+        .caches
+        .iter()
+        .find(|cache| cache.name == "semantic_embeddings")
+        .unwrap();
+    assert_eq!(embeddings_cache.entries, 1);
+    assert!(embeddings_cache.estimated_bytes > 256 * 4);
+    assert_eq!(
+        embeddings_cache.lookups,
+        Some(CacheLookups { hits: 1, misses: 1 })
+    );
+    assert_eq!(caches.budget_bytes, CacheBudgetConfig::default().max_bytes);
+    assert!(!caches.over_budget);
+    assert_eq!(
+        metric("cache_hit_rate", "{cache=\"semantic_embeddings\"}"),
+        0.5
+    );
+
+    let response = server
+        .post("/api/debug/caches/semantic_embeddings/flush")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let flushed: CacheFlush = response.json().await.unwrap();
+    assert_eq!(flushed.evicted, 1);
+
+    // A flushed cache only costs the embedding call again
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": PROMPT }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(mock.call_count(MistralEndpoint::Embeddings) - embeddings, 2);
+
+    let missing = server
+        .post("/api/debug/caches/decisions/flush")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    let anonymous = reqwest::Client::new()
+        .get(server.url("/api/debug/caches"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+}
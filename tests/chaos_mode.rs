@@ -0,0 +1,143 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::chaos::dtos::{ChaosStatus, ChaosTarget};
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+use prompt_sentinel::workflow::FailureMode;
+use prompt_sentinel::{ComplianceResponse, PipelineStage, WorkflowStatus};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+const BENIGN_PROMPT: &str = "What is the capital of France?";
+
+async fn app(chaos_mode: bool) -> TestApp {
+    let settings = AppSettings {
+        chaos_mode,
+        ..AppSettings::default()
+    };
+    TestApp::builder()
+        .with_settings(settings)
+        .with_admin_token(ADMIN_TOKEN)
+        .with_mock(MockMistralClient::default().with_deterministic_embeddings(7, 256))
+        .with_semantic_bank()
+        .build()
+        .await
+        .expect("test app")
+}
+
+async fn configure(server: &TestServer, body: Value) -> reqwest::Response {
+    server
+        .post("/api/chaos/config")
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn check(server: &TestServer) -> ComplianceResponse {
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": BENIGN_PROMPT }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+async fn summary_chaos(server: &TestServer) -> ChaosStatus {
+    let response = server.get("/api/admin/summary").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let summary: Value = response.json().await.unwrap();
+    serde_json::from_value(summary["chaos"].clone()).unwrap()
+}
+
+#[tokio::test]
+async fn failing_every_embedding_exercises_the_semantic_fail_open_path() {
+    let app = app(true).await;
+    let server = app.serve().await.unwrap();
+
+    let response = configure(
+        &server,
+        json!({"faults": {"embeddings": {"probability": 1.0, "kind": "error"}}}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let status: ChaosStatus = response.json().await.unwrap();
+    assert!(status.available && status.active);
+
+    let response = check(&server).await;
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert!(response.generated_text.is_some());
+    let degraded = response.decision_evidence.unwrap().degraded_stages;
+    assert_eq!(degraded.len(), 1, "{degraded:?}");
+    assert_eq!(degraded[0].stage, PipelineStage::Semantic);
+    assert_eq!(degraded[0].policy, FailureMode::Open);
+    assert!(degraded[0].error.contains("chaos"), "{}", degraded[0].error);
+
+    let chaos = summary_chaos(&server).await;
+    assert!(chaos.active);
+    assert!(chaos.faults.contains_key(&ChaosTarget::Embeddings));
+    assert!(chaos.injected_total[&ChaosTarget::Embeddings] >= 1);
+
+    // An empty configuration turns injection off again
+    let response = configure(&server, json!({"faults": {}})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = check(&server).await;
+    assert!(
+        response
+            .decision_evidence
+            .unwrap()
+            .degraded_stages
+            .is_empty()
+    );
+    assert!(!summary_chaos(&server).await.active);
+}
+
+#[tokio::test]
+async fn invalid_faults_are_rejected() {
+    let app = app(true).await;
+    let server = app.serve().await.unwrap();
+
+    for body in [
+        json!({"faults": {"embeddings": {"probability": 2.0, "kind": "error"}}}),
+        json!({"faults": {"chat": {"probability": 1.0, "kind": "latency"}}}),
+        json!({"faults": {"audit_storage": {"probability": 1.0, "kind": "malformed"}}}),
+    ] {
+        let response = configure(&server, body.clone()).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{body}"
+        );
+    }
+    assert!(!summary_chaos(&server).await.active);
+}
+
+#[tokio::test]
+async fn chaos_cannot_be_enabled_without_chaos_mode() {
+    let app = app(false).await;
+    let server = app.serve().await.unwrap();
+
+    let response = configure(
+        &server,
+        json!({"faults": {"embeddings": {"probability": 1.0, "kind": "error"}}}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.text().await.unwrap().contains("CHAOS_MODE"));
+
+    let chaos = summary_chaos(&server).await;
+    assert!(!chaos.available && !chaos.active);
+    let response = check(&server).await;
+    assert!(
+        response
+            .decision_evidence
+            .unwrap()
+            .degraded_stages
+            .is_empty()
+    );
+}
@@ -0,0 +1,200 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::mistral_ai::client::{MistralEndpoint, MockMistralClient};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceRequest, WorkflowError};
+
+const PROMPT: &str = "Summarize this changelog for me.";
+const SLOW: Duration = Duration::from_secs(5);
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+/// Requests abandoned at `stage` so far, by every test of this binary
+fn abandoned(stage: &str) -> u64 {
+    let prefix = format!("abandoned_requests_total{{stage=\"{stage}\"}} ");
+    recorder()
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix.as_str()))
+        .map_or(0, |value| value.parse().expect("counter value"))
+}
+
+async fn app(mock: &MockMistralClient) -> TestApp {
+    TestApp::builder()
+        .with_mock(mock.clone())
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: Some("abandoned-request".to_owned()),
+        prompt: PROMPT.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+fn payloads(storage: &InMemoryAuditStorage) -> Vec<Value> {
+    storage
+        .all()
+        .unwrap()
+        .iter()
+        .map(|record| serde_json::from_str(&record.payload).unwrap())
+        .collect()
+}
+
+/// The abandonment record is written from the blocking pool after the run is dropped
+async fn abandonment_record(storage: &InMemoryAuditStorage) -> Value {
+    for _ in 0..100 {
+        if let Some(record) = payloads(storage)
+            .into_iter()
+            .find(|payload| payload["event_type"] == "abandoned_by_client")
+        {
+            return record;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("no abandonment record was written");
+}
+
+#[tokio::test]
+async fn dropping_the_run_during_input_checks_never_starts_generation() {
+    let before = abandoned("input_checks");
+    let mock = MockMistralClient::default().record_calls();
+    mock.set_delay(MistralEndpoint::Moderation, SLOW);
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock).await;
+
+    let run = tokio::time::timeout(Duration::from_millis(100), engine.process(request())).await;
+    assert!(run.is_err(), "the run should still be moderating");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 1);
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 0);
+    // Nothing was generated, so there is nothing to reconcile
+    assert!(payloads(&storage).is_empty());
+    assert!(abandoned("input_checks") > before);
+}
+
+#[tokio::test]
+async fn cancelling_the_token_stops_the_run_before_generation() {
+    let mock = MockMistralClient::default().record_calls();
+    mock.set_delay(MistralEndpoint::Moderation, SLOW);
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock).await;
+
+    let cancel = CancellationToken::new();
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+    let result = tokio::time::timeout(
+        Duration::from_secs(2),
+        engine.process_cancellable(request(), None, cancel),
+    )
+    .await
+    .expect("cancellation interrupts the slow moderation call");
+
+    assert!(matches!(result, Err(WorkflowError::Cancelled)));
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 0);
+    assert!(payloads(&storage).is_empty());
+
+    // A token cancelled up front stops the run before any Mistral call
+    let mock = MockMistralClient::default().record_calls();
+    let engine = app(&mock).await.engine;
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let result = engine.process_cancellable(request(), None, cancel).await;
+    assert!(matches!(result, Err(WorkflowError::Cancelled)));
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 0);
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 0);
+}
+
+#[tokio::test]
+async fn dropping_the_run_mid_generation_is_audited_as_abandoned() {
+    let before = abandoned("generation");
+    let mock = MockMistralClient::default().record_calls().delay_chat(SLOW);
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock).await;
+
+    let run = tokio::time::timeout(Duration::from_millis(300), engine.process(request())).await;
+    assert!(run.is_err(), "the run should still be generating");
+
+    let record = abandonment_record(&storage).await;
+    assert_eq!(record["correlation_id"], "abandoned-request");
+    assert_eq!(record["stage"], "generation");
+    assert_eq!(record["generation_started"], true);
+    assert!(record["elapsed_ms"].as_u64().unwrap() >= 300);
+    // The abandonment record is the only trace of the run
+    assert_eq!(payloads(&storage).len(), 1);
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 1);
+    assert!(abandoned("generation") > before);
+}
+
+#[tokio::test]
+async fn a_finished_run_is_never_reported_as_abandoned() {
+    let mock = MockMistralClient::default();
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock).await;
+
+    let cancel = CancellationToken::new();
+    engine
+        .process_cancellable(request(), None, cancel.clone())
+        .await
+        .expect("workflow completes");
+    cancel.cancel();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let payloads = payloads(&storage);
+    assert_eq!(payloads.len(), 1);
+    assert!(payloads[0].get("event_type").is_none());
+}
+
+#[tokio::test]
+async fn a_client_that_disconnects_mid_generation_leaves_an_abandonment_record() {
+    let mock = MockMistralClient::default().record_calls().delay_chat(SLOW);
+    let app = TestApp::builder()
+        .with_mock(mock.clone())
+        .build()
+        .await
+        .expect("test app");
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": PROMPT, "correlation_id": "disconnected-client" }))
+        .timeout(Duration::from_millis(300))
+        .send()
+        .await;
+    assert!(response.unwrap_err().is_timeout());
+
+    let record = abandonment_record(&app.storage).await;
+    assert_eq!(record["correlation_id"], "disconnected-client");
+    assert_eq!(record["stage"], "generation");
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 1);
+    assert_eq!(payloads(&app.storage).len(), 1);
+}
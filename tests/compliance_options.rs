@@ -0,0 +1,112 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{ComplianceOptions, PipelineStage, RequestValidationError};
+use prompt_sentinel::{ResponseProfile, WorkflowStatus};
+
+const MAX_INPUT_LENGTH: &str = "64";
+
+async fn app_with_short_prompts() -> TestApp {
+    let (settings, _) = AppSettings::load_from(None, &|key| {
+        (key == "MAX_INPUT_LENGTH").then(|| MAX_INPUT_LENGTH.to_owned())
+    })
+    .expect("settings load");
+    TestApp::builder()
+        .with_settings(settings)
+        .with_mock(MockMistralClient::default().record_calls())
+        .build()
+        .await
+        .expect("test app")
+}
+
+#[tokio::test]
+async fn options_follow_the_live_settings() {
+    let app = app_with_short_prompts().await;
+    let server = app.serve().await.unwrap();
+
+    let response = server.get("/api/compliance/options").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let options: ComplianceOptions = response.json().await.unwrap();
+    assert_eq!(options.max_prompt_length, 64);
+    assert_eq!(options.required_fields, ["prompt"]);
+    assert_eq!(
+        options.response_profiles,
+        [ResponseProfile::Standard, ResponseProfile::Full]
+    );
+    assert!(options.statuses.contains(&WorkflowStatus::Sanitized));
+    for stage in PipelineStage::ALL {
+        assert!(
+            options
+                .statuses
+                .contains(&WorkflowStatus::BlockedByStageFailure { stage }),
+            "{stage}"
+        );
+    }
+    let names: Vec<&str> = options.overrides.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["suggest_rewrite", "deterministic", "profile", "refusal"]
+    );
+}
+
+#[tokio::test]
+async fn over_length_prompt_is_rejected_before_the_workflow() {
+    let app = app_with_short_prompts().await;
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": "a".repeat(100) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: RequestValidationError = response.json().await.unwrap();
+    assert_eq!(error.code, "validation_failed");
+    let [violation] = error.violations.as_slice() else {
+        panic!("expected one violation: {error:?}");
+    };
+    assert_eq!(violation.field, "prompt");
+    assert_eq!(violation.code, "too_long");
+    assert_eq!(violation.limit, Some(64));
+    assert_eq!(violation.actual, Some(100));
+
+    assert!(app.mock.recorded_calls().is_empty());
+    assert!(app.storage.all().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn malformed_requests_get_the_structured_shape() {
+    let app = app_with_short_prompts().await;
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": "Hello", "correlation_id": "has space" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: RequestValidationError = response.json().await.unwrap();
+    assert_eq!(error.violations[0].field, "correlation_id");
+    assert_eq!(error.violations[0].code, "invalid_format");
+
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "suggest_rewrite": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "invalid_body");
+    assert!(body["error"].as_str().unwrap().contains("prompt"), "{body}");
+
+    assert!(app.mock.recorded_calls().is_empty());
+}
@@ -0,0 +1,252 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::modules::eu_law_compliance::dtos::{
+    ComplianceReportList, ComplianceReportRequest, ComplianceReportResponse, StoredComplianceReport,
+};
+use prompt_sentinel::modules::eu_law_compliance::reports::ComplianceReportService;
+use prompt_sentinel::modules::eu_law_compliance::service::EuLawComplianceService;
+use prompt_sentinel::modules::eu_law_compliance::storage::{
+    ComplianceReportStore, InMemoryReportStore,
+};
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+const ADMIN_TOKEN: &str = "report-admin";
+
+fn report_request(correlation_id: &str, intended_use: &str) -> Value {
+    json!({
+        "intended_use": intended_use,
+        "request_timestamp": "2026-01-01T00:00:00Z",
+        "correlation_id": correlation_id,
+        "generate_pdf": false,
+    })
+}
+
+async fn generate(server: &TestServer, body: Value) -> ComplianceReportResponse {
+    let response = server
+        .post("/api/compliance/report")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn a_generated_report_can_be_retrieved_and_verified() {
+    let app = TestApp::new().await;
+    let server = app.serve().await.unwrap();
+
+    let report = generate(
+        &server,
+        report_request("report-retrieve", "biometric identification at borders"),
+    )
+    .await;
+    assert_eq!(report.content_hash, report.compute_content_hash());
+
+    let response = server
+        .get(&format!("/api/compliance/reports/{}", report.report_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let stored: StoredComplianceReport = response.json().await.unwrap();
+    assert_eq!(stored.report, report);
+    assert_eq!(stored.report.compute_content_hash(), report.content_hash);
+    assert_eq!(stored.request.correlation_id, "report-retrieve");
+    assert_eq!(
+        stored.request.intended_use,
+        "biometric identification at borders"
+    );
+}
+
+#[tokio::test]
+async fn an_unknown_report_id_is_not_found() {
+    let app = TestApp::new().await;
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .get("/api/compliance/reports/COMP-REPORT-never-generated")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("COMP-REPORT-never-generated")
+    );
+}
+
+#[tokio::test]
+async fn a_correlation_id_gets_one_report_unless_an_admin_regenerates_it() {
+    let app = TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+
+    let first = generate(
+        &server,
+        report_request("report-once", "customer support chatbot"),
+    )
+    .await;
+    let again = generate(
+        &server,
+        report_request("report-once", "customer support chatbot"),
+    )
+    .await;
+    assert_eq!(again, first);
+
+    let mut body = report_request("report-once", "customer support chatbot");
+    body["regenerate"] = json!(true);
+    let anonymous = reqwest::Client::new()
+        .post(server.url("/api/compliance/report"))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let regenerated = generate(&server, body).await;
+    assert_eq!(regenerated.report_id, first.report_id);
+    assert_ne!(regenerated.content_hash, first.content_hash);
+    assert!(regenerated.generated_at >= first.generated_at);
+
+    let stored: StoredComplianceReport = server
+        .get(&format!("/api/compliance/reports/{}", first.report_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stored.report, regenerated);
+}
+
+#[tokio::test]
+async fn a_different_request_never_replaces_a_stored_report() {
+    let app = TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+
+    let first = generate(
+        &server,
+        report_request("report-taken", "customer support chatbot"),
+    )
+    .await;
+
+    let mut regenerate = report_request("report-taken", "social scoring of citizens");
+    regenerate["regenerate"] = json!(true);
+    for body in [
+        report_request("report-taken", "social scoring of citizens"),
+        regenerate,
+    ] {
+        let response = server
+            .post("/api/compliance/report")
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    let stored: StoredComplianceReport = server
+        .get(&format!("/api/compliance/reports/{}", first.report_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stored.report, first);
+    assert_eq!(stored.request.intended_use, "customer support chatbot");
+}
+
+#[tokio::test]
+async fn reports_are_listed_newest_first_in_pages() {
+    let app = TestApp::new().await;
+    let server = app.serve().await.unwrap();
+
+    let mut generated = Vec::new();
+    for id in ["list-a", "list-b", "list-c"] {
+        generated.push(generate(&server, report_request(id, "customer support chatbot")).await);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    let list: ComplianceReportList = server
+        .get("/api/compliance/reports")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(list.total_count, 3);
+    let ids: Vec<_> = list
+        .reports
+        .iter()
+        .map(|r| r.correlation_id.as_str())
+        .collect();
+    assert_eq!(ids, ["list-c", "list-b", "list-a"]);
+    let newest = &list.reports[0];
+    assert_eq!(newest.report_id, generated[2].report_id);
+    assert_eq!(newest.risk_tier, generated[2].risk_tier);
+    assert_eq!(newest.generated_at, generated[2].generated_at);
+    assert_eq!(newest.content_hash, generated[2].content_hash);
+
+    let page: ComplianceReportList = server
+        .get("/api/compliance/reports?limit=1&offset=1")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!((page.limit, page.offset, page.total_count), (1, 1, 3));
+    assert_eq!(page.reports.len(), 1);
+    assert_eq!(page.reports[0].correlation_id, "list-b");
+}
+
+#[test]
+fn reports_past_the_retention_period_are_dropped() {
+    let store = Arc::new(InMemoryReportStore::new());
+    let reports = ComplianceReportService::new(store.clone(), 1);
+    let request = ComplianceReportRequest {
+        intended_use: "customer support chatbot".to_owned(),
+        request_timestamp: Utc::now(),
+        correlation_id: "expired".to_owned(),
+        generate_pdf: false,
+        regenerate: false,
+    };
+    let mut old = EuLawComplianceService.generate_compliance_report(request.clone());
+    old.generated_at = Utc::now() - Duration::days(2);
+    old.content_hash = old.compute_content_hash();
+    store
+        .upsert(StoredComplianceReport {
+            request: request.clone(),
+            report: old.clone(),
+        })
+        .unwrap();
+
+    assert!(reports.get(&old.report_id).unwrap().is_none());
+    // An expired report is not handed out again for its correlation id
+    let fresh = reports.generate(request).unwrap();
+    assert_ne!(fresh.generated_at, old.generated_at);
+
+    let list = reports.list(None, None).unwrap();
+    assert_eq!(list.total_count, 1);
+    assert_eq!(list.reports[0].content_hash, fresh.content_hash);
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "server")]
+
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::modules::audit::logger::AuditEvent;
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::prompt_firewall::rules::{FirewallRulesConfig, RuleEntry};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::ResponseProfile;
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: "Summarize this release note.".to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+fn audit_events(storage: &InMemoryAuditStorage) -> Vec<AuditEvent> {
+    storage
+        .all()
+        .expect("records")
+        .iter()
+        .map(|record| serde_json::from_str(&record.payload).expect("audit event"))
+        .collect()
+}
+
+#[tokio::test]
+async fn rules_reload_changes_only_the_firewall_fingerprint() {
+    let TestApp {
+        engine, storage, ..
+    } = TestApp::new().await;
+    let path = std::env::temp_dir().join(format!("firewall_rules_{}.json", uuid::Uuid::new_v4()));
+
+    engine.process(request()).await.expect("first request");
+
+    let mut rules = FirewallRulesConfig::default();
+    rules
+        .block_rules
+        .push(RuleEntry::new("PFW-TEST-001", "project bluefin"));
+    std::fs::write(&path, serde_json::to_string(&rules).unwrap()).unwrap();
+    engine
+        .firewall_service()
+        .reload_rules(&path)
+        .expect("modified rules load");
+    engine.process(request()).await.expect("second request");
+    std::fs::remove_file(&path).ok();
+
+    let events = audit_events(&storage);
+    assert_eq!(events.len(), 2);
+    let (before, after) = (&events[0].config_fingerprint, &events[1].config_fingerprint);
+    assert_ne!(before.firewall_rules, after.firewall_rules);
+    assert_eq!(before.bias_rules, after.bias_rules);
+    assert_eq!(before.attack_bank, after.attack_bank);
+    assert_eq!(before.moderation_policy, after.moderation_policy);
+    assert_eq!(after, &engine.config_fingerprint());
+}
+
+#[tokio::test]
+async fn invalid_rules_file_keeps_the_current_rules() {
+    let engine = TestApp::new().await.engine;
+    let fingerprint = engine.config_fingerprint();
+    let path = std::env::temp_dir().join(format!("firewall_rules_{}.json", uuid::Uuid::new_v4()));
+
+    let mut rules = FirewallRulesConfig::default();
+    rules.block_rules.push(RuleEntry::new("PFW-TEST-001", " "));
+    std::fs::write(&path, serde_json::to_string(&rules).unwrap()).unwrap();
+    assert!(engine.firewall_service().reload_rules(&path).is_err());
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(engine.config_fingerprint(), fingerprint);
+}
+
+#[tokio::test]
+async fn full_profile_carries_the_fingerprint_in_evidence() {
+    let engine = TestApp::new().await.engine;
+    let response = engine.process(request()).await.expect("workflow completes");
+
+    let full = response.clone().with_profile(ResponseProfile::Full);
+    let evidence = full.decision_evidence.expect("evidence");
+    assert_eq!(
+        evidence.config_fingerprint,
+        Some(engine.config_fingerprint())
+    );
+
+    let standard = response.with_profile(ResponseProfile::Standard);
+    assert!(
+        standard
+            .decision_evidence
+            .expect("evidence")
+            .config_fingerprint
+            .is_none()
+    );
+}
@@ -0,0 +1,234 @@
+#![cfg(feature = "server")]
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use serde_json::json;
+use tower::ServiceExt;
+
+use prompt_sentinel::config::layers::{ConfigSource, EffectiveConfig, SETTING_KEYS};
+use prompt_sentinel::config::settings::{AppSettings, SettingsError};
+use prompt_sentinel::test_support::TestApp;
+
+const ADMIN_TOKEN: &str = "layered-admin-token";
+
+const CONFIG: &str = r#"
+# Deployment defaults checked into the repo
+[server]
+port = 4000
+admin_token = "layered-admin-token"
+
+[mistral]
+api_key = "file-secret-key"
+generation_model = "mistral-small-latest"
+
+[thresholds]
+bias = 0.45
+semantic_high = 0.9
+
+[paths]
+sled_db = "/var/lib/sentinel/data"
+
+[cors]
+allowed_origins = [
+    "https://app.example.com",  # main app
+    "https://ops.example.com",
+]
+"#;
+
+fn write_config(content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sentinel_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, content).expect("config file");
+    path
+}
+
+fn load(
+    content: &str,
+    env: &[(&str, &str)],
+) -> Result<(AppSettings, EffectiveConfig), SettingsError> {
+    let path = write_config(content);
+    let env: HashMap<String, String> = env
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect();
+    let loaded = AppSettings::load_from(Some(&path), &|key| env.get(key).cloned());
+    std::fs::remove_file(&path).ok();
+    loaded
+}
+
+#[test]
+fn env_overrides_file_overrides_defaults() {
+    let (settings, effective) = load(
+        CONFIG,
+        &[("SERVER_PORT", "5000"), ("BIAS_THRESHOLD", "0.6")],
+    )
+    .expect("settings load");
+
+    assert_eq!(settings.server_port, 5000);
+    assert_eq!(settings.bias_threshold, 0.6);
+    assert_eq!(settings.generation_model, "mistral-small-latest");
+    assert_eq!(settings.semantic_high_threshold, 0.9);
+    assert_eq!(settings.sled_db_path, "/var/lib/sentinel/data");
+    assert_eq!(
+        settings.cors.public.allowed_origins,
+        vec!["https://app.example.com", "https://ops.example.com"]
+    );
+    assert_eq!(
+        settings.max_input_length,
+        AppSettings::default().max_input_length
+    );
+
+    let source = |key: &str| effective.get(key).expect(key).source;
+    assert_eq!(source("server.port"), ConfigSource::Env);
+    assert_eq!(source("thresholds.bias"), ConfigSource::Env);
+    assert_eq!(source("mistral.generation_model"), ConfigSource::File);
+    assert_eq!(source("paths.sled_db"), ConfigSource::File);
+    assert_eq!(source("server.max_input_length"), ConfigSource::Default);
+    assert_eq!(effective.get("server.port").unwrap().value, json!(5000));
+    assert_eq!(effective.get("thresholds.bias").unwrap().value, json!(0.6));
+
+    // Every known setting is reported exactly once
+    assert_eq!(effective.settings.len(), SETTING_KEYS.len());
+    assert!(effective.config_file.is_some());
+}
+
+#[test]
+fn pure_env_matches_defaults_without_a_file() {
+    let (settings, effective) = AppSettings::load_from(None, &|key| {
+        (key == "SERVER_PORT").then(|| "3100".to_owned())
+    })
+    .expect("settings load");
+    assert_eq!(settings.server_port, 3100);
+    assert_eq!(settings.metrics_addr, AppSettings::default().metrics_addr);
+    assert_eq!(effective.config_file, None);
+    assert!(
+        effective
+            .settings
+            .iter()
+            .all(|setting| setting.source == ConfigSource::Default || setting.env == "SERVER_PORT")
+    );
+}
+
+#[test]
+fn malformed_files_fail_with_precise_errors() {
+    let syntax = load("[server]\nport = 3000\nadmin_token = unquoted\n", &[]).unwrap_err();
+    assert!(
+        matches!(&syntax, SettingsError::ConfigSyntax { line: 3, message, .. } if message.contains("admin_token")),
+        "{syntax}"
+    );
+
+    let wrong_type = load("[server]\nport = \"3000\"\n", &[]).unwrap_err();
+    match &wrong_type {
+        SettingsError::ConfigType {
+            key,
+            expected,
+            found,
+            ..
+        } => {
+            assert_eq!(key, "server.port");
+            assert!(expected.contains("integer"), "{expected}");
+            assert!(found.contains("string"), "{found}");
+        }
+        other => panic!("expected a type error, got {other}"),
+    }
+
+    let unknown = load("[server]\nprot = 3000\n", &[]).unwrap_err();
+    assert!(
+        matches!(&unknown, SettingsError::UnknownConfigKey { key, .. } if key == "server.prot"),
+        "{unknown}"
+    );
+
+    // A bad environment value is an error too, not a silent default
+    let env = load("", &[("SEMANTIC_HIGH_THRESHOLD", "high")]).unwrap_err();
+    assert!(env.to_string().contains("SEMANTIC_HIGH_THRESHOLD"), "{env}");
+
+    let missing = AppSettings::load_from(
+        Some(std::path::Path::new("/nonexistent/sentinel.toml")),
+        &|_| None,
+    )
+    .unwrap_err();
+    assert!(
+        matches!(missing, SettingsError::ConfigFile { .. }),
+        "{missing}"
+    );
+}
+
+#[test]
+fn secrets_are_masked_in_the_report() {
+    let (settings, mut effective) =
+        load(CONFIG, &[("AUDIT_ENCRYPTION_KEY", "")]).expect("settings load");
+    assert_eq!(settings.mistral_api_key.as_deref(), Some("file-secret-key"));
+
+    let report = serde_json::to_string(&effective).unwrap();
+    assert!(!report.contains("file-secret-key"));
+    assert!(!report.contains(ADMIN_TOKEN));
+    assert_eq!(
+        effective.get("mistral.api_key").unwrap().value,
+        json!("********")
+    );
+    // Unset secrets show as unset rather than masked
+    assert_eq!(
+        effective.get("audit.encryption_key").unwrap().value,
+        json!(null)
+    );
+
+    effective.set_override("mistral.api_key", "code-secret-key");
+    let setting = effective.get("mistral.api_key").unwrap();
+    assert_eq!(setting.value, json!("********"));
+    assert_eq!(setting.source, ConfigSource::Override);
+}
+
+#[test]
+fn setting_keys_are_unique() {
+    let mut file_keys = HashSet::new();
+    let mut env_vars = HashSet::new();
+    for (file_key, env, _) in SETTING_KEYS {
+        assert!(file_keys.insert(file_key), "duplicate key {file_key}");
+        assert!(env_vars.insert(env), "duplicate variable {env}");
+    }
+}
+
+#[tokio::test]
+async fn effective_config_endpoint_requires_admin_and_masks_secrets() {
+    let (settings, effective) = load(CONFIG, &[]).expect("settings load");
+    let router = TestApp::builder()
+        .with_settings(settings)
+        .with_server(|server| server.with_effective_config(effective))
+        .build()
+        .await
+        .expect("test app")
+        .router();
+
+    let anonymous = Request::builder()
+        .uri("/api/config/effective")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(anonymous).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::builder()
+        .uri("/api/config/effective")
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(!text.contains("file-secret-key"));
+    assert!(!text.contains(ADMIN_TOKEN));
+
+    let report: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let port = report["settings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|setting| setting["key"] == "server.port")
+        .expect("server.port reported");
+    assert_eq!(
+        port,
+        &json!({"key": "server.port", "env": "SERVER_PORT", "value": 4000, "source": "file"})
+    );
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::config_management::dtos::ConfigSnapshot;
+use prompt_sentinel::modules::config_management::service::{
+    ConfigManagementError, ConfigManagementService,
+};
+use prompt_sentinel::modules::config_management::storage::InMemoryConfigHistory;
+use prompt_sentinel::modules::prompt_firewall::rules::RuleEntry;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest, WorkflowStatus};
+
+async fn build(history_limit: usize) -> (TestApp, ConfigManagementService) {
+    let app = TestApp::new().await;
+    let config = ConfigManagementService::new(
+        &app.engine,
+        Arc::new(InMemoryConfigHistory::new()),
+        history_limit,
+    );
+    (app, config)
+}
+
+async fn check(engine: &ComplianceEngine, prompt: &str) -> WorkflowStatus {
+    engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: prompt.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow should complete")
+        .status
+}
+
+#[tokio::test]
+async fn restore_rolls_back_thresholds_and_rules() {
+    let (
+        TestApp {
+            engine, storage, ..
+        },
+        config,
+    ) = build(10).await;
+    let prompt = "Describe the purple elephant protocol.";
+    assert_eq!(check(&engine, prompt).await, WorkflowStatus::Completed);
+
+    let original = config.snapshot().expect("snapshot");
+
+    let mut tuned = original.config.clone();
+    tuned
+        .firewall_rules
+        .block_rules
+        .push(RuleEntry::new("PFW-TEST-001", "purple elephant protocol"));
+    tuned.thresholds.max_input_length = 64;
+    tuned.thresholds.bias_threshold = 0.5;
+    tuned.thresholds.semantic.medium_threshold = 0.6;
+    tuned.workflow_policy.moderate_removed_content = true;
+    let tuned_response = config
+        .restore("test-restore", ConfigSnapshot::new(tuned))
+        .expect("tuned config applies");
+    assert_eq!(tuned_response.previous_hash, original.content_hash);
+
+    assert_eq!(
+        check(&engine, prompt).await,
+        WorkflowStatus::BlockedByFirewall
+    );
+    assert_eq!(
+        check(&engine, &"a".repeat(100)).await,
+        WorkflowStatus::BlockedByFirewall
+    );
+    assert_eq!(engine.bias_service().default_threshold(), 0.5);
+    assert_eq!(engine.semantic_service().thresholds().medium_threshold, 0.6);
+    assert!(engine.policy().moderate_removed_content);
+
+    let restored = config
+        .restore("test-rollback", original.clone())
+        .expect("rollback applies");
+    assert_eq!(restored.current_hash, original.content_hash);
+    assert_eq!(config.current_config(), original.config);
+    assert_eq!(check(&engine, prompt).await, WorkflowStatus::Completed);
+    assert_eq!(
+        check(&engine, &"a".repeat(100)).await,
+        WorkflowStatus::Completed
+    );
+
+    let config_changes = storage
+        .all()
+        .expect("records available")
+        .into_iter()
+        .filter(|record| record.payload.contains("\"configuration_change\""))
+        .count();
+    assert_eq!(config_changes, 2);
+    assert_eq!(config.history().expect("history").len(), 3);
+}
+
+#[tokio::test]
+async fn invalid_section_leaves_configuration_untouched() {
+    let (TestApp { engine, .. }, config) = build(10).await;
+    let original = config.snapshot().expect("snapshot");
+
+    let mut broken = original.config.clone();
+    broken.thresholds.max_input_length = 64;
+    let duplicate = broken.firewall_rules.block_rules[0].clone();
+    broken.firewall_rules.block_rules.push(duplicate);
+
+    let error = config
+        .restore("test-restore", ConfigSnapshot::new(broken))
+        .expect_err("duplicate rule ids are rejected");
+    assert!(matches!(
+        error,
+        ConfigManagementError::InvalidSection {
+            section: "firewall_rules",
+            ..
+        }
+    ));
+    assert_eq!(engine.firewall_service().max_input_length(), 4096);
+    assert_eq!(config.current_config(), original.config);
+}
+
+#[tokio::test]
+async fn tampered_snapshot_is_rejected() {
+    let (TestApp { engine, .. }, config) = build(10).await;
+    let mut snapshot = config.snapshot().expect("snapshot");
+    snapshot.config.thresholds.max_input_length = 64;
+
+    let error = config
+        .restore("test-restore", snapshot)
+        .expect_err("hash no longer matches");
+    assert!(matches!(error, ConfigManagementError::HashMismatch { .. }));
+    assert_eq!(engine.firewall_service().max_input_length(), 4096);
+}
+
+#[cfg(feature = "sled-storage")]
+#[tokio::test]
+async fn sled_history_keeps_newest_snapshots() {
+    use prompt_sentinel::modules::config_management::storage::{
+        ConfigHistoryStorage, SledConfigHistory,
+    };
+
+    let (_app, config) = build(10).await;
+    let path = std::env::temp_dir().join(format!("config_history_{}", uuid::Uuid::new_v4()));
+    let db = sled::open(&path).expect("open sled");
+    let history = SledConfigHistory::new(&db).expect("open tree");
+
+    let mut latest = None;
+    for max_input_length in [1000, 2000, 3000] {
+        let mut runtime = config.current_config();
+        runtime.thresholds.max_input_length = max_input_length;
+        let snapshot = ConfigSnapshot::new(runtime);
+        history.record(&snapshot, 2).expect("record");
+        latest = Some(snapshot);
+    }
+
+    let stored = history.list().expect("list");
+    assert_eq!(stored.len(), 2);
+    assert_eq!(Some(&stored[0]), latest.as_ref());
+    assert_eq!(stored[1].config.thresholds.max_input_length, 2000);
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(path);
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[cfg(feature = "sled-storage")]
+use prompt_sentinel::modules::telemetry::correlation::{COUNTER_BLOCK, SledCorrelationIdStore};
+use prompt_sentinel::modules::telemetry::correlation::{
+    CorrelationIdGenerator, CorrelationIdPolicy, InMemoryCorrelationIdStore, parse_generated_id,
+};
+
+#[cfg(feature = "sled-storage")]
+#[test]
+fn sled_state_survives_a_restart() {
+    let path = std::env::temp_dir().join(format!("correlation_ids_{}", uuid::Uuid::new_v4()));
+    // sled releases its file lock from a background thread, so reopening the path right
+    // away can fail; a fresh store over the same database reads the state back the same
+    let db = sled::open(&path).expect("open sled");
+    let open = || {
+        let store = SledCorrelationIdStore::new(&db).expect("open store");
+        CorrelationIdGenerator::open(Arc::new(store), None).expect("generator")
+    };
+
+    let first = open();
+    let instance_id = first.instance_id().to_owned();
+    let before: Vec<String> = (0..5).map(|_| first.generate()).collect();
+    drop(first);
+
+    let restarted = open();
+    assert_eq!(restarted.instance_id(), instance_id);
+    let after = restarted.generate();
+    let last = parse_generated_id(before.last().unwrap()).unwrap();
+    let resumed = parse_generated_id(&after).unwrap();
+    assert_eq!(resumed.instance_id, Some(instance_id.as_str()));
+    // The counter skips the rest of the reserved block instead of starting over
+    assert_eq!(resumed.counter, 1 + COUNTER_BLOCK);
+    assert!(resumed.counter > last.counter);
+    assert!(resumed.created_at >= last.created_at);
+    assert!(!before.contains(&after));
+    drop(restarted);
+    drop(db);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[test]
+fn replicas_never_generate_the_same_id() {
+    let replicas: Vec<Arc<CorrelationIdGenerator>> = ["replica-a", "replica-b", "replica-c"]
+        .into_iter()
+        .map(|name| {
+            let store = Arc::new(InMemoryCorrelationIdStore::new());
+            Arc::new(CorrelationIdGenerator::open(store, Some(name)).expect("generator"))
+        })
+        .collect();
+
+    // Every replica starts its counter at 1 and runs at the same time
+    let handles: Vec<_> = replicas
+        .iter()
+        .flat_map(|replica| (0..4).map(move |_| replica.clone()))
+        .map(|replica| {
+            std::thread::spawn(move || (0..500).map(|_| replica.generate()).collect::<Vec<_>>())
+        })
+        .collect();
+    let ids: Vec<String> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+    let policy = CorrelationIdPolicy::default();
+    for replica in &replicas {
+        let own: Vec<u64> = ids
+            .iter()
+            .map(|id| parse_generated_id(id).unwrap())
+            .filter(|parsed| parsed.instance_id == Some(replica.instance_id()))
+            .map(|parsed| parsed.counter)
+            .collect();
+        assert_eq!(own.len(), 2_000);
+        assert_eq!(own.iter().collect::<HashSet<_>>().len(), own.len());
+    }
+    assert!(ids.iter().all(|id| policy.accepts(id)));
+}
@@ -0,0 +1,201 @@
+#![cfg(feature = "server")]
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderMap, Request, StatusCode, header};
+use tower::ServiceExt;
+
+use prompt_sentinel::config::cors::{CorsPolicy, CorsSettings};
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::test_support::TestApp;
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+async fn build_router(cors: CorsSettings) -> Router {
+    let settings = AppSettings {
+        cors,
+        ..AppSettings::default()
+    };
+    TestApp::builder()
+        .with_settings(settings)
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .expect("test app")
+        .router()
+}
+
+fn configured() -> CorsSettings {
+    let defaults = CorsSettings::default();
+    CorsSettings {
+        public: CorsPolicy {
+            allowed_origins: vec![
+                "https://app.example.com".to_owned(),
+                "https://*.partners.example.com".to_owned(),
+            ],
+            ..defaults.public
+        },
+        admin: CorsPolicy {
+            allowed_origins: vec!["https://ops.example.com".to_owned()],
+            allow_credentials: true,
+            ..defaults.admin
+        },
+    }
+}
+
+async fn preflight(
+    router: &Router,
+    path: &str,
+    origin: &str,
+    method: &str,
+) -> (StatusCode, HeaderMap) {
+    let request = Request::builder()
+        .method("OPTIONS")
+        .uri(path)
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+        .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+fn allowed_origin(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[tokio::test]
+async fn default_policy_is_same_origin_only() {
+    let router = build_router(CorsSettings::default()).await;
+    for path in ["/api/compliance/check", "/api/audit/trail", "/api/selftest"] {
+        let (_, headers) = preflight(&router, path, "https://attacker.test", "POST").await;
+        assert_eq!(allowed_origin(&headers), None, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn public_routes_allow_listed_and_wildcard_origins() {
+    let router = build_router(configured()).await;
+
+    let (status, headers) = preflight(
+        &router,
+        "/api/compliance/check",
+        "https://app.example.com",
+        "POST",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(allowed_origin(&headers), Some("https://app.example.com"));
+    let methods = headers
+        .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    assert!(methods.contains("POST"), "{methods}");
+    assert!(
+        headers
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none()
+    );
+
+    let (_, headers) = preflight(
+        &router,
+        "/api/compliance/check",
+        "https://acme.partners.example.com",
+        "POST",
+    )
+    .await;
+    assert_eq!(
+        allowed_origin(&headers),
+        Some("https://acme.partners.example.com")
+    );
+
+    for origin in [
+        "https://partners.example.com",
+        "https://evilpartners.example.com",
+        "https://app.example.com.attacker.test",
+        "http://app.example.com",
+    ] {
+        let (_, headers) = preflight(&router, "/api/compliance/check", origin, "POST").await;
+        assert_eq!(allowed_origin(&headers), None, "{origin}");
+    }
+}
+
+#[tokio::test]
+async fn admin_routes_use_the_stricter_policy() {
+    let router = build_router(configured()).await;
+
+    // The public allowlist does not extend to audit, config or admin endpoints
+    for path in ["/api/audit/trail", "/api/config/restore", "/api/selftest"] {
+        let (_, headers) = preflight(&router, path, "https://app.example.com", "POST").await;
+        assert_eq!(allowed_origin(&headers), None, "{path}");
+    }
+
+    // Preflight is answered before the admin token check
+    let (status, headers) =
+        preflight(&router, "/api/selftest", "https://ops.example.com", "POST").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(allowed_origin(&headers), Some("https://ops.example.com"));
+    assert_eq!(
+        headers
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .and_then(|value| value.to_str().ok()),
+        Some("true")
+    );
+}
+
+#[tokio::test]
+async fn allow_any_restores_permissive_public_policy() {
+    let mut cors = CorsSettings::default();
+    cors.public.allow_any = true;
+    let router = build_router(cors).await;
+
+    let (_, headers) = preflight(
+        &router,
+        "/api/compliance/check",
+        "http://localhost:5175",
+        "POST",
+    )
+    .await;
+    assert_eq!(allowed_origin(&headers), Some("*"));
+
+    let (_, headers) =
+        preflight(&router, "/api/audit/trail", "http://localhost:5175", "POST").await;
+    assert_eq!(allowed_origin(&headers), None);
+}
+
+#[tokio::test]
+async fn system_summary_reports_applied_policy() {
+    let router = build_router(configured()).await;
+    let request = Request::builder()
+        .uri("/api/admin/summary")
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        summary["cors"]["public"]["allowed_origins"],
+        serde_json::json!(["https://app.example.com", "https://*.partners.example.com"])
+    );
+    assert_eq!(
+        summary["cors"]["admin"]["allowed_origins"],
+        serde_json::json!(["https://ops.example.com"])
+    );
+    assert_eq!(summary["cors"]["admin"]["allow_credentials"], true);
+    assert_eq!(summary["maintenance"]["settings"]["enabled"], false);
+    let fingerprint = &summary["config_fingerprint"];
+    assert_eq!(
+        fingerprint["firewall_rules"].as_str().map(str::len),
+        Some(64)
+    );
+    assert_eq!(
+        fingerprint["moderation_policy"].as_str().map(str::len),
+        Some(64)
+    );
+}
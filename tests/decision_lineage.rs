@@ -0,0 +1,245 @@
+#![cfg(feature = "server")]
+
+use std::collections::HashMap;
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::appeals::dtos::Appeal;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::telemetry::context::CORRELATION_ID_HEADER;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+use prompt_sentinel::workflow::{
+    DecisionLineage, ExplanationRedaction, LineageEntryKind, LineageRelation,
+};
+
+/// Blocked by PFW-005 ("jailbreak")
+const PHONE_PROMPT: &str = "What does it mean to jailbreak an iPhone?";
+const DECISION: &str = "lineage-decision-1";
+const SUPPORT_TOKEN: &str = "support-secret";
+
+async fn check(server: &TestServer, correlation_id: Option<&str>) -> Value {
+    let mut request = server.post("/api/compliance/check");
+    if let Some(correlation_id) = correlation_id {
+        request = request.header(CORRELATION_ID_HEADER, correlation_id);
+    }
+    let response = request
+        .json(&json!({ "prompt": PHONE_PROMPT }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+async fn get_lineage(server: &TestServer, correlation_id: &str) -> reqwest::Response {
+    server
+        .get(&format!("/api/decisions/{correlation_id}/lineage"))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn lineage_links_retries_appeals_exemptions_and_replays() {
+    let app = TestApp::builder()
+        .with_settings(AppSettings {
+            support_token: Some(SUPPORT_TOKEN.to_owned()),
+            ..AppSettings::default()
+        })
+        .with_admin_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let server = app.serve().await.unwrap();
+
+    // The client retries its blocked request under the same correlation id
+    assert_eq!(
+        check(&server, Some(DECISION)).await["status"],
+        "blocked_by_firewall"
+    );
+    assert_eq!(
+        check(&server, Some(DECISION)).await["status"],
+        "blocked_by_firewall"
+    );
+
+    let appeal: Appeal = server
+        .post("/api/appeals")
+        .json(&json!({
+            "correlation_id": DECISION,
+            "justification": "I was asking about my own phone",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let resolved: Appeal = server
+        .post(&format!("/api/appeals/{}/resolve", appeal.id))
+        .json(&json!({
+            "outcome": "overturned",
+            "note": "consumer device question",
+            "resolved_by": "trust-and-safety",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let exemption_ids = resolved.resolution.expect("resolution").exemption_ids;
+    assert_eq!(exemption_ids.len(), 1);
+
+    // A later request under its own correlation id goes through on the exemption
+    let exempted = check(&server, None).await;
+    assert_eq!(exempted["status"], "completed");
+    let exempted_id = exempted["correlation_id"].as_str().unwrap().to_owned();
+
+    let response = server
+        .post(&format!("/api/audit/replay/{DECISION}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let replay: Value = response.json().await.unwrap();
+    let replay_id = replay["replay_correlation_id"].as_str().unwrap();
+
+    let response = get_lineage(&server, DECISION).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let lineage: DecisionLineage = response.json().await.unwrap();
+    assert_eq!(lineage.correlation_id, DECISION);
+    let kinds: Vec<LineageEntryKind> = lineage.entries.iter().map(|entry| entry.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            LineageEntryKind::Decision,
+            LineageEntryKind::Retry,
+            LineageEntryKind::AppealFiled,
+            LineageEntryKind::ExemptionGranted,
+            LineageEntryKind::AppealResolved,
+            LineageEntryKind::ExemptedDecision,
+            LineageEntryKind::Replay,
+        ]
+    );
+    assert!(
+        lineage
+            .entries
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    );
+
+    let entries = &lineage.entries;
+    assert_eq!(entries[0].status, "blocked_by_firewall");
+    assert_eq!(entries[2].appeal_id.as_deref(), Some(appeal.id.as_str()));
+    assert_eq!(entries[3].exemption_ids, exemption_ids);
+    assert_eq!(entries[4].status, "overturned");
+    assert_eq!(entries[4].exemption_ids, exemption_ids);
+    assert_eq!(entries[5].correlation_id, exempted_id);
+    assert_eq!(entries[5].status, "completed");
+    assert_eq!(entries[5].exemption_ids, exemption_ids);
+    assert_eq!(entries[6].correlation_id, replay_id);
+    assert_eq!(entries[6].replay_mode.as_deref(), Some("current"));
+
+    // Every entry is a record of the audit chain, and every link points back at one
+    let stored: HashMap<String, String> = app
+        .storage
+        .all()
+        .unwrap()
+        .into_iter()
+        .map(|record| (record.proof.chain_hash, record.proof.record_hash))
+        .collect();
+    for (index, entry) in entries.iter().enumerate() {
+        assert_eq!(stored.get(&entry.chain_hash), Some(&entry.record_hash));
+        for link in &entry.links {
+            let target = entries[..index]
+                .iter()
+                .position(|earlier| earlier.chain_hash == link.chain_hash)
+                .unwrap_or_else(|| panic!("{:?} link of entry {index} resolves", link.relation));
+            let expected = match link.relation {
+                LineageRelation::Retries => 0,
+                LineageRelation::Appeals | LineageRelation::Exempts | LineageRelation::Replays => 1,
+                LineageRelation::Resolves => 2,
+                LineageRelation::Grants | LineageRelation::AppliesExemption => 3,
+                LineageRelation::Archives => unreachable!("no exemption was archived"),
+            };
+            assert_eq!(
+                target, expected,
+                "{:?} link of entry {index}",
+                link.relation
+            );
+        }
+    }
+    let links = |index: usize| {
+        entries[index]
+            .links
+            .iter()
+            .map(|link| link.relation)
+            .collect::<Vec<_>>()
+    };
+    assert!(links(0).is_empty());
+    assert_eq!(links(1), [LineageRelation::Retries]);
+    assert_eq!(links(2), [LineageRelation::Appeals]);
+    assert_eq!(links(3), [LineageRelation::Exempts]);
+    assert_eq!(
+        links(4),
+        [LineageRelation::Resolves, LineageRelation::Grants]
+    );
+    assert_eq!(links(5), [LineageRelation::AppliesExemption]);
+    assert_eq!(links(6), [LineageRelation::Replays]);
+
+    // The exempted request has a lineage of its own, and unknown ids have none
+    let own: DecisionLineage = get_lineage(&server, &exempted_id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(own.entries.len(), 1);
+    assert_eq!(own.entries[0].kind, LineageEntryKind::Decision);
+    assert_eq!(
+        get_lineage(&server, "no-such-decision").await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // Support staff see the timeline without the exemptions, which name the rules waived
+    let path = server.url(&format!("/api/decisions/{DECISION}/lineage"));
+    let anonymous = reqwest::Client::new();
+    let response = anonymous.get(&path).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let support: DecisionLineage = anonymous
+        .get(&path)
+        .bearer_auth(SUPPORT_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(support.redaction, ExplanationRedaction::Generalized);
+    assert_eq!(support.entries.len(), entries.len());
+    assert!(
+        support
+            .entries
+            .iter()
+            .all(|entry| entry.exemption_ids.is_empty())
+    );
+    let response = anonymous
+        .get(format!("{path}?audience=admin"))
+        .bearer_auth(SUPPORT_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let preview: DecisionLineage = server
+        .get(&format!(
+            "/api/decisions/{DECISION}/lineage?audience=support"
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(preview.entries, support.entries);
+}
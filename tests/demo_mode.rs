@@ -0,0 +1,239 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use serde_json::json;
+
+use prompt_sentinel::config::settings::{AppSettings, SettingsError};
+use prompt_sentinel::modules::audit::storage::{AuditStorage, PromptStorageMode};
+use prompt_sentinel::modules::demo::dtos::DemoConfig;
+use prompt_sentinel::modules::demo::service::canned_response;
+use prompt_sentinel::modules::mistral_ai::client::{MockMistralClient, RecordedCall};
+use prompt_sentinel::modules::prompt_firewall::dtos::FirewallAction;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceResponse, FrameworkConfig, WorkflowStatus};
+
+const PRIVATE_PROMPT: &str = "Summarize the roadmap of project purple walrus for me";
+const SANITIZED_PROMPT: &str = "<script>alert(1)</script> Describe the purple walrus launch";
+
+fn demo_settings(quota_requests: u32) -> AppSettings {
+    AppSettings {
+        demo: DemoConfig {
+            enabled: true,
+            quota_requests,
+            ..DemoConfig::default()
+        },
+        ..AppSettings::default()
+    }
+}
+
+async fn demo_app(quota_requests: u32) -> TestApp {
+    TestApp::builder()
+        .with_settings(demo_settings(quota_requests))
+        // A token does not bring the admin endpoints back
+        .with_admin_token("demo-admin")
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn env_with<'a>(extra: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |key: &str| {
+        extra
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| (*value).to_owned())
+            .or_else(|| match key {
+                "MISTRAL_GENERATION_MODEL" => Some("mistral-large-latest".to_owned()),
+                "MISTRAL_EMBEDDING_MODEL" => Some("mistral-embed".to_owned()),
+                "AUDIT_BACKEND" => Some("memory".to_owned()),
+                _ => None,
+            })
+    }
+}
+
+#[tokio::test]
+async fn a_demo_server_answers_with_canned_text_without_calling_chat() {
+    let env = env_with(&[
+        ("DEMO_MODE", "true"),
+        ("DEMO_MOCK_MODERATION", "true"),
+        ("DEMO_MOCK_EMBEDDINGS", "true"),
+        ("AUDIT_PROMPT_STORAGE", "full"),
+        // The mock does not list the moderation model
+        ("STARTUP_POLICY_MISTRAL", "skip"),
+    ]);
+    let loaded = AppSettings::load_from(None, &env).expect("settings");
+    assert_eq!(loaded.0.audit_prompt_storage, PromptStorageMode::Redacted);
+    let mock = MockMistralClient::default().record_calls();
+    let server = FrameworkConfig::initialize_with_client(loaded, Arc::new(mock.clone()))
+        .await
+        .expect("demo server initializes");
+    let app = server.build_router();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+    });
+    let client = reqwest::Client::new();
+
+    for (prompt, action, status) in [
+        (
+            PRIVATE_PROMPT,
+            FirewallAction::Allow,
+            WorkflowStatus::Completed,
+        ),
+        (
+            SANITIZED_PROMPT,
+            FirewallAction::Sanitize,
+            WorkflowStatus::Sanitized,
+        ),
+    ] {
+        let response: ComplianceResponse = client
+            .post(format!("http://{address}/api/compliance/check"))
+            .json(&json!({ "prompt": prompt }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.status, status);
+        assert!(response.demo_mode);
+        assert_eq!(response.firewall.action, action);
+        assert_eq!(
+            response.generated_text.as_deref(),
+            Some(canned_response(&action))
+        );
+        assert!(response.input_moderation.is_some());
+    }
+
+    // Generation, moderation and embeddings all stayed local
+    let remote: Vec<_> = mock
+        .recorded_calls()
+        .into_iter()
+        .filter(|call| {
+            matches!(
+                call,
+                RecordedCall::Chat(_)
+                    | RecordedCall::Moderation(_)
+                    | RecordedCall::ModerationBatch(_)
+                    | RecordedCall::Embeddings(_)
+            )
+        })
+        .collect();
+    assert!(remote.is_empty(), "{remote:?}");
+}
+
+#[tokio::test]
+async fn only_the_public_checks_are_served() {
+    let app = demo_app(20).await;
+    let server = app.serve().await.unwrap();
+
+    for path in [
+        "/api/firewall/rules",
+        "/api/config/snapshot",
+        "/api/config/effective",
+        "/api/admin/summary",
+        "/api/appeals",
+    ] {
+        let response = server.get(path).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "GET {path}");
+    }
+    for path in ["/api/audit/trail", "/api/selftest", "/api/appeals"] {
+        let response = server.post(path).json(&json!({})).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "POST {path}");
+    }
+
+    assert_eq!(
+        server.get("/health").send().await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        server
+            .get("/api/compliance/options")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn each_address_is_held_to_its_quota() {
+    let app = demo_app(2).await;
+    let server = app.serve().await.unwrap();
+    let check = || {
+        server
+            .post("/api/compliance/check")
+            .json(&json!({ "prompt": "What is the capital of France?" }))
+            .send()
+    };
+
+    for _ in 0..2 {
+        assert_eq!(check().await.unwrap().status(), StatusCode::OK);
+    }
+    let refused = check().await.unwrap();
+    assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = refused.headers()[RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=3600).contains(&retry_after));
+    let message = refused.text().await.unwrap();
+    assert!(
+        message.starts_with("The demo allows 2 requests per hour from each address"),
+        "{message}"
+    );
+
+    // Reading is not counted
+    assert_eq!(
+        server.get("/health").send().await.unwrap().status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn no_prompt_text_reaches_the_audit_store() {
+    let app = demo_app(20).await;
+    let server = app.serve().await.unwrap();
+    for prompt in [PRIVATE_PROMPT, SANITIZED_PROMPT] {
+        let response = server
+            .post("/api/compliance/check")
+            .json(&json!({ "prompt": prompt }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let records = app.storage.all().expect("records");
+    assert!(records.len() >= 2);
+    for record in records {
+        assert!(
+            !record.payload.to_lowercase().contains("purple walrus"),
+            "{}",
+            record.payload
+        );
+    }
+}
+
+#[test]
+fn demo_mode_refuses_admin_and_support_tokens() {
+    for token in ["ADMIN_API_TOKEN", "SUPPORT_API_TOKEN"] {
+        let extra = [("DEMO_MODE", "true"), (token, "secret")];
+        let env = env_with(&extra);
+        let error = AppSettings::load_from(None, &env).expect_err("demo with a token");
+        assert!(
+            matches!(&error, SettingsError::Invalid(message) if message.contains("DEMO_MODE")),
+            "{error}"
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use prompt_sentinel::firewall_core::rules::PhraseMatchKind;
 use prompt_sentinel::modules::eu_law_compliance::dtos::ComplianceCheckRequest;
-use prompt_sentinel::modules::eu_law_compliance::model::AiRiskTier;
+use prompt_sentinel::modules::eu_law_compliance::model::{
+    AiRiskTier, ComplianceFinding, KeywordMatch,
+};
 use prompt_sentinel::modules::eu_law_compliance::service::EuLawComplianceService;
 
 #[test]
@@ -76,3 +79,125 @@ fn missing_intended_use_context_is_flagged() {
     assert!(!response.compliant);
     assert!(response.findings.iter().any(|f| f.code == "EU-SCOPE-001"));
 }
+
+fn documented_use(intended_use: &str) -> ComplianceCheckRequest {
+    ComplianceCheckRequest {
+        intended_use: intended_use.to_owned(),
+        technical_documentation_available: true,
+        transparency_notice_available: true,
+        copyright_controls_available: true,
+    }
+}
+
+fn prohibited_keyword(findings: &[ComplianceFinding]) -> &KeywordMatch {
+    findings
+        .iter()
+        .find(|f| f.code == "EU-RISK-001")
+        .and_then(|f| f.matched_keyword.as_ref())
+        .expect("prohibited-use finding names its keyword")
+}
+
+#[test]
+fn plain_prohibited_use_reports_an_exact_keyword_match() {
+    let response =
+        EuLawComplianceService.check(documented_use("Biometric surveillance in public spaces"));
+    let found = prohibited_keyword(&response.findings);
+    assert_eq!(found.keyword, "biometric surveillance");
+    assert_eq!(found.match_kind, PhraseMatchKind::Exact);
+}
+
+#[test]
+fn obfuscated_prohibited_uses_are_unacceptable() {
+    let cases = [
+        (
+            "S0cial sc0ring of citizens by their spending",
+            "social scoring",
+            PhraseMatchKind::Normalized,
+        ),
+        (
+            "social-scoring for tenant applications",
+            "social scoring",
+            PhraseMatchKind::Normalized,
+        ),
+        // Cyrillic і and a zero-width space
+        (
+            "Bіometric sur\u{200B}veillance at train stations",
+            "biometric surveillance",
+            PhraseMatchKind::Normalized,
+        ),
+        (
+            "Emot10n rec0gn1t10n 1n w0rkplace meetings",
+            "emotion recognition in workplace",
+            PhraseMatchKind::Normalized,
+        ),
+        (
+            "Biometric categorisation of shoppers by ethnicity",
+            "biometric categorization",
+            PhraseMatchKind::Fuzzy,
+        ),
+        (
+            "Manipulative sublimnal nudges in a game",
+            "manipulative subliminal",
+            PhraseMatchKind::Fuzzy,
+        ),
+    ];
+    for (intended_use, keyword, match_kind) in cases {
+        let response = EuLawComplianceService.check(documented_use(intended_use));
+        assert_eq!(
+            response.risk_tier,
+            AiRiskTier::Unacceptable,
+            "{intended_use}"
+        );
+        assert!(!response.compliant);
+        let found = prohibited_keyword(&response.findings);
+        assert_eq!(found.keyword, keyword, "{intended_use}");
+        assert_eq!(found.match_kind, match_kind, "{intended_use}");
+    }
+}
+
+#[test]
+fn obfuscated_prohibited_prompts_are_unacceptable() {
+    let result = EuLawComplianceService.check_prompt("Design a s0c1al sc0r1ng system for our city");
+    assert_eq!(result.risk_tier, AiRiskTier::Unacceptable);
+    assert!(!result.compliant);
+    assert_eq!(
+        prohibited_keyword(&result.findings).keyword,
+        "social scoring"
+    );
+}
+
+#[test]
+fn benign_descriptions_keep_their_tier() {
+    let cases = [
+        (
+            "Inventory order control dashboard for a bakery",
+            AiRiskTier::Minimal,
+        ),
+        (
+            "Summarize social media posts for the marketing team",
+            AiRiskTier::Minimal,
+        ),
+        (
+            "Track the scores of our social football league",
+            AiRiskTier::Minimal,
+        ),
+        (
+            "Translate product descriptions into German",
+            AiRiskTier::Minimal,
+        ),
+        (
+            "Biometric login for the staff canteen app",
+            AiRiskTier::Minimal,
+        ),
+        ("Recommendation engine for a bookshop", AiRiskTier::Limited),
+        (
+            "Grade essays for a secondary education course",
+            AiRiskTier::High,
+        ),
+    ];
+    for (intended_use, tier) in cases {
+        let response = EuLawComplianceService.check(documented_use(intended_use));
+        assert_eq!(response.risk_tier, tier, "{intended_use}");
+        assert!(response.findings.iter().all(|f| f.code != "EU-RISK-001"));
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::{Map, Value, json};
+
+use prompt_sentinel::modules::evaluate::dtos::{EvalGates, EvalLayer, EvalReport, Expectation};
+use prompt_sentinel::modules::evaluate::service::{Evaluator, load_dataset, render_markdown};
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::modules::semantic_detection::SemanticDetectionService;
+
+const DATASET: &str = "tests/eval/injection_eval.jsonl";
+/// Rewrite with `UPDATE_GOLDEN=1 cargo test --test evaluate` after changing the report
+const GOLDEN_SHAPE: &str = "tests/eval/report_shape.json";
+
+async fn firewall_report() -> EvalReport {
+    let cases = load_dataset(Path::new(DATASET)).expect("dataset");
+    Evaluator::new(PromptFirewallService::default())
+        .run(DATASET, &cases)
+        .await
+        .expect("evaluation")
+}
+
+/// The report with every value replaced by its JSON type, and arrays by their first item
+fn shape(value: &Value) -> Value {
+    match value {
+        Value::Null => json!("null"),
+        Value::Bool(_) => json!("bool"),
+        Value::Number(_) => json!("number"),
+        Value::String(_) => json!("string"),
+        Value::Array(items) => Value::Array(items.first().map(shape).into_iter().collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), shape(value)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[tokio::test]
+async fn report_structure_matches_the_golden_file() {
+    let report = serde_json::to_value(firewall_report().await).unwrap();
+    let actual = shape(&report);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let pretty = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(GOLDEN_SHAPE, pretty + "\n").unwrap();
+    }
+    let golden: Value =
+        serde_json::from_str(&std::fs::read_to_string(GOLDEN_SHAPE).expect("golden file")).unwrap();
+    assert_eq!(
+        actual, golden,
+        "report structure changed; see {GOLDEN_SHAPE}"
+    );
+
+    // The report reads back
+    let _: EvalReport = serde_json::from_value(report).unwrap();
+}
+
+#[tokio::test]
+async fn counts_add_up_across_the_report() {
+    let report = firewall_report().await;
+    let overall = &report.overall;
+    assert_eq!(overall.cases, report.cases.len());
+    let attacks = report
+        .cases
+        .iter()
+        .filter(|case| case.expected == Expectation::Block)
+        .count();
+    assert_eq!(
+        overall.confusion.true_positives + overall.confusion.false_negatives,
+        attacks
+    );
+    assert_eq!(report.profile.layers, [EvalLayer::Firewall]);
+    assert!(report.score_distributions.is_empty());
+
+    // Without other layers every firewall block is the firewall's alone
+    let firewall = &report.layers[0];
+    assert_eq!(firewall.attacks_blocked, overall.confusion.true_positives);
+    assert_eq!(firewall.attacks_blocked_alone, firewall.attacks_blocked);
+    assert_eq!(firewall.false_positives, overall.confusion.false_positives);
+
+    let direct = &report.tags["direct"];
+    assert_eq!(
+        direct.cases,
+        direct.confusion.true_positives + direct.confusion.false_negatives
+    );
+    assert!(direct.confusion.true_positives > 0, "{direct:?}");
+    assert_eq!(report.tags["benign"].confusion.false_positives, 0);
+
+    let markdown = render_markdown(&report);
+    assert!(markdown.contains("| **overall** |"), "{markdown}");
+    assert!(markdown.contains("| paraphrase |"), "{markdown}");
+}
+
+#[tokio::test]
+async fn semantic_layer_adds_scores_and_a_contribution() {
+    let mistral = MistralService::new(
+        Arc::new(MockMistralClient::default().with_deterministic_embeddings(7, 64)),
+        "mistral-large-latest",
+        None,
+        "mistral-embed",
+    );
+    let semantic = SemanticDetectionService::new(mistral, 0.70, 0.80, 0.02);
+    semantic.initialize().await.expect("attack bank");
+    let cases = load_dataset(Path::new(DATASET)).expect("dataset");
+
+    let report = Evaluator::new(PromptFirewallService::default())
+        .with_semantic(semantic)
+        .with_profile_name("mock-semantic")
+        .run(DATASET, &cases)
+        .await
+        .expect("evaluation");
+    assert_eq!(
+        report.profile.layers,
+        [EvalLayer::Firewall, EvalLayer::Semantic]
+    );
+    let (medium, high) = report.profile.semantic_cutoffs.expect("cutoffs");
+    assert!((medium - 0.72).abs() < 1e-6 && (high - 0.82).abs() < 1e-6);
+    assert!(
+        report
+            .cases
+            .iter()
+            .all(|case| case.semantic_score.is_some())
+    );
+    let counted: usize = report
+        .score_distributions
+        .values()
+        .map(|distribution| distribution.histogram.iter().sum::<usize>())
+        .sum();
+    assert_eq!(counted, cases.len());
+    assert_eq!(report.layers.len(), 2);
+}
+
+#[tokio::test]
+async fn gates_fail_runs_that_cross_them() {
+    let report = firewall_report().await;
+    let recall = report.overall.recall.unwrap();
+    let fp_rate = report.overall.false_positive_rate.unwrap();
+
+    let passing = EvalGates {
+        min_recall: Some(recall),
+        max_false_positive_rate: Some(fp_rate),
+    };
+    assert!(passing.check(&report).is_empty());
+
+    let strict = EvalGates {
+        min_recall: Some((recall + 0.01).min(1.0)),
+        max_false_positive_rate: Some(fp_rate - 0.01),
+    };
+    let failures = strict.check(&report);
+    assert_eq!(failures.len(), 2, "{failures:?}");
+    assert!(failures[0].contains("recall"), "{failures:?}");
+}
+
+#[test]
+fn malformed_dataset_lines_are_reported_by_number() {
+    let path = std::env::temp_dir().join(format!("eval_{}.jsonl", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        "{\"id\": \"a\", \"text\": \"hi\", \"expected\": \"allow\", \"tags\": []}\n\n\
+         {\"id\": \"b\", \"text\": \"hi\", \"expected\": \"maybe\"}\n",
+    )
+    .unwrap();
+    let error = load_dataset(&path).expect_err("bad expectation");
+    let _ = std::fs::remove_file(&path);
+    assert!(error.to_string().contains("line 3"), "{error}");
+}
@@ -0,0 +1,343 @@
+#![cfg(feature = "server")]
+
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::exemptions::dtos::{
+    ArchiveReason, Exemption, ExemptionRequest, ExemptionsResponse,
+};
+use prompt_sentinel::modules::maintenance::dtos::{MaintenanceRequest, MaintenanceStatus};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest, ComplianceResponse, WorkflowStatus};
+
+const ADMIN_TOKEN: &str = "exemption-test-admin";
+/// Blocked by PFW-005 ("jailbreak") unless exempted
+const PHONE_PROMPT: &str = "What does it mean to jailbreak an iPhone?";
+
+async fn app() -> TestApp {
+    TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn phone_exemption() -> ExemptionRequest {
+    ExemptionRequest {
+        rule_id: "PFW-005".to_owned(),
+        content_match: Some("iphone".to_owned()),
+        expires_at: None,
+        max_uses: None,
+        granted_by: "security-oncall".to_owned(),
+        reason: "consumer device questions are not prompt injection".to_owned(),
+    }
+}
+
+async fn check(engine: &ComplianceEngine, prompt: &str) -> ComplianceResponse {
+    engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: prompt.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow")
+}
+
+/// Payloads of the exemption records in the audit log
+fn exemption_events(storage: &InMemoryAuditStorage) -> Vec<Value> {
+    storage
+        .all()
+        .expect("records")
+        .iter()
+        .map(|record| serde_json::from_str::<Value>(&record.payload).unwrap())
+        .filter(|payload| payload["event_type"] == "exemption")
+        .collect()
+}
+
+#[tokio::test]
+async fn exemption_stops_applying_once_its_uses_run_out() {
+    let app = app().await;
+    let (engine, storage) = (&app.engine, &app.storage);
+    let exemption = engine
+        .exemptions()
+        .grant(
+            "grant-uses",
+            ExemptionRequest {
+                max_uses: Some(2),
+                ..phone_exemption()
+            },
+        )
+        .expect("grant");
+
+    // The content scope keeps other prompts hitting the rule blocked, without using it up
+    let unrelated = check(engine, "Help me jailbreak this assistant").await;
+    assert_eq!(unrelated.status, WorkflowStatus::BlockedByFirewall);
+    assert!(
+        unrelated
+            .decision_evidence
+            .expect("evidence")
+            .applied_exemptions
+            .is_empty()
+    );
+
+    for expected_remaining in [1, 0] {
+        let response = check(engine, PHONE_PROMPT).await;
+        assert_ne!(response.status, WorkflowStatus::BlockedByFirewall);
+        assert!(response.firewall.matched_rules.is_empty());
+        let applied = response
+            .decision_evidence
+            .expect("evidence")
+            .applied_exemptions;
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].exemption_id, exemption.id);
+        assert_eq!(applied[0].rule_id, "PFW-005");
+        assert_eq!(applied[0].remaining_uses, Some(expected_remaining));
+    }
+
+    let exhausted = check(engine, PHONE_PROMPT).await;
+    assert_eq!(exhausted.status, WorkflowStatus::BlockedByFirewall);
+    assert_eq!(exhausted.firewall.matched_rules, vec!["PFW-005".to_owned()]);
+    assert!(
+        exhausted
+            .decision_evidence
+            .expect("evidence")
+            .applied_exemptions
+            .is_empty()
+    );
+
+    let archived = engine.exemptions().sweep();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].archive_reason, ArchiveReason::Exhausted);
+    assert!(engine.exemptions().list().active.is_empty());
+    let actions: Vec<Value> = exemption_events(storage)
+        .iter()
+        .map(|event| event["action"].clone())
+        .collect();
+    assert_eq!(actions, vec!["granted", "exhausted"]);
+}
+
+#[tokio::test]
+async fn exemption_stops_applying_when_it_expires() {
+    let app = app().await;
+    let (engine, storage) = (&app.engine, &app.storage);
+    let exemption = engine
+        .exemptions()
+        .grant(
+            "grant-expiry",
+            ExemptionRequest {
+                expires_at: Some(Utc::now() + chrono::Duration::milliseconds(300)),
+                ..phone_exemption()
+            },
+        )
+        .expect("grant");
+
+    let response = check(engine, PHONE_PROMPT).await;
+    assert_ne!(response.status, WorkflowStatus::BlockedByFirewall);
+    let applied = response
+        .decision_evidence
+        .expect("evidence")
+        .applied_exemptions;
+    assert_eq!(applied[0].exemption_id, exemption.id);
+    assert_eq!(applied[0].remaining_uses, None);
+    assert!(response.decision_trace.iter().any(|step| {
+        step.rule_refs
+            .contains(&format!("exemption:{}", exemption.id))
+    }));
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    // Expired entries stop applying before the sweep gets to them
+    assert_eq!(engine.exemptions().list().active.len(), 1);
+    let response = check(engine, PHONE_PROMPT).await;
+    assert_eq!(response.status, WorkflowStatus::BlockedByFirewall);
+
+    let archived = engine.exemptions().sweep();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].archive_reason, ArchiveReason::Expired);
+    let listing = engine.exemptions().list();
+    assert!(listing.active.is_empty());
+    assert_eq!(listing.archived[0].exemption.id, exemption.id);
+    assert_eq!(
+        exemption_events(storage).last().unwrap()["action"],
+        "expired"
+    );
+}
+
+#[tokio::test]
+async fn unbounded_or_unknown_exemptions_are_rejected() {
+    let app = app().await;
+    let server = app.serve().await.unwrap();
+    let base_url = server.base_url.clone();
+    let client = reqwest::Client::new();
+
+    for (request, message) in [
+        (
+            phone_exemption(),
+            "an exemption needs expires_at, max_uses or both",
+        ),
+        (
+            ExemptionRequest {
+                rule_id: "PFW-404".to_owned(),
+                max_uses: Some(1),
+                ..phone_exemption()
+            },
+            "unknown firewall rule 'PFW-404'",
+        ),
+    ] {
+        let response = client
+            .post(format!("{base_url}/api/exemptions"))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 422);
+        assert!(response.text().await.unwrap().contains(message));
+    }
+
+    let response = client
+        .post(format!("{base_url}/api/exemptions"))
+        .json(&ExemptionRequest {
+            max_uses: Some(1),
+            ..phone_exemption()
+        })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+async fn set_maintenance(client: &reqwest::Client, base_url: &str, enabled: bool) {
+    let response = client
+        .post(format!("{base_url}/api/admin/maintenance"))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&MaintenanceRequest {
+            enabled,
+            message: None,
+            max_queue: Some(5),
+            max_wait_ms: Some(10_000),
+        })
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
+
+async fn queue_depth(client: &reqwest::Client, base_url: &str) -> usize {
+    let status: MaintenanceStatus = client
+        .get(format!("{base_url}/api/admin/maintenance"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    status.queue_depth
+}
+
+#[tokio::test]
+async fn revocation_applies_to_requests_already_in_flight() {
+    let app = app().await;
+    let storage = &app.storage;
+    let server = app.serve().await.unwrap();
+    let base_url = server.base_url.clone();
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{base_url}/api/exemptions"))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&ExemptionRequest {
+            max_uses: Some(10),
+            ..phone_exemption()
+        })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let exemption: Exemption = response.json().await.unwrap();
+
+    let send_check = |client: reqwest::Client, base_url: String| async move {
+        client
+            .post(format!("{base_url}/api/compliance/check"))
+            .json(&ComplianceRequest {
+                correlation_id: None,
+                prompt: PHONE_PROMPT.to_owned(),
+                suggest_rewrite: false,
+                template: None,
+                deterministic: None,
+                session_id: None,
+            })
+            .send()
+            .await
+            .unwrap()
+            .json::<ComplianceResponse>()
+            .await
+            .unwrap()
+    };
+    let allowed = send_check(client.clone(), base_url.clone()).await;
+    assert_eq!(allowed.status, WorkflowStatus::Completed);
+
+    // Exempted prompts need Mistral, so they wait in the maintenance queue
+    set_maintenance(&client, &base_url, true).await;
+    let in_flight: Vec<_> = (0..2)
+        .map(|_| tokio::spawn(send_check(client.clone(), base_url.clone())))
+        .collect();
+    while queue_depth(&client, &base_url).await < 2 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let response = client
+        .delete(format!("{base_url}/api/exemptions/{}", exemption.id))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    set_maintenance(&client, &base_url, false).await;
+
+    for request in in_flight {
+        let response = request.await.unwrap();
+        assert_eq!(response.status, WorkflowStatus::BlockedByFirewall);
+        assert!(
+            response
+                .decision_evidence
+                .expect("evidence")
+                .applied_exemptions
+                .is_empty()
+        );
+    }
+
+    let listing: ExemptionsResponse = client
+        .get(format!("{base_url}/api/exemptions"))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(listing.active.is_empty());
+    assert_eq!(listing.archived[0].exemption.id, exemption.id);
+    assert_eq!(listing.archived[0].archive_reason, ArchiveReason::Revoked);
+    // Nine uses were left when it was revoked
+    assert_eq!(listing.archived[0].exemption.remaining_uses, Some(9));
+
+    let response = client
+        .delete(format!("{base_url}/api/exemptions/{}", exemption.id))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(
+        exemption_events(storage).last().unwrap()["action"],
+        "revoked"
+    );
+}
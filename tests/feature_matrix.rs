@@ -0,0 +1,52 @@
+//! Settings follow the storage backends compiled into this build
+
+use std::collections::HashMap;
+
+use prompt_sentinel::config::settings::{AppSettings, SettingsError};
+use prompt_sentinel::modules::audit::storage::AuditBackend;
+
+fn load(env: &[(&str, &str)]) -> Result<AppSettings, SettingsError> {
+    let env: HashMap<String, String> = env
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    AppSettings::load_from(None, &|key| env.get(key).cloned()).map(|(settings, _)| settings)
+}
+
+#[test]
+fn default_backend_is_compiled_in() {
+    let expected = if cfg!(feature = "sled-storage") {
+        AuditBackend::Sled
+    } else {
+        AuditBackend::Memory
+    };
+    assert_eq!(AuditBackend::default(), expected);
+    assert_eq!(load(&[]).expect("defaults load").audit_backend, expected);
+    assert!(AuditBackend::Memory.is_available());
+    assert_eq!(AuditBackend::Memory.required_feature(), None);
+}
+
+#[test]
+fn backends_missing_from_the_build_are_rejected() {
+    for (backend, value) in [
+        (AuditBackend::Sled, "sled"),
+        (AuditBackend::Sqlite, "sqlite"),
+    ] {
+        let loaded = load(&[("AUDIT_BACKEND", value)]);
+        if backend.is_available() {
+            assert_eq!(loaded.expect("compiled in").audit_backend, backend);
+        } else {
+            let Err(SettingsError::Invalid(message)) = loaded else {
+                panic!("{value} should be rejected without its feature");
+            };
+            assert!(
+                message.contains(backend.required_feature().unwrap()),
+                "{message}"
+            );
+        }
+    }
+    assert_eq!(
+        AuditBackend::Sqlite.is_available(),
+        cfg!(feature = "sqlite-storage")
+    );
+}
@@ -19,6 +19,8 @@ async fn firewall_evaluation_large_prompt_stays_reasonably_fast() {
         .inspect(PromptFirewallRequest {
             prompt: payload,
             correlation_id: None,
+            detected_language: None,
+            pre_translated_text: None,
         })
         .await;
     let elapsed = started.elapsed();
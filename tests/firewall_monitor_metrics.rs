@@ -0,0 +1,117 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::sync::OnceLock;
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+use prompt_sentinel::modules::prompt_firewall::rules::{
+    CompiledFirewallRules, ExpectedAction, FirewallRulesConfig, RuleAssertion, RuleEntry, RuleMode,
+};
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{DocumentScanRequest, DocumentVerdict, ScannedDocument};
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest};
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+/// Monitor hits counted so far for `rule_id`
+fn monitor_hits(rule_id: &str) -> f64 {
+    let series = format!("firewall_monitor_matches_total{{rule_id=\"{rule_id}\"}}");
+    recorder()
+        .render()
+        .lines()
+        .find(|line| line.starts_with(&series))
+        .map_or(0.0, |line| {
+            line.rsplit(' ').next().unwrap().parse().unwrap()
+        })
+}
+
+/// Default rules plus a monitored block rule for `pattern`
+fn firewall(rule_id: &str, pattern: &str, assertions: Vec<RuleAssertion>) -> PromptFirewallService {
+    let mut rules = FirewallRulesConfig::default();
+    rules.block_rules.push(RuleEntry {
+        mode: RuleMode::Monitor,
+        ..RuleEntry::new(rule_id, pattern)
+    });
+    rules.assertions = assertions;
+    PromptFirewallService::default()
+        .with_rules(CompiledFirewallRules::compile(rules).expect("valid rules"))
+}
+
+async fn build_engine(firewall: PromptFirewallService) -> ComplianceEngine {
+    TestApp::builder()
+        .with_firewall(firewall)
+        .build()
+        .await
+        .expect("test app")
+        .engine
+}
+
+#[tokio::test]
+async fn document_scans_count_monitor_hits() {
+    recorder();
+    let engine = build_engine(firewall("PFW-MON-DOC", "project bluefin", Vec::new())).await;
+
+    let response = engine
+        .scan_documents(DocumentScanRequest {
+            correlation_id: None,
+            documents: vec![ScannedDocument {
+                id: "doc-1".to_owned(),
+                text: "Status notes for project bluefin, week 12.".to_owned(),
+                source: None,
+            }],
+            context: None,
+        })
+        .await
+        .expect("scan");
+
+    assert_eq!(response.verdict, DocumentVerdict::Allow);
+    assert!(response.documents[0].matched_rules.is_empty());
+    assert_eq!(monitor_hits("PFW-MON-DOC"), 1.0);
+}
+
+#[tokio::test]
+async fn rule_tests_count_monitor_hits() {
+    recorder();
+    let service = firewall(
+        "PFW-MON-TEST",
+        "project marlin",
+        vec![RuleAssertion {
+            prompt: "Tell me about project marlin".to_owned(),
+            expect: ExpectedAction::Allow,
+        }],
+    );
+    // Compiling the rules ran the assertions, which is not a rule test
+    assert_eq!(monitor_hits("PFW-MON-TEST"), 0.0);
+
+    let report = service.test_rules();
+    assert_eq!(report.passed, 1);
+    assert_eq!(monitor_hits("PFW-MON-TEST"), 1.0);
+}
+
+#[tokio::test]
+async fn chat_requests_count_each_hit_once() {
+    recorder();
+    let engine = build_engine(firewall("PFW-MON-CHAT", "project tarpon", Vec::new())).await;
+
+    engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: "Tell me about project tarpon".to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow");
+
+    assert_eq!(monitor_hits("PFW-MON-CHAT"), 1.0);
+}
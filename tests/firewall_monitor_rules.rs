@@ -0,0 +1,196 @@
+#![cfg(feature = "server")]
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use tower::ServiceExt;
+
+use prompt_sentinel::modules::audit::logger::AuditEvent;
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::config_management::dtos::RulePromotionResponse;
+use prompt_sentinel::modules::prompt_firewall::rules::{
+    CompiledFirewallRules, ExpectedAction, FirewallRulesConfig, RuleAssertion, RuleEntry, RuleMode,
+};
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{ComplianceResponse, WorkflowStatus};
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest};
+
+const MONITOR_RULE: &str = "PFW-MON-001";
+const CODENAME_PROMPT: &str = "Tell me about project bluefin";
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// Default rules plus a codename rule in monitor mode
+fn monitor_rules(assertions: Vec<RuleAssertion>) -> CompiledFirewallRules {
+    let mut rules = FirewallRulesConfig::default();
+    rules.block_rules.push(RuleEntry {
+        mode: RuleMode::Monitor,
+        ..RuleEntry::new(MONITOR_RULE, "project bluefin")
+    });
+    rules.assertions = assertions;
+    CompiledFirewallRules::compile(rules).expect("valid rules")
+}
+
+async fn app(rules: CompiledFirewallRules) -> TestApp {
+    TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .with_firewall(PromptFirewallService::default().with_rules(rules))
+        .build()
+        .await
+        .expect("test app")
+}
+
+async fn check(engine: &ComplianceEngine, prompt: &str) -> ComplianceResponse {
+    engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: prompt.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow")
+}
+
+async fn promote(router: &Router, rule_id: &str) -> (StatusCode, Vec<u8>) {
+    promote_with(router, rule_id, Some(ADMIN_TOKEN)).await
+}
+
+async fn promote_with(
+    router: &Router,
+    rule_id: &str,
+    token: Option<&str>,
+) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri(format!("/api/firewall/rules/{rule_id}/promote"));
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let response = router
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, body.to_vec())
+}
+
+/// Every audit record payload, oldest first
+fn payloads(storage: &InMemoryAuditStorage) -> Vec<serde_json::Value> {
+    storage
+        .all()
+        .expect("records")
+        .iter()
+        .map(|record| serde_json::from_str(&record.payload).expect("json payload"))
+        .collect()
+}
+
+#[tokio::test]
+async fn a_monitored_match_is_recorded_but_allowed() {
+    let app = app(monitor_rules(Vec::new())).await;
+    let (engine, storage) = (&app.engine, &app.storage);
+
+    let response = check(engine, CODENAME_PROMPT).await;
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert!(response.generated_text.is_some());
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(evidence.firewall_action, "allow");
+    assert!(evidence.firewall_matched_rules.is_empty());
+    assert_eq!(evidence.monitored_firewall_matches.len(), 1);
+    assert_eq!(evidence.monitored_firewall_matches[0].id, MONITOR_RULE);
+
+    let event: AuditEvent = storage
+        .all()
+        .expect("records")
+        .iter()
+        .find_map(|record| serde_json::from_str::<AuditEvent>(&record.payload).ok())
+        .expect("audit event");
+    assert_eq!(event.final_status, "completed");
+    assert_eq!(
+        event.monitored_firewall_matches,
+        evidence.monitored_firewall_matches
+    );
+
+    // Prompts the monitor rule does not match carry no monitored key at all
+    let response = check(engine, "What is the capital of France?").await;
+    let wire = serde_json::to_value(response.decision_evidence.expect("evidence")).unwrap();
+    assert!(wire.get("monitored_firewall_matches").is_none());
+}
+
+#[tokio::test]
+async fn promotion_blocks_the_same_prompt_on_the_next_request() {
+    let app = app(monitor_rules(Vec::new())).await;
+    let (engine, router, storage) = (&app.engine, app.router(), &app.storage);
+    assert_eq!(
+        check(engine, CODENAME_PROMPT).await.status,
+        WorkflowStatus::Completed
+    );
+
+    let (status, body) = promote(&router, MONITOR_RULE).await;
+    assert_eq!(status, StatusCode::OK);
+    let promotion: RulePromotionResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(promotion.rule_id, MONITOR_RULE);
+    assert_ne!(promotion.previous_hash, promotion.current_hash);
+
+    let response = check(engine, CODENAME_PROMPT).await;
+    assert_eq!(response.status, WorkflowStatus::BlockedByFirewall);
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(evidence.firewall_matched_rules, [MONITOR_RULE]);
+    assert!(evidence.monitored_firewall_matches.is_empty());
+
+    let change = payloads(storage)
+        .into_iter()
+        .find(|payload| payload["action"] == "firewall_rule_promoted")
+        .expect("configuration change recorded");
+    assert_eq!(change["event_type"], "configuration_change");
+    assert_eq!(change["previous_hash"], promotion.previous_hash.as_str());
+    assert_eq!(change["new_hash"], promotion.current_hash.as_str());
+    assert!(change["detail"].as_str().unwrap().contains(MONITOR_RULE));
+
+    // Already enforcing, and a rule that does not exist
+    assert_eq!(promote(&router, MONITOR_RULE).await.0, StatusCode::CONFLICT);
+    assert_eq!(promote(&router, "PFW-NOPE").await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn promotion_needs_the_admin_token() {
+    let app = app(monitor_rules(Vec::new())).await;
+    let (engine, router) = (&app.engine, app.router());
+    let before = engine.firewall_service().rules_fingerprint();
+
+    for token in [None, Some("wrong-token")] {
+        let (status, _) = promote_with(&router, MONITOR_RULE, token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{token:?}");
+    }
+    assert_eq!(engine.firewall_service().rules_fingerprint(), before);
+    assert_eq!(
+        check(engine, CODENAME_PROMPT).await.status,
+        WorkflowStatus::Completed
+    );
+}
+
+#[tokio::test]
+async fn assertions_treat_monitor_rules_as_non_blocking() {
+    // An assertion expecting allow holds while the rule only monitors
+    let rules = monitor_rules(vec![RuleAssertion {
+        prompt: CODENAME_PROMPT.to_owned(),
+        expect: ExpectedAction::Allow,
+    }]);
+    let app = app(rules).await;
+    let (engine, router) = (&app.engine, app.router());
+    let before = engine.firewall_service().rules_fingerprint();
+
+    let (status, body) = promote(&router, MONITOR_RULE).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["failed_assertions"][0]["prompt"], CODENAME_PROMPT);
+    assert_eq!(engine.firewall_service().rules_fingerprint(), before);
+    assert_eq!(
+        check(engine, CODENAME_PROMPT).await.status,
+        WorkflowStatus::Completed
+    );
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "server")]
+
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use chrono::Utc;
+use tower::ServiceExt;
+
+use prompt_sentinel::modules::config_management::dtos::ConfigSnapshot;
+use prompt_sentinel::modules::prompt_firewall::dtos::{FirewallAction, RuleAssertionReport};
+use prompt_sentinel::modules::prompt_firewall::rules::{
+    CompiledFirewallRules, ExpectedAction, FirewallRulesConfig, RuleAssertion, RuleEntry,
+    RulesLoadError,
+};
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::test_support::TestApp;
+
+const CODENAME_PROMPT: &str = "Tell me about project bluefin";
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+fn assertion(prompt: &str, expect: ExpectedAction) -> RuleAssertion {
+    RuleAssertion {
+        prompt: prompt.to_owned(),
+        expect,
+    }
+}
+
+/// Default rules plus a codename rule, asserting it blocks
+fn codename_rules() -> FirewallRulesConfig {
+    let mut rules = FirewallRulesConfig::default();
+    rules
+        .block_rules
+        .push(RuleEntry::new("PFW-TEST-001", "project bluefin"));
+    rules.assertions = vec![
+        assertion(CODENAME_PROMPT, ExpectedAction::Block),
+        assertion("summarize this report", ExpectedAction::Allow),
+    ];
+    rules
+}
+
+fn write_rules(rules: &FirewallRulesConfig) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("firewall_rules_{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, serde_json::to_string(rules).unwrap()).unwrap();
+    path
+}
+
+async fn app(firewall: PromptFirewallService) -> TestApp {
+    TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .with_firewall(firewall)
+        .build()
+        .await
+        .expect("test app")
+}
+
+async fn send(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    body: Option<String>,
+) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"));
+    if body.is_some() {
+        request = request.header(header::CONTENT_TYPE, "application/json");
+    }
+    let response = router
+        .clone()
+        .oneshot(
+            request
+                .body(body.map(Body::from).unwrap_or_default())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    (
+        status,
+        to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec(),
+    )
+}
+
+#[tokio::test]
+async fn reload_activates_rules_whose_assertions_pass() {
+    let firewall = PromptFirewallService::default();
+    let before = firewall.rules_fingerprint();
+    let path = write_rules(&codename_rules());
+
+    firewall.reload_rules(&path).expect("assertions hold");
+    std::fs::remove_file(&path).ok();
+
+    assert_ne!(firewall.rules_fingerprint(), before);
+    assert_eq!(
+        firewall.inspect_local(CODENAME_PROMPT).action,
+        FirewallAction::Block
+    );
+    let report = firewall.test_rules();
+    assert_eq!((report.total, report.passed), (2, 2));
+    assert!(report.failures.is_empty());
+}
+
+#[tokio::test]
+async fn reload_rejects_rules_whose_assertions_fail() {
+    let firewall = PromptFirewallService::default();
+    let before = firewall.rules_fingerprint();
+    let mut rules = codename_rules();
+    // The codename rule was "fixed" into a pattern that no longer matches
+    rules.block_rules.last_mut().unwrap().pattern = "project blue-fin leak".to_owned();
+    rules.fuzzy_matching.enabled = false;
+    let path = write_rules(&rules);
+
+    let error = firewall.reload_rules(&path).expect_err("assertion fails");
+    std::fs::remove_file(&path).ok();
+
+    let RulesLoadError::AssertionsFailed(failures) = error else {
+        panic!("expected assertion failures, got {error}");
+    };
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].prompt, CODENAME_PROMPT);
+    assert_eq!(failures[0].expected, ExpectedAction::Block);
+    assert_eq!(failures[0].actual, FirewallAction::Allow);
+    assert_eq!(firewall.rules_fingerprint(), before);
+}
+
+#[tokio::test]
+async fn restore_returns_the_failing_assertions() {
+    let app = app(PromptFirewallService::default()).await;
+    let before = app.engine.firewall_service().rules_fingerprint();
+    let router = app.router();
+
+    let (status, body) = send(&router, "GET", "/api/config/snapshot", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let snapshot: ConfigSnapshot = serde_json::from_slice(&body).unwrap();
+    let mut config = snapshot.config;
+    config
+        .firewall_rules
+        .assertions
+        .push(assertion("summarize this report", ExpectedAction::Block));
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/api/config/restore",
+        Some(serde_json::to_string(&ConfigSnapshot::new(config)).unwrap()),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let failures = body["failed_assertions"]
+        .as_array()
+        .expect("failures listed");
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0]["prompt"], "summarize this report");
+    assert_eq!(failures[0]["expected"], "block");
+    assert_eq!(failures[0]["actual"], "allow");
+    assert_eq!(app.engine.firewall_service().rules_fingerprint(), before);
+}
+
+#[tokio::test]
+async fn test_endpoint_reports_assertions_broken_by_expiry() {
+    let mut rules = codename_rules();
+    rules.block_rules.last_mut().unwrap().expires_at =
+        Some(Utc::now() + chrono::Duration::milliseconds(300));
+    let firewall = PromptFirewallService::default()
+        .with_rules(CompiledFirewallRules::compile(rules).expect("assertions hold while active"));
+    let router = app(firewall).await.router();
+
+    let (status, body) = send(&router, "POST", "/api/firewall/rules/test", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let report: RuleAssertionReport = serde_json::from_slice(&body).unwrap();
+    assert_eq!((report.total, report.passed), (2, 2));
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let (status, body) = send(&router, "POST", "/api/firewall/rules/test", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let report: RuleAssertionReport = serde_json::from_slice(&body).unwrap();
+    assert_eq!((report.total, report.passed), (2, 1));
+    assert_eq!(report.failures[0].prompt, CODENAME_PROMPT);
+}
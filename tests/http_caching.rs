@@ -0,0 +1,118 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use reqwest::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::http_cache::dtos::CachePolicy;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+async fn app() -> TestApp {
+    let settings = AppSettings {
+        http_cache: CachePolicy {
+            compliance_config_max_age_secs: 10,
+            summary_max_age_secs: 30,
+        },
+        ..AppSettings::default()
+    };
+    TestApp::builder()
+        .with_settings(settings)
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .expect("test app")
+}
+
+/// Fetch `path` fresh, returning its tag and `Cache-Control`
+async fn fetch(server: &TestServer, path: &str) -> (String, String) {
+    let response = server.get(path).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let header = |name| response.headers()[name].to_str().unwrap().to_owned();
+    let tagged = (header(ETAG), header(CACHE_CONTROL));
+    let _: Value = response.json().await.unwrap();
+    tagged
+}
+
+async fn revalidate(server: &TestServer, path: &str, etag: &str) -> reqwest::Response {
+    server
+        .get(path)
+        .header(IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn compliance_config_is_revalidated_until_it_is_updated() {
+    let app = app().await;
+    let server = app.serve().await.unwrap();
+    let path = "/api/compliance/config";
+
+    let (etag, cache_control) = fetch(&server, path).await;
+    assert_eq!(cache_control, "private, max-age=10");
+
+    let response = revalidate(&server, path, &etag).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[ETAG], etag.as_str());
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    // Writing back the keywords on disk leaves the shared configuration as other tests
+    // expect it, but still counts as an update
+    let keywords: Value =
+        serde_json::from_str(&std::fs::read_to_string("config/eu_risk_keywords.json").unwrap())
+            .unwrap();
+    let response = server
+        .post(path)
+        .json(&json!({
+            "risk_thresholds": {
+                "unacceptable_keywords": keywords["unacceptable"],
+                "high_risk_keywords": keywords["high"],
+                "limited_risk_keywords": keywords["limited"],
+            },
+            "documentation_requirements": null,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = revalidate(&server, path, &etag).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = response.headers()[ETAG].to_str().unwrap().to_owned();
+    assert_ne!(updated, etag);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "success");
+
+    let response = revalidate(&server, path, &format!("\"stale\", W/{updated}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn system_summary_is_revalidated_until_maintenance_changes() {
+    let app = app().await;
+    let server = app.serve().await.unwrap();
+    let path = "/api/admin/summary";
+
+    let (etag, cache_control) = fetch(&server, path).await;
+    assert_eq!(cache_control, "private, max-age=30");
+
+    let response = revalidate(&server, path, &etag).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = server
+        .post("/api/admin/maintenance")
+        .json(&json!({ "enabled": false, "max_wait_ms": 1_000 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = revalidate(&server, path, &etag).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()[ETAG], etag.as_str());
+    let summary: Value = response.json().await.unwrap();
+    assert_eq!(summary["maintenance"]["settings"]["max_wait_ms"], 1_000);
+}
@@ -0,0 +1,181 @@
+#![cfg(all(feature = "http-client", feature = "server"))]
+
+use std::time::Duration;
+
+use prompt_sentinel::client::{ClientError, PromptSentinelClient};
+use prompt_sentinel::modules::audit::storage::AuditTrailRequest;
+use prompt_sentinel::modules::maintenance::dtos::MaintenanceRequest;
+use prompt_sentinel::modules::semantic_detection::dtos::{SemanticRiskLevel, SemanticScanRequest};
+use prompt_sentinel::test_support::{TestApp, TestServer};
+use prompt_sentinel::{ComplianceRequest, WorkflowStatus};
+
+const ADMIN_TOKEN: &str = "client-test-admin";
+
+/// Serve the real router on a random local port, until the server drops
+async fn spawn_server() -> TestServer {
+    TestApp::builder()
+        .with_admin_token(ADMIN_TOKEN)
+        .build()
+        .await
+        .expect("test app")
+        .serve()
+        .await
+        .unwrap()
+}
+
+async fn set_maintenance(base_url: &str, request: MaintenanceRequest) {
+    let response = reqwest::Client::new()
+        .post(format!("{base_url}/api/admin/maintenance"))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success(), "{}", response.status());
+}
+
+fn maintenance(enabled: bool, max_queue: usize, max_wait_ms: u64) -> MaintenanceRequest {
+    MaintenanceRequest {
+        enabled,
+        message: Some("Mistral maintenance".to_owned()),
+        max_queue: Some(max_queue),
+        max_wait_ms: Some(max_wait_ms),
+    }
+}
+
+fn request(prompt: &str) -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: prompt.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[tokio::test]
+async fn check_and_audit_trail_share_the_header_correlation_id() {
+    let server = spawn_server().await;
+    let base_url = server.base_url.clone();
+    let client = PromptSentinelClient::new(base_url, None).with_correlation_id("client-corr-0001");
+
+    let response = client
+        .check(request("Summarize this release note."))
+        .await
+        .expect("check");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.correlation_id, "client-corr-0001");
+
+    let trail = client
+        .audit_trail(AuditTrailRequest {
+            limit: Some(10),
+            offset: None,
+            start_time: None,
+            end_time: None,
+            correlation_id: Some("client-corr-0001".to_owned()),
+            cursor: None,
+        })
+        .await
+        .expect("audit trail");
+    assert_eq!(trail.total_count, 1);
+    assert_eq!(trail.records[0].correlation_id, "client-corr-0001");
+}
+
+#[tokio::test]
+async fn semantic_scan_and_health_round_trip() {
+    let server = spawn_server().await;
+    let base_url = server.base_url.clone();
+    let client = PromptSentinelClient::new(base_url, Some("unused-key".to_owned()));
+
+    client.health().await.expect("healthy");
+    let result = client
+        .semantic_scan(SemanticScanRequest {
+            text: "What is the capital of France?".to_owned(),
+            detected_language: None,
+            pre_translated_text: None,
+        })
+        .await
+        .expect("semantic scan");
+    assert_eq!(result.risk_level, SemanticRiskLevel::Low);
+}
+
+#[tokio::test]
+async fn rejected_input_maps_to_a_non_retryable_api_error() {
+    let server = spawn_server().await;
+    let base_url = server.base_url.clone();
+    let client = PromptSentinelClient::new(base_url, None);
+
+    let error = client
+        .semantic_scan(SemanticScanRequest {
+            text: "   ".to_owned(),
+            detected_language: None,
+            pre_translated_text: None,
+        })
+        .await
+        .expect_err("empty text");
+
+    let ClientError::Api {
+        code,
+        message,
+        retryable,
+    } = &error
+    else {
+        panic!("expected an API error, got {error}");
+    };
+    assert_eq!(*code, 422);
+    assert_eq!(message, "text must not be empty");
+    assert!(!retryable);
+}
+
+#[tokio::test]
+async fn maintenance_rejections_are_retried_until_they_clear() {
+    let server = spawn_server().await;
+    let base_url = server.base_url.clone();
+    // A zero-length queue rejects immediately with 503
+    set_maintenance(&base_url, maintenance(true, 0, 1_000)).await;
+
+    let exhausted = PromptSentinelClient::new(base_url.clone(), None)
+        .with_retries(1, Duration::from_millis(10))
+        .check(request("Summarize this release note."))
+        .await
+        .expect_err("still in maintenance");
+    assert!(matches!(
+        exhausted,
+        ClientError::Api {
+            code: 503,
+            retryable: true,
+            ..
+        }
+    ));
+    assert!(exhausted.is_retryable());
+
+    let toggle_url = base_url.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        set_maintenance(&toggle_url, maintenance(false, 0, 1_000)).await;
+    });
+    let response = PromptSentinelClient::new(base_url, None)
+        .with_retries(6, Duration::from_millis(50))
+        .check(request("Summarize this release note."))
+        .await
+        .expect("succeeds once maintenance ends");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+}
+
+#[tokio::test]
+async fn overall_timeout_bounds_a_stalled_call() {
+    let server = spawn_server().await;
+    let base_url = server.base_url.clone();
+    // Requests are held in the maintenance queue until it is disabled
+    set_maintenance(&base_url, maintenance(true, 5, 10_000)).await;
+
+    let error = PromptSentinelClient::new(base_url.clone(), None)
+        .with_timeout(Duration::from_millis(200))
+        .check(request("Summarize this release note."))
+        .await
+        .expect_err("held in the queue");
+    assert!(matches!(error, ClientError::Timeout(timeout) if timeout.as_millis() == 200));
+
+    set_maintenance(&base_url, maintenance(false, 5, 10_000)).await;
+}
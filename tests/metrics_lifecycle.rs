@@ -0,0 +1,72 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use prompt_sentinel::modules::telemetry::metrics::get_metrics;
+use prompt_sentinel::modules::telemetry::prometheus::{
+    MetricsListener, MetricsSetupError, install_prometheus_recorder, render_prometheus,
+};
+
+#[test]
+fn installing_twice_returns_the_same_recorder() {
+    let first = install_prometheus_recorder().expect("first installation");
+    let second = install_prometheus_recorder().expect("second installation");
+
+    get_metrics().increment_sanitize_probing_escalations();
+    assert!(
+        first
+            .render()
+            .contains("sanitize_probing_escalations_total")
+    );
+    assert!(
+        second
+            .render()
+            .contains("sanitize_probing_escalations_total")
+    );
+    assert!(
+        render_prometheus()
+            .expect("recorder installed")
+            .contains("sanitize_probing_escalations_total")
+    );
+}
+
+#[tokio::test]
+async fn the_listener_serves_on_the_configured_address_until_shut_down() {
+    let handle = install_prometheus_recorder().expect("recorder");
+    get_metrics().increment_sanitize_probing_escalations();
+
+    let listener = MetricsListener::bind("127.0.0.1:0", handle)
+        .await
+        .expect("listener");
+    let addr = listener.local_addr();
+    assert!(addr.ip().is_loopback());
+    let body = reqwest::get(format!("http://{addr}/metrics"))
+        .await
+        .expect("scrape")
+        .text()
+        .await
+        .unwrap();
+    assert!(
+        body.contains("sanitize_probing_escalations_total"),
+        "{body}"
+    );
+
+    listener.shutdown().await;
+    assert!(
+        reqwest::get(format!("http://{addr}/metrics"))
+            .await
+            .is_err()
+    );
+    // The port is released
+    tokio::net::TcpListener::bind(addr)
+        .await
+        .expect("port free after shutdown");
+}
+
+#[tokio::test]
+async fn an_invalid_address_is_reported() {
+    let handle = install_prometheus_recorder().expect("recorder");
+    let error = MetricsListener::bind("not-an-address", handle)
+        .await
+        .err()
+        .expect("invalid address");
+    assert!(matches!(error, MetricsSetupError::InvalidAddress { .. }));
+}
@@ -0,0 +1,35 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+//! A recorder installed by someone else; in its own binary, since it is process-wide
+
+use std::time::Duration;
+
+use prompt_sentinel::PromptSentinelServer;
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::telemetry::prometheus::{
+    MetricsSetupError, install_prometheus_recorder, render_prometheus,
+};
+use prompt_sentinel::test_support::TestApp;
+
+#[tokio::test]
+async fn a_foreign_recorder_disables_metrics_without_failing_startup() {
+    metrics::set_global_recorder(metrics::NoopRecorder).expect("first recorder");
+
+    assert!(matches!(
+        install_prometheus_recorder(),
+        Err(MetricsSetupError::RecorderInUse)
+    ));
+    assert_eq!(render_prometheus(), None);
+
+    let settings = AppSettings {
+        server_port: 0,
+        metrics_addr: "127.0.0.1:0".to_owned(),
+        ..AppSettings::default()
+    };
+    let engine = TestApp::new().await.engine;
+    let server = PromptSentinelServer::new(settings, engine);
+    server
+        .start_with_shutdown(tokio::time::sleep(Duration::from_millis(200)))
+        .await
+        .expect("server runs without metrics");
+}
@@ -0,0 +1,146 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde_json::json;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    HttpMistralClient, MistralClient, MistralClientError,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::EmbeddingRequest;
+
+type Statuses = Arc<Mutex<VecDeque<StatusCode>>>;
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+/// Answer with the queued statuses in turn, then 200 once the queue is empty
+async fn scripted_server(statuses: &[StatusCode]) -> HttpMistralClient {
+    let queue: Statuses = Arc::new(Mutex::new(statuses.iter().copied().collect()));
+    let next = |State(queue): State<Statuses>| async move {
+        let status = queue.lock().unwrap().pop_front().unwrap_or(StatusCode::OK);
+        let body = json!({
+            "data": [{ "id": "mistral-embed", "embedding": [0.5, 0.5] }],
+        });
+        (status, axum::Json(body))
+    };
+    let router = Router::new()
+        .route("/v1/embeddings", post(next))
+        .route("/v1/models", get(next))
+        .with_state(queue);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    HttpMistralClient::new(format!("http://{address}"), "test-key")
+        .unwrap()
+        .with_retry_delay(Duration::from_millis(1))
+}
+
+/// Value of the one series of `name` carrying every label in `labels`
+fn sample(name: &str, labels: &[(&str, &str)]) -> f64 {
+    let rendered = recorder().render();
+    let line = rendered
+        .lines()
+        .find(|line| {
+            line.starts_with(&format!("{name}{{"))
+                && labels
+                    .iter()
+                    .all(|(key, value)| line.contains(&format!("{key}=\"{value}\"")))
+        })
+        .unwrap_or_else(|| panic!("no {name} {labels:?} in:\n{rendered}"));
+    line.rsplit(' ').next().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn rate_limited_attempt_is_counted_and_retried() {
+    recorder();
+    let client = scripted_server(&[StatusCode::TOO_MANY_REQUESTS]).await;
+
+    let response = client
+        .embeddings(EmbeddingRequest {
+            model: "mistral-embed".to_owned(),
+            input: "hello".to_owned(),
+        })
+        .await
+        .expect("second attempt succeeds");
+    assert_eq!(response.vector, [0.5, 0.5]);
+
+    let endpoint = ("endpoint", "embeddings");
+    assert_eq!(
+        sample(
+            "mistral_api_calls_total",
+            &[endpoint, ("status_class", "4xx")]
+        ),
+        1.0
+    );
+    assert_eq!(
+        sample(
+            "mistral_api_calls_total",
+            &[endpoint, ("status_class", "2xx")]
+        ),
+        1.0
+    );
+    assert_eq!(sample("mistral_api_retries_total", &[endpoint]), 1.0);
+    assert_eq!(sample("mistral_api_consecutive_failures", &[endpoint]), 0.0);
+    assert_eq!(
+        sample(
+            "mistral_api_latency_seconds_count",
+            &[endpoint, ("scope", "attempt")]
+        ),
+        2.0
+    );
+    assert_eq!(
+        sample(
+            "mistral_api_latency_seconds_count",
+            &[endpoint, ("scope", "call")]
+        ),
+        1.0
+    );
+}
+
+#[tokio::test]
+async fn exhausted_retries_leave_a_failure_streak() {
+    recorder();
+    let client = scripted_server(&[StatusCode::SERVICE_UNAVAILABLE; 4]).await;
+
+    let error = client.list_models().await.expect_err("every attempt fails");
+    assert!(matches!(
+        error,
+        MistralClientError::ApiError { status: 503, .. }
+    ));
+
+    let endpoint = ("endpoint", "models");
+    assert_eq!(
+        sample(
+            "mistral_api_calls_total",
+            &[endpoint, ("status_class", "5xx")]
+        ),
+        4.0
+    );
+    assert_eq!(sample("mistral_api_retries_total", &[endpoint]), 3.0);
+    assert_eq!(sample("mistral_api_consecutive_failures", &[endpoint]), 4.0);
+    assert_eq!(
+        sample(
+            "mistral_api_latency_seconds_count",
+            &[endpoint, ("scope", "call")]
+        ),
+        1.0
+    );
+
+    // The next success ends the streak
+    client.list_models().await.expect("server recovered");
+    assert_eq!(sample("mistral_api_consecutive_failures", &[endpoint]), 0.0);
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "server")]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use serde_json::json;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    HttpMistralClient, MistralApiPaths, MistralClient, MistralClientError,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatMessage, EmbeddingRequest, ModerationRequest,
+};
+
+const PREFIX: &str = "/gateway";
+
+type Hits = Arc<Mutex<Vec<String>>>;
+
+/// A gateway serving Mistral's routes under `/gateway`, with moderation and embeddings
+/// moved to its own paths; anything else is a 404
+async fn gateway() -> (String, Hits) {
+    let hits = Hits::default();
+    let router = Router::new()
+        .fallback(|State(hits): State<Hits>, uri: Uri| async move {
+            let path = uri.path().to_owned();
+            hits.lock().unwrap().push(path.clone());
+            let body = match path.strip_prefix(PREFIX) {
+                Some("/v1/models") => json!({ "data": [{ "id": "mistral-large-latest" }] }),
+                Some("/v1/chat/completions") => json!({
+                    "model": "mistral-large-latest",
+                    "choices": [{ "message": { "content": "hello" } }],
+                }),
+                Some("/moderate") => json!({
+                    "results": [{ "categories": {}, "category_scores": {} }],
+                }),
+                Some("/embed") => json!({ "data": [{ "embedding": [0.25, 0.75] }] }),
+                _ => return (StatusCode::NOT_FOUND, axum::Json(json!("no route"))),
+            };
+            (StatusCode::OK, axum::Json(body))
+        })
+        .with_state(hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{address}"), hits)
+}
+
+fn client(base_url: &str) -> HttpMistralClient {
+    HttpMistralClient::new(base_url, "test-key")
+        .expect("valid base URL")
+        .with_retry_delay(Duration::from_millis(1))
+}
+
+fn chat() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "mistral-large-latest".to_owned(),
+        messages: vec![ChatMessage {
+            role: "user".to_owned(),
+            content: "hi".to_owned(),
+        }],
+        safe_prompt: false,
+        temperature: None,
+        max_tokens: None,
+        random_seed: None,
+    }
+}
+
+fn hits(hits: &Hits) -> Vec<String> {
+    std::mem::take(&mut *hits.lock().unwrap())
+}
+
+#[tokio::test]
+async fn a_base_url_with_a_prefix_and_a_trailing_v1_reaches_the_right_routes() {
+    let (root, seen) = gateway().await;
+
+    for base_url in [
+        format!("{root}{PREFIX}"),
+        format!("{root}{PREFIX}/"),
+        format!("{root}{PREFIX}/v1"),
+        format!("{root}{PREFIX}/v1/"),
+    ] {
+        let client = client(&base_url);
+        let models = client.list_models().await.expect(&base_url);
+        assert_eq!(models.models, ["mistral-large-latest"]);
+        let response = client.chat_completion(chat()).await.expect(&base_url);
+        assert_eq!(response.output_text, "hello");
+        assert_eq!(
+            hits(&seen),
+            ["/gateway/v1/models", "/gateway/v1/chat/completions"],
+            "{base_url}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn path_overrides_route_each_endpoint_to_the_gateway() {
+    let (root, seen) = gateway().await;
+    let client = client(&format!("{root}{PREFIX}")).with_paths(MistralApiPaths {
+        moderation: "/moderate".to_owned(),
+        embeddings: "embed".to_owned(),
+        ..MistralApiPaths::default()
+    });
+
+    let moderation = client
+        .moderate(ModerationRequest {
+            model: Some("mistral-moderation-latest".to_owned()),
+            input: "hello".to_owned(),
+        })
+        .await
+        .expect("moderation through the override");
+    assert!(!moderation.flagged);
+    let embedding = client
+        .embeddings(EmbeddingRequest {
+            model: "mistral-embed".to_owned(),
+            input: "hello".to_owned(),
+        })
+        .await
+        .expect("embeddings through the override");
+    assert_eq!(embedding.vector, [0.25, 0.75]);
+    // Paths left alone keep Mistral's routes
+    client.list_models().await.expect("default models path");
+
+    assert_eq!(
+        hits(&seen),
+        ["/gateway/moderate", "/gateway/embed", "/gateway/v1/models"]
+    );
+}
+
+#[tokio::test]
+async fn a_wrong_base_url_fails_once_with_a_hint() {
+    let (root, seen) = gateway().await;
+    let client = client(&format!("{root}/v1"));
+
+    let error = client
+        .list_models()
+        .await
+        .expect_err("nothing is served there");
+    let MistralClientError::ApiError { status, message } = &error else {
+        panic!("expected an API error, got {error}");
+    };
+    assert_eq!(*status, 404);
+    assert!(message.contains(&format!("{root}/v1/models")), "{message}");
+    assert!(message.contains("MISTRAL_BASE_URL"), "{message}");
+    // A missing route is not retried
+    assert_eq!(hits(&seen).len(), 1);
+}
+
+#[test]
+fn a_base_url_that_is_not_http_is_refused_at_construction() {
+    let error = HttpMistralClient::new("ftp://api.mistral.ai", "test-key")
+        .err()
+        .expect("ftp is refused");
+    assert!(error.to_string().contains("ftp"), "{error}");
+}
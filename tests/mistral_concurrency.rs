@@ -0,0 +1,236 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use tower::ServiceExt;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralClient, MistralClientError, MistralEndpoint, MockMistralClient,
+};
+use prompt_sentinel::modules::mistral_ai::concurrency::{
+    LimitedOperation, MistralConcurrencyLimits,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationRequest,
+    ModerationResponse, TranslationRequest, TranslationResponse,
+};
+use prompt_sentinel::modules::mistral_ai::service::{MistralService, MistralServiceError};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{ComplianceRequest, WorkflowError};
+
+/// Tracks how many chat and moderation calls are running at once
+#[derive(Default)]
+struct Gauge {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Gauge {
+    fn enter(&self) {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+    }
+
+    fn exit(&self) {
+        self.current.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Default)]
+struct ProbeClient {
+    base: MockMistralClient,
+    chat: Arc<Gauge>,
+    moderation: Arc<Gauge>,
+}
+
+#[async_trait]
+impl MistralClient for ProbeClient {
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralClientError> {
+        self.chat.enter();
+        let response = self.base.chat_completion(request).await;
+        self.chat.exit();
+        response
+    }
+
+    async fn moderate(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, MistralClientError> {
+        self.moderation.enter();
+        let response = self.base.moderate(request).await;
+        self.moderation.exit();
+        response
+    }
+
+    async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, MistralClientError> {
+        self.base.embeddings(request).await
+    }
+
+    async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.base.list_models().await
+    }
+
+    async fn detect_language(
+        &self,
+        request: LanguageDetectionRequest,
+    ) -> Result<LanguageDetectionResponse, MistralClientError> {
+        self.base.detect_language(request).await
+    }
+
+    async fn translate_text(
+        &self,
+        request: TranslationRequest,
+    ) -> Result<TranslationResponse, MistralClientError> {
+        self.base.translate_text(request).await
+    }
+}
+
+/// Chat is slow enough that a second request queues behind the first
+async fn saturated_chat() -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            mistral_concurrency: MistralConcurrencyLimits {
+                max_concurrent_chat: 1,
+                max_wait_ms: 20,
+                ..MistralConcurrencyLimits::default()
+            },
+            ..AppSettings::default()
+        })
+        .with_mock(MockMistralClient::default().delay_chat(Duration::from_millis(300)))
+        .build()
+        .await
+        .expect("test app")
+}
+
+#[tokio::test]
+async fn burst_never_exceeds_per_operation_caps() {
+    let probe = ProbeClient::default();
+    probe
+        .base
+        .set_delay(MistralEndpoint::Chat, Duration::from_millis(20));
+    probe
+        .base
+        .set_delay(MistralEndpoint::Moderation, Duration::from_millis(20));
+    let service = MistralService::new(
+        Arc::new(probe.clone()),
+        "mistral-large-latest",
+        Some("mistral-moderation-latest".to_owned()),
+        "mistral-embed",
+    )
+    .with_concurrency_limits(MistralConcurrencyLimits {
+        max_concurrent_chat: 2,
+        max_concurrent_moderation: 8,
+        max_concurrent_embeddings: 4,
+        max_wait_ms: 10_000,
+    });
+
+    let mut tasks = Vec::new();
+    for index in 0..24 {
+        let chat = service.clone();
+        tasks.push(tokio::spawn(async move {
+            chat.generate_text(format!("prompt {index}"), true)
+                .await
+                .map(|_| ())
+        }));
+        let moderation = service.clone();
+        tasks.push(tokio::spawn(async move {
+            moderation
+                .moderate_text(format!("prompt {index}"))
+                .await
+                .map(|_| ())
+        }));
+    }
+    for task in tasks {
+        task.await
+            .unwrap()
+            .expect("every call fits in the wait budget");
+    }
+
+    assert_eq!(probe.chat.peak(), 2);
+    // Moderation is not held back by the saturated chat limit
+    assert!(probe.moderation.peak() > 2, "{}", probe.moderation.peak());
+    assert!(probe.moderation.peak() <= 8, "{}", probe.moderation.peak());
+}
+
+#[tokio::test]
+async fn saturated_generation_fails_with_retry_hint() {
+    let engine = Arc::new(saturated_chat().await.engine);
+    let request = || ComplianceRequest {
+        correlation_id: None,
+        prompt: "Summarize this release note.".to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    };
+
+    let first = tokio::spawn({
+        let engine = engine.clone();
+        async move { engine.process(request()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let error = engine
+        .process(request())
+        .await
+        .expect_err("no chat slot frees up in time");
+
+    assert!(matches!(
+        error,
+        WorkflowError::Mistral(MistralServiceError::ConcurrencyLimitTimeout {
+            operation: LimitedOperation::Chat,
+            ..
+        })
+    ));
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(20)));
+    first.await.unwrap().expect("first request completes");
+}
+
+#[tokio::test]
+async fn endpoint_returns_503_with_retry_after() {
+    let router = saturated_chat().await.router();
+    let check = || {
+        let router = router.clone();
+        async move {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/compliance/check")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "prompt": "Summarize this release note." }).to_string(),
+                ))
+                .unwrap();
+            router.oneshot(request).await.unwrap()
+        }
+    };
+
+    let first = tokio::spawn(check());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let response = check().await;
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()),
+        Some("1")
+    );
+    assert_eq!(first.await.unwrap().status(), StatusCode::OK);
+}
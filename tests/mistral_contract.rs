@@ -0,0 +1,194 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    HttpMistralClient, MistralClient, MistralClientError,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatMessage, EmbeddingRequest, ModerationRequest, TokenUsage,
+};
+
+const CHAT: &str = include_str!("fixtures/mistral/chat_completion.json");
+const CHAT_PARTS: &str = include_str!("fixtures/mistral/chat_completion_parts.json");
+const CHAT_LEGACY: &str = include_str!("fixtures/mistral/chat_completion_legacy.json");
+const CHAT_DRIFTED: &str = include_str!("fixtures/mistral/chat_completion_drifted.json");
+const MODERATION: &str = include_str!("fixtures/mistral/moderation.json");
+const MODERATION_LEGACY: &str = include_str!("fixtures/mistral/moderation_legacy.json");
+const EMBEDDINGS: &str = include_str!("fixtures/mistral/embeddings.json");
+const EMBEDDINGS_DRIFTED: &str = include_str!("fixtures/mistral/embeddings_drifted.json");
+const MODELS: &str = include_str!("fixtures/mistral/models.json");
+
+/// A client for a server answering every request with `fixture`, and the number of
+/// requests it received
+async fn serving(fixture: &'static str) -> (HttpMistralClient, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let router = Router::new().fallback(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "application/json")],
+                fixture,
+            )
+                .into_response()
+        }
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    let client = HttpMistralClient::new(format!("http://{address}"), "test-key")
+        .unwrap()
+        .with_retry_delay(Duration::from_millis(1));
+    (client, hits)
+}
+
+fn chat() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "mistral-large-latest".to_owned(),
+        messages: vec![ChatMessage {
+            role: "user".to_owned(),
+            content: "Why write unit tests?".to_owned(),
+        }],
+        safe_prompt: false,
+        temperature: None,
+        max_tokens: None,
+        random_seed: None,
+    }
+}
+
+fn moderation() -> ModerationRequest {
+    ModerationRequest {
+        model: Some("mistral-moderation-latest".to_owned()),
+        input: "some text".to_owned(),
+    }
+}
+
+fn invalid_response(error: MistralClientError) -> String {
+    match error {
+        MistralClientError::InvalidResponse(message) => message,
+        other => panic!("expected an invalid response, got {other}"),
+    }
+}
+
+#[tokio::test]
+async fn current_chat_completions_are_read() {
+    let (client, _) = serving(CHAT).await;
+    let response = client.chat_completion(chat()).await.unwrap();
+    assert_eq!(response.output_text, "Unit tests catch regressions early.");
+    assert_eq!(response.model, "mistral-large-2411");
+    assert_eq!(
+        response.usage,
+        Some(TokenUsage {
+            prompt_tokens: 12,
+            completion_tokens: 7,
+            total_tokens: 19,
+        })
+    );
+}
+
+#[tokio::test]
+async fn array_content_keeps_only_its_text_parts() {
+    let (client, _) = serving(CHAT_PARTS).await;
+    let response = client.chat_completion(chat()).await.unwrap();
+    assert_eq!(
+        response.output_text,
+        "Unit tests catch regressions early.\nThey also document intent."
+    );
+    assert_eq!(response.usage.unwrap().total_tokens, 42);
+}
+
+#[tokio::test]
+async fn legacy_chat_completions_without_model_or_usage_are_read() {
+    let (client, _) = serving(CHAT_LEGACY).await;
+    let response = client.chat_completion(chat()).await.unwrap();
+    assert_eq!(response.output_text, "Unit tests catch regressions early.");
+    assert_eq!(response.model, "mistral-large-latest");
+    assert_eq!(response.usage, None);
+}
+
+#[tokio::test]
+async fn moderation_without_a_flagged_field_is_flagged_by_its_categories() {
+    let (client, _) = serving(MODERATION).await;
+    let response = client.moderate(moderation()).await.unwrap();
+    assert!(response.flagged);
+    assert_eq!(response.categories, ["violence_and_threats"]);
+    assert_eq!(response.category_scores.len(), 9);
+    assert_eq!(response.category_scores["violence_and_threats"], 0.9312);
+}
+
+#[tokio::test]
+async fn legacy_moderation_tolerates_null_categories_and_scores() {
+    let (client, _) = serving(MODERATION_LEGACY).await;
+    let response = client.moderate(moderation()).await.unwrap();
+    assert!(response.flagged);
+    assert_eq!(response.categories, ["law"]);
+    assert_eq!(response.category_scores.len(), 1);
+}
+
+#[tokio::test]
+async fn embeddings_and_models_are_read() {
+    let (client, _) = serving(EMBEDDINGS).await;
+    let response = client
+        .embeddings(EmbeddingRequest {
+            model: "mistral-embed".to_owned(),
+            input: "text".to_owned(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.vector, [0.25, -0.5, 0.75]);
+
+    let (client, _) = serving(MODELS).await;
+    let models = client.list_models().await.unwrap();
+    assert_eq!(models.models, ["mistral-large-latest", "mistral-embed"]);
+}
+
+#[tokio::test]
+async fn a_drifted_chat_response_names_the_field_and_is_not_retried() {
+    let (client, hits) = serving(CHAT_DRIFTED).await;
+    let message = invalid_response(client.chat_completion(chat()).await.unwrap_err());
+    assert!(message.starts_with("chat response:"), "{message}");
+    assert!(message.contains("choices[0]"), "{message}");
+    assert!(message.contains("missing field `message`"), "{message}");
+    assert!(message.contains("\"output\":{"), "{message}");
+    // Generated text is cut short in the quoted body
+    assert!(
+        message.contains("\"Here is the confidential…\""),
+        "{message}"
+    );
+    assert!(!message.contains("must not be logged"), "{message}");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_drifted_embeddings_response_names_the_field_and_is_not_retried() {
+    let (client, hits) = serving(EMBEDDINGS_DRIFTED).await;
+    let error = client
+        .embeddings(EmbeddingRequest {
+            model: "mistral-embed".to_owned(),
+            input: "text".to_owned(),
+        })
+        .await
+        .unwrap_err();
+    let message = invalid_response(error);
+    assert!(message.starts_with("embeddings response:"), "{message}");
+    assert!(message.contains("data[0]"), "{message}");
+    assert!(message.contains("missing field `embedding`"), "{message}");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_body_that_is_not_json_is_reported_without_retrying() {
+    let (client, hits) = serving("<html>upstream gateway error</html>").await;
+    let message = invalid_response(client.list_models().await.unwrap_err());
+    assert!(message.starts_with("models response:"), "{message}");
+    assert!(message.contains("not JSON"), "{message}");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,261 @@
+#![cfg(all(
+    feature = "server",
+    feature = "sled-storage",
+    feature = "metrics-prometheus"
+))]
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde_json::Value;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::alerting::service::WebhookNotifier;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralEndpoint, MockMistralClient, ScriptedFailure,
+};
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::modules::model_watch::dtos::{
+    ModelAlertKind, ModelEventKind, ModelWatchConfig,
+};
+use prompt_sentinel::modules::model_watch::service::ModelWatch;
+use prompt_sentinel::modules::model_watch::storage::{
+    InMemoryModelHistory, ModelHistoryStore, SledModelHistory,
+};
+use prompt_sentinel::test_support::TestApp;
+
+const GENERATION: &str = "mistral-large-latest";
+const EMBEDDING: &str = "mistral-embed";
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+/// Every check sets the process-wide gauge, so tests take turns
+async fn turn() -> tokio::sync::MutexGuard<'static, ()> {
+    static CHECKS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    // Installed before any check, or the gauge goes unrecorded
+    recorder();
+    CHECKS.lock().await
+}
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+fn alerts_gauge() -> f64 {
+    recorder()
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix("model_watch_alerts "))
+        .and_then(|value| value.parse().ok())
+        .expect("model_watch_alerts gauge")
+}
+
+/// Collect every alert posted to the returned URL
+async fn webhook_receiver() -> (String, Received) {
+    let received = Received::default();
+    let router = Router::new()
+        .route(
+            "/alerts",
+            post(
+                |State(received): State<Received>, Json(alert): Json<Value>| async move {
+                    received.lock().unwrap().push(alert);
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{address}/alerts"), received)
+}
+
+fn models(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| (*id).to_owned()).collect()
+}
+
+fn watch(mock: &MockMistralClient, history: Arc<dyn ModelHistoryStore>) -> ModelWatch {
+    let mistral = MistralService::new(Arc::new(mock.clone()), GENERATION, None, EMBEDDING);
+    ModelWatch::new(mistral, history, ModelWatchConfig::default())
+}
+
+#[tokio::test]
+async fn a_vanished_model_and_an_announced_deprecation_raise_alerts_once() {
+    let _turn = turn().await;
+    let (url, received) = webhook_receiver().await;
+    let mock = MockMistralClient::default();
+    mock.set_models(models(&[GENERATION, EMBEDDING, "mistral-small-latest"]));
+    let watch = watch(&mock, Arc::new(InMemoryModelHistory::new()))
+        .with_notifier(Some(WebhookNotifier::new(url)));
+
+    // The first check only records what is listed
+    let status = watch.check().await;
+    assert!(status.alerts.is_empty());
+    assert_eq!(alerts_gauge(), 0.0);
+    let history = watch.history().unwrap();
+    assert_eq!(history.len(), 3);
+    assert!(history.iter().all(|model| model.present
+        && model.events.len() == 1
+        && model.events[0].kind == ModelEventKind::Appeared));
+
+    mock.set_models(models(&[
+        EMBEDDING,
+        "mistral-small-latest",
+        "mistral-large-2402-deprecated",
+    ]));
+    let status = watch.check().await;
+    let kinds: Vec<_> = status
+        .alerts
+        .iter()
+        .map(|alert| (alert.kind, alert.model.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (ModelAlertKind::ConfiguredModelMissing, GENERATION),
+            (
+                ModelAlertKind::DeprecationAnnounced,
+                "mistral-large-2402-deprecated"
+            ),
+        ]
+    );
+    assert_eq!(alerts_gauge(), 2.0);
+    let posted = received.lock().unwrap().clone();
+    assert_eq!(posted.len(), 2);
+    assert_eq!(posted[0]["alert"], "configured_model_missing");
+    assert_eq!(posted[0]["level"], "warning");
+    assert_eq!(posted[0]["details"]["model"], GENERATION);
+
+    let history = watch.history().unwrap();
+    let large = history.iter().find(|m| m.model == GENERATION).unwrap();
+    assert!(!large.present);
+    let kinds: Vec<_> = large.events.iter().map(|event| event.kind).collect();
+    assert_eq!(
+        kinds,
+        [ModelEventKind::Appeared, ModelEventKind::Disappeared]
+    );
+    assert!(large.events[0].at <= large.events[1].at);
+
+    // Alerts that are still active are neither posted again nor re-dated
+    let again = watch.check().await;
+    assert_eq!(again.alerts, status.alerts);
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    mock.set_models(models(&[GENERATION, EMBEDDING, "mistral-small-latest"]));
+    let cleared = watch.check().await;
+    assert!(cleared.alerts.is_empty());
+    assert_eq!(alerts_gauge(), 0.0);
+    assert_eq!(received.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn transient_listing_failures_change_nothing() {
+    let _turn = turn().await;
+    let (url, received) = webhook_receiver().await;
+    let mock = MockMistralClient::default().record_calls();
+    mock.set_models(models(&[EMBEDDING]));
+    let watch = watch(&mock, Arc::new(InMemoryModelHistory::new()))
+        .with_notifier(Some(WebhookNotifier::new(url)));
+    let before = watch.check().await;
+    assert_eq!(before.alerts.len(), 1);
+    assert_eq!(received.lock().unwrap().len(), 1);
+    let history = watch.history().unwrap();
+
+    mock.fail_next(MistralEndpoint::Models, 3, ScriptedFailure::unavailable());
+    for attempt in 1..=3 {
+        let status = watch.check().await;
+        assert_eq!(status.consecutive_failures, attempt);
+        assert!(status.last_error.is_some());
+        assert_eq!(status.alerts, before.alerts);
+        assert_eq!(status.last_success_at, before.last_success_at);
+    }
+    assert_eq!(mock.call_count(MistralEndpoint::Models), 4);
+    assert_eq!(received.lock().unwrap().len(), 1);
+    assert_eq!(watch.history().unwrap(), history);
+    assert_eq!(alerts_gauge(), 1.0);
+
+    let recovered = watch.check().await;
+    assert_eq!(recovered.consecutive_failures, 0);
+    assert_eq!(recovered.last_error, None);
+    assert_eq!(recovered.alerts, before.alerts);
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn the_history_in_sled_survives_a_restart() {
+    let _turn = turn().await;
+    let path = std::env::temp_dir().join(format!("model_watch_{}", uuid::Uuid::new_v4()));
+    // sled releases its file lock from a background thread, so a fresh store over the
+    // same database stands in for reopening the path
+    let db = sled::open(&path).expect("open sled");
+    let mock = MockMistralClient::default();
+    let open = || {
+        watch(
+            &mock,
+            Arc::new(SledModelHistory::new(&db).expect("open store")),
+        )
+    };
+
+    open().check().await;
+    mock.set_models(models(&[GENERATION, EMBEDDING, "codestral-latest"]));
+    let restarted = open();
+    restarted.check().await;
+
+    let history = restarted.history().unwrap();
+    let ids: Vec<_> = history.iter().map(|m| m.model.as_str()).collect();
+    assert_eq!(ids, ["codestral-latest", EMBEDDING, GENERATION]);
+    // Models seen before the restart are not reported as new
+    assert!(history.iter().all(|m| m.events.len() == 1));
+    drop(restarted);
+    drop(db);
+    let _ = std::fs::remove_dir_all(path);
+}
+
+#[tokio::test]
+async fn alerts_warn_in_readiness_and_the_history_is_served() {
+    let _turn = turn().await;
+    let settings = AppSettings {
+        moderation_model: None,
+        ..AppSettings::default()
+    };
+    let app = TestApp::builder()
+        .with_settings(settings)
+        .build()
+        .await
+        .expect("test app");
+    let server = app.serve().await.unwrap();
+
+    app.model_watch.check().await;
+    app.mock.set_models(models(&[EMBEDDING]));
+    app.model_watch.check().await;
+
+    let response = server.get("/ready").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["ready"], true);
+    assert_eq!(
+        body["model_watch"]["alerts"][0]["kind"],
+        "configured_model_missing"
+    );
+
+    let response = server.get("/api/models/history").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    let large = body["models"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|model| model["model"] == GENERATION)
+        .unwrap();
+    assert_eq!(large["present"], false);
+    assert_eq!(large["events"][1]["kind"], "disappeared");
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "server")]
+
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::extract::State;
+use axum::routing::post;
+use serde_json::Value;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    HttpMistralClient, MistralClient, MistralClientError, MistralEndpoint, MockMistralClient,
+    RecordedCall,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{ModerationBatchRequest, ModerationResponse};
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::WorkflowPolicy;
+use prompt_sentinel::{ComplianceRequest, WorkflowStatus};
+
+const THREE_RESULTS: &str = include_str!("fixtures/moderation_batch_three_results.json");
+const TWO_RESULTS: &str = include_str!("fixtures/moderation_batch_two_results.json");
+
+/// Serve `fixture` for every moderation call, keeping the request bodies
+async fn moderation_server(fixture: &'static str) -> (String, Arc<Mutex<Vec<Value>>>) {
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let router = Router::new()
+        .route(
+            "/v1/moderations",
+            post(
+                move |State(bodies): State<Arc<Mutex<Vec<Value>>>>, body: String| async move {
+                    bodies
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::from_str(&body).unwrap());
+                    ([("content-type", "application/json")], fixture)
+                },
+            ),
+        )
+        .with_state(bodies.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{address}"), bodies)
+}
+
+fn batch(inputs: &[&str]) -> ModerationBatchRequest {
+    ModerationBatchRequest {
+        model: Some("mistral-moderation-latest".to_owned()),
+        input: inputs.iter().map(|input| (*input).to_owned()).collect(),
+    }
+}
+
+fn moderation(flagged: bool, categories: &[&str]) -> ModerationResponse {
+    ModerationResponse {
+        flagged,
+        categories: categories.iter().map(|c| (*c).to_owned()).collect(),
+        severity: if flagged { 0.9 } else { 0.0 },
+        ..ModerationResponse::default()
+    }
+}
+
+#[tokio::test]
+async fn http_client_sends_one_array_and_maps_results_by_position() {
+    let (base_url, bodies) = moderation_server(THREE_RESULTS).await;
+    let client = HttpMistralClient::new(base_url, "test-key").unwrap();
+
+    let responses = client
+        .moderate_batch(batch(&["summary please", "how to hurt someone", "thanks"]))
+        .await
+        .expect("batch moderation");
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(
+        bodies[0]["input"],
+        serde_json::json!(["summary please", "how to hurt someone", "thanks"])
+    );
+    assert_eq!(bodies[0]["model"], "mistral-moderation-latest");
+
+    assert_eq!(responses.len(), 3);
+    assert!(!responses[0].flagged && !responses[2].flagged);
+    assert!(responses[1].flagged);
+    let mut categories = responses[1].categories.clone();
+    categories.sort();
+    assert_eq!(
+        categories,
+        vec!["dangerous_and_criminal_content", "violence_and_threats"]
+    );
+    assert!(responses[1].severity > 0.0);
+}
+
+#[tokio::test]
+async fn http_client_rejects_a_mismatched_result_count() {
+    let (base_url, _) = moderation_server(TWO_RESULTS).await;
+    let client = HttpMistralClient::new(base_url, "test-key").unwrap();
+
+    let error = client
+        .moderate_batch(batch(&["one", "two", "three"]))
+        .await
+        .expect_err("two results for three inputs");
+
+    let MistralClientError::InvalidResponse(message) = error else {
+        panic!("expected an invalid response, got {error}");
+    };
+    assert_eq!(message, "moderation returned 2 results for 3 inputs");
+}
+
+#[tokio::test]
+async fn mock_consumes_its_sequence_per_input() {
+    let mock = MockMistralClient::with_moderation_sequence(vec![
+        moderation(false, &[]),
+        moderation(true, &["selfharm"]),
+        moderation(false, &[]),
+    ])
+    .unwrap()
+    .record_calls();
+
+    let responses = mock
+        .moderate_batch(batch(&["a", "b"]))
+        .await
+        .expect("batch moderation");
+
+    assert_eq!(
+        responses,
+        vec![moderation(false, &[]), moderation(true, &["selfharm"])]
+    );
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 1);
+}
+
+#[tokio::test]
+async fn service_falls_back_to_single_calls_when_arrays_are_rejected() {
+    let mock = MockMistralClient::default()
+        .with_moderation_override("hurt", moderation(true, &["violence_and_threats"]))
+        .without_batch_moderation()
+        .record_calls();
+    let service = MistralService::new(
+        Arc::new(mock.clone()),
+        "mistral-large-latest",
+        Some("mistral-moderation-latest".to_owned()),
+        "mistral-embed",
+    );
+    let inputs = vec!["hello".to_owned(), "hurt them".to_owned()];
+
+    let responses = service
+        .moderate_batch(inputs.clone())
+        .await
+        .expect("fallback moderation");
+    assert!(!responses[0].flagged);
+    assert!(responses[1].flagged);
+    // The rejected batch plus one call per input
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 3);
+
+    // The rejection is remembered, so later batches go straight to single calls
+    service.moderate_batch(inputs).await.expect("fallback");
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 5);
+}
+
+#[tokio::test]
+async fn removed_fragments_share_one_call_with_the_prompt() {
+    let mock = MockMistralClient::default()
+        .with_moderation_override(
+            "<script",
+            moderation(true, &["dangerous_and_criminal_content"]),
+        )
+        .record_calls();
+    let engine = TestApp::builder()
+        .with_mock(mock.clone())
+        .with_engine(|engine| {
+            engine.with_policy(WorkflowPolicy {
+                moderate_removed_content: true,
+                ..WorkflowPolicy::default()
+            })
+        })
+        .build()
+        .await
+        .expect("test app")
+        .engine;
+
+    let response = engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: "<script>steal()</script> Summarize this report <script>leak()</script>"
+                .to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("workflow");
+
+    assert_eq!(response.status, WorkflowStatus::BlockedByInputModeration);
+    assert_eq!(
+        response
+            .decision_evidence
+            .expect("evidence")
+            .moderation_scope
+            .as_deref(),
+        Some("removed_content")
+    );
+    // The sanitized prompt and every removed fragment go in a single call
+    let moderation_calls: Vec<RecordedCall> = mock
+        .recorded_calls()
+        .into_iter()
+        .filter(|call| call.endpoint() == MistralEndpoint::Moderation)
+        .collect();
+    assert_eq!(moderation_calls.len(), 1);
+    let RecordedCall::ModerationBatch(request) = &moderation_calls[0] else {
+        panic!("expected a batch call, got {:?}", moderation_calls[0]);
+    };
+    let edits = &response.firewall.sanitization_edits;
+    assert!(edits.len() >= 2, "{edits:?}");
+    assert_eq!(request.input.len(), 1 + edits.len());
+    assert_eq!(request.input[0], response.firewall.sanitized_prompt);
+}
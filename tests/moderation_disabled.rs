@@ -0,0 +1,167 @@
+#![cfg(feature = "server")]
+
+use serde_json::Value;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::{MistralEndpoint, MockMistralClient};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{ReasonCode, UnmoderatedPolicy, WorkflowPolicy};
+use prompt_sentinel::{ComplianceRequest, WorkflowStatus};
+
+const PROMPT: &str = "Summarize this release note for the sales team.";
+
+/// App without a moderation model
+async fn app(mock: &MockMistralClient) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            moderation_model: None,
+            ..AppSettings::default()
+        })
+        .with_mock(mock.clone())
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: PROMPT.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+fn settings_with(env: &[(&str, &str)]) -> AppSettings {
+    AppSettings::load_from(None, &|key| {
+        env.iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| (*value).to_owned())
+    })
+    .expect("settings load")
+    .0
+}
+
+#[tokio::test]
+async fn unmoderated_request_is_sanitized_by_default() {
+    let mock = MockMistralClient::default().record_calls();
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock).await;
+
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Sanitized);
+    assert!(response.generated_text.is_some());
+    assert!(response.input_moderation.is_none());
+    assert!(response.output_moderation.is_none());
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 0);
+
+    let evidence = response.decision_evidence.expect("decision evidence");
+    assert_eq!(
+        evidence.final_reason_code,
+        ReasonCode::ModerationNotConfigured
+    );
+    assert_eq!(
+        evidence.moderation_skipped_reason.as_deref(),
+        Some("not_configured")
+    );
+
+    for stage in ["input_moderation", "output_moderation"] {
+        let step = response
+            .decision_trace
+            .iter()
+            .find(|step| step.stage == stage)
+            .unwrap_or_else(|| panic!("no {stage} step"));
+        assert_eq!(step.verdict, "skip", "{stage}");
+        assert_eq!(step.rule_refs, ["not_configured"], "{stage}");
+    }
+
+    let record = storage.all().expect("records").pop().expect("record");
+    let payload: Value = serde_json::from_str(&record.payload).unwrap();
+    assert_eq!(payload["moderation_skipped_reason"], "not_configured");
+}
+
+#[tokio::test]
+async fn allow_policy_completes_unmoderated_requests() {
+    let mock = MockMistralClient::default().record_calls();
+    let engine = app(&mock).await.engine.with_policy(WorkflowPolicy {
+        unmoderated: UnmoderatedPolicy::Allow,
+        ..WorkflowPolicy::default()
+    });
+
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 0);
+    let evidence = response.decision_evidence.expect("decision evidence");
+    assert_eq!(
+        evidence.moderation_skipped_reason.as_deref(),
+        Some("not_configured")
+    );
+}
+
+#[test]
+fn moderation_model_can_be_disabled_from_the_environment() {
+    assert!(
+        settings_with(&[("MISTRAL_MODERATION_MODEL", "disabled")])
+            .moderation_model
+            .is_none()
+    );
+    assert!(
+        settings_with(&[("MISTRAL_MODERATION_MODEL", "  ")])
+            .moderation_model
+            .is_none()
+    );
+    assert_eq!(
+        settings_with(&[("MISTRAL_MODERATION_MODEL", " mistral-moderation-2411 ")])
+            .moderation_model
+            .as_deref(),
+        Some("mistral-moderation-2411")
+    );
+
+    let settings = settings_with(&[("UNMODERATED_REQUESTS", "allow")]);
+    assert_eq!(settings.unmoderated_requests, UnmoderatedPolicy::Allow);
+    assert_eq!(
+        AppSettings::default().unmoderated_requests,
+        UnmoderatedPolicy::Sanitize
+    );
+    assert!(
+        AppSettings::load_from(None, &|key| {
+            (key == "UNMODERATED_REQUESTS").then(|| "block".to_owned())
+        })
+        .is_err()
+    );
+}
+
+#[cfg(feature = "server")]
+#[tokio::test]
+async fn health_reports_moderation_as_not_configured() {
+    use prompt_sentinel::modules::mistral_ai::dtos::ModelValidationResponse;
+    use prompt_sentinel::test_support::TestApp;
+
+    let settings = AppSettings {
+        generation_model: "mistral-large-latest".to_owned(),
+        moderation_model: None,
+        ..AppSettings::default()
+    };
+    let app = TestApp::builder()
+        .with_settings(settings)
+        .build()
+        .await
+        .expect("test app");
+    let server = app.serve().await.expect("server");
+
+    let response = server.get("/api/mistral/health").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["moderation"], "not_configured");
+
+    let response = server.get("/v1/models").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let models: ModelValidationResponse = response.json().await.unwrap();
+    assert!(models.moderation_model.is_none());
+    assert_eq!(models.moderation.as_deref(), Some("not_configured"));
+    assert_eq!(models.overall_status, "all_models_available");
+}
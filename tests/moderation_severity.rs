@@ -0,0 +1,147 @@
+#![cfg(feature = "server")]
+
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::modules::mistral_ai::dtos::{ModerationCategory, ModerationResponse};
+use prompt_sentinel::modules::mistral_ai::severity::{ModerationSeverity, SeverityMode};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::DecisionEvidence;
+use prompt_sentinel::{ComplianceRequest, WorkflowStatus};
+
+/// Input moderation flags a minor and a severe category, with raw scores
+fn flagging_mock() -> MockMistralClient {
+    let flagged = ModerationResponse {
+        flagged: true,
+        categories: vec!["law".to_owned(), "selfharm".to_owned()],
+        category_scores: BTreeMap::from([
+            ("law".to_owned(), 0.62),
+            ("selfharm".to_owned(), 0.97),
+            ("pii".to_owned(), 0.01),
+        ]),
+        ..ModerationResponse::default()
+    };
+    MockMistralClient::with_moderation_sequence(vec![flagged]).expect("moderation sequence")
+}
+
+async fn app(severity: ModerationSeverity) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            moderation_severity: severity,
+            ..AppSettings::default()
+        })
+        .with_mock(flagging_mock())
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: Some("severity-1".to_owned()),
+        prompt: "Tell me about the weather today.".to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[tokio::test]
+async fn flagged_categories_carry_their_severities_into_evidence_and_audit() {
+    let TestApp {
+        engine, storage, ..
+    } = app(ModerationSeverity::default()).await;
+
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::BlockedByInputModeration);
+    assert_eq!(response.input_moderation.as_ref().unwrap().severity, 1.0);
+
+    let expected = vec![
+        ModerationCategory {
+            category: "law".to_owned(),
+            severity: 0.3,
+            score: Some(0.62),
+        },
+        ModerationCategory {
+            category: "selfharm".to_owned(),
+            severity: 1.0,
+            score: Some(0.97),
+        },
+    ];
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(evidence.moderation_categories, expected);
+
+    let record = storage.all().expect("records").pop().expect("record");
+    let payload: Value = serde_json::from_str(&record.payload).unwrap();
+    assert_eq!(
+        payload["output_moderation_categories"],
+        json!([
+            { "category": "law", "severity": 0.3, "score": 0.62 },
+            { "category": "selfharm", "severity": 1.0, "score": 0.97 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn legacy_mode_keeps_the_category_count_severity() {
+    let engine = app(ModerationSeverity::legacy()).await.engine;
+
+    let response = engine.process(request()).await.expect("workflow");
+    let moderation = response.input_moderation.expect("input moderation");
+    assert!((moderation.severity - 0.4).abs() < 1e-6);
+    let evidence = response.decision_evidence.expect("evidence");
+    assert!(
+        evidence
+            .moderation_categories
+            .iter()
+            .all(|category| category.severity == 0.2)
+    );
+}
+
+#[tokio::test]
+async fn evidence_with_bare_category_names_still_reads() {
+    let engine = app(ModerationSeverity::default()).await.engine;
+    let response = engine.process(request()).await.expect("workflow");
+    let mut evidence = serde_json::to_value(response.decision_evidence.expect("evidence")).unwrap();
+
+    // Evidence and audit records written before severities were kept list names only
+    evidence["moderation_categories"] = json!(["law", "selfharm"]);
+    let old: DecisionEvidence = serde_json::from_value(evidence).expect("old shape");
+    assert_eq!(old.moderation_categories, vec!["law", "selfharm"]);
+    assert!(
+        old.moderation_categories
+            .iter()
+            .all(|category| category.severity == 0.0 && category.score.is_none())
+    );
+}
+
+#[test]
+fn severity_mode_and_weights_come_from_settings() {
+    let path = std::env::temp_dir().join(format!("severity_{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, r#"{"weights": {"law": 0.75}}"#).unwrap();
+    let env = |key: &str| match key {
+        "MODERATION_SEVERITY_MODE" => Some("weighted_sum".to_owned()),
+        "MODERATION_SEVERITY_WEIGHTS_PATH" => Some(path.display().to_string()),
+        _ => None,
+    };
+    let (settings, _) = AppSettings::load_from(None, &env).expect("settings load");
+    assert_eq!(settings.moderation_severity.mode, SeverityMode::WeightedSum);
+    assert_eq!(settings.moderation_severity.weights.weight("law"), 0.75);
+    assert_eq!(settings.moderation_severity.weights.weight("selfharm"), 0.5);
+
+    std::fs::write(&path, r#"{"weights": {"law": 2.0}}"#).unwrap();
+    assert!(AppSettings::load_from(None, &env).is_err());
+    let _ = std::fs::remove_file(&path);
+
+    assert!(
+        AppSettings::load_from(None, &|key| {
+            (key == "MODERATION_SEVERITY_MODE").then(|| "average".to_owned())
+        })
+        .is_err()
+    );
+}
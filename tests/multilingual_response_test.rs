@@ -1,72 +1,43 @@
-use prompt_sentinel::config::settings::AppSettings;
-use prompt_sentinel::modules::audit::logger::AuditLogger;
-use prompt_sentinel::modules::audit::storage::AuditStorage;
-use prompt_sentinel::modules::audit::storage::SledAuditStorage;
+#![cfg(feature = "server")]
+
 use prompt_sentinel::modules::bias_detection::service::BiasDetectionService;
-use prompt_sentinel::modules::mistral_ai::client::{MistralClient, MockMistralClient};
-use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
 use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
-use prompt_sentinel::modules::semantic_detection::service::SemanticDetectionService;
+use prompt_sentinel::test_support::TestApp;
 use prompt_sentinel::workflow::{ComplianceEngine, ComplianceRequest};
 use std::sync::Arc;
 
+/// Language detection and translation wired to the mock, as the server wires them
+async fn engine() -> ComplianceEngine {
+    let mock = MockMistralClient::default();
+    let client = Arc::new(mock.clone());
+    TestApp::builder()
+        .with_mock(mock)
+        .with_firewall(PromptFirewallService::new_with_mistral(
+            4096,
+            client.clone(),
+        ))
+        .with_bias(BiasDetectionService::new_with_mistral(0.35, client))
+        .with_semantic_bank()
+        .build()
+        .await
+        .expect("attack bank should load")
+        .engine
+}
+
 #[tokio::test]
 async fn test_spanish_response_translation() {
-    // Setup with mock Mistral client
-    let settings = AppSettings {
-        server_port: 3000,
-        mistral_api_key: Some("mock".to_string()),
-        mistral_base_url: "http://localhost".to_string(),
-        generation_model: "mock-model".to_string(),
-        moderation_model: Some("mock-moderation".to_string()),
-        embedding_model: "mock-embedding".to_string(),
-        bias_threshold: 0.35,
-        max_input_length: 4096,
-        semantic_medium_threshold: 0.70,
-        semantic_high_threshold: 0.80,
-        semantic_decision_margin: 0.02,
-    };
-
-    let audit_storage: Arc<dyn AuditStorage> =
-        Arc::new(SledAuditStorage::new("test_multilingual_data").unwrap());
-    let audit_logger = AuditLogger::new(audit_storage);
-
-    let mistral_client: Arc<dyn MistralClient> = Arc::new(MockMistralClient::default());
-    let mistral_service = MistralService::new(
-        mistral_client.clone(),
-        settings.generation_model.clone(),
-        settings.moderation_model.clone(),
-        settings.embedding_model.clone(),
-    );
-
-    let firewall_service =
-        PromptFirewallService::new_with_mistral(settings.max_input_length, mistral_client.clone());
-    let bias_service =
-        BiasDetectionService::new_with_mistral(settings.bias_threshold, mistral_client.clone());
-
-    let semantic_service = SemanticDetectionService::new(
-        mistral_service.clone(),
-        settings.semantic_medium_threshold,
-        settings.semantic_high_threshold,
-        settings.semantic_decision_margin,
-    );
-
-    // Initialize semantic service
-    semantic_service.initialize().await.unwrap();
-
-    let engine = ComplianceEngine::new(
-        firewall_service,
-        semantic_service,
-        bias_service,
-        mistral_service,
-        audit_logger,
-    );
+    let engine = engine().await;
 
     // Test with Spanish prompt
     let response = engine
         .process(ComplianceRequest {
             correlation_id: None,
             prompt: "Hola, ¿cómo estás?".to_string(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
         })
         .await
         .unwrap();
@@ -86,61 +57,17 @@ async fn test_spanish_response_translation() {
 
 #[tokio::test]
 async fn test_english_response_unchanged() {
-    // Setup with mock Mistral client
-    let settings = AppSettings {
-        server_port: 3000,
-        mistral_api_key: Some("mock".to_string()),
-        mistral_base_url: "http://localhost".to_string(),
-        generation_model: "mock-model".to_string(),
-        moderation_model: Some("mock-moderation".to_string()),
-        embedding_model: "mock-embedding".to_string(),
-        bias_threshold: 0.35,
-        max_input_length: 4096,
-        semantic_medium_threshold: 0.70,
-        semantic_high_threshold: 0.80,
-        semantic_decision_margin: 0.02,
-    };
-
-    let audit_storage: Arc<dyn AuditStorage> =
-        Arc::new(SledAuditStorage::new("test_english_data").unwrap());
-    let audit_logger = AuditLogger::new(audit_storage);
-
-    let mistral_client: Arc<dyn MistralClient> = Arc::new(MockMistralClient::default());
-    let mistral_service = MistralService::new(
-        mistral_client.clone(),
-        settings.generation_model.clone(),
-        settings.moderation_model.clone(),
-        settings.embedding_model.clone(),
-    );
-
-    let firewall_service =
-        PromptFirewallService::new_with_mistral(settings.max_input_length, mistral_client.clone());
-    let bias_service =
-        BiasDetectionService::new_with_mistral(settings.bias_threshold, mistral_client.clone());
-
-    let semantic_service = SemanticDetectionService::new(
-        mistral_service.clone(),
-        settings.semantic_medium_threshold,
-        settings.semantic_high_threshold,
-        settings.semantic_decision_margin,
-    );
-
-    // Initialize semantic service
-    semantic_service.initialize().await.unwrap();
-
-    let engine = ComplianceEngine::new(
-        firewall_service,
-        semantic_service,
-        bias_service,
-        mistral_service,
-        audit_logger,
-    );
+    let engine = engine().await;
 
     // Test with English prompt
     let response = engine
         .process(ComplianceRequest {
             correlation_id: None,
             prompt: "Hello, how are you?".to_string(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
         })
         .await
         .unwrap();
@@ -34,13 +34,14 @@ async fn test_model_validation_endpoint() {
 
 #[test]
 fn test_compliance_report_generation() {
-    let service = EuLawComplianceService::default();
+    let service = EuLawComplianceService;
 
     let request = ComplianceReportRequest {
         intended_use: "AI-powered chatbot for customer support".to_string(),
         request_timestamp: Utc::now(),
         correlation_id: "test-123".to_string(),
         generate_pdf: false,
+        regenerate: false,
     };
 
     let response = service.generate_compliance_report(request);
@@ -53,7 +54,7 @@ fn test_compliance_report_generation() {
 
 #[test]
 fn test_compliance_configuration_management() {
-    let service = EuLawComplianceService::default();
+    let service = EuLawComplianceService;
 
     // Test getting current configuration
     let current_config = service.get_current_configuration();
@@ -140,6 +141,7 @@ fn test_audit_trail_filters() {
         start_time: None,
         end_time: None,
         correlation_id: None,
+        cursor: None,
     };
 
     // The actual implementation would be tested with a real storage backend
@@ -0,0 +1,169 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::{MockMistralClient, RecordedCall};
+use prompt_sentinel::modules::mistral_ai::dtos::{ChatCompletionResponse, ModerationResponse};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::OutputModerationChunking;
+
+fn flagged() -> ModerationResponse {
+    ModerationResponse {
+        flagged: true,
+        categories: vec!["violence".to_owned()],
+        severity: 0.9,
+        ..ModerationResponse::default()
+    }
+}
+
+fn answer(text: String) -> ChatCompletionResponse {
+    ChatCompletionResponse {
+        model: "mistral-large-latest".to_owned(),
+        output_text: text,
+        usage: None,
+    }
+}
+
+/// `chars` characters of harmless filler
+fn filler(chars: usize) -> String {
+    "all quiet here ".chars().cycle().take(chars).collect()
+}
+
+fn chunking(max_chars: usize, overlap_chars: usize) -> AppSettings {
+    AppSettings {
+        output_moderation_chunking: OutputModerationChunking {
+            max_chars,
+            overlap_chars,
+            max_concurrency: 2,
+        },
+        ..AppSettings::default()
+    }
+}
+
+async fn check(app: &TestApp) -> Value {
+    let server = app.serve().await.unwrap();
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": "Write me a long story" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+fn audit_payload(app: &TestApp, correlation_id: &Value) -> Value {
+    app.storage
+        .all()
+        .unwrap()
+        .iter()
+        .map(|record| serde_json::from_str::<Value>(&record.payload).unwrap())
+        .find(|payload| payload["correlation_id"] == *correlation_id)
+        .expect("request audited")
+}
+
+#[tokio::test]
+async fn long_output_flagged_only_in_its_middle_chunk_is_blocked() {
+    let clean = ModerationResponse::default();
+    // The prompt, then the three chunks of the answer
+    let mock = MockMistralClient::with_moderation_sequence(vec![
+        clean.clone(),
+        clean.clone(),
+        flagged(),
+        clean,
+    ])
+    .unwrap()
+    .with_chat_response(answer(filler(250)))
+    .record_calls();
+    let app = TestApp::builder()
+        .with_settings(chunking(100, 10))
+        .with_mock(mock.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let body = check(&app).await;
+    assert_eq!(body["status"], "blocked_by_output_moderation");
+    assert!(body["generated_text"].is_null());
+    let expected = json!({
+        "count": 3,
+        "flagged_chunks": [1],
+        "flagged_ranges": [[90, 190]],
+        "payload_too_large": false
+    });
+    assert_eq!(
+        body["decision_evidence"]["output_moderation_chunks"],
+        expected
+    );
+    assert_eq!(
+        audit_payload(&app, &body["correlation_id"])["output_moderation_chunks"],
+        expected
+    );
+
+    // All three chunks went out in one batch call
+    let batches: Vec<usize> = mock
+        .recorded_calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            RecordedCall::ModerationBatch(request) => Some(request.input.len()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(batches, vec![3]);
+}
+
+#[tokio::test]
+async fn output_refused_as_too_large_is_moderated_in_chunks_instead() {
+    let mut output = filler(140);
+    output.push_str("quokka uprising");
+    output.push_str(&filler(95));
+    let mock = MockMistralClient::default()
+        .with_moderation_override("quokka uprising", flagged())
+        .with_moderation_input_limit(150)
+        .without_batch_moderation()
+        .with_chat_response(answer(output));
+    let app = TestApp::builder().with_mock(mock).build().await.unwrap();
+
+    let body = check(&app).await;
+    assert_eq!(body["status"], "blocked_by_output_moderation");
+    let chunks = &body["decision_evidence"]["output_moderation_chunks"];
+    assert_eq!(chunks["count"], 3);
+    assert_eq!(chunks["flagged_chunks"], json!([1]));
+    assert_eq!(chunks["payload_too_large"], true);
+    assert!(body["decision_evidence"].get("degraded_stages").is_none());
+}
+
+#[tokio::test]
+async fn short_output_keeps_a_single_moderation_call() {
+    let mock = MockMistralClient::default().record_calls();
+    let app = TestApp::builder()
+        .with_settings(chunking(100, 10))
+        .with_mock(mock.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let body = check(&app).await;
+    assert_eq!(body["status"], "completed");
+    assert!(
+        body["decision_evidence"]
+            .get("output_moderation_chunks")
+            .is_none()
+    );
+    let calls = mock.recorded_calls();
+    assert!(
+        calls
+            .iter()
+            .all(|call| !matches!(call, RecordedCall::ModerationBatch(_)))
+    );
+    assert_eq!(
+        calls
+            .iter()
+            .filter(|call| matches!(call, RecordedCall::Moderation(_)))
+            .count(),
+        2
+    );
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use prompt_sentinel::workflow::{
+    DEFAULT_REASON_LOCALE, DecisionEvidence, ReasonCode, ReasonParams, ReasonRenderer,
+    register_reason_locale, render_reason,
+};
+
+fn params(value: serde_json::Value) -> ReasonParams {
+    serde_json::from_value(value).expect("params object")
+}
+
+/// Every code with representative params, its wire name and the English text it renders
+fn cases() -> Vec<(ReasonCode, &'static str, ReasonParams, &'static str)> {
+    vec![
+        (
+            ReasonCode::EuProhibitedPractice,
+            "eu_prohibited_practice",
+            params(json!({"finding": "Social scoring of natural persons"})),
+            "Blocked by EU AI Act Article 5 (Prohibited Practices): Social scoring of natural persons",
+        ),
+        (
+            ReasonCode::FirewallRuleMatch,
+            "firewall_rule_match",
+            params(json!({"rule_ids": ["PFW-001", "PFW-007"]})),
+            "Blocked by firewall rule: PFW-001, PFW-007",
+        ),
+        (
+            ReasonCode::RepeatOfBlockedPrompt,
+            "repeat_of_blocked_prompt",
+            params(json!({"correlation_id": "attempt-1", "similarity": 0.934})),
+            "Similar to recently blocked prompt attempt-1 (similarity: 0.93)",
+        ),
+        (
+            ReasonCode::SemanticSimilarity,
+            "semantic_similarity",
+            params(json!({
+                "template_id": "SEM-003",
+                "category": "roleplay_jailbreak",
+                "score": 0.87,
+                "raised_score": 0.97,
+                "repeat_correlation_id": "attempt-1"
+            })),
+            "Semantic similarity to attack pattern SEM-003 (category: roleplay_jailbreak, score: 0.87); risk raised to 0.97 as similar to recently blocked prompt attempt-1",
+        ),
+        (
+            ReasonCode::LlmJudgeEscalation,
+            "llm_judge_escalation",
+            params(json!({
+                "trigger": "semantic_gray_zone",
+                "answer": "yes",
+                "template_version": "builtin-1"
+            })),
+            "Blocked on a second opinion from the judge model (trigger: semantic_gray_zone, answer: yes, template: builtin-1)",
+        ),
+        (
+            ReasonCode::StageFailure,
+            "stage_failure",
+            params(json!({"stage": "moderation", "error": "HTTP 429"})),
+            "The moderation stage failed and its failure policy is closed: HTTP 429",
+        ),
+        (
+            ReasonCode::InputModerationFlag,
+            "input_moderation_flag",
+            params(json!({"categories": ["hate", "violence"]})),
+            "Flagged by content moderation: hate, violence",
+        ),
+        (
+            ReasonCode::RemovedContentModerationFlag,
+            "removed_content_moderation_flag",
+            params(json!({"categories": ["selfharm"]})),
+            "Content removed by sanitization flagged by moderation: selfharm",
+        ),
+        (
+            ReasonCode::OutputModerationFlag,
+            "output_moderation_flag",
+            params(json!({"categories": ["pii"]})),
+            "Output flagged by moderation: pii",
+        ),
+        (
+            ReasonCode::SanitizerProbing,
+            "sanitizer_probing",
+            params(json!({"session_id": "s-1", "prior_correlation_ids": ["a", "b"]})),
+            "Blocked for probing the sanitizer: session s-1 sent similar sanitized prompts a, b",
+        ),
+        (
+            ReasonCode::OutputFirewallEcho,
+            "output_firewall_echo",
+            params(json!({"rule_ids": ["PFW-001"]})),
+            "Output repeats firewall block rules: PFW-001",
+        ),
+        (
+            ReasonCode::ExcessiveSanitization,
+            "excessive_sanitization",
+            params(json!({"rule_ids": ["PFW-SAN-001"]})),
+            "Blocked because sanitization removed excessive content: PFW-SAN-001",
+        ),
+        (
+            ReasonCode::Sanitized,
+            "sanitized",
+            ReasonParams::new(),
+            "Input sanitized by firewall",
+        ),
+        (
+            ReasonCode::ElevatedSemanticRisk,
+            "elevated_semantic_risk",
+            params(json!({"score": 0.75})),
+            "Elevated risk (semantic score: 0.75), proceeded with caution",
+        ),
+        (
+            ReasonCode::ModerationNotConfigured,
+            "moderation_not_configured",
+            ReasonParams::new(),
+            "Content moderation is not configured, proceeded with caution",
+        ),
+        (
+            ReasonCode::AllChecksPassed,
+            "all_checks_passed",
+            ReasonParams::new(),
+            "All checks passed",
+        ),
+        (
+            ReasonCode::PolicyRule,
+            "policy_rule",
+            params(json!({"rule_id": "biased-jailbreak"})),
+            "Decided by policy rule biased-jailbreak",
+        ),
+    ]
+}
+
+#[test]
+fn codes_have_stable_wire_names_and_english_text() {
+    for (code, wire, params, text) in cases() {
+        assert_eq!(serde_json::to_value(code).unwrap(), json!(wire));
+        assert_eq!(
+            serde_json::from_value::<ReasonCode>(json!(wire)).unwrap(),
+            code
+        );
+        assert_eq!(render_reason(code, &params, DEFAULT_REASON_LOCALE), text);
+    }
+
+    // Codes from newer builds read back as unspecified rather than failing
+    assert_eq!(
+        serde_json::from_value::<ReasonCode>(json!("not_a_code_yet")).unwrap(),
+        ReasonCode::Unspecified
+    );
+}
+
+#[test]
+fn evidence_wire_format_is_locked() {
+    let evidence: DecisionEvidence = serde_json::from_value(json!({
+        "firewall_action": "allow",
+        "firewall_matched_rules": [],
+        "semantic_risk_score": 0.9,
+        "semantic_matched_template": "SEM-003",
+        "semantic_category": "roleplay_jailbreak",
+        "moderation_flagged": false,
+        "moderation_categories": [],
+        "final_decision": "block",
+        "final_reason": "Semantic similarity to attack pattern SEM-003 (category: roleplay_jailbreak, score: 0.87)",
+        "final_reason_code": "semantic_similarity",
+        "reason_params": {
+            "template_id": "SEM-003",
+            "category": "roleplay_jailbreak",
+            "score": 0.87
+        }
+    }))
+    .expect("evidence");
+    assert_eq!(evidence.final_reason_code, ReasonCode::SemanticSimilarity);
+    assert_eq!(
+        render_reason(
+            evidence.final_reason_code,
+            &evidence.reason_params,
+            DEFAULT_REASON_LOCALE
+        ),
+        evidence.final_reason
+    );
+
+    let wire = serde_json::to_value(&evidence).unwrap();
+    assert_eq!(wire["final_reason_code"], "semantic_similarity");
+    assert_eq!(
+        wire["reason_params"].to_string(),
+        r#"{"category":"roleplay_jailbreak","score":0.87,"template_id":"SEM-003"}"#
+    );
+
+    // Evidence recorded before reason codes existed still deserializes
+    let mut legacy = wire;
+    let fields = legacy.as_object_mut().unwrap();
+    fields.remove("final_reason_code");
+    fields.remove("reason_params");
+    let legacy: DecisionEvidence = serde_json::from_value(legacy).unwrap();
+    assert_eq!(legacy.final_reason_code, ReasonCode::Unspecified);
+    assert!(legacy.reason_params.is_empty());
+}
+
+struct Shouting;
+
+impl ReasonRenderer for Shouting {
+    fn render(&self, code: ReasonCode, _params: &ReasonParams) -> Option<String> {
+        (code == ReasonCode::AllChecksPassed).then(|| "ALL CHECKS PASSED".to_owned())
+    }
+}
+
+#[test]
+fn registered_locales_fall_back_to_english() {
+    register_reason_locale("x-shouting", Arc::new(Shouting));
+    let none = ReasonParams::new();
+    assert_eq!(
+        render_reason(ReasonCode::AllChecksPassed, &none, "x-shouting"),
+        "ALL CHECKS PASSED"
+    );
+    assert_eq!(
+        render_reason(ReasonCode::Sanitized, &none, "x-shouting"),
+        "Input sanitized by firewall"
+    );
+    assert_eq!(
+        render_reason(ReasonCode::AllChecksPassed, &none, "fr"),
+        "All checks passed"
+    );
+}
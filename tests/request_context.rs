@@ -0,0 +1,77 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::sync::OnceLock;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use tower::ServiceExt;
+
+use prompt_sentinel::modules::telemetry::context::CORRELATION_ID_HEADER;
+use prompt_sentinel::test_support::TestApp;
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+async fn get(router: &Router, path: &str, correlation_id: Option<&str>) -> (StatusCode, String) {
+    let mut request = Request::builder().uri(path);
+    if let Some(id) = correlation_id {
+        request = request.header(CORRELATION_ID_HEADER, id);
+    }
+    let response = router
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let header = response
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .expect("correlation id header is set")
+        .to_owned();
+    (response.status(), header)
+}
+
+#[tokio::test]
+async fn responses_carry_correlation_id_and_route_metrics() {
+    let metrics = recorder();
+    let router = TestApp::new().await.router();
+
+    let (status, generated) = get(&router, "/api/compliance/options", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!generated.is_empty());
+
+    let (_, echoed) = get(&router, "/api/compliance/options", Some("client-trace-42")).await;
+    assert_eq!(echoed, "client-trace-42");
+
+    let rendered = metrics.render();
+    let responses = rendered
+        .lines()
+        .find(|line| {
+            line.starts_with("responses_total")
+                && line.contains("endpoint=\"/api/compliance/options\"")
+                && line.contains("status_class=\"2xx\"")
+        })
+        .unwrap_or_else(|| panic!("missing route metric in:\n{rendered}"));
+    assert!(responses.ends_with(" 2"), "{responses}");
+    assert!(rendered.contains("request_latency_seconds"));
+}
+
+#[tokio::test]
+async fn malformed_correlation_ids_are_replaced() {
+    let router = TestApp::new().await.router();
+    let oversized = "a".repeat(200);
+    for supplied in ["has spaces", oversized.as_str()] {
+        let (status, id) = get(&router, "/health", Some(supplied)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(id, supplied);
+        assert!(!id.is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::modules::request_fingerprint::service::{
+    canonicalize, is_fingerprint, prompt_fingerprint, request_fingerprint,
+};
+
+const PROMPT: &str = "What is the capital of France?";
+
+fn request(prompt: &str) -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: prompt.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+/// Outputs pinned so that any change to the algorithm shows up here; such a change
+/// needs a new version prefix, not new vectors
+#[test]
+fn golden_vectors() {
+    assert_eq!(
+        prompt_fingerprint(PROMPT),
+        "v1:115049a298532be2f181edb03f766770c0db84c22aff39003fec340deaec7545"
+    );
+    assert_eq!(
+        prompt_fingerprint(""),
+        "v1:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    // Decomposed accent, CRLF and surrounding whitespace
+    assert_eq!(
+        canonicalize("  Cafe\u{301}\r\nau lait \n"),
+        "Caf\u{e9}\nau lait"
+    );
+    assert_eq!(
+        prompt_fingerprint("  Cafe\u{301}\r\nau lait \n"),
+        "v1:e31b52b823faac4eda78abb540908e534678567c6b352022ff63492d52c32282"
+    );
+    assert_eq!(
+        request_fingerprint(PROMPT, &BTreeMap::new(), None),
+        "v1:fdaa3fc2772f81914c93490718318c6785bda4314dc8ce32522b4e85a99f783a"
+    );
+    let overrides = BTreeMap::from([
+        ("suggest_rewrite".to_owned(), json!(true)),
+        ("deterministic".to_owned(), json!(true)),
+    ]);
+    assert_eq!(
+        request_fingerprint(PROMPT, &overrides, Some("acme")),
+        "v1:0a1ba67a738337a4ad9f94a175500c6d8351b7ce3a22fb438128468bad395a4a"
+    );
+}
+
+#[test]
+fn only_light_canonicalization_is_applied() {
+    // Case, inner whitespace, homoglyphs and zero-width characters all count
+    for variant in [
+        "what is the capital of France?",
+        "What is  the capital of France?",
+        "What is the c\u{0430}pital of France?",
+        "What is the capital\u{200B} of France?",
+    ] {
+        assert_ne!(prompt_fingerprint(variant), prompt_fingerprint(PROMPT));
+    }
+    assert_eq!(
+        prompt_fingerprint("\tWhat is the capital of France?\r\n"),
+        prompt_fingerprint(PROMPT)
+    );
+}
+
+#[test]
+fn request_fingerprint_covers_overrides_and_tenant() {
+    let plain = request_fingerprint(PROMPT, &BTreeMap::new(), None);
+    assert_ne!(plain, prompt_fingerprint(PROMPT));
+    assert_ne!(
+        request_fingerprint(PROMPT, &BTreeMap::new(), Some("acme")),
+        plain
+    );
+
+    let overrides = |variables: Value| {
+        BTreeMap::from([
+            ("template_id".to_owned(), json!("support_reply")),
+            ("variables".to_owned(), variables),
+        ])
+    };
+    // Object key order and the line endings of string values do not matter
+    let mut reordered = serde_json::Map::new();
+    reordered.insert("tone".to_owned(), json!("formal"));
+    reordered.insert("customer".to_owned(), json!("Ana\r\n"));
+    assert_eq!(
+        request_fingerprint("", &overrides(Value::Object(reordered)), None),
+        request_fingerprint(
+            "",
+            &overrides(json!({"customer": "Ana", "tone": "formal"})),
+            None
+        )
+    );
+    assert_ne!(
+        request_fingerprint(
+            "",
+            &overrides(json!({"customer": "Ana", "tone": "casual"})),
+            None
+        ),
+        request_fingerprint(
+            "",
+            &overrides(json!({"customer": "Ana", "tone": "formal"})),
+            None
+        )
+    );
+}
+
+#[test]
+fn request_overrides_leave_out_defaults_and_ids() {
+    let mut sent = request(PROMPT);
+    sent.correlation_id = Some("support-ticket-1".to_owned());
+    sent.session_id = Some("session-1".to_owned());
+    assert!(sent.fingerprint_overrides().is_empty());
+    assert_eq!(
+        sent.fingerprint(None),
+        request_fingerprint(PROMPT, &BTreeMap::new(), None)
+    );
+
+    sent.deterministic = Some(false);
+    assert_eq!(
+        sent.fingerprint_overrides(),
+        BTreeMap::from([("deterministic".to_owned(), json!(false))])
+    );
+}
+
+#[cfg(feature = "server")]
+#[tokio::test]
+async fn full_profile_carries_the_request_fingerprint_in_evidence() {
+    use prompt_sentinel::test_support::TestApp;
+    use prompt_sentinel::workflow::ResponseProfile;
+
+    let engine = TestApp::new().await.engine;
+    let mut sent = request(PROMPT);
+    sent.suggest_rewrite = true;
+    let response = engine
+        .process(sent.clone())
+        .await
+        .expect("workflow completes");
+
+    let full = response.clone().with_profile(ResponseProfile::Full);
+    let evidence = full.decision_evidence.expect("evidence");
+    assert_eq!(evidence.request_fingerprint, Some(sent.fingerprint(None)));
+
+    let standard = response.with_profile(ResponseProfile::Standard);
+    let evidence = standard.decision_evidence.expect("evidence");
+    assert_eq!(evidence.request_fingerprint, None);
+}
+
+proptest! {
+    #[test]
+    fn canonicalization_is_idempotent(text in any::<String>()) {
+        let canonical = canonicalize(&text);
+        prop_assert_eq!(canonicalize(&canonical), canonical.clone());
+        prop_assert_eq!(prompt_fingerprint(&canonical), prompt_fingerprint(&text));
+    }
+
+    #[test]
+    fn line_endings_and_outer_whitespace_do_not_change_the_fingerprint(
+        lines in prop::collection::vec("[a-zA-Z0-9 ,.?é]{0,12}", 1..5),
+        lead in "[ \t\n]{0,3}",
+        trail in "[ \t\r\n]{0,3}",
+    ) {
+        let unix = lines.join("\n");
+        let windows = format!("{lead}{}{trail}", lines.join("\r\n"));
+        let mac = lines.join("\r");
+        prop_assert_eq!(prompt_fingerprint(&windows), prompt_fingerprint(&unix));
+        prop_assert_eq!(prompt_fingerprint(&mac), prompt_fingerprint(&unix));
+    }
+
+    #[test]
+    fn fingerprints_are_versioned_lowercase_sha256(text in any::<String>(), tenant in proptest::option::of("[a-z]{1,8}")) {
+        let prompt = prompt_fingerprint(&text);
+        let request = request_fingerprint(&text, &BTreeMap::new(), tenant.as_deref());
+        prop_assert!(is_fingerprint(&prompt), "{}", prompt);
+        prop_assert!(is_fingerprint(&request), "{}", request);
+        prop_assert_eq!(prompt_fingerprint(&text), prompt);
+    }
+}
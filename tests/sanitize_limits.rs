@@ -0,0 +1,116 @@
+#![cfg(feature = "server")]
+
+use prompt_sentinel::modules::prompt_firewall::dtos::FirewallAction;
+use prompt_sentinel::modules::prompt_firewall::rules::{
+    CompiledFirewallRules, FirewallRulesConfig, RuleEntry, RulesLoadError,
+};
+use prompt_sentinel::modules::prompt_firewall::service::PromptFirewallService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::ReasonCode;
+use prompt_sentinel::{ComplianceEngine, ComplianceRequest, WorkflowStatus};
+
+/// Boilerplate a deployment strips from pasted snippets
+const GENERATED_BLOCK: &str = "// <auto-generated>\n// This code was generated by a tool.\n// Changes to this file may be lost.\n// </auto-generated>";
+
+fn rules_with_sanitize_pattern(pattern: &str) -> FirewallRulesConfig {
+    let mut rules = FirewallRulesConfig::default();
+    rules
+        .sanitize_patterns
+        .push(RuleEntry::new("PFW-SAN-900", pattern));
+    rules
+}
+
+fn write_rules(rules: &FirewallRulesConfig) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("firewall_rules_{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, serde_json::to_string(rules).unwrap()).unwrap();
+    path
+}
+
+async fn build_engine(rules: FirewallRulesConfig) -> ComplianceEngine {
+    let firewall = PromptFirewallService::default()
+        .with_rules(CompiledFirewallRules::compile(rules).expect("valid rules"));
+    TestApp::builder()
+        .with_firewall(firewall)
+        .build()
+        .await
+        .expect("test app")
+        .engine
+}
+
+fn request(prompt: &str) -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: prompt.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[test]
+fn reload_rejects_patterns_that_would_strip_ordinary_text() {
+    let firewall = PromptFirewallService::default();
+    let before = firewall.rules_fingerprint();
+
+    // A single space would glue every prompt into one word
+    for pattern in [" ", "a", "the "] {
+        let path = write_rules(&rules_with_sanitize_pattern(pattern));
+        let error = firewall.reload_rules(&path).expect_err("invalid pattern");
+        std::fs::remove_file(&path).ok();
+        assert!(
+            matches!(&error, RulesLoadError::Invalid(message) if message.contains("PFW-SAN-900")),
+            "{pattern:?}: {error}"
+        );
+    }
+    assert_eq!(firewall.rules_fingerprint(), before);
+    assert_eq!(
+        firewall
+            .inspect_local("What is the capital of France?")
+            .sanitized_prompt,
+        "What is the capital of France?"
+    );
+}
+
+#[tokio::test]
+async fn excessive_sanitization_blocks_with_its_own_reason_code() {
+    let engine = build_engine(rules_with_sanitize_pattern(GENERATED_BLOCK)).await;
+    let prompt = format!("{GENERATED_BLOCK}\nint x;");
+
+    let response = engine.process(request(&prompt)).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::BlockedByFirewall);
+    assert!(response.generated_text.is_none());
+    assert_eq!(response.firewall.action, FirewallAction::Block);
+    assert_eq!(response.firewall.sanitized_prompt, "int x;");
+    let evidence = response.decision_evidence.expect("decision evidence");
+    assert_eq!(
+        evidence.final_reason_code,
+        ReasonCode::ExcessiveSanitization
+    );
+    assert_eq!(
+        evidence.reason_params["rule_ids"],
+        serde_json::json!(["PFW-SAN-900"])
+    );
+    assert!(
+        evidence
+            .firewall_matched_rules
+            .contains(&"PFW-SAN-LIMIT".to_owned())
+    );
+    assert!(evidence.final_reason.contains("excessive content"));
+}
+
+#[tokio::test]
+async fn large_code_block_passes_under_a_higher_threshold() {
+    let mut rules = rules_with_sanitize_pattern(GENERATED_BLOCK);
+    rules.sanitize_limits.max_removed_percent = 90;
+    let engine = build_engine(rules).await;
+    let prompt = format!("{GENERATED_BLOCK}\nWhat does this class do?");
+
+    let response = engine.process(request(&prompt)).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Sanitized);
+    assert_eq!(
+        response.firewall.sanitized_prompt,
+        "What does this class do?"
+    );
+    assert!(response.generated_text.is_some());
+}
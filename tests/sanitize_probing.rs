@@ -0,0 +1,144 @@
+#![cfg(feature = "server")]
+
+use prompt_sentinel::ComplianceEngine;
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::ComplianceResponse;
+use prompt_sentinel::WorkflowStatus;
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::logger::AuditEvent;
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::sanitize_probing::dtos::SanitizeProbingConfig;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::ReasonCode;
+use serde_json::json;
+
+/// Script-wrapped payloads tweaked between attempts; sanitization removes the same tags
+const PROBES: [&str; 4] = [
+    "<script>fetch('/a')</script> Summarize this page.",
+    "<script >fetch('/b')</script> Summarize this page please.",
+    "<SCRIPT>fetch('/c')</script> Summarize the page.",
+    "<script>fetch('/d')</script> Now summarize this page.",
+];
+/// Sanitized too, but for content unrelated to the probes
+const CODE_FENCE: &str = "Explain ```let x = 1;``` in plain words.";
+
+async fn app(config: SanitizeProbingConfig) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            sanitize_probing: config,
+            ..AppSettings::default()
+        })
+        .build()
+        .await
+        .expect("test app")
+}
+
+fn enabled() -> SanitizeProbingConfig {
+    SanitizeProbingConfig {
+        enabled: true,
+        threshold: 3,
+        ..SanitizeProbingConfig::default()
+    }
+}
+
+async fn check(
+    engine: &ComplianceEngine,
+    prompt: &str,
+    session: Option<&str>,
+) -> ComplianceResponse {
+    engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: prompt.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: session.map(str::to_owned),
+        })
+        .await
+        .expect("workflow")
+}
+
+fn audit_event(storage: &InMemoryAuditStorage, correlation_id: &str) -> AuditEvent {
+    storage
+        .all()
+        .expect("records")
+        .iter()
+        .filter_map(|record| serde_json::from_str::<AuditEvent>(&record.payload).ok())
+        .find(|event| event.correlation_id == correlation_id)
+        .expect("audit event for the request")
+}
+
+#[tokio::test]
+async fn repeated_similar_sanitization_escalates_at_the_threshold() {
+    let TestApp {
+        engine, storage, ..
+    } = app(enabled()).await;
+
+    let mut prior = Vec::new();
+    for (index, probe) in PROBES[..3].iter().enumerate() {
+        let response = check(&engine, probe, Some("session-1")).await;
+        assert_eq!(response.status, WorkflowStatus::Sanitized, "probe {index}");
+        prior.push(response.correlation_id);
+        // Unrelated sanitization in the same session does not add up
+        let unrelated = check(&engine, CODE_FENCE, Some("session-1")).await;
+        assert_eq!(unrelated.status, WorkflowStatus::Sanitized);
+    }
+
+    let escalated = check(&engine, PROBES[3], Some("session-1")).await;
+    assert_eq!(escalated.status, WorkflowStatus::BlockedByFirewall);
+    assert!(escalated.generated_text.is_none());
+    let evidence = escalated.decision_evidence.expect("decision evidence");
+    assert_eq!(evidence.final_reason_code, ReasonCode::SanitizerProbing);
+    assert_eq!(evidence.policy_rule.as_deref(), Some("sanitize-probing"));
+    assert_eq!(
+        evidence.reason_params["prior_correlation_ids"],
+        json!(prior)
+    );
+    let step = &escalated.decision_trace[evidence.decisive_step.expect("decisive step")];
+    assert_eq!(step.stage, "sanitize_probing");
+    assert_eq!(step.rule_refs, prior);
+
+    let event = audit_event(&storage, &escalated.correlation_id);
+    assert_eq!(event.session_id.as_deref(), Some("session-1"));
+    let probing = event.sanitize_probing.expect("probing escalation");
+    assert_eq!(probing.session_id, "session-1");
+    assert_eq!(probing.prior_correlation_ids, prior);
+}
+
+#[tokio::test]
+async fn sessions_are_counted_apart_and_need_an_id() {
+    let engine = app(enabled()).await.engine;
+    for probe in &PROBES[..3] {
+        check(&engine, probe, Some("session-1")).await;
+    }
+
+    let other_session = check(&engine, PROBES[3], Some("session-2")).await;
+    assert_eq!(other_session.status, WorkflowStatus::Sanitized);
+    for probe in PROBES {
+        let anonymous = check(&engine, probe, None).await;
+        assert_eq!(anonymous.status, WorkflowStatus::Sanitized);
+        assert!(
+            !anonymous
+                .decision_trace
+                .iter()
+                .any(|step| step.stage == "sanitize_probing")
+        );
+    }
+}
+
+#[tokio::test]
+async fn probing_detection_is_off_by_default() {
+    let TestApp {
+        engine, storage, ..
+    } = app(SanitizeProbingConfig::default()).await;
+    for probe in PROBES.iter().chain(&PROBES) {
+        let response = check(&engine, probe, Some("session-1")).await;
+        assert_eq!(response.status, WorkflowStatus::Sanitized);
+        assert!(
+            audit_event(&storage, &response.correlation_id)
+                .sanitize_probing
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "server")]
+
+use prompt_sentinel::WorkflowStatus;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::self_test::dtos::SelfTestProbe;
+use prompt_sentinel::modules::self_test::service::{
+    SELF_TEST_CORRELATION_PREFIX, SelfTestService, default_probes,
+};
+use prompt_sentinel::test_support::TestApp;
+
+#[tokio::test]
+async fn built_in_probes_pass_against_mock_pipeline() {
+    let TestApp {
+        engine, storage, ..
+    } = TestApp::new().await;
+    let report = SelfTestService::new().run(&engine, true).await;
+
+    assert!(report.passed, "{report:#?}");
+    assert!(report.audit_recorded);
+    assert_eq!(report.probes.len(), default_probes().len());
+
+    let direct = report
+        .probes
+        .iter()
+        .find(|probe| probe.name == "direct_injection")
+        .expect("direct injection probe");
+    assert_eq!(
+        direct.actual_status,
+        Some(WorkflowStatus::BlockedByFirewall)
+    );
+    assert!(direct.stage_latencies_ms.contains_key("firewall"));
+    assert!(direct.mistral_stages_exercised.is_empty());
+    assert!(
+        direct
+            .mistral_stages_skipped
+            .contains(&"generation".to_owned())
+    );
+
+    let benign = report
+        .probes
+        .iter()
+        .find(|probe| probe.name == "benign")
+        .expect("benign probe");
+    assert!(
+        benign
+            .mistral_stages_exercised
+            .contains(&"generation".to_owned())
+    );
+    assert!(
+        benign
+            .mistral_stages_exercised
+            .contains(&"output_moderation".to_owned())
+    );
+
+    let records = storage.all().expect("records available");
+    assert_eq!(records.len(), report.probes.len());
+    for record in &records {
+        assert!(
+            record
+                .correlation_id
+                .starts_with(SELF_TEST_CORRELATION_PREFIX)
+        );
+        assert!(record.payload.contains("\"self_test\":true"));
+    }
+}
+
+#[tokio::test]
+async fn probes_can_skip_the_audit_trail() {
+    let TestApp {
+        engine, storage, ..
+    } = TestApp::new().await;
+    let report = SelfTestService::new().run(&engine, false).await;
+
+    assert!(report.passed);
+    assert!(!report.audit_recorded);
+    assert!(storage.all().expect("records available").is_empty());
+}
+
+#[tokio::test]
+async fn violated_expectation_fails_the_run() {
+    let engine = TestApp::new().await.engine;
+    let mut probes = default_probes();
+    probes.push(SelfTestProbe {
+        name: "misconfigured".to_owned(),
+        prompt: "Summarize this release note.".to_owned(),
+        expected_status: WorkflowStatus::BlockedByFirewall,
+        expect_bias_flagged: None,
+    });
+    let report = SelfTestService::new()
+        .with_probes(probes)
+        .run(&engine, false)
+        .await;
+
+    assert!(!report.passed);
+    let failed = report
+        .probes
+        .iter()
+        .filter(|probe| !probe.passed)
+        .collect::<Vec<_>>();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].name, "misconfigured");
+    assert_eq!(failed[0].actual_status, Some(WorkflowStatus::Completed));
+    assert!(
+        failed[0]
+            .failure
+            .as_deref()
+            .unwrap()
+            .contains("expected BlockedByFirewall")
+    );
+}
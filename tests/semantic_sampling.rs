@@ -0,0 +1,173 @@
+#![cfg(feature = "server")]
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::logger::AuditEvent;
+use prompt_sentinel::modules::audit::proof::hash_record;
+use prompt_sentinel::modules::audit::storage::{AuditStorage, InMemoryAuditStorage};
+use prompt_sentinel::modules::prompt_firewall::dtos::FirewallAction;
+use prompt_sentinel::modules::semantic_detection::dtos::{SamplingKey, SemanticSamplingPolicy};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::ResponseProfile;
+use prompt_sentinel::{ComplianceRequest, StageOutcome, WorkflowStatus};
+
+const LOW_RISK_PROMPT: &str = "Summarize this quarterly report in two sentences";
+const SAMPLING_SECRET: &str = "sampling-secret";
+
+async fn app(rate: f32) -> TestApp {
+    TestApp::builder()
+        .with_settings(AppSettings {
+            semantic_sampling: SemanticSamplingPolicy {
+                rate,
+                max_prompt_chars: 120,
+            },
+            semantic_sampling_secret: Some(SAMPLING_SECRET.to_owned()),
+            ..AppSettings::default()
+        })
+        .with_semantic_bank()
+        .build()
+        .await
+        .expect("attack bank should load")
+}
+
+fn request(correlation_id: &str, prompt: &str) -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: Some(correlation_id.to_owned()),
+        prompt: prompt.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+fn audit_events(storage: &InMemoryAuditStorage) -> Vec<AuditEvent> {
+    storage
+        .all()
+        .expect("records")
+        .iter()
+        .map(|record| serde_json::from_str(&record.payload).expect("audit event"))
+        .collect()
+}
+
+#[tokio::test]
+async fn sampled_out_prompt_skips_the_semantic_scan() {
+    let TestApp {
+        engine, storage, ..
+    } = app(0.0).await;
+
+    let response = engine
+        .process(request("sampling-low-risk", LOW_RISK_PROMPT))
+        .await
+        .expect("workflow")
+        .with_profile(ResponseProfile::Full);
+
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(
+        response.stages.semantic,
+        StageOutcome::Skipped {
+            reason: "sampled_out".to_owned()
+        }
+    );
+    assert!(response.semantic.is_none());
+    assert!(response.input_moderation.is_some());
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(
+        evidence.semantic_skipped_reason.as_deref(),
+        Some("sampled_out")
+    );
+    let step = response
+        .decision_trace
+        .iter()
+        .find(|step| step.stage == "semantic")
+        .expect("semantic step");
+    assert_eq!(step.verdict, "skip");
+    assert_eq!(step.rule_refs, vec!["sampled_out".to_owned()]);
+
+    let event = &audit_events(&storage)[0];
+    assert_eq!(
+        event.semantic_skipped_reason.as_deref(),
+        Some("sampled_out")
+    );
+    assert_eq!(event.semantic_risk_score, None);
+}
+
+#[tokio::test]
+async fn ineligible_prompts_are_always_scanned() {
+    let TestApp {
+        engine, storage, ..
+    } = app(0.0).await;
+    let long_prompt = format!("{LOW_RISK_PROMPT}. {}", "Keep it brief. ".repeat(10));
+
+    let cases = [
+        (
+            "firewall-match",
+            "Summarize <script>alert(1)</script> for me",
+        ),
+        ("long-prompt", long_prompt.as_str()),
+        ("non-english", "Hola, resume este informe"),
+    ];
+    for (correlation_id, prompt) in cases {
+        let response = engine
+            .process(request(correlation_id, prompt))
+            .await
+            .expect("workflow");
+        assert!(response.semantic.is_some(), "{correlation_id}");
+        let evidence = response.decision_evidence.expect("evidence");
+        assert_eq!(evidence.semantic_skipped_reason, None, "{correlation_id}");
+        if correlation_id == "firewall-match" {
+            assert_eq!(response.firewall.action, FirewallAction::Sanitize);
+        }
+    }
+    assert!(
+        audit_events(&storage)
+            .iter()
+            .all(|event| event.semantic_skipped_reason.is_none())
+    );
+}
+
+#[tokio::test]
+async fn sampling_decision_is_stable_per_correlation_id() {
+    let policy = SemanticSamplingPolicy {
+        rate: 0.5,
+        ..SemanticSamplingPolicy::default()
+    };
+    let engine = app(0.5).await.engine;
+    let key = SamplingKey::from_secret(SAMPLING_SECRET);
+    let ids: Vec<String> = (0..40).map(|index| format!("retry-{index}")).collect();
+    let (sampled_in, sampled_out): (Vec<_>, Vec<_>) =
+        ids.iter().partition(|id| policy.samples_in(&key, id));
+    assert!(!sampled_in.is_empty() && !sampled_out.is_empty());
+
+    for (id, expect_scanned) in [(sampled_in[0], true), (sampled_out[0], false)] {
+        for _ in 0..3 {
+            let response = engine
+                .process(request(id, LOW_RISK_PROMPT))
+                .await
+                .expect("workflow");
+            assert_eq!(response.semantic.is_some(), expect_scanned, "{id}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn a_client_cannot_pick_correlation_ids_that_skip_the_scan() {
+    let engine = app(0.5).await.engine;
+    // Without the secret, a client can only rank ids by a public hash, the way
+    // sampling used to choose; the ids it expects to skip the scan are still scanned
+    let chosen: Vec<String> = (0..200)
+        .map(|index| format!("chosen-{index}"))
+        .filter(|id| hash_record(id).as_str() >= "8")
+        .take(40)
+        .collect();
+    assert_eq!(chosen.len(), 40);
+
+    let mut scanned = 0;
+    for id in &chosen {
+        let response = engine
+            .process(request(id, LOW_RISK_PROMPT))
+            .await
+            .expect("workflow");
+        scanned += usize::from(response.semantic.is_some());
+    }
+    assert!((8..=32).contains(&scanned), "{scanned} of 40 scanned");
+}
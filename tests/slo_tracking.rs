@@ -0,0 +1,298 @@
+#![cfg(all(feature = "server", feature = "metrics-prometheus"))]
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use tower::ServiceExt;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralEndpoint, MockMistralClient, ScriptedFailure,
+};
+use prompt_sentinel::modules::slo::dtos::{
+    AlertSeverity, SloObjective, SloObjectives, SloStatusResponse,
+};
+use prompt_sentinel::modules::slo::service::SloTracker;
+use prompt_sentinel::test_support::TestApp;
+
+const CHECK: &str = "/api/compliance/check";
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn recorder() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("recorder installs once per test binary")
+    })
+}
+
+fn feed(tracker: &SloTracker, at: Instant, fast: u64, slow: u64, failed: u64) {
+    for _ in 0..fast {
+        tracker.record_at(CHECK, Duration::from_millis(120), false, at);
+    }
+    for _ in 0..slow {
+        tracker.record_at(CHECK, Duration::from_millis(3_000), false, at);
+    }
+    for _ in 0..failed {
+        tracker.record_at(CHECK, Duration::from_millis(40), true, at);
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn synthetic_traffic_yields_slis_and_burn_rates() {
+    let tracker = SloTracker::new(SloObjectives::default());
+    let now = Instant::now();
+    feed(&tracker, now, 980, 15, 5);
+    tracker.record_at("/health", Duration::from_millis(2), false, now);
+
+    let status = tracker.status_at(now);
+    let endpoint = status
+        .endpoints
+        .iter()
+        .find(|endpoint| endpoint.endpoint == CHECK)
+        .expect("route tracked");
+    let window = endpoint.window("5m").expect("5m window");
+    assert_eq!(window.requests, 1000);
+    assert_eq!(window.failures, 5);
+    assert_eq!(window.slow_requests, 15);
+    assert_close(window.latency_sli.unwrap(), 980.0 / 995.0);
+    assert_close(window.availability_sli.unwrap(), 0.995);
+    assert_close(window.latency_burn_rate, (15.0 / 995.0) / 0.01);
+    assert_close(window.availability_burn_rate, 0.005 / 0.001);
+    // The 99th percentile falls among the 3s requests, in the 2.5s..5s bucket
+    assert_eq!(window.latency_percentile_ms, Some(5_000));
+    assert_eq!(endpoint.window("6h").unwrap().requests, 1000);
+    assert_close(
+        endpoint.budget_remaining(SloObjective::Latency),
+        1.0 - window.latency_burn_rate,
+    );
+    assert_close(endpoint.budget_remaining(SloObjective::Availability), -4.0);
+    assert!(endpoint.alerts.is_empty(), "{:?}", endpoint.alerts);
+
+    assert_eq!(status.overall.window("1h").unwrap().requests, 1001);
+    let health = status
+        .endpoints
+        .iter()
+        .find(|endpoint| endpoint.endpoint == "/health")
+        .expect("health tracked");
+    assert_eq!(health.window("5m").unwrap().latency_sli, Some(1.0));
+    assert_eq!(health.window("5m").unwrap().latency_percentile_ms, Some(5));
+}
+
+#[test]
+fn windows_slide_and_old_traffic_is_dropped() {
+    let tracker = SloTracker::new(SloObjectives::default());
+    let start = Instant::now();
+    feed(&tracker, start, 90, 0, 10);
+
+    let later = tracker.status_at(start + 10 * MINUTE);
+    let five_minutes = later.overall.window("5m").unwrap();
+    assert_eq!(five_minutes.requests, 0);
+    assert_eq!(five_minutes.availability_sli, None);
+    assert_eq!(five_minutes.availability_burn_rate, 0.0);
+    let hour = later.overall.window("1h").unwrap();
+    assert_eq!(hour.failures, 10);
+    assert_close(hour.availability_burn_rate, 100.0);
+
+    let next_day = tracker.status_at(start + 7 * 60 * MINUTE);
+    assert_eq!(next_day.overall.window("6h").unwrap().requests, 0);
+    assert_close(
+        next_day
+            .overall
+            .budget_remaining(SloObjective::Availability),
+        1.0,
+    );
+    assert!(next_day.endpoints.is_empty());
+
+    // A request a minute for eight hours: only the last six hours are retained
+    let tracker = SloTracker::new(SloObjectives::default());
+    for minute in 0..480 {
+        tracker.record_at(
+            CHECK,
+            Duration::from_millis(50),
+            false,
+            start + minute * MINUTE,
+        );
+    }
+    let status = tracker.status_at(start + 479 * MINUTE);
+    assert_eq!(status.overall.window("5m").unwrap().requests, 5);
+    assert_eq!(status.overall.window("1h").unwrap().requests, 60);
+    assert_eq!(status.overall.window("6h").unwrap().requests, 360);
+}
+
+#[test]
+fn burn_rate_alerts_need_both_windows() {
+    let tracker = SloTracker::new(SloObjectives::default());
+    let start = Instant::now();
+    // 20% failures against a 0.1% budget: a burn rate of 200 everywhere
+    feed(&tracker, start, 80, 0, 20);
+    let status = tracker.status_at(start);
+    let mut alerts: Vec<(SloObjective, AlertSeverity)> = status
+        .overall
+        .alerts
+        .iter()
+        .map(|alert| (alert.objective, alert.severity))
+        .collect();
+    alerts.sort_by_key(|(_, severity)| severity.as_str());
+    assert_eq!(
+        alerts,
+        [
+            (SloObjective::Availability, AlertSeverity::Page),
+            (SloObjective::Availability, AlertSeverity::Ticket),
+        ]
+    );
+
+    // Half an hour of clean traffic clears the 5m window, so the page stops while the
+    // slower ticket keeps firing
+    let recovered = start + 30 * MINUTE;
+    feed(&tracker, recovered, 1_000, 0, 0);
+    let status = tracker.status_at(recovered);
+    let hour = status.overall.window("1h").unwrap();
+    assert_close(hour.availability_burn_rate, (20.0 / 1_100.0) / 0.001);
+    assert_eq!(status.overall.alerts.len(), 1);
+    let ticket = &status.overall.alerts[0];
+    assert_eq!(ticket.severity, AlertSeverity::Ticket);
+    assert_eq!(ticket.long_window, "6h");
+    assert_eq!(ticket.short_window, "1h");
+    assert_close(ticket.threshold, 6.0);
+
+    // Slow requests burn the latency budget independently
+    let tracker = SloTracker::new(SloObjectives {
+        latency_threshold_ms: 500,
+        ..SloObjectives::default()
+    });
+    tracker.record_at(CHECK, Duration::from_millis(501), false, start);
+    tracker.record_at(CHECK, Duration::from_millis(500), false, start);
+    let status = tracker.status_at(start);
+    let window = status.overall.window("5m").unwrap();
+    assert_eq!(window.latency_sli, Some(0.5));
+    assert_close(window.latency_burn_rate, 50.0);
+    assert_eq!(window.availability_burn_rate, 0.0);
+    assert!(
+        status
+            .overall
+            .alerts
+            .iter()
+            .all(|alert| alert.objective == SloObjective::Latency)
+    );
+}
+
+#[test]
+fn objectives_are_validated() {
+    assert!(SloObjectives::default().validate().is_ok());
+    for invalid in [
+        SloObjectives {
+            latency_threshold_ms: 0,
+            ..SloObjectives::default()
+        },
+        SloObjectives {
+            latency_target: 1.0,
+            ..SloObjectives::default()
+        },
+        SloObjectives {
+            availability_target: 0.0,
+            ..SloObjectives::default()
+        },
+        SloObjectives {
+            availability_target: f64::NAN,
+            ..SloObjectives::default()
+        },
+    ] {
+        assert!(invalid.validate().is_err(), "{invalid:?}");
+    }
+}
+
+async fn get(router: &Router, path: &str) -> (StatusCode, Vec<u8>) {
+    let response = router
+        .clone()
+        .oneshot(Request::builder().uri(path).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn middleware_feeds_every_route() {
+    let recorder = recorder();
+    let mock = MockMistralClient::default();
+    mock.fail_next(MistralEndpoint::Models, 1, ScriptedFailure::unavailable());
+    let app = TestApp::builder()
+        .with_mock(mock)
+        .build()
+        .await
+        .expect("test app");
+    let router = app.router();
+
+    for _ in 0..3 {
+        assert_eq!(get(&router, "/health").await.0, StatusCode::OK);
+    }
+    assert_eq!(
+        get(&router, "/api/mistral/health").await.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    let (status, body) = get(&router, "/api/slo/status").await;
+    assert_eq!(status, StatusCode::OK);
+    let report: SloStatusResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(report.objectives, SloObjectives::default());
+    let overall = report.overall.window("5m").unwrap();
+    assert_eq!(overall.requests, 4);
+    assert_eq!(overall.failures, 1);
+    assert_close(overall.availability_sli.unwrap(), 0.75);
+
+    let find = |route: &str| {
+        report
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.endpoint == route)
+            .unwrap_or_else(|| panic!("{route} not tracked"))
+    };
+    assert_eq!(find("/health").window("5m").unwrap().requests, 3);
+    assert_eq!(find("/health").window("5m").unwrap().failures, 0);
+    let mistral = find("/api/mistral/health");
+    assert_eq!(mistral.window("5m").unwrap().failures, 1);
+    assert_close(mistral.availability_budget_remaining, 1.0 - 1_000.0);
+
+    // The status request itself is recorded once it has been answered
+    let (_, body) = get(&router, "/api/slo/status").await;
+    let report: SloStatusResponse = serde_json::from_slice(&body).unwrap();
+    assert!(
+        report
+            .endpoints
+            .iter()
+            .any(|endpoint| endpoint.endpoint == "/api/slo/status")
+    );
+
+    let rendered = recorder.render();
+    let burn_rate: f64 = rendered
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix(
+                r#"slo_burn_rate_1h{endpoint="/api/mistral/health",objective="availability"} "#,
+            )
+        })
+        .expect("1h burn rate gauge")
+        .parse()
+        .unwrap();
+    assert!((burn_rate - 1_000.0).abs() < 1e-6, "{burn_rate}");
+    assert!(rendered.contains(r#"slo_latency_sli{endpoint="/health",window="5m"} 1"#));
+    assert!(rendered.contains("slo_error_budget_remaining{"));
+    assert!(rendered.contains(
+        r#"slo_burn_rate_alert{endpoint="/api/mistral/health",objective="availability",severity="page"} 1"#
+    ));
+}
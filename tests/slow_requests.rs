@@ -0,0 +1,130 @@
+#![cfg(feature = "server")]
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::json;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::diagnostics::dtos::{SlowRequestPolicy, SlowRequestsResponse};
+use prompt_sentinel::modules::mistral_ai::client::MockMistralClient;
+use prompt_sentinel::modules::telemetry::context::CORRELATION_ID_HEADER;
+use prompt_sentinel::test_support::TestApp;
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+const PROMPT: &str = "Summarize the benefits of renewable energy";
+
+async fn app(delay: Duration) -> TestApp {
+    let settings = AppSettings {
+        slow_requests: SlowRequestPolicy {
+            threshold_ms: 30_000,
+            route_thresholds: BTreeMap::from([("/api/compliance/check".to_owned(), 100)]),
+            capacity: 10,
+        },
+        ..AppSettings::default()
+    };
+    TestApp::builder()
+        .with_settings(settings)
+        .with_admin_token(ADMIN_TOKEN)
+        .with_mock(MockMistralClient::default().delay_chat(delay))
+        .build()
+        .await
+        .expect("test app")
+}
+
+async fn slow_requests(server: &prompt_sentinel::test_support::TestServer) -> SlowRequestsResponse {
+    let response = server.get("/api/debug/slow-requests").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn delayed_generation_leaves_a_diagnostic_naming_the_stage() {
+    let app = app(Duration::from_millis(150)).await;
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .post("/api/compliance/check")
+        .header(CORRELATION_ID_HEADER, "slow-check-1")
+        .json(&json!({ "prompt": PROMPT }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let diagnostics = slow_requests(&server).await;
+    assert_eq!(diagnostics.total, 1, "{diagnostics:?}");
+    let record = &diagnostics.requests[0];
+    assert_eq!(record.correlation_id, "slow-check-1");
+    assert_eq!(record.route, "/api/compliance/check");
+    assert_eq!(record.threshold_ms, 100);
+    assert!(record.duration_ms > 100);
+    assert_eq!(record.slowest_stage.as_deref(), Some("generation"));
+    let generation = record
+        .stages
+        .iter()
+        .find(|timing| timing.stage == "generation")
+        .expect("generation timing");
+    assert!(generation.duration_ms >= 150, "{record:?}");
+    assert!(
+        record
+            .stages
+            .iter()
+            .any(|timing| timing.stage == "firewall")
+    );
+    assert!(record.request_bytes.is_some_and(|bytes| bytes > 0));
+    assert!(record.queue_wait_ms.contains_key("chat"));
+
+    // Diagnostics never carry the prompt
+    let raw = serde_json::to_string(&diagnostics).unwrap();
+    assert!(!raw.contains("renewable"), "{raw}");
+}
+
+#[tokio::test]
+async fn fast_requests_and_other_routes_leave_no_diagnostic() {
+    let app = app(Duration::ZERO).await;
+    let server = app.serve().await.unwrap();
+
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": PROMPT }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        server.get("/health").send().await.unwrap().status(),
+        StatusCode::OK
+    );
+
+    assert_eq!(slow_requests(&server).await.total, 0);
+}
+
+#[test]
+fn thresholds_come_from_settings() {
+    let env = |key: &str| match key {
+        "SLOW_REQUEST_THRESHOLD_MS" => Some("2500".to_owned()),
+        "SLOW_REQUEST_ROUTE_THRESHOLDS" => {
+            Some("/api/compliance/check=8000, /health=50".to_owned())
+        }
+        "SLOW_REQUEST_BUFFER_SIZE" => Some("20".to_owned()),
+        _ => None,
+    };
+    let (settings, _) = AppSettings::load_from(None, &env).expect("settings load");
+    let policy = settings.slow_requests;
+    assert_eq!(policy.threshold_ms, 2500);
+    assert_eq!(policy.threshold_for("/api/compliance/check"), 8000);
+    assert_eq!(policy.threshold_for("/health"), 50);
+    assert_eq!(policy.threshold_for("/api/semantic/scan"), 2500);
+    assert_eq!(policy.capacity, 20);
+
+    for (key, value) in [
+        ("SLOW_REQUEST_THRESHOLD_MS", "0"),
+        ("SLOW_REQUEST_ROUTE_THRESHOLDS", "/health"),
+        ("SLOW_REQUEST_BUFFER_SIZE", "0"),
+    ] {
+        let env = |name: &str| (name == key).then(|| value.to_owned());
+        assert!(AppSettings::load_from(None, &env).is_err(), "{key}={value}");
+    }
+}
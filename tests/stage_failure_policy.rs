@@ -0,0 +1,204 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::bias_detection::service::BiasDetectionService;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralEndpoint, MockMistralClient, ScriptedFailure,
+};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{FailureMode, ReasonCode, StageFailurePolicy};
+use prompt_sentinel::{ComplianceRequest, ComplianceResponse, PipelineStage, WorkflowStatus};
+
+/// Detected as English by the mock
+const ENGLISH_PROMPT: &str = "What is the capital of France?";
+/// Detected as Spanish by the mock; no Spanish bias term pack ships
+const SPANISH_PROMPT: &str = "hola, como estas?";
+
+async fn setup(stage: PipelineStage, mode: FailureMode) -> TestApp {
+    let mock = MockMistralClient::default();
+    let mut policy = StageFailurePolicy::default();
+    match stage {
+        PipelineStage::Language => policy.language = mode,
+        PipelineStage::Bias => policy.bias = mode,
+        PipelineStage::Semantic => policy.semantic = mode,
+        PipelineStage::Moderation => policy.moderation = mode,
+        PipelineStage::Translation => policy.translation = mode,
+    }
+    let builder = TestApp::builder()
+        .with_settings(AppSettings {
+            stage_failure_policy: policy,
+            ..AppSettings::default()
+        })
+        .with_mock(mock.clone());
+    // Only the bias test needs a bias scan that translates through Mistral
+    let builder = match stage {
+        PipelineStage::Bias => {
+            builder.with_bias(BiasDetectionService::new_with_mistral(0.35, Arc::new(mock)))
+        }
+        PipelineStage::Semantic => builder.with_semantic_bank(),
+        _ => builder,
+    };
+    builder.build().await.expect("attack bank should load")
+}
+
+async fn check(setup: &TestApp, prompt: &str) -> (ComplianceResponse, Value) {
+    let response = setup
+        .engine
+        .process(ComplianceRequest {
+            correlation_id: None,
+            prompt: prompt.to_owned(),
+            suggest_rewrite: false,
+            template: None,
+            deterministic: None,
+            session_id: None,
+        })
+        .await
+        .expect("stage failures never fail the request");
+    let record = setup.storage.all().expect("records").pop().expect("record");
+    (response, serde_json::from_str(&record.payload).unwrap())
+}
+
+/// The failure is named in the evidence and the audit record under either policy, and
+/// a closed policy blocks with a stage failure step deciding it
+fn assert_failure_recorded(
+    response: &ComplianceResponse,
+    audit: &Value,
+    stage: PipelineStage,
+    mode: FailureMode,
+) {
+    let evidence = response.decision_evidence.as_ref().expect("evidence");
+    assert_eq!(evidence.degraded_stages.len(), 1, "{evidence:?}");
+    assert_eq!(evidence.degraded_stages[0].stage, stage);
+    assert_eq!(evidence.degraded_stages[0].policy, mode);
+    assert!(evidence.degraded_stages[0].error.contains("503"));
+    assert_eq!(audit["degraded_stages"][0]["stage"], stage.as_str());
+    assert_eq!(audit["degraded_stages"][0]["policy"], mode.as_str());
+
+    match mode {
+        FailureMode::Closed => {
+            assert_eq!(
+                response.status,
+                WorkflowStatus::BlockedByStageFailure { stage }
+            );
+            assert_eq!(audit["final_status"], "blocked_by_stage_failure");
+            assert_eq!(evidence.final_decision, "block");
+            assert!(evidence.final_reason.contains(stage.as_str()));
+            assert_eq!(evidence.final_reason_code, ReasonCode::StageFailure);
+            assert_eq!(evidence.reason_params["stage"], stage.as_str());
+            assert_eq!(audit["final_reason_code"], "stage_failure");
+            let decisive = &response.decision_trace[evidence.decisive_step.expect("step")];
+            assert_eq!(decisive.stage, "stage_failure");
+            assert_eq!(decisive.rule_refs, vec![stage.as_str().to_owned()]);
+            assert!(response.generated_text.is_none());
+        }
+        FailureMode::Open => {
+            assert_eq!(response.status, WorkflowStatus::Completed);
+            assert!(response.generated_text.is_some());
+        }
+    }
+}
+
+fn fail_once(setup: &TestApp, endpoint: MistralEndpoint) {
+    setup
+        .mock
+        .fail_next(endpoint, 1, ScriptedFailure::unavailable());
+}
+
+/// Fail the translation a stage makes after the prompt's own translation, which comes
+/// first and falls back to the original text without failing any stage
+fn fail_stage_translation(setup: &TestApp) {
+    setup.mock.fail_next(
+        MistralEndpoint::Translation,
+        2,
+        ScriptedFailure::unavailable(),
+    );
+}
+
+#[tokio::test]
+async fn language_detection_failure_follows_policy() {
+    for mode in [FailureMode::Open, FailureMode::Closed] {
+        let setup = setup(PipelineStage::Language, mode).await;
+        fail_once(&setup, MistralEndpoint::LanguageDetection);
+        let (response, audit) = check(&setup, SPANISH_PROMPT).await;
+
+        assert_failure_recorded(&response, &audit, PipelineStage::Language, mode);
+        // Failing open treats the prompt as English, so nothing is translated back
+        assert_eq!(audit["detected_language"], "English");
+    }
+}
+
+#[tokio::test]
+async fn bias_translation_failure_follows_policy() {
+    for mode in [FailureMode::Open, FailureMode::Closed] {
+        let setup = setup(PipelineStage::Bias, mode).await;
+        fail_stage_translation(&setup);
+        let (response, audit) = check(&setup, SPANISH_PROMPT).await;
+
+        assert_failure_recorded(&response, &audit, PipelineStage::Bias, mode);
+    }
+}
+
+#[tokio::test]
+async fn semantic_failure_follows_policy() {
+    for mode in [FailureMode::Open, FailureMode::Closed] {
+        let setup = setup(PipelineStage::Semantic, mode).await;
+        // The mock embeds every text identically, so a working scan would block
+        fail_once(&setup, MistralEndpoint::Embeddings);
+        let (response, audit) = check(&setup, ENGLISH_PROMPT).await;
+
+        assert_failure_recorded(&response, &audit, PipelineStage::Semantic, mode);
+        assert!(response.semantic.is_none());
+    }
+}
+
+#[tokio::test]
+async fn moderation_failure_follows_policy() {
+    for mode in [FailureMode::Open, FailureMode::Closed] {
+        let setup = setup(PipelineStage::Moderation, mode).await;
+        fail_once(&setup, MistralEndpoint::Moderation);
+        let (response, audit) = check(&setup, ENGLISH_PROMPT).await;
+
+        assert_failure_recorded(&response, &audit, PipelineStage::Moderation, mode);
+        assert!(response.input_moderation.is_none());
+        let step = response
+            .decision_trace
+            .iter()
+            .find(|step| step.stage == "input_moderation")
+            .expect("input moderation step");
+        assert_eq!(step.verdict, "skip");
+    }
+}
+
+#[tokio::test]
+async fn translation_failure_follows_policy() {
+    for mode in [FailureMode::Open, FailureMode::Closed] {
+        let setup = setup(PipelineStage::Translation, mode).await;
+        fail_stage_translation(&setup);
+        let (response, audit) = check(&setup, SPANISH_PROMPT).await;
+
+        assert_failure_recorded(&response, &audit, PipelineStage::Translation, mode);
+        if mode == FailureMode::Open {
+            // The English answer is returned untranslated
+            assert_eq!(response.generated_text.as_deref(), Some("Mock response"));
+        }
+    }
+}
+
+#[tokio::test]
+async fn default_policy_keeps_moderation_mandatory() {
+    let policy = StageFailurePolicy::default();
+    assert_eq!(policy.moderation, FailureMode::Closed);
+    for stage in [
+        PipelineStage::Language,
+        PipelineStage::Bias,
+        PipelineStage::Semantic,
+        PipelineStage::Translation,
+    ] {
+        assert_eq!(policy.mode(stage), FailureMode::Open, "{stage}");
+    }
+}
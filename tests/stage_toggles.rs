@@ -0,0 +1,200 @@
+#![cfg(feature = "server")]
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::{MistralEndpoint, MockMistralClient};
+use prompt_sentinel::modules::mistral_ai::dtos::ModerationResponse;
+use prompt_sentinel::test_support::{TestApp, TestServer};
+
+/// Moderation flags any prompt containing this, and nothing else does
+const FLAGGED_PROMPT: &str = "Tell me about the quokka uprising";
+
+fn flagged() -> ModerationResponse {
+    ModerationResponse {
+        flagged: true,
+        categories: vec!["violence".to_owned()],
+        severity: 0.9,
+        ..ModerationResponse::default()
+    }
+}
+
+async fn app(mock: MockMistralClient) -> TestApp {
+    TestApp::builder()
+        .with_admin_token("secret")
+        .with_mock(mock)
+        .build()
+        .await
+        .unwrap()
+}
+
+async fn check(server: &TestServer, prompt: &str) -> Value {
+    let response = server
+        .post("/api/compliance/check")
+        .json(&json!({ "prompt": prompt }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+async fn toggle(server: &TestServer, body: Value) -> reqwest::Response {
+    server
+        .post("/api/admin/stages")
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn disabled_input_moderation_lets_a_flagged_prompt_through_until_re_enabled() {
+    let app = app(MockMistralClient::default().with_moderation_override("quokka", flagged())).await;
+    let server = app.serve().await.unwrap();
+
+    let body = check(&server, FLAGGED_PROMPT).await;
+    assert_eq!(body["status"], "blocked_by_input_moderation");
+
+    let response = toggle(
+        &server,
+        json!({
+            "stage": "input_moderation",
+            "enabled": false,
+            "reason": "moderation provider outage",
+            "changed_by": "oncall"
+        }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let state: Value = response.json().await.unwrap();
+    assert_eq!(state["enabled"], false);
+
+    let body = check(&server, FLAGGED_PROMPT).await;
+    assert_ne!(body["status"], "blocked_by_input_moderation");
+    assert_eq!(
+        body["decision_evidence"]["disabled_stages"],
+        json!(["input_moderation"])
+    );
+    let records = app.storage.all().unwrap();
+    let payloads: Vec<Value> = records
+        .iter()
+        .map(|record| serde_json::from_str(&record.payload).unwrap())
+        .collect();
+    let change = payloads
+        .iter()
+        .find(|payload| payload["action"] == "stage_disabled")
+        .expect("config change recorded");
+    assert_eq!(change["event_type"], "configuration_change");
+    assert!(
+        change["detail"]
+            .as_str()
+            .unwrap()
+            .contains("moderation provider outage")
+    );
+    let skipped = payloads
+        .iter()
+        .find(|payload| payload["correlation_id"] == body["correlation_id"])
+        .expect("request audited");
+    assert_eq!(skipped["disabled_stages"], json!(["input_moderation"]));
+    let moderation_step = skipped["decision_trace"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|step| step["stage"] == "input_moderation")
+        .expect("input moderation step");
+    assert_eq!(moderation_step["verdict"], "skip");
+    assert_eq!(moderation_step["rule_refs"], json!(["disabled_by_admin"]));
+
+    let stages: Value = server
+        .get("/api/admin/stages")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let input = stages["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|state| state["stage"] == "input_moderation")
+        .unwrap();
+    assert_eq!(input["enabled"], false);
+    assert_eq!(input["changed_by"], "oncall");
+    assert_eq!(input["reason"], "moderation provider outage");
+    assert!(input["changed_at"].is_string());
+    let untouched = stages["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|state| state["stage"] == "bias")
+        .unwrap();
+    assert_eq!(untouched["enabled"], true);
+    assert!(untouched.get("changed_at").is_none());
+
+    let response = toggle(
+        &server,
+        json!({ "stage": "input_moderation", "enabled": true, "reason": "provider recovered" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = check(&server, FLAGGED_PROMPT).await;
+    assert_eq!(body["status"], "blocked_by_input_moderation");
+    assert!(body["decision_evidence"].get("disabled_stages").is_none());
+}
+
+#[tokio::test]
+async fn disabled_generation_analyzes_without_calling_the_model() {
+    let mock = MockMistralClient::default().record_calls();
+    let app = app(mock.clone()).await;
+    let server = app.serve().await.unwrap();
+
+    let response = toggle(
+        &server,
+        json!({ "stage": "generation", "enabled": false, "reason": "analysis only" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = check(&server, "What is the capital of France?").await;
+    assert!(body["generated_text"].is_null());
+    assert_eq!(
+        body["decision_evidence"]["disabled_stages"],
+        json!(["generation"])
+    );
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 0);
+}
+
+#[tokio::test]
+async fn the_firewall_cannot_be_disabled_and_every_toggle_needs_a_reason() {
+    let app = app(MockMistralClient::default()).await;
+    let server = app.serve().await.unwrap();
+
+    let response = toggle(
+        &server,
+        json!({ "stage": "firewall", "enabled": false, "reason": "too strict" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = toggle(
+        &server,
+        json!({ "stage": "bias", "enabled": false, "reason": "  " }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let summary: Value = server
+        .get("/api/admin/summary")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(summary.get("disabled_stages").is_none());
+    assert!(app.storage.all().unwrap().is_empty());
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use prompt_sentinel::FrameworkConfig;
+use prompt_sentinel::config::settings::AppSettings;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralEndpoint, MockMistralClient, ScriptedFailure,
+};
+
+const ADMIN_TOKEN: &str = "startup-admin-token";
+
+/// Boot the production wiring with the startup policies in `overrides`, over a mock whose
+/// first `failures` model listings fail
+async fn boot(
+    overrides: &'static [(&'static str, &'static str)],
+    failures: usize,
+) -> Result<axum::Router, Box<dyn std::error::Error>> {
+    let env = move |key: &str| {
+        if let Some((_, value)) = overrides.iter().find(|(name, _)| *name == key) {
+            return Some((*value).to_owned());
+        }
+        match key {
+            "MISTRAL_GENERATION_MODEL" => Some("mistral-large-latest".to_owned()),
+            "MISTRAL_MODERATION_MODEL" => Some("disabled".to_owned()),
+            "MISTRAL_EMBEDDING_MODEL" => Some("mistral-embed".to_owned()),
+            "AUDIT_BACKEND" => Some("memory".to_owned()),
+            "ADMIN_API_TOKEN" => Some(ADMIN_TOKEN.to_owned()),
+            "STARTUP_RETRY_INITIAL_BACKOFF_MS" => Some("10".to_owned()),
+            _ => None,
+        }
+    };
+    let loaded = AppSettings::load_from(None, &env).expect("settings");
+    let mock = MockMistralClient::default();
+    mock.fail_next(
+        MistralEndpoint::Models,
+        failures,
+        ScriptedFailure::unavailable(),
+    );
+    let server = FrameworkConfig::initialize_with_client(loaded, Arc::new(mock)).await?;
+    Ok(server.build_router())
+}
+
+async fn get(router: &axum::Router, uri: &str) -> (StatusCode, Value) {
+    let request = Request::get(uri)
+        .header("Authorization", format!("Bearer {ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn dependency<'a>(body: &'a Value, name: &str) -> &'a Value {
+    body["dependencies"]
+        .as_array()
+        .expect("dependencies")
+        .iter()
+        .find(|status| status["dependency"] == name)
+        .unwrap_or_else(|| panic!("no {name} in {body}"))
+}
+
+#[tokio::test]
+async fn lazy_validation_recovers_and_flips_readiness_without_a_restart() {
+    let router = boot(
+        &[
+            ("STARTUP_POLICY_MISTRAL", "lazy"),
+            ("STARTUP_POLICY_SEMANTIC", "lazy"),
+        ],
+        2,
+    )
+    .await
+    .expect("lazy startup never fails");
+
+    let (status, body) = get(&router, "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(body["ready"], false);
+
+    let mut ready = body;
+    for _ in 0..200 {
+        let (status, body) = get(&router, "/ready").await;
+        ready = body;
+        if status == StatusCode::OK {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(ready["ready"], true, "{ready}");
+    let mistral = dependency(&ready, "mistral");
+    assert_eq!(mistral["state"], "healthy");
+    assert_eq!(mistral["policy"], "lazy");
+    assert_eq!(mistral["attempts"], 3);
+    assert_eq!(dependency(&ready, "semantic")["state"], "healthy");
+
+    let (status, summary) = get(&router, "/api/admin/summary").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(dependency(&summary, "semantic")["policy"], "lazy");
+    assert_eq!(dependency(&summary, "mistral")["state"], "healthy");
+}
+
+#[tokio::test]
+async fn fail_fast_refuses_to_start_when_validation_fails() {
+    let error = boot(&[], 1).await.expect_err("startup should fail");
+    assert!(
+        error
+            .to_string()
+            .contains("mistral failed startup validation"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn skipped_dependencies_are_never_validated() {
+    let router = boot(
+        &[
+            ("STARTUP_POLICY_MISTRAL", "skip"),
+            ("STARTUP_POLICY_SEMANTIC", "skip"),
+        ],
+        usize::MAX,
+    )
+    .await
+    .expect("nothing is validated");
+
+    let (status, body) = get(&router, "/ready").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    for name in ["mistral", "semantic"] {
+        let skipped = dependency(&body, name);
+        assert_eq!(skipped["state"], "skipped");
+        assert_eq!(skipped["attempts"], 0);
+    }
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use prompt_sentinel::ComplianceRequest;
+use prompt_sentinel::WorkflowStatus;
+use prompt_sentinel::modules::audit::storage::AuditStorage;
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralClient, MistralClientError, MistralEndpoint, MockMistralClient,
+};
+use prompt_sentinel::modules::mistral_ai::dtos::{
+    ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse,
+    LanguageDetectionRequest, LanguageDetectionResponse, ModelListResponse, ModerationRequest,
+    ModerationResponse, TranslationRequest, TranslationResponse,
+};
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::workflow::{ReasonCode, WorkflowPolicy};
+
+const PROMPT: &str = "Hola, dame una receta de paella";
+
+/// Mock that really translates into Spanish by tagging the text, so the translated
+/// output differs from the English one
+#[derive(Clone)]
+struct TranslatingMock {
+    base: Arc<dyn MistralClient>,
+}
+
+#[async_trait]
+impl MistralClient for TranslatingMock {
+    async fn chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, MistralClientError> {
+        self.base.chat_completion(request).await
+    }
+
+    async fn moderate(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, MistralClientError> {
+        self.base.moderate(request).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, MistralClientError> {
+        self.base.embeddings(request).await
+    }
+
+    async fn list_models(&self) -> Result<ModelListResponse, MistralClientError> {
+        self.base.list_models().await
+    }
+
+    async fn detect_language(
+        &self,
+        request: LanguageDetectionRequest,
+    ) -> Result<LanguageDetectionResponse, MistralClientError> {
+        self.base.detect_language(request).await
+    }
+
+    async fn translate_text(
+        &self,
+        request: TranslationRequest,
+    ) -> Result<TranslationResponse, MistralClientError> {
+        let translated_text = if request.target_language.eq_ignore_ascii_case("spanish") {
+            format!("[es] {}", request.text)
+        } else {
+            request.text
+        };
+        Ok(TranslationResponse { translated_text })
+    }
+}
+
+fn clean() -> ModerationResponse {
+    ModerationResponse {
+        flagged: false,
+        categories: vec![],
+        severity: 0.0,
+        ..ModerationResponse::default()
+    }
+}
+
+fn flagged() -> ModerationResponse {
+    ModerationResponse {
+        flagged: true,
+        categories: vec!["dangerous".to_owned()],
+        severity: 0.9,
+        ..ModerationResponse::default()
+    }
+}
+
+/// Input and English output pass; every later call (the translated output) is flagged
+fn second_pass_flagging() -> MockMistralClient {
+    MockMistralClient::with_moderation_sequence(vec![clean(), clean(), flagged()])
+        .expect("moderation sequence")
+        .record_calls()
+}
+
+/// App over `mock`, seen through a [`TranslatingMock`] when `translating`
+async fn app(mock: &MockMistralClient, translating: bool) -> TestApp {
+    let builder = TestApp::builder().with_mock(mock.clone());
+    let builder = if translating {
+        builder.with_client(|base| Arc::new(TranslatingMock { base }))
+    } else {
+        builder
+    };
+    builder.build().await.expect("test app")
+}
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: Some("translated-1".to_owned()),
+        prompt: PROMPT.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[tokio::test]
+async fn flagged_translation_blocks_with_translated_variant() {
+    let mock = second_pass_flagging();
+    let TestApp {
+        engine, storage, ..
+    } = app(&mock, true).await;
+
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::BlockedByOutputModeration);
+    assert_eq!(response.generated_text, None);
+    assert!(!response.output_moderation.as_ref().unwrap().flagged);
+    assert!(
+        response
+            .translated_output_moderation
+            .as_ref()
+            .unwrap()
+            .flagged
+    );
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 3);
+
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(evidence.final_reason_code, ReasonCode::OutputModerationFlag);
+    assert_eq!(evidence.reason_params["variant"], "translated");
+    assert_eq!(evidence.moderation_scope.as_deref(), Some("translated"));
+    assert_eq!(evidence.moderation_categories, vec!["dangerous"]);
+    assert_eq!(
+        evidence.final_reason,
+        "Translated output flagged by moderation: dangerous"
+    );
+
+    // Both passes are in the audit payload
+    let records = storage.all().expect("records");
+    let payload: Value = serde_json::from_str(&records[0].payload).unwrap();
+    assert_eq!(payload["final_status"], "blocked_by_output_moderation");
+    assert_eq!(payload["output_moderation"]["flagged"], false);
+    assert_eq!(payload["translated_output_moderation"]["flagged"], true);
+    assert_eq!(
+        payload["translated_output_moderation"]["categories"],
+        serde_json::json!(["dangerous"])
+    );
+}
+
+#[tokio::test]
+async fn flagged_english_output_reports_english_variant() {
+    let mock = MockMistralClient::with_moderation_sequence(vec![clean(), flagged()])
+        .expect("moderation sequence")
+        .record_calls();
+    let engine = app(&mock, true).await.engine;
+
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::BlockedByOutputModeration);
+    assert_eq!(response.translated_output_moderation, None);
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 2);
+    let evidence = response.decision_evidence.expect("evidence");
+    assert_eq!(evidence.reason_params["variant"], "english");
+    assert_eq!(evidence.moderation_scope.as_deref(), Some("english"));
+    assert_eq!(
+        evidence.final_reason,
+        "Output flagged by moderation: dangerous"
+    );
+}
+
+#[tokio::test]
+async fn second_pass_is_skipped_when_disabled_or_translation_is_identical() {
+    // Disabled by policy
+    let mock = second_pass_flagging();
+    let engine = app(&mock, true).await.engine;
+    let engine = engine.with_policy(WorkflowPolicy {
+        moderate_translated_output: false,
+        ..WorkflowPolicy::default()
+    });
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.translated_output_moderation, None);
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 2);
+
+    // The plain mock returns the text unchanged, so there is nothing new to moderate
+    let mock = second_pass_flagging();
+    let engine = app(&mock, false).await.engine;
+    let response = engine.process(request()).await.expect("workflow");
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert_eq!(response.translated_output_moderation, None);
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 2);
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use prompt_sentinel::modules::mistral_ai::client::{
+    MistralEndpoint, MockMistralClient, RecordedCall, ScriptedFailure,
+};
+use prompt_sentinel::modules::mistral_ai::service::MistralService;
+use prompt_sentinel::test_support::TestApp;
+use prompt_sentinel::{
+    ComplianceEngine, ComplianceRequest, PipelineStage, StageOutcome, WorkflowStatus,
+};
+
+const PROMPT: &str = "Summarize this changelog for me.";
+
+async fn build_engine(mock: &MockMistralClient, initialize_semantic: bool) -> ComplianceEngine {
+    let builder = TestApp::builder().with_mock(mock.clone());
+    let builder = if initialize_semantic {
+        builder.with_semantic_bank()
+    } else {
+        builder
+    };
+    builder
+        .build()
+        .await
+        .expect("attack bank should load")
+        .engine
+}
+
+fn request() -> ComplianceRequest {
+    ComplianceRequest {
+        correlation_id: None,
+        prompt: PROMPT.to_owned(),
+        suggest_rewrite: false,
+        template: None,
+        deterministic: None,
+        session_id: None,
+    }
+}
+
+#[tokio::test]
+async fn semantic_scan_fails_open_when_embeddings_are_down() {
+    let mock = MockMistralClient::default();
+    let engine = build_engine(&mock, true).await;
+
+    // The mock embeds every text identically, so a working scan would block this prompt
+    mock.fail_next(
+        MistralEndpoint::Embeddings,
+        1,
+        ScriptedFailure::unavailable(),
+    );
+    let response = engine.process(request()).await.expect("workflow completes");
+
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    assert!(response.semantic.is_none());
+    assert!(matches!(
+        response.stages.semantic,
+        StageOutcome::Failed { .. }
+    ));
+    let semantic_step = response
+        .decision_trace
+        .iter()
+        .find(|step| step.stage == "semantic")
+        .expect("semantic step");
+    assert_eq!(semantic_step.verdict, "skip");
+
+    // With the outage over the same prompt is caught again
+    let response = engine.process(request()).await.expect("workflow completes");
+    assert_eq!(response.status, WorkflowStatus::BlockedBySemantic);
+}
+
+#[tokio::test]
+async fn slow_generation_shows_in_trace() {
+    // No per-stage timeout exists yet, so a slow model only delays the response.
+    // This pins the latency accounting a timeout will build on.
+    let delay = Duration::from_millis(50);
+    let mock = MockMistralClient::default()
+        .delay_chat(delay)
+        .record_calls();
+    let engine = build_engine(&mock, false).await;
+
+    let response = engine.process(request()).await.expect("workflow completes");
+
+    assert_eq!(response.status, WorkflowStatus::Completed);
+    let generation = response
+        .decision_trace
+        .iter()
+        .find(|step| step.stage == "generation")
+        .expect("generation step");
+    assert!(generation.duration_ms >= delay.as_millis() as u64);
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 1);
+}
+
+#[tokio::test]
+async fn moderation_failure_is_not_retried_by_the_workflow() {
+    let mock = MockMistralClient::default().record_calls();
+    mock.fail_next(
+        MistralEndpoint::Moderation,
+        1,
+        ScriptedFailure::rate_limited(),
+    );
+    let engine = build_engine(&mock, false).await;
+
+    // Moderation fails closed by default
+    let response = engine.process(request()).await.expect("workflow completes");
+    assert_eq!(
+        response.status,
+        WorkflowStatus::BlockedByStageFailure {
+            stage: PipelineStage::Moderation
+        }
+    );
+    assert!(matches!(
+        response.stages.input_moderation,
+        StageOutcome::Failed { ref error } if error.contains("HTTP 429")
+    ));
+    assert_eq!(response.stages.generation, StageOutcome::NotReached);
+    let evidence = response.decision_evidence.expect("evidence");
+    assert!(evidence.final_reason.contains("HTTP 429"));
+    // Retries belong to the HTTP client; the workflow gives up after one attempt
+    assert_eq!(mock.call_count(MistralEndpoint::Moderation), 1);
+    assert_eq!(mock.call_count(MistralEndpoint::Chat), 0);
+
+    // Once the scripted failure is used up, input and output are moderated once each
+    engine.process(request()).await.expect("workflow completes");
+    let moderated: Vec<String> = mock
+        .recorded_calls()
+        .into_iter()
+        .filter_map(|call| match call {
+            RecordedCall::Moderation(request) => Some(request.input),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(moderated, vec![PROMPT, PROMPT, "Mock response"]);
+}
+
+#[tokio::test]
+async fn repeated_failures_are_consumed_in_order() {
+    let mock = MockMistralClient::default().fail_embeddings_times(2);
+    let mistral = MistralService::new(
+        Arc::new(mock.clone()),
+        "mistral-large-latest",
+        None,
+        "mistral-embed",
+    );
+
+    assert!(mistral.embed_text("first").await.is_err());
+    assert!(mistral.embed_text("second").await.is_err());
+    assert!(mistral.embed_text("third").await.is_ok());
+    // Nothing was recorded because recording was never enabled
+    assert!(mock.recorded_calls().is_empty());
+}